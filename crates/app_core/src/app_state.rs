@@ -3,13 +3,18 @@ use serde::{
     Serialize
 };
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fs,
     path::{
         PathBuf
     },
-    env
+    env,
+    str::FromStr,
+    time::Duration
 };
+use bars::{BarOptions, CsvDialect};
+use database_ops::{CancellationToken, DownloadControl, Ticker};
+use string_helpers::Locale;
 use timestamp_tools::{
     calculate_seconds_in_period,
     get_period_portions_from_string
@@ -25,6 +30,11 @@ use crate::errors::{
 pub struct SystemPaths {
     pub base: PathBuf,
     pub candle_data: PathBuf,
+    pub catalog: PathBuf,
+    /// Terminal interface state kept between runs, see `UiState`
+    pub ui_state: PathBuf,
+    /// Usage counts waiting to be reported, see `telemetry::UsageStats`
+    pub telemetry: PathBuf,
 }
 
 impl SystemPaths {
@@ -60,8 +70,11 @@ impl SystemPaths {
         base.push("dtrade");
         let mut candle_data = base.clone();
         candle_data.push("candle_data");
+        let catalog = base.join("catalog.json");
+        let ui_state = base.join("ui_state.json");
+        let telemetry = base.join("telemetry.json");
     
-        Ok(Self { base, candle_data })
+        Ok(Self { base, candle_data, catalog, ui_state, telemetry })
 
     }
 }
@@ -77,7 +90,7 @@ impl AppState {
     pub fn new() -> Result<Self, InitializationError> {
         
         let config = load_config()
-            .map_err(|e| InitializationError::Config(e))?;
+            .map_err(InitializationError::Config)?;
 
         let paths: SystemPaths = SystemPaths::new()
             .map_err(|_| InitializationError::InitFailure)?;
@@ -112,19 +125,46 @@ impl AppState {
 /// struct. An Engine gets an instance of the AppConfig. There really only 
 /// ever needs to be one AppConfig value and it will be the one that's owned
 /// by the Engine.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct AppConfig {
     pub backtesting: BackTestSettings,
     pub supported_exchanges: SupportedExchanges,
     pub data_download: DataDownload, 
     pub chart_parameters: ChartParams,
+    #[serde(default)]
+    pub locale: Locale,
+    #[serde(default)]
+    pub csv_dialect: CsvDialect,
+    #[serde(default)]
+    pub timeouts: JobTimeouts,
+    #[serde(default)]
+    pub bars: BarOptions,
+    #[serde(default)]
+    pub http: HttpSettings,
+    #[serde(default)]
+    pub tui: TuiSettings,
+    /// Shorthands for command lines, e.g. `"btc1h": "candles kraken BTCUSD 
+    /// 1h"`, run as `dtrade btc1h`. See `arg_parsing::expand_alias`
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+    #[serde(default)]
+    pub telemetry: TelemetrySettings,
+    #[serde(default)]
+    pub redis: RedisSettings,
+    #[serde(default)]
+    pub mqtt: MqttSettings,
+    #[serde(default)]
+    pub integrations: IntegrationSettings,
+    #[serde(default)]
+    pub live: LiveTradingSettings,
 }
 
-impl AppConfig {
-    pub fn default() -> Self {
+impl Default for AppConfig {
+    fn default() -> Self {
         Self {
             backtesting: BackTestSettings { 
-                inside_bar: true 
+                inside_bar: true,
+                slippage_bps: default_slippage_bps(),
             },
             supported_exchanges: SupportedExchanges { 
                 active: HashMap::from([
@@ -132,12 +172,28 @@ impl AppConfig {
                 ]) 
             },
             data_download: DataDownload {
-                cache_size: "6M".to_string() 
+                cache_size: "6M".to_string(),
+                stall_timeout_mins: default_stall_timeout_mins(),
+                stall_retries: default_stall_retries(),
             },
             chart_parameters: ChartParams {
                 num_bars: 1000,
                 log_scale: true,
-            }
+                operations_width: default_operations_width(),
+                output_height: default_output_height(),
+            },
+            locale: Locale::default(),
+            csv_dialect: CsvDialect::default(),
+            timeouts: JobTimeouts::default(),
+            bars: BarOptions::default(),
+            http: HttpSettings::default(),
+            tui: TuiSettings::default(),
+            aliases: BTreeMap::new(),
+            telemetry: TelemetrySettings::default(),
+            redis: RedisSettings::default(),
+            mqtt: MqttSettings::default(),
+            integrations: IntegrationSettings::default(),
+            live: LiveTradingSettings::default(),
         }
     }
 }
@@ -145,13 +201,36 @@ impl AppConfig {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct BackTestSettings {
     pub inside_bar: bool,
+    /// Adverse price move applied to simulated taker fills, in basis points
+    #[serde(default = "default_slippage_bps")]
+    pub slippage_bps: u32,
 } 
 
+fn default_slippage_bps() -> u32 {
+    5
+}
+
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ChartParams {
     pub num_bars: u16,
     pub log_scale: bool,
+    /// Columns taken by the terminal interface's Operations pane, unless 
+    /// wide mode hides it
+    #[serde(default = "default_operations_width")]
+    pub operations_width: u16,
+    /// Rows taken by the terminal interface's Output pane, unless wide mode
+    /// hides it
+    #[serde(default = "default_output_height")]
+    pub output_height: u16,
+}
+
+fn default_operations_width() -> u16 {
+    21
+}
+
+fn default_output_height() -> u16 {
+    10
 }
 
 
@@ -164,6 +243,20 @@ pub struct SupportedExchanges {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct DataDownload {
     pub cache_size: String,
+    /// Minutes a download may go without progress before it's aborted
+    #[serde(default = "default_stall_timeout_mins")]
+    pub stall_timeout_mins: u64,
+    /// Restarts of a stalled download before it's marked failed
+    #[serde(default = "default_stall_retries")]
+    pub stall_retries: u32,
+}
+
+fn default_stall_timeout_mins() -> u64 {
+    5
+}
+
+fn default_stall_retries() -> u32 {
+    1
 }
 
 /// Configuration for data downloads. 
@@ -189,6 +282,533 @@ impl DataDownload {
             Err(_) => DEFAULT_RETURN_VAL
         } 
     }
+
+    /// Watchdog settings for a download run that stops on `cancel`
+    pub fn download_control(&self, cancel: CancellationToken) 
+        -> DownloadControl 
+    {
+        DownloadControl::new(
+            cancel,
+            Duration::from_secs(self.stall_timeout_mins.max(1) * 60),
+            self.stall_retries
+        )
+    }
+}
+
+
+/// Environment variable holding API keys on top of those in the config, in
+/// the same `KEY:ROLE,KEY:ROLE` form `parse_api_keys` reads
+pub const API_KEYS_ENV: &str = "DTRADE_API_KEYS";
+
+/// Settings for the REST API started with `start --http`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct HttpSettings {
+    /// Socket address the server listens on
+    pub address: String,
+    /// Keys clients must send. With none here or in `API_KEYS_ENV`, the API
+    /// is open to anyone who can reach it, but only for reading.
+    pub api_keys: Vec<ApiKey>,
+    /// Socket address of the Arrow Flight service started alongside the 
+    /// API, none to leave it off
+    pub flight_address: Option<String>,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        HttpSettings { 
+            address: "127.0.0.1:8080".to_string(),
+            api_keys: Vec::new(),
+            flight_address: None,
+        }
+    }
+}
+
+impl HttpSettings {
+
+    /// The configured keys followed by any from `API_KEYS_ENV`
+    pub fn all_api_keys(&self) -> Result<Vec<ApiKey>, String> {
+
+        let mut keys: Vec<ApiKey> = self.api_keys.clone();
+
+        if let Ok(value) = env::var(API_KEYS_ENV) {
+            keys.extend(
+                parse_api_keys(&value)
+                    .map_err(|e| format!("{}: {}", API_KEYS_ENV, e))?
+            );
+        };
+
+        Ok(keys)
+    }
+}
+
+/// Publishing to Redis from the server started with `start --http`, off
+/// until `url` is set
+///
+/// Channels are `PREFIX:ticks:EXCHANGE:TICKER`, 
+/// `PREFIX:candles:EXCHANGE:TICKER:PERIOD` and 
+/// `PREFIX:signals:EXCHANGE:TICKER:PERIOD`, for every pair in the database,
+/// and `PREFIX:report` for the daily report.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct RedisSettings {
+    /// e.g. `redis://127.0.0.1:6379`
+    pub url: Option<String>,
+    pub channel_prefix: String,
+    /// Periods whose candles are published as they close
+    pub candle_periods: Vec<String>,
+    /// Specs run on each of `candle_periods`, e.g. `sma-cross(20,50)`, whose
+    /// orders on a newly closed candle are published
+    pub strategies: Vec<String>,
+}
+
+impl Default for RedisSettings {
+    fn default() -> Self {
+        RedisSettings {
+            url: None,
+            channel_prefix: "dtrade".to_string(),
+            candle_periods: vec!["1h".to_string()],
+            strategies: Vec::new(),
+        }
+    }
+}
+
+/// Publishing latest prices and alerts to an MQTT broker from the server 
+/// started with `start --http`, off until `host` is set
+///
+/// Topics are `PREFIX/EXCHANGE/TICKER/price`, retained and holding just the
+/// price, `PREFIX/EXCHANGE/TICKER/alert`, `PREFIX/status`, `online` or 
+/// `offline`, and `PREFIX/report`, the latest daily report.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct MqttSettings {
+    pub host: Option<String>,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub client_id: String,
+    pub topic_prefix: String,
+    pub alerts: Vec<PriceAlert>,
+}
+
+impl Default for MqttSettings {
+    fn default() -> Self {
+        MqttSettings {
+            host: None,
+            port: 1883,
+            username: None,
+            password: None,
+            client_id: "dtrade".to_string(),
+            topic_prefix: "dtrade".to_string(),
+            alerts: Vec::new(),
+        }
+    }
+}
+
+/// Triggers when a pair's latest price crosses above `above` or below 
+/// `below`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PriceAlert {
+    pub exchange: String,
+    pub ticker: String,
+    #[serde(default)]
+    pub above: Option<f64>,
+    #[serde(default)]
+    pub below: Option<f64>,
+}
+
+/// Environment variables holding the Kraken API key and secret, used over
+/// those in the config
+pub const KRAKEN_API_KEY_ENV: &str = "DTRADE_KRAKEN_API_KEY";
+pub const KRAKEN_API_SECRET_ENV: &str = "DTRADE_KRAKEN_API_SECRET";
+
+/// Trading a real Kraken account with `dtrade live`, off until `enabled` is
+/// set and a key is given here or in `KRAKEN_API_KEY_ENV` and 
+/// `KRAKEN_API_SECRET_ENV`
+///
+/// The key only needs the permissions of the commands used: querying funds
+/// for `live balance`, querying open orders for `live orders` and creating
+/// orders for `live buy` and `live sell`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct LiveTradingSettings {
+    pub enabled: bool,
+    pub kraken_api_key: Option<String>,
+    /// The private key as Kraken shows it, base64 encoded
+    pub kraken_api_secret: Option<String>,
+    /// Largest order `live buy` and `live sell` will place, in the quote 
+    /// currency
+    pub max_order_value: f64,
+}
+
+impl Default for LiveTradingSettings {
+    fn default() -> Self {
+        LiveTradingSettings {
+            enabled: false,
+            kraken_api_key: None,
+            kraken_api_secret: None,
+            max_order_value: 100.0,
+        }
+    }
+}
+
+impl LiveTradingSettings {
+
+    /// The Kraken key and secret, each from its environment variable when 
+    /// it's set
+    pub fn kraken_keys(&self) -> Option<(String, String)> {
+
+        let key: Option<String> = env::var(KRAKEN_API_KEY_ENV)
+            .ok()
+            .or(self.kraken_api_key.clone())
+            .filter(|k| !k.is_empty());
+        let secret: Option<String> = env::var(KRAKEN_API_SECRET_ENV)
+            .ok()
+            .or(self.kraken_api_secret.clone())
+            .filter(|s| !s.is_empty());
+
+        key.zip(secret)
+    }
+}
+
+/// Services downloads are mirrored to
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct IntegrationSettings {
+    /// Message buses each exchange's tick batches and download events are 
+    /// published to, keyed by exchange. See `bus_sink`
+    pub message_bus: BTreeMap<String, BusSinkSettings>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BusSinkSettings {
+    pub bus: MessageBus,
+    /// NATS server URL, or Kafka bootstrap servers as `HOST:PORT,HOST:PORT`
+    pub url: String,
+    /// Kafka topics are `PREFIX.ticks` and `PREFIX.jobs`. NATS subjects 
+    /// add `.EXCHANGE.TICKER`.
+    #[serde(default = "default_bus_prefix")]
+    pub prefix: String,
+}
+
+fn default_bus_prefix() -> String {
+    "dtrade".to_string()
+}
+
+/// Each is only available when built with the feature of the same name
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageBus {
+    Kafka,
+    Nats,
+}
+
+impl std::fmt::Display for MessageBus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MessageBus::Kafka => write!(f, "kafka"),
+            MessageBus::Nats => write!(f, "nats"),
+        }
+    }
+}
+
+/// What a client holding an API key may do. Every role can read, and
+/// `Admin` can do everything the others can.
+///
+/// The `read` scope of older configs is read as `Viewer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiRole {
+    /// Only reads stored data, e.g. pairs, candles and paper accounts
+    #[serde(alias = "read")]
+    Viewer,
+    /// Also changes stored data, e.g. updating or repairing pairs
+    Operator,
+    /// Also places and cancels paper orders
+    Trader,
+    Admin,
+}
+
+/// What a request to the API does, checked against the caller's `ApiRole`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiAccess {
+    Read,
+    /// Changes stored data or the config
+    Mutate,
+    /// Places, cancels or fills paper orders
+    Trade,
+}
+
+impl ApiRole {
+
+    pub fn allows(&self, access: ApiAccess) -> bool {
+        matches!(
+            (self, access),
+            (_, ApiAccess::Read) 
+            | (ApiRole::Admin, _)
+            | (ApiRole::Operator, ApiAccess::Mutate)
+            | (ApiRole::Trader, ApiAccess::Trade)
+        )
+    }
+}
+
+impl FromStr for ApiRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match &s.trim().to_lowercase()[..] {
+            "viewer" | "read" => Ok(ApiRole::Viewer),
+            "operator" => Ok(ApiRole::Operator),
+            "trader" => Ok(ApiRole::Trader),
+            "admin" => Ok(ApiRole::Admin),
+            _ => Err(format!(
+                "Unknown API role: {}, use viewer, operator, trader or admin",
+                s
+            ))
+        }
+    }
+}
+
+impl std::fmt::Display for ApiRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiRole::Viewer => write!(f, "viewer"),
+            ApiRole::Operator => write!(f, "operator"),
+            ApiRole::Trader => write!(f, "trader"),
+            ApiRole::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ApiKey {
+    pub key: String,
+    #[serde(alias = "scope")]
+    pub role: ApiRole,
+}
+
+impl ApiKey {
+
+    /// The key's last four characters, to tell keys apart in the audit log
+    /// without giving them away. Keys under 12 characters show none.
+    pub fn id(&self) -> String {
+        let chars: Vec<char> = self.key.chars().collect();
+        match chars.len() >= 12 {
+            true => {
+                let last: String = chars[chars.len() - 4..].iter().collect();
+                format!("...{}", last)
+            },
+            false => "...".to_string()
+        }
+    }
+}
+
+/// Reads keys written as `KEY:ROLE`, separated by commas, e.g. 
+/// `k3y:admin,0th3r:viewer`
+pub fn parse_api_keys(value: &str) -> Result<Vec<ApiKey>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (key, role) = entry
+                .rsplit_once(':')
+                .ok_or_else(|| format!("Expected KEY:ROLE, got {}", entry))?;
+            if key.trim().is_empty() {
+                return Err(format!("Empty API key in {}", entry))
+            };
+            Ok(ApiKey { key: key.trim().to_string(), role: role.parse()? })
+        })
+        .collect()
+}
+
+/// Writes keys in the form `parse_api_keys` reads
+pub fn format_api_keys(keys: &[ApiKey]) -> String {
+    keys
+        .iter()
+        .map(|k| format!("{}:{}", k.key, k.role))
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// Reads Kraken pairs separated by commas, e.g. `XBTUSD, ethusd`, as
+/// uppercase tickers
+pub fn parse_watchlist(value: &str) -> Result<Vec<String>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            entry
+                .parse::<Ticker>()
+                .map(String::from)
+                .map_err(|e| e.to_string())
+        })
+        .collect()
+}
+
+
+/// Long running job types that can be given a maximum runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobKind {
+    IntegrityCheck,
+    Backfill,
+    CandleBuild,
+}
+
+impl std::fmt::Display for JobKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JobKind::IntegrityCheck => write!(f, "Integrity check"),
+            JobKind::Backfill => write!(f, "Backfill"),
+            JobKind::CandleBuild => write!(f, "Candle build"),
+        }
+    }
+}
+
+/// Maximum runtime of each job type, in minutes. 0 means no limit.
+///
+/// A job that runs past its limit is cancelled and fails with 
+/// `RunTimeError::Timeout`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct JobTimeouts {
+    pub integrity_check_mins: u64,
+    pub backfill_mins: u64,
+    pub candle_build_mins: u64,
+}
+
+impl JobTimeouts {
+
+    pub fn limit(&self, job: JobKind) -> Option<Duration> {
+        let mins: u64 = match job {
+            JobKind::IntegrityCheck => self.integrity_check_mins,
+            JobKind::Backfill => self.backfill_mins,
+            JobKind::CandleBuild => self.candle_build_mins,
+        };
+        match mins {
+            0 => None,
+            m => Some(Duration::from_secs(m * 60))
+        }
+    }
+}
+
+
+/// Screen the terminal interface opens on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StartScreen {
+    /// Whichever screen was open when the interface was last closed
+    #[default]
+    Last,
+    /// The operations list, with no screen open
+    Menu,
+    Database,
+    Candles,
+    Settings,
+    Strategies,
+    Chart,
+    Integrity,
+    Watch,
+    Jobs,
+    Paper,
+    Orders,
+}
+
+impl FromStr for StartScreen {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match &s.trim().to_lowercase()[..] {
+            "last" => Ok(StartScreen::Last),
+            "menu" => Ok(StartScreen::Menu),
+            "database" | "db" => Ok(StartScreen::Database),
+            "candles" => Ok(StartScreen::Candles),
+            "settings" => Ok(StartScreen::Settings),
+            "strategies" => Ok(StartScreen::Strategies),
+            "chart" => Ok(StartScreen::Chart),
+            "integrity" => Ok(StartScreen::Integrity),
+            "watch" | "watchlist" => Ok(StartScreen::Watch),
+            "jobs" => Ok(StartScreen::Jobs),
+            "paper" => Ok(StartScreen::Paper),
+            "orders" => Ok(StartScreen::Orders),
+            _ => Err(format!("Unknown screen: {}", s))
+        }
+    }
+}
+
+impl std::fmt::Display for StartScreen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StartScreen::Last => write!(f, "last"),
+            StartScreen::Menu => write!(f, "menu"),
+            StartScreen::Database => write!(f, "database"),
+            StartScreen::Candles => write!(f, "candles"),
+            StartScreen::Settings => write!(f, "settings"),
+            StartScreen::Strategies => write!(f, "strategies"),
+            StartScreen::Chart => write!(f, "chart"),
+            StartScreen::Integrity => write!(f, "integrity"),
+            StartScreen::Watch => write!(f, "watch"),
+            StartScreen::Jobs => write!(f, "jobs"),
+            StartScreen::Paper => write!(f, "paper"),
+            StartScreen::Orders => write!(f, "orders"),
+        }
+    }
+}
+
+/// Settings for the terminal interface
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct TuiSettings {
+    /// Overridden by `start --screen`
+    pub start_screen: StartScreen,
+    /// Kraken pairs the watch screen quotes, e.g. `XBTUSD`
+    pub watchlist: Vec<String>,
+}
+
+/// Anonymous usage stats, off unless turned on in the settings
+///
+/// Only how often each command ran and which kinds of errors it hit are 
+/// counted, never pairs, paths, keys or addresses. See `telemetry`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct TelemetrySettings {
+    pub enabled: bool,
+    /// Where the counts are posted. With none, they're only kept in 
+    /// `SystemPaths::telemetry`.
+    pub endpoint: Option<String>,
+}
+
+/// Terminal interface state kept between runs, in `SystemPaths::ui_state`
+///
+/// Unlike the config, it's written without asking whenever the interface 
+/// closes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiState {
+    /// The screen open when the interface was closed, never 
+    /// `StartScreen::Last`
+    pub last_screen: Option<StartScreen>,
+}
+
+impl UiState {
+
+    /// Reads the state file, starting afresh if it's missing or unreadable
+    pub fn load(paths: &SystemPaths) -> Self {
+        fs::read_to_string(&paths.ui_state)
+            .ok()
+            .and_then(|d| serde_json::from_str(&d).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, paths: &SystemPaths) -> Result<(), ConfigError> {
+
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|_| ConfigError::SaveStateFailed)?;
+
+        fs::write(&paths.ui_state, json)
+            .map_err(|_| ConfigError::SaveStateFailed)
+    }
 }
 
 
@@ -198,12 +818,11 @@ pub fn load_config() -> Result<AppConfig, ConfigError> {
     let system_paths: SystemPaths = SystemPaths::new()?;
     let json_path: PathBuf = system_paths.base.join("config.json");
 
-    if json_path.exists() {
-        if let Ok(d) = fs::read_to_string(&json_path) {
-            if let Ok(j) = serde_json::from_str::<AppConfig>(&d) {
-                return Ok(j) 
-            }
-        }
+    if json_path.exists()
+        && let Ok(d) = fs::read_to_string(&json_path)
+        && let Ok(j) = serde_json::from_str::<AppConfig>(&d) 
+    {
+        return Ok(j) 
     };
     
     println!(
@@ -212,7 +831,7 @@ pub fn load_config() -> Result<AppConfig, ConfigError> {
 
     let config = AppConfig::default();
 
-    if let Ok(_) = save_config(&config, &system_paths) {
+    if save_config(&config, &system_paths).is_ok() {
         Ok(config)
     }
     else {
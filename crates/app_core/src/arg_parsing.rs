@@ -1,5 +1,43 @@
-use std::{env::args};
-use bars::{BarSeries};
+use std::{
+    collections::{BTreeMap, HashMap}, 
+    env::args, 
+    iter, 
+    str::FromStr
+};
+use crate::{
+    app_state::{JobKind, StartScreen},
+    live::LiveOrder,
+    paper::{
+        Contingent,
+        DEFAULT_ACCOUNT,
+        DEFAULT_CASH,
+        NewOrder,
+        PaperAccount,
+        PaperOrder,
+        is_account_name,
+    },
+    recurring::NewRecurringBuy,
+};
+use backtest::{Objective, OrderSide, WalkForwardConfig};
+use database_ops::{
+    DatabaseIntegrity,
+    Ticker,
+    kraken_private::{AddedOrder, KrakenOrder},
+    usage::ApiUsageReport
+};
+use clap::{Args, CommandFactory, Parser, Subcommand, error::ErrorKind};
+use bars::{
+    BarSeries, 
+    CsvDialect, 
+    CsvQuoting, 
+    ExportFormat, 
+    export::timestamp_format_is_valid
+};
+use timestamp_tools::{
+    calculate_seconds_in_period, 
+    get_period_portions_from_string, 
+    parse_date_or_timestamp
+};
 
 
 // --------------------------- COMMAND ENUMS ------------------------------- //
@@ -7,29 +45,196 @@ use bars::{BarSeries};
 pub enum Command {
     AddPair {
         exchange: String,
-        ticker: String
+        ticker: Ticker
     },
     DropPair {
         exchange: String,
-        ticker: String
+        ticker: Ticker
     },
     DbIntegrityCheck {
         exchange: String,
-        ticker: String
+        ticker: String,
+        repair: bool
+    },
+    RollbackBatch {
+        batch_id: i64
     },
     UpdatePairs,
+    /// Updates only the pairs whose last download ended in an error
+    RetryFailed,
+    /// Reports requests sent to each exchange and a monthly estimate
+    ApiUsage,
+    /// Rows, size, tick range and lag of every pair table
+    DbStats,
+    ListPairs,
+    ExportPair {
+        exchange: String,
+        ticker: Ticker,
+        out: Option<String>
+    },
+    ImportPair {
+        path: String
+    },
     
     StartServer {
-        http: bool
+        http: bool,
+        /// Start the terminal interface with destructive actions disabled
+        guest: bool,
+        /// Screen the terminal interface opens on, instead of the config's
+        screen: Option<StartScreen>
     },
 
     CandleBuilder {
         exchange: String,
-        ticker: String,
+        ticker: Ticker,
+        period: String,
+        integrity_check: bool,
+        fill_gaps: bool,
+        listing_anchor: bool,
+        export: Option<ExportFormat>,
+        /// Where to write the candles instead of the candle_data directory,
+        /// `-` for standard output
+        out: Option<String>,
+        export_ticks: bool,
+        csv_flags: CsvFlags,
+        /// Only the ticks from this unix time on, in seconds
+        from: Option<u64>,
+        /// Only the ticks before this unix time, in seconds
+        to: Option<u64>,
+    },
+
+    Chart {
+        exchange: String,
+        ticker: Ticker,
         period: String,
-        integrity_check: bool
+        num_bars: usize
+    },
+
+    Backtest {
+        exchange: String,
+        ticker: Ticker,
+        period: String,
+        strategy: String,
+        cash: f64,
+        walk_forward: Option<WalkForwardConfig>,
+        optimize: Option<String>
+    },
+
+    Catalog {
+        exchange: Option<String>,
+        ticker: Option<String>,
+        refresh: bool
+    },
+
+    Verify {
+        path: String
+    },
+
+    ListAliases,
+    AddAlias {
+        name: String,
+        /// The command line the alias expands to, without `dtrade`
+        command: String
+    },
+    RemoveAlias {
+        name: String
+    },
+
+    /// A dotted path into config.json, e.g. `chart_parameters.num_bars`, or
+    /// the whole config without one
+    GetConfig {
+        key: Option<String>
+    },
+    SetConfig {
+        key: String,
+        value: String
+    },
+
+    /// Paper trading commands, each on the named account
+    PaperStatus {
+        account: String
+    },
+    PaperOrder {
+        account: String,
+        order: NewOrder
+    },
+    PaperCancel {
+        account: String,
+        order_id: i64
+    },
+    /// Moves the limit or stop price of an open order
+    PaperModify {
+        account: String,
+        order_id: i64,
+        price: f64
+    },
+    PaperHistory {
+        account: String
+    },
+    PaperSync {
+        account: String
+    },
+    PaperReset {
+        account: String,
+        cash: f64
+    },
+    /// Market orders moving the account's pairs on `exchange` to `targets`,
+    /// weights of its equity, placed once `confirmed`
+    PaperRebalance {
+        account: String,
+        exchange: String,
+        targets: Vec<(Ticker, f64)>,
+        min_drift: f64,
+        confirmed: bool
+    },
+
+    /// The latest `limit` rows of the audit log, only those of `action` 
+    /// when one is given
+    AuditLog {
+        action: Option<String>,
+        limit: usize
+    },
+
+    /// Live trading commands, on the exchange account of the configured key
+    LiveBalance,
+    LiveOpenOrders,
+    LiveOrder {
+        order: LiveOrder
+    },
+    LiveCancel {
+        txid: String
+    },
+    LiveModify {
+        txid: String,
+        price: f64
+    },
+
+    /// Every paper and live fill with its fee and realized P&L, written to
+    /// `out` or a dated file in the current directory
+    ExportFills {
+        format: Option<ExportFormat>,
+        out: Option<String>,
+        /// Only the fills from this unix time on, in seconds
+        from: Option<u64>,
+        /// Only the fills before this unix time, in seconds
+        to: Option<u64>,
     },
 
+    /// Recurring buys, run by `start --http` once they're due
+    RecurringAdd {
+        schedule: NewRecurringBuy
+    },
+    RecurringList,
+    /// Stops a recurring buy, keeping its history
+    RecurringRemove {
+        id: i64
+    },
+    RecurringHistory {
+        id: i64
+    },
+    /// Places the recurring buys that are due now
+    RecurringRun,
+
     Help,
 }
 
@@ -42,10 +247,13 @@ impl std::fmt::Display for Command {
             Command::DropPair { exchange, ticker } => {
                 write!(f, "DropPair: {}-{}", exchange, ticker)
             },
-            Command::StartServer { http } => {
+            Command::StartServer { http, guest, .. } => {
                 if *http {
                     write!(f, "StartServer: HTTP")
                 }
+                else if *guest {
+                    write!(f, "StartServer: TUI (guest)")
+                }
                 else {
                     write!(f, "StartServer: TUI")
                 }
@@ -53,20 +261,184 @@ impl std::fmt::Display for Command {
             Command::UpdatePairs => {
                 write!(f, "UpdatePairs")
             },
+            Command::RetryFailed => {
+                write!(f, "RetryFailed")
+            },
+            Command::ApiUsage => {
+                write!(f, "ApiUsage")
+            },
+            Command::DbStats => {
+                write!(f, "DbStats")
+            },
+            Command::ListPairs => {
+                write!(f, "ListPairs")
+            },
             Command::CandleBuilder { 
-                exchange, ticker, period, integrity_check 
+                exchange, 
+                ticker, 
+                period, 
+                integrity_check, 
+                fill_gaps, 
+                listing_anchor,
+                export, 
+                out,
+                export_ticks,
+                csv_flags,
+                from,
+                to
             } => {
                 write!(f, 
-                    "CandleBuilder: {} {} {} {}", 
+                    "CandleBuilder: {} {} {} {} {} {} {:?} {:?} {} {:?} {:?} \
+                    {:?}", 
                     exchange, 
                     ticker, 
                     period,
-                    integrity_check
+                    integrity_check,
+                    fill_gaps,
+                    listing_anchor,
+                    export,
+                    out,
+                    export_ticks,
+                    csv_flags,
+                    from,
+                    to
+                )
+            },
+            Command::DbIntegrityCheck { exchange, ticker, repair } => {
+                write!(f, 
+                    "DbIntegrityCheck: {} {} {}", 
+                    exchange, 
+                    ticker, 
+                    repair
+                )
+            },
+            Command::RollbackBatch { batch_id } => {
+                write!(f, "RollbackBatch: {}", batch_id)
+            },
+            Command::ExportPair { exchange, ticker, out } => {
+                write!(f, "ExportPair: {} {} {:?}", exchange, ticker, out)
+            },
+            Command::ImportPair { path } => {
+                write!(f, "ImportPair: {}", path)
+            },
+            Command::Chart { exchange, ticker, period, num_bars } => {
+                write!(f, 
+                    "Chart: {} {} {} {}", 
+                    exchange, 
+                    ticker, 
+                    period, 
+                    num_bars
+                )
+            },
+            Command::Backtest { 
+                exchange, 
+                ticker, 
+                period, 
+                strategy, 
+                cash, 
+                walk_forward,
+                optimize
+            } => {
+                write!(f, 
+                    "Backtest: {} {} {} {} {} {:?} {:?}", 
+                    exchange, 
+                    ticker, 
+                    period, 
+                    strategy, 
+                    cash, 
+                    walk_forward,
+                    optimize
+                )
+            },
+            Command::Catalog { exchange, ticker, refresh } => {
+                write!(f, 
+                    "Catalog: {:?} {:?} {}", 
+                    exchange, 
+                    ticker, 
+                    refresh
+                )
+            },
+            Command::Verify { path } => {
+                write!(f, "Verify: {}", path)
+            },
+            Command::ListAliases => {
+                write!(f, "ListAliases")
+            },
+            Command::AddAlias { name, command } => {
+                write!(f, "AddAlias: {} = {}", name, command)
+            },
+            Command::RemoveAlias { name } => {
+                write!(f, "RemoveAlias: {}", name)
+            },
+            Command::GetConfig { key } => {
+                write!(f, "GetConfig: {:?}", key)
+            },
+            Command::SetConfig { key, value } => {
+                write!(f, "SetConfig: {} = {}", key, value)
+            },
+            Command::PaperStatus { account } => {
+                write!(f, "PaperStatus: {}", account)
+            },
+            Command::PaperOrder { account, order } => {
+                write!(f, "PaperOrder: {} {:?}", account, order)
+            },
+            Command::PaperCancel { account, order_id } => {
+                write!(f, "PaperCancel: {} {}", account, order_id)
+            },
+            Command::PaperModify { account, order_id, price } => {
+                write!(f, "PaperModify: {} {} {}", account, order_id, price)
+            },
+            Command::PaperHistory { account } => {
+                write!(f, "PaperHistory: {}", account)
+            },
+            Command::PaperSync { account } => {
+                write!(f, "PaperSync: {}", account)
+            },
+            Command::PaperReset { account, cash } => {
+                write!(f, "PaperReset: {} {}", account, cash)
+            },
+            Command::PaperRebalance { account, exchange, targets, .. } => {
+                write!(
+                    f, 
+                    "PaperRebalance: {} {} {:?}", 
+                    account, 
+                    exchange, 
+                    targets
+                )
+            },
+            Command::AuditLog { action, limit } => {
+                write!(f, "AuditLog: {:?} {}", action, limit)
+            },
+            Command::LiveBalance => write!(f, "LiveBalance"),
+            Command::LiveOpenOrders => write!(f, "LiveOpenOrders"),
+            Command::LiveOrder { order } => {
+                write!(f, "LiveOrder: {:?}", order)
+            },
+            Command::LiveCancel { txid } => write!(f, "LiveCancel: {}", txid),
+            Command::LiveModify { txid, price } => {
+                write!(f, "LiveModify: {} {}", txid, price)
+            },
+            Command::ExportFills { format, out, from, to } => {
+                write!(
+                    f, 
+                    "ExportFills: {:?} {:?} {:?}-{:?}", 
+                    format, 
+                    out, 
+                    from, 
+                    to
                 )
             },
-            Command::DbIntegrityCheck { exchange, ticker } => {
-                write!(f, "DbIntegrityCheck: {} {}", exchange, ticker)
+            Command::RecurringAdd { schedule } => {
+                write!(f, "RecurringAdd: {:?}", schedule)
+            },
+            Command::RecurringList => write!(f, "RecurringList"),
+            Command::RecurringRemove { id } => {
+                write!(f, "RecurringRemove: {}", id)
+            },
+            Command::RecurringHistory { id } => {
+                write!(f, "RecurringHistory: {}", id)
             },
+            Command::RecurringRun => write!(f, "RecurringRun"),
             Command::Help => {
                 write!(f, "Help")
             },
@@ -74,8 +446,83 @@ impl std::fmt::Display for Command {
     }
 }
 
+impl Command {
+
+    /// Whether running the command changes stored data or writes exports,
+    /// meaning the data catalog needs regenerating afterwards
+    pub fn changes_data(&self) -> bool {
+        match self {
+            Command::AddPair { .. }
+            | Command::DropPair { .. }
+            | Command::RollbackBatch { .. }
+            | Command::ImportPair { .. }
+            | Command::UpdatePairs 
+            | Command::RetryFailed => true,
+            Command::DbIntegrityCheck { repair, .. } => *repair,
+            Command::CandleBuilder { export, out, export_ticks, .. } => {
+                (export.is_some() && out.is_none()) || *export_ticks
+            },
+            _ => false
+        }
+    }
+
+    /// The job type whose timeout applies to the command, if any
+    pub fn job_kind(&self) -> Option<JobKind> {
+        match self {
+            Command::DbIntegrityCheck { .. } => Some(JobKind::IntegrityCheck),
+            Command::AddPair { .. } 
+            | Command::UpdatePairs 
+            | Command::RetryFailed => Some(JobKind::Backfill),
+            Command::CandleBuilder { .. } => Some(JobKind::CandleBuild),
+            _ => None
+        }
+    }
+}
+
+/// CSV dialect options given on the command line, applied on top of the
+/// dialect from the config file
+#[derive(Debug, Clone, Default)]
+pub struct CsvFlags {
+    pub delimiter: Option<char>,
+    pub quoting: Option<CsvQuoting>,
+    pub no_header: bool,
+    pub timestamp_format: Option<String>,
+}
+
+impl CsvFlags {
+
+    pub fn apply(&self, dialect: &CsvDialect) -> CsvDialect {
+
+        let mut dialect: CsvDialect = dialect.clone();
+
+        if self.delimiter.is_some() {
+            dialect.delimiter = self.delimiter;
+        };
+        if let Some(q) = self.quoting {
+            dialect.quoting = q;
+        };
+        if self.no_header {
+            dialect.header = false;
+        };
+        if self.timestamp_format.is_some() {
+            dialect.timestamp_format = self.timestamp_format.clone();
+        };
+
+        dialect
+    }
+}
+
 pub enum DataResponse {
     Bars(BarSeries),
+    Pairs(HashMap<String, Vec<String>>),
+    Integrity(Vec<DatabaseIntegrity>),
+    ApiUsage(ApiUsageReport),
+    PaperAccount(PaperAccount),
+    PaperOrder(Box<PaperOrder>),
+    PaperOrders(Vec<PaperOrder>),
+    LiveBalances(BTreeMap<String, f64>),
+    LiveOrders(Vec<KrakenOrder>),
+    LiveOrder(AddedOrder),
 }
 
 pub enum Response {
@@ -83,6 +530,21 @@ pub enum Response {
     Data(DataResponse),
 }
 
+/// A command given without all of its arguments, with the ones that were
+/// given. The engine asks for the rest when run from a terminal, see 
+/// `Engine::new`.
+#[derive(Debug, Clone)]
+pub enum PartialCommand {
+    Candles {
+        exchange: Option<String>,
+        /// Empty when none were given
+        tickers: Vec<Ticker>,
+    },
+    AddPairs {
+        exchange: Option<String>,
+    },
+}
+
 
 // ----------------------------- STRUCTS ----------------------------------- //
 /// Argument Parser
@@ -92,7 +554,12 @@ pub enum Response {
 pub struct ParsedArgs {
     pub commands: Vec<Command>,
     pub parser_error: Option<ParserError>,
+    /// Set along with a `ParserError::MissingArgs` that a prompt can fill in
+    pub partial: Option<PartialCommand>,
     pub dev_mode: bool,
+    /// Set by `--json`: results and errors are printed as JSON on standard
+    /// output, and progress messages are left out
+    pub json: bool,
 }
 
 impl ParsedArgs {
@@ -102,7 +569,9 @@ impl ParsedArgs {
         ParsedArgs {
             commands: Vec::new(),
             parser_error: None,
+            partial: None,
             dev_mode: false,
+            json: false,
         }     
     
     }
@@ -149,253 +618,1568 @@ impl std::fmt::Display for ParserError {
                 write!(f, "UnknownArg: {}", e)
             },
             ParserError::UnknownFlags(e) => {
-                write!(f, "UnknownFlags: {:?}", e)
+                write!(f, "UnknownFlags: {}", e.join(", "))
             },
             ParserError::TooManyArgs(e) => {
-                write!(f, "TooManyArgs: {:?}", e)
+                write!(f, "TooManyArgs: {}", e)
             },
             ParserError::MissingArgs(e) => {
-                write!(f, "MissingArgs: {:?}", e)
+                write!(f, "MissingArgs: {}", e)
+            },
+        }
+    }
+}
+
+impl From<clap::Error> for ParserError {
+    /// Keeps clap's message, which ends with the subcommand's usage
+    fn from(e: clap::Error) -> Self {
+
+        let rendered: String = e.render().to_string();
+        let message: String = rendered
+            .trim_start_matches("error: ")
+            .trim_end()
+            .to_string();
+
+        match e.kind() {
+            ErrorKind::InvalidSubcommand => {
+                ParserError::UnknownCommand(message)
             },
+            ErrorKind::UnknownArgument => {
+                ParserError::UnknownFlags(vec![message])
+            },
+            ErrorKind::TooManyValues => ParserError::TooManyArgs(message),
+            ErrorKind::MissingRequiredArgument 
+            | ErrorKind::MissingSubcommand
+            | ErrorKind::TooFewValues
+            | ErrorKind::WrongNumberOfValues
+            | ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand => {
+                ParserError::MissingArgs(message)
+            },
+            _ => ParserError::UnknownArg(message)
         }
     }
 }
 
-const ARG_ERROR: &'static str = { 
+const ARG_ERROR: &str = { 
     "\x1b[1;31mInvalid command: try --help for all options\x1b[0m"
 };
 
-/// Parses command line arguments into a ParsedArgs struct 
+/// Exchanges pairs can be added to and removed from
+const EXCHANGES: [&str; 1] = ["kraken"];
+
+
+// ------------------------------ CLI SPEC --------------------------------- //
+/// The command line, as clap parses it. Each subcommand's arguments turn 
+/// into `Command`s in its `push_commands`.
 ///
-/// If 'None' is passed in as the argument, then commands are taken from 
-/// std::env::args(). Otherwise, pass Some(a) where a is a vector of string 
-/// values. A ParsedArgs struct is always returned no matter what. If any 
-/// arguments were invalid, then `ParsedArgs.parser_error` will contain 
-/// a specific error showing what went wrong. If `parser_error` is None,
-/// then the argument parsing was successful.
-pub fn parse_args(passed_arguments: Option<Vec<String>>) -> ParsedArgs {
+/// `--help` prints the full manual, `engine::HELP_STRING`, while 
+/// `dtrade COMMAND --help` prints the help clap derives from the doc 
+/// comments below.
+#[derive(Parser, Debug)]
+#[command(
+    name = "dtrade", 
+    version, 
+    about = "Cryptocurrency data management and candle builder tool",
+    long_about = None,
+    disable_help_flag = true,
+    disable_help_subcommand = true
+)]
+struct Cli {
+    /// Show the full manual
+    #[arg(short, long)]
+    help: bool,
 
-    // Initialization
-    let mut arguments: Vec<String> = match passed_arguments {
-        Some(a) => a, 
-        None => args().skip(2).collect()
-    };
-    
-    let mut parsed_args: ParsedArgs = ParsedArgs::new();
+    /// Run the dev_testing() function in src/lib.rs
+    #[arg(long)]
+    dev: bool,
 
-    // Helper functions
-    fn is_long_flag(arg: &str) -> bool {
-        arg.len() >= 2 && arg.starts_with("--")
-    }
+    /// Print results and errors as JSON, for scripts
+    #[arg(long, global = true)]
+    json: bool,
 
-    fn is_short_flag(arg: &str) -> bool {
-        !is_long_flag(arg) && arg.starts_with("-") && arg.len() > 1
-    }
+    #[command(subcommand)]
+    command: Option<CliCommand>,
+}
 
-    fn is_flag(arg: &str) -> bool {
-        is_long_flag(arg) || is_short_flag(arg)
-    }
+#[derive(Subcommand, Debug)]
+enum CliCommand {
+    /// Build OHLCV candles for a pair and timeframe
+    Candles(CandlesArgs),
+    /// Print a candlestick chart of the most recent bars
+    Chart(ChartArgs),
+    /// Run a strategy over a pair's candles and print a performance report
+    Backtest(BacktestArgs),
+    /// Show the data catalog of stored pairs and exports
+    Catalog(CatalogArgs),
+    /// Re-check exported files against their .sha256 checksum files
+    Verify(VerifyArgs),
+    /// Add, remove, update and check the stored pairs
+    Database(DatabaseArgs),
+    /// Export one pair to a pair file, or import one
+    Pair(PairArgs),
+    /// Start the terminal interface, or the HTTP server
+    Start(StartArgs),
+    /// List, add or remove command aliases
+    Alias(AliasArgs),
+    /// Print or change a value in config.json
+    Config(ConfigArgs),
+    /// Trade a simulated account against the stored ticks
+    Paper(PaperArgs),
+    /// Show the commands that changed pairs, config or paper accounts
+    Audit(AuditArgs),
+    /// Trade the Kraken account of the configured API key
+    Live(LiveArgs),
+    /// Export every paper and live fill with its fee and realized P&L
+    Fills(FillsArgs),
+    /// Buy a pair for a fixed amount on a schedule, in a paper account or
+    /// the live one
+    Recurring(RecurringArgs),
+}
 
-    let mut command_buffer: Vec<String> = Vec::new();
-    let mut op_mode: &String = &String::new();
-    let mut flag_name: &String = &String::new();
-    let mut unknown_flags: Vec<String> = Vec::new();
+#[derive(Args, Debug)]
+struct CandlesArgs {
+    /// Name of the exchange (kraken, binance, ...)
+    exchange: Option<String>,
 
-    // Specific option variables
-    let mut exchange: String = String::new();
-    let mut db_int_check_name: String = "all".to_string(); 
-    let mut db_int_check_ticker: String = "all".to_string(); 
-    let mut db_int_check: bool = false;
-    let mut server_start_http_mode: bool = false;
+    /// Trading pair symbol (btcusd, ethusdt, ...), or several separated 
+    /// by commas
+    // The full path keeps clap from taking it as a repeated argument
+    #[arg(value_parser = parse_list::<Ticker>)]
+    ticker: Option<::std::vec::Vec<Ticker>>,
 
-    if arguments.len() == 0 {
-        println!("{ARG_ERROR}");
-    };
+    /// Candle timeframe (1m, 15m, 4h, 1d, ...), or several separated by 
+    /// commas
+    #[arg(value_parser = parse_list::<String>)]
+    period: Option<::std::vec::Vec<String>>,
 
-    for (i, arg) in arguments.iter().enumerate() {
-     
-        if i == 0 && !is_flag(&arg) {
-            op_mode = arg;
-        }
-        
-        else if op_mode != "" {
-
-            match &op_mode[..] {
-                
-                "database" => {
-                    if is_flag(arg) {
-                        flag_name = arg;
-                        exchange = String::new();
-                        
-                        if flag_name == "--update" {
-                            parsed_args.commands.push(
-                                Command::UpdatePairs
-                            );                               
-                        }
-                        else if flag_name == "--integrity" {
-                            db_int_check = true; 
-                        };
-                    }
-                    else {  // Flag option parsing
-                        
-                        if flag_name == "--add-pairs" 
-                        || flag_name == "--rm-pairs" {
-                            
-                            if exchange == "" {
-                                match &arg[..] {
-                                    "kraken" 
-                                    // | other exchanges here
-                                    => {
-                                        exchange = arg.to_string();
-                                    },
-                                    _ => {
-                                        parsed_args.parser_error = Some(
-                                            ParserError::UnknownArg(
-                                                format!(
-                                                    "Invalid exchange: {}",
-                                                    arg
-                                                ) 
-                                            ) 
-                                        );
-                                        return parsed_args
-                                    }
-                                }
-                            } 
-                            else {
-
-                                if flag_name == "--add-pairs" {
-                                    parsed_args.commands.push(
-                                        Command::AddPair { 
-                                            exchange: exchange.clone(), 
-                                            ticker: arg.to_string() 
-                                        }
-                                    );
-                                }
-                                else if flag_name == "--rm-pairs" {
-                                    parsed_args.commands.push(
-                                        Command::DropPair { 
-                                            exchange: exchange.clone(), 
-                                            ticker: arg.to_string() 
-                                        }
-                                    );
-                                };
-                            }
-                        }
-
-                        else if flag_name == "--integrity" {
-                            if db_int_check_name == "all" {
-                                db_int_check_name = arg.to_string(); 
-                            }
-                            else if db_int_check_ticker == "all" {
-                                db_int_check_ticker = arg.to_string(); 
-                            };
-                        }
-
-                        else {
-                            unknown_flags.push(
-                                format!(
-                                    "Invalid flag: {}",
-                                    arg
-                                )
-                            ); 
-                        }
-                    }; 
-                },
+    /// Check the pair's ticks before and after building the candles
+    #[arg(short, long)]
+    integrity: bool,
 
-                "candles" => {
-                    
-                    if !is_flag(&arg) {
-                        command_buffer.push(arg.to_string());
-                    }
-                    else if command_buffer.len() == 3 && is_flag(&arg) {
-                        command_buffer.push(arg.to_string());
-                    };
+    /// Insert flat, zero-volume bars for periods without trades
+    #[arg(long)]
+    fill_gaps: bool,
 
-                },
+    /// Open the first bar at the pair's first stored tick
+    #[arg(long)]
+    listing_anchor: bool,
 
-                "start" => {
+    /// Save the candles as csv, json, jsonl or parquet
+    #[arg(
+        short, 
+        long, 
+        visible_alias = "format",
+        value_name = "FORMAT", 
+        value_parser = parse_from_str::<ExportFormat>
+    )]
+    export: Option<ExportFormat>,
 
-                    if arg == "--http" {
-                        server_start_http_mode = true;
-                    };
+    /// Write the candles to PATH, or to standard output with -
+    #[arg(short, long, value_name = "PATH", allow_hyphen_values = true)]
+    out: Option<String>,
 
-                },
+    /// Also save the ticks the candles were built from
+    #[arg(long)]
+    ticks: bool,
 
-                _ => {}
-            }
+    /// CSV field delimiter, a single character or 'tab'
+    #[arg(short, long, value_name = "C", value_parser = parse_delimiter)]
+    delimiter: Option<char>,
 
-        }
+    /// CSV quoting: minimal, all or never
+    #[arg(
+        long, 
+        value_name = "MODE", 
+        value_parser = parse_from_str::<CsvQuoting>
+    )]
+    quote: Option<CsvQuoting>,
 
-        else if is_flag(&arg) {
-            
-            if arg.len() < 2 { continue };
-            
-            match &arg[2..] {
-                "help" => parsed_args.commands.push(Command::Help),
-                "dev" => parsed_args.dev_mode = true,
-                _ => { println!("{ARG_ERROR}") }
-            }        
-        }
+    /// Leave the header row out of CSV exports
+    #[arg(long)]
+    no_header: bool,
 
-        else {
-            println!("{ARG_ERROR}");
-        };
+    /// strftime pattern for the CSV timestamp column, in UTC
+    #[arg(long, value_name = "FMT", value_parser = parse_timestamp_format)]
+    timestamp_format: Option<String>,
 
-    }; 
+    /// Only build from ticks on or after DATE, a date, date and time or 
+    /// unix timestamp
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    from: Option<u64>,
 
-    if unknown_flags.len() > 0 {
-        parsed_args.parser_error = Some(
-            ParserError::UnknownFlags(unknown_flags)
-        )
-    };
+    /// Only build from ticks before DATE
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    to: Option<u64>,
+}
 
-    match &op_mode[..] {
-        "candles" => {
-
-            let ex = command_buffer.remove(0);
-            let sym = command_buffer.remove(0);
-            let p = command_buffer.remove(0);
-            let int_check = match command_buffer.len() {
-                1 => {
-                    let opt = command_buffer.remove(0);
-                    if opt == "--integrity" || opt == "-i" {
-                        true 
-                    }
-                    else {
-                        false
-                    }
-                }, 
-                _ => false 
-            };
+#[derive(Args, Debug)]
+struct ChartArgs {
+    exchange: String,
+    ticker: Ticker,
+    period: String,
 
-            parsed_args.commands.push(
-                Command::CandleBuilder { 
-                    exchange: ex, 
-                    ticker: sym, 
-                    period: p, 
-                    integrity_check: int_check 
-                }
-            );
-        },
+    /// Draw the chart with text, the only style for now
+    #[arg(long)]
+    ascii: bool,
 
-        "database" => {
-            if db_int_check {
-                parsed_args.commands.push(
-                    Command::DbIntegrityCheck { 
-                        exchange: db_int_check_name, 
-                        ticker: db_int_check_ticker 
-                    }
-                );
-            };
-        },
+    /// Number of bars to draw
+    #[arg(
+        short = 'n', 
+        long = "bars", 
+        value_name = "N", 
+        default_value_t = 60,
+        value_parser = parse_num_bars
+    )]
+    num_bars: usize,
+}
 
-        "start" => {
-            parsed_args.commands.push(Command::StartServer {
-                http: server_start_http_mode
-            });
-        },
+#[derive(Args, Debug)]
+struct BacktestArgs {
+    exchange: String,
+    ticker: Ticker,
+    period: String,
+
+    /// Strategy and parameters, e.g. "sma-cross(20,50)" or "PATH.rhai(20)"
+    #[arg(short, long, value_name = "SPEC", default_value = "sma-cross")]
+    strategy: String,
+
+    /// Starting cash in the quote currency
+    #[arg(
+        long, 
+        value_name = "N", 
+        default_value_t = 10_000.0, 
+        value_parser = parse_cash
+    )]
+    cash: f64,
+
+    /// Re-optimize on windows of IN bars, trading the OUT bars after each
+    #[arg(long, value_name = "IN,OUT", value_parser = parse_windows)]
+    walk_forward: Option<(usize, usize)>,
+
+    /// What walk-forward optimization maximizes: sharpe, sortino or return
+    #[arg(
+        long, 
+        value_name = "NAME", 
+        value_parser = parse_from_str::<Objective>
+    )]
+    objective: Option<Objective>,
+
+    /// Grid search over the parameter ranges in a TOML file
+    #[arg(long, value_name = "FILE")]
+    optimize: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct CatalogArgs {
+    exchange: Option<String>,
+    ticker: Option<String>,
+
+    /// Regenerate the catalog from the database before showing it
+    #[arg(short, long)]
+    refresh: bool,
+}
+
+#[derive(Args, Debug)]
+struct VerifyArgs {
+    /// An exported file, its checksum file or a directory
+    path: String,
+}
+
+#[derive(Args, Debug)]
+struct FillsArgs {
+    /// Save the fills as csv or parquet
+    #[arg(
+        short, 
+        long, 
+        value_name = "FORMAT", 
+        value_parser = parse_from_str::<ExportFormat>
+    )]
+    format: Option<ExportFormat>,
+
+    /// Write the fills to PATH
+    #[arg(short, long, value_name = "PATH")]
+    out: Option<String>,
+
+    /// Only the fills on or after DATE, a date, date and time or unix 
+    /// timestamp
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    from: Option<u64>,
+
+    /// Only the fills before DATE
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    to: Option<u64>,
+}
+
+#[derive(Args, Debug)]
+struct RecurringArgs {
+    #[command(subcommand)]
+    action: RecurringAction,
+}
+
+#[derive(Subcommand, Debug)]
+enum RecurringAction {
+    /// Schedule a buy of AMOUNT of the quote currency's worth of a pair
+    Add(RecurringAddArgs),
+    /// List the recurring buys
+    List,
+    /// Stop a recurring buy, keeping its history
+    Remove {
+        id: i64,
+    },
+    /// Show a recurring buy's runs and the average cost of what it bought
+    History {
+        id: i64,
+    },
+    /// Place the buys that are due now, as `start --http` does every 
+    /// minute
+    Run,
+}
+
+#[derive(Args, Debug)]
+struct RecurringAddArgs {
+    exchange: String,
+    ticker: Ticker,
+
+    /// Spent on each buy, in the quote currency
+    #[arg(value_parser = parse_amount)]
+    amount: f64,
+
+    /// Time between two buys, e.g. 4h, 1d or 1w
+    #[arg(long, value_name = "PERIOD", value_parser = parse_cadence)]
+    every: u64,
+
+    /// Paper account to buy in (default: default)
+    #[arg(
+        long, 
+        value_name = "NAME", 
+        value_parser = parse_account, 
+        conflicts_with = "live"
+    )]
+    account: Option<String>,
+
+    /// Buy on the live Kraken account instead of a paper one
+    #[arg(long)]
+    live: bool,
+
+    /// First buy at DATE instead of now
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    start: Option<u64>,
+}
+
+#[derive(Args, Debug)]
+struct AuditArgs {
+    /// Only show one action, e.g. pair.add or config.set
+    #[arg(long, value_name = "ACTION")]
+    action: Option<String>,
+
+    /// Number of rows to show, newest first
+    #[arg(
+        short = 'n', 
+        long, 
+        value_name = "N", 
+        default_value_t = 50,
+        value_parser = parse_num_rows
+    )]
+    limit: usize,
+}
+
+#[derive(Args, Debug)]
+struct DatabaseArgs {
+    /// Add pairs to the database: EXCHANGE TICKER [TICKER...]
+    #[arg(long, num_args = 0.., value_name = "PAIR")]
+    add_pairs: Option<Vec<String>>,
+
+    /// Remove pairs from the database: EXCHANGE TICKER [TICKER...]
+    #[arg(long, num_args = 2.., value_name = "PAIR")]
+    rm_pairs: Option<Vec<String>>,
+
+    /// Undo the download batches with these IDs
+    #[arg(long, num_args = 1.., value_name = "ID")]
+    rollback_batch: Vec<i64>,
+
+    /// Download new trades for every pair
+    #[arg(long)]
+    update: bool,
+
+    /// Update only the pairs whose last download ended in an error
+    #[arg(long)]
+    retry_failed: bool,
+
+    /// Report the requests sent to each exchange
+    #[arg(long)]
+    api_usage: bool,
+
+    /// Show every pair table's rows, size, tick range and lag
+    #[arg(long)]
+    stats: bool,
+
+    /// Check every pair, an exchange's pairs, or a single pair
+    #[arg(long, num_args = 0..=2, value_names = ["EXCHANGE", "TICKER"])]
+    integrity: Option<Vec<String>>,
+
+    /// Re-download around missing ticks found by --integrity
+    #[arg(long, requires = "integrity")]
+    repair: bool,
+}
+
+#[derive(Args, Debug)]
+struct PairArgs {
+    #[command(subcommand)]
+    action: PairAction,
+}
+
+#[derive(Subcommand, Debug)]
+enum PairAction {
+    /// Save one pair to a portable, compressed pair file
+    Export {
+        exchange: String,
+        ticker: Ticker,
+
+        /// Defaults to EXCHANGE_TICKER.dtpack in the current directory
+        #[arg(short, long, value_name = "FILE")]
+        out: Option<String>,
+    },
+    /// Add the pair saved in a pair file to the database
+    Import {
+        file: String,
+    },
+}
+
+#[derive(Args, Debug)]
+struct StartArgs {
+    /// Start the REST API server instead of the terminal interface
+    #[arg(long)]
+    http: bool,
+
+    /// Disable deleting pairs and saving settings in the interface
+    #[arg(long)]
+    guest: bool,
+
+    /// Screen the terminal interface opens on
+    #[arg(long, value_name = "SCREEN")]
+    screen: Option<StartScreen>,
+}
+
+#[derive(Args, Debug)]
+struct AliasArgs {
+    #[command(subcommand)]
+    action: AliasAction,
+}
+
+#[derive(Subcommand, Debug)]
+enum AliasAction {
+    /// List the aliases in config.json
+    List,
+    /// Add an alias, replacing any of the same name
+    Add {
+        name: String,
+
+        /// The command line NAME stands for, e.g. candles kraken BTCUSD 1h
+        #[arg(
+            required = true, 
+            num_args = 1.., 
+            trailing_var_arg = true, 
+            allow_hyphen_values = true
+        )]
+        command: Vec<String>,
+    },
+    /// Remove an alias
+    Remove {
+        name: String,
+    },
+}
+
+#[derive(Args, Debug)]
+struct ConfigArgs {
+    #[command(subcommand)]
+    action: ConfigAction,
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigAction {
+    /// Print a value, or the whole config without KEY
+    Get {
+        /// Dotted path of the value, e.g. chart_parameters.num_bars
+        key: Option<String>,
+    },
+    /// Change a value and save the config
+    Set {
+        /// Dotted path of the value, e.g. data_download.cache_size
+        key: String,
+
+        /// The new value, blank to clear an optional one
+        #[arg(allow_hyphen_values = true)]
+        value: String,
+    },
+}
+
+#[derive(Args, Debug)]
+struct PaperArgs {
+    /// Account to trade, opened with 10,000 in cash when it's new
+    #[arg(
+        long, 
+        global = true, 
+        value_name = "NAME", 
+        default_value = DEFAULT_ACCOUNT,
+        value_parser = parse_account
+    )]
+    account: String,
+
+    #[command(subcommand)]
+    action: PaperAction,
+}
+
+#[derive(Subcommand, Debug)]
+enum PaperAction {
+    /// Show the account's cash, positions and open orders
+    Status,
+    /// Place a buy order
+    Buy(PaperOrderArgs),
+    /// Place a sell order
+    Sell(PaperOrderArgs),
+    /// Cancel an open order
+    Cancel {
+        id: i64,
+    },
+    /// Move the limit or stop price of an open order
+    Modify {
+        id: i64,
+
+        /// The new price
+        #[arg(long, value_name = "PRICE", value_parser = parse_price)]
+        price: f64,
+    },
+    /// List every order of the account, newest first
+    History,
+    /// Fill open orders against the ticks stored since they were placed
+    Sync,
+    /// Buy and sell at market to match target weights of the equity,
+    /// asking before the orders are placed
+    Rebalance(PaperRebalanceArgs),
+    /// Empty the account and start it again
+    Reset {
+        /// Starting cash in the quote currency
+        #[arg(
+            long, 
+            value_name = "N", 
+            default_value_t = DEFAULT_CASH, 
+            value_parser = parse_cash
+        )]
+        cash: f64,
+    },
+}
+
+#[derive(Args, Debug)]
+struct LiveArgs {
+    #[command(subcommand)]
+    action: LiveAction,
+}
+
+#[derive(Subcommand, Debug)]
+enum LiveAction {
+    /// Show the account's balance of each asset
+    Balance,
+    /// List the account's open orders
+    Orders,
+    /// Place a buy order
+    Buy(LiveOrderArgs),
+    /// Place a sell order
+    Sell(LiveOrderArgs),
+    /// Cancel an open order
+    Cancel {
+        txid: String,
+    },
+    /// Move the price of an open order
+    Modify {
+        txid: String,
+
+        /// The new price
+        #[arg(long, value_name = "PRICE", value_parser = parse_price)]
+        price: f64,
+    },
+}
+
+#[derive(Args, Debug)]
+struct LiveOrderArgs {
+    exchange: String,
+    ticker: Ticker,
+
+    /// Volume in the base currency
+    #[arg(value_parser = parse_volume)]
+    volume: f64,
+
+    /// Limit price, a market order without one
+    #[arg(long, value_name = "PRICE", value_parser = parse_price)]
+    limit: Option<f64>,
+
+    /// Have the exchange check the order without placing it
+    #[arg(long)]
+    validate: bool,
+}
+
+#[derive(Args, Debug)]
+struct PaperRebalanceArgs {
+    exchange: String,
+
+    /// Weights of the equity by ticker, e.g. btcusd=0.5 ethusd=0.3, with
+    /// the rest kept in cash. Held pairs left out are sold.
+    #[arg(
+        required = true, 
+        num_args = 1.., 
+        value_name = "TICKER=WEIGHT", 
+        value_parser = parse_weight
+    )]
+    targets: Vec<(Ticker, f64)>,
+
+    /// Leave pairs less than this share of the equity off target alone
+    #[arg(
+        long, 
+        value_name = "SHARE", 
+        default_value_t = 0.01, 
+        value_parser = parse_drift
+    )]
+    min_drift: f64,
+
+    /// Place the orders without asking
+    #[arg(long)]
+    yes: bool,
+}
+
+#[derive(Args, Debug)]
+struct PaperOrderArgs {
+    exchange: String,
+    ticker: Ticker,
+
+    /// Volume in the base currency
+    #[arg(value_parser = parse_volume)]
+    volume: f64,
+
+    /// Limit price, a market order without one
+    #[arg(long, value_name = "PRICE", value_parser = parse_price)]
+    limit: Option<f64>,
+
+    /// Stop price: fill at market once a tick reaches it
+    #[arg(
+        long, 
+        value_name = "PRICE", 
+        value_parser = parse_price, 
+        conflicts_with = "limit"
+    )]
+    stop: Option<f64>,
+
+    /// Place a stop order next to the limit order, the first of the two
+    /// to fill cancels the other
+    #[arg(
+        long, 
+        value_name = "PRICE", 
+        value_parser = parse_price, 
+        requires = "limit"
+    )]
+    oco_stop: Option<f64>,
+
+    /// Close the position with a stop at this price once the order fills,
+    /// along with --take-profit
+    #[arg(
+        long, 
+        value_name = "PRICE", 
+        value_parser = parse_price, 
+        requires = "take_profit"
+    )]
+    stop_loss: Option<f64>,
+
+    /// Close the position with a limit at this price once the order fills,
+    /// along with --stop-loss
+    #[arg(
+        long, 
+        value_name = "PRICE", 
+        value_parser = parse_price, 
+        requires = "stop_loss"
+    )]
+    take_profit: Option<f64>,
+
+    /// Place the order at DATE, replaying the stored ticks after it
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    at: Option<u64>,
+}
+
+
+// ---------------------------- VALUE PARSERS ------------------------------ //
+/// Parses any `FromStr` type whose error only implements `Display`
+fn parse_from_str<T: FromStr>(arg: &str) -> Result<T, String> 
+    where T::Err: std::fmt::Display {
+    arg.parse::<T>().map_err(|e| e.to_string())
+}
+
+/// Parses a comma separated list, leaving out blanks and repeats
+fn parse_list<T: FromStr + PartialEq>(arg: &str) -> Result<Vec<T>, String> 
+    where T::Err: std::fmt::Display {
+
+    let mut items: Vec<T> = Vec::new();
+
+    for item in arg.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+        let item: T = parse_from_str(item)?;
+        if !items.contains(&item) {
+            items.push(item);
+        };
+    };
+
+    match items.is_empty() {
+        true => Err(format!("Nothing listed in {:?}", arg)),
+        false => Ok(items)
+    }
+}
+
+fn parse_delimiter(arg: &str) -> Result<char, String> {
+    let mut chars = arg.chars();
+    match (arg, chars.next(), chars.next()) {
+        ("tab" | "\\t", _, _) => Ok('\t'),
+        (_, Some(c), None) => Ok(c),
+        _ => Err(format!("Invalid delimiter: {}", arg))
+    }
+}
+
+fn parse_timestamp_format(arg: &str) -> Result<String, String> {
+    match timestamp_format_is_valid(arg) {
+        true => Ok(arg.to_string()),
+        false => Err(format!("Invalid timestamp format: {}", arg))
+    }
+}
+
+/// Parses an ISO 8601 date, or date and time, or a unix timestamp into 
+/// unix seconds
+fn parse_date(arg: &str) -> Result<u64, String> {
+    parse_date_or_timestamp(arg).map_err(|_| format!(
+        "Invalid date: {}, try e.g. 2024-03-01, 2024-03-01T12:30 or a unix \
+        timestamp",
+        arg
+    ))
+}
+
+fn parse_num_bars(arg: &str) -> Result<usize, String> {
+    match arg.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("Invalid number of bars: {}", arg))
+    }
+}
+
+fn parse_num_rows(arg: &str) -> Result<usize, String> {
+    match arg.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("Invalid number of rows: {}", arg))
+    }
+}
+
+fn parse_cash(arg: &str) -> Result<f64, String> {
+    match arg.parse::<f64>() {
+        Ok(c) if c > 0.0 => Ok(c),
+        _ => Err(format!("Invalid starting cash: {}", arg))
+    }
+}
+
+fn parse_volume(arg: &str) -> Result<f64, String> {
+    match arg.parse::<f64>() {
+        Ok(v) if v > 0.0 => Ok(v),
+        _ => Err(format!("Invalid volume: {}", arg))
+    }
+}
+
+fn parse_amount(arg: &str) -> Result<f64, String> {
+    match arg.parse::<f64>() {
+        Ok(a) if a > 0.0 => Ok(a),
+        _ => Err(format!("Invalid amount: {}", arg))
+    }
+}
+
+/// Parses a time between two recurring buys, e.g. 4h or 1w, into seconds
+fn parse_cadence(arg: &str) -> Result<u64, String> {
+    let invalid = || format!(
+        "Invalid period: {}, try e.g. 30m, 4h, 1d or 1w", 
+        arg
+    );
+    let (symbol, n) = get_period_portions_from_string(arg)
+        .map_err(|_| invalid())?;
+    let secs: u64 = match symbol {
+        'w' => Ok(n.saturating_mul(604_800)),
+        _ => calculate_seconds_in_period(n, symbol)
+    }
+        .map_err(|_| invalid())?;
+    match secs >= 60 {
+        true => Ok(secs),
+        false => Err(invalid())
+    }
+}
+
+fn parse_price(arg: &str) -> Result<f64, String> {
+    match arg.parse::<f64>() {
+        Ok(p) if p > 0.0 => Ok(p),
+        _ => Err(format!("Invalid limit price: {}", arg))
+    }
+}
+
+fn parse_account(arg: &str) -> Result<String, String> {
+    match is_account_name(arg) {
+        true => Ok(arg.to_string()),
+        false => Err(format!(
+            "Invalid account name: {}, use up to 32 letters, digits, - and _",
+            arg
+        ))
+    }
+}
+
+/// Parses a rebalancing target given as `TICKER=WEIGHT`
+fn parse_weight(arg: &str) -> Result<(Ticker, f64), String> {
+    let (ticker, weight) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("Expected TICKER=WEIGHT, got {}", arg))?;
+    match weight.trim().parse::<f64>() {
+        Ok(w) if (0.0..=1.0).contains(&w) => {
+            Ok((parse_from_str(ticker.trim())?, w))
+        },
+        _ => Err(format!("Invalid weight: {}, use 0 to 1", weight))
+    }
+}
+
+fn parse_drift(arg: &str) -> Result<f64, String> {
+    match arg.parse::<f64>() {
+        Ok(d) if (0.0..1.0).contains(&d) => Ok(d),
+        _ => Err(format!("Invalid drift: {}, use 0 to 1", arg))
+    }
+}
+
+/// Parses walk-forward window sizes given as `IN,OUT`
+fn parse_windows(arg: &str) -> Result<(usize, usize), String> {
+    arg.split_once(',')
+        .and_then(|(a, b)| Some((
+            a.trim().parse::<usize>().ok()?,
+            b.trim().parse::<usize>().ok()?
+        )))
+        .filter(|(a, b)| *a > 0 && *b > 0)
+        .ok_or_else(|| format!("Invalid walk-forward windows: {}", arg))
+}
+
+/// Splits `--add-pairs` or `--rm-pairs` values into the exchange and its 
+/// tickers
+fn parse_pairs(values: Vec<String>) 
+    -> Result<(Option<String>, Vec<Ticker>), ParserError> {
+
+    let mut values = values.into_iter();
+
+    let exchange: Option<String> = match values.next() {
+        Some(e) if EXCHANGES.contains(&&e[..]) => Some(e),
+        Some(e) => return Err(ParserError::UnknownArg(
+            format!("Invalid exchange: {}", e)
+        )),
+        None => None
+    };
+
+    let tickers: Vec<Ticker> = values
+        .map(|t| t.parse::<Ticker>())
+        .collect::<Result<_, _>>()
+        .map_err(|e| ParserError::UnknownArg(e.to_string()))?;
+
+    Ok((exchange, tickers))
+}
+
+
+// ------------------------- SUBCOMMAND COMMANDS --------------------------- //
+impl CandlesArgs {
+
+    fn push_commands(self, parsed_args: &mut ParsedArgs) 
+        -> Result<(), ParserError> {
+
+        let (Some(exchange), Some(tickers), Some(periods)) = (
+            self.exchange.clone(), 
+            self.ticker.clone(), 
+            self.period
+        ) else {
+            parsed_args.partial = Some(PartialCommand::Candles {
+                exchange: self.exchange,
+                tickers: self.ticker.unwrap_or_default()
+            });
+            return Err(ParserError::MissingArgs(
+                "candles needs EXCHANGE TICKER PERIOD".to_string()
+            ))
+        };
+
+        if let (Some(from), Some(to)) = (self.from, self.to)
+            && from >= to
+        {
+            return Err(ParserError::UnknownArg(
+                "--from has to be before --to".to_string()
+            ))
+        };
+
+        if self.out.is_some() && tickers.len() * periods.len() > 1 {
+            return Err(ParserError::UnknownArg(
+                "--out takes a single ticker and period, use --format to \
+                save several".to_string()
+            ))
+        };
+
+        let csv_flags = CsvFlags {
+            delimiter: self.delimiter,
+            quoting: self.quote,
+            no_header: self.no_header,
+            timestamp_format: self.timestamp_format
+        };
+
+        for ticker in &tickers {
+            for period in &periods {
+                parsed_args.commands.push(Command::CandleBuilder { 
+                    exchange: exchange.clone(), 
+                    ticker: ticker.clone(), 
+                    period: period.clone(), 
+                    integrity_check: self.integrity,
+                    fill_gaps: self.fill_gaps,
+                    listing_anchor: self.listing_anchor,
+                    export: self.export,
+                    out: self.out.clone(),
+                    export_ticks: self.ticks,
+                    csv_flags: csv_flags.clone(),
+                    from: self.from,
+                    to: self.to
+                });
+            };
+        };
+
+        Ok(())
+    }
+}
+
+impl ChartArgs {
+
+    fn push_commands(self, parsed_args: &mut ParsedArgs) 
+        -> Result<(), ParserError> {
+
+        parsed_args.commands.push(Command::Chart {
+            exchange: self.exchange,
+            ticker: self.ticker,
+            period: self.period,
+            num_bars: self.num_bars
+        });
+
+        Ok(())
+    }
+}
+
+impl BacktestArgs {
+
+    fn push_commands(self, parsed_args: &mut ParsedArgs) 
+        -> Result<(), ParserError> {
+
+        let objective: Objective = self.objective.unwrap_or_default();
+
+        parsed_args.commands.push(Command::Backtest {
+            exchange: self.exchange,
+            ticker: self.ticker,
+            period: self.period,
+            strategy: self.strategy,
+            cash: self.cash,
+            walk_forward: self.walk_forward.map(|(is, oos)| {
+                WalkForwardConfig {
+                    in_sample: is,
+                    out_of_sample: oos,
+                    objective
+                }
+            }),
+            optimize: self.optimize
+        });
+
+        Ok(())
+    }
+}
+
+impl CatalogArgs {
+
+    fn push_commands(self, parsed_args: &mut ParsedArgs) 
+        -> Result<(), ParserError> {
+
+        parsed_args.commands.push(Command::Catalog {
+            exchange: self.exchange,
+            ticker: self.ticker,
+            refresh: self.refresh
+        });
+
+        Ok(())
+    }
+}
+
+impl AuditArgs {
+
+    fn push_commands(self, parsed_args: &mut ParsedArgs) 
+        -> Result<(), ParserError> {
+        parsed_args.commands.push(Command::AuditLog { 
+            action: self.action, 
+            limit: self.limit 
+        });
+        Ok(())
+    }
+}
+
+impl FillsArgs {
+
+    fn push_commands(self, parsed_args: &mut ParsedArgs) 
+        -> Result<(), ParserError> {
+
+        if let (Some(from), Some(to)) = (self.from, self.to)
+            && from >= to
+        {
+            return Err(ParserError::UnknownArg(
+                "--from has to be before --to".to_string()
+            ))
+        };
+
+        if let Some(format @ (ExportFormat::Json | ExportFormat::Jsonl)) = 
+            self.format 
+        {
+            return Err(ParserError::UnknownArg(format!(
+                "Fills are exported as csv or parquet, not {}", format
+            )))
+        };
+
+        parsed_args.commands.push(Command::ExportFills { 
+            format: self.format, 
+            out: self.out, 
+            from: self.from, 
+            to: self.to 
+        });
+        Ok(())
+    }
+}
+
+impl RecurringArgs {
+
+    fn push_commands(self, parsed_args: &mut ParsedArgs) 
+        -> Result<(), ParserError> {
+
+        parsed_args.commands.push(match self.action {
+            RecurringAction::Add(a) => a.into_command()?,
+            RecurringAction::List => Command::RecurringList,
+            RecurringAction::Remove { id } => {
+                Command::RecurringRemove { id }
+            },
+            RecurringAction::History { id } => {
+                Command::RecurringHistory { id }
+            },
+            RecurringAction::Run => Command::RecurringRun,
+        });
+
+        Ok(())
+    }
+}
+
+impl RecurringAddArgs {
+
+    fn into_command(self) -> Result<Command, ParserError> {
+
+        let exchange: String = self.exchange.to_lowercase();
+
+        if !EXCHANGES.contains(&exchange.as_str()) {
+            return Err(ParserError::UnknownArg(
+                format!("Invalid exchange: {}", self.exchange)
+            ))
+        };
+
+        let account: Option<String> = match self.live {
+            true => None,
+            false => Some(
+                self.account.unwrap_or_else(|| DEFAULT_ACCOUNT.to_string())
+            )
+        };
+
+        Ok(Command::RecurringAdd {
+            schedule: NewRecurringBuy {
+                account,
+                exchange,
+                ticker: self.ticker,
+                amount: self.amount,
+                every: self.every,
+                start: self.start
+            }
+        })
+    }
+}
+
+impl VerifyArgs {
+
+    fn push_commands(self, parsed_args: &mut ParsedArgs) 
+        -> Result<(), ParserError> {
+        parsed_args.commands.push(Command::Verify { path: self.path });
+        Ok(())
+    }
+}
+
+impl DatabaseArgs {
+
+    /// Every flag given runs, in the order the fields are declared
+    fn push_commands(self, parsed_args: &mut ParsedArgs) 
+        -> Result<(), ParserError> {
+
+        let mut missing: Option<ParserError> = None;
+
+        if let Some(values) = self.add_pairs {
+
+            let (exchange, tickers) = parse_pairs(values)?;
+
+            match (exchange, tickers.is_empty()) {
+                (Some(ex), false) => for ticker in tickers {
+                    parsed_args.commands.push(Command::AddPair { 
+                        exchange: ex.clone(), 
+                        ticker 
+                    });
+                },
+                // Given no tickers, or no exchange either
+                (exchange, _) => {
+                    parsed_args.partial = Some(
+                        PartialCommand::AddPairs { exchange }
+                    );
+                    missing = Some(ParserError::MissingArgs(
+                        "--add-pairs needs EXCHANGE TICKER [TICKER...]"
+                            .to_string()
+                    ));
+                }
+            };
+        };
+
+        if let Some(values) = self.rm_pairs {
+
+            let (exchange, tickers) = parse_pairs(values)?;
+            let exchange: String = exchange.unwrap_or_default();
+
+            for ticker in tickers {
+                parsed_args.commands.push(Command::DropPair { 
+                    exchange: exchange.clone(), 
+                    ticker 
+                });
+            };
+        };
+
+        for batch_id in self.rollback_batch {
+            parsed_args.commands.push(Command::RollbackBatch { batch_id });
+        };
+
+        if self.update {
+            parsed_args.commands.push(Command::UpdatePairs);
+        };
+        if self.retry_failed {
+            parsed_args.commands.push(Command::RetryFailed);
+        };
+        if self.api_usage {
+            parsed_args.commands.push(Command::ApiUsage);
+        };
+        if self.stats {
+            parsed_args.commands.push(Command::DbStats);
+        };
+
+        if let Some(names) = self.integrity {
+            let mut names = names.into_iter();
+            parsed_args.commands.push(Command::DbIntegrityCheck { 
+                exchange: names.next().unwrap_or_else(|| "all".to_string()), 
+                ticker: names.next().unwrap_or_else(|| "all".to_string()),
+                repair: self.repair
+            });
+        };
+
+        match missing {
+            Some(e) => Err(e),
+            None => Ok(())
+        }
+    }
+}
+
+impl PairArgs {
+
+    fn push_commands(self, parsed_args: &mut ParsedArgs) 
+        -> Result<(), ParserError> {
+
+        parsed_args.commands.push(match self.action {
+            PairAction::Export { exchange, ticker, out } => {
+                Command::ExportPair { exchange, ticker, out }
+            },
+            PairAction::Import { file } => Command::ImportPair { path: file }
+        });
+
+        Ok(())
+    }
+}
+
+impl AliasArgs {
+
+    fn push_commands(self, parsed_args: &mut ParsedArgs) 
+        -> Result<(), ParserError> {
+
+        parsed_args.commands.push(match self.action {
+            AliasAction::List => Command::ListAliases,
+            AliasAction::Add { name, command } => {
+
+                if !is_alias_name(&name) {
+                    return Err(ParserError::UnknownArg(format!(
+                        "Invalid alias name: {}, use letters, digits, - and \
+                        _, and no command's name",
+                        name
+                    )))
+                };
+                if !is_command_name(&command[0]) {
+                    return Err(ParserError::UnknownCommand(format!(
+                        "An alias has to start with a command, not {}",
+                        command[0]
+                    )))
+                };
+
+                Command::AddAlias { name, command: join_words(&command) }
+            },
+            AliasAction::Remove { name } => Command::RemoveAlias { name }
+        });
+
+        Ok(())
+    }
+}
+
+impl ConfigArgs {
+
+    fn push_commands(self, parsed_args: &mut ParsedArgs) 
+        -> Result<(), ParserError> {
+
+        parsed_args.commands.push(match self.action {
+            ConfigAction::Get { key } => Command::GetConfig { key },
+            ConfigAction::Set { key, value } => {
+                Command::SetConfig { key, value }
+            }
+        });
+
+        Ok(())
+    }
+}
+
+impl PaperArgs {
+
+    fn push_commands(self, parsed_args: &mut ParsedArgs) 
+        -> Result<(), ParserError> {
+
+        let account: String = self.account;
+
+        parsed_args.commands.push(match self.action {
+            PaperAction::Status => Command::PaperStatus { account },
+            PaperAction::Buy(a) => a.into_command(account, OrderSide::Buy)?,
+            PaperAction::Sell(a) => a.into_command(account, OrderSide::Sell)?,
+            PaperAction::Cancel { id } => {
+                Command::PaperCancel { account, order_id: id }
+            },
+            PaperAction::Modify { id, price } => {
+                Command::PaperModify { account, order_id: id, price }
+            },
+            PaperAction::History => Command::PaperHistory { account },
+            PaperAction::Sync => Command::PaperSync { account },
+            PaperAction::Rebalance(a) => a.into_command(account)?,
+            PaperAction::Reset { cash } => {
+                Command::PaperReset { account, cash }
+            }
+        });
+
+        Ok(())
+    }
+}
+
+impl PaperRebalanceArgs {
+
+    fn into_command(self, account: String) -> Result<Command, ParserError> {
+
+        let exchange: String = self.exchange.to_lowercase();
+
+        if !EXCHANGES.contains(&exchange.as_str()) {
+            return Err(ParserError::UnknownArg(
+                format!("Invalid exchange: {}", self.exchange)
+            ))
+        };
+
+        let total: f64 = self.targets.iter().map(|(_, w)| w).sum();
+        if total > 1.0 + f64::EPSILON {
+            return Err(ParserError::UnknownArg(
+                format!("Target weights add up to {}, more than 1", total)
+            ))
+        };
+
+        Ok(Command::PaperRebalance {
+            account,
+            exchange,
+            targets: self.targets,
+            min_drift: self.min_drift,
+            confirmed: self.yes
+        })
+    }
+}
+
+impl PaperOrderArgs {
+
+    fn into_command(self, account: String, side: OrderSide) 
+        -> Result<Command, ParserError> {
+
+        let exchange: String = self.exchange.to_lowercase();
+
+        if !EXCHANGES.contains(&exchange.as_str()) {
+            return Err(ParserError::UnknownArg(
+                format!("Invalid exchange: {}", self.exchange)
+            ))
+        };
+
+        let contingent: Contingent = Contingent::from_prices(
+            self.oco_stop,
+            self.stop_loss,
+            self.take_profit
+        )
+            .map_err(ParserError::UnknownArg)?;
+
+        Ok(Command::PaperOrder {
+            account,
+            order: NewOrder {
+                exchange,
+                ticker: self.ticker,
+                side,
+                volume: self.volume,
+                limit: self.limit,
+                stop: self.stop,
+                at: self.at,
+                contingent
+            }
+        })
+    }
+}
+
+impl LiveArgs {
+
+    fn push_commands(self, parsed_args: &mut ParsedArgs) 
+        -> Result<(), ParserError> {
+
+        parsed_args.commands.push(match self.action {
+            LiveAction::Balance => Command::LiveBalance,
+            LiveAction::Orders => Command::LiveOpenOrders,
+            LiveAction::Buy(a) => a.into_command(OrderSide::Buy)?,
+            LiveAction::Sell(a) => a.into_command(OrderSide::Sell)?,
+            LiveAction::Cancel { txid } => Command::LiveCancel { txid },
+            LiveAction::Modify { txid, price } => {
+                Command::LiveModify { txid, price }
+            },
+        });
+
+        Ok(())
+    }
+}
+
+impl LiveOrderArgs {
+
+    fn into_command(self, side: OrderSide) -> Result<Command, ParserError> {
+
+        let exchange: String = self.exchange.to_lowercase();
+
+        if !EXCHANGES.contains(&exchange.as_str()) {
+            return Err(ParserError::UnknownArg(
+                format!("Invalid exchange: {}", self.exchange)
+            ))
+        };
+
+        Ok(Command::LiveOrder {
+            order: LiveOrder {
+                exchange,
+                ticker: self.ticker,
+                side,
+                volume: self.volume,
+                limit: self.limit,
+                validate: self.validate
+            }
+        })
+    }
+}
+
+impl StartArgs {
+
+    fn push_commands(self, parsed_args: &mut ParsedArgs) 
+        -> Result<(), ParserError> {
+
+        parsed_args.commands.push(Command::StartServer {
+            http: self.http,
+            guest: self.guest,
+            screen: self.screen
+        });
+
+        Ok(())
+    }
+}
+
+
+// -------------------------------- ALIASES -------------------------------- //
+/// Whether `name` is one of the subcommands, e.g. `candles`
+fn is_command_name(name: &str) -> bool {
+    Cli::command().get_subcommands().any(|c| c.get_name() == name)
+}
+
+/// Alias names are words that don't hide a command
+pub fn is_alias_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        && !name.starts_with('-')
+        && !is_command_name(name)
+}
+
+/// Splits a command line into words, keeping text in single or double 
+/// quotes together
+pub fn split_words(line: &str) -> Vec<String> {
+
+    let mut words: Vec<String> = Vec::new();
+    let mut word: String = String::new();
+    let mut in_word: bool = false;
+    let mut quote: Option<char> = None;
+
+    for c in line.chars() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => word.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_word = true;
+            },
+            (None, _) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                };
+            },
+            (None, _) => {
+                word.push(c);
+                in_word = true;
+            }
+        };
+    };
+
+    if in_word {
+        words.push(word);
+    };
+
+    words
+}
+
+/// Joins words back into a command line `split_words` splits the same way
+fn join_words(words: &[String]) -> String {
+    words
+        .iter()
+        .map(|w| match w.is_empty() || w.contains(char::is_whitespace) {
+            true => format!("\"{}\"", w),
+            false => w.clone()
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Replaces an alias given as the first argument with the words it stands 
+/// for, keeping the arguments after it
+///
+/// Aliases aren't expanded again, so one alias can't start with another. 
+/// The subcommands always win over an alias of the same name.
+pub fn expand_alias(
+    arguments: Vec<String>, 
+    aliases: &BTreeMap<String, String>
+) -> Vec<String> {
+
+    let Some(expansion) = arguments
+        .first()
+        .filter(|a| !is_command_name(a))
+        .and_then(|a| aliases.get(a))
+    else {
+        return arguments
+    };
+
+    split_words(expansion)
+        .into_iter()
+        .chain(arguments.into_iter().skip(1))
+        .collect()
+}
+
+
+/// Parses command line arguments into a ParsedArgs struct 
+///
+/// If 'None' is passed in as the argument, then commands are taken from 
+/// std::env::args(). Otherwise, pass Some(a) where a is a vector of string 
+/// values. A ParsedArgs struct is always returned no matter what. If any 
+/// arguments were invalid, then `ParsedArgs.parser_error` will contain 
+/// a specific error showing what went wrong. If `parser_error` is None,
+/// then the argument parsing was successful.
+///
+/// A first argument naming one of `aliases` is expanded first, see 
+/// `expand_alias`.
+///
+/// `dtrade COMMAND --help` and `--version` print their text here, and 
+/// return no commands.
+pub fn parse_args(
+    passed_arguments: Option<Vec<String>>, 
+    aliases: &BTreeMap<String, String>
+) -> ParsedArgs {
+
+    // Initialization
+    let arguments: Vec<String> = expand_alias(
+        match passed_arguments {
+            Some(a) => a, 
+            None => args().skip(2).collect()
+        },
+        aliases
+    );
+    
+    let mut parsed_args: ParsedArgs = ParsedArgs::new();
+
+    if arguments.is_empty() {
+        println!("{ARG_ERROR}");
+        return parsed_args
+    };
+
+    let cli: Cli = match Cli::try_parse_from(
+        iter::once("dtrade".to_string()).chain(arguments)
+    ) {
+        Ok(c) => c,
+        Err(e) => {
+            match e.kind() {
+                ErrorKind::DisplayHelp | ErrorKind::DisplayVersion => {
+                    e.print().ok();
+                },
+                _ => parsed_args.parser_error = Some(ParserError::from(e))
+            };
+            return parsed_args
+        }
+    };
+
+    parsed_args.dev_mode = cli.dev;
+    parsed_args.json = cli.json;
+
+    if cli.help {
+        parsed_args.commands.push(Command::Help);
+    };
+
+    let pushed: Result<(), ParserError> = match cli.command {
+        Some(CliCommand::Candles(a)) => a.push_commands(&mut parsed_args),
+        Some(CliCommand::Chart(a)) => a.push_commands(&mut parsed_args),
+        Some(CliCommand::Backtest(a)) => a.push_commands(&mut parsed_args),
+        Some(CliCommand::Catalog(a)) => a.push_commands(&mut parsed_args),
+        Some(CliCommand::Verify(a)) => a.push_commands(&mut parsed_args),
+        Some(CliCommand::Database(a)) => a.push_commands(&mut parsed_args),
+        Some(CliCommand::Pair(a)) => a.push_commands(&mut parsed_args),
+        Some(CliCommand::Start(a)) => a.push_commands(&mut parsed_args),
+        Some(CliCommand::Alias(a)) => a.push_commands(&mut parsed_args),
+        Some(CliCommand::Config(a)) => a.push_commands(&mut parsed_args),
+        Some(CliCommand::Paper(a)) => a.push_commands(&mut parsed_args),
+        Some(CliCommand::Audit(a)) => a.push_commands(&mut parsed_args),
+        Some(CliCommand::Live(a)) => a.push_commands(&mut parsed_args),
+        Some(CliCommand::Fills(a)) => a.push_commands(&mut parsed_args),
+        Some(CliCommand::Recurring(a)) => {
+            a.push_commands(&mut parsed_args)
+        },
+        None if !cli.help && !cli.dev => {
+            println!("{ARG_ERROR}");
+            Ok(())
+        },
+        None => Ok(())
+    };
 
-        _ => {}
+    if let Err(e) = pushed {
+        parsed_args.parser_error = Some(e);
     };
 
     parsed_args
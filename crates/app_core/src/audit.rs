@@ -0,0 +1,209 @@
+use std::{collections::HashMap, fmt::Display};
+
+use serde_json::{Value, json};
+use sqlx::PgPool;
+
+use database_ops::{
+    DbError,
+    audit::{AuditOrigin, record_action},
+};
+
+use crate::{
+    app_state::AppConfig,
+    arg_parsing::Command,
+    config_fields::{changed_values, is_secret},
+};
+
+
+/// Stands in for credentials in the audit log
+const REDACTED: &str = "<redacted>";
+
+
+/// A command that changes something, as the audit log records it
+#[derive(Debug, Clone)]
+pub struct Audited {
+    /// e.g. `pair.add`, as `dtrade audit --action` filters on
+    pub action: &'static str,
+    pub params: Value,
+}
+
+impl Audited {
+
+    /// Adds the command to the audit log, with the error it failed with
+    pub async fn record(
+        &self,
+        origin: &AuditOrigin,
+        error: Option<&str>,
+        db_pool: PgPool
+    ) -> Result<(), DbError> {
+        record_action(db_pool, origin, self.action, &self.params, error).await
+    }
+}
+
+
+/// What the audit log records of `cmd`, `None` for commands that only read
+pub fn audited(cmd: &Command) -> Option<Audited> {
+
+    let (action, params): (&'static str, Value) = match cmd {
+        Command::AddPair { exchange, ticker } => (
+            "pair.add",
+            json!({ "exchange": exchange, "ticker": ticker })
+        ),
+        Command::DropPair { exchange, ticker } => (
+            "pair.drop",
+            json!({ "exchange": exchange, "ticker": ticker })
+        ),
+        Command::DbIntegrityCheck { exchange, ticker, repair: true } => (
+            "pair.repair",
+            json!({ "exchange": exchange, "ticker": ticker })
+        ),
+        Command::RollbackBatch { batch_id } => (
+            "batch.rollback",
+            json!({ "batch_id": batch_id })
+        ),
+        Command::ImportPair { path } => {
+            ("pair.import", json!({ "path": path }))
+        },
+        Command::UpdatePairs => ("pairs.update", json!({})),
+        Command::RetryFailed => ("pairs.retry_failed", json!({})),
+        Command::AddAlias { name, command } => (
+            "alias.add",
+            json!({ "name": name, "command": command })
+        ),
+        Command::RemoveAlias { name } => {
+            ("alias.remove", json!({ "name": name }))
+        },
+        Command::SetConfig { key, value } => (
+            "config.set",
+            json!({
+                "key": key,
+                "value": match is_secret(key) {
+                    true => REDACTED,
+                    false => value.as_str()
+                }
+            })
+        ),
+        Command::PaperOrder { account, order } => (
+            "paper.order",
+            json!({
+                "account": account,
+                "exchange": order.exchange,
+                "ticker": order.ticker,
+                "side": order.side.to_string(),
+                "volume": order.volume,
+                "limit": order.limit,
+                "stop": order.stop,
+                "at": order.at,
+                "contingent": order.contingent,
+            })
+        ),
+        Command::PaperCancel { account, order_id } => (
+            "paper.cancel",
+            json!({ "account": account, "order_id": order_id })
+        ),
+        Command::PaperModify { account, order_id, price } => (
+            "paper.modify",
+            json!({
+                "account": account,
+                "order_id": order_id,
+                "price": price,
+            })
+        ),
+        Command::PaperSync { account } => {
+            ("paper.sync", json!({ "account": account }))
+        },
+        Command::PaperReset { account, cash } => (
+            "paper.reset",
+            json!({ "account": account, "cash": cash })
+        ),
+        Command::PaperRebalance { 
+            account, 
+            exchange, 
+            targets, 
+            min_drift, 
+            confirmed 
+        } => (
+            "paper.rebalance",
+            json!({
+                "account": account,
+                "exchange": exchange,
+                "targets": targets
+                    .iter()
+                    .map(|(ticker, weight)| (ticker.to_string(), *weight))
+                    .collect::<HashMap<String, f64>>(),
+                "min_drift": min_drift,
+                "confirmed": confirmed,
+            })
+        ),
+        Command::LiveOrder { order } => (
+            "live.order",
+            json!({
+                "exchange": order.exchange,
+                "ticker": order.ticker,
+                "side": order.side.to_string(),
+                "volume": order.volume,
+                "limit": order.limit,
+                "validate": order.validate,
+            })
+        ),
+        Command::LiveCancel { txid } => {
+            ("live.cancel", json!({ "txid": txid }))
+        },
+        Command::LiveModify { txid, price } => (
+            "live.modify",
+            json!({ "txid": txid, "price": price })
+        ),
+        Command::RecurringAdd { schedule } => (
+            "recurring.add",
+            json!({
+                "account": schedule.account,
+                "exchange": schedule.exchange,
+                "ticker": schedule.ticker,
+                "amount": schedule.amount,
+                "every": schedule.every,
+                "start": schedule.start
+            })
+        ),
+        Command::RecurringRemove { id } => {
+            ("recurring.remove", json!({ "id": id }))
+        },
+        Command::RecurringRun => ("recurring.run", json!({})),
+        _ => return None
+    };
+
+    Some(Audited { action, params })
+}
+
+/// Records `cmd`, with the error of `result` if it failed, for callers that
+/// ran it themselves rather than through the engine, e.g. the terminal 
+/// interface. Commands that only read are left out.
+pub async fn record_command<T, E: Display>(
+    cmd: &Command,
+    origin: &AuditOrigin,
+    result: &Result<T, E>,
+    db_pool: PgPool
+) -> Result<(), DbError> {
+
+    let Some(audited) = audited(cmd) else { return Ok(()) };
+
+    let error: Option<String> = result.as_ref().err().map(|e| e.to_string());
+    audited.record(origin, error.as_deref(), db_pool).await
+}
+
+/// Records a config saved as a whole, e.g. by the settings screen, as a
+/// `config.set` of each value that changed
+pub async fn record_config_change(
+    old: &AppConfig,
+    new: &AppConfig,
+    origin: &AuditOrigin,
+    db_pool: PgPool
+) -> Result<(), DbError> {
+
+    for (key, value) in changed_values(old, new) {
+        if let Some(entry) = audited(&Command::SetConfig { key, value }) {
+            entry.record(origin, None, db_pool.clone()).await?;
+        };
+    };
+
+    Ok(())
+}
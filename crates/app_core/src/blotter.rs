@@ -0,0 +1,389 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    sync::Arc
+};
+
+use parquet::{
+    data_type::{ByteArray, ByteArrayType, DoubleType, Int64Type},
+    file::{properties::WriterProperties, writer::SerializedFileWriter},
+    schema::parser::parse_message_type
+};
+use reqwest::Client;
+use serde::{Serialize, Serializer};
+use sqlx::PgPool;
+
+use backtest::OrderSide;
+use bars::{CsvDialect, ExportFormat, checksum::write_checksum};
+use portfolio::CostBasis;
+use string_helpers::Locale;
+use timestamp_tools::{db_timestamp_to_date_string, get_current_unix_timestamp};
+
+use crate::{
+    app_state::LiveTradingSettings,
+    errors::RunTimeError,
+    live::{self, LIVE_EXCHANGE},
+    paper::{OrderStatus, account_names, order_history},
+};
+
+
+const PARQUET_SCHEMA: &str = "
+    message fills {
+        REQUIRED BYTE_ARRAY source (UTF8);
+        REQUIRED BYTE_ARRAY order_id (UTF8);
+        REQUIRED INT64 time;
+        REQUIRED BYTE_ARRAY exchange (UTF8);
+        REQUIRED BYTE_ARRAY pair (UTF8);
+        REQUIRED BYTE_ARRAY side (UTF8);
+        REQUIRED DOUBLE volume;
+        REQUIRED DOUBLE price;
+        REQUIRED DOUBLE fee;
+        REQUIRED DOUBLE realized_pnl;
+    }
+";
+
+const CSV_HEADER: [&str; 10] = [
+    "source",
+    "order_id",
+    "time",
+    "exchange",
+    "pair",
+    "side",
+    "volume",
+    "price",
+    "fee",
+    "realized_pnl",
+];
+
+
+/// A fill of a paper or live order, one row of the blotter
+#[derive(Debug, Clone, Serialize)]
+pub struct BlotterFill {
+    /// `paper:ACCOUNT` or `live`
+    pub source: String,
+    pub order_id: String,
+    /// Microseconds since the epoch
+    pub time: u64,
+    pub exchange: String,
+    pub pair: String,
+    #[serde(serialize_with = "side_name")]
+    pub side: OrderSide,
+    pub volume: f64,
+    pub price: f64,
+    /// In the quote currency
+    pub fee: f64,
+    /// What a sell made over the average cost of what it sold, fees in.
+    /// Zero for buys, whose fees go into the cost instead.
+    pub realized_pnl: f64,
+}
+
+fn side_name<S: Serializer>(side: &OrderSide, s: S)
+    -> Result<S::Ok, S::Error> {
+    s.collect_str(side)
+}
+
+
+/// Every fill of every paper account, and of the live account once live
+/// trading is on, oldest first, with the P&L each realized
+///
+/// The P&L is worked out over the whole history of each pair of each
+/// source, see `CostBasis`, so that a window of it, from `from` up to
+/// `to` in unix seconds, still sells against what was bought before it.
+pub async fn collect_fills(
+    from: Option<u64>,
+    to: Option<u64>,
+    live_settings: &LiveTradingSettings,
+    client: &Client,
+    db_pool: &PgPool
+) -> Result<Vec<BlotterFill>, RunTimeError> {
+
+    let mut fills: Vec<BlotterFill> = Vec::new();
+
+    for account in account_names(db_pool)
+        .await
+        .map_err(RunTimeError::Paper)?
+    {
+        let orders = order_history(&account, db_pool)
+            .await
+            .map_err(RunTimeError::Paper)?;
+
+        fills.extend(orders
+            .into_iter()
+            .filter(|o| o.status == OrderStatus::Filled)
+            .filter_map(|o| Some(BlotterFill {
+                source: format!("paper:{}", account),
+                order_id: o.id.to_string(),
+                time: o.fill_time?,
+                exchange: o.exchange,
+                pair: o.ticker,
+                side: o.side,
+                volume: o.volume,
+                price: o.fill_price?,
+                fee: o.fee.unwrap_or(0.0),
+                realized_pnl: 0.0,
+            }))
+        );
+    };
+
+    if live_settings.enabled {
+        let trades = live::trade_history(live_settings, client)
+            .await
+            .map_err(RunTimeError::Live)?;
+
+        fills.extend(trades.into_iter().map(|t| BlotterFill {
+            source: "live".to_string(),
+            order_id: t.order_txid,
+            time: t.time,
+            exchange: LIVE_EXCHANGE.to_string(),
+            pair: t.pair,
+            side: match t.side.as_str() {
+                "sell" => OrderSide::Sell,
+                _ => OrderSide::Buy
+            },
+            volume: t.volume,
+            price: t.price,
+            fee: t.fee,
+            realized_pnl: 0.0,
+        }));
+    };
+
+    realize(&mut fills);
+
+    let from: u64 = from.map(|f| f.saturating_mul(1_000_000)).unwrap_or(0);
+    let to: u64 = to.map(|t| t.saturating_mul(1_000_000)).unwrap_or(u64::MAX);
+    fills.retain(|f| f.time >= from && f.time < to);
+
+    Ok(fills)
+}
+
+/// Sorts `fills` oldest first and sets the P&L each one realized, by the
+/// average cost of its pair at its source
+pub fn realize(fills: &mut [BlotterFill]) {
+
+    fills.sort_by(|a, b| a.time
+        .cmp(&b.time)
+        .then_with(|| a.source.cmp(&b.source))
+        .then_with(|| a.order_id.cmp(&b.order_id))
+    );
+
+    let mut positions: HashMap<(String, String, String), CostBasis> =
+        HashMap::new();
+
+    for fill in fills.iter_mut() {
+        fill.realized_pnl = positions
+            .entry((
+                fill.source.clone(),
+                fill.exchange.clone(),
+                fill.pair.clone()
+            ))
+            .or_default()
+            .fill(fill.side, fill.volume, fill.price, fill.fee);
+    };
+}
+
+/// `fills_DATE.EXT`, dated today, where fills are written without a path
+pub fn default_file_name(format: ExportFormat) -> String {
+
+    let now: String = db_timestamp_to_date_string(
+        get_current_unix_timestamp() * 1_000_000
+    );
+
+    format!(
+        "fills_{}.{}",
+        now.split(' ').next().unwrap_or_default(),
+        format.extension()
+    )
+}
+
+/// Writes `fills` to `path` as CSV or Parquet, with a `.sha256` checksum
+/// file alongside
+///
+/// CSV follows the delimiter, quoting and header of `dialect`, with times
+/// in UTC and plain numbers so spreadsheets and tax tools read them as
+/// they are. Parquet keeps the times in microseconds.
+pub fn write_fills(
+    path: &Path,
+    format: ExportFormat,
+    fills: &[BlotterFill],
+    locale: &Locale,
+    dialect: &CsvDialect
+) -> Result<(), RunTimeError> {
+
+    match format {
+        ExportFormat::Csv => write_csv(path, fills, locale, dialect),
+        ExportFormat::Parquet => write_parquet(path, fills),
+        ExportFormat::Json | ExportFormat::Jsonl => Err(export_error(
+            format!("Fills are exported as csv or parquet, not {}", format)
+        ))
+    }?;
+
+    write_checksum(path).map_err(RunTimeError::Bar)?;
+
+    Ok(())
+}
+
+fn export_error<E: std::fmt::Display>(e: E) -> RunTimeError {
+    RunTimeError::Export(e.to_string())
+}
+
+fn write_csv(
+    path: &Path,
+    fills: &[BlotterFill],
+    locale: &Locale,
+    dialect: &CsvDialect
+) -> Result<(), RunTimeError> {
+
+    let delimiter: char = dialect.delimiter(locale);
+    let separator: String = delimiter.to_string();
+    let line = |fields: Vec<String>| fields
+        .iter()
+        .map(|f| dialect.quote(f, delimiter))
+        .collect::<Vec<String>>()
+        .join(&separator);
+
+    let mut writer = BufWriter::new(
+        File::create(path).map_err(export_error)?
+    );
+
+    if dialect.header {
+        let header: Vec<String> = CSV_HEADER
+            .iter()
+            .map(|h| h.to_string())
+            .collect();
+        writeln!(writer, "{}", line(header)).map_err(export_error)?;
+    };
+
+    for fill in fills {
+        let fields: Vec<String> = vec![
+            fill.source.clone(),
+            fill.order_id.clone(),
+            db_timestamp_to_date_string(fill.time),
+            fill.exchange.clone(),
+            fill.pair.clone(),
+            fill.side.to_string(),
+            fill.volume.to_string(),
+            fill.price.to_string(),
+            fill.fee.to_string(),
+            fill.realized_pnl.to_string(),
+        ];
+        writeln!(writer, "{}", line(fields)).map_err(export_error)?;
+    };
+
+    writer.flush().map_err(export_error)
+}
+
+fn write_parquet(path: &Path, fills: &[BlotterFill])
+    -> Result<(), RunTimeError> {
+
+    let schema = Arc::new(
+        parse_message_type(PARQUET_SCHEMA).map_err(export_error)?
+    );
+    let props = Arc::new(WriterProperties::builder().build());
+    let file = File::create(path).map_err(export_error)?;
+
+    let mut writer = SerializedFileWriter::new(file, schema, props)
+        .map_err(export_error)?;
+    let mut row_group = writer.next_row_group().map_err(export_error)?;
+
+    let text = |field: fn(&BlotterFill) -> String| fills
+        .iter()
+        .map(|f| ByteArray::from(field(f).as_str()))
+        .collect::<Vec<ByteArray>>();
+    let number = |field: fn(&BlotterFill) -> f64| fills
+        .iter()
+        .map(field)
+        .collect::<Vec<f64>>();
+
+    // In the order of `PARQUET_SCHEMA`
+    enum Column {
+        Text(Vec<ByteArray>),
+        Time(Vec<i64>),
+        Number(Vec<f64>),
+    }
+    let columns: [Column; 10] = [
+        Column::Text(text(|f| f.source.clone())),
+        Column::Text(text(|f| f.order_id.clone())),
+        Column::Time(fills.iter().map(|f| f.time as i64).collect()),
+        Column::Text(text(|f| f.exchange.clone())),
+        Column::Text(text(|f| f.pair.clone())),
+        Column::Text(text(|f| f.side.to_string())),
+        Column::Number(number(|f| f.volume)),
+        Column::Number(number(|f| f.price)),
+        Column::Number(number(|f| f.fee)),
+        Column::Number(number(|f| f.realized_pnl)),
+    ];
+
+    for values in &columns {
+
+        let Some(mut column) = row_group.next_column().map_err(export_error)?
+        else {
+            break
+        };
+
+        match values {
+            Column::Text(v) => column
+                .typed::<ByteArrayType>()
+                .write_batch(v, None, None),
+            Column::Time(v) => column
+                .typed::<Int64Type>()
+                .write_batch(v, None, None),
+            Column::Number(v) => column
+                .typed::<DoubleType>()
+                .write_batch(v, None, None),
+        }
+            .map_err(export_error)?;
+
+        column.close().map_err(export_error)?;
+    };
+
+    row_group.close().map_err(export_error)?;
+    writer.close().map_err(export_error)?;
+
+    Ok(())
+}
+
+
+// -------------------------- UNIT TESTING --------------------------------- //
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn fill(source: &str, time: u64, side: OrderSide, price: f64)
+        -> BlotterFill {
+        BlotterFill {
+            source: source.to_string(),
+            order_id: time.to_string(),
+            time,
+            exchange: "kraken".to_string(),
+            pair: "XBTUSD".to_string(),
+            side,
+            volume: 1.0,
+            price,
+            fee: 1.0,
+            realized_pnl: 0.0,
+        }
+    }
+
+    #[test]
+    fn realize_test() {
+
+        let mut fills: Vec<BlotterFill> = vec![
+            fill("paper:default", 3, OrderSide::Sell, 150.0),
+            fill("live", 2, OrderSide::Buy, 200.0),
+            fill("paper:default", 1, OrderSide::Buy, 100.0),
+        ];
+        realize(&mut fills);
+
+        let times: Vec<u64> = fills.iter().map(|f| f.time).collect();
+        assert_eq!(times, vec![1, 2, 3]);
+
+        // The live buy is its own position, so the paper sell realizes
+        // 149 over the 101 its buy cost
+        assert_eq!(fills[0].realized_pnl, 0.0);
+        assert_eq!(fills[1].realized_pnl, 0.0);
+        assert!((fills[2].realized_pnl - 48.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use sqlx::{PgPool, types::BigDecimal};
+use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
+
+use database_ops::{
+    DataDownloadStatus,
+    fetch_rows_page,
+    lineage::{IngestBatch, batches_after, latest_batch_id},
+};
+
+#[cfg(feature = "kafka")]
+use std::time::Duration;
+#[cfg(feature = "kafka")]
+use rdkafka::{
+    ClientConfig,
+    producer::{FutureProducer, FutureRecord},
+    util::Timeout,
+};
+
+use crate::app_state::{BusSinkSettings, IntegrationSettings};
+#[cfg(any(feature = "kafka", feature = "nats"))]
+use crate::app_state::MessageBus;
+
+
+/// How long Kafka has to acknowledge a message before it's reported lost
+#[cfg(feature = "kafka")]
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+
+/// One committed download batch and its ticks, published to `PREFIX.ticks`
+#[derive(Serialize)]
+struct TickBatchEvent<'a> {
+    batch_id: i64,
+    job_id: i64,
+    exchange: &'a str,
+    ticker: &'a str,
+    ticks: Vec<TickRecord<'a>>,
+}
+
+#[derive(Serialize)]
+struct TickRecord<'a> {
+    id: u64,
+    /// Microseconds since the epoch, as stored
+    time: u64,
+    price: &'a BigDecimal,
+    volume: &'a BigDecimal,
+}
+
+/// A connected bus. Without the `kafka` and `nats` features there are none.
+enum Connection {
+    #[cfg(feature = "kafka")]
+    Kafka(FutureProducer),
+    #[cfg(feature = "nats")]
+    Nats(async_nats::Client),
+}
+
+async fn connect(settings: &BusSinkSettings) -> Result<Connection, String> {
+    match settings.bus {
+        #[cfg(feature = "kafka")]
+        MessageBus::Kafka => ClientConfig::new()
+            .set("bootstrap.servers", &settings.url)
+            .create()
+            .map(Connection::Kafka)
+            .map_err(|e| e.to_string()),
+        #[cfg(feature = "nats")]
+        MessageBus::Nats => async_nats::connect(settings.url.as_str())
+            .await
+            .map(Connection::Nats)
+            .map_err(|e| e.to_string()),
+        #[allow(unreachable_patterns)]
+        bus => Err(format!("dtrade was built without the {} feature", bus))
+    }
+}
+
+struct Sink {
+    connection: Connection,
+    prefix: String,
+}
+
+impl Sink {
+
+    /// Publishes to `PREFIX.KIND`, keyed by the pair on Kafka and with the
+    /// pair appended to the subject on NATS
+    #[cfg_attr(
+        not(any(feature = "kafka", feature = "nats")),
+        allow(unused_variables)
+    )]
+    async fn publish(
+        &self,
+        kind: &str,
+        exchange: &str,
+        ticker: &str,
+        payload: String
+    ) -> Result<(), String> {
+
+        let topic: String = format!("{}.{}", self.prefix, kind);
+
+        match self.connection {
+            #[cfg(feature = "kafka")]
+            Connection::Kafka(ref producer) => {
+                let key: String = format!("{}.{}", exchange, ticker);
+                producer
+                    .send(
+                        FutureRecord::to(&topic).key(&key).payload(&payload),
+                        Timeout::After(SEND_TIMEOUT)
+                    )
+                    .await
+                    .map(|_| ())
+                    .map_err(|(e, _)| e.to_string())
+            },
+            #[cfg(feature = "nats")]
+            Connection::Nats(ref client) => client
+                .publish(
+                    format!("{}.{}.{}", topic, exchange, ticker),
+                    payload.into()
+                )
+                .await
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Reads `batch`'s ticks back from the database and publishes them as
+    /// one message
+    async fn publish_batch(
+        &self,
+        batch_id: i64,
+        batch: &IngestBatch,
+        db_pool: PgPool
+    ) -> Result<(), String> {
+
+        let ticks = fetch_rows_page(
+            &batch.exchange,
+            &batch.asset,
+            batch.first_tick_id.saturating_sub(1),
+            (None, None),
+            batch.last_tick_id.saturating_sub(batch.first_tick_id) + 1,
+            db_pool
+        )
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let payload: String = serde_json::to_string(&TickBatchEvent {
+            batch_id,
+            job_id: batch.job_id,
+            exchange: &batch.exchange,
+            ticker: &batch.asset,
+            ticks: ticks
+                .iter()
+                .filter(|(id, ..)| *id <= batch.last_tick_id)
+                .map(|(id, time, price, volume)| TickRecord {
+                    id: *id,
+                    time: *time,
+                    price,
+                    volume,
+                })
+                .collect(),
+        }).map_err(|e| e.to_string())?;
+
+        self.publish("ticks", &batch.exchange, &batch.asset, payload).await
+    }
+}
+
+
+/// Starts mirroring downloads to the message buses in `settings`, and
+/// returns where to send their status events. `None` with no bus
+/// configured.
+///
+/// Every event is published to `PREFIX.jobs` on its exchange's bus. After
+/// each progress event, the batches committed since the last one are read
+/// back through `_ingest_batches` and published to `PREFIX.ticks`, so
+/// batches committed before the sink started aren't. A bus that can't be
+/// reached is reported and left out.
+pub async fn spawn_bus_sinks(settings: &IntegrationSettings, db_pool: PgPool)
+    -> Option<UnboundedSender<DataDownloadStatus>>
+{
+    if settings.message_bus.is_empty() {
+        return None
+    };
+
+    let mut last_batch: i64 = match latest_batch_id(db_pool.clone()).await {
+        Ok(id) => id,
+        Err(e) => {
+            eprintln!(
+                "\x1b[1;31mMessage bus sinks not started: {}\x1b[0m", 
+                e
+            );
+            return None
+        }
+    };
+
+    let configured = settings.message_bus.clone();
+    let (tx, mut rx) = unbounded_channel::<DataDownloadStatus>();
+
+    tokio::spawn(async move {
+
+        // Keyed by lowercase exchange, as batches record it
+        let mut sinks: HashMap<String, Sink> = HashMap::new();
+        for (exchange, bus) in configured {
+            match connect(&bus).await {
+                Ok(connection) => {
+                    sinks.insert(exchange.to_lowercase(), Sink {
+                        connection,
+                        prefix: bus.prefix
+                    });
+                },
+                Err(e) => eprintln!(
+                    "\x1b[1;31mNo {} sink for {}: {}\x1b[0m",
+                    bus.bus,
+                    exchange,
+                    e
+                )
+            };
+        };
+
+        while let Some(event) = rx.recv().await {
+
+            let (exchange, ticker) = event.exchange_and_ticker();
+            let exchange: String = exchange.to_lowercase();
+
+            if let Some(sink) = sinks.get(&exchange) {
+                let sent = match serde_json::to_string(&event) {
+                    Ok(payload) => {
+                        sink.publish("jobs", &exchange, ticker, payload).await
+                    },
+                    Err(e) => Err(e.to_string())
+                };
+                if let Err(e) = sent {
+                    eprintln!("\x1b[1;31mMessage bus: {}\x1b[0m", e);
+                };
+            };
+
+            // Sent once a batch has been committed
+            if !matches!(event, DataDownloadStatus::Progress { .. }) {
+                continue
+            };
+
+            let batches = match batches_after(last_batch, db_pool.clone())
+                .await
+            {
+                Ok(b) => b,
+                Err(e) => {
+                    eprintln!("\x1b[1;31mMessage bus: {}\x1b[0m", e);
+                    continue
+                }
+            };
+
+            for (id, batch) in batches {
+                last_batch = id;
+                let Some(sink) = sinks.get(&batch.exchange.to_lowercase())
+                else {
+                    continue
+                };
+                if let Err(e) = sink
+                    .publish_batch(id, &batch, db_pool.clone())
+                    .await
+                {
+                    eprintln!("\x1b[1;31mMessage bus: {}\x1b[0m", e);
+                };
+            };
+        };
+    });
+
+    Some(tx)
+}
@@ -0,0 +1,300 @@
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use bars::checksum::{CHECKSUM_EXTENSION, checksum_path};
+use database_ops::{fetch_exchanges_and_pairs_from_db, fetch_table_coverage};
+use timestamp_tools::{
+    db_timestamp_to_date_string,
+    get_current_unix_timestamp
+};
+
+use crate::{app_state::SystemPaths, errors::CatalogError};
+
+
+// ------------------------------ DATA CATALOG ----------------------------- //
+/// A file saved from a dataset, e.g. a candle or tick export
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ExportArtifact {
+    pub file_name: String,
+    pub size_bytes: u64,
+    /// Unix timestamp (seconds) of the last write
+    pub modified: u64,
+    /// Hash recorded in the file's `.sha256` checksum file, if any
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+/// Everything known about one stored pair
+///
+/// Times are unix timestamps in seconds. `last_update` is when the row count
+/// or coverage last changed, as seen by catalog regeneration.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DatasetEntry {
+    pub exchange: String,
+    pub ticker: String,
+    pub source: String,
+    pub first_tick: Option<u64>,
+    pub last_tick: Option<u64>,
+    pub row_count: u64,
+    pub last_update: u64,
+    pub exports: Vec<ExportArtifact>,
+}
+
+/// Machine-readable manifest of every stored dataset, kept in
+/// `SystemPaths::catalog`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DataCatalog {
+    pub generated_at: u64,
+    pub datasets: Vec<DatasetEntry>,
+}
+
+impl DataCatalog {
+
+    /// Reads the catalog file. Returns `Ok(None)` if it hasn't been written
+    /// yet.
+    pub fn load(path: &Path) -> Result<Option<Self>, CatalogError> {
+
+        if !path.exists() {
+            return Ok(None)
+        };
+
+        let text: String = fs::read_to_string(path)
+            .map_err(|e| CatalogError::ReadFailed(e.to_string()))?;
+
+        serde_json::from_str(&text)
+            .map(Some)
+            .map_err(|e| CatalogError::ParseFailure(e.to_string()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), CatalogError> {
+
+        let json: String = serde_json::to_string_pretty(self)
+            .map_err(|e| CatalogError::SaveFailed(e.to_string()))?;
+
+        fs::write(path, json)
+            .map_err(|e| CatalogError::SaveFailed(e.to_string()))
+    }
+
+    /// Builds a fresh catalog from the database and the export directory
+    ///
+    /// `previous` carries `last_update` forward for datasets that haven't
+    /// changed since it was generated.
+    pub async fn generate(
+        paths: &SystemPaths,
+        previous: Option<&DataCatalog>,
+        db_pool: PgPool
+    ) -> Result<Self, CatalogError> {
+
+        let now: u64 = get_current_unix_timestamp();
+
+        let mut pairs: Vec<(String, String)> =
+            fetch_exchanges_and_pairs_from_db(db_pool.clone(), false)
+                .await
+                .into_iter()
+                .flat_map(|(exchange, tickers)| {
+                    let exchange: String = exchange.to_lowercase();
+                    tickers
+                        .into_iter()
+                        .map(move |(t, _)| (exchange.clone(), t))
+                })
+                .collect();
+        pairs.sort();
+
+        let mut datasets: Vec<DatasetEntry> = Vec::with_capacity(pairs.len());
+
+        for (exchange, ticker) in pairs {
+
+            let (row_count, first, last) = fetch_table_coverage(
+                &exchange,
+                &ticker,
+                db_pool.clone()
+            )
+                .await
+                .map_err(CatalogError::Db)?;
+
+            let first_tick: Option<u64> = first.map(|t| t / 1_000_000);
+            let last_tick: Option<u64> = last.map(|t| t / 1_000_000);
+
+            let last_update: u64 = previous
+                .and_then(|c| c.get(&exchange, &ticker))
+                .filter(|d| {
+                    d.row_count == row_count
+                    && d.first_tick == first_tick
+                    && d.last_tick == last_tick
+                })
+                .map_or(now, |d| d.last_update);
+
+            datasets.push(DatasetEntry {
+                source: data_source(&exchange).to_string(),
+                exports: find_exports(&paths.candle_data, &exchange, &ticker),
+                exchange,
+                ticker,
+                first_tick,
+                last_tick,
+                row_count,
+                last_update,
+            });
+        };
+
+        Ok(DataCatalog { generated_at: now, datasets })
+    }
+
+    pub fn get(&self, exchange: &str, ticker: &str) -> Option<&DatasetEntry> {
+        self.datasets.iter().find(|d| {
+            d.exchange.eq_ignore_ascii_case(exchange)
+            && d.ticker.eq_ignore_ascii_case(ticker)
+        })
+    }
+
+    /// Keeps only the datasets matching the exchange and ticker, when given
+    pub fn filtered(&self, exchange: Option<&str>, ticker: Option<&str>)
+        -> Self
+    {
+        let matches = |value: &str, filter: Option<&str>| {
+            filter.is_none_or(|f| value.eq_ignore_ascii_case(f))
+        };
+
+        DataCatalog {
+            generated_at: self.generated_at,
+            datasets: self.datasets
+                .iter()
+                .filter(|d| {
+                    matches(&d.exchange, exchange)
+                    && matches(&d.ticker, ticker)
+                })
+                .cloned()
+                .collect()
+        }
+    }
+}
+
+impl std::fmt::Display for DataCatalog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+
+        fn date(timestamp: Option<u64>) -> String {
+            match timestamp {
+                Some(t) => db_timestamp_to_date_string(t * 1_000_000),
+                None => "-".to_string()
+            }
+        }
+
+        writeln!(f,
+            "\x1b[1;36mData Catalog\x1b[0m (generated {})",
+            date(Some(self.generated_at))
+        )?;
+
+        if self.datasets.is_empty() {
+            writeln!(f, "  No datasets")?;
+        };
+
+        for d in &self.datasets {
+            writeln!(f,
+                "\x1b[1;33m{} {}\x1b[0m",
+                d.exchange,
+                d.ticker
+            )?;
+            writeln!(f, "  \x1b[33msource     \x1b[0m: {}", d.source)?;
+            writeln!(f,
+                "  \x1b[33mcoverage   \x1b[0m: {} -> {}",
+                date(d.first_tick),
+                date(d.last_tick)
+            )?;
+            writeln!(f, "  \x1b[33mrows       \x1b[0m: {}", d.row_count)?;
+            writeln!(f,
+                "  \x1b[33mlast_update\x1b[0m: {}",
+                date(Some(d.last_update))
+            )?;
+            writeln!(f, "  \x1b[33mexports    \x1b[0m: {}", d.exports.len())?;
+            for export in &d.exports {
+                writeln!(f,
+                    "    {} ({} bytes)",
+                    export.file_name,
+                    export.size_bytes
+                )?;
+            };
+        };
+
+        Ok(())
+    }
+}
+
+
+/// Regenerates the catalog file from the current database state
+///
+/// Meant to be called after any job that changes stored data or writes
+/// exports.
+pub async fn refresh_catalog(db_pool: PgPool)
+    -> Result<DataCatalog, CatalogError>
+{
+    let paths: SystemPaths = SystemPaths::new()
+        .map_err(|e| CatalogError::ReadFailed(e.to_string()))?;
+
+    let previous: Option<DataCatalog> = DataCatalog::load(&paths.catalog)
+        .unwrap_or_default();
+
+    let catalog = DataCatalog::generate(
+        &paths,
+        previous.as_ref(),
+        db_pool
+    ).await?;
+    catalog.save(&paths.catalog)?;
+
+    Ok(catalog)
+}
+
+
+// --------------------------- HELPER FUNCTIONS ---------------------------- //
+fn data_source(exchange: &str) -> &'static str {
+    match exchange {
+        "kraken" => "https://api.kraken.com/0/public/Trades",
+        _ => "unknown"
+    }
+}
+
+/// Lists the export files for a pair. Exports are named
+/// `exchange_ticker_...`, see `BarSeries::get_file_name`.
+fn find_exports(dir: &Path, exchange: &str, ticker: &str)
+    -> Vec<ExportArtifact>
+{
+    let prefix: String = format!("{}_{}_", exchange, ticker).to_lowercase();
+
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new()
+    };
+
+    let mut exports: Vec<ExportArtifact> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let file_name: String = entry.file_name()
+                .to_string_lossy()
+                .to_string();
+            let path = entry.path();
+            if !file_name.to_lowercase().starts_with(&prefix)
+                || path.extension().is_some_and(|e| e == CHECKSUM_EXTENSION)
+            {
+                return None
+            };
+            let meta = entry.metadata().ok()?;
+            let modified: u64 = meta.modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_secs());
+            let sha256: Option<String> = fs::read_to_string(
+                checksum_path(&path)
+            )
+                .ok()
+                .and_then(|s| s.split_whitespace().next().map(String::from));
+            Some(ExportArtifact {
+                file_name,
+                size_bytes: meta.len(),
+                modified,
+                sha256
+            })
+        })
+        .collect();
+
+    exports.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    exports
+}
@@ -0,0 +1,304 @@
+use std::{
+    fmt::{
+        Display,
+        Formatter,
+        self,
+    },
+    net::SocketAddr,
+};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use bars::{CsvQuoting, export::timestamp_format_is_valid};
+use string_helpers::DateStyle;
+use timestamp_tools::{WeekStart, period_is_valid};
+
+use crate::{
+    app_state::{AppConfig, StartScreen, parse_api_keys, parse_watchlist},
+    arg_parsing::ParserError,
+};
+
+
+/// What a config value has to look like, checked by the settings screen and
+/// `config set`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    Bool,
+    Integer,
+    Float,
+    Text,
+    TimeFrame,
+    DateStyle,
+    CsvQuoting,
+    Delimiter,
+    TimestampFormat,
+    WeekStart,
+    Address,
+    OptionalAddress,
+    ApiKeys,
+    StartScreen,
+    Tickers,
+    Url,
+}
+
+impl Display for FieldKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldKind::Bool => write!(f, "Bool"),
+            FieldKind::Integer => write!(f, "Integer"),
+            FieldKind::Float => write!(f, "Float"),
+            FieldKind::Text => write!(f, "Text"),
+            FieldKind::TimeFrame => write!(f, "TimeFrame"),
+            FieldKind::DateStyle => write!(f, "DateStyle"),
+            FieldKind::CsvQuoting => write!(f, "CsvQuoting"),
+            FieldKind::Delimiter => write!(f, "Delimiter"),
+            FieldKind::TimestampFormat => write!(f, "TimestampFormat"),
+            FieldKind::WeekStart => write!(f, "WeekStart"),
+            FieldKind::Address => write!(f, "Address"),
+            FieldKind::OptionalAddress => write!(f, "OptionalAddress"),
+            FieldKind::ApiKeys => write!(f, "ApiKeys"),
+            FieldKind::StartScreen => write!(f, "StartScreen"),
+            FieldKind::Tickers => write!(f, "Tickers"),
+            FieldKind::Url => write!(f, "Url"),
+        }
+    }
+}
+
+impl FieldKind {
+
+    /// Whether `value`, as typed in, is one of the kind
+    pub fn accepts(&self, value: &str) -> bool {
+        match self {
+            FieldKind::Bool => value.parse::<bool>().is_ok(),
+            FieldKind::Integer => value.parse::<u64>().is_ok(),
+            FieldKind::Float => value.parse::<f64>().is_ok(),
+            FieldKind::Text => true,
+            FieldKind::TimeFrame => period_is_valid(value),
+            FieldKind::DateStyle => value.parse::<DateStyle>().is_ok(),
+            FieldKind::CsvQuoting => value.parse::<CsvQuoting>().is_ok(),
+            // Blank falls back to the defaults
+            FieldKind::Delimiter => value.chars().count() <= 1,
+            FieldKind::TimestampFormat => {
+                value.is_empty() || timestamp_format_is_valid(value)
+            },
+            FieldKind::WeekStart => value.parse::<WeekStart>().is_ok(),
+            FieldKind::Address => value.parse::<SocketAddr>().is_ok(),
+            // Blank for none
+            FieldKind::OptionalAddress => {
+                value.is_empty() || value.parse::<SocketAddr>().is_ok()
+            },
+            FieldKind::ApiKeys => parse_api_keys(value).is_ok(),
+            FieldKind::StartScreen => value.parse::<StartScreen>().is_ok(),
+            FieldKind::Tickers => parse_watchlist(value).is_ok(),
+            // Blank for none
+            FieldKind::Url => {
+                value.is_empty()
+                    || value.starts_with("https://")
+                    || value.starts_with("http://")
+            },
+        }
+    }
+
+    /// `value` as it's stored in config.json, for the kinds typed in
+    /// differently
+    fn stored_value(&self, value: &str) -> Option<Value> {
+
+        fn stored<T: Serialize, E>(parsed: Result<T, E>) -> Option<Value> {
+            parsed.ok().and_then(|v| serde_json::to_value(v).ok())
+        }
+
+        match self {
+            FieldKind::DateStyle => stored(value.parse::<DateStyle>()),
+            FieldKind::CsvQuoting => stored(value.parse::<CsvQuoting>()),
+            FieldKind::WeekStart => stored(value.parse::<WeekStart>()),
+            FieldKind::StartScreen => stored(value.parse::<StartScreen>()),
+            FieldKind::ApiKeys => stored(parse_api_keys(value)),
+            FieldKind::Tickers => stored(parse_watchlist(value)),
+            FieldKind::Delimiter
+            | FieldKind::TimestampFormat
+            | FieldKind::OptionalAddress
+            | FieldKind::Url if value.is_empty() => Some(Value::Null),
+            _ => None
+        }
+    }
+}
+
+
+/// The kind of the value at the dotted `key`, for values with more rules
+/// than their type's. Switches and numbers are checked by type alone.
+pub fn field_kind(key: &str) -> Option<FieldKind> {
+    match key {
+        "data_download.cache_size" => Some(FieldKind::TimeFrame),
+        "bars.week_start" => Some(FieldKind::WeekStart),
+        "locale.date_style" => Some(FieldKind::DateStyle),
+        "csv_dialect.delimiter" => Some(FieldKind::Delimiter),
+        "csv_dialect.quoting" => Some(FieldKind::CsvQuoting),
+        "csv_dialect.timestamp_format" => Some(FieldKind::TimestampFormat),
+        "http.address" => Some(FieldKind::Address),
+        "http.api_keys" => Some(FieldKind::ApiKeys),
+        "http.flight_address" => Some(FieldKind::OptionalAddress),
+        "tui.start_screen" => Some(FieldKind::StartScreen),
+        "tui.watchlist" => Some(FieldKind::Tickers),
+        "telemetry.endpoint" => Some(FieldKind::Url),
+        _ => None
+    }
+}
+
+/// Values that hold credentials, left out wherever changes are recorded
+const SECRET_KEYS: [&str; 5] = [
+    "http.api_keys",
+    "mqtt.password",
+    "redis.url",
+    "live.kraken_api_key",
+    "live.kraken_api_secret",
+];
+
+/// Whether the value at the dotted `key` holds a credential, or a section
+/// holding one
+pub fn is_secret(key: &str) -> bool {
+    SECRET_KEYS.iter().any(|secret| {
+        *secret == key || secret.starts_with(&format!("{}.", key))
+    })
+}
+
+/// The dotted keys whose values differ between `old` and `new`, with the
+/// new value as it would be typed for `config set`
+pub fn changed_values(old: &AppConfig, new: &AppConfig)
+    -> Vec<(String, String)> {
+
+    fn compare(
+        prefix: &str,
+        old: &Value,
+        new: &Value,
+        changes: &mut Vec<(String, String)>
+    ) {
+        match (old, new) {
+            (Value::Object(a), Value::Object(b)) => {
+                for (name, value) in b {
+                    let key: String = match prefix.is_empty() {
+                        true => name.clone(),
+                        false => format!("{}.{}", prefix, name)
+                    };
+                    compare(
+                        &key,
+                        a.get(name).unwrap_or(&Value::Null),
+                        value,
+                        changes
+                    );
+                };
+            },
+            _ if old == new => {},
+            _ => changes.push((prefix.to_string(), match new {
+                Value::Null => String::new(),
+                Value::String(s) => s.clone(),
+                _ => new.to_string()
+            }))
+        };
+    }
+
+    let mut changes: Vec<(String, String)> = Vec::new();
+    compare(
+        "",
+        &serde_json::to_value(old).unwrap_or_default(),
+        &serde_json::to_value(new).unwrap_or_default(),
+        &mut changes
+    );
+    changes
+}
+
+fn lookup<'a>(root: &'a Value, key: &str) -> Option<&'a Value> {
+    key.split('.').try_fold(root, |value, part| value.get(part))
+}
+
+fn lookup_mut<'a>(root: &'a mut Value, key: &str) -> Option<&'a mut Value> {
+    key.split('.').try_fold(root, |value, part| value.get_mut(part))
+}
+
+fn unknown_key(key: &str) -> ParserError {
+    ParserError::UnknownArg(format!("No config key {}", key))
+}
+
+/// What `value` could be stored as in place of `current`, most likely first
+fn candidates(current: &Value, value: &str) -> Vec<Value> {
+
+    let text: Value = Value::String(value.to_string());
+
+    match current {
+        // Optional text is cleared with a blank
+        Value::String(_) if value.is_empty() => vec![Value::Null, text],
+        Value::String(_) => vec![text],
+        Value::Null if value.is_empty() => vec![Value::Null],
+        Value::Null => match serde_json::from_str::<Value>(value) {
+            Ok(parsed) => vec![parsed, text],
+            Err(_) => vec![text]
+        },
+        // Switches, numbers, lists and sections are typed as JSON
+        _ => vec![serde_json::from_str::<Value>(value).unwrap_or(text)]
+    }
+}
+
+
+/// The value at the dotted `key` of config.json, e.g.
+/// `chart_parameters.num_bars`, or the whole config without one
+pub fn get_config_value(config: &AppConfig, key: Option<&str>)
+    -> Result<Value, ParserError> {
+
+    let root: Value = serde_json::to_value(config).unwrap_or_default();
+
+    match key {
+        Some(key) => lookup(&root, key)
+            .cloned()
+            .ok_or_else(|| unknown_key(key)),
+        None => Ok(root)
+    }
+}
+
+/// `config` with the value at the dotted `key` set to `value`, as typed on
+/// the command line
+///
+/// The value has to pass its key's `field_kind` and fit the type already
+/// there: true or false for switches, a number for numbers and JSON for
+/// lists and sections. A blank clears optional values.
+pub fn set_config_value(config: &AppConfig, key: &str, value: &str)
+    -> Result<AppConfig, ParserError> {
+
+    let mut root: Value = serde_json::to_value(config).unwrap_or_default();
+
+    let current: &Value = lookup(&root, key).ok_or_else(|| unknown_key(key))?;
+
+    let kind: Option<FieldKind> = field_kind(key);
+    if let Some(kind) = kind
+        && !kind.accepts(value)
+    {
+        return Err(ParserError::UnknownArg(format!(
+            "Invalid value for {}: {:?} isn't a valid {}",
+            key,
+            value,
+            kind
+        )))
+    };
+
+    let attempts: Vec<Value> = match kind.and_then(|k| k.stored_value(value)) {
+        Some(stored) => vec![stored],
+        None => candidates(current, value)
+    };
+
+    let mut error: String = String::new();
+
+    for candidate in attempts {
+        if let Some(target) = lookup_mut(&mut root, key) {
+            *target = candidate;
+        };
+        match serde_json::from_value::<AppConfig>(root.clone()) {
+            Ok(config) => return Ok(config),
+            Err(e) => error = e.to_string()
+        };
+    };
+
+    Err(ParserError::UnknownArg(format!(
+        "Invalid value for {}: {}",
+        key,
+        error
+    )))
+}
@@ -1,34 +1,104 @@
-use std::{collections::HashMap, io::{self, Write}};
+use std::{
+    collections::HashMap, 
+    io::{self, Write}, 
+    path::{Path, PathBuf}, 
+    time::Duration
+};
 
-use bars::{BarSeries, BarType, BarBuildError};
+use backtest::{
+    BacktestError,
+    CostModel, 
+    OptimizeSpec,
+    ParamGrid, 
+    SimBroker, 
+    Timeframes,
+    parse_spec, 
+    run_backtest, 
+    run_optimization,
+    run_walk_forward, 
+    strategy_from_params,
+    strategy_from_spec
+};
+use bars::{
+    BarOptions,
+    BarSeries, 
+    BarType, 
+    BarBuildError,
+    CsvDialect,
+    ExportFormat,
+    checksum::{ChecksumStatus, verify_checksums}
+};
+use charts::Chart;
 use database_ops::*;
+use database_ops::audit::{AuditOrigin, audit_log};
+use database_ops::checkpoint::{
+    ExchangeCursor, 
+    resume_from_checkpoint, 
+    stored_cursor_date
+};
+use database_ops::kraken::KrakenCursor;
+use string_helpers::Locale;
+use timestamp_tools::period_is_valid;
 
 use crate::{
-    app_state::AppState,
-    errors::{RunTimeError},
+    app_state::{AppState, JobKind, StartScreen, SystemPaths, save_config},
+    audit::{Audited, audited},
+    blotter,
+    bus_sink::spawn_bus_sinks,
+    catalog::{DataCatalog, refresh_catalog},
+    config_fields::{get_config_value, set_config_value},
+    errors::{InitializationError, PaperError, RunTimeError},
+    job_registry::JobRegistry,
+    live,
+    pair_cache::PairCache,
+    paper::{
+        self, 
+        Contingent, 
+        CostModels, 
+        NewOrder, 
+        OrderStatus, 
+        PaperOrder
+    },
+    prompt,
+    recurring,
+    telemetry,
     arg_parsing::{
         Command,
+        CsvFlags,
         DataResponse,
         ParsedArgs,
+        ParserError,
+        PartialCommand,
         Response,
         parse_args
     },
+    CandleRequest,
     DataDownloadStatus,
     DownloadStatusViewer,
-    PgPool
+    PgPool,
+    backtest_cost_model,
+    build_candle_batch,
+    build_timeframes,
+    preflight_candles,
+    preflight_memory
 };
 
 use reqwest::Client;
-use tokio::{sync::mpsc::unbounded_channel};
+use serde::Serialize;
+use tokio::sync::{
+    broadcast, 
+    mpsc::{UnboundedSender, unbounded_channel}
+};
 
 
-const HELP_STRING: &'static str = r#"
+const HELP_STRING: &str = r#"
 NAME
     dtrade — Cryptocurrency data management and candle builder tool
 
 SYNOPSIS
     dtrade COMMAND [OPTIONS]...
 
+    dtrade COMMAND --help
     dtrade --help | -h
     dtrade --version
 
@@ -40,12 +110,28 @@ DESCRIPTION
     generation.
 
 COMMANDS
-    candles EXCHANGE TICKER PERIOD [--integrity | -i]
+    candles EXCHANGE TICKER[,TICKER...] PERIOD[,PERIOD...] [--integrity | -i] 
+            [--fill-gaps] [--listing-anchor] [--export FORMAT] [--out PATH] 
+            [--ticks] [--delimiter C] [--quote MODE] [--no-header] 
+            [--timestamp-format FMT] [--from DATE] [--to DATE]
         Build OHLCV candles for the given exchange, trading pair and timeframe.
+        Run from a terminal with any of EXCHANGE, TICKER or PERIOD left out,
+        the missing ones are asked for, choosing from the stored pairs.
+
+        Several tickers and periods, separated by commas, build every 
+        combination at once. Each ticker's ticks are read a single time for
+        all its periods, and the pairs are built concurrently.
 
         Examples:
             dtrade candles kraken btcusd 1h
             dtrade candles binance ethusdt 15m -i
+            dtrade candles kraken btcusd 4h --export parquet
+            dtrade candles kraken btcusd 1d -e csv -d tab --no-header
+            dtrade candles kraken btcusd 1h -e jsonl --ticks
+            dtrade candles kraken btcusd 4h --out btc_4h.parquet
+            dtrade candles kraken btcusd 1h --format json --out - | jq
+            dtrade candles kraken btcusd 1d --from 2024-01-01 --to 2024-07-01
+            dtrade candles kraken btcusd,ethusd 1h,4h -e csv
 
         Arguments:
             EXCHANGE     Name of the exchange (kraken, binance, ...)
@@ -56,8 +142,341 @@ COMMANDS
             --integrity, -i
                 Perform database integrity check before/after building candles
 
+            --fill-gaps
+                Insert flat, zero-volume bars for periods without trades so
+                the output has a continuous time axis
+
+            --listing-anchor
+                Open the first bar at the pair's first stored tick instead 
+                of a calendar boundary, so a young pair's first candle isn't
+                partial. Defaults to the config
+
+            --export, --format, -e FORMAT
+                Save the candles to the candle_data directory. FORMAT is one 
+                of csv, json, jsonl (one candle per line) or parquet
+
+            --out, -o PATH
+                Write the candles to PATH instead, or print them with -. 
+                Without --format, PATH's extension picks the format, and 
+                anything else is written as CSV. Only for a single ticker 
+                and period
+
+            --ticks
+                Also save the ticks the candles were built from, one JSON 
+                object per line
+
+            --delimiter, -d C
+                CSV field delimiter, a single character or 'tab'. Defaults
+                to the config, then to ',' (';' with decimal commas)
+
+            --quote MODE
+                CSV quoting: minimal (default), all or never
+
+            --no-header
+                Leave the header row out of CSV exports
+
+            --timestamp-format FMT
+                strftime pattern for the CSV timestamp column, in UTC 
+                (e.g. "%Y-%m-%d %H:%M"). Defaults to the locale date style
+
+            --from DATE, --to DATE
+                Build candles only from the ticks in a time window instead 
+                of the latest million: from DATE on, and before the --to 
+                DATE. A DATE is an ISO date (2024-03-01), a date and time 
+                in UTC (2024-03-01T12:30, or with an offset such as +02:00)
+                or a unix timestamp in seconds. Either can be left out.
+                A window whose ticks are estimated to need more memory 
+                than the build memory budget in Settings is refused
+
+    chart --ascii EXCHANGE TICKER PERIOD [--bars N | -n N]
+        Print a candlestick chart of the most recent bars to the terminal.
+
+        Examples:
+            dtrade chart --ascii kraken BTCUSD 1h
+            dtrade chart --ascii kraken BTCUSD 15m --bars 100
+
+        Options:
+            --bars, -n N
+                Number of bars to draw (default: 60)
+
+    backtest EXCHANGE TICKER PERIOD [--strategy SPEC | -s SPEC] [--cash N] 
+             [--walk-forward IN,OUT] [--objective NAME] [--optimize FILE] 
+        Run a strategy over the pair's candles with simulated fees and 
+        slippage, and print a performance report.
+
+        Examples:
+            dtrade backtest kraken BTCUSD 1h
+            dtrade backtest kraken BTCUSD 4h -s "sma-cross(20,50)" --json
+            dtrade backtest kraken BTCUSD 1h -s "inside-bar(5,2)"
+            dtrade backtest kraken BTCUSD 1h -s "breakout.rhai(20)"
+            dtrade backtest kraken BTCUSD 1h --walk-forward 500,100
+            dtrade backtest kraken BTCUSD 1h --optimize strategy.toml
+
+        Options:
+            --strategy, -s SPEC
+                Strategy and parameters (default: sma-cross, i.e. 
+                sma-cross(10,30)). Built-in strategies:
+
+                    sma-cross(FAST,SLOW)
+                        Long while the FAST SMA is above the SLOW one
+                    inside-bar(EXPIRY,REWARD)
+                        Long on a close above the mother bar of an inside 
+                        bar within EXPIRY bars, stop at the mother bar's 
+                        low, target REWARD times the risk (default: 5,2). 
+                        Needs Inside Bar Testing enabled in settings
+                    PATH.rhai(PARAMS...)
+                        Rhai script defining on_bar(bar, account), and 
+                        optionally init(params) and warmup(), the bars 
+                        to see before trading. on_bar returns (), "buy",
+                        "sell" or #{ side, volume, limit }. Scripts keep 
+                        state in `this` and can call sma(values, period).
+                        To read other periods of the pair, timeframes() 
+                        returns e.g. ["1h"], and each of their closed bars
+                        goes to on_timeframe_bar(period, bar)
+
+                Orders placed before a strategy's indicators have warmed 
+                up are ignored. A stretch without trades as long as an 
+                indicator's warmup, in missing bars or filled flat ones, 
+                starts it warming up again.
+
+            --cash N
+                Starting cash in the quote currency (default: 10000)
+
+            --walk-forward IN,OUT
+                Walk-forward analysis: re-optimize the strategy's parameters
+                on each window of IN bars, trade the OUT bars that follow 
+                with the best ones, then roll forward by OUT bars. Reports
+                each window and the out-of-sample windows combined
+
+            --objective NAME
+                What walk-forward optimization maximizes: sharpe (default),
+                sortino or return
+
+            --optimize FILE
+                Grid search: backtest every combination of the parameter 
+                ranges in a TOML file, in parallel, and print the results 
+                ranked by the file's objective. With --walk-forward, the 
+                file's ranges and objective are used in each window. Format:
+
+                    strategy = "sma-cross"  # or a .rhai script path
+                    objective = "sharpe"    # sharpe, sortino or return
+                    top = 10                # rows to show (default: all)
+
+                    [[params]]
+                    name = "fast"
+                    values = [5, 10, 15]
+
+                    [[params]]
+                    name = "slow"
+                    range = [20, 60, 10]    # start, end, step
+
+    paper status | buy | sell | cancel | modify | history | sync | rebalance
+            | reset
+        Trade a simulated account against the stored ticks. Accounts keep
+        their cash, positions and orders in the _paper_accounts, 
+        _paper_positions and _paper_orders tables, and a new one opens 
+        with 10,000 in cash. Orders fill on the ticks stored after they 
+        were placed, with the pair's fees and the backtests' slippage: 
+        market orders on the next tick, limit orders at their price once a
+        tick trades through it. Sync after `database --update` to fill 
+        orders on new ticks; new orders sync first, while status and 
+        history only read.
+
+            status
+                Cash, equity, positions at their last price, open orders,
+                as of the last sync
+            buy | sell EXCHANGE TICKER VOLUME [--limit PRICE | --stop PRICE]
+                    [--oco-stop PRICE] [--stop-loss PRICE --take-profit PRICE]
+                    [--at DATE]
+                Place an order. With --at it's placed at DATE, and the 
+                ticks stored since then are replayed against it. A stop 
+                order fills at market once a tick reaches its price. 
+                --oco-stop places a stop next to a limit order, the first 
+                of the two to fill cancels the other. --stop-loss and 
+                --take-profit make a bracket: both exits are pending until 
+                the order fills, then the first of them to fill cancels 
+                the other
+            cancel ID
+                Cancel an open order
+            modify ID --price PRICE
+                Move an open order's limit or stop to PRICE. The account 
+                syncs first, so the ticks stored before the change fill it
+                at its old price
+            history
+                Every order, newest first, with its fill or why it was 
+                rejected
+            sync
+                Fill the open orders the stored ticks have reached
+            rebalance EXCHANGE TICKER=WEIGHT... [--min-drift SHARE] [--yes]
+                Buy and sell at market to hold each pair at its weight of
+                the equity, the rest in cash. Held pairs left out are 
+                sold, and pairs less than SHARE (default: 0.01) off target 
+                are left alone. The orders are listed and asked about 
+                first, --yes places them without asking. Buys waiting on 
+                the sells' proceeds are refused until a later run
+            reset [--cash N]
+                Empty the account and start it again with N (default: 
+                10000) in cash
+
+        Examples:
+            dtrade paper buy kraken BTCUSD 0.05
+            dtrade paper sell kraken BTCUSD 0.05 --limit 105000
+            dtrade paper sell kraken BTCUSD 0.05 --limit 105000 --oco-stop 95000
+            dtrade paper buy kraken ETHUSD 2 --stop-loss 3000 --take-profit 4000
+            dtrade paper buy kraken ETHUSD 2 --at 2024-03-01 --account replay
+            dtrade paper rebalance kraken BTCUSD=0.6 ETHUSD=0.3
+            dtrade paper status --json
+
+        Options:
+            --account NAME
+                Account to trade (default: default)
+
+    audit [--action ACTION] [--limit N | -n N]
+        Show the commands that changed something, newest first: pairs 
+        added, dropped, updated, repaired or rolled back, config and alias 
+        changes and paper and live orders. Each row has its time (UTC), 
+        where it came from (cli, tui, or http with the last characters of 
+        the caller's API key) and its parameters, with keys and passwords 
+        left out. Rows are kept in the _audit_log table, which only takes 
+        new rows.
+
+        Examples:
+            dtrade audit
+            dtrade audit --action pair.drop --json
+
+        Options:
+            --action ACTION
+                Only show one action, e.g. pair.add, pair.drop, config.set
+                or paper.order
+            --limit N, -n N
+                Number of rows to show (default: 50)
+
+    live balance | orders | buy | sell | cancel | modify
+        Trade a real Kraken account through its private API. Off until 
+        turned on with `config set live.enabled true` and given an API key
+        and secret, in live.kraken_api_key and live.kraken_api_secret or 
+        the DTRADE_KRAKEN_API_KEY and DTRADE_KRAKEN_API_SECRET environment
+        variables. Orders worth more than live.max_order_value (default: 
+        100, in the quote currency) are refused before they're sent, market
+        orders valued at the current ask or bid.
+
+            balance
+                The account's balance of each asset
+            orders
+                The account's open orders, oldest first
+            buy | sell EXCHANGE TICKER VOLUME [--limit PRICE] [--validate]
+                Place an order. With --validate the exchange only checks 
+                it
+            cancel TXID
+                Cancel an open order
+            modify TXID --price PRICE
+                Move an open order's price. Kraken replaces it with a new 
+                order, and the volume left to fill is checked against 
+                live.max_order_value at the new price
+
+        Examples:
+            dtrade live balance
+            dtrade live buy kraken XBTUSD 0.0005 --limit 60000 --validate
+            dtrade live orders --json
+
+    fills [--format FORMAT | -f FORMAT] [--out PATH | -o PATH]
+            [--from DATE] [--to DATE]
+        Export every fill of the paper accounts, and of the live account
+        once live trading is on, with its fee and the P&L it realized over
+        the average cost of the pair, for tax reports and other tools. The
+        P&L counts the whole history, so a window of it still sells against
+        earlier buys. Also the `e` key of the Orders screen.
+
+            --format, -f FORMAT
+                csv or parquet (default: the extension of --out, else csv)
+            --out, -o PATH
+                Where to write (default: fills_DATE.EXT)
+            --from DATE, --to DATE
+                Only fills from DATE up to, not including, --to
+
+        Examples:
+            dtrade fills --from 2025-01-01 --to 2026-01-01 -o fills.csv
+
+    recurring add | list | remove | history | run
+        Buy a pair for a fixed amount of the quote currency on a schedule,
+        in a paper account or the live one. `start --http` places the buys
+        that are due every minute, skipping the ones missed while it was
+        down, and `recurring run` places them once, e.g. from cron. Buys
+        that can't be placed are kept in the history with the reason.
+
+            add EXCHANGE TICKER AMOUNT --every PERIOD [--account NAME |
+                    --live] [--start DATE]
+                Schedule a market buy of AMOUNT every PERIOD (30m, 4h, 1d,
+                1w, ...), in the paper account NAME (default: default) or
+                with --live on the live account, first at --start or now
+            list
+                The recurring buys still running
+            remove ID
+                Stop a recurring buy, keeping its history
+            history ID
+                Every run of a recurring buy with the order it placed and
+                its fill, and the average cost of what it bought, fees in
+            run
+                Place the buys that are due now
+
+        Examples:
+            dtrade recurring add kraken XBTUSD 50 --every 1w
+            dtrade recurring history 1
+
+    catalog [EXCHANGE [TICKER]] [--refresh | -r]
+        Show the data catalog: every stored pair with its source, date 
+        coverage, row count, last update and exported files. The catalog is
+        kept in catalog.json next to the config, and is regenerated after 
+        commands that change data or write exports.
+
+        Examples:
+            dtrade catalog
+            dtrade catalog kraken BTCUSD --json
+
+        Options:
+            --refresh, -r
+                Regenerate the catalog from the database before showing it
+
+    verify PATH
+        Re-check exported files against the .sha256 checksum files written
+        next to them. PATH is an exported file, its checksum file or a 
+        directory; bare file names are looked up in candle_data.
+
+        Examples:
+            dtrade verify kraken_BTCUSD_1h_1767225600-1767967200.csv
+            dtrade verify ~/.config/dtrade/candle_data
+
+    alias list | add NAME COMMAND... | remove NAME
+        Manage shorthands for command lines, kept under "aliases" in 
+        config.json. An alias given as the first argument is replaced by 
+        its command line, and any arguments after it are appended. Alias 
+        names can't be command names, and an alias can't use another one.
+
+        Examples:
+            dtrade alias add btc1h candles kraken BTCUSD 1h --export csv
+            dtrade btc1h --fill-gaps
+            dtrade alias add bt backtest kraken BTCUSD 4h -s "sma-cross(20,50)"
+            dtrade alias list
+            dtrade alias remove btc1h
+
+    config get [KEY] | set KEY VALUE
+        Print or change a value in config.json, by its dotted path. Without
+        a KEY, get prints the whole config. set checks the value like the 
+        settings screen does (periods, addresses, URLs, date and CSV 
+        options...) and that it fits the value it replaces: true or false 
+        for switches, a number for numbers, JSON for lists and sections. A
+        blank VALUE ("") clears an optional value.
+
+        Examples:
+            dtrade config get chart_parameters.num_bars
+            dtrade config set data_download.cache_size 3M
+            dtrade config set supported_exchanges.active.kraken true
+            dtrade config set telemetry.endpoint ""
+
     database --add-pairs EXCHANGE TICKER [TICKER...]
         Add one or more trading pairs to the database for the given exchange.
+        Run from a terminal without them, the exchange and the pairs are
+        asked for.
 
         Example:
             dtrade database --add-pairs kraken SOLUSD ETHUSD XRPUSD
@@ -71,11 +490,76 @@ COMMANDS
 
     database --update
         Update/fetch latest pair metadata and information from exchanges.
+        A download whose position doesn't move for the configured stall 
+        timeout (default: 5 minutes) is aborted and restarted from its last
+        batch, up to the configured number of retries (default: 1).
+
+        Downloads can be mirrored to Kafka or NATS, per exchange, under
+        "integrations.message_bus" in config.json, e.g. "kraken": {"bus":
+        "nats", "url": "nats://127.0.0.1:4222"}. Every committed batch of
+        ticks is published to dtrade.ticks and every download event to
+        dtrade.jobs (NATS subjects end in .EXCHANGE.TICKER, Kafka messages
+        are keyed by it). Needs a build with --features kafka or nats.
 
         Example:
             dtrade database --update
 
-    database --integrity [EXCHANGE [TICKER]]
+    database --retry-failed
+        Update only the pairs whose last download ended in an error, as 
+        recorded in the _job_history table. Pairs that were cancelled or 
+        finished are left alone.
+
+        Example:
+            dtrade database --retry-failed
+
+    database --api-usage
+        Report the requests sent to each exchange per day over the last 30
+        days, counted in the _api_requests table, and estimate a month of 
+        them at the current update cadence and number of pairs. Warns when 
+        the estimate comes near the exchange's rate limit.
+
+        Example:
+            dtrade database --api-usage
+
+    database --stats
+        Show every pair table's row count, size on disk (with indexes), 
+        first and last tick, and ingestion lag: how long ago the last tick 
+        was. Row counts are Postgres' estimate once a table has been 
+        analyzed.
+
+        Example:
+            dtrade database --stats --json
+
+    database --rollback-batch ID
+        Undo a bad download batch: delete the ticks it inserted and move the
+        pair's download position back to where it was before the batch.
+        Every committed batch is recorded, with the ID of the job that 
+        downloaded it, in the _ingest_batches table. Only a pair's latest 
+        batch can be rolled back, and the data catalog is regenerated 
+        afterwards.
+
+        Example:
+            dtrade database --rollback-batch 1042
+
+    pair export EXCHANGE TICKER [--out FILE | -o FILE]
+        Save one pair to a portable, compressed pair file: its table 
+        schema, every tick and its download position. Share a single pair's
+        history without dumping the whole database. FILE defaults to 
+        EXCHANGE_TICKER.dtpack in the current directory.
+
+        Example:
+            dtrade pair export kraken BTCUSD --out btcusd.dtpack
+
+    pair import FILE
+        Add the pair saved in a pair file to the database. Later updates 
+        carry on downloading from where the file's ticks end. Fails if the 
+        pair is already stored; remove it with --rm-pairs first to replace
+        it.
+
+        Example:
+            dtrade pair import btcusd.dtpack
+
+    database --integrity [EXCHANGE [TICKER]] [--repair]
         Check database integrity (missing candles, duplicates, gaps, etc.).
 
         When no arguments are given, checks all exchanges and pairs.
@@ -86,18 +570,129 @@ COMMANDS
             dtrade database --integrity
             dtrade database --integrity kraken
             dtrade database --integrity kraken BTCUSD
+            dtrade database --integrity kraken BTCUSD --repair
+
+        Options:
+            --repair
+                Re-download the time windows around any missing ticks and 
+                fill the gaps, then run the check again
+
+    start [--http] [--guest] [--screen SCREEN]
+        Start the terminal interface, or with --http a REST API server on
+        the address in config.json ("http.address", default 
+        127.0.0.1:8080). Endpoints return JSON:
+
+            GET  /pairs
+                Stored pairs, grouped by exchange
+            GET  /candles/{exchange}/{ticker}/{period}
+                Candles, with optional ?fill_gaps=true and 
+                ?listing_anchor=true
+            GET  /integrity?exchange=E&ticker=T
+                Database integrity check of one pair
+            POST /integrity?exchange=E&ticker=T
+                Integrity check that repairs missing ticks
+            POST /update
+                Download new trades for every pair
+            GET  /update/events?exchange=E&ticker=T
+                Server-Sent Events with the progress of downloads, 
+                optionally for one exchange or pair
+            GET  /ws?exchange=E&ticker=T&period=P
+                WebSocket streaming download progress, and with a pair 
+                and period, each bar that closes as new trades arrive
+            GET  /paper/ACCOUNT
+                Paper account with its positions and open orders, as of
+                its last sync
+            GET  /paper/ACCOUNT/orders
+                Every order of the paper account, newest first
+            POST /paper/ACCOUNT/sync
+                Fill the open paper orders the stored ticks have reached,
+                returning the orders filled, rejected or cancelled
+            POST /paper/ACCOUNT/buy, POST /paper/ACCOUNT/sell
+                Place a paper order from a JSON body such as 
+                {"exchange": "kraken", "ticker": "BTCUSD", "volume": 0.01},
+                with optional "limit" or "stop", "oco_stop", 
+                "stop_loss" and "take_profit", and "at" in unix seconds
+            DELETE /paper/ACCOUNT/orders/ID
+                Cancel an open paper order
+
+        Clients send an API key as "Authorization: Bearer KEY", an 
+        X-Api-Key header or an api_key query parameter. Keys come from 
+        "http.api_keys" in config.json and the DTRADE_API_KEYS environment
+        variable (KEY:ROLE,KEY:ROLE), with one of the roles:
+            viewer      Reads data, pairs, candles and paper accounts
+            operator    Also updates, repairs and changes stored data
+            trader      Also places, syncs and cancels paper orders
+            admin       Everything
+        Keys with the older read scope are viewers. With no keys set, the
+        API is open to anyone but read-only, as for viewers.
+
+        With "http.flight_address" set in config.json (e.g. 
+        127.0.0.1:8815), an Arrow Flight service starts alongside the API
+        and streams stored ticks as record batches of id, time, price and
+        volume. Flights are listed per pair, and a ticket is JSON such as
+        {"exchange": "kraken", "ticker": "BTCUSD", "from": 1704067200}, 
+        with "from" and "to" in unix seconds and optional. Keys are sent 
+        as "authorization: Bearer KEY" or x-api-key metadata.
+
+        With "redis.url" set (e.g. redis://127.0.0.1:6379), each finished
+        download publishes JSON to Redis: new ticks on
+        dtrade:ticks:EXCHANGE:TICKER, candles of "redis.candle_periods"
+        as they close on dtrade:candles:EXCHANGE:TICKER:PERIOD, and the
+        orders "redis.strategies" place on them on
+        dtrade:signals:EXCHANGE:TICKER:PERIOD. The "dtrade" prefix is
+        "redis.channel_prefix".
+
+        With "mqtt.host" set (and "mqtt.port", default 1883), every pair's
+        latest price is published retained to dtrade/EXCHANGE/TICKER/price
+        on start and after each committed download batch, e.g. 
+        dtrade/kraken/BTCUSD/price, as a plain number that dashboards can
+        show as is. Alerts under "mqtt.alerts", e.g. {"exchange": 
+        "kraken", "ticker": "BTCUSD", "above": 100000}, publish JSON to 
+        dtrade/EXCHANGE/TICKER/alert when the price crosses "above" or 
+        "below". dtrade/status is online or offline. The "dtrade" prefix
+        is "mqtt.topic_prefix", and "mqtt.username" and "mqtt.password" 
+        log in to the broker.
 
-    start
-        Start the trading server / background service.
+        Options:
+            --guest
+                Read-only guest mode for shared terminals and demos: the 
+                terminal interface disables deleting pairs and saving 
+                settings
+
+            --screen SCREEN
+                Screen the terminal interface opens on: database, candles, 
+                settings, strategies, chart, integrity, watch (live quotes 
+                for "tui.watchlist"), jobs (background downloads), paper 
+                (paper trading account), orders (paper and live orders), 
+                menu (no screen) or last (the one open when it was last 
+                closed). Defaults to the config's "tui.start_screen", which
+                defaults to last
 
 OPTIONS (global)
     --help, -h
-        Show this help message and exit.
+        Show this help message and exit. After a COMMAND, show a short 
+        summary of the command's arguments and options instead.
+
+    --version, -V
+        Print the version and exit.
 
     --dev 
         Runs the dev_testing() function in src/lib.rs. Intended only for 
         developing new features
 
+    --json
+        Print results as JSON on standard output instead of text, for 
+        scripts and CI: backtest reports (with every trade), the catalog,
+        database --stats, --integrity and --api-usage, config, paper 
+        trading results, the audit log, and a summary of each series 
+        candles builds (bars, time range and where it was saved). Progress
+        messages are left out, and a failed run prints 
+        {"error": {"kind", "message", "exit_code"}} instead, still exiting
+        with the status below. Can go anywhere on the command line.
+
+        Example:
+            dtrade database --integrity kraken BTCUSD --json | jq .is_ok
+
 EXAMPLES
     Fetch and add new pairs from Kraken:
         dtrade database --add-pairs kraken SOLUSD ETHUSD
@@ -114,6 +709,16 @@ EXAMPLES
     Mixed command (integrity check + update):
         dtrade database --integrity kraken BTCUSD --update
 
+USAGE STATS
+    Off by default. When turned on in the settings, dtrade counts how 
+    often each command runs and which kinds of errors it hits (e.g. 
+    "candles.export": 12, "database": 1), and how many requests went to 
+    Kraken, how many connections they opened and how long they took, and
+    nothing else: no pairs, files, keys or addresses. The counts are kept
+    in telemetry.json next to config.json, and are only sent when a report
+    URL is set as well ("telemetry.endpoint"), as a JSON POST after each
+    command line run. 
+
 EXIT STATUS
     0     Success
     1     General error / invalid usage
@@ -121,6 +726,17 @@ EXIT STATUS
     3     Parser error (unknown flags, missing arguments, ...)
     4     Database connection / query failure
     5     Candle builder error
+    6     Backtest error
+    7     Data catalog error
+    8     Job timed out (see "timeouts" in config.json)
+    9     Candle request can't be served (pair not added, no data, bad 
+          period)
+    10    Paper trading error (order rejected, no funds, no such order)
+    11    Live trading error (off, no API key, order over the limit, or 
+          rejected by the exchange)
+    12    Export error (the file couldn't be written)
+    13    Recurring buy error (no such schedule, or its account or pair 
+          can't be used)
 
 BUGS / LIMITATIONS
     Currently only Kraken is fully tested for pair adding/removal.
@@ -128,7 +744,7 @@ BUGS / LIMITATIONS
     --integrity on very large datasets may be slow.
 
 SEE ALSO
-    Rust crates: sqlx, reqwest, clap (for future refactors), tokio
+    Rust crates: sqlx, reqwest, clap, tokio
     Related projects: ccxt (exchange library inspiration)
 
 Report bugs or suggestions at: 
@@ -153,6 +769,56 @@ impl std::fmt::Display for Server {
 }
 
 
+/// What to do with one series of `Engine::run_candle_builds` once it's built
+struct CandleOutput {
+    exchange: String,
+    ticker: Ticker,
+    period: String,
+    integrity_check: bool,
+    fill_gaps: bool,
+    export: Option<ExportFormat>,
+    out: Option<String>,
+    export_ticks: bool,
+    csv_flags: CsvFlags,
+}
+
+/// One series of `Engine::run_candle_builds`, as printed with `--json`
+#[derive(Serialize)]
+struct CandleSummary {
+    exchange: String,
+    ticker: String,
+    period: String,
+    bars: usize,
+    /// Unix timestamps (seconds), `None` without bars
+    first_open: Option<i64>,
+    last_close: Option<i64>,
+    saved_to: Option<String>,
+    ticks_saved_to: Option<String>,
+}
+
+/// Prints the orders a paper trading sync filled, rejected, cancelled or
+/// opened
+fn print_paper_fills(orders: &[PaperOrder]) {
+    for order in orders {
+        let color: &str = match order.status {
+            OrderStatus::Filled => "\x1b[32m",
+            OrderStatus::Open | OrderStatus::Pending => "\x1b[33m",
+            OrderStatus::Cancelled => "\x1b[35m",
+            OrderStatus::Rejected => "\x1b[31m"
+        };
+        println!("{}{}\x1b[0m", color, order);
+    };
+}
+
+/// Prints `value` as JSON, for `--json`
+fn print_json<T: Serialize>(value: &T) {
+    match serde_json::to_string_pretty(value) {
+        Ok(s) => println!("{}", s),
+        Err(e) => eprintln!("{}", e)
+    };
+}
+
+
 /// # Main App Engine
 ///
 /// Responsible for loading app state, communicating with the database, 
@@ -163,6 +829,20 @@ pub struct Engine {
     pub request_client: Client,
     pub args: ParsedArgs,
     pub op_mode: Server,
+    /// Receives a copy of every download progress event, for the HTTP 
+    /// server's stream
+    pub status_feed: Option<broadcast::Sender<DataDownloadStatus>>,
+    /// Mirrors download events to the message buses, see `start_bus_feed`
+    pub bus_feed: Option<UnboundedSender<DataDownloadStatus>>,
+    /// Set by `start --guest`: the terminal interface hides or disables 
+    /// actions that delete data or change settings
+    pub guest_mode: bool,
+    /// Set by `start --screen`, overriding `TuiSettings::start_screen`
+    pub start_screen: Option<StartScreen>,
+    /// Background jobs spawned by the terminal interface
+    pub jobs: JobRegistry,
+    /// Stored pairs, for screens that list them on every frame
+    pub pairs: PairCache,
 }
 
 impl Engine {
@@ -170,11 +850,20 @@ impl Engine {
     pub fn new(database: Db) -> Result<Self, RunTimeError> {
 
         let state: AppState = AppState::new()
-            .map_err(|e| RunTimeError::Init(e))?;
+            .map_err(RunTimeError::Init)?;
+
+        let request_client: Client = kraken::kraken_client()
+            .map_err(|e| RunTimeError::Init(
+                InitializationError::HttpClient(e.to_string())
+            ))?;
 
-        let request_client: Client = Client::new();
+        let mut args: ParsedArgs = parse_args(None, &state.config.aliases);
 
-        let args: ParsedArgs = parse_args(None);
+        // Missing arguments are asked for instead, when there's a terminal
+        // to ask on, see `complete_partial`
+        if args.partial.is_some() && prompt::is_interactive() {
+            args.parser_error = None;
+        };
 
         if let Some(e) = args.parser_error {
             return Err(RunTimeError::Arguments(e))
@@ -182,7 +871,19 @@ impl Engine {
 
         let op_mode: Server = Server::OneShot;
 
-        Ok(Engine { state, database, request_client, args, op_mode })
+        Ok(Engine { 
+            state, 
+            database, 
+            request_client, 
+            args, 
+            op_mode, 
+            status_feed: None,
+            bus_feed: None,
+            guest_mode: false,
+            start_screen: None,
+            jobs: JobRegistry::new(),
+            pairs: PairCache::default()
+        })
 
     }
 
@@ -193,12 +894,72 @@ impl Engine {
     pub async fn execute_commands(&mut self) -> Result<Response, RunTimeError> {
         
         let mut response: Option<Response> = None;
+        let mut data_changed: bool = false;
 
-        for _ in 0..self.args.commands.len() {
+        if let Some(partial) = self.args.partial.take() {
+            let completed: Vec<Command> = self.complete_partial(partial)
+                .await?;
+            self.args.commands.splice(0..0, completed);
+        };
+
+        while !self.args.commands.is_empty() {
             
-            let cmd = self.args.commands.remove(0);
+            let mut cmds: Vec<Command> = vec![self.args.commands.remove(0)];
+
+            // Candle builds listed together, e.g. for several tickers or 
+            // periods, run as one batch reading each pair's ticks once
+            if let Command::CandleBuilder { .. } = cmds[0] {
+                let n: usize = self.args.commands
+                    .iter()
+                    .take_while(|c| matches!(c, Command::CandleBuilder { .. }))
+                    .count();
+                cmds.extend(self.args.commands.drain(..n));
+            };
+
+            let features: Vec<&str> = cmds
+                .iter()
+                .map(telemetry::feature_name)
+                .collect();
+            data_changed |= cmds.iter().any(Command::changes_data);
+            let audits: Vec<Audited> = cmds
+                .iter()
+                .filter_map(audited)
+                .collect();
+
+            let limit = cmds[0].job_kind().and_then(|job| {
+                self.state.config.timeouts.limit(job).map(|l| (job, l))
+            });
+
+            // Stops its downloads cooperatively when the limit is hit
+            let cooperative: bool = matches!(
+                cmds[0], 
+                Command::UpdatePairs | Command::RetryFailed
+            );
+
+            let run = async {
+                match cmds.len() {
+                    1 => self.handle(cmds.remove(0)).await,
+                    _ => self.run_candle_builds(cmds).await
+                }
+            };
+
+            let result = match limit {
+                Some((job, limit)) if !cooperative => {
+                    tokio::time::timeout(limit, run)
+                        .await
+                        .unwrap_or(Err(RunTimeError::Timeout { job, limit }))
+                },
+                _ => run.await
+            };
+
+            for feature in features {
+                self.record_usage(feature, &result);
+            };
+            for entry in &audits {
+                self.record_audit(entry, &AuditOrigin::Cli, &result).await;
+            };
             
-            match self.handle(cmd).await? {
+            match result? {
                 Response::Ok => {},
                 Response::Data(data) => {
                     response = Some(Response::Data(data));
@@ -206,6 +967,21 @@ impl Engine {
             }; 
         };
 
+        telemetry::report(
+            &self.state.config.telemetry, 
+            &self.state.paths, 
+            &self.request_client
+        ).await;
+
+        if data_changed
+            && let Err(e) = refresh_catalog(self.database.get_pool()).await
+        {
+            eprintln!(
+                "\x1b[33mCouldn't update the data catalog: {}\x1b[0m", 
+                e
+            );
+        };
+
         Ok(match response {
             Some(data) => data,
             None => Response::Ok
@@ -229,7 +1005,8 @@ impl Engine {
                     self.database.get_pool(),
                     &self.request_client,
                     None
-                ).await.map_err(|e| RunTimeError::DataBase(e))?;
+                ).await.map_err(RunTimeError::DataBase)?;
+                self.pairs.invalidate();
 
                 Ok(Response::Ok)
             },
@@ -238,161 +1015,1907 @@ impl Engine {
                 
                 drop_pair(&exchange, &ticker, self.database.get_pool())
                     .await 
-                    .map_err(|e| RunTimeError::DataBase(e))?;
+                    .map_err(RunTimeError::DataBase)?;
+                self.pairs.invalidate();
+
+                Ok(Response::Ok)
+            },
+
+            Command::RollbackBatch { batch_id } => {
+
+                let (batch, deleted) = lineage::rollback_batch(
+                    batch_id, 
+                    self.database.get_pool()
+                )
+                    .await
+                    .map_err(RunTimeError::DataBase)?;
+
+                println!(
+                    "\x1b[1;32mRolled back batch {} of {} {}\x1b[0m: deleted \
+                    {} ticks ({} to {}). The next update downloads again \
+                    from tick {}, {}",
+                    batch_id,
+                    batch.exchange,
+                    batch.asset,
+                    deleted,
+                    batch.first_tick_id,
+                    batch.last_tick_id,
+                    batch.prev_next_tick_id,
+                    stored_cursor_date(&batch.exchange, batch.prev_cursor_ns)
+                );
+
+                Ok(Response::Ok)
+            },
+
+            Command::ExportPair { exchange, ticker, out } => {
+
+                let path: PathBuf = match out {
+                    Some(p) => PathBuf::from(p),
+                    None => PathBuf::from(format!(
+                        "{}_{}.{}",
+                        exchange,
+                        ticker,
+                        pack::PAIR_FILE_EXTENSION
+                    ))
+                };
+
+                let header = pack::export_pair(
+                    &exchange, 
+                    &ticker, 
+                    &path, 
+                    self.database.get_pool()
+                )
+                    .await
+                    .map_err(RunTimeError::DataBase)?;
+
+                println!(
+                    "\x1b[1;32mExported {} {}\x1b[0m: {} ticks ({}) to {}",
+                    header.exchange,
+                    header.asset,
+                    header.tick_count,
+                    header.date_range(),
+                    path.display()
+                );
 
                 Ok(Response::Ok)
             },
 
-            Command::StartServer { http } => {
+            Command::ImportPair { path } => {
+
+                let header = pack::import_pair(
+                    &PathBuf::from(&path), 
+                    self.database.get_pool()
+                )
+                    .await
+                    .map_err(RunTimeError::DataBase)?;
+                self.pairs.invalidate();
+
+                println!(
+                    "\x1b[1;32mImported {} {}\x1b[0m: {} ticks ({}). The \
+                    next update downloads from {}",
+                    header.exchange,
+                    header.asset,
+                    header.tick_count,
+                    header.date_range(),
+                    stored_cursor_date(&header.exchange, header.cursor_ns)
+                );
+
+                Ok(Response::Ok)
+            },
+
+            Command::StartServer { http, guest, screen } => {
                 if http {
                     self.op_mode = Server::HTTP;
                 }
                 else {
                     self.op_mode = Server::CLI;
                 };
+                self.guest_mode = guest;
+                self.start_screen = screen;
                 Ok(Response::Ok)
             },
 
             Command::UpdatePairs => {
+                let bus_feed = self.start_bus_feed().await;
                 run_database_table_updates(
                     &self.state, 
                     &self.request_client, 
                     self.database.get_pool(),
+                    self.status_feed.clone(),
+                    bus_feed,
+                    Vec::new()
                 ).await?;
                 
                 Ok(Response::Ok)
             },
 
-            Command::CandleBuilder { 
-                exchange, ticker, period, integrity_check 
-            } => {
-    
-                let bars = BarSeries::new(
-                    exchange, 
-                    ticker, 
-                    period, 
-                    BarType::Candle, 
-                    self.database.get_pool() 
-                )
+            Command::RetryFailed => {
+                let failed: Vec<(String, String)> = jobs::failed_pairs(
+                    self.database.get_pool()
+                ).await.map_err(RunTimeError::DataBase)?;
+
+                if failed.is_empty() {
+                    println!("\x1b[32mNo failed downloads to retry\x1b[0m");
+                    return Ok(Response::Ok)
+                };
+
+                println!(
+                    "\x1b[1;36mRetrying {} failed download(s)...\x1b[0m", 
+                    failed.len()
+                );
+
+                let bus_feed = self.start_bus_feed().await;
+                run_database_table_updates(
+                    &self.state, 
+                    &self.request_client, 
+                    self.database.get_pool(),
+                    self.status_feed.clone(),
+                    bus_feed,
+                    failed
+                ).await?;
+                
+                Ok(Response::Ok)
+            },
+
+            Command::ApiUsage => {
+                let report = usage::api_usage_report(self.database.get_pool())
                     .await
-                    .map_err(|e| RunTimeError::Bar(e))?;
+                    .map_err(RunTimeError::DataBase)?;
+
+                if self.args.json {
+                    print_json(&report);
+                }
+                else if !matches!(self.op_mode, Server::HTTP) {
+                    print!("{report}");
+                };
+                Ok(Response::Data(DataResponse::ApiUsage(report)))
+            },
+
+            Command::DbStats => {
+                let report = stats::database_stats(self.database.get_pool())
+                    .await;
 
-                if integrity_check {
-                    let is_ok: bool = bars.bar_integrity_check();
-                    if !is_ok {
-                        return Err(RunTimeError::Bar(
-                            BarBuildError::IntegrityCorruption
-                        )) 
-                    }; 
+                if self.args.json {
+                    print_json(&report);
+                }
+                else {
+                    print!("{}", report);
                 };
 
-                Ok(Response::Data(DataResponse::Bars(bars)))
+                Ok(Response::Ok)
+            },
+
+            Command::ListPairs => {
+                let pairs = fetch_exchanges_and_pairs_from_db(
+                    self.database.get_pool(),
+                    false
+                )
+                    .await
+                    .into_iter()
+                    .map(|(exchange, tickers)| (
+                        exchange,
+                        tickers.into_iter().map(|(t, _)| t).collect()
+                    ))
+                    .collect();
+
+                Ok(Response::Data(DataResponse::Pairs(pairs)))
+            },
+
+            cmd @ Command::CandleBuilder { .. } => {
+                self.run_candle_builds(vec![cmd]).await
             },
 
-            Command::DbIntegrityCheck { exchange, ticker } => {
-                let check = db_integrity_check(
+            Command::DbIntegrityCheck { exchange, ticker, repair } => {
+
+                let verbose: bool = !self.args.json 
+                    && !matches!(self.op_mode, Server::HTTP);
+
+                let checks = db_integrity_check(
                     &exchange, 
                     &ticker, 
+                    repair,
+                    verbose,
+                    &self.request_client,
                     self.database.get_pool() 
                 ).await;
 
-                println!("{check}");
-                Ok(Response::Ok)
+                if self.args.json {
+                    print_json(&checks);
+                }
+                else if verbose {
+                    for check in &checks {
+                        println!("{check}");
+                    };
+                };
+                Ok(Response::Data(DataResponse::Integrity(checks)))
             },
 
-            Command::Help => {
-                println!("{}", HELP_STRING);
-                Ok(Response::Ok)
-            }
-        }    
-    }
+            Command::Chart { exchange, ticker, period, num_bars } => {
+                
+                const CHART_HEIGHT: usize = 20;
 
-    pub fn set_args(&mut self, args: Vec<String>) {
-        self.args = parse_args(Some(args));
-    }
-}
+                preflight_candles(
+                    &exchange, 
+                    &ticker, 
+                    &period, 
+                    self.database.get_pool()
+                ).await?;
 
+                let bars = BarSeries::new(
+                    exchange, 
+                    ticker.into(), 
+                    period, 
+                    BarType::Candle, 
+                    self.state.config.bars,
+                    self.database.get_pool() 
+                )
+                    .await
+                    .map_err(RunTimeError::Bar)?;
 
-/// Updates all database tables. Emits progress messages to the terminal
-/// in real time.
-pub async fn run_database_table_updates(
-    state: &AppState,
-    client: &reqwest::Client,
-    db_pool: PgPool
-) -> Result<(), RunTimeError> {
+                let chart = Chart::new(bars);
+                println!("{}", chart.ascii_candles(num_bars, CHART_HEIGHT));
+                
+                Ok(Response::Ok)
+            },
 
-    // Progress listener
-    let (prog_tx, mut prog_rx) = unbounded_channel::<DataDownloadStatus>();
+            Command::Backtest { 
+                exchange, 
+                ticker, 
+                period, 
+                strategy, 
+                cash, 
+                walk_forward,
+                optimize
+            } => {
 
-    tokio::spawn(async move {
-        let mut viewer = DownloadStatusViewer::new();
-        
-        print!("\x1b[?25l");  // Hide cursor
-        while let Some(event) = prog_rx.recv().await {
-            
-            viewer.update_status(event);
-          
-            // Move cursor to top
-            if viewer.last_rendered_lines > 0 {
-                print!("\x1b[{}A", viewer.last_rendered_lines);
-            };
+                let json: bool = self.args.json;
 
-            // Clear old lines
-            for _ in 0..viewer.last_rendered_lines {
-                print!("\r\x1b[2K\n");
-            };
+                let spec: Option<OptimizeSpec> = match optimize {
+                    Some(path) => Some(
+                        OptimizeSpec::load(&PathBuf::from(path))
+                            .map_err(RunTimeError::Backtest)?
+                    ),
+                    None => None
+                };
 
-            // Move cursor to top, again
-            if viewer.last_rendered_lines > 0 {
-                print!("\x1b[{}A", viewer.last_rendered_lines);
-            };
+                let (name, _) = parse_spec(&strategy)
+                    .map_err(RunTimeError::Backtest)?;
+                self.check_strategy_enabled(&name)?;
+                if let Some(s) = &spec {
+                    self.check_strategy_enabled(&s.grid.strategy)?;
+                };
+                let mut strategy = strategy_from_spec(&strategy)
+                    .map_err(RunTimeError::Backtest)?;
+
+                // Periods read by the optimized strategy, when it's not the
+                // one given with --strategy
+                let mut periods: Vec<String> = strategy.timeframes();
+                if let Some(s) = &spec
+                    && let Some(params) = s.grid
+                        .combinations()
+                        .into_iter()
+                        .next()
+                    && let Ok(optimized) = strategy_from_params(
+                        &s.grid.strategy, 
+                        &params
+                    )
+                {
+                    periods.extend(optimized.timeframes());
+                };
 
-            viewer.render_lines();
-            print!("{}", viewer);
-            io::stdout().flush().ok();
-        
-        }
-        print!("\x1b[?25h");  // Show cursor
-    });
+                let timeframes: Timeframes = build_timeframes(
+                    &exchange, 
+                    &ticker, 
+                    &periods, 
+                    self.state.config.bars,
+                    self.database.get_pool()
+                )
+                    .await
+                    .map_err(RunTimeError::Bar)?;
 
-    update_database_tables(
-        &state.get_active_exchanges(),
-        state.time_offset(),
-        client,
-        db_pool,
-        prog_tx.clone(),
-        None,
-        None
-    )
-        .await
-        .map_err(|e| RunTimeError::DataBase(e))?;
+                let bars = BarSeries::new(
+                    exchange, 
+                    ticker.to_string(), 
+                    period, 
+                    BarType::Candle, 
+                    self.state.config.bars,
+                    self.database.get_pool() 
+                )
+                    .await
+                    .map_err(RunTimeError::Bar)?;
+
+                let cost_model: CostModel = self.cost_model(&ticker).await?;
+
+                if let Some(mut config) = walk_forward {
+
+                    let grid: ParamGrid = match &spec {
+                        Some(s) => {
+                            config.objective = s.objective;
+                            s.grid.clone()
+                        },
+                        None => ParamGrid::default_for(&name)
+                            .map_err(RunTimeError::Backtest)?
+                    };
+
+                    let report = run_walk_forward(
+                        &bars, 
+                        &timeframes,
+                        &grid, 
+                        config, 
+                        cost_model, 
+                        cash
+                    )
+                        .map_err(RunTimeError::Backtest)?;
+
+                    if json {
+                        print_json(&report);
+                    }
+                    else {
+                        println!("{}", report);
+                    };
+
+                    return Ok(Response::Ok)
+                };
 
-    Ok(())
+                if let Some(spec) = spec {
+
+                    let mut report = run_optimization(
+                        &bars, 
+                        &timeframes,
+                        &spec.grid, 
+                        spec.objective, 
+                        &cost_model, 
+                        cash
+                    )
+                        .map_err(RunTimeError::Backtest)?;
+
+                    if let Some(top) = spec.top {
+                        report.truncate(top);
+                    };
+
+                    if json {
+                        print_json(&report);
+                    }
+                    else {
+                        print!("{}", report);
+                    };
+
+                    return Ok(Response::Ok)
+                };
 
-}
+                let report = run_backtest(
+                    &bars, 
+                    &timeframes,
+                    strategy.as_mut(), 
+                    SimBroker::new(cost_model, cash)
+                )
+                    .map_err(RunTimeError::Backtest)?;
 
-/// Checks the integrity of database tables, to see if any tick data is missing
+                if json {
+                    match report.to_json() {
+                        Ok(s) => println!("{}", s),
+                        Err(e) => eprintln!("{}", e)
+                    };
+                }
+                else {
+                    println!("{}", report);
+                };
+
+                Ok(Response::Ok)
+            },
+
+            Command::Catalog { exchange, ticker, refresh } => {
+
+                let paths: SystemPaths = SystemPaths::new()
+                    .map_err(|e| RunTimeError::Init(
+                        InitializationError::Config(e)
+                    ))?;
+
+                let stored: Option<DataCatalog> = match refresh {
+                    true => None,
+                    false => DataCatalog::load(&paths.catalog)
+                        .map_err(RunTimeError::Catalog)?
+                };
+
+                let catalog: DataCatalog = match stored {
+                    Some(c) => c,
+                    None => refresh_catalog(self.database.get_pool())
+                        .await
+                        .map_err(RunTimeError::Catalog)?
+                };
+
+                let catalog = catalog.filtered(
+                    exchange.as_deref(), 
+                    ticker.as_deref()
+                );
+
+                if self.args.json {
+                    print_json(&catalog);
+                }
+                else {
+                    print!("{}", catalog);
+                };
+
+                Ok(Response::Ok)
+            },
+
+            Command::Verify { path } => {
+
+                // Bare file names are looked up in the export directory
+                let mut target: PathBuf = PathBuf::from(&path);
+                if !target.exists() 
+                    && let Ok(paths) = SystemPaths::new()
+                    && paths.candle_data.join(&path).exists()
+                {
+                    target = paths.candle_data.join(&path);
+                };
+
+                let results = verify_checksums(&target)
+                    .map_err(RunTimeError::Bar)?;
+
+                let mut failed: usize = 0;
+
+                for (file, status) in &results {
+                    let color: &str = match status {
+                        ChecksumStatus::Valid => "\x1b[32m",
+                        ChecksumStatus::MissingChecksum => "\x1b[33m",
+                        _ => {
+                            failed += 1;
+                            "\x1b[31m"
+                        }
+                    };
+                    println!(
+                        "{}{}\x1b[0m  {}", 
+                        color, 
+                        status, 
+                        file.display()
+                    );
+                };
+
+                if failed > 0 {
+                    return Err(RunTimeError::Bar(BarBuildError::Checksum(
+                        format!(
+                            "{} of {} files failed verification", 
+                            failed, 
+                            results.len()
+                        )
+                    )))
+                };
+
+                Ok(Response::Ok)
+            },
+
+            Command::ListAliases => {
+
+                let aliases = &self.state.config.aliases;
+
+                if aliases.is_empty() {
+                    println!(
+                        "No aliases yet, add one with `dtrade alias add NAME \
+                        COMMAND...`"
+                    );
+                };
+
+                let width: usize = aliases
+                    .keys()
+                    .map(|name| name.len())
+                    .max()
+                    .unwrap_or(0);
+
+                for (name, command) in aliases {
+                    println!(
+                        "  \x1b[1;36m{:<width$}\x1b[0m  dtrade {}", 
+                        name, 
+                        command
+                    );
+                };
+
+                Ok(Response::Ok)
+            },
+
+            Command::AddAlias { name, command } => {
+
+                self.state.config.aliases.insert(name.clone(), command.clone());
+                self.save_config()?;
+
+                println!(
+                    "\x1b[1;32mAdded alias {}\x1b[0m: dtrade {}", 
+                    name, 
+                    command
+                );
+
+                Ok(Response::Ok)
+            },
+
+            Command::RemoveAlias { name } => {
+
+                if self.state.config.aliases.remove(&name).is_none() {
+                    return Err(RunTimeError::Arguments(
+                        ParserError::UnknownArg(format!(
+                            "No alias named {}", name
+                        ))
+                    ))
+                };
+                self.save_config()?;
+
+                println!("\x1b[1;32mRemoved alias {}\x1b[0m", name);
+
+                Ok(Response::Ok)
+            },
+
+            Command::GetConfig { key } => {
+
+                let value = get_config_value(
+                    &self.state.config, 
+                    key.as_deref()
+                ).map_err(RunTimeError::Arguments)?;
+
+                // Text is printed as is, for scripts to read
+                match (&value, self.args.json) {
+                    (serde_json::Value::String(s), false) => println!("{}", s),
+                    _ => print_json(&value)
+                };
+
+                Ok(Response::Ok)
+            },
+
+            Command::SetConfig { key, value } => {
+
+                self.state.config = set_config_value(
+                    &self.state.config, 
+                    &key, 
+                    &value
+                ).map_err(RunTimeError::Arguments)?;
+                self.save_config()?;
+
+                let stored = get_config_value(
+                    &self.state.config, 
+                    Some(key.as_str())
+                ).map_err(RunTimeError::Arguments)?;
+
+                if self.args.json {
+                    print_json(&stored);
+                }
+                else {
+                    println!("\x1b[1;32mSet {}\x1b[0m to {}", key, stored);
+                };
+
+                Ok(Response::Ok)
+            },
+
+            Command::PaperStatus { account } => {
+
+                let summary = paper::load_account(
+                    &account, 
+                    &self.database.get_pool()
+                )
+                    .await
+                    .map_err(RunTimeError::Paper)?;
+
+                if self.args.json {
+                    print_json(&summary);
+                }
+                else if !matches!(self.op_mode, Server::HTTP) {
+                    print!("{}", summary);
+                };
+
+                Ok(Response::Data(DataResponse::PaperAccount(summary)))
+            },
+
+            Command::PaperOrder { account, order } => {
+
+                let db_pool: PgPool = self.database.get_pool();
+                let mut cost_models: CostModels = self.paper_cost_models();
+
+                let placed = paper::place_order(
+                    &account, 
+                    order, 
+                    &mut cost_models, 
+                    &db_pool
+                )
+                    .await
+                    .map_err(RunTimeError::Paper)?;
+
+                // Orders placed in the past fill on the stored ticks now
+                let fills = paper::sync(&account, &mut cost_models, &db_pool)
+                    .await
+                    .map_err(RunTimeError::Paper)?;
+
+                let order = fills
+                    .iter()
+                    .find(|o| o.id == placed.id)
+                    .cloned()
+                    .unwrap_or(placed);
+
+                if self.args.json {
+                    print_json(&order);
+                }
+                else if !matches!(self.op_mode, Server::HTTP) {
+
+                    // With its OCO stop or bracket exits, as they are now
+                    let placed: Vec<PaperOrder> = std::iter::once(
+                        order.clone()
+                    )
+                        .chain(paper::contingent_orders(
+                            &account, 
+                            order.id, 
+                            &db_pool
+                        )
+                            .await
+                            .map_err(RunTimeError::Paper)?)
+                        .collect();
+
+                    for o in &placed {
+                        println!("\x1b[1;32mPlaced order\x1b[0m {}", o);
+                    };
+                    print_paper_fills(
+                        &fills
+                            .into_iter()
+                            .filter(|f| placed.iter().all(|o| o.id != f.id))
+                            .collect::<Vec<PaperOrder>>()
+                    );
+                };
+
+                Ok(Response::Data(DataResponse::PaperOrder(Box::new(order))))
+            },
+
+            Command::PaperCancel { account, order_id } => {
+
+                let order = paper::cancel_order(
+                    &account, 
+                    order_id, 
+                    &self.database.get_pool()
+                )
+                    .await
+                    .map_err(RunTimeError::Paper)?;
+
+                if self.args.json {
+                    print_json(&order);
+                }
+                else if !matches!(self.op_mode, Server::HTTP) {
+                    println!("\x1b[1;32mCancelled order\x1b[0m {}", order);
+                };
+
+                Ok(Response::Data(DataResponse::PaperOrder(Box::new(order))))
+            },
+
+            Command::PaperModify { account, order_id, price } => {
+
+                let db_pool: PgPool = self.database.get_pool();
+                let mut cost_models: CostModels = self.paper_cost_models();
+
+                // Fills it at its old price on the ticks stored before now
+                let fills = paper::sync(&account, &mut cost_models, &db_pool)
+                    .await
+                    .map_err(RunTimeError::Paper)?;
+
+                let order = paper::modify_order(
+                    &account, 
+                    order_id, 
+                    price, 
+                    &mut cost_models, 
+                    &db_pool
+                )
+                    .await
+                    .map_err(RunTimeError::Paper)?;
+
+                if self.args.json {
+                    print_json(&order);
+                }
+                else if !matches!(self.op_mode, Server::HTTP) {
+                    print_paper_fills(&fills);
+                    println!("\x1b[1;32mModified order\x1b[0m {}", order);
+                };
+
+                Ok(Response::Data(DataResponse::PaperOrder(Box::new(order))))
+            },
+
+            Command::PaperHistory { account } => {
+
+                let orders = paper::order_history(
+                    &account, 
+                    &self.database.get_pool()
+                )
+                    .await
+                    .map_err(RunTimeError::Paper)?;
+
+                if self.args.json {
+                    print_json(&orders);
+                }
+                else if !matches!(self.op_mode, Server::HTTP) {
+                    if orders.is_empty() {
+                        println!(
+                            "No orders yet, place one with `dtrade paper buy \
+                            EXCHANGE TICKER VOLUME`"
+                        );
+                    };
+                    for order in &orders {
+                        println!("{}", order);
+                    };
+                };
+
+                Ok(Response::Data(DataResponse::PaperOrders(orders)))
+            },
+
+            Command::PaperSync { account } => {
+
+                let fills = paper::sync(
+                    &account, 
+                    &mut self.paper_cost_models(), 
+                    &self.database.get_pool()
+                )
+                    .await
+                    .map_err(RunTimeError::Paper)?;
+
+                if self.args.json {
+                    print_json(&fills);
+                }
+                else if !matches!(self.op_mode, Server::HTTP) {
+                    match fills.is_empty() {
+                        true => println!("No open orders reached yet"),
+                        false => print_paper_fills(&fills)
+                    };
+                };
+
+                Ok(Response::Data(DataResponse::PaperOrders(fills)))
+            },
+
+            Command::PaperReset { account, cash } => {
+
+                let db_pool: PgPool = self.database.get_pool();
+
+                paper::reset_account(&account, cash, &db_pool)
+                    .await
+                    .map_err(RunTimeError::Paper)?;
+
+                if self.args.json {
+                    let summary = paper::load_account(&account, &db_pool)
+                        .await
+                        .map_err(RunTimeError::Paper)?;
+                    print_json(&summary);
+                }
+                else {
+                    println!(
+                        "\x1b[1;32mReset paper account {}\x1b[0m with {:.2}",
+                        account,
+                        cash
+                    );
+                };
+
+                Ok(Response::Ok)
+            },
+
+            Command::PaperRebalance { 
+                account, 
+                exchange, 
+                targets, 
+                min_drift, 
+                confirmed 
+            } => {
+
+                let db_pool: PgPool = self.database.get_pool();
+                let mut cost_models: CostModels = self.paper_cost_models();
+                let verbose: bool = !self.args.json 
+                    && !matches!(self.op_mode, Server::HTTP);
+
+                // Fills since the last look change what's held
+                let fills = paper::sync(&account, &mut cost_models, &db_pool)
+                    .await
+                    .map_err(RunTimeError::Paper)?;
+
+                let targets: HashMap<String, f64> = targets
+                    .into_iter()
+                    .map(|(ticker, weight)| (ticker.to_string(), weight))
+                    .collect();
+
+                let orders = paper::rebalance_orders(
+                    &account, 
+                    &exchange, 
+                    &targets, 
+                    min_drift, 
+                    &self.request_client, 
+                    &db_pool
+                )
+                    .await
+                    .map_err(RunTimeError::Paper)?;
+
+                if verbose {
+                    print_paper_fills(&fills);
+                    match orders.is_empty() {
+                        true => println!(
+                            "Paper account {} is already on target", 
+                            account
+                        ),
+                        false => {
+                            println!(
+                                "\x1b[1;36mRebalancing {}\x1b[0m", 
+                                account
+                            );
+                            for order in &orders {
+                                println!("  {}", order);
+                            };
+                        }
+                    };
+                };
+
+                let place: bool = !orders.is_empty() && (confirmed 
+                    || (verbose 
+                        && prompt::is_interactive() 
+                        && prompt::confirm("Place these orders?")));
+
+                if !place {
+                    if self.args.json {
+                        print_json(&orders);
+                    }
+                    else if verbose && !orders.is_empty() {
+                        println!("Nothing placed, add --yes to place them");
+                    };
+                    return Ok(Response::Ok)
+                };
+
+                // Sells go first, but fill on later ticks, so buys waiting
+                // on their proceeds are refused until the next run
+                let mut placed: Vec<PaperOrder> = Vec::new();
+                for order in orders {
+                    let new_order = NewOrder {
+                        exchange: exchange.clone(),
+                        ticker: order.ticker
+                            .parse::<Ticker>()
+                            .map_err(|e| RunTimeError::Paper(
+                                PaperError::OrderRejected(e.to_string())
+                            ))?,
+                        side: order.side,
+                        volume: order.volume,
+                        limit: None,
+                        stop: None,
+                        at: None,
+                        contingent: Contingent::None
+                    };
+                    match paper::place_order(
+                        &account, 
+                        new_order, 
+                        &mut cost_models, 
+                        &db_pool
+                    ).await {
+                        Ok(p) => placed.push(p),
+                        Err(e) if verbose => {
+                            eprintln!("\x1b[1;31m{}: {}\x1b[0m", order, e)
+                        },
+                        Err(_) => {}
+                    };
+                };
+
+                if self.args.json {
+                    print_json(&placed);
+                }
+                else if verbose {
+                    for order in &placed {
+                        println!("\x1b[1;32mPlaced order\x1b[0m {}", order);
+                    };
+                };
+
+                Ok(Response::Data(DataResponse::PaperOrders(placed)))
+            },
+
+            Command::AuditLog { action, limit } => {
+
+                let entries = audit_log(
+                    self.database.get_pool(), 
+                    action.as_deref(), 
+                    limit
+                )
+                    .await
+                    .map_err(RunTimeError::DataBase)?;
+
+                if self.args.json {
+                    print_json(&entries);
+                }
+                else if entries.is_empty() {
+                    println!("No commands recorded yet");
+                }
+                else {
+                    println!("\x1b[1;36mAudit log (newest first):\x1b[0m");
+                    for entry in &entries {
+                        println!("{}", entry);
+                    };
+                };
+
+                Ok(Response::Ok)
+            },
+
+            Command::LiveBalance => {
+
+                let balances = live::balances(
+                    &self.state.config.live, 
+                    &self.request_client
+                )
+                    .await
+                    .map_err(RunTimeError::Live)?;
+
+                if self.args.json {
+                    print_json(&balances);
+                }
+                else if !matches!(self.op_mode, Server::HTTP) {
+                    if balances.is_empty() {
+                        println!("The account holds nothing");
+                    };
+                    for (asset, amount) in &balances {
+                        println!("{:<10} {}", asset, amount);
+                    };
+                };
+
+                Ok(Response::Data(DataResponse::LiveBalances(balances)))
+            },
+
+            Command::LiveOpenOrders => {
+
+                let orders = live::open_orders(
+                    &self.state.config.live, 
+                    &self.request_client
+                )
+                    .await
+                    .map_err(RunTimeError::Live)?;
+
+                if self.args.json {
+                    print_json(&orders);
+                }
+                else if !matches!(self.op_mode, Server::HTTP) {
+                    if orders.is_empty() {
+                        println!("No open orders");
+                    };
+                    for order in &orders {
+                        println!("{}", order);
+                    };
+                };
+
+                Ok(Response::Data(DataResponse::LiveOrders(orders)))
+            },
+
+            Command::LiveOrder { order } => {
+
+                let placed = live::place_order(
+                    &self.state.config.live, 
+                    &order, 
+                    &self.request_client
+                )
+                    .await
+                    .map_err(RunTimeError::Live)?;
+
+                if self.args.json {
+                    print_json(&placed);
+                }
+                else if !matches!(self.op_mode, Server::HTTP) {
+                    let verb: &str = match order.validate {
+                        true => "Validated order",
+                        false => "Placed order"
+                    };
+                    println!("\x1b[1;32m{}\x1b[0m {}", verb, placed);
+                };
+
+                Ok(Response::Data(DataResponse::LiveOrder(placed)))
+            },
+
+            Command::LiveCancel { txid } => {
+
+                live::cancel_order(
+                    &self.state.config.live, 
+                    &txid, 
+                    &self.request_client
+                )
+                    .await
+                    .map_err(RunTimeError::Live)?;
+
+                if self.args.json {
+                    print_json(&serde_json::json!({ "cancelled": txid }));
+                }
+                else if !matches!(self.op_mode, Server::HTTP) {
+                    println!("\x1b[1;32mCancelled order\x1b[0m {}", txid);
+                };
+
+                Ok(Response::Ok)
+            },
+
+            Command::LiveModify { txid, price } => {
+
+                let modified = live::modify_order(
+                    &self.state.config.live, 
+                    &txid, 
+                    price, 
+                    &self.request_client
+                )
+                    .await
+                    .map_err(RunTimeError::Live)?;
+
+                if self.args.json {
+                    print_json(&modified);
+                }
+                else if !matches!(self.op_mode, Server::HTTP) {
+                    println!("\x1b[1;32mModified order\x1b[0m {}", modified);
+                };
+
+                Ok(Response::Data(DataResponse::LiveOrder(modified)))
+            },
+
+            Command::RecurringAdd { schedule } => {
+
+                let schedule = recurring::add_schedule(
+                    &schedule,
+                    &self.database.get_pool()
+                )
+                    .await
+                    .map_err(RunTimeError::Recurring)?;
+
+                if self.args.json {
+                    print_json(&schedule);
+                }
+                else if !matches!(self.op_mode, Server::HTTP) {
+                    println!(
+                        "\x1b[1;32mScheduled recurring buy\x1b[0m {}",
+                        schedule
+                    );
+                    println!(
+                        "Buys run while `dtrade start --http` does, or on \
+                        `dtrade recurring run`"
+                    );
+                };
+
+                Ok(Response::Ok)
+            },
+
+            Command::RecurringList => {
+
+                let schedules = recurring::schedules(&self.database.get_pool())
+                    .await
+                    .map_err(RunTimeError::Recurring)?;
+
+                if self.args.json {
+                    print_json(&schedules);
+                }
+                else if !matches!(self.op_mode, Server::HTTP) {
+                    if schedules.is_empty() {
+                        println!(
+                            "No recurring buys, add one with `dtrade \
+                            recurring add EXCHANGE TICKER AMOUNT --every 1d`"
+                        );
+                    };
+                    for schedule in &schedules {
+                        println!("{}", schedule);
+                    };
+                };
+
+                Ok(Response::Ok)
+            },
+
+            Command::RecurringRemove { id } => {
+
+                let schedule = recurring::remove_schedule(
+                    id,
+                    &self.database.get_pool()
+                )
+                    .await
+                    .map_err(RunTimeError::Recurring)?;
+
+                if self.args.json {
+                    print_json(&schedule);
+                }
+                else if !matches!(self.op_mode, Server::HTTP) {
+                    println!(
+                        "\x1b[1;32mRemoved recurring buy\x1b[0m {}",
+                        schedule
+                    );
+                };
+
+                Ok(Response::Ok)
+            },
+
+            Command::RecurringHistory { id } => {
+
+                let history = recurring::history(
+                    id,
+                    &self.state.config.live,
+                    &mut self.paper_cost_models(),
+                    &self.request_client,
+                    &self.database.get_pool()
+                )
+                    .await
+                    .map_err(RunTimeError::Recurring)?;
+
+                if self.args.json {
+                    print_json(&history);
+                }
+                else if !matches!(self.op_mode, Server::HTTP) {
+                    println!("{}", history);
+                };
+
+                Ok(Response::Ok)
+            },
+
+            Command::RecurringRun => {
+
+                let runs = recurring::run_due(
+                    &self.state.config.live,
+                    &mut self.paper_cost_models(),
+                    &self.request_client,
+                    &self.database.get_pool()
+                )
+                    .await
+                    .map_err(RunTimeError::Recurring)?;
+
+                if self.args.json {
+                    print_json(&runs);
+                }
+                else if !matches!(self.op_mode, Server::HTTP) {
+                    if runs.is_empty() {
+                        println!("No recurring buys due");
+                    };
+                    for run in &runs {
+                        println!("{}", run);
+                    };
+                };
+
+                Ok(Response::Ok)
+            },
+
+            Command::ExportFills { format, out, from, to } => {
+
+                let fills = blotter::collect_fills(
+                    from, 
+                    to, 
+                    &self.state.config.live, 
+                    &self.request_client, 
+                    &self.database.get_pool()
+                ).await?;
+
+                // Without --format, the file's extension decides
+                let format: ExportFormat = format
+                    .or_else(|| out
+                        .as_deref()
+                        .and_then(|o| Path::new(o).extension())
+                        .and_then(|e| e.to_str())
+                        .and_then(|e| e.parse().ok())
+                    )
+                    .unwrap_or(ExportFormat::Csv);
+
+                let path: PathBuf = PathBuf::from(
+                    out.unwrap_or_else(|| blotter::default_file_name(format))
+                );
+
+                blotter::write_fills(
+                    &path, 
+                    format, 
+                    &fills, 
+                    &self.state.config.locale, 
+                    &self.state.config.csv_dialect
+                )?;
+
+                let realized: f64 = fills
+                    .iter()
+                    .fold(0.0, |sum, f| sum + f.realized_pnl);
+
+                if self.args.json {
+                    print_json(&serde_json::json!({
+                        "path": path,
+                        "fills": fills.len(),
+                        "realized_pnl": realized,
+                    }));
+                }
+                else if !matches!(self.op_mode, Server::HTTP) {
+                    println!(
+                        "\x1b[1;32mExported {} fills\x1b[0m to {}, realized \
+                        P&L {:.2}",
+                        fills.len(),
+                        path.display(),
+                        realized
+                    );
+                };
+
+                Ok(Response::Ok)
+            },
+
+            Command::Help => {
+                println!("{}", HELP_STRING);
+                Ok(Response::Ok)
+            }
+        }    
+    }
+
+    /// Counts a run of `feature` in the usage stats, when they're turned on
+    pub fn record_usage(
+        &self, 
+        feature: &str, 
+        result: &Result<Response, RunTimeError>
+    ) {
+        telemetry::record(
+            &self.state.config.telemetry, 
+            &self.state.paths, 
+            feature, 
+            result.as_ref().err()
+        );
+    }
+
+    /// Adds a command that changed something to the audit log, warning when
+    /// it can't be
+    pub async fn record_audit(
+        &self,
+        entry: &Audited,
+        origin: &AuditOrigin,
+        result: &Result<Response, RunTimeError>
+    ) {
+        let error: Option<String> = result
+            .as_ref()
+            .err()
+            .map(|e| e.to_string());
+
+        if let Err(e) = entry
+            .record(origin, error.as_deref(), self.database.get_pool())
+            .await
+        {
+            eprintln!("\x1b[33m{}\x1b[0m", e);
+        };
+    }
+
+    /// Builds the candles of each `Command::CandleBuilder` in `cmds`, then
+    /// saves each series as its command asks
+    ///
+    /// Every pair's ticks are read once for all the periods built from 
+    /// them, see `build_candle_batch`. Returns the last series.
+    async fn run_candle_builds(&self, cmds: Vec<Command>) 
+        -> Result<Response, RunTimeError> {
+
+        let mut requests: Vec<CandleRequest> = Vec::new();
+        let mut outputs: Vec<CandleOutput> = Vec::new();
+
+        for cmd in cmds {
+
+            let Command::CandleBuilder { 
+                exchange, 
+                ticker, 
+                period, 
+                integrity_check, 
+                fill_gaps, 
+                listing_anchor,
+                export, 
+                out,
+                export_ticks,
+                csv_flags,
+                from,
+                to
+            } = cmd else {
+                continue
+            };
+
+            preflight_candles(
+                &exchange, 
+                &ticker, 
+                &period, 
+                self.database.get_pool()
+            ).await?;
+
+            let mut options: BarOptions = self.state.config.bars;
+            options.listing_anchor |= listing_anchor;
+
+            outputs.push(CandleOutput {
+                exchange: exchange.clone(),
+                ticker: ticker.clone(),
+                period: period.clone(),
+                integrity_check,
+                fill_gaps,
+                export,
+                out,
+                export_ticks,
+                csv_flags
+            });
+            requests.push(CandleRequest {
+                exchange,
+                ticker: ticker.into(),
+                period,
+                options,
+                range: (
+                    from.map(|t| t.saturating_mul(1_000_000)), 
+                    to.map(|t| t.saturating_mul(1_000_000))
+                )
+            });
+        };
+
+        // Series built from the same ticks hold a copy each
+        let mut builds: HashMap<(String, String, _), u64> = HashMap::new();
+        for request in &requests {
+            *builds.entry((
+                request.exchange.to_lowercase(), 
+                request.ticker.clone(), 
+                request.range
+            )).or_default() += 1;
+        };
+
+        for ((exchange, ticker, range), n) in builds {
+            preflight_memory(
+                &exchange, 
+                &ticker, 
+                range, 
+                n,
+                self.state.config.bars.memory_budget_mb, 
+                self.database.get_pool()
+            ).await?;
+        };
+
+        let series: Vec<BarSeries> = build_candle_batch(
+            requests, 
+            self.database.get_pool()
+        )
+            .await
+            .map_err(RunTimeError::Bar)?;
+
+        let mut response: Response = Response::Ok;
+
+        // With --json, messages are left out and the series are summed up
+        // at the end, unless one was written to standard output
+        let json: bool = self.args.json;
+        let mut summaries: Vec<CandleSummary> = Vec::new();
+        let mut to_stdout: bool = false;
+
+        for (output, mut bars) in outputs.into_iter().zip(series) {
+
+            let mut saved_to: Option<String> = None;
+            let mut ticks_saved_to: Option<String> = None;
+
+            if output.fill_gaps {
+                bars.fill_gaps().map_err(RunTimeError::Bar)?;
+            };
+
+            if output.integrity_check {
+                let is_ok: bool = bars.bar_integrity_check();
+                if !is_ok {
+                    return Err(RunTimeError::Bar(
+                        BarBuildError::IntegrityCorruption
+                    )) 
+                }; 
+            };
+
+            let dialect: CsvDialect = output.csv_flags.apply(
+                &self.state.config.csv_dialect
+            );
+            let locale: &Locale = &self.state.config.locale;
+
+            match (output.out, output.export) {
+                (Some(out), format) => {
+
+                    // Without --format, the file's extension decides
+                    let format: ExportFormat = format
+                        .or_else(|| Path::new(&out)
+                            .extension()
+                            .and_then(|e| e.to_str())
+                            .and_then(|e| e.parse().ok())
+                        )
+                        .unwrap_or(ExportFormat::Csv);
+
+                    if out == "-" {
+                        let text: String = bars
+                            .to_text(format, locale, &dialect)
+                            .map_err(RunTimeError::Bar)?;
+                        println!("{}", text);
+                        to_stdout = true;
+                    }
+                    else {
+                        bars.export_to(
+                            Path::new(&out), 
+                            format, 
+                            locale, 
+                            &dialect
+                        )
+                            .map_err(RunTimeError::Bar)?;
+                        if !json {
+                            println!(
+                                "\x1b[1;32mSaved data to {}\x1b[0m", 
+                                out
+                            );
+                        };
+                        saved_to = Some(out);
+                    };
+                },
+                (None, Some(format)) => {
+                    let path = bars
+                        .export(
+                            &self.state.paths.candle_data, 
+                            format, 
+                            locale,
+                            &dialect
+                        )
+                        .map_err(RunTimeError::Bar)?;
+                    if !json {
+                        println!(
+                            "\x1b[1;32mSaved data to {}\x1b[0m", 
+                            path.display()
+                        );
+                    };
+                    saved_to = Some(path.display().to_string());
+                },
+                (None, None) => {
+                    if !output.export_ticks 
+                        && !json
+                        && !matches!(self.op_mode, Server::HTTP) 
+                    {
+                        eprintln!(
+                            "Built {} {} {} candles, save them with --out \
+                            PATH or --format FORMAT",
+                            bars.len(),
+                            output.ticker,
+                            output.period
+                        );
+                    };
+                }
+            };
+
+            if output.export_ticks {
+                let path = bars
+                    .export_ticks(&self.state.paths.candle_data)
+                    .map_err(RunTimeError::Bar)?;
+                if !json {
+                    println!(
+                        "\x1b[1;32mSaved ticks to {}\x1b[0m", 
+                        path.display()
+                    );
+                };
+                ticks_saved_to = Some(path.display().to_string());
+            };
+
+            if json {
+                summaries.push(CandleSummary {
+                    exchange: output.exchange,
+                    ticker: output.ticker.to_string(),
+                    period: output.period,
+                    bars: bars.len(),
+                    first_open: bars.bars
+                        .first()
+                        .map(|b| b.open_date().timestamp()),
+                    last_close: bars.bars
+                        .last()
+                        .map(|b| b.close_date().timestamp()),
+                    saved_to,
+                    ticks_saved_to,
+                });
+            };
+
+            response = Response::Data(DataResponse::Bars(bars));
+        };
+
+        if json && !to_stdout {
+            print_json(&summaries);
+        };
+
+        Ok(response)
+    }
+
+    /// Writes the config back to config.json, after a command changed it
+    fn save_config(&self) -> Result<(), RunTimeError> {
+        save_config(&self.state.config, &self.state.paths)
+            .map_err(|e| RunTimeError::Init(InitializationError::Config(e)))
+    }
+
+    /// Rejects built-in strategies that are switched off in the config
+    fn check_strategy_enabled(&self, name: &str) -> Result<(), RunTimeError> {
+        if name == "inside-bar" && !self.state.config.backtesting.inside_bar {
+            return Err(RunTimeError::Backtest(BacktestError::InvalidStrategy(
+                "inside-bar is disabled in settings (Inside Bar Testing)"
+                    .to_string()
+            )))
+        };
+        Ok(())
+    }
+
+    /// Trading costs for backtesting `ticker`, warning when the exchange's
+    /// fees and order minimums couldn't be fetched
+    async fn cost_model(&self, ticker: &str) 
+        -> Result<CostModel, RunTimeError> {
+
+        let (cost_model, fetch_error) = backtest_cost_model(
+            ticker, 
+            self.state.config.backtesting.slippage_bps,
+            &self.request_client
+        )
+            .await
+            .map_err(RunTimeError::Backtest)?;
+
+        if let Some(e) = fetch_error {
+            eprintln!(
+                "\x1b[33mCouldn't fetch pair info ({}), \
+                backtesting without fees or order minimums\x1b[0m",
+                e
+            );
+        };
+
+        Ok(cost_model)
+    }
+
+    /// Cost models for paper trading, with the backtests' slippage
+    fn paper_cost_models(&self) -> CostModels {
+        CostModels::new(
+            self.state.config.backtesting.slippage_bps, 
+            self.request_client.clone()
+        )
+    }
+
+    /// Asks on the terminal for the arguments `partial` is missing, offering
+    /// the stored pairs for `candles` and the exchange's pairs for
+    /// `--add-pairs`
+    async fn complete_partial(&self, partial: PartialCommand)
+        -> Result<Vec<Command>, RunTimeError> {
+
+        let missing = |what: &str| RunTimeError::Arguments(
+            ParserError::MissingArgs(format!("No {} given", what))
+        );
+
+        match partial {
+
+            PartialCommand::Candles { exchange, tickers } => {
+
+                let pairs: HashMap<String, Vec<String>> = self.pairs
+                    .load(self.database.get_pool())
+                    .await;
+
+                if pairs.is_empty() {
+                    return Err(RunTimeError::Arguments(
+                        ParserError::MissingArgs(
+                            "No pairs stored yet, add one with `dtrade \
+                            database --add-pairs EXCHANGE TICKER`"
+                                .to_string()
+                        )
+                    ))
+                };
+
+                let exchange: String = match exchange {
+                    Some(e) => e,
+                    None => {
+                        let mut exchanges: Vec<String> = pairs
+                            .keys()
+                            .cloned()
+                            .collect();
+                        exchanges.sort();
+                        prompt::select("Exchange", &exchanges)
+                            .ok_or_else(|| missing("exchange"))?
+                    }
+                }.to_lowercase();
+
+                let tickers: Vec<Ticker> = match tickers.is_empty() {
+                    false => tickers,
+                    true => {
+                        let mut tickers: Vec<String> = pairs
+                            .iter()
+                            .find(|(e, _)| e.eq_ignore_ascii_case(&exchange))
+                            .map(|(_, t)| t.clone())
+                            .unwrap_or_default();
+                        tickers.sort();
+                        vec![
+                            prompt::select("Ticker", &tickers)
+                                .and_then(|t| t.parse::<Ticker>().ok())
+                                .ok_or_else(|| missing("ticker"))?
+                        ]
+                    }
+                };
+
+                let period: String = prompt::ask(
+                    "Period (e.g. 15m, 4h, 1d or 500t)",
+                    |p| match period_is_valid(p) {
+                        true => Ok(p.to_string()),
+                        false => Err(format!("{} isn't a period", p))
+                    }
+                )
+                    .ok_or_else(|| missing("period"))?;
+
+                Ok(tickers.into_iter().map(|ticker| Command::CandleBuilder {
+                    exchange: exchange.clone(),
+                    ticker,
+                    period: period.clone(),
+                    integrity_check: false,
+                    fill_gaps: false,
+                    listing_anchor: false,
+                    export: None,
+                    out: None,
+                    export_ticks: false,
+                    csv_flags: CsvFlags::default(),
+                    from: None,
+                    to: None
+                }).collect())
+            },
+
+            PartialCommand::AddPairs { exchange } => {
+
+                let exchange: String = match exchange {
+                    Some(e) => e,
+                    None => prompt::select(
+                        "Exchange",
+                        &self.state.get_active_exchanges()
+                    )
+                        .ok_or_else(|| missing("exchange"))?
+                }.to_lowercase();
+
+                let stored: Vec<String> = self.pairs
+                    .load(self.database.get_pool())
+                    .await
+                    .into_iter()
+                    .filter(|(e, _)| e.eq_ignore_ascii_case(&exchange))
+                    .flat_map(|(_, t)| t)
+                    .collect();
+
+                let mut available: Vec<String> = match &exchange[..] {
+                    "kraken" => kraken::request_all_assets_from_kraken(
+                        &self.request_client
+                    )
+                        .await
+                        .map_err(|e| RunTimeError::DataBase(
+                            DbError::QueryFailed(format!(
+                                "Couldn't list Kraken's pairs: {}", e
+                            ))
+                        ))?
+                        .into_keys()
+                        .filter(|t| !stored.contains(t))
+                        .collect(),
+                    _ => Vec::new()
+                };
+
+                let mut commands: Vec<Command> = Vec::new();
+
+                println!("Pick pairs to add, an empty answer when done");
+                while let Some(choice) = prompt::select("Ticker", &available) {
+                    available.retain(|t| *t != choice);
+                    if let Ok(ticker) = choice.parse::<Ticker>() {
+                        commands.push(Command::AddPair {
+                            exchange: exchange.clone(),
+                            ticker
+                        });
+                    };
+                };
+
+                match commands.is_empty() {
+                    true => Err(missing("ticker")),
+                    false => Ok(commands)
+                }
+            },
+        }
+    }
+
+    /// Where to send download events for the message buses in 
+    /// `AppConfig::integrations`, see `bus_sink`. The sinks start on the
+    /// first call, and `None` means no bus is configured.
+    pub async fn start_bus_feed(&mut self) 
+        -> Option<UnboundedSender<DataDownloadStatus>> 
+    {
+        if self.bus_feed.is_none() {
+            self.bus_feed = spawn_bus_sinks(
+                &self.state.config.integrations, 
+                self.database.get_pool()
+            ).await;
+        };
+        self.bus_feed.clone()
+    }
+
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.args = parse_args(Some(args), &self.state.config.aliases);
+    }
+}
+
+
+/// Updates all database tables. Emits progress messages to the terminal
+/// in real time, and to `status_feed` and `bus_feed` when given.
+///
+/// Given `pairs` as `(exchange, ticker)`, only those are updated, one after
+/// the other. Once every download has ended, a summary of the run is 
+/// printed, see `jobs::UpdateSummary`.
+///
+/// Past the backfill limit in `AppConfig::timeouts`, the downloads are 
+/// stopped after their current batch, as with Ctrl-C, and 
+/// `RunTimeError::Timeout` is returned.
+pub async fn run_database_table_updates(
+    state: &AppState,
+    client: &reqwest::Client,
+    db_pool: PgPool,
+    status_feed: Option<broadcast::Sender<DataDownloadStatus>>,
+    bus_feed: Option<UnboundedSender<DataDownloadStatus>>,
+    pairs: Vec<(String, String)>,
+) -> Result<(), RunTimeError> {
+
+    // Progress listener
+    let (prog_tx, mut prog_rx) = unbounded_channel::<DataDownloadStatus>();
+
+    let viewer_task = tokio::spawn(async move {
+        let mut viewer = DownloadStatusViewer::new();
+        
+        print!("\x1b[?25l");  // Hide cursor
+        while let Some(event) = prog_rx.recv().await {
+
+            // Only fails when nobody is listening
+            if let Some(feed) = &status_feed {
+                let _ = feed.send(event.clone());
+            };
+            if let Some(feed) = &bus_feed {
+                let _ = feed.send(event.clone());
+            };
+            
+            viewer.update_status(event);
+          
+            // Move cursor to top
+            if viewer.last_rendered_lines > 0 {
+                print!("\x1b[{}A", viewer.last_rendered_lines);
+            };
+
+            // Clear old lines
+            for _ in 0..viewer.last_rendered_lines {
+                print!("\r\x1b[2K\n");
+            };
+
+            // Move cursor to top, again
+            if viewer.last_rendered_lines > 0 {
+                print!("\x1b[{}A", viewer.last_rendered_lines);
+            };
+
+            viewer.render_lines();
+            print!("{}", viewer);
+            io::stdout().flush().ok();
+        
+        }
+        print!("\x1b[?25h");  // Show cursor
+    });
+
+    // Ctrl-C asks the downloads to stop after their current batch. A second
+    // Ctrl-C exits straight away.
+    let cancel: CancellationToken = CancellationToken::new();
+    let signal_cancel: CancellationToken = cancel.clone();
+
+    let signal_listener = tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            signal_cancel.cancel();
+            eprintln!(
+                "\r\x1b[2K\x1b[33mStopping after the current batch \
+                (Ctrl-C again to quit now)...\x1b[0m"
+            );
+            if tokio::signal::ctrl_c().await.is_ok() {
+                print!("\x1b[?25h");
+                std::process::exit(130);
+            };
+        };
+    });
+
+    let limit: Option<Duration> = state.config.timeouts.limit(JobKind::Backfill);
+    let timer_cancel: CancellationToken = cancel.clone();
+
+    let timer = tokio::spawn(async move {
+        let Some(limit) = limit else { return false };
+        tokio::time::sleep(limit).await;
+        eprintln!(
+            "\r\x1b[2K\x1b[33mBackfill time limit reached, stopping after \
+            the current batch...\x1b[0m"
+        );
+        timer_cancel.cancel();
+        true
+    });
+
+    let targets: Vec<(Option<&str>, Option<&str>)> = match pairs.is_empty() {
+        true => vec![(None, None)],
+        false => pairs
+            .iter()
+            .map(|(e, t)| (Some(&e[..]), Some(&t[..])))
+            .collect()
+    };
+
+    let control: DownloadControl = state.config.data_download
+        .download_control(cancel.clone());
+    let mut result: Result<(), DbError> = Ok(());
+
+    for pair in targets {
+
+        if cancel.is_cancelled() {
+            break
+        };
+
+        let outcome = update_database_tables(
+            &state.get_active_exchanges(),
+            state.time_offset(),
+            client,
+            db_pool.clone(),
+            prog_tx.clone(),
+            pair,
+            control.clone()
+        ).await;
+
+        if let Err(e) = outcome 
+            && matches!(result, Ok(()) | Err(DbError::Cancelled)) 
+        {
+            result = Err(e);
+        };
+    };
+    drop(prog_tx);
+
+    signal_listener.abort();
+    timer.abort();
+    let timed_out: bool = timer.await.unwrap_or(false);
+    let _ = viewer_task.await;
+
+    match jobs::job_summary(control.job_id, db_pool.clone()).await {
+        Ok(summary) => print!("\n{}", summary),
+        Err(e) => eprintln!(
+            "\x1b[33mCouldn't summarise the update: {}\x1b[0m", 
+            e
+        )
+    };
+
+    if cancel.is_cancelled() {
+        print_resume_summary(db_pool).await;
+    };
+
+    if timed_out && let Some(limit) = limit {
+        return Err(RunTimeError::Timeout { job: JobKind::Backfill, limit })
+    };
+
+    result.map_err(RunTimeError::DataBase)
+
+}
+
+/// Prints where each pair's download will pick up on the next update
+async fn print_resume_summary(db_pool: PgPool) {
+
+    println!("\n\x1b[1;36mDownloads stopped. Resumable state:\x1b[0m");
+
+    let pairs: HashMap<String, Vec<(String, Option<PairStats>)>> = 
+        fetch_exchanges_and_pairs_from_db(db_pool.clone(), false).await;
+
+    for (exchange, tickers) in pairs {
+
+        if exchange.to_lowercase() != "kraken" {
+            continue
+        };
+
+        for (ticker, _) in tickers {
+            match resume_from_checkpoint::<KrakenCursor>(
+                &ticker, 
+                db_pool.clone()
+            )
+                .await 
+            {
+                Ok(checkpoint) => println!(
+                    "  \x1b[33m{} {}\x1b[0m: next tick {}, from {}",
+                    exchange,
+                    ticker,
+                    checkpoint.next_tick_id,
+                    checkpoint.cursor.to_date_string()
+                ),
+                Err(e) => println!(
+                    "  \x1b[33m{} {}\x1b[0m: \x1b[31m{}\x1b[0m", 
+                    exchange, 
+                    ticker, 
+                    e
+                )
+            };
+        };
+    };
+}
+
+/// Checks the integrity of database tables, to see if any tick data is missing
+///
+/// When `repair` is set, missing ticks are re-downloaded and the table is 
+/// checked a second time so the report reflects the repaired state. Each 
+/// repair is printed as it finishes when `verbose` is set.
 async fn db_integrity_check(
     exchange: &str, 
     ticker: &str, 
+    repair: bool,
+    verbose: bool,
+    client: &reqwest::Client,
     db_pool: PgPool
-) -> String {
+) -> Vec<DatabaseIntegrity> {
   
-    let tables: Vec<String> = match fetch_tables(db_pool.clone()).await {
-        Ok(d) => d,
-        Err(_) => Vec::new()
-    };
+    let tables: Vec<String> = fetch_tables(db_pool.clone())
+        .await
+        .unwrap_or_default();
 
     let mut tables_to_check: HashMap<String, Vec<String>> = HashMap::new();
 
     if exchange != "all" {
         tables_to_check.entry(exchange.to_string())
-            .or_insert(Vec::new());
+            .or_default();
     };
 
     if ticker != "all" {
         tables_to_check.entry(exchange.to_string())
-            .or_insert(Vec::new())
+            .or_default()
             .push(ticker.to_lowercase());
     };
 
@@ -408,25 +2931,56 @@ async fn db_integrity_check(
         
         if exchange == "all" { 
             tables_to_check.entry(ex.to_string())
-                .or_insert(Vec::new());
+                .or_default();
         };
 
         if ticker == "all" { 
              tables_to_check.entry(ex.to_string())
-                .or_insert(Vec::new())
+                .or_default()
                 .push(t.to_string());
         };
     
     };
 
-    let mut integrity = String::new();
+    let mut integrity: Vec<DatabaseIntegrity> = Vec::new();
     
     for (exc, pairs) in tables_to_check {
         for pair in pairs {
-            let check = database_ops::integrity_check(
+            let mut check = database_ops::integrity_check(
                 &exc, &pair, db_pool.clone(), None 
             ).await;
-            integrity.push_str(&format!("{}\n", check));
+
+            if repair && !check.missing_ticks.is_empty() {
+                
+                let repaired = repair_missing_ticks(
+                    &exc, 
+                    &pair, 
+                    &check.missing_ticks, 
+                    client, 
+                    db_pool.clone()
+                ).await;
+
+                if verbose {
+                    match repaired {
+                        Ok(n) => println!(
+                            "\x1b[1;32mRepaired {} ticks in {}\x1b[0m", 
+                            n, 
+                            check.table_name
+                        ),
+                        Err(e) => println!(
+                            "\x1b[1;31mRepair failed for {}: {}\x1b[0m", 
+                            check.table_name, 
+                            e
+                        )
+                    };
+                };
+
+                check = database_ops::integrity_check(
+                    &exc, &pair, db_pool.clone(), None 
+                ).await;
+            };
+
+            integrity.push(check);
         }; 
     };
 
@@ -1,6 +1,13 @@
-pub use database_ops::DbError;
+use std::time::Duration;
+
+pub use database_ops::{DbError, ExchangeError};
 pub use bars::BarBuildError;
+pub use backtest::BacktestError;
 pub use crate::arg_parsing::{ParserError};
+use crate::{
+    app_state::{JobKind, KRAKEN_API_KEY_ENV, KRAKEN_API_SECRET_ENV},
+    telemetry,
+};
 
 
 #[derive(Debug)]
@@ -9,6 +16,19 @@ pub enum RunTimeError {
     Init(InitializationError),
     Bar(BarBuildError),
     Arguments(ParserError),
+    Backtest(BacktestError),
+    Catalog(CatalogError),
+    Preflight(PreflightError),
+    Paper(PaperError),
+    Live(LiveError),
+    /// A file, e.g. the fills export, couldn't be written
+    Export(String),
+    Recurring(RecurringError),
+    /// A job ran past its limit in `AppConfig::timeouts` and was cancelled
+    Timeout {
+        job: JobKind,
+        limit: Duration,
+    },
 }
 
 impl std::fmt::Display for RunTimeError {
@@ -18,6 +38,22 @@ impl std::fmt::Display for RunTimeError {
             RunTimeError::Init(e) => write!(f, "{}", e),
             RunTimeError::Bar(e) => write!(f, "{}", e),
             RunTimeError::Arguments(e) => write!(f, "{}", e),
+            RunTimeError::Backtest(e) => write!(f, "{}", e),
+            RunTimeError::Catalog(e) => write!(f, "{}", e),
+            RunTimeError::Preflight(e) => write!(f, "{}", e),
+            RunTimeError::Paper(e) => write!(f, "{}", e),
+            RunTimeError::Live(e) => write!(f, "{}", e),
+            RunTimeError::Export(e) => {
+                write!(f, "RunTimeError::Export: {}", e)
+            },
+            RunTimeError::Recurring(e) => write!(f, "{}", e),
+            RunTimeError::Timeout { job, limit } => write!(
+                f, 
+                "RunTimeError::Timeout: {} exceeded its {} minute limit and \
+                was cancelled", 
+                job, 
+                limit.as_secs() / 60
+            ),
         }
     }
 }
@@ -27,11 +63,25 @@ pub fn error_handler(err: RunTimeError) {
     eprintln!("\x1b[1;31m{}\x1b[0m", err) 
 }
 
+/// Prints `err` as JSON on standard output, for `--json`, e.g.
+/// `{"error": {"kind": "database", "message": "...", "exit_code": 4}}`
+pub fn json_error_handler(err: RunTimeError, exit_code: i32) {
+    let error = serde_json::json!({
+        "error": {
+            "kind": telemetry::error_category(&err),
+            "message": err.to_string(),
+            "exit_code": exit_code
+        }
+    });
+    println!("{}", error)
+}
+
 
 #[derive(Debug)]
 pub enum InitializationError {
     Db(DbError),
     Config(ConfigError),
+    HttpClient(String),
     InitFailure
 }
 
@@ -44,6 +94,9 @@ impl std::fmt::Display for InitializationError {
             InitializationError::Config(e) => write!(
                 f, "InitializationError::Config: {}", e
             ),
+            InitializationError::HttpClient(e) => write!(
+                f, "InitializationError::HttpClient: {}", e
+            ),
             InitializationError::InitFailure => write!(
                 f, "InitializationError::InitFailure"
             ),
@@ -85,5 +138,279 @@ impl std::fmt::Display for ConfigError {
 }
 
 
+#[derive(Debug)]
+pub enum CatalogError {
+    Db(DbError),
+    ReadFailed(String),
+    ParseFailure(String),
+    SaveFailed(String),
+}
+
+impl std::fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CatalogError::Db(e) => write!(
+                f, "CatalogError::Db: {}", e
+            ),
+            CatalogError::ReadFailed(e) => write!(
+                f, "CatalogError::ReadFailed: {}", e
+            ),
+            CatalogError::ParseFailure(e) => write!(
+                f, "CatalogError::ParseFailure: {}", e
+            ),
+            CatalogError::SaveFailed(e) => write!(
+                f, "CatalogError::SaveFailed: {}", e
+            ),
+        }
+    }
+}
+
+
+/// Why a paper trading command failed
+#[derive(Debug)]
+pub enum PaperError {
+    Db(DbError),
+    /// The pair has no stored ticks to place an order against
+    NoData {
+        exchange: String,
+        ticker: String,
+    },
+    OrderRejected(String),
+    InsufficientFunds(String),
+    /// The account has no open order with this ID
+    NoOrder(i64),
+    /// No account has been opened with this name
+    NoAccount(String),
+}
+
+impl std::fmt::Display for PaperError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PaperError::Db(e) => write!(
+                f, "PaperError::Db: {}", e
+            ),
+            PaperError::NoData { exchange, ticker } => write!(
+                f,
+                "PaperError::NoData: no ticks stored for {} {}, run \
+                `dtrade database --update` first",
+                exchange,
+                ticker
+            ),
+            PaperError::OrderRejected(e) => write!(
+                f, "PaperError::OrderRejected: {}", e
+            ),
+            PaperError::InsufficientFunds(e) => write!(
+                f, "PaperError::InsufficientFunds: {}", e
+            ),
+            PaperError::NoOrder(id) => write!(
+                f, "PaperError::NoOrder: no open order #{}", id
+            ),
+            PaperError::NoAccount(name) => write!(
+                f,
+                "PaperError::NoAccount: no paper account named {}, it's \
+                opened by its first order, sync or reset",
+                name
+            ),
+        }
+    }
+}
+
+
 
+/// Why a live trading command failed
+#[derive(Debug)]
+pub enum LiveError {
+    /// `live.enabled` is off in the config
+    Disabled,
+    /// No Kraken API key or secret in the config or the environment
+    MissingKeys,
+    InvalidKeys(String),
+    UnsupportedExchange(String),
+    /// The order is worth more than `live.max_order_value`
+    OverLimit {
+        value: f64,
+        limit: f64,
+    },
+    /// The account has no open order with this ID, or none with a price to
+    /// change
+    NoOrder(String),
+    Exchange(ExchangeError),
+}
 
+impl std::fmt::Display for LiveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LiveError::Disabled => write!(
+                f,
+                "LiveError::Disabled: live trading is off, turn it on with \
+                `dtrade config set live.enabled true`"
+            ),
+            LiveError::MissingKeys => write!(
+                f,
+                "LiveError::MissingKeys: set live.kraken_api_key and \
+                live.kraken_api_secret, or {} and {}",
+                KRAKEN_API_KEY_ENV,
+                KRAKEN_API_SECRET_ENV
+            ),
+            LiveError::InvalidKeys(e) => write!(
+                f, "LiveError::InvalidKeys: {}", e
+            ),
+            LiveError::UnsupportedExchange(e) => write!(
+                f, "LiveError::UnsupportedExchange: {} has no live trading", e
+            ),
+            LiveError::OverLimit { value, limit } => write!(
+                f,
+                "LiveError::OverLimit: the order is worth about {:.2}, over \
+                the {:.2} allowed by live.max_order_value",
+                value,
+                limit
+            ),
+            LiveError::NoOrder(txid) => write!(
+                f, "LiveError::NoOrder: no open order {}", txid
+            ),
+            LiveError::Exchange(e) => write!(
+                f, "LiveError::Exchange: {}", e
+            ),
+        }
+    }
+}
+
+/// Why a recurring buy couldn't be scheduled, changed or looked up. Buys
+/// that fail when they run are kept in their history instead.
+#[derive(Debug)]
+pub enum RecurringError {
+    Db(DbError),
+    /// No recurring buy has this ID
+    NoSchedule(i64),
+    /// The paper account or pair to buy in can't be used
+    Paper(PaperError),
+    /// The live account's fills couldn't be read for the history
+    Live(LiveError),
+}
+
+impl std::fmt::Display for RecurringError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RecurringError::Db(e) => write!(
+                f, "RecurringError::Db: {}", e
+            ),
+            RecurringError::NoSchedule(id) => write!(
+                f,
+                "RecurringError::NoSchedule: no recurring buy #{}, list \
+                them with `dtrade recurring list`",
+                id
+            ),
+            RecurringError::Paper(e) => write!(
+                f, "RecurringError::Paper: {}", e
+            ),
+            RecurringError::Live(e) => write!(
+                f, "RecurringError::Live: {}", e
+            ),
+        }
+    }
+}
+
+
+/// Why a scheduled report couldn't be built or kept
+#[derive(Debug)]
+pub enum ReportError {
+    Db(DbError),
+    /// A paper account's equity couldn't be read
+    Paper(PaperError),
+}
+
+impl std::fmt::Display for ReportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ReportError::Db(e) => write!(f, "ReportError::Db: {}", e),
+            ReportError::Paper(e) => write!(f, "ReportError::Paper: {}", e),
+        }
+    }
+}
+
+/// Why a candle request can't be served, found before any ticks are read
+#[derive(Debug)]
+pub enum PreflightError {
+    InvalidPeriod(String),
+    PairNotAdded {
+        exchange: String,
+        ticker: String,
+    },
+    NoData {
+        exchange: String,
+        ticker: String,
+    },
+    /// The stored ticks don't span a single bar of `period`. `held` says
+    /// what's there, e.g. "2 days of ticks"
+    NotEnoughData {
+        exchange: String,
+        ticker: String,
+        period: String,
+        held: String,
+    },
+    /// Building from `ticks` ticks is estimated to take `needed_mb`, over 
+    /// the `budget_mb` set in the bar options
+    OverMemoryBudget {
+        ticks: u64,
+        needed_mb: u64,
+        budget_mb: u64,
+    },
+}
+
+impl std::fmt::Display for PreflightError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PreflightError::InvalidPeriod(p) => write!(
+                f,
+                "PreflightError::InvalidPeriod: {} isn't a period, use a \
+                number followed by s, m, h, d, w, M or t, e.g. 15m, 4h or \
+                500t",
+                p
+            ),
+            PreflightError::PairNotAdded { exchange, ticker } => write!(
+                f,
+                "PreflightError::PairNotAdded: {} {} hasn't been added, run \
+                `dtrade database --add-pairs {} {}` first",
+                exchange,
+                ticker,
+                exchange,
+                ticker
+            ),
+            PreflightError::NoData { exchange, ticker } => write!(
+                f,
+                "PreflightError::NoData: {} {} has no ticks yet, run \
+                `dtrade database --update` first",
+                exchange,
+                ticker
+            ),
+            PreflightError::NotEnoughData { 
+                exchange, 
+                ticker, 
+                period, 
+                held 
+            } => write!(
+                f,
+                "PreflightError::NotEnoughData: {} {} only holds {}, not \
+                enough for one {} bar. Run `dtrade database --update` or \
+                choose a shorter period",
+                exchange,
+                ticker,
+                held,
+                period
+            ),
+            PreflightError::OverMemoryBudget { 
+                ticks, 
+                needed_mb, 
+                budget_mb 
+            } => write!(
+                f,
+                "PreflightError::OverMemoryBudget: {} ticks would take about \
+                {} MB to build, over the {} MB budget. Narrow the build with \
+                --from and --to, or raise the budget in Settings",
+                ticks,
+                needed_mb,
+                budget_mb
+            ),
+        }
+    }
+}
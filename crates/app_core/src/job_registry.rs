@@ -0,0 +1,275 @@
+use std::sync::{
+    Arc,
+    Mutex,
+    MutexGuard,
+    atomic::{AtomicU64, Ordering}
+};
+
+use sqlx::PgPool;
+use tokio::{sync::mpsc::UnboundedSender, task::JoinSet};
+
+use database_ops::{
+    CancellationToken,
+    DataDownloadStatus,
+    DbError,
+    DownloadControl,
+    download_new_data_to_db_table,
+};
+use timestamp_tools::get_current_unix_timestamp;
+
+use crate::app_state::JobKind;
+
+
+/// Finished jobs kept for the jobs screen, oldest dropped first
+const JOBS_KEPT: usize = 200;
+
+
+/// Where a job is in its life
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobState {
+    Queued,
+    Running,
+    Finished,
+    Failed(String),
+    Cancelled,
+}
+
+impl JobState {
+
+    pub fn is_done(&self) -> bool {
+        !matches!(self, JobState::Queued | JobState::Running)
+    }
+}
+
+impl std::fmt::Display for JobState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JobState::Queued => write!(f, "queued"),
+            JobState::Running => write!(f, "running"),
+            JobState::Finished => write!(f, "finished"),
+            JobState::Failed(_) => write!(f, "failed"),
+            JobState::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+/// A background task working on one pair
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub id: u64,
+    pub kind: JobKind,
+    pub exchange: String,
+    pub ticker: String,
+    /// Percent done, for jobs that report it
+    pub progress: Option<u8>,
+    pub state: JobState,
+    /// Unix timestamp (seconds)
+    pub started_at: u64,
+    cancel: CancellationToken,
+}
+
+impl Job {
+
+    /// Failed and cancelled downloads can be run again
+    pub fn is_retryable(&self) -> bool {
+        self.kind == JobKind::Backfill
+            && matches!(self.state, JobState::Failed(_) | JobState::Cancelled)
+    }
+}
+
+
+// ------------------------------ JOB REGISTRY ----------------------------- //
+/// Every background job spawned by the app, for the jobs screen to list and
+/// act on
+///
+/// Cheap to clone, and every clone shares the same jobs. Each job gets a
+/// cancellation token of its own, a child of its run's token, so a single
+/// job can be stopped without its run.
+#[derive(Debug, Clone, Default)]
+pub struct JobRegistry {
+    jobs: Arc<Mutex<Vec<Job>>>,
+    last_id: Arc<AtomicU64>,
+}
+
+impl JobRegistry {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A poisoned lock only means a task panicked mid-update, and the list
+    /// is still worth showing
+    fn lock(&self) -> MutexGuard<'_, Vec<Job>> {
+        self.jobs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Adds a queued job for `exchange` `ticker`, returning its ID and the
+    /// token that cancels it
+    pub fn register(
+        &self,
+        kind: JobKind,
+        exchange: &str,
+        ticker: &str,
+        parent: &CancellationToken
+    ) -> (u64, CancellationToken) {
+
+        let cancel: CancellationToken = parent.child_token();
+        let id: u64 = self.last_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut jobs = self.lock();
+
+        jobs.push(Job {
+            id,
+            kind,
+            exchange: exchange.to_lowercase(),
+            ticker: ticker.to_uppercase(),
+            progress: None,
+            state: JobState::Queued,
+            started_at: get_current_unix_timestamp(),
+            cancel: cancel.clone(),
+        });
+
+        let done: usize = jobs.iter().filter(|j| j.state.is_done()).count();
+        if done > JOBS_KEPT {
+            let mut excess: usize = done - JOBS_KEPT;
+            jobs.retain(|j| match excess > 0 && j.state.is_done() {
+                true => {
+                    excess -= 1;
+                    false
+                },
+                false => true
+            });
+        };
+
+        (id, cancel)
+    }
+
+    pub fn set_state(&self, id: u64, state: JobState) {
+        if let Some(job) = self.lock().iter_mut().find(|j| j.id == id) {
+            if state == JobState::Finished {
+                job.progress = Some(100);
+            };
+            job.state = state;
+        };
+    }
+
+    /// Marks a job done with the outcome of its work
+    pub fn finish(&self, id: u64, result: &Result<(), DbError>) {
+        self.set_state(id, match result {
+            Ok(()) => JobState::Finished,
+            Err(DbError::Cancelled) => JobState::Cancelled,
+            Err(e) => JobState::Failed(e.to_string()),
+        });
+    }
+
+    /// Moves the running download of the pair `status` is about forward
+    pub fn apply_status(&self, status: &DataDownloadStatus) {
+
+        let DataDownloadStatus::Progress { percent, .. } = status else {
+            return
+        };
+        let (exchange, ticker) = status.exchange_and_ticker();
+
+        if let Some(job) = self.lock().iter_mut().rev().find(|j| {
+            j.kind == JobKind::Backfill
+                && j.state == JobState::Running
+                && j.exchange.eq_ignore_ascii_case(exchange)
+                && j.ticker.eq_ignore_ascii_case(ticker)
+        }) {
+            job.progress = Some(*percent);
+        };
+    }
+
+    /// Asks a job to stop, returning whether it was still going
+    pub fn cancel(&self, id: u64) -> bool {
+        match self.lock().iter().find(|j| j.id == id) {
+            Some(job) if !job.state.is_done() => {
+                job.cancel.cancel();
+                true
+            },
+            _ => false
+        }
+    }
+
+    /// Forgets every job that's done
+    pub fn clear_done(&self) {
+        self.lock().retain(|j| !j.state.is_done());
+    }
+
+    /// Every job, oldest first
+    pub fn snapshot(&self) -> Vec<Job> {
+        self.lock().clone()
+    }
+
+    pub fn running(&self) -> usize {
+        self.lock().iter().filter(|j| !j.state.is_done()).count()
+    }
+}
+
+
+/// Downloads every pair in `pairs` at once, each as a backfill job of its
+/// own in `registry`
+///
+/// The downloads share `control`'s watchdog and job ID, and cancelling
+/// `control.cancel` stops all of them. Returns the first error other than a
+/// cancellation, as `update_database_tables` does.
+pub async fn run_backfill_jobs(
+    registry: JobRegistry,
+    pairs: Vec<(String, String)>,
+    time_offset: u64,
+    client: reqwest::Client,
+    db_pool: PgPool,
+    progress_tx: UnboundedSender<DataDownloadStatus>,
+    control: DownloadControl
+) -> Result<(), DbError> {
+
+    let mut tasks: JoinSet<Result<(), DbError>> = JoinSet::new();
+
+    for (exchange, ticker) in pairs {
+
+        let (id, cancel) = registry.register(
+            JobKind::Backfill,
+            &exchange,
+            &ticker,
+            &control.cancel
+        );
+        let job_control = DownloadControl { cancel, ..control.clone() };
+
+        let registry = registry.clone();
+        let client = client.clone();
+        let db_pool = db_pool.clone();
+        let progress_tx = progress_tx.clone();
+
+        tasks.spawn(async move {
+            registry.set_state(id, JobState::Running);
+            let result = download_new_data_to_db_table(
+                &exchange,
+                &ticker,
+                db_pool,
+                time_offset,
+                &client,
+                progress_tx,
+                &job_control
+            ).await;
+            registry.finish(id, &result);
+            result
+        });
+    };
+
+    let mut result: Result<(), DbError> = Ok(());
+
+    while let Some(res) = tasks.join_next().await {
+
+        let outcome: Result<(), DbError> = match res {
+            Ok(inner) => inner,
+            Err(join_err) => Err(DbError::TaskJoin(join_err))
+        };
+
+        if let Err(e) = outcome
+            && matches!(result, Ok(()) | Err(DbError::Cancelled))
+        {
+            result = Err(e);
+        };
+    };
+
+    result
+}
@@ -1,15 +1,31 @@
-use std::{collections::HashMap};
+use std::{collections::HashMap, sync::Arc};
 
 pub mod arg_parsing;
 pub mod app_state;
+pub mod audit;
+pub mod blotter;
+pub mod bus_sink;
+pub mod catalog;
+pub mod config_fields;
 pub mod engine;
 pub mod errors;
+pub mod job_registry;
+pub mod live;
+pub mod pair_cache;
+pub mod paper;
+pub mod prompt;
+pub mod recurring;
+pub mod report;
+pub mod telemetry;
 
 use engine::Engine;
 pub use database_ops::{self, Db, DbError, DataDownloadStatus};
-pub use bars::{self, BarBuildError, BarSeries, BarType};
+pub use bars::{self, BarBuildError, BarOptions, BarSeries, BarType};
+pub use indicators::{self, Indicator, IndicatorRegistry};
+pub use backtest;
+pub use charts;
 pub use app_state::{AppState};
-pub use errors::{RunTimeError, InitializationError};
+pub use errors::{RunTimeError, InitializationError, PreflightError};
 pub use arg_parsing::{
     parse_args, 
     ParsedArgs, 
@@ -18,13 +34,37 @@ pub use arg_parsing::{
     DataResponse
 };
 
-use sqlx::PgPool;
+use backtest::{BacktestError, CostModel, Timeframes};
+use database_ops::{
+    PairStats,
+    count_rows_in_time_range,
+    fetch_pair_stats, 
+    fetch_tables, 
+    get_table_name, 
+    kraken
+};
+use sqlx::{PgPool, types::BigDecimal};
+use timestamp_tools::{
+    calculate_seconds_in_period, 
+    get_period_portions_from_string
+};
+use tokio::task::{JoinError, JoinSet};
 
 
 enum StatusMessageProgress {
     Started,
     Completed,
     Failed,
+    Cancelled,
+    Stalled {
+        cursor: String,
+        idle_secs: u64,
+        retrying: bool,
+    },
+    Retrying {
+        reason: String,
+        delay_secs: u64,
+    },
 }
 
 struct StatusMessage {
@@ -77,7 +117,7 @@ impl DownloadStatusViewer {
                 
                 text.push_str(&format!("  \x1b[33m{}\x1b[0m: ", token));
                 
-                match status.progress {
+                match &status.progress {
                     StatusMessageProgress::Started => {
                         text.push_str(&format!(
                             "Download Progress: \x1b[1;32m{}%\x1b[0m\n",
@@ -89,6 +129,37 @@ impl DownloadStatusViewer {
                     },
                     StatusMessageProgress::Failed => {
                         text.push_str("\x1b[1;31mFAILED\x1b[0m\n"); 
+                    },
+                    StatusMessageProgress::Cancelled => {
+                        text.push_str(&format!(
+                            "\x1b[1;33mStopped at {}%\x1b[0m\n",
+                            status.percent_complete
+                        ));
+                    },
+                    StatusMessageProgress::Stalled { 
+                        cursor, 
+                        idle_secs, 
+                        retrying 
+                    } => {
+                        text.push_str(&format!(
+                            "\x1b[1;31mSTALLED\x1b[0m at {}% (cursor {}, no \
+                            progress for {}s){}\n",
+                            status.percent_complete,
+                            cursor,
+                            idle_secs,
+                            match retrying {
+                                true => ", retrying",
+                                false => ""
+                            }
+                        ));
+                    },
+                    StatusMessageProgress::Retrying { reason, delay_secs } => {
+                        text.push_str(&format!(
+                            "\x1b[1;33m{}\x1b[0m at {}%, retrying in {}s\n",
+                            reason,
+                            status.percent_complete,
+                            delay_secs
+                        ));
                     }
                 };
                 
@@ -105,7 +176,7 @@ impl DownloadStatusViewer {
         let (exchange, ticker) = status.exchange_and_ticker();
 
         let entry = self.pairs.entry(exchange.to_string())
-            .or_insert_with(HashMap::new)
+            .or_default()
             .entry(ticker.to_string())
             .or_insert_with(StatusMessage::new);
 
@@ -115,6 +186,12 @@ impl DownloadStatusViewer {
             },
             DataDownloadStatus::Progress { percent, .. } => {
                 entry.percent_complete = percent;
+                // A retried download is moving again
+                if let StatusMessageProgress::Stalled { .. }
+                | StatusMessageProgress::Retrying { .. } = entry.progress 
+                {
+                    entry.progress = StatusMessageProgress::Started;
+                };
             },
             DataDownloadStatus::Finished { .. } => {
                 entry.percent_complete = 100;
@@ -122,6 +199,27 @@ impl DownloadStatusViewer {
             },
             DataDownloadStatus::Error { .. } => {
                 entry.progress = StatusMessageProgress::Failed;
+            },
+            DataDownloadStatus::Cancelled { .. } => {
+                entry.progress = StatusMessageProgress::Cancelled;
+            },
+            DataDownloadStatus::Stalled { 
+                cursor, 
+                idle_secs, 
+                retrying, 
+                .. 
+            } => {
+                entry.progress = StatusMessageProgress::Stalled { 
+                    cursor, 
+                    idle_secs, 
+                    retrying 
+                };
+            },
+            DataDownloadStatus::Retrying { reason, delay_secs, .. } => {
+                entry.progress = StatusMessageProgress::Retrying { 
+                    reason, 
+                    delay_secs 
+                };
             }
         };
     }
@@ -139,14 +237,14 @@ pub async fn initialize_app_engine() -> Result<Engine, RunTimeError> {
 
     let database = Db::new()
         .await
-        .map_err(|e| RunTimeError::DataBase(e))?;
+        .map_err(RunTimeError::DataBase)?;
 
     let engine = Engine::new(database)?;
 
     let active_exchanges: Vec<String> = engine.state.get_active_exchanges();
 
     database_ops::initialize(&active_exchanges).await
-        .map_err(|e| RunTimeError::DataBase(e))?; 
+        .map_err(RunTimeError::DataBase)?; 
 
     Ok(engine)
 }
@@ -157,6 +255,7 @@ pub async fn build_candles(
     exchange: &str, 
     ticker: &str, 
     period: &str,
+    options: BarOptions,
     db_pool: PgPool
 ) 
     -> Result<BarSeries, BarBuildError> 
@@ -166,9 +265,299 @@ pub async fn build_candles(
         ticker.to_string(), 
         period.to_string(), 
         BarType::Candle, 
+        options,
         db_pool).await
 }
 
+/// One series for `build_candle_batch` to build
+#[derive(Debug, Clone)]
+pub struct CandleRequest {
+    pub exchange: String,
+    pub ticker: String,
+    pub period: String,
+    pub options: BarOptions,
+    /// Ticks from its start up to its end, in microseconds. With both ends 
+    /// open, the latest ticks like `BarSeries::new`
+    pub range: (Option<u64>, Option<u64>),
+}
+
+/// The ticks a `CandleRequest` reads: its exchange, ticker and range
+type TickSource = (String, String, (Option<u64>, Option<u64>));
+
+type Ticks = Vec<(u64, u64, BigDecimal, BigDecimal)>;
+
+impl CandleRequest {
+    fn source(&self) -> TickSource {
+        (self.exchange.to_lowercase(), self.ticker.to_uppercase(), self.range)
+    }
+}
+
+/// Builds the candles of every request at once, reading each pair's ticks
+/// a single time however many periods are built from them
+///
+/// The reads run concurrently, one task per pair and range, then so do the
+/// builds. Returns the series in the order of `requests`, or the first 
+/// error.
+pub async fn build_candle_batch(
+    requests: Vec<CandleRequest>, 
+    db_pool: PgPool
+) 
+    -> Result<Vec<BarSeries>, BarBuildError> 
+{
+    let join_err = |e: JoinError| BarBuildError::BuildFailed(e.to_string());
+
+    // Whether any build from the source opens at the pair's listing
+    let mut sources: HashMap<TickSource, bool> = HashMap::new();
+    for request in &requests {
+        *sources.entry(request.source()).or_default() |= 
+            request.options.listing_anchor;
+    };
+
+    type Read = (TickSource, Ticks, Option<u64>);
+    let mut reads: JoinSet<Result<Read, BarBuildError>> = JoinSet::new();
+
+    for (source, anchored) in sources {
+        let db_pool = db_pool.clone();
+        reads.spawn(async move {
+            let (exchange, ticker, range) = &source;
+            let listing: Option<u64> = match anchored {
+                true => BarSeries::fetch_listing(
+                    exchange, 
+                    ticker, 
+                    db_pool.clone()
+                ).await?,
+                false => None
+            };
+            let ticks: Ticks = BarSeries::fetch_ticks(
+                exchange, 
+                ticker, 
+                match range {
+                    (None, None) => None,
+                    range => Some(*range)
+                },
+                db_pool
+            ).await?;
+            Ok((source, ticks, listing))
+        });
+    };
+
+    let mut read: HashMap<TickSource, (Arc<Ticks>, Option<u64>)> = 
+        HashMap::new();
+    while let Some(res) = reads.join_next().await {
+        let (source, ticks, listing) = res.map_err(join_err)??;
+        read.insert(source, (Arc::new(ticks), listing));
+    };
+
+    let count: usize = requests.len();
+    let mut builds: JoinSet<(usize, Result<BarSeries, BarBuildError>)> = 
+        JoinSet::new();
+
+    for (i, request) in requests.into_iter().enumerate() {
+        let (ticks, listing) = read[&request.source()].clone();
+        builds.spawn_blocking(move || {
+            let built = bars::BarInfo::new(
+                request.exchange, 
+                request.ticker, 
+                request.period, 
+                request.options
+            ).and_then(|info| BarSeries::from_ticks(
+                info, 
+                BarType::Candle, 
+                listing, 
+                ticks.to_vec()
+            ));
+            (i, built)
+        });
+    };
+
+    let mut series: Vec<Option<BarSeries>> = (0..count).map(|_| None)
+        .collect();
+    while let Some(res) = builds.join_next().await {
+        let (i, built) = res.map_err(join_err)?;
+        series[i] = Some(built?);
+    };
+
+    Ok(series.into_iter().flatten().collect())
+}
+
+/// Checks that candles of `period` can be built for a pair before any ticks
+/// are read, so a missing pair or a bad period gets a message saying what 
+/// to do about it
+///
+/// The pair's table has to exist and hold at least one bar's worth of 
+/// ticks: enough time for time based periods, enough ticks for tick based 
+/// ones.
+pub async fn preflight_candles(
+    exchange: &str, 
+    ticker: &str, 
+    period: &str,
+    db_pool: PgPool
+) 
+    -> Result<(), RunTimeError> 
+{
+    let exchange: String = exchange.to_lowercase();
+    let ticker: String = ticker.to_uppercase();
+
+    let (symbol, n) = match get_period_portions_from_string(period) {
+        Ok((_, 0)) | Err(_) => return Err(RunTimeError::Preflight(
+            PreflightError::InvalidPeriod(period.to_string())
+        )),
+        Ok(portions) => portions
+    };
+
+    let tables: Vec<String> = fetch_tables(db_pool.clone())
+        .await
+        .map_err(RunTimeError::DataBase)?;
+
+    if !tables.contains(&get_table_name(&exchange, &ticker)) {
+        return Err(RunTimeError::Preflight(PreflightError::PairNotAdded { 
+            exchange, 
+            ticker 
+        }))
+    };
+
+    let stats: PairStats = fetch_pair_stats(&exchange, &ticker, db_pool)
+        .await
+        .map_err(RunTimeError::DataBase)?;
+
+    let (Some(first), Some(last)) = (stats.first_time, stats.last_time) else {
+        return Err(RunTimeError::Preflight(PreflightError::NoData { 
+            exchange, 
+            ticker 
+        }))
+    };
+
+    let held: Option<String> = match symbol {
+        't' => (stats.approx_ticks < n).then(|| {
+            format!("about {} ticks", stats.approx_ticks)
+        }),
+        _ => {
+            let bar_secs: u64 = match symbol {
+                'w' => 7 * 86_400 * n,
+                _ => calculate_seconds_in_period(n, symbol).map_err(|_| {
+                    RunTimeError::Preflight(PreflightError::InvalidPeriod(
+                        period.to_string()
+                    ))
+                })?
+            };
+            let held_secs: u64 = last.saturating_sub(first) / 1_000_000;
+            (held_secs < bar_secs).then(|| match held_secs / 86_400 {
+                0 => format!("{} hours of ticks", held_secs / 3600),
+                days => format!("{} days of ticks", days)
+            })
+        }
+    };
+
+    match held {
+        Some(held) => Err(RunTimeError::Preflight(
+            PreflightError::NotEnoughData { 
+                exchange, 
+                ticker, 
+                period: period.to_string(), 
+                held 
+            }
+        )),
+        None => Ok(())
+    }
+}
+
+/// Checks that the ticks a candle build would read fit in `budget_mb`, 
+/// before any are read, with `builds` series built from them
+///
+/// Without a `range` the build reads the latest ticks, at most 
+/// `bars::LATEST_TICKS`. Times are in microseconds, and a budget of 0 
+/// skips the check.
+pub async fn preflight_memory(
+    exchange: &str, 
+    ticker: &str, 
+    range: (Option<u64>, Option<u64>),
+    builds: u64,
+    budget_mb: u64,
+    db_pool: PgPool
+) 
+    -> Result<(), RunTimeError> 
+{
+    if budget_mb == 0 {
+        return Ok(())
+    };
+
+    let ticks: u64 = match range {
+        (None, None) => fetch_pair_stats(exchange, ticker, db_pool)
+            .await
+            .map_err(RunTimeError::DataBase)?
+            .approx_ticks
+            .min(bars::LATEST_TICKS),
+        (from, to) => count_rows_in_time_range(
+            exchange, 
+            ticker, 
+            from, 
+            to, 
+            db_pool
+        )
+            .await
+            .map_err(RunTimeError::DataBase)?
+    };
+
+    let needed_mb: u64 = bars::estimate_build_bytes(ticks)
+        .saturating_mul(builds)
+        .div_ceil(1 << 20);
+
+    match needed_mb > budget_mb {
+        true => Err(RunTimeError::Preflight(
+            PreflightError::OverMemoryBudget { ticks, needed_mb, budget_mb }
+        )),
+        false => Ok(())
+    }
+}
+
+/// Builds the candles of each period a strategy reads besides the one it
+/// trades, see `backtest::Strategy::timeframes`
+pub async fn build_timeframes(
+    exchange: &str, 
+    ticker: &str, 
+    periods: &[String],
+    options: BarOptions,
+    db_pool: PgPool
+) 
+    -> Result<Timeframes, BarBuildError> 
+{
+    let mut timeframes: Timeframes = Timeframes::new();
+
+    for period in periods {
+        if timeframes.periods().contains(&period.as_str()) {
+            continue
+        };
+        timeframes.insert(build_candles(
+            exchange, 
+            ticker, 
+            period, 
+            options, 
+            db_pool.clone()
+        ).await?);
+    };
+
+    Ok(timeframes)
+}
+
+/// Trading costs for backtesting `ticker`, from the pair's info on the
+/// exchange. Falls back to slippage only when that can't be fetched, and
+/// returns the reason alongside.
+pub async fn backtest_cost_model(
+    ticker: &str,
+    slippage_bps: u32,
+    client: &reqwest::Client
+)
+    -> Result<(CostModel, Option<reqwest::Error>), BacktestError>
+{
+    match kraken::request_asset_info_from_kraken(ticker, client).await {
+        Ok(info) => Ok((
+            CostModel::from_pair_info(&info, slippage_bps)?,
+            None
+        )),
+        Err(e) => Ok((CostModel::slippage_only(slippage_bps), Some(e)))
+    }
+}
+
 
 // -------------------------- UNIT TESTING --------------------------------- //
 #[cfg(test)]
@@ -176,9 +565,14 @@ mod tests {
 
     use bars::*;
     use crate::engine::Engine;
-    use database_ops::{Db, fetch_tables, integrity_check};
+    use database_ops::{
+        Db, 
+        fetch_exchanges_and_pairs_from_db, 
+        fetch_tables, 
+        integrity_check
+    };
+    
     
-    use tokio;
 
     #[tokio::test]
     async fn database_connection_test() {
@@ -190,12 +584,197 @@ mod tests {
 
         let pool = db.get_pool();
 
-        if let Err(_) = fetch_tables(pool.clone()).await {
+        if fetch_tables(pool.clone()).await.is_err() {
             panic!();
         };
 
     }
 
+    #[tokio::test]
+    async fn pair_cache_test() {
+
+        let db: Db = Db::new().await.unwrap();
+        let pool = db.get_pool();
+        let cache = crate::pair_cache::PairCache::default();
+
+        assert!(cache.is_stale());
+        let pairs = cache.load(pool.clone()).await;
+        assert!(!cache.is_stale());
+        assert_eq!(pairs, cache.pairs());
+
+        let stored = fetch_exchanges_and_pairs_from_db(pool.clone(), true)
+            .await;
+        for (exchange, tickers) in &stored {
+            let names: Vec<String> = tickers
+                .iter()
+                .map(|(t, stats)| {
+                    assert!(stats.is_some());
+                    t.clone()
+                })
+                .collect();
+            assert_eq!(Some(&names), pairs.get(exchange));
+        };
+        assert_eq!(pairs.len(), stored.len());
+
+        cache.invalidate();
+        assert!(cache.is_stale());
+        assert_eq!(pairs, cache.pairs());
+    }
+
+    #[tokio::test]
+    async fn audit_log_test() {
+
+        use database_ops::audit::{AuditOrigin, audit_log};
+
+        let db: Db = Db::new().await.unwrap();
+        let pool = db.get_pool();
+        let exchanges: Vec<String> = vec!["kraken".to_string()];
+        database_ops::first_time_setup(&exchanges, pool.clone())
+            .await
+            .unwrap();
+
+        let cmd = crate::arg_parsing::Command::SetConfig {
+            key: "http.api_keys".to_string(),
+            value: "audit-test-key-0000:admin".to_string()
+        };
+        let origin = AuditOrigin::Http(Some("...0000".to_string()));
+        let entry = crate::audit::audited(&cmd).unwrap();
+        entry.record(&origin, None, pool.clone()).await.unwrap();
+
+        let latest = audit_log(pool.clone(), Some("config.set"), 1)
+            .await
+            .unwrap();
+        assert_eq!(latest[0].origin, "http:...0000");
+        assert_eq!(latest[0].params["key"], "http.api_keys");
+        assert_eq!(latest[0].params["value"], "<redacted>");
+        assert!(latest[0].error.is_none());
+
+        // Rows can be added but never changed or removed
+        let deleted = sqlx::query("DELETE FROM _audit_log WHERE id = $1")
+            .bind(latest[0].id)
+            .execute(&pool)
+            .await;
+        assert!(deleted.is_err());
+
+        // Commands that only read aren't recorded
+        assert!(crate::audit::audited(&crate::arg_parsing::Command::ListPairs)
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn live_order_guard_test() {
+
+        use backtest::OrderSide;
+        use crate::{
+            app_state::LiveTradingSettings,
+            errors::LiveError,
+            live::{LiveOrder, kraken_credentials, place_order},
+        };
+
+        let client = reqwest::Client::new();
+        let mut settings = LiveTradingSettings::default();
+
+        // Off by default, whatever keys are around
+        assert!(matches!(
+            kraken_credentials(&settings),
+            Err(LiveError::Disabled)
+        ));
+
+        settings.enabled = true;
+        settings.kraken_api_key = Some("test-key".to_string());
+        settings.kraken_api_secret = Some("not base64!".to_string());
+        if std::env::var(crate::app_state::KRAKEN_API_SECRET_ENV).is_err() {
+            assert!(matches!(
+                kraken_credentials(&settings),
+                Err(LiveError::InvalidKeys(_))
+            ));
+        };
+
+        settings.kraken_api_secret = Some("c2VjcmV0".to_string());
+        let mut order = LiveOrder {
+            exchange: "kraken".to_string(),
+            ticker: "XBTUSD".parse().unwrap(),
+            side: OrderSide::Buy,
+            volume: 1.0,
+            limit: Some(settings.max_order_value * 2.0),
+            validate: true,
+        };
+
+        // Refused before anything is sent
+        assert!(matches!(
+            place_order(&settings, &order, &client).await,
+            Err(LiveError::OverLimit { .. })
+        ));
+
+        order.exchange = "binance".to_string();
+        assert!(matches!(
+            place_order(&settings, &order, &client).await,
+            Err(LiveError::UnsupportedExchange(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn preflight_candles_test() {
+
+        let db: Db = Db::new().await.unwrap();
+        let pool = db.get_pool();
+
+        let missing = crate::preflight_candles(
+            "kraken",
+            "NOSUCHPAIR",
+            "1h",
+            pool.clone()
+        ).await;
+        assert!(matches!(
+            missing,
+            Err(crate::RunTimeError::Preflight(
+                crate::PreflightError::PairNotAdded { .. }
+            ))
+        ));
+
+        for period in ["", "h", "0h", "1x"] {
+            let invalid = crate::preflight_candles(
+                "kraken",
+                "BTCUSD",
+                period,
+                pool.clone()
+            ).await;
+            assert!(matches!(
+                invalid,
+                Err(crate::RunTimeError::Preflight(
+                    crate::PreflightError::InvalidPeriod(_)
+                ))
+            ));
+        };
+    }
+
+    #[tokio::test]
+    async fn preflight_memory_test() {
+
+        let db: Db = Db::new().await.unwrap();
+        let pool = db.get_pool();
+
+        let unlimited = crate::preflight_memory(
+            "kraken",
+            "NOSUCHPAIR",
+            (None, None),
+            1,
+            0,
+            pool.clone()
+        ).await;
+        assert!(unlimited.is_ok());
+
+        let empty_window = crate::preflight_memory(
+            "kraken",
+            "BTCUSD",
+            (Some(0), Some(1)),
+            1,
+            1,
+            pool
+        ).await;
+        assert!(empty_window.is_ok());
+    }
+
     #[tokio::test]
     async fn database_integrity_check() {
          
@@ -246,11 +825,12 @@ mod tests {
         let ticker = "BTCUSD".to_string();
         let period = "1h".to_string();
         
-        let candles = match BarSeries::new(
+        let _candles = match BarSeries::new(
             exchange, 
             ticker, 
             period, 
             BarType::Candle, 
+            BarOptions::default(),
             engine.database.get_pool()
         ).await {
             Ok(c) => c,
@@ -260,5 +840,648 @@ mod tests {
         };
     }
 
-}
 
+    #[tokio::test]
+    async fn resample_test() {
+
+        let database: Db = Db::new().await.unwrap();
+        let pool = database.get_pool();
+
+        let hourly = BarSeries::new(
+            "kraken".to_string(), 
+            "BTCUSD".to_string(), 
+            "1h".to_string(), 
+            BarType::Candle, 
+            BarOptions::default(),
+            pool.clone()
+        ).await.unwrap();
+
+        let four_hour = BarSeries::new(
+            "kraken".to_string(), 
+            "BTCUSD".to_string(), 
+            "4h".to_string(), 
+            BarType::Candle, 
+            BarOptions::default(),
+            pool
+        ).await.unwrap();
+
+        let resampled = match hourly.resample("4h") {
+            Ok(b) => b,
+            Err(e) => panic!("Test failed: {}", e)
+        };
+
+        assert_eq!(resampled.len(), four_hour.len());
+        
+        for (a, b) in resampled.into_iter().zip(&four_hour) {
+            assert_eq!(a.to_ohlcv(), b.to_ohlcv());
+        };
+
+        assert!(hourly.resample("90m").is_err());
+    }
+
+    #[tokio::test]
+    async fn indicator_test() {
+
+        let database: Db = Db::new().await.unwrap();
+        let pool = database.get_pool();
+
+        let series = BarSeries::new(
+            "kraken".to_string(), 
+            "BTCUSD".to_string(), 
+            "1h".to_string(), 
+            BarType::Candle, 
+            BarOptions::default(),
+            pool
+        ).await.unwrap();
+
+        let registry = indicators::IndicatorRegistry::new();
+
+        let sma = registry.from_spec("sma(3)").unwrap().compute(&series);
+        let closes: Vec<f64> = series.bars
+            .iter()
+            .map(|b| b.close().to_string().parse::<f64>().unwrap())
+            .collect();
+
+        assert_eq!(sma[0].values.len(), series.len());
+        assert!(sma[0].values[1].is_none());
+        let expected: f64 = closes[..3].iter().sum::<f64>() / 3.0;
+        assert!((sma[0].values[2].unwrap() - expected).abs() < 1e-6);
+
+        let rsi = registry.create("rsi", &[]).unwrap().compute(&series);
+        assert!(rsi[0].values.iter().flatten().all(|v| (0.0..=100.0).contains(v)));
+
+        let mut macd_indicator = registry.from_spec("MACD(12,26,9)").unwrap();
+        let macd = macd_indicator.compute(&series);
+        assert_eq!(macd.len(), 3);
+
+        // Feeding bars one at a time must match the batch computation
+        for (i, bar) in series.bars.iter().enumerate() {
+            let preview = macd_indicator.preview(bar);
+            let values = macd_indicator.update(bar);
+            assert_eq!(preview, values);
+            for (line, value) in macd.iter().zip(values) {
+                assert_eq!(line.values[i], value);
+            };
+        };
+
+        assert!(registry.from_spec("vwap(10)").is_err());
+        assert!(registry.from_spec("macd(26,12)").is_err());
+    }
+
+    #[tokio::test]
+    async fn sim_broker_test() {
+
+        use backtest::{
+            CostModel, Liquidity, Order, OrderKind, OrderSide, SimBroker
+        };
+        use database_ops::kraken::AssetPairInfo;
+
+        let database: Db = Db::new().await.unwrap();
+
+        let series = BarSeries::new(
+            "kraken".to_string(), 
+            "BTCUSD".to_string(), 
+            "1h".to_string(), 
+            BarType::Candle, 
+            BarOptions::default(),
+            database.get_pool()
+        ).await.unwrap();
+
+        let info: AssetPairInfo = serde_json::from_value(serde_json::json!({
+            "altname": "XBTUSD", "wsname": "XBT/USD",
+            "aclass_base": "currency", "base": "XXBT",
+            "aclass_quote": "currency", "quote": "ZUSD", "lot": "unit",
+            "cost_decimals": 5, "pair_decimals": 1, "lot_decimals": 8,
+            "lot_multiplier": 1, "leverage_buy": [], "leverage_sell": [],
+            "fees": [[0, 0.4], [10000, 0.35]],
+            "fees_maker": [[0, 0.25], [10000, 0.2]],
+            "fee_volume_currency": "ZUSD",
+            "ordermin": "0.0001", "costmin": "0.5", "tick_size": "0.1",
+            "status": "online"
+        })).unwrap();
+
+        let model = CostModel::from_pair_info(&info, 10).unwrap();
+        let mut broker = SimBroker::new(model, 100_000.0);
+
+        let bar = &series.bars[1];
+        let open: f64 = bar.open().to_string().parse().unwrap();
+
+        let buy = Order { 
+            side: OrderSide::Buy, 
+            kind: OrderKind::Market, 
+            volume: 1.123456789 
+        };
+        let fill = broker.execute(&buy, bar).unwrap().unwrap();
+
+        // Slippage moves the price up, then it's rounded up to the tick
+        assert!(fill.price > open);
+        assert!((fill.price * 10.0 - (fill.price * 10.0).round()).abs() < 1e-6);
+        assert_eq!(fill.volume, 1.12345678);
+        assert_eq!(fill.liquidity, Liquidity::Taker);
+        assert!((fill.fee - fill.cost * 0.004).abs() < 1e-6);
+
+        // The first buy crosses 10k of volume, so the next fill is cheaper
+        let sell = Order { 
+            side: OrderSide::Sell, 
+            kind: OrderKind::Limit(open * 10.0), 
+            volume: 1.0 
+        };
+        assert!(broker.execute(&sell, &series.bars[2]).unwrap().is_none());
+
+        let sell = Order { kind: OrderKind::Market, ..sell };
+        let fill = broker.execute(&sell, &series.bars[2]).unwrap().unwrap();
+        assert!((fill.fee - fill.cost * 0.0035).abs() < 1e-6);
+
+        let dust = Order { volume: 0.00001, ..sell };
+        assert!(broker.execute(&dust, &series.bars[3]).is_err());
+
+        let too_much = Order { volume: 5.0, ..sell };
+        assert!(broker.execute(&too_much, &series.bars[3]).is_err());
+    }
+
+    // Chaos tests: each interrupts a download of its own scratch pair, then
+    // resumes it and checks what `integrity_check` and the checkpoints are
+    // meant to guarantee. A pair that's already stored is left alone and
+    // the test is skipped.
+
+    /// A day of trades, so there are pages left to interrupt
+    const CHAOS_OFFSET: u64 = 24 * 60 * 60;
+
+    /// Adds `ticker` to the database, unless it's there already, returning
+    /// how many ticks its first page stored
+    async fn add_chaos_pair(
+        ticker: &str, 
+        db_pool: sqlx::PgPool, 
+        client: &reqwest::Client
+    ) -> Option<i64> {
+
+        let table: String = database_ops::get_table_name("kraken", ticker);
+        if fetch_tables(db_pool.clone()).await.unwrap().contains(&table) {
+            println!("{} is already stored, skipping", ticker);
+            return None
+        };
+
+        database_ops::add_new_pair(
+            "kraken", 
+            ticker, 
+            CHAOS_OFFSET, 
+            db_pool.clone(), 
+            client, 
+            None
+        ).await.unwrap();
+
+        Some(count_ticks(ticker, db_pool).await)
+    }
+
+    async fn count_ticks(ticker: &str, db_pool: sqlx::PgPool) -> i64 {
+        let query: String = format!(
+            "SELECT COUNT(*) FROM {}", 
+            database_ops::get_table_name("kraken", ticker)
+        );
+        sqlx::query_scalar(&query).fetch_one(&db_pool).await.unwrap()
+    }
+
+    /// Waits until a download has stored more than `stored` ticks
+    async fn wait_for_progress(
+        ticker: &str, 
+        stored: i64, 
+        db_pool: sqlx::PgPool
+    ) -> Result<(), String> {
+        for _ in 0..240 {
+            if count_ticks(ticker, db_pool.clone()).await > stored {
+                return Ok(())
+            };
+            tokio::time::sleep(std::time::Duration::from_millis(250)).await;
+        };
+        Err("No progress in a minute".to_string())
+    }
+
+    fn chaos_control() -> database_ops::DownloadControl {
+        database_ops::DownloadControl::new(
+            database_ops::CancellationToken::new(), 
+            std::time::Duration::from_secs(60), 
+            0
+        )
+    }
+
+    /// Downloads `ticker` to the end on a healthy connection, then checks
+    /// that the interrupted runs left no gaps or duplicates behind, and
+    /// that every stored tick came in a recorded batch
+    async fn resume_and_check(
+        ticker: &str, 
+        db_pool: sqlx::PgPool, 
+        client: &reqwest::Client
+    ) -> Result<(), String> {
+
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+
+        database_ops::download_new_data_to_db_table(
+            "kraken", 
+            ticker, 
+            db_pool.clone(), 
+            CHAOS_OFFSET, 
+            client, 
+            tx, 
+            &chaos_control()
+        ).await.map_err(|e| format!("Resume failed: {}", e))?;
+
+        let integrity = integrity_check(
+            "kraken", 
+            ticker, 
+            db_pool.clone(), 
+            None
+        ).await;
+
+        if !integrity.is_ok || !integrity.error.is_empty() {
+            return Err(format!(
+                "{} missing ticks, error: '{}'", 
+                integrity.missing_ticks.len(), 
+                integrity.error
+            ))
+        };
+
+        let table: String = database_ops::get_table_name("kraken", ticker);
+        let (rows, distinct): (i64, i64) = sqlx::query_as(&format!(
+            "SELECT COUNT(*), COUNT(DISTINCT id) FROM {}", 
+            table
+        ))
+            .fetch_one(&db_pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if rows != distinct || rows as u64 != integrity.total_ticks {
+            return Err(format!(
+                "{} rows, {} distinct IDs, {} counted by the check", 
+                rows, 
+                distinct, 
+                integrity.total_ticks
+            ))
+        };
+
+        let next_tick_id: i64 = sqlx::query_scalar(
+            "SELECT next_tick_id FROM _last_tick_history WHERE asset = $1"
+        )
+            .bind(ticker)
+            .fetch_one(&db_pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if next_tick_id as u64 != integrity.last_tick_id + 1 {
+            return Err(format!(
+                "Checkpoint at {}, last stored tick {}", 
+                next_tick_id, 
+                integrity.last_tick_id
+            ))
+        };
+
+        let batched: i64 = sqlx::query_scalar(r#"
+            SELECT COALESCE(SUM(tick_count), 0)::BIGINT 
+            FROM _ingest_batches 
+            WHERE exchange = 'kraken' AND asset = $1
+        "#)
+            .bind(ticker)
+            .fetch_one(&db_pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        match batched == rows {
+            true => Ok(()),
+            false => Err(format!(
+                "{} ticks in recorded batches, {} stored", 
+                batched, 
+                rows
+            ))
+        }
+    }
+
+    /// Drops the scratch pair before failing, so the next run isn't skipped
+    async fn finish_chaos_test(
+        ticker: &str, 
+        result: Result<(), String>, 
+        db_pool: sqlx::PgPool
+    ) {
+        database_ops::drop_pair("kraken", ticker, db_pool).await.unwrap();
+        if let Err(e) = result {
+            panic!("{}: {}", ticker, e);
+        };
+    }
+
+    #[tokio::test]
+    async fn chaos_restart_test() {
+
+        const TICKER: &str = "XBTEUR";
+
+        let database: Db = Db::new().await.unwrap();
+        let pool = database.get_pool();
+        let client = database_ops::kraken::kraken_client().unwrap();
+
+        let Some(stored) = add_chaos_pair(TICKER, pool.clone(), &client).await
+        else {
+            return
+        };
+
+        // Aborting the task stops it dead between any two awaits, like the
+        // process being killed
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let (task_pool, task_client) = (pool.clone(), client.clone());
+        let download = tokio::spawn(async move {
+            database_ops::download_new_data_to_db_table(
+                "kraken", 
+                TICKER, 
+                task_pool, 
+                CHAOS_OFFSET, 
+                &task_client, 
+                tx, 
+                &chaos_control()
+            ).await
+        });
+
+        let progress = wait_for_progress(TICKER, stored, pool.clone()).await;
+        download.abort();
+        let _ = download.await;
+
+        let result = match progress {
+            Ok(()) => resume_and_check(TICKER, pool.clone(), &client).await,
+            Err(e) => Err(e)
+        };
+        finish_chaos_test(TICKER, result, pool).await;
+    }
+
+    #[tokio::test]
+    async fn chaos_db_connection_test() {
+
+        use database_ops::{DbLogin, connection::DATABASE_NAME};
+
+        const TICKER: &str = "ETHEUR";
+        const APP_NAME: &str = "dtrade_chaos_test";
+
+        let database: Db = Db::new().await.unwrap();
+        let pool = database.get_pool();
+        let client = database_ops::kraken::kraken_client().unwrap();
+
+        let Some(stored) = add_chaos_pair(TICKER, pool.clone(), &client).await
+        else {
+            return
+        };
+
+        // A pool of its own, named so only its connections get killed
+        let login = DbLogin::new();
+        let doomed_pool = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(4)
+            .connect(&format!(
+                "postgres://{}:{}@{}:{}/{}?application_name={}",
+                login.user,
+                login.password,
+                login.host,
+                login.port,
+                DATABASE_NAME,
+                APP_NAME
+            ))
+            .await
+            .unwrap();
+
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let task_client = client.clone();
+        let download = tokio::spawn(async move {
+            database_ops::download_new_data_to_db_table(
+                "kraken", 
+                TICKER, 
+                doomed_pool, 
+                CHAOS_OFFSET, 
+                &task_client, 
+                tx, 
+                &chaos_control()
+            ).await
+        });
+
+        let progress = wait_for_progress(TICKER, stored, pool.clone()).await;
+
+        // A few rounds, so some land in the middle of a batch
+        for _ in 0..3 {
+            sqlx::query(r#"
+                SELECT pg_terminate_backend(pid) 
+                FROM pg_stat_activity 
+                WHERE application_name = $1 AND pid <> pg_backend_pid()
+            "#)
+                .bind(APP_NAME)
+                .execute(&pool)
+                .await
+                .unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(400)).await;
+        };
+
+        let _ = download.await;
+
+        let result = match progress {
+            Ok(()) => resume_and_check(TICKER, pool.clone(), &client).await,
+            Err(e) => Err(e)
+        };
+        finish_chaos_test(TICKER, result, pool).await;
+    }
+
+    #[tokio::test]
+    async fn chaos_network_drop_test() {
+
+        use tokio::{
+            io::{AsyncReadExt, AsyncWriteExt, copy_bidirectional},
+            net::{TcpListener, TcpStream},
+            task::JoinSet,
+        };
+
+        const TICKER: &str = "SOLEUR";
+
+        let database: Db = Db::new().await.unwrap();
+        let pool = database.get_pool();
+        let client = database_ops::kraken::kraken_client().unwrap();
+
+        let Some(stored) = add_chaos_pair(TICKER, pool.clone(), &client).await
+        else {
+            return
+        };
+
+        // A CONNECT proxy in front of the exchange. Aborting it closes every
+        // tunnel and the listener at once, like the network going away.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_url = format!("http://{}", listener.local_addr().unwrap());
+
+        let proxy = tokio::spawn(async move {
+            let mut tunnels: JoinSet<()> = JoinSet::new();
+            while let Ok((mut inbound, _)) = listener.accept().await {
+                tunnels.spawn(async move {
+
+                    let mut buf = [0u8; 4096];
+                    let mut read: usize = 0;
+                    while !buf[..read].windows(4).any(|w| w == b"\r\n\r\n") {
+                        match inbound.read(&mut buf[read..]).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => read += n
+                        };
+                        if read == buf.len() { return };
+                    };
+
+                    let request = String::from_utf8_lossy(&buf[..read]);
+                    let Some(target) = request.split_whitespace().nth(1)
+                    else {
+                        return
+                    };
+                    let Ok(mut outbound) = TcpStream::connect(target).await
+                    else {
+                        return
+                    };
+
+                    const ESTABLISHED: &[u8] = 
+                        b"HTTP/1.1 200 Connection established\r\n\r\n";
+
+                    if inbound.write_all(ESTABLISHED).await.is_ok()
+                    {
+                        let _ = copy_bidirectional(
+                            &mut inbound, 
+                            &mut outbound
+                        ).await;
+                    };
+                });
+            };
+        });
+
+        let proxied_client = reqwest::Client::builder()
+            .proxy(reqwest::Proxy::all(&proxy_url).unwrap())
+            .build()
+            .unwrap();
+
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let task_pool = pool.clone();
+        let download = tokio::spawn(async move {
+            database_ops::download_new_data_to_db_table(
+                "kraken", 
+                TICKER, 
+                task_pool, 
+                CHAOS_OFFSET, 
+                &proxied_client, 
+                tx, 
+                &chaos_control()
+            ).await
+        });
+
+        let progress = wait_for_progress(TICKER, stored, pool.clone()).await;
+        proxy.abort();
+
+        // No retries, so the dropped network has to end the download
+        let dropped = download.await.unwrap();
+
+        let result = match (progress, dropped) {
+            (Err(e), _) => Err(e),
+            (Ok(()), Ok(())) => {
+                Err("The download finished before the drop".to_string())
+            },
+            (Ok(()), Err(_)) => {
+                resume_and_check(TICKER, pool.clone(), &client).await
+            }
+        };
+        finish_chaos_test(TICKER, result, pool).await;
+    }
+
+    /// Stores `prices` as the ticks of a made up Kraken pair, a second
+    /// apart from `start` (unix seconds), replacing any stored before
+    async fn store_paper_ticks(
+        ticker: &str, 
+        start: u64, 
+        prices: &[f64], 
+        db_pool: &sqlx::PgPool
+    ) {
+        let table: String = database_ops::get_table_name("kraken", ticker);
+        for query in [
+            format!("DROP TABLE IF EXISTS {}", table),
+            format!(
+                "CREATE TABLE {} (id BIGINT PRIMARY KEY, \
+                price DECIMAL(20,10) NOT NULL, \
+                volume DECIMAL(20,10) NOT NULL, time BIGINT NOT NULL, \
+                buy_sell CHAR(1) NOT NULL, market_limit CHAR(1) NOT NULL, \
+                misc VARCHAR(16))",
+                table
+            )
+        ] {
+            sqlx::query(&query).execute(db_pool).await.unwrap();
+        };
+        for (i, price) in prices.iter().enumerate() {
+            sqlx::query(&format!(
+                "INSERT INTO {} VALUES ($1, $2, 1, $3, 'b', 'm', '')", 
+                table
+            ))
+                .bind(i as i64 + 1)
+                .bind(*price)
+                .bind(((start + i as u64) * 1_000_000) as i64)
+                .execute(db_pool)
+                .await
+                .unwrap();
+        };
+    }
+
+    async fn drop_paper_ticks(ticker: &str, db_pool: &sqlx::PgPool) {
+        sqlx::query(&format!(
+            "DROP TABLE {}", 
+            database_ops::get_table_name("kraken", ticker)
+        ))
+            .execute(db_pool)
+            .await
+            .unwrap();
+    }
+
+    fn paper_order(ticker: &str, side: portfolio::OrderSide, at: u64) 
+        -> crate::paper::NewOrder {
+        crate::paper::NewOrder {
+            exchange: "kraken".to_string(),
+            ticker: ticker.parse().unwrap(),
+            side,
+            volume: 2.0,
+            limit: None,
+            stop: None,
+            at: Some(at),
+            contingent: crate::paper::Contingent::None,
+        }
+    }
+
+    #[tokio::test]
+    async fn paper_concurrent_sync_test() {
+
+        use crate::paper::{self, CostModels, OrderStatus};
+
+        const TICKER: &str = "PAPERSYNCUSD";
+        const ACCOUNT: &str = "concurrent_sync_test";
+        const START: u64 = 1_700_000_000;
+
+        let db: Db = Db::new().await.unwrap();
+        let pool = db.get_pool();
+        let models = || CostModels::new(0, reqwest::Client::new());
+
+        store_paper_ticks(TICKER, START, &[100.0; 4], &pool).await;
+        paper::reset_account(ACCOUNT, 1000.0, &pool).await.unwrap();
+
+        let order = paper::place_order(
+            ACCOUNT, 
+            paper_order(TICKER, portfolio::OrderSide::Buy, START), 
+            &mut models(), 
+            &pool
+        ).await.unwrap();
+
+        let (mut first, mut second) = (models(), models());
+        let (a, b) = tokio::join!(
+            paper::sync(ACCOUNT, &mut first, &pool),
+            paper::sync(ACCOUNT, &mut second, &pool)
+        );
+        let fills: usize = a.unwrap().len() + b.unwrap().len();
+
+        let account = paper::load_account(ACCOUNT, &pool).await.unwrap();
+        let history = paper::order_history(ACCOUNT, &pool).await.unwrap();
+
+        paper::reset_account(ACCOUNT, 1000.0, &pool).await.unwrap();
+        drop_paper_ticks(TICKER, &pool).await;
+
+        assert_eq!(fills, 1);
+        assert_eq!(history[0].id, order.id);
+        assert_eq!(history[0].status, OrderStatus::Filled);
+        assert_eq!(account.cash, 800.0);
+        assert_eq!(account.positions.len(), 1);
+        assert_eq!(account.positions[0].volume, 2.0);
+    }
+
+}
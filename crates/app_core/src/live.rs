@@ -0,0 +1,199 @@
+use std::collections::BTreeMap;
+
+use reqwest::Client;
+
+use backtest::OrderSide;
+use database_ops::{
+    Ticker,
+    kraken::{TickerQuote, exchange_error, request_ticker_from_kraken},
+    kraken_private::{
+        AddedOrder,
+        KrakenCredentials,
+        KrakenOrder,
+        KrakenOrderRequest,
+        KrakenTrade,
+        add_order,
+        cancel_order as cancel_kraken_order,
+        edit_order,
+        request_balances,
+        request_open_orders,
+        request_trades_history,
+    },
+};
+
+use crate::{app_state::LiveTradingSettings, errors::LiveError};
+
+
+/// The only exchange orders can be placed on so far
+pub const LIVE_EXCHANGE: &str = "kraken";
+
+
+/// An order to place on the exchange, see `place_order`
+#[derive(Debug, Clone)]
+pub struct LiveOrder {
+    pub exchange: String,
+    pub ticker: Ticker,
+    pub side: OrderSide,
+    pub volume: f64,
+    /// Market order without one
+    pub limit: Option<f64>,
+    /// Have the exchange check the order without placing it
+    pub validate: bool,
+}
+
+
+/// The Kraken key to sign requests with, once live trading is on and one is
+/// configured
+pub fn kraken_credentials(settings: &LiveTradingSettings)
+    -> Result<KrakenCredentials, LiveError> {
+
+    if !settings.enabled {
+        return Err(LiveError::Disabled)
+    };
+
+    let (key, secret) = settings
+        .kraken_keys()
+        .ok_or(LiveError::MissingKeys)?;
+
+    KrakenCredentials::new(&key, &secret).map_err(LiveError::InvalidKeys)
+}
+
+/// The account's balance of every asset it holds
+pub async fn balances(settings: &LiveTradingSettings, client: &Client)
+    -> Result<BTreeMap<String, f64>, LiveError> {
+
+    let credentials = kraken_credentials(settings)?;
+
+    request_balances(&credentials, client)
+        .await
+        .map_err(|e| LiveError::Exchange(exchange_error(e)))
+}
+
+/// The account's open orders, oldest first
+pub async fn open_orders(settings: &LiveTradingSettings, client: &Client)
+    -> Result<Vec<KrakenOrder>, LiveError> {
+
+    let credentials = kraken_credentials(settings)?;
+
+    request_open_orders(&credentials, client)
+        .await
+        .map_err(|e| LiveError::Exchange(exchange_error(e)))
+}
+
+/// Every fill of the account's orders, oldest first
+pub async fn trade_history(settings: &LiveTradingSettings, client: &Client)
+    -> Result<Vec<KrakenTrade>, LiveError> {
+
+    let credentials = kraken_credentials(settings)?;
+
+    request_trades_history(&credentials, client)
+        .await
+        .map_err(|e| LiveError::Exchange(exchange_error(e)))
+}
+
+/// Places `order` on the exchange, once it's checked against
+/// `max_order_value`
+///
+/// Limit orders are valued at their price and market orders at the side of
+/// the book they'd take, so the check holds for both before anything is
+/// sent.
+pub async fn place_order(
+    settings: &LiveTradingSettings,
+    order: &LiveOrder,
+    client: &Client
+) -> Result<AddedOrder, LiveError> {
+
+    if order.exchange != LIVE_EXCHANGE {
+        return Err(LiveError::UnsupportedExchange(order.exchange.clone()))
+    };
+
+    let credentials = kraken_credentials(settings)?;
+
+    let price: f64 = match order.limit {
+        Some(limit) => limit,
+        None => {
+            let quote: TickerQuote = request_ticker_from_kraken(
+                order.ticker.as_str(),
+                client
+            )
+                .await
+                .map_err(|e| LiveError::Exchange(exchange_error(e)))?;
+            match order.side {
+                OrderSide::Buy => quote.ask,
+                OrderSide::Sell => quote.bid,
+            }
+        }
+    };
+
+    let value: f64 = price * order.volume;
+    if value > settings.max_order_value {
+        return Err(LiveError::OverLimit {
+            value,
+            limit: settings.max_order_value
+        })
+    };
+
+    let request = KrakenOrderRequest {
+        pair: order.ticker.to_string(),
+        side: order.side.to_string(),
+        volume: order.volume,
+        limit: order.limit,
+        validate: order.validate,
+    };
+
+    add_order(&request, &credentials, client)
+        .await
+        .map_err(|e| LiveError::Exchange(exchange_error(e)))
+}
+
+/// Cancels the open order `txid`
+pub async fn cancel_order(
+    settings: &LiveTradingSettings,
+    txid: &str,
+    client: &Client
+) -> Result<(), LiveError> {
+
+    let credentials = kraken_credentials(settings)?;
+
+    let cancelled: u32 = cancel_kraken_order(txid, &credentials, client)
+        .await
+        .map_err(|e| LiveError::Exchange(exchange_error(e)))?;
+
+    match cancelled {
+        0 => Err(LiveError::NoOrder(txid.to_string())),
+        _ => Ok(())
+    }
+}
+
+/// Moves the price of the open order `txid`, once the volume left to fill
+/// at the new price is checked against `max_order_value`
+pub async fn modify_order(
+    settings: &LiveTradingSettings,
+    txid: &str,
+    price: f64,
+    client: &Client
+) -> Result<AddedOrder, LiveError> {
+
+    let credentials = kraken_credentials(settings)?;
+
+    let Some(order) = request_open_orders(&credentials, client)
+        .await
+        .map_err(|e| LiveError::Exchange(exchange_error(e)))?
+        .into_iter()
+        .find(|o| o.txid == txid && o.price.is_some())
+    else {
+        return Err(LiveError::NoOrder(txid.to_string()))
+    };
+
+    let value: f64 = price * (order.volume - order.volume_filled);
+    if value > settings.max_order_value {
+        return Err(LiveError::OverLimit {
+            value,
+            limit: settings.max_order_value
+        })
+    };
+
+    edit_order(txid, &order.pair, price, &credentials, client)
+        .await
+        .map_err(|e| LiveError::Exchange(exchange_error(e)))
+}
@@ -0,0 +1,131 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use sqlx::PgPool;
+
+use database_ops::fetch_exchanges_and_pairs_from_db;
+
+
+/// How long a loaded list of pairs is served before it's read again
+pub const PAIR_CACHE_TTL: Duration = Duration::from_secs(30);
+
+
+#[derive(Debug, Default)]
+struct CachedPairs {
+    pairs: HashMap<String, Vec<String>>,
+    loaded_at: Option<Instant>,
+    /// Bumped by `invalidate`, so a load that started before it doesn't
+    /// count as fresh
+    generation: u64,
+    loading: bool,
+}
+
+
+// ------------------------------- PAIR CACHE ------------------------------ //
+/// The stored pairs by exchange, as `fetch_exchanges_and_pairs_from_db`
+/// returns them, read from the database at most once per `ttl`
+///
+/// Screens read it on every frame without waiting, and reload it in the
+/// background once it's stale. Cheap to clone, and every clone shares the
+/// same list. Anything that adds or drops tables should `invalidate` it.
+#[derive(Debug, Clone)]
+pub struct PairCache {
+    cached: Arc<Mutex<CachedPairs>>,
+    ttl: Duration,
+}
+
+impl Default for PairCache {
+    fn default() -> Self {
+        Self::new(PAIR_CACHE_TTL)
+    }
+}
+
+impl PairCache {
+
+    pub fn new(ttl: Duration) -> Self {
+        PairCache { cached: Arc::default(), ttl }
+    }
+
+    /// A poisoned lock only means a load panicked, and the last list is
+    /// still good to serve
+    fn lock(&self) -> MutexGuard<'_, CachedPairs> {
+        self.cached.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The pairs as last loaded, empty until the first load finishes
+    pub fn pairs(&self) -> HashMap<String, Vec<String>> {
+        self.lock().pairs.clone()
+    }
+
+    pub fn is_stale(&self) -> bool {
+        match self.lock().loaded_at {
+            Some(t) => t.elapsed() >= self.ttl,
+            None => true
+        }
+    }
+
+    /// Makes the next read go to the database
+    pub fn invalidate(&self) {
+        let mut cached = self.lock();
+        cached.loaded_at = None;
+        cached.generation += 1;
+    }
+
+    /// Reads the pairs from the database now
+    pub async fn refresh(&self, db_pool: PgPool) 
+        -> HashMap<String, Vec<String>> {
+
+        let generation: u64 = self.lock().generation;
+        let pairs: HashMap<String, Vec<String>> = 
+            fetch_exchanges_and_pairs_from_db(db_pool, false)
+                .await
+                .into_iter()
+                .map(|(exchange, tickers)| (
+                    exchange,
+                    tickers.into_iter().map(|(t, _)| t).collect()
+                ))
+                .collect();
+
+        let mut cached = self.lock();
+        cached.pairs = pairs.clone();
+        cached.loading = false;
+        if cached.generation == generation {
+            cached.loaded_at = Some(Instant::now());
+        };
+
+        pairs
+    }
+
+    /// The pairs, read from the database first when stale
+    pub async fn load(&self, db_pool: PgPool) 
+        -> HashMap<String, Vec<String>> {
+        match self.is_stale() {
+            true => self.refresh(db_pool).await,
+            false => self.pairs()
+        }
+    }
+
+    /// Reloads the pairs in a background task when stale, one load at a time
+    pub fn refresh_if_stale(&self, db_pool: PgPool) {
+
+        if !self.is_stale() {
+            return
+        };
+
+        {
+            let mut cached = self.lock();
+            if cached.loading {
+                return
+            };
+            cached.loading = true;
+        }
+
+        let cache = self.clone();
+        tokio::spawn(async move {
+            cache.refresh(db_pool).await;
+        });
+    }
+}
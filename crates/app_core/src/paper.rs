@@ -0,0 +1,1794 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    str::FromStr
+};
+
+use reqwest::Client;
+use serde::{Serialize, Serializer};
+use sqlx::{PgConnection, PgPool};
+
+use backtest::{CostModel, Liquidity, OrderSide};
+use database_ops::{
+    DbError,
+    Ticker,
+    fetch_tables,
+    get_table_name,
+    kraken::{AssetPairInfo, request_asset_info_from_kraken}
+};
+use portfolio::{Holding, RebalanceOrder, compute_rebalance_orders};
+use timestamp_tools::{db_timestamp_to_date_string, get_current_unix_timestamp};
+
+use crate::{backtest_cost_model, errors::PaperError};
+
+
+/// Account used when none is named
+pub const DEFAULT_ACCOUNT: &str = "default";
+
+/// Cash a new account starts with, in the quote currency
+pub const DEFAULT_CASH: f64 = 10_000.0;
+
+const MICROS_PER_DAY: u64 = 86_400_000_000;
+
+
+/// Simulated accounts, by name. Cash is in the quote currency.
+const CREATE_ACCOUNT_TABLE: &str = r#"
+    CREATE TABLE IF NOT EXISTS _paper_accounts (
+        name VARCHAR(32) PRIMARY KEY,
+        starting_cash DOUBLE PRECISION NOT NULL,
+        cash DOUBLE PRECISION NOT NULL,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    );
+"#;
+
+/// Held volume of each pair, and what it was bought for with fees
+const CREATE_POSITION_TABLE: &str = r#"
+    CREATE TABLE IF NOT EXISTS _paper_positions (
+        account VARCHAR(32) NOT NULL,
+        exchange VARCHAR(16) NOT NULL,
+        asset VARCHAR(12) NOT NULL,
+        volume DOUBLE PRECISION NOT NULL,
+        cost DOUBLE PRECISION NOT NULL,
+        PRIMARY KEY (account, exchange, asset)
+    );
+"#;
+
+/// Every order placed, and how it ended
+///
+/// `placed_tick` is the ID of the pair's last tick when the order was
+/// placed, so only the ticks after it can fill the order. Times are in
+/// microseconds, like the ticks'. Orders sharing a `group_id` cancel each
+/// other when one fills, and an order with a `parent_id` is pending until
+/// that order fills.
+const CREATE_ORDER_TABLE: &str = r#"
+    CREATE TABLE IF NOT EXISTS _paper_orders (
+        id BIGSERIAL PRIMARY KEY,
+        account VARCHAR(32) NOT NULL,
+        exchange VARCHAR(16) NOT NULL,
+        asset VARCHAR(12) NOT NULL,
+        side VARCHAR(4) NOT NULL,
+        limit_price DOUBLE PRECISION,
+        stop_price DOUBLE PRECISION,
+        volume DOUBLE PRECISION NOT NULL,
+        status VARCHAR(9) NOT NULL DEFAULT 'open',
+        placed_tick BIGINT NOT NULL,
+        placed_time BIGINT NOT NULL,
+        fill_price DOUBLE PRECISION,
+        fee DOUBLE PRECISION,
+        fill_tick BIGINT,
+        fill_time BIGINT,
+        note TEXT,
+        group_id BIGINT,
+        parent_id BIGINT
+    );
+"#;
+
+/// Each account's equity over time, one row per `record_equity`. Times are
+/// Unix timestamps (seconds).
+const CREATE_EQUITY_TABLE: &str = r#"
+    CREATE TABLE IF NOT EXISTS _paper_equity (
+        account VARCHAR(32) NOT NULL,
+        time BIGINT NOT NULL,
+        equity DOUBLE PRECISION NOT NULL,
+        PRIMARY KEY (account, time)
+    );
+"#;
+
+/// Adds the stop and grouping columns to order tables created before them
+const MIGRATE_ORDER_TABLE: &str = r#"
+    ALTER TABLE _paper_orders
+        ADD COLUMN IF NOT EXISTS stop_price DOUBLE PRECISION,
+        ADD COLUMN IF NOT EXISTS group_id BIGINT,
+        ADD COLUMN IF NOT EXISTS parent_id BIGINT;
+"#;
+
+const ORDER_COLUMNS: &str = "id, exchange, asset, side, limit_price, \
+    stop_price, volume, status, placed_tick, placed_time, fill_price, fee, \
+    fill_tick, fill_time, note, group_id, parent_id";
+
+/// A row of `ORDER_COLUMNS`, past what tuples can be read into
+#[derive(sqlx::FromRow)]
+struct OrderRow {
+    id: i64,
+    exchange: String,
+    asset: String,
+    side: String,
+    limit_price: Option<f64>,
+    stop_price: Option<f64>,
+    volume: f64,
+    status: String,
+    placed_tick: i64,
+    placed_time: i64,
+    fill_price: Option<f64>,
+    fee: Option<f64>,
+    fill_tick: Option<i64>,
+    fill_time: Option<i64>,
+    note: Option<String>,
+    group_id: Option<i64>,
+    parent_id: Option<i64>,
+}
+
+
+// ------------------------------- ORDERS ---------------------------------- //
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderStatus {
+    /// Waits for its parent order to fill, like the exits of a bracket
+    Pending,
+    Open,
+    Filled,
+    Cancelled,
+    /// The account couldn't cover it, or it broke the pair's minimums,
+    /// when it would have filled
+    Rejected,
+}
+
+impl FromStr for OrderStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(OrderStatus::Pending),
+            "open" => Ok(OrderStatus::Open),
+            "filled" => Ok(OrderStatus::Filled),
+            "cancelled" => Ok(OrderStatus::Cancelled),
+            "rejected" => Ok(OrderStatus::Rejected),
+            _ => Err(format!("Unknown order status: {}", s))
+        }
+    }
+}
+
+impl fmt::Display for OrderStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrderStatus::Pending => write!(f, "pending"),
+            OrderStatus::Open => write!(f, "open"),
+            OrderStatus::Filled => write!(f, "filled"),
+            OrderStatus::Cancelled => write!(f, "cancelled"),
+            OrderStatus::Rejected => write!(f, "rejected"),
+        }
+    }
+}
+
+/// Orders placed along with an order, see `place_order`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Contingent {
+    #[default]
+    None,
+    /// A stop order of the same side and volume next to a limit order,
+    /// the first of them to fill cancels the other
+    Oco { stop: f64 },
+    /// A stop loss and a take profit closing the position once the order
+    /// fills, the first of them to fill cancels the other
+    Bracket { stop_loss: f64, take_profit: f64 },
+}
+
+impl Contingent {
+
+    /// From the prices given with an order: an OCO stop, or a stop loss 
+    /// and a take profit together
+    pub fn from_prices(
+        oco_stop: Option<f64>,
+        stop_loss: Option<f64>,
+        take_profit: Option<f64>
+    ) -> Result<Self, String> {
+        match (oco_stop, stop_loss, take_profit) {
+            (None, None, None) => Ok(Contingent::None),
+            (Some(stop), None, None) => Ok(Contingent::Oco { stop }),
+            (None, Some(stop_loss), Some(take_profit)) => {
+                Ok(Contingent::Bracket { stop_loss, take_profit })
+            },
+            (None, _, _) => Err(
+                "A bracket needs both a stop loss and a take profit"
+                    .to_string()
+            ),
+            (Some(_), _, _) => Err(
+                "An order can have an OCO stop or a bracket, not both"
+                    .to_string()
+            )
+        }
+    }
+}
+
+/// An order to place, see `place_order`
+#[derive(Debug, Clone)]
+pub struct NewOrder {
+    pub exchange: String,
+    pub ticker: Ticker,
+    pub side: OrderSide,
+    pub volume: f64,
+    /// Market order without one
+    pub limit: Option<f64>,
+    /// Waits for a tick at or past this price, then fills at market. Not
+    /// with a `limit`.
+    pub stop: Option<f64>,
+    /// Unix time (seconds) to place the order at instead of now, so the
+    /// stored ticks after it replay against it
+    pub at: Option<u64>,
+    pub contingent: Contingent,
+}
+
+/// A stored order
+#[derive(Debug, Clone, Serialize)]
+pub struct PaperOrder {
+    pub id: i64,
+    pub exchange: String,
+    pub ticker: String,
+    #[serde(serialize_with = "side_name")]
+    pub side: OrderSide,
+    /// `None` for market and stop orders
+    pub limit_price: Option<f64>,
+    pub stop_price: Option<f64>,
+    pub volume: f64,
+    pub status: OrderStatus,
+    pub placed_tick: u64,
+    /// Microseconds since the epoch
+    pub placed_time: u64,
+    pub fill_price: Option<f64>,
+    pub fee: Option<f64>,
+    pub fill_tick: Option<u64>,
+    pub fill_time: Option<u64>,
+    /// Why the order was rejected or cancelled, or whether it filled as
+    /// maker or taker
+    pub note: Option<String>,
+    /// The OCO or bracket the order is part of
+    pub group_id: Option<i64>,
+    /// The entry of the bracket the order is an exit of
+    pub parent_id: Option<i64>,
+}
+
+impl PaperOrder {
+
+    fn from_row(row: OrderRow) -> Self {
+        PaperOrder {
+            id: row.id,
+            exchange: row.exchange,
+            ticker: row.asset,
+            side: match row.side.as_str() {
+                "sell" => OrderSide::Sell,
+                _ => OrderSide::Buy
+            },
+            limit_price: row.limit_price,
+            stop_price: row.stop_price,
+            volume: row.volume,
+            status: row.status.parse().unwrap_or(OrderStatus::Rejected),
+            placed_tick: row.placed_tick as u64,
+            placed_time: row.placed_time as u64,
+            fill_price: row.fill_price,
+            fee: row.fee,
+            fill_tick: row.fill_tick.map(|t| t as u64),
+            fill_time: row.fill_time.map(|t| t as u64),
+            note: row.note,
+            group_id: row.group_id,
+            parent_id: row.parent_id,
+        }
+    }
+
+    /// "market", "limit PRICE" or "stop PRICE"
+    pub fn kind(&self) -> String {
+        match (self.limit_price, self.stop_price) {
+            (Some(p), _) => format!("limit {}", p),
+            (None, Some(p)) => format!("stop {}", p),
+            (None, None) => "market".to_string()
+        }
+    }
+
+    /// e.g. "bracket #12" for an exit of order 12, or "oco #12"
+    pub fn link(&self) -> Option<String> {
+        match (self.parent_id, self.group_id) {
+            (Some(parent), _) => Some(format!("bracket #{}", parent)),
+            (None, Some(group)) => Some(format!("oco #{}", group)),
+            (None, None) => None
+        }
+    }
+}
+
+impl fmt::Display for PaperOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+
+        write!(
+            f,
+            "#{} {} {} {} {} {} {}",
+            self.id,
+            db_timestamp_to_date_string(self.placed_time),
+            self.side,
+            self.volume,
+            self.ticker,
+            self.kind(),
+            self.status
+        )?;
+
+        if let Some(link) = self.link() {
+            write!(f, " ({})", link)?;
+        };
+        if let (Some(price), Some(fee)) = (self.fill_price, self.fee) {
+            write!(f, " at {} (fee {:.2})", price, fee)?;
+        };
+        if let Some(note) = &self.note {
+            write!(f, ": {}", note)?;
+        };
+
+        Ok(())
+    }
+}
+
+fn side_name<S: Serializer>(side: &OrderSide, s: S)
+    -> Result<S::Ok, S::Error> {
+    s.collect_str(side)
+}
+
+
+// ------------------------------ ACCOUNTS --------------------------------- //
+/// Account names are up to 32 letters, digits, - and _
+pub fn is_account_name(name: &str) -> bool {
+    !name.is_empty() 
+        && name.len() <= 32 
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || "-_".contains(c))
+}
+
+/// A pair held by an account
+#[derive(Debug, Clone, Serialize)]
+pub struct PaperPosition {
+    pub exchange: String,
+    pub ticker: String,
+    pub volume: f64,
+    /// What the volume was bought for, fees included
+    pub cost: f64,
+    /// The pair's last stored price, `None` if it can't be read
+    pub last_price: Option<f64>,
+}
+
+impl PaperPosition {
+
+    /// Marked at the last price, or at cost without one
+    pub fn value(&self) -> f64 {
+        match self.last_price {
+            Some(p) => self.volume * p,
+            None => self.cost
+        }
+    }
+}
+
+/// An account's balances, positions and open orders
+#[derive(Debug, Clone, Serialize)]
+pub struct PaperAccount {
+    pub name: String,
+    pub starting_cash: f64,
+    pub cash: f64,
+    /// Cash plus the positions at their last prices
+    pub equity: f64,
+    pub positions: Vec<PaperPosition>,
+    /// Pending bracket exits included
+    pub open_orders: Vec<PaperOrder>,
+}
+
+impl PaperAccount {
+
+    /// Profit or loss since the account was opened or reset, in percent
+    pub fn return_pct(&self) -> f64 {
+        match self.starting_cash > 0.0 {
+            true => (self.equity / self.starting_cash - 1.0) * 100.0,
+            false => 0.0
+        }
+    }
+}
+
+impl fmt::Display for PaperAccount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+
+        writeln!(f, "\x1b[1;36mPaper account {}\x1b[0m", self.name)?;
+        writeln!(f, "  Cash:    {:.2}", self.cash)?;
+        writeln!(
+            f,
+            "  Equity:  {:.2} ({:+.2}% on {:.2})",
+            self.equity,
+            self.return_pct(),
+            self.starting_cash
+        )?;
+
+        writeln!(f, "\x1b[1mPositions\x1b[0m")?;
+        if self.positions.is_empty() {
+            writeln!(f, "  None")?;
+        };
+        for p in &self.positions {
+            writeln!(
+                f,
+                "  {} {:<10} {:>14} at {:>12}  value {:.2}  cost {:.2}",
+                p.exchange,
+                p.ticker,
+                p.volume,
+                match p.last_price {
+                    Some(price) => price.to_string(),
+                    None => "?".to_string()
+                },
+                p.value(),
+                p.cost
+            )?;
+        };
+
+        writeln!(f, "\x1b[1mOpen orders\x1b[0m")?;
+        if self.open_orders.is_empty() {
+            writeln!(f, "  None")?;
+        };
+        for order in &self.open_orders {
+            writeln!(f, "  {}", order)?;
+        };
+
+        Ok(())
+    }
+}
+
+
+/// Cost models of the pairs traded, fetched from the exchange once each
+///
+/// Pairs whose info can't be fetched trade with slippage only, like in
+/// backtests.
+pub struct CostModels {
+    slippage_bps: u32,
+    client: Client,
+    models: HashMap<String, CostModel>,
+}
+
+impl CostModels {
+
+    pub fn new(slippage_bps: u32, client: Client) -> Self {
+        CostModels { slippage_bps, client, models: HashMap::new() }
+    }
+
+    pub async fn get(&mut self, ticker: &str)
+        -> Result<&CostModel, PaperError> {
+
+        if !self.models.contains_key(ticker) {
+            let (model, _) = backtest_cost_model(
+                ticker,
+                self.slippage_bps,
+                &self.client
+            )
+                .await
+                .map_err(|e| PaperError::OrderRejected(e.to_string()))?;
+            self.models.insert(ticker.to_string(), model);
+        };
+
+        Ok(&self.models[ticker])
+    }
+}
+
+
+fn query_failed(what: &'static str) -> impl Fn(sqlx::Error) -> PaperError {
+    move |e| PaperError::Db(
+        DbError::QueryFailed(format!("{}: {}", what, e))
+    )
+}
+
+/// Creates the paper trading tables, if they're not there yet
+async fn create_tables(db_pool: &PgPool) -> Result<(), PaperError> {
+    for table in [
+        CREATE_ACCOUNT_TABLE,
+        CREATE_POSITION_TABLE,
+        CREATE_ORDER_TABLE,
+        MIGRATE_ORDER_TABLE,
+        CREATE_EQUITY_TABLE
+    ] {
+        sqlx::query(table)
+            .execute(db_pool)
+            .await
+            .map_err(|e| PaperError::Db(
+                DbError::TableCreationFailed(e.to_string())
+            ))?;
+    };
+    Ok(())
+}
+
+/// Creates the tables, and the account with `DEFAULT_CASH` if it's new
+async fn open_account(account: &str, db_pool: &PgPool)
+    -> Result<(), PaperError> {
+
+    create_tables(db_pool).await?;
+
+    sqlx::query(r#"
+        INSERT INTO _paper_accounts (name, starting_cash, cash)
+        VALUES ($1, $2, $2)
+        ON CONFLICT (name) DO NOTHING;
+    "#)
+        .bind(account)
+        .bind(DEFAULT_CASH)
+        .execute(db_pool)
+        .await
+        .map_err(query_failed("Couldn't open the paper account"))?;
+
+    Ok(())
+}
+
+/// Fails with `NoAccount` unless `account` has been opened. Unlike
+/// `open_account` it creates nothing, for the commands that only read.
+async fn require_account(account: &str, db_pool: &PgPool)
+    -> Result<(), PaperError> {
+
+    let tables: Option<String> = sqlx::query_scalar(
+        "SELECT to_regclass('_paper_accounts')::TEXT"
+    )
+        .fetch_one(db_pool)
+        .await
+        .map_err(query_failed("Couldn't look for the paper tables"))?;
+
+    let found: Option<i32> = match tables {
+        Some(_) => sqlx::query_scalar(
+            "SELECT 1 FROM _paper_accounts WHERE name = $1"
+        )
+            .bind(account)
+            .fetch_optional(db_pool)
+            .await
+            .map_err(query_failed("Couldn't read the paper account"))?,
+        None => None
+    };
+
+    match found {
+        Some(_) => Ok(()),
+        None => Err(PaperError::NoAccount(account.to_string()))
+    }
+}
+
+/// The pair's last tick at or before `at` (unix seconds), or its last
+/// tick, as its ID, time and price
+async fn tick_before(
+    exchange: &str,
+    ticker: &str,
+    at: Option<u64>,
+    db_pool: &PgPool
+) -> Result<Option<(u64, u64, f64)>, PaperError> {
+
+    let query: String = format!(
+        r#"
+        SELECT id, time, price::float8 FROM {}
+        WHERE time <= $1
+        ORDER BY id DESC
+        LIMIT 1;
+        "#,
+        get_table_name(exchange, ticker)
+    );
+
+    let before: i64 = match at {
+        Some(t) => t.saturating_mul(1_000_000).min(i64::MAX as u64) as i64,
+        None => i64::MAX
+    };
+
+    sqlx::query_as::<_, (i64, i64, f64)>(&query)
+        .bind(before)
+        .fetch_optional(db_pool)
+        .await
+        .map(|row| row.map(|(i, t, p)| (i as u64, t as u64, p)))
+        .map_err(query_failed("Couldn't read the pair's ticks"))
+}
+
+/// The first tick after the order was placed whose price is at or past
+/// `crossing`, where a buy at that price would fill, or the first one at
+/// all without it
+///
+/// A buy limit crosses at or below its price, a sell limit at or above it.
+/// Stops cross the other way, so are given with the other side.
+async fn tick_after(
+    order: &PaperOrder,
+    crossing: Option<(OrderSide, f64)>,
+    db_pool: &PgPool
+) -> Result<Option<(u64, u64, f64)>, PaperError> {
+
+    let limit: Option<f64> = crossing.map(|(_, price)| price);
+    let crossing: &str = match crossing {
+        None => "",
+        Some((OrderSide::Buy, _)) => "AND price <= $2",
+        Some((OrderSide::Sell, _)) => "AND price >= $2",
+    };
+
+    let sql: String = format!(
+        r#"
+        SELECT id, time, price::float8 FROM {}
+        WHERE id > $1 {}
+        ORDER BY id
+        LIMIT 1;
+        "#,
+        get_table_name(&order.exchange, &order.ticker),
+        crossing
+    );
+
+    let mut query = sqlx::query_as::<_, (i64, i64, f64)>(&sql)
+        .bind(order.placed_tick.min(i64::MAX as u64) as i64);
+    if let Some(limit) = limit {
+        query = query.bind(limit);
+    };
+
+    query
+        .fetch_optional(db_pool)
+        .await
+        .map(|row| row.map(|(i, t, p)| (i as u64, t as u64, p)))
+        .map_err(query_failed("Couldn't read the pair's ticks"))
+}
+
+async fn fetch_orders(
+    account: &str,
+    filter: &str,
+    db_pool: &PgPool
+) -> Result<Vec<PaperOrder>, PaperError> {
+
+    let query: String = format!(
+        "SELECT {} FROM _paper_orders WHERE account = $1 {}",
+        ORDER_COLUMNS,
+        filter
+    );
+
+    sqlx::query_as::<_, OrderRow>(&query)
+        .bind(account)
+        .fetch_all(db_pool)
+        .await
+        .map(|rows| rows.into_iter().map(PaperOrder::from_row).collect())
+        .map_err(query_failed("Couldn't read the paper orders"))
+}
+
+async fn fetch_order(id: i64, db_pool: &PgPool)
+    -> Result<PaperOrder, PaperError> {
+
+    let query: String = format!(
+        "SELECT {} FROM _paper_orders WHERE id = $1",
+        ORDER_COLUMNS
+    );
+
+    sqlx::query_as::<_, OrderRow>(&query)
+        .bind(id)
+        .fetch_optional(db_pool)
+        .await
+        .map_err(query_failed("Couldn't read the paper order"))?
+        .map(PaperOrder::from_row)
+        .ok_or(PaperError::NoOrder(id))
+}
+
+
+async fn fetch_orders_by_id(ids: &[i64], db_pool: &PgPool)
+    -> Result<Vec<PaperOrder>, PaperError> {
+
+    let mut orders: Vec<PaperOrder> = Vec::with_capacity(ids.len());
+    for id in ids {
+        orders.push(fetch_order(*id, db_pool).await?);
+    };
+
+    Ok(orders)
+}
+
+
+/// Places an order in `account`, opening the account if it's new, and
+/// returns it as stored
+///
+/// The order is placed after the pair's last stored tick, or with `at`
+/// after the last one before then, and fills on the ticks that follow,
+/// see `sync`. The volume is rounded to the pair's lot size and its prices
+/// to the tick size, in the order's favour. Orders below the pair's
+/// minimums, or that the account couldn't cover at the reference price,
+/// are refused here.
+///
+/// An OCO stop is stored as a second open order, and a bracket's exits as
+/// pending orders that open once this one fills, see `contingent_orders`.
+pub async fn place_order(
+    account: &str,
+    order: NewOrder,
+    cost_models: &mut CostModels,
+    db_pool: &PgPool
+) -> Result<PaperOrder, PaperError> {
+
+    // Times in microseconds have to fit the table's BIGINT
+    let placed_time: u64 = match order.at {
+        Some(at) => at
+            .checked_mul(1_000_000)
+            .filter(|t| *t <= i64::MAX as u64)
+            .ok_or_else(|| PaperError::OrderRejected(format!(
+                "Can't place an order at {}, it's too far in the future",
+                at
+            )))?,
+        None => get_current_unix_timestamp() * 1_000_000
+    };
+
+    open_account(account, db_pool).await?;
+
+    let exchange: String = order.exchange.to_lowercase();
+    let ticker: &str = order.ticker.as_str();
+
+    // Orders come from the API too, so anything but a stored pair's table
+    // is kept out of the queries
+    let tables: Vec<String> = fetch_tables(db_pool.clone())
+        .await
+        .map_err(PaperError::Db)?;
+    if !tables.contains(&get_table_name(&exchange, ticker)) {
+        return Err(PaperError::NoData {
+            exchange,
+            ticker: ticker.to_string()
+        })
+    };
+
+    let Some((tick_id, _, last_price)) = tick_before(
+        &exchange,
+        ticker,
+        order.at,
+        db_pool
+    ).await? else {
+        return Err(PaperError::NoData {
+            exchange,
+            ticker: ticker.to_string()
+        })
+    };
+
+    let model: &CostModel = cost_models.get(ticker).await?;
+
+    let volume: f64 = model.round_volume(order.volume);
+    // Rounded in the order's favour, a limit or take profit towards the
+    // book, a stop or stop loss away from it
+    let toward = |price: f64, side: OrderSide| match side {
+        OrderSide::Buy => model.round_price(price, OrderSide::Sell),
+        OrderSide::Sell => model.round_price(price, OrderSide::Buy),
+    };
+    let away = |price: f64, side: OrderSide| model.round_price(price, side);
+
+    let limit: Option<f64> = order.limit.map(|l| toward(l, order.side));
+    let stop: Option<f64> = order.stop.map(|s| away(s, order.side));
+    let price: f64 = limit.or(stop).unwrap_or(last_price);
+
+    model
+        .check_minimums(volume, price)
+        .map_err(|e| PaperError::OrderRejected(e.to_string()))?;
+
+    let exit_side: OrderSide = match order.side {
+        OrderSide::Buy => OrderSide::Sell,
+        OrderSide::Sell => OrderSide::Buy,
+    };
+
+    // Prices below the order's, then above it, for a buy
+    let (below, above): (f64, f64) = match order.contingent {
+        Contingent::None => (price, price),
+        Contingent::Oco { stop } => match order.side {
+            OrderSide::Buy => (price, stop),
+            OrderSide::Sell => (stop, price),
+        },
+        Contingent::Bracket { stop_loss, take_profit } => match order.side {
+            OrderSide::Buy => (stop_loss, take_profit),
+            OrderSide::Sell => (take_profit, stop_loss),
+        },
+    };
+    let refusal: Option<&str> = match order.contingent {
+        _ if limit.is_some() && stop.is_some() => {
+            Some("An order takes a limit or a stop price, not both")
+        },
+        Contingent::Oco { .. } if limit.is_none() => {
+            Some("An OCO pairs a limit order with a stop")
+        },
+        Contingent::Oco { .. } if below >= above => Some(
+            "An OCO's stop has to be on the other side of the price from \
+            its limit"
+        ),
+        Contingent::Bracket { .. } if below >= price || price >= above => {
+            Some(
+                "A bracket's stop loss and take profit have to be on either \
+                side of the entry price"
+            )
+        },
+        _ => None
+    };
+    if let Some(refusal) = refusal {
+        return Err(PaperError::OrderRejected(refusal.to_string()))
+    };
+
+    match order.side {
+        OrderSide::Buy => {
+            let cash: f64 = sqlx::query_scalar(
+                "SELECT cash FROM _paper_accounts WHERE name = $1"
+            )
+                .bind(account)
+                .fetch_one(db_pool)
+                .await
+                .map_err(query_failed("Couldn't read the paper account"))?;
+            if volume * price > cash {
+                return Err(PaperError::InsufficientFunds(format!(
+                    "Need {:.2}, have {:.2}", volume * price, cash
+                )))
+            };
+        },
+        OrderSide::Sell => {
+            let held: f64 = sqlx::query_scalar(r#"
+                SELECT COALESCE(SUM(volume), 0) FROM _paper_positions
+                WHERE account = $1 AND exchange = $2 AND asset = $3
+            "#)
+                .bind(account)
+                .bind(&exchange)
+                .bind(ticker)
+                .fetch_one(db_pool)
+                .await
+                .map_err(query_failed("Couldn't read the paper position"))?;
+            if volume > held {
+                return Err(PaperError::InsufficientFunds(format!(
+                    "Can't sell {}, position is {}", volume, held
+                )))
+            };
+        }
+    };
+
+    let failed = query_failed("Couldn't place the paper order");
+    let mut tx = db_pool.begin().await.map_err(&failed)?;
+
+    let placed = StoredOrder {
+        account,
+        exchange: &exchange,
+        ticker,
+        side: order.side,
+        limit,
+        stop,
+        volume,
+        status: OrderStatus::Open,
+        placed_tick: tick_id,
+        placed_time,
+        group_id: None,
+        parent_id: None,
+    };
+    let id: i64 = insert_order(&mut tx, &placed).await?;
+
+    match order.contingent {
+        Contingent::None => {},
+        Contingent::Oco { stop } => {
+            sqlx::query("UPDATE _paper_orders SET group_id = id WHERE id = $1")
+                .bind(id)
+                .execute(&mut *tx)
+                .await
+                .map_err(&failed)?;
+            insert_order(&mut tx, &StoredOrder {
+                limit: None,
+                stop: Some(away(stop, order.side)),
+                group_id: Some(id),
+                ..placed
+            }).await?;
+        },
+        Contingent::Bracket { stop_loss, take_profit } => {
+            // Opened from the entry's fill, see `apply_fill`
+            let exit = StoredOrder {
+                side: exit_side,
+                status: OrderStatus::Pending,
+                group_id: Some(id),
+                parent_id: Some(id),
+                ..placed
+            };
+            insert_order(&mut tx, &StoredOrder {
+                limit: Some(toward(take_profit, exit_side)),
+                stop: None,
+                ..exit
+            }).await?;
+            insert_order(&mut tx, &StoredOrder {
+                limit: None,
+                stop: Some(away(stop_loss, exit_side)),
+                ..exit
+            }).await?;
+        },
+    };
+
+    tx.commit().await.map_err(&failed)?;
+
+    fetch_order(id, db_pool).await
+}
+
+/// The orders placed along with order `id`, its OCO stop or its bracket's
+/// exits
+pub async fn contingent_orders(account: &str, id: i64, db_pool: &PgPool)
+    -> Result<Vec<PaperOrder>, PaperError> {
+    fetch_orders(
+        account,
+        &format!(
+            "AND id <> {id} AND (group_id = {id} OR parent_id = {id}) \
+            ORDER BY id"
+        ),
+        db_pool
+    ).await
+}
+
+/// An order as it's stored, see `insert_order`
+#[derive(Clone, Copy)]
+struct StoredOrder<'a> {
+    account: &'a str,
+    exchange: &'a str,
+    ticker: &'a str,
+    side: OrderSide,
+    limit: Option<f64>,
+    stop: Option<f64>,
+    volume: f64,
+    status: OrderStatus,
+    placed_tick: u64,
+    placed_time: u64,
+    group_id: Option<i64>,
+    parent_id: Option<i64>,
+}
+
+/// Stores `order`, returning its ID
+async fn insert_order(conn: &mut PgConnection, order: &StoredOrder<'_>)
+    -> Result<i64, PaperError> {
+
+    sqlx::query_scalar(r#"
+        INSERT INTO _paper_orders (
+            account,
+            exchange,
+            asset,
+            side,
+            limit_price,
+            stop_price,
+            volume,
+            status,
+            placed_tick,
+            placed_time,
+            group_id,
+            parent_id
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+        RETURNING id;
+    "#)
+        .bind(order.account)
+        .bind(order.exchange)
+        .bind(order.ticker)
+        .bind(order.side.to_string())
+        .bind(order.limit)
+        .bind(order.stop)
+        .bind(order.volume)
+        .bind(order.status.to_string())
+        .bind(order.placed_tick as i64)
+        .bind(order.placed_time as i64)
+        .bind(order.group_id)
+        .bind(order.parent_id)
+        .fetch_one(conn)
+        .await
+        .map_err(query_failed("Couldn't place the paper order"))
+}
+
+/// Cancels the pending exits of order `parent`, and returns their IDs
+async fn cancel_exits(conn: &mut PgConnection, parent: i64, note: &str)
+    -> Result<Vec<i64>, PaperError> {
+
+    sqlx::query_scalar(r#"
+        UPDATE _paper_orders SET status = 'cancelled', note = $2
+        WHERE parent_id = $1 AND status = 'pending'
+        RETURNING id;
+    "#)
+        .bind(parent)
+        .bind(note)
+        .fetch_all(conn)
+        .await
+        .map_err(query_failed("Couldn't cancel the bracket's exits"))
+}
+
+/// Cancels the open or pending order `id` of `account`, and the exits of
+/// its bracket
+pub async fn cancel_order(account: &str, id: i64, db_pool: &PgPool)
+    -> Result<PaperOrder, PaperError> {
+
+    open_account(account, db_pool).await?;
+
+    let failed = query_failed("Couldn't cancel the paper order");
+    let mut tx = db_pool.begin().await.map_err(&failed)?;
+
+    let cancelled: u64 = sqlx::query(r#"
+        UPDATE _paper_orders SET status = 'cancelled'
+        WHERE id = $1 AND account = $2 AND status IN ('open', 'pending');
+    "#)
+        .bind(id)
+        .bind(account)
+        .execute(&mut *tx)
+        .await
+        .map_err(&failed)?
+        .rows_affected();
+
+    if cancelled == 0 {
+        return Err(PaperError::NoOrder(id))
+    };
+
+    cancel_exits(&mut tx, id, &format!("#{} was cancelled", id)).await?;
+    tx.commit().await.map_err(&failed)?;
+
+    fetch_order(id, db_pool).await
+}
+
+/// Moves the price of the open or pending order `id` of `account`, its
+/// limit or its stop, and returns the order as stored
+///
+/// The price is rounded to the pair's tick size like `place_order` rounds
+/// it, and the order only fills on the ticks stored after the change, so
+/// sync first to fill it on the ones before at its old price. The other
+/// orders of its OCO or bracket keep their prices.
+pub async fn modify_order(
+    account: &str,
+    id: i64,
+    price: f64,
+    cost_models: &mut CostModels,
+    db_pool: &PgPool
+) -> Result<PaperOrder, PaperError> {
+
+    require_account(account, db_pool).await?;
+
+    let Some(order) = fetch_orders(
+        account,
+        &format!("AND id = {} AND status IN ('open', 'pending')", id),
+        db_pool
+    )
+        .await?
+        .pop()
+    else {
+        return Err(PaperError::NoOrder(id))
+    };
+
+    let model: &CostModel = cost_models.get(&order.ticker).await?;
+
+    // Rounded in the order's favour, as when it was placed
+    let against: OrderSide = match order.side {
+        OrderSide::Buy => OrderSide::Sell,
+        OrderSide::Sell => OrderSide::Buy,
+    };
+    let (limit, stop): (Option<f64>, Option<f64>) = match (
+        order.limit_price,
+        order.stop_price
+    ) {
+        (Some(_), _) => (Some(model.round_price(price, against)), None),
+        (None, Some(_)) => (None, Some(model.round_price(price, order.side))),
+        (None, None) => return Err(PaperError::OrderRejected(
+            "A market order has no price to change".to_string()
+        ))
+    };
+
+    model
+        .check_minimums(order.volume, limit.or(stop).unwrap_or(price))
+        .map_err(|e| PaperError::OrderRejected(e.to_string()))?;
+
+    let Some((tick_id, _, _)) = tick_before(
+        &order.exchange,
+        &order.ticker,
+        None,
+        db_pool
+    ).await? else {
+        return Err(PaperError::NoData {
+            exchange: order.exchange,
+            ticker: order.ticker
+        })
+    };
+
+    let modified: u64 = sqlx::query(r#"
+        UPDATE _paper_orders
+        SET limit_price = $1, stop_price = $2, placed_tick = $3
+        WHERE id = $4 AND account = $5 AND status IN ('open', 'pending');
+    "#)
+        .bind(limit)
+        .bind(stop)
+        .bind(tick_id as i64)
+        .bind(id)
+        .bind(account)
+        .execute(db_pool)
+        .await
+        .map_err(query_failed("Couldn't change the paper order"))?
+        .rows_affected();
+
+    match modified {
+        0 => Err(PaperError::NoOrder(id)),
+        _ => fetch_order(id, db_pool).await
+    }
+}
+
+/// Empties `account`'s positions and orders, and starts it again with
+/// `cash`
+pub async fn reset_account(account: &str, cash: f64, db_pool: &PgPool)
+    -> Result<(), PaperError> {
+
+    create_tables(db_pool).await?;
+
+    let mut tx = db_pool
+        .begin()
+        .await
+        .map_err(query_failed("Couldn't reset the paper account"))?;
+
+    for query in [
+        "DELETE FROM _paper_orders WHERE account = $1",
+        "DELETE FROM _paper_positions WHERE account = $1",
+        "DELETE FROM _paper_equity WHERE account = $1",
+        "DELETE FROM _paper_accounts WHERE name = $1",
+    ] {
+        sqlx::query(query)
+            .bind(account)
+            .execute(&mut *tx)
+            .await
+            .map_err(query_failed("Couldn't reset the paper account"))?;
+    };
+
+    sqlx::query(r#"
+        INSERT INTO _paper_accounts (name, starting_cash, cash)
+        VALUES ($1, $2, $2);
+    "#)
+        .bind(account)
+        .bind(cash)
+        .execute(&mut *tx)
+        .await
+        .map_err(query_failed("Couldn't reset the paper account"))?;
+
+    tx.commit()
+        .await
+        .map_err(query_failed("Couldn't reset the paper account"))
+}
+
+/// `account`'s balances, positions at their last prices, and open orders,
+/// as they were after the last sync
+pub async fn load_account(account: &str, db_pool: &PgPool)
+    -> Result<PaperAccount, PaperError> {
+
+    require_account(account, db_pool).await?;
+
+    let (starting_cash, cash): (f64, f64) = sqlx::query_as(
+        "SELECT starting_cash, cash FROM _paper_accounts WHERE name = $1"
+    )
+        .bind(account)
+        .fetch_one(db_pool)
+        .await
+        .map_err(query_failed("Couldn't read the paper account"))?;
+
+    let held: Vec<(String, String, f64, f64)> = sqlx::query_as(r#"
+        SELECT exchange, asset, volume, cost FROM _paper_positions
+        WHERE account = $1
+        ORDER BY exchange, asset
+    "#)
+        .bind(account)
+        .fetch_all(db_pool)
+        .await
+        .map_err(query_failed("Couldn't read the paper positions"))?;
+
+    let mut positions: Vec<PaperPosition> = Vec::new();
+    for (exchange, ticker, volume, cost) in held {
+        let last_price: Option<f64> = tick_before(
+            &exchange,
+            &ticker,
+            None,
+            db_pool
+        )
+            .await
+            .ok()
+            .flatten()
+            .map(|(_, _, price)| price);
+        positions.push(PaperPosition {
+            exchange,
+            ticker,
+            volume,
+            cost,
+            last_price,
+        });
+    };
+
+    let equity: f64 = cash + positions
+        .iter()
+        .map(PaperPosition::value)
+        .sum::<f64>();
+
+    Ok(PaperAccount {
+        name: account.to_string(),
+        starting_cash,
+        cash,
+        equity,
+        positions,
+        open_orders: fetch_orders(
+            account,
+            "AND status IN ('open', 'pending') ORDER BY id",
+            db_pool
+        ).await?,
+    })
+}
+
+/// The market orders that would move `account`'s pairs on `exchange` to
+/// `targets`, weights of its equity by ticker, see
+/// `compute_rebalance_orders`
+///
+/// Held pairs of `exchange` missing from `targets` are sold off. Pairs are
+/// priced at their last stored tick and the pair info is fetched from
+/// Kraken. Cash held by open orders isn't set aside.
+pub async fn rebalance_orders(
+    account: &str,
+    exchange: &str,
+    targets: &HashMap<String, f64>,
+    min_drift: f64,
+    client: &Client,
+    db_pool: &PgPool
+) -> Result<Vec<RebalanceOrder>, PaperError> {
+
+    open_account(account, db_pool).await?;
+    let summary: PaperAccount = load_account(account, db_pool).await?;
+    let exchange: String = exchange.to_lowercase();
+
+    let holdings: Vec<Holding> = summary.positions
+        .iter()
+        .filter(|p| p.exchange == exchange)
+        .map(|p| Holding { ticker: p.ticker.clone(), quantity: p.volume })
+        .collect();
+
+    let mut tickers: Vec<String> = targets.keys().cloned().collect();
+    tickers.extend(holdings.iter().map(|h| h.ticker.clone()));
+    tickers.sort();
+    tickers.dedup();
+
+    let tables: Vec<String> = fetch_tables(db_pool.clone())
+        .await
+        .map_err(PaperError::Db)?;
+
+    let mut prices: HashMap<String, f64> = HashMap::new();
+    let mut pair_info: BTreeMap<String, AssetPairInfo> = BTreeMap::new();
+
+    for ticker in tickers {
+
+        let last: Option<(u64, u64, f64)> = match tables.contains(
+            &get_table_name(&exchange, &ticker)
+        ) {
+            true => tick_before(&exchange, &ticker, None, db_pool).await?,
+            false => None
+        };
+        let Some((_, _, price)) = last else {
+            return Err(PaperError::NoData { exchange, ticker })
+        };
+
+        let info: AssetPairInfo = request_asset_info_from_kraken(
+            &ticker,
+            client
+        )
+            .await
+            .map_err(|e| PaperError::OrderRejected(format!(
+                "Couldn't fetch the pair info of {}: {}", ticker, e
+            )))?;
+
+        prices.insert(ticker.clone(), price);
+        pair_info.insert(ticker, info);
+    };
+
+    compute_rebalance_orders(
+        targets,
+        &holdings,
+        &prices,
+        summary.cash,
+        min_drift,
+        &pair_info
+    )
+        .map_err(|e| PaperError::OrderRejected(e.to_string()))
+}
+
+/// The price of the pair's last stored tick, which a market order placed
+/// now is checked against
+pub async fn last_price(exchange: &str, ticker: &str, db_pool: &PgPool)
+    -> Result<f64, PaperError> {
+
+    let exchange: String = exchange.to_lowercase();
+    let no_data = || PaperError::NoData {
+        exchange: exchange.clone(),
+        ticker: ticker.to_string()
+    };
+
+    let tables: Vec<String> = fetch_tables(db_pool.clone())
+        .await
+        .map_err(PaperError::Db)?;
+    if !tables.contains(&get_table_name(&exchange, ticker)) {
+        return Err(no_data())
+    };
+
+    tick_before(&exchange, ticker, None, db_pool)
+        .await?
+        .map(|(_, _, price)| price)
+        .ok_or_else(no_data)
+}
+
+/// Every order of `account`, newest first
+pub async fn order_history(account: &str, db_pool: &PgPool)
+    -> Result<Vec<PaperOrder>, PaperError> {
+    require_account(account, db_pool).await?;
+    fetch_orders(account, "ORDER BY id DESC", db_pool).await
+}
+
+
+/// The names of every opened account, in order. None before the first
+/// one is opened.
+pub async fn account_names(db_pool: &PgPool)
+    -> Result<Vec<String>, PaperError> {
+
+    let tables: Option<String> = sqlx::query_scalar(
+        "SELECT to_regclass('_paper_accounts')::TEXT"
+    )
+        .fetch_one(db_pool)
+        .await
+        .map_err(query_failed("Couldn't look for the paper tables"))?;
+
+    match tables {
+        Some(_) => sqlx::query_scalar(
+            "SELECT name FROM _paper_accounts ORDER BY name"
+        )
+            .fetch_all(db_pool)
+            .await
+            .map_err(query_failed("Couldn't read the paper accounts")),
+        None => Ok(Vec::new())
+    }
+}
+
+
+/// Stores every account's equity as of its last sync, for the equity curves
+/// of `equity_curve`. Returns the number of accounts recorded.
+pub async fn record_equity(db_pool: &PgPool) -> Result<usize, PaperError> {
+
+    let names: Vec<String> = account_names(db_pool).await?;
+    if names.is_empty() {
+        return Ok(0)
+    };
+    create_tables(db_pool).await?;
+
+    let now: i64 = get_current_unix_timestamp() as i64;
+    for name in &names {
+        let account: PaperAccount = load_account(name, db_pool).await?;
+        sqlx::query(r#"
+            INSERT INTO _paper_equity (account, time, equity)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (account, time) DO UPDATE SET equity = $3;
+        "#)
+            .bind(name)
+            .bind(now)
+            .bind(account.equity)
+            .execute(db_pool)
+            .await
+            .map_err(query_failed("Couldn't record the paper equity"))?;
+    };
+
+    Ok(names.len())
+}
+
+/// `account`'s recorded equity since `since` (Unix seconds), as
+/// `(time, equity)` oldest first. Empty before the first `record_equity`.
+pub async fn equity_curve(account: &str, since: u64, db_pool: &PgPool)
+    -> Result<Vec<(u64, f64)>, PaperError> {
+
+    let tables: Option<String> = sqlx::query_scalar(
+        "SELECT to_regclass('_paper_equity')::TEXT"
+    )
+        .fetch_one(db_pool)
+        .await
+        .map_err(query_failed("Couldn't look for the paper tables"))?;
+    if tables.is_none() {
+        return Ok(Vec::new())
+    };
+
+    let rows: Vec<(i64, f64)> = sqlx::query_as(r#"
+        SELECT time, equity FROM _paper_equity
+        WHERE account = $1 AND time >= $2
+        ORDER BY time
+    "#)
+        .bind(account)
+        .bind(since.min(i64::MAX as u64) as i64)
+        .fetch_all(db_pool)
+        .await
+        .map_err(query_failed("Couldn't read the paper equity"))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(time, equity)| (time.max(0) as u64, equity))
+        .collect())
+}
+
+// ------------------------------- MATCHING -------------------------------- //
+/// An open order and the tick that fills it
+struct Match {
+    order: PaperOrder,
+    tick_id: u64,
+    time: u64,
+    price: f64,
+    liquidity: Liquidity,
+}
+
+/// The tick that fills `order`, if one has been stored since it was placed
+///
+/// Market orders fill on the next tick as a taker, with slippage. Limit
+/// orders fill at the limit as a maker on the first tick that trades
+/// through it, or on the next tick as a taker if that one is already past
+/// it, never at a worse price than the limit. Stop orders fill like market
+/// orders on the first tick at or past the stop.
+async fn find_match(
+    order: &PaperOrder,
+    model: &CostModel,
+    db_pool: &PgPool
+) -> Result<Option<Match>, PaperError> {
+
+    if let Some(stop) = order.stop_price {
+        let against: OrderSide = match order.side {
+            OrderSide::Buy => OrderSide::Sell,
+            OrderSide::Sell => OrderSide::Buy,
+        };
+        return Ok(tick_after(order, Some((against, stop)), db_pool)
+            .await?
+            .map(|(tick_id, time, price)| Match {
+                order: order.clone(),
+                tick_id,
+                time,
+                price: model.round_price(
+                    model.apply_slippage(price, order.side),
+                    order.side
+                ),
+                liquidity: Liquidity::Taker,
+            }))
+    };
+
+    let Some((tick_id, time, price)) = tick_after(order, None, db_pool).await?
+    else {
+        return Ok(None)
+    };
+
+    let side: OrderSide = order.side;
+    let taker_price: f64 = model.round_price(
+        model.apply_slippage(price, side),
+        side
+    );
+
+    let taker = |price: f64| Some(Match {
+        order: order.clone(),
+        tick_id,
+        time,
+        price,
+        liquidity: Liquidity::Taker,
+    });
+
+    let Some(limit) = order.limit_price else {
+        return Ok(taker(taker_price))
+    };
+
+    match side {
+        OrderSide::Buy if price <= limit => {
+            return Ok(taker(taker_price.min(limit)))
+        },
+        OrderSide::Sell if price >= limit => {
+            return Ok(taker(taker_price.max(limit)))
+        },
+        _ => {}
+    };
+
+    Ok(tick_after(order, Some((side, limit)), db_pool)
+        .await?
+        .map(|(tick_id, time, _)| Match {
+            order: order.clone(),
+            tick_id,
+            time,
+            price: limit,
+            liquidity: Liquidity::Maker,
+        }))
+}
+
+/// Marks the order rejected, with why, and cancels its bracket's exits,
+/// returning their IDs. An order no longer open is left as it is.
+async fn reject(conn: &mut PgConnection, id: i64, note: String)
+    -> Result<Vec<i64>, PaperError> {
+
+    let rejected = sqlx::query(r#"
+        UPDATE _paper_orders SET status = 'rejected', note = $2
+        WHERE id = $1 AND status = 'open';
+    "#)
+        .bind(id)
+        .bind(note)
+        .execute(&mut *conn)
+        .await
+        .map_err(query_failed("Couldn't reject the paper order"))?;
+
+    match rejected.rows_affected() {
+        0 => Ok(Vec::new()),
+        _ => cancel_exits(conn, id, &format!("#{} was rejected", id)).await
+    }
+}
+
+/// Fills `found` in one transaction, charging the fee for the account's
+/// traded volume of the 30 days before the fill, or rejects it if the
+/// account can't cover it by then
+///
+/// Returns the order, and the orders of its OCO or bracket it cancelled or
+/// opened, or `None` when the order was no longer open, e.g. because a
+/// sync running alongside filled or cancelled it first.
+async fn apply_fill(
+    account: &str,
+    found: Match,
+    model: &CostModel,
+    db_pool: &PgPool
+) -> Result<Option<(PaperOrder, Vec<PaperOrder>)>, PaperError> {
+
+    let failed = query_failed("Couldn't fill the paper order");
+    let order: &PaperOrder = &found.order;
+
+    let mut tx = db_pool.begin().await.map_err(&failed)?;
+
+    let cash: f64 = sqlx::query_scalar(
+        "SELECT cash FROM _paper_accounts WHERE name = $1 FOR UPDATE"
+    )
+        .bind(account)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(&failed)?;
+
+    // Claims the order before anything is charged. Another sync holding it
+    // is waited for, and whatever it did is left alone, as is an order
+    // whose price was changed since it was matched.
+    let stored: Option<(String, Option<f64>, Option<f64>)> = sqlx::query_as(
+        r#"
+        SELECT status, limit_price, stop_price FROM _paper_orders
+        WHERE id = $1
+        FOR UPDATE
+        "#
+    )
+        .bind(order.id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(&failed)?;
+
+    let claimed: bool = stored.is_some_and(|(status, limit, stop)| {
+        status == "open"
+            && limit == order.limit_price
+            && stop == order.stop_price
+    });
+    if !claimed {
+        tx.rollback().await.map_err(&failed)?;
+        return Ok(None)
+    };
+
+    let (held, held_cost): (f64, f64) = sqlx::query_as(r#"
+        SELECT volume, cost FROM _paper_positions
+        WHERE account = $1 AND exchange = $2 AND asset = $3
+        FOR UPDATE
+    "#)
+        .bind(account)
+        .bind(&order.exchange)
+        .bind(&order.ticker)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(&failed)?
+        .unwrap_or((0.0, 0.0));
+
+    let volume_30d: f64 = sqlx::query_scalar(r#"
+        SELECT COALESCE(SUM(fill_price * volume), 0)::float8
+        FROM _paper_orders
+        WHERE account = $1
+            AND status = 'filled'
+            AND fill_time > $2
+            AND fill_time <= $3
+    "#)
+        .bind(account)
+        .bind(found.time.saturating_sub(30 * MICROS_PER_DAY) as i64)
+        .bind(found.time as i64)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(&failed)?;
+
+    let cost: f64 = order.volume * found.price;
+    let fee: f64 = cost * model.fee_rate(found.liquidity, volume_30d);
+
+    let refusal: Option<String> = match model
+        .check_minimums(order.volume, found.price)
+    {
+        Err(e) => Some(e.to_string()),
+        Ok(_) => match order.side {
+            OrderSide::Buy if cost + fee > cash => Some(format!(
+                "Need {:.2}, had {:.2}", cost + fee, cash
+            )),
+            OrderSide::Sell if order.volume > held + 1e-12 => Some(format!(
+                "Can't sell {}, position was {}", order.volume, held
+            )),
+            _ => None
+        }
+    };
+
+    if let Some(note) = refusal {
+        let cancelled: Vec<i64> = reject(&mut tx, order.id, note).await?;
+        tx.commit().await.map_err(&failed)?;
+        return Ok(Some((
+            fetch_order(order.id, db_pool).await?,
+            fetch_orders_by_id(&cancelled, db_pool).await?
+        )))
+    };
+
+    let (cash, volume, position_cost) = match order.side {
+        OrderSide::Buy => (
+            cash - cost - fee,
+            held + order.volume,
+            held_cost + cost + fee
+        ),
+        OrderSide::Sell => (
+            cash + cost - fee,
+            held - order.volume,
+            held_cost * (1.0 - order.volume / held)
+        ),
+    };
+
+    sqlx::query("UPDATE _paper_accounts SET cash = $2 WHERE name = $1")
+        .bind(account)
+        .bind(cash)
+        .execute(&mut *tx)
+        .await
+        .map_err(&failed)?;
+
+    // Float error can leave dust behind a full sale
+    let position = match volume > 1e-12 {
+        true => sqlx::query(r#"
+            INSERT INTO _paper_positions
+                (account, exchange, asset, volume, cost)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (account, exchange, asset)
+            DO UPDATE SET volume = $4, cost = $5;
+        "#)
+            .bind(account)
+            .bind(&order.exchange)
+            .bind(&order.ticker)
+            .bind(volume)
+            .bind(position_cost),
+        false => sqlx::query(r#"
+            DELETE FROM _paper_positions
+            WHERE account = $1 AND exchange = $2 AND asset = $3;
+        "#)
+            .bind(account)
+            .bind(&order.exchange)
+            .bind(&order.ticker)
+    };
+
+    position.execute(&mut *tx).await.map_err(&failed)?;
+
+    let liquidity: &str = match found.liquidity {
+        Liquidity::Maker => "maker",
+        Liquidity::Taker => "taker",
+    };
+
+    let filled = sqlx::query(r#"
+        UPDATE _paper_orders SET
+            status = 'filled',
+            fill_price = $2,
+            fee = $3,
+            fill_tick = $4,
+            fill_time = $5,
+            note = $6
+        WHERE id = $1 AND status = 'open';
+    "#)
+        .bind(order.id)
+        .bind(found.price)
+        .bind(fee)
+        .bind(found.tick_id as i64)
+        .bind(found.time as i64)
+        .bind(liquidity)
+        .execute(&mut *tx)
+        .await
+        .map_err(&failed)?;
+
+    if filled.rows_affected() == 0 {
+        tx.rollback().await.map_err(&failed)?;
+        return Ok(None)
+    };
+
+    // The other legs of the order's OCO or bracket are cancelled, and the
+    // exits of its own bracket open from the fill
+    let mut linked: Vec<i64> = match order.group_id {
+        Some(group) => sqlx::query_scalar(r#"
+            UPDATE _paper_orders SET status = 'cancelled', note = $3
+            WHERE group_id = $1
+                AND id <> $2
+                AND status IN ('open', 'pending')
+            RETURNING id;
+        "#)
+            .bind(group)
+            .bind(order.id)
+            .bind(format!("#{} filled", order.id))
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(&failed)?,
+        None => Vec::new()
+    };
+
+    let opened: Vec<i64> = sqlx::query_scalar(r#"
+        UPDATE _paper_orders SET 
+            status = 'open', 
+            placed_tick = $2, 
+            placed_time = $3
+        WHERE parent_id = $1 AND status = 'pending'
+        RETURNING id;
+    "#)
+        .bind(order.id)
+        .bind(found.tick_id as i64)
+        .bind(found.time as i64)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(&failed)?;
+    linked.extend(opened);
+
+    tx.commit().await.map_err(&failed)?;
+
+    Ok(Some((
+        fetch_order(order.id, db_pool).await?,
+        fetch_orders_by_id(&linked, db_pool).await?
+    )))
+}
+
+/// Fills `account`'s open orders against the ticks stored since they were
+/// placed, and returns the orders that filled or were rejected, followed by
+/// any of their OCO or bracket they cancelled or opened
+///
+/// Run after new ticks are downloaded to trade live, or after placing
+/// orders in the past to replay the stored ticks against them. Fills are
+/// applied in the order their ticks happened, across pairs, so cash spent
+/// by one fill isn't there for a later one. An order whose pair's ticks
+/// can't be read, e.g. after the pair was removed, is rejected. A fill
+/// that cancels or opens other orders starts the matching over, so a
+/// bracket's exits can fill on the ticks after its entry.
+pub async fn sync(
+    account: &str,
+    cost_models: &mut CostModels,
+    db_pool: &PgPool
+) -> Result<Vec<PaperOrder>, PaperError> {
+
+    open_account(account, db_pool).await?;
+
+    let mut changed: Vec<PaperOrder> = Vec::new();
+
+    'matching: loop {
+
+        let open: Vec<PaperOrder> = fetch_orders(
+            account,
+            "AND status = 'open' ORDER BY id",
+            db_pool
+        ).await?;
+
+        let mut matches: Vec<Match> = Vec::new();
+
+        for order in open {
+            let model: &CostModel = cost_models.get(&order.ticker).await?;
+            match find_match(&order, model, db_pool).await {
+                Ok(Some(found)) => matches.push(found),
+                Ok(None) => {},
+                Err(e) => {
+                    let mut conn = db_pool
+                        .acquire()
+                        .await
+                        .map_err(query_failed("Couldn't reach the database"))?;
+                    let cancelled = reject(&mut conn, order.id, e.to_string())
+                        .await?;
+                    changed.push(fetch_order(order.id, db_pool).await?);
+                    changed.extend(
+                        fetch_orders_by_id(&cancelled, db_pool).await?
+                    );
+                }
+            };
+        };
+
+        matches.sort_by_key(|m| (m.time, m.order.id));
+
+        for found in matches {
+            let model: &CostModel = cost_models
+                .get(&found.order.ticker)
+                .await?;
+            let Some((order, linked)) = apply_fill(
+                account, 
+                found, 
+                model, 
+                db_pool
+            ).await? else {
+                continue
+            };
+            changed.push(order);
+            if !linked.is_empty() {
+                changed.extend(linked);
+                continue 'matching
+            };
+        };
+
+        break
+    };
+
+    Ok(changed)
+}
@@ -0,0 +1,106 @@
+use std::io::{self, BufRead, IsTerminal, Write};
+
+
+/// Choices listed at once, the rest are found by typing part of a name
+const MAX_SHOWN: usize = 20;
+
+
+/// Whether missing arguments can be asked for, with the terminal on both
+/// ends
+pub fn is_interactive() -> bool {
+    io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
+/// Prints `label` and reads the answer, `None` at the end of input
+fn read_line(label: &str) -> Option<String> {
+
+    print!("\x1b[1;36m{}\x1b[0m: ", label);
+    io::stdout().flush().ok()?;
+
+    let mut line = String::new();
+    match io::stdin().lock().read_line(&mut line) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line.trim().to_string())
+    }
+}
+
+/// Asks for one of `choices`, by its number or its name
+///
+/// Typing part of a name narrows the list down to the choices containing
+/// it. An empty answer, or the end of input, returns `None`.
+pub fn select(label: &str, choices: &[String]) -> Option<String> {
+
+    if choices.is_empty() {
+        return None
+    };
+
+    let mut shown: Vec<&String> = choices.iter().collect();
+
+    loop {
+
+        for (i, choice) in shown.iter().take(MAX_SHOWN).enumerate() {
+            println!("  \x1b[33m{:>2}\x1b[0m) {}", i + 1, choice);
+        };
+        if shown.len() > MAX_SHOWN {
+            println!(
+                "  ... and {} more, type part of a name to narrow them down",
+                shown.len() - MAX_SHOWN
+            );
+        };
+
+        let answer: String = read_line(label)?;
+        if answer.is_empty() {
+            return None
+        };
+
+        if let Ok(n) = answer.parse::<usize>()
+            && n >= 1
+            && n <= shown.len().min(MAX_SHOWN)
+        {
+            return Some(shown[n - 1].clone())
+        };
+
+        if let Some(choice) = choices
+            .iter()
+            .find(|c| c.eq_ignore_ascii_case(&answer))
+        {
+            return Some(choice.clone())
+        };
+
+        let needle: String = answer.to_lowercase();
+        let matches: Vec<&String> = choices
+            .iter()
+            .filter(|c| c.to_lowercase().contains(&needle))
+            .collect();
+
+        match matches.len() {
+            0 => println!("\x1b[33mNothing matches {}\x1b[0m", answer),
+            1 => return Some(matches[0].clone()),
+            _ => shown = matches
+        };
+    }
+}
+
+/// Asks until `parse` accepts the answer, printing why it didn't
+///
+/// An empty answer, or the end of input, returns `None`.
+pub fn ask<T>(label: &str, parse: impl Fn(&str) -> Result<T, String>)
+    -> Option<T>
+{
+    loop {
+        let answer: String = read_line(label)?;
+        if answer.is_empty() {
+            return None
+        };
+        match parse(&answer) {
+            Ok(value) => return Some(value),
+            Err(e) => println!("\x1b[33m{}\x1b[0m", e)
+        };
+    }
+}
+
+/// Asks a yes or no question, no unless the answer starts with y
+pub fn confirm(label: &str) -> bool {
+    read_line(&format!("{} [y/N]", label))
+        .is_some_and(|a| a.to_lowercase().starts_with('y'))
+}
@@ -0,0 +1,720 @@
+use std::{collections::HashMap, fmt};
+
+use reqwest::Client;
+use serde::Serialize;
+use sqlx::PgPool;
+
+use backtest::OrderSide;
+use database_ops::{
+    DbError,
+    Ticker,
+    kraken::{TickerQuote, exchange_error, request_ticker_from_kraken},
+};
+use portfolio::CostBasis;
+use timestamp_tools::{db_timestamp_to_date_string, get_current_unix_timestamp};
+
+use crate::{
+    app_state::LiveTradingSettings,
+    errors::{LiveError, PaperError, RecurringError},
+    live::{self, LIVE_EXCHANGE, LiveOrder},
+    paper::{
+        self,
+        Contingent,
+        CostModels,
+        NewOrder,
+        OrderStatus,
+        PaperOrder
+    },
+};
+
+
+/// Buys of a fixed amount of the quote currency, repeated every
+/// `every_secs`. `account` is the paper account to buy in, NULL for the
+/// live one. `next_run` is in microseconds, like the ticks' times, and
+/// removed schedules are kept inactive so their history stays readable.
+const CREATE_SCHEDULE_TABLE: &str = r#"
+    CREATE TABLE IF NOT EXISTS _recurring_buys (
+        id BIGSERIAL PRIMARY KEY,
+        account VARCHAR(32),
+        exchange VARCHAR(16) NOT NULL,
+        asset VARCHAR(12) NOT NULL,
+        amount DOUBLE PRECISION NOT NULL,
+        every_secs BIGINT NOT NULL,
+        next_run BIGINT NOT NULL,
+        active BOOLEAN NOT NULL DEFAULT TRUE,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    );
+"#;
+
+/// Every time a schedule ran, with the order it placed, paper order ID or
+/// live transaction ID, or why it couldn't place one
+const CREATE_RUN_TABLE: &str = r#"
+    CREATE TABLE IF NOT EXISTS _recurring_buy_runs (
+        id BIGSERIAL PRIMARY KEY,
+        schedule_id BIGINT NOT NULL,
+        run_time BIGINT NOT NULL,
+        order_id TEXT,
+        volume DOUBLE PRECISION,
+        note TEXT
+    );
+"#;
+
+const SCHEDULE_COLUMNS: &str = "id, account, exchange, asset, amount, \
+    every_secs, next_run, active";
+
+#[derive(sqlx::FromRow)]
+struct ScheduleRow {
+    id: i64,
+    account: Option<String>,
+    exchange: String,
+    asset: String,
+    amount: f64,
+    every_secs: i64,
+    next_run: i64,
+    active: bool,
+}
+
+#[derive(sqlx::FromRow)]
+struct RunRow {
+    id: i64,
+    schedule_id: i64,
+    run_time: i64,
+    order_id: Option<String>,
+    volume: Option<f64>,
+    note: Option<String>,
+}
+
+
+/// A recurring buy to schedule, see `add_schedule`
+#[derive(Debug, Clone)]
+pub struct NewRecurringBuy {
+    /// The paper account to buy in, the live account without one
+    pub account: Option<String>,
+    pub exchange: String,
+    pub ticker: Ticker,
+    /// Spent on each buy, in the quote currency
+    pub amount: f64,
+    /// Seconds between two buys
+    pub every: u64,
+    /// Unix time (seconds) of the first buy, now without one
+    pub start: Option<u64>,
+}
+
+/// A stored recurring buy
+#[derive(Debug, Clone, Serialize)]
+pub struct RecurringBuy {
+    pub id: i64,
+    pub account: Option<String>,
+    pub exchange: String,
+    pub ticker: String,
+    pub amount: f64,
+    /// Seconds between two buys
+    pub every: u64,
+    /// Microseconds since the epoch
+    pub next_run: u64,
+    /// Whether it still runs, removed schedules are kept for their history
+    pub active: bool,
+}
+
+impl RecurringBuy {
+
+    fn from_row(row: ScheduleRow) -> Self {
+        RecurringBuy {
+            id: row.id,
+            account: row.account,
+            exchange: row.exchange,
+            ticker: row.asset,
+            amount: row.amount,
+            every: row.every_secs as u64,
+            next_run: row.next_run as u64,
+            active: row.active,
+        }
+    }
+
+    /// `paper:ACCOUNT` or `live`, like the sources of `blotter::BlotterFill`
+    pub fn target(&self) -> String {
+        match &self.account {
+            Some(account) => format!("paper:{}", account),
+            None => "live".to_string()
+        }
+    }
+}
+
+impl fmt::Display for RecurringBuy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+
+        write!(
+            f,
+            "#{} buy {} of {} {} every {} on {}",
+            self.id,
+            self.amount,
+            self.exchange,
+            self.ticker,
+            cadence(self.every),
+            self.target()
+        )?;
+
+        match self.active {
+            true => write!(
+                f,
+                ", next at {}",
+                db_timestamp_to_date_string(self.next_run)
+            ),
+            false => write!(f, " (removed)")
+        }
+    }
+}
+
+/// One run of a recurring buy
+#[derive(Debug, Clone, Serialize)]
+pub struct RecurringRun {
+    pub id: i64,
+    pub schedule_id: i64,
+    /// Microseconds since the epoch
+    pub time: u64,
+    /// The paper order's ID or the live transaction ID, none when the
+    /// order couldn't be placed
+    pub order_id: Option<String>,
+    /// What was ordered, or once filled what was bought
+    pub volume: Option<f64>,
+    /// Filled in by `history` once the order has filled
+    pub fill_price: Option<f64>,
+    pub fee: Option<f64>,
+    /// Why no order was placed
+    pub note: Option<String>,
+}
+
+impl RecurringRun {
+
+    fn from_row(row: RunRow) -> Self {
+        RecurringRun {
+            id: row.id,
+            schedule_id: row.schedule_id,
+            time: row.run_time as u64,
+            order_id: row.order_id,
+            volume: row.volume,
+            fill_price: None,
+            fee: None,
+            note: row.note,
+        }
+    }
+}
+
+impl fmt::Display for RecurringRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+
+        write!(
+            f,
+            "{} #{}",
+            db_timestamp_to_date_string(self.time),
+            self.schedule_id
+        )?;
+
+        match (&self.order_id, self.volume) {
+            (Some(order), Some(volume)) => {
+                write!(f, " order {} for {}", order, volume)?
+            },
+            _ => write!(f, " no order")?
+        };
+        match (self.fill_price, self.fee) {
+            (Some(price), Some(fee)) => {
+                write!(f, " filled at {} (fee {:.2})", price, fee)?
+            },
+            _ if self.order_id.is_some() => write!(f, " not filled yet")?,
+            _ => {}
+        };
+        if let Some(note) = &self.note {
+            write!(f, ": {}", note)?;
+        };
+
+        Ok(())
+    }
+}
+
+/// A recurring buy's runs, oldest first, and what the filled ones hold
+#[derive(Debug, Clone, Serialize)]
+pub struct RecurringHistory {
+    pub schedule: RecurringBuy,
+    pub runs: Vec<RecurringRun>,
+    /// The volume bought and its cost, fees in
+    pub basis: CostBasis,
+}
+
+impl fmt::Display for RecurringHistory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+
+        writeln!(f, "{}", self.schedule)?;
+
+        if self.runs.is_empty() {
+            writeln!(f, "  No runs yet")?;
+        };
+        for run in &self.runs {
+            writeln!(f, "  {}", run)?;
+        };
+
+        write!(
+            f,
+            "Bought {} for {:.2}, an average cost of {:.2}",
+            self.basis.volume,
+            self.basis.cost,
+            self.basis.average_cost()
+        )
+    }
+}
+
+
+/// e.g. "1d" for a day, in the largest unit that divides it
+pub fn cadence(secs: u64) -> String {
+    let units: [(u64, char); 4] = [
+        (604_800, 'w'),
+        (86_400, 'd'),
+        (3_600, 'h'),
+        (60, 'm'),
+    ];
+    units
+        .iter()
+        .find(|(unit, _)| secs > 0 && secs.is_multiple_of(*unit))
+        .map(|(unit, symbol)| format!("{}{}", secs / unit, symbol))
+        .unwrap_or_else(|| format!("{}s", secs))
+}
+
+/// The first run after `now` on the schedule of `next_run`, skipping the
+/// runs missed while nothing ran rather than buying them all at once
+fn next_run_after(next_run: u64, every_micros: u64, now: u64) -> u64 {
+    let every_micros: u64 = every_micros.max(1);
+    match now.checked_sub(next_run) {
+        Some(late) => next_run + (late / every_micros + 1) * every_micros,
+        None => next_run
+    }
+}
+
+
+fn query_failed(what: &'static str)
+    -> impl Fn(sqlx::Error) -> RecurringError {
+    move |e| RecurringError::Db(
+        DbError::QueryFailed(format!("{}: {}", what, e))
+    )
+}
+
+async fn create_tables(db_pool: &PgPool) -> Result<(), RecurringError> {
+    for table in [CREATE_SCHEDULE_TABLE, CREATE_RUN_TABLE] {
+        sqlx::query(table)
+            .execute(db_pool)
+            .await
+            .map_err(|e| RecurringError::Db(
+                DbError::TableCreationFailed(e.to_string())
+            ))?;
+    };
+    Ok(())
+}
+
+async fn fetch_schedules(filter: &str, db_pool: &PgPool)
+    -> Result<Vec<RecurringBuy>, RecurringError> {
+
+    create_tables(db_pool).await?;
+
+    let query: String = format!(
+        "SELECT {} FROM _recurring_buys {}",
+        SCHEDULE_COLUMNS,
+        filter
+    );
+
+    sqlx::query_as::<_, ScheduleRow>(&query)
+        .fetch_all(db_pool)
+        .await
+        .map(|rows| rows.into_iter().map(RecurringBuy::from_row).collect())
+        .map_err(query_failed("Couldn't read the recurring buys"))
+}
+
+async fn fetch_schedule(id: i64, db_pool: &PgPool)
+    -> Result<RecurringBuy, RecurringError> {
+
+    create_tables(db_pool).await?;
+
+    let query: String = format!(
+        "SELECT {} FROM _recurring_buys WHERE id = $1",
+        SCHEDULE_COLUMNS
+    );
+
+    sqlx::query_as::<_, ScheduleRow>(&query)
+        .bind(id)
+        .fetch_optional(db_pool)
+        .await
+        .map_err(query_failed("Couldn't read the recurring buy"))?
+        .map(RecurringBuy::from_row)
+        .ok_or(RecurringError::NoSchedule(id))
+}
+
+
+/// Stores a recurring buy, whose first run is at `start` or as soon as the
+/// scheduler looks, see `run_due`
+///
+/// Paper buys need the pair's ticks stored, and live ones the exchange
+/// live trading is on, so schedules that could never run are refused here.
+pub async fn add_schedule(new: &NewRecurringBuy, db_pool: &PgPool)
+    -> Result<RecurringBuy, RecurringError> {
+
+    let exchange: String = new.exchange.to_lowercase();
+
+    match new.account {
+        Some(_) => {
+            paper::last_price(&exchange, new.ticker.as_str(), db_pool)
+                .await
+                .map_err(RecurringError::Paper)?;
+        },
+        None if exchange != LIVE_EXCHANGE => {
+            return Err(RecurringError::Live(
+                LiveError::UnsupportedExchange(exchange)
+            ))
+        },
+        None => {}
+    };
+
+    create_tables(db_pool).await?;
+
+    let start: u64 = new
+        .start
+        .unwrap_or_else(get_current_unix_timestamp)
+        .saturating_mul(1_000_000)
+        .min(i64::MAX as u64);
+
+    let id: i64 = sqlx::query_scalar(r#"
+        INSERT INTO _recurring_buys
+            (account, exchange, asset, amount, every_secs, next_run)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id
+    "#)
+        .bind(&new.account)
+        .bind(&exchange)
+        .bind(new.ticker.as_str())
+        .bind(new.amount)
+        .bind(new.every.min(i64::MAX as u64) as i64)
+        .bind(start as i64)
+        .fetch_one(db_pool)
+        .await
+        .map_err(query_failed("Couldn't store the recurring buy"))?;
+
+    fetch_schedule(id, db_pool).await
+}
+
+/// The recurring buys still running, by ID
+pub async fn schedules(db_pool: &PgPool)
+    -> Result<Vec<RecurringBuy>, RecurringError> {
+    fetch_schedules("WHERE active ORDER BY id", db_pool).await
+}
+
+/// Stops recurring buy `id`, keeping it and its runs for `history`
+pub async fn remove_schedule(id: i64, db_pool: &PgPool)
+    -> Result<RecurringBuy, RecurringError> {
+
+    let schedule: RecurringBuy = fetch_schedule(id, db_pool).await?;
+    if !schedule.active {
+        return Err(RecurringError::NoSchedule(id))
+    };
+
+    sqlx::query("UPDATE _recurring_buys SET active = FALSE WHERE id = $1")
+        .bind(id)
+        .execute(db_pool)
+        .await
+        .map_err(query_failed("Couldn't remove the recurring buy"))?;
+
+    Ok(RecurringBuy { active: false, ..schedule })
+}
+
+
+/// Runs every recurring buy that's due, and returns the runs as stored
+///
+/// Each schedule is moved on to its next run before its order is placed,
+/// and only if no other scheduler moved it first, so two servers on one
+/// database don't buy twice. An order that can't be placed, e.g. for lack
+/// of cash, is kept in the run's note and waits for the next run.
+pub async fn run_due(
+    live_settings: &LiveTradingSettings,
+    cost_models: &mut CostModels,
+    client: &Client,
+    db_pool: &PgPool
+) -> Result<Vec<RecurringRun>, RecurringError> {
+
+    let now: u64 = get_current_unix_timestamp() * 1_000_000;
+
+    let due: Vec<RecurringBuy> = fetch_schedules(
+        &format!("WHERE active AND next_run <= {} ORDER BY next_run", now),
+        db_pool
+    ).await?;
+
+    let mut runs: Vec<RecurringRun> = Vec::with_capacity(due.len());
+
+    for schedule in due {
+
+        let next: u64 = next_run_after(
+            schedule.next_run,
+            schedule.every.saturating_mul(1_000_000),
+            now
+        );
+
+        let claimed: u64 = sqlx::query(r#"
+            UPDATE _recurring_buys SET next_run = $1
+            WHERE id = $2 AND next_run = $3 AND active
+        "#)
+            .bind(next.min(i64::MAX as u64) as i64)
+            .bind(schedule.id)
+            .bind(schedule.next_run as i64)
+            .execute(db_pool)
+            .await
+            .map_err(query_failed("Couldn't move the recurring buy on"))?
+            .rows_affected();
+
+        if claimed == 0 {
+            continue
+        };
+
+        let ticker: Result<Ticker, String> = schedule.ticker
+            .parse::<Ticker>()
+            .map_err(|e| e.to_string());
+
+        let placed: Result<(String, f64), String> = match (
+            &schedule.account,
+            ticker
+        ) {
+            (_, Err(e)) => Err(e),
+            (Some(account), Ok(ticker)) => buy_paper(
+                account,
+                &schedule,
+                ticker,
+                cost_models,
+                db_pool
+            ).await.map_err(|e| e.to_string()),
+            (None, Ok(ticker)) => buy_live(
+                &schedule,
+                ticker,
+                live_settings,
+                cost_models,
+                client
+            ).await.map_err(|e| e.to_string()),
+        };
+
+        let (order_id, volume, note) = match placed {
+            Ok((order_id, volume)) => (Some(order_id), Some(volume), None),
+            Err(e) => (None, None, Some(e))
+        };
+
+        let row: RunRow = sqlx::query_as(r#"
+            INSERT INTO _recurring_buy_runs
+                (schedule_id, run_time, order_id, volume, note)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, schedule_id, run_time, order_id, volume, note
+        "#)
+            .bind(schedule.id)
+            .bind(now as i64)
+            .bind(order_id)
+            .bind(volume)
+            .bind(note)
+            .fetch_one(db_pool)
+            .await
+            .map_err(query_failed("Couldn't store the recurring buy's run"))?;
+
+        runs.push(RecurringRun::from_row(row));
+    };
+
+    Ok(runs)
+}
+
+/// Places a market buy of the schedule's amount at the last stored price,
+/// after filling the account's open orders, the earlier runs' among them,
+/// on the ticks stored since
+async fn buy_paper(
+    account: &str,
+    schedule: &RecurringBuy,
+    ticker: Ticker,
+    cost_models: &mut CostModels,
+    db_pool: &PgPool
+) -> Result<(String, f64), PaperError> {
+
+    match paper::sync(account, cost_models, db_pool).await {
+        Ok(_) | Err(PaperError::NoAccount(_)) => {},
+        Err(e) => return Err(e)
+    };
+
+    let price: f64 = paper::last_price(
+        &schedule.exchange,
+        ticker.as_str(),
+        db_pool
+    ).await?;
+
+    let order = NewOrder {
+        exchange: schedule.exchange.clone(),
+        ticker,
+        side: OrderSide::Buy,
+        volume: schedule.amount / price,
+        limit: None,
+        stop: None,
+        at: None,
+        contingent: Contingent::None
+    };
+
+    let placed: PaperOrder = paper::place_order(
+        account,
+        order,
+        cost_models,
+        db_pool
+    ).await?;
+
+    Ok((placed.id.to_string(), placed.volume))
+}
+
+/// Places a market buy of the schedule's amount at the exchange's ask, in
+/// the pair's lot size
+async fn buy_live(
+    schedule: &RecurringBuy,
+    ticker: Ticker,
+    live_settings: &LiveTradingSettings,
+    cost_models: &mut CostModels,
+    client: &Client
+) -> Result<(String, f64), LiveError> {
+
+    // Off or without keys, the ask isn't worth asking for
+    live::kraken_credentials(live_settings)?;
+
+    let quote: TickerQuote = request_ticker_from_kraken(
+        ticker.as_str(),
+        client
+    )
+        .await
+        .map_err(|e| LiveError::Exchange(exchange_error(e)))?;
+
+    let mut volume: f64 = schedule.amount / quote.ask;
+    // Without the pair's info Kraken is left to round it
+    if let Ok(model) = cost_models.get(ticker.as_str()).await {
+        volume = model.round_volume(volume);
+    };
+
+    let order = LiveOrder {
+        exchange: schedule.exchange.clone(),
+        ticker,
+        side: OrderSide::Buy,
+        volume,
+        limit: None,
+        validate: false,
+    };
+
+    let added = live::place_order(live_settings, &order, client).await?;
+
+    Ok((added.txids.join(","), volume))
+}
+
+
+/// Recurring buy `id`'s runs, with the fills of the orders they placed and
+/// the average cost of what those bought, see `CostBasis`
+///
+/// A paper account fills its open orders on the stored ticks first, and
+/// the live account's fills are asked of the exchange.
+pub async fn history(
+    id: i64,
+    live_settings: &LiveTradingSettings,
+    cost_models: &mut CostModels,
+    client: &Client,
+    db_pool: &PgPool
+) -> Result<RecurringHistory, RecurringError> {
+
+    let schedule: RecurringBuy = fetch_schedule(id, db_pool).await?;
+
+    let mut runs: Vec<RecurringRun> = sqlx::query_as::<_, RunRow>(r#"
+        SELECT id, schedule_id, run_time, order_id, volume, note
+        FROM _recurring_buy_runs
+        WHERE schedule_id = $1
+        ORDER BY id
+    "#)
+        .bind(id)
+        .fetch_all(db_pool)
+        .await
+        .map_err(query_failed("Couldn't read the recurring buy's runs"))?
+        .into_iter()
+        .map(RecurringRun::from_row)
+        .collect();
+
+    // Volume, price and fee of each order's fills
+    let fills: HashMap<String, (f64, f64, f64)> = match &schedule.account {
+        _ if runs.iter().all(|r| r.order_id.is_none()) => HashMap::new(),
+        Some(account) => {
+            paper::sync(account, cost_models, db_pool)
+                .await
+                .map_err(RecurringError::Paper)?;
+            paper::order_history(account, db_pool)
+                .await
+                .map_err(RecurringError::Paper)?
+                .into_iter()
+                .filter(|o| o.status == OrderStatus::Filled)
+                .filter_map(|o| Some((
+                    o.id.to_string(),
+                    (o.volume, o.fill_price?, o.fee.unwrap_or(0.0))
+                )))
+                .collect()
+        },
+        None => {
+            let mut fills: HashMap<String, (f64, f64, f64)> = HashMap::new();
+            for trade in live::trade_history(live_settings, client)
+                .await
+                .map_err(RecurringError::Live)?
+            {
+                let (volume, value, fee) = fills
+                    .entry(trade.order_txid)
+                    .or_default();
+                *volume += trade.volume;
+                *value += trade.volume * trade.price;
+                *fee += trade.fee;
+            };
+            // Partial fills add up to one at their average price
+            fills
+                .into_iter()
+                .map(|(txid, (volume, value, fee))| {
+                    (txid, (volume, value / volume.max(f64::EPSILON), fee))
+                })
+                .collect()
+        },
+    };
+
+    let mut basis = CostBasis::default();
+
+    for run in runs.iter_mut() {
+        let Some(&(volume, price, fee)) = run
+            .order_id
+            .as_ref()
+            .and_then(|id| fills.get(id))
+        else {
+            continue
+        };
+        run.volume = Some(volume);
+        run.fill_price = Some(price);
+        run.fee = Some(fee);
+        basis.fill(OrderSide::Buy, volume, price, fee);
+    };
+
+    Ok(RecurringHistory { schedule, runs, basis })
+}
+
+
+// -------------------------- UNIT TESTING --------------------------------- //
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn cadence_test() {
+        assert_eq!(cadence(604_800), "1w");
+        assert_eq!(cadence(172_800), "2d");
+        assert_eq!(cadence(5_400), "90m");
+        assert_eq!(cadence(90), "90s");
+    }
+
+    #[test]
+    fn next_run_after_test() {
+        // Not due yet
+        assert_eq!(next_run_after(100, 10, 50), 100);
+        // Due now, or late by less than a run
+        assert_eq!(next_run_after(100, 10, 100), 110);
+        assert_eq!(next_run_after(100, 10, 105), 110);
+        // Runs missed in between are skipped
+        assert_eq!(next_run_after(100, 10, 135), 140);
+    }
+}
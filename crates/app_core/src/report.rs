@@ -0,0 +1,293 @@
+use std::{fmt, time::Duration};
+
+use serde::Serialize;
+use sqlx::PgPool;
+
+use database_ops::{
+    alerts::{FiredAlert, recent_alerts},
+    fetch_tables,
+    get_table_name,
+    jobs::record_report,
+    lineage::{IngestRate, ingest_rates, new_job_id, tick_gaps},
+    signals::{SignalCount, signal_counts},
+};
+use timestamp_tools::get_current_unix_timestamp;
+
+use crate::{
+    errors::ReportError,
+    paper::{account_names, equity_curve, load_account},
+};
+
+
+/// Kind of the daily summary in `_report_history`
+pub const DAILY_REPORT: &str = "daily";
+
+/// Span the daily summary covers, and the time between two of them
+pub const REPORT_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+
+
+/// What one pair took in over the report's window
+#[derive(Debug, Clone, Serialize)]
+pub struct PairActivity {
+    pub exchange: String,
+    pub ticker: String,
+    pub ticks: u64,
+    pub batches: u64,
+    /// Runs of missing tick IDs among the ticks taken in, see
+    /// `lineage::tick_gaps`
+    pub gaps: u64,
+    pub missing_ticks: u64,
+}
+
+/// How a paper account's equity moved over the report's window
+#[derive(Debug, Clone, Serialize)]
+pub struct EquityChange {
+    pub account: String,
+    /// From the account's first snapshot in the window, `None` without one
+    pub start: Option<f64>,
+    pub equity: f64,
+    pub pnl: Option<f64>,
+}
+
+/// A summary of the last `REPORT_WINDOW`: what was downloaded and the gaps
+/// in it, the alerts and signals sent and how the paper accounts did
+#[derive(Debug, Clone, Serialize)]
+pub struct DailyReport {
+    pub job_id: i64,
+    /// Unix timestamps (seconds) of the window
+    pub from: u64,
+    pub to: u64,
+    pub pairs: Vec<PairActivity>,
+    /// Newest first
+    pub alerts: Vec<FiredAlert>,
+    pub signals: Vec<SignalCount>,
+    pub accounts: Vec<EquityChange>,
+}
+
+impl DailyReport {
+
+    pub fn total_ticks(&self) -> u64 {
+        self.pairs.iter().map(|p| p.ticks).sum()
+    }
+
+    pub fn total_signals(&self) -> u64 {
+        self.signals.iter().map(|s| s.buys + s.sells).sum()
+    }
+
+    /// e.g. "120000 ticks over 4 pairs, 1 gap (35 ticks missing), 3 alerts,
+    /// 5 signals"
+    pub fn headline(&self) -> String {
+        let gaps: u64 = self.pairs.iter().map(|p| p.gaps).sum();
+        format!(
+            "{} ticks over {} pairs, {} gap{} ({} ticks missing), {} alerts, \
+            {} signals",
+            self.total_ticks(),
+            self.pairs.len(),
+            gaps,
+            match gaps == 1 {
+                true => "",
+                false => "s"
+            },
+            self.pairs.iter().map(|p| p.missing_ticks).sum::<u64>(),
+            self.alerts.len(),
+            self.total_signals()
+        )
+    }
+}
+
+impl fmt::Display for DailyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+
+        writeln!(f, "\x1b[1;36mDaily Report (job {}):\x1b[0m", self.job_id)?;
+        writeln!(f, "  {}", self.headline())?;
+
+        for pair in &self.pairs {
+            write!(
+                f,
+                "  \x1b[33m{} {}\x1b[0m: {} ticks in {} batches",
+                pair.exchange,
+                pair.ticker,
+                pair.ticks,
+                pair.batches
+            )?;
+            if pair.gaps > 0 {
+                write!(
+                    f,
+                    ", \x1b[31m{} gaps, {} ticks missing\x1b[0m",
+                    pair.gaps,
+                    pair.missing_ticks
+                )?;
+            };
+            writeln!(f)?;
+        };
+        for alert in &self.alerts {
+            writeln!(f, "  Alert: {}", alert)?;
+        };
+        for s in &self.signals {
+            writeln!(
+                f,
+                "  Signals: {} {} {} {}: {} buy, {} sell",
+                s.exchange,
+                s.ticker,
+                s.period,
+                s.strategy,
+                s.buys,
+                s.sells
+            )?;
+        };
+        for account in &self.accounts {
+            write!(
+                f,
+                "  Paper \x1b[33m{}\x1b[0m: equity {:.2}",
+                account.account,
+                account.equity
+            )?;
+            match account.pnl {
+                Some(pnl) => writeln!(f, ", {:+.2} over the day", pnl)?,
+                None => writeln!(f, ", no snapshot to compare with")?,
+            };
+        };
+        Ok(())
+    }
+}
+
+
+/// Builds the summary of the last `REPORT_WINDOW`, from the ingest batches,
+/// alert and signal history and the paper accounts' equity snapshots
+pub async fn build_daily_report(db_pool: &PgPool)
+    -> Result<DailyReport, ReportError>
+{
+    let job_id: i64 = new_job_id();
+    let to: u64 = get_current_unix_timestamp();
+    let from: u64 = to.saturating_sub(REPORT_WINDOW.as_secs());
+
+    let rates: Vec<IngestRate> = ingest_rates(REPORT_WINDOW, db_pool.clone())
+        .await
+        .map_err(ReportError::Db)?;
+
+    let tables: Vec<String> = fetch_tables(db_pool.clone())
+        .await
+        .map_err(ReportError::Db)?;
+
+    let mut pairs: Vec<PairActivity> = Vec::new();
+    // Pairs dropped since are left out
+    for rate in rates.into_iter().filter(|r| {
+        tables.contains(&get_table_name(&r.exchange, &r.ticker))
+    }) {
+        let (gaps, missing_ticks) = tick_gaps(
+            &rate.exchange,
+            &rate.ticker,
+            rate.first_tick_id,
+            rate.last_tick_id,
+            db_pool.clone()
+        )
+            .await
+            .map_err(ReportError::Db)?;
+        pairs.push(PairActivity {
+            exchange: rate.exchange,
+            ticker: rate.ticker,
+            ticks: rate.ticks,
+            batches: rate.batches,
+            gaps,
+            missing_ticks,
+        });
+    };
+
+    let alerts: Vec<FiredAlert> = recent_alerts(REPORT_WINDOW, db_pool.clone())
+        .await
+        .map_err(ReportError::Db)?;
+    let signals: Vec<SignalCount> = signal_counts(
+        REPORT_WINDOW,
+        db_pool.clone()
+    )
+        .await
+        .map_err(ReportError::Db)?;
+
+    let mut accounts: Vec<EquityChange> = Vec::new();
+    for name in account_names(db_pool).await.map_err(ReportError::Paper)? {
+        let equity: f64 = load_account(&name, db_pool)
+            .await
+            .map_err(ReportError::Paper)?
+            .equity;
+        let start: Option<f64> = equity_curve(&name, from, db_pool)
+            .await
+            .map_err(ReportError::Paper)?
+            .first()
+            .map(|(_, equity)| *equity);
+        accounts.push(EquityChange {
+            account: name,
+            start,
+            equity,
+            pnl: start.map(|start| equity - start),
+        });
+    };
+
+    Ok(DailyReport { job_id, from, to, pairs, alerts, signals, accounts })
+}
+
+/// Keeps `report` in `_report_history`, under its job ID
+pub async fn store_report(report: &DailyReport, db_pool: &PgPool)
+    -> Result<(), ReportError>
+{
+    let body = serde_json::to_value(report).unwrap_or_default();
+    record_report(db_pool.clone(), report.job_id, DAILY_REPORT, &body)
+        .await
+        .map_err(ReportError::Db)
+}
+
+
+// -------------------------- UNIT TESTING --------------------------------- //
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn pair(ticker: &str, ticks: u64, gaps: u64, missing: u64)
+        -> PairActivity
+    {
+        PairActivity {
+            exchange: "kraken".to_string(),
+            ticker: ticker.to_string(),
+            ticks,
+            batches: 1,
+            gaps,
+            missing_ticks: missing,
+        }
+    }
+
+    #[test]
+    fn headline_test() {
+        let mut report = DailyReport {
+            job_id: 1,
+            from: 0,
+            to: 86_400,
+            pairs: vec![
+                pair("XBTUSD", 1_000, 1, 35),
+                pair("ETHUSD", 500, 0, 0)
+            ],
+            alerts: Vec::new(),
+            signals: vec![SignalCount {
+                exchange: "kraken".to_string(),
+                ticker: "XBTUSD".to_string(),
+                period: "1h".to_string(),
+                strategy: "sma-cross(20,50)".to_string(),
+                buys: 2,
+                sells: 3,
+            }],
+            accounts: Vec::new(),
+        };
+        assert_eq!(
+            report.headline(),
+            "1500 ticks over 2 pairs, 1 gap (35 ticks missing), 0 alerts, \
+            5 signals"
+        );
+
+        report.pairs.clear();
+        report.signals.clear();
+        assert_eq!(
+            report.headline(),
+            "0 ticks over 0 pairs, 0 gaps (0 ticks missing), 0 alerts, \
+            0 signals"
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use std::{collections::BTreeMap, fs, path::Path, time::Duration};
+
+use database_ops::kraken::{ClientMetrics, take_client_metrics};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use timestamp_tools::get_current_unix_timestamp;
+
+use crate::{
+    app_state::{SystemPaths, TelemetrySettings},
+    arg_parsing::Command,
+    errors::{ConfigError, RunTimeError},
+};
+
+
+/// How long a report may take before it's left for the next run
+const REPORT_TIMEOUT: Duration = Duration::from_secs(5);
+
+
+// ------------------------------ USAGE STATS ------------------------------ //
+/// Counts collected while telemetry is on, kept in `SystemPaths::telemetry`
+/// until they're reported
+///
+/// This is everything a report holds: the app version, when counting 
+/// started, how many times each command ran or failed with each kind 
+/// of error, by the names from `feature_name` and `error_category`, and 
+/// how the Kraken client's connection pool did. 
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct UsageStats {
+    pub version: String,
+    /// Unix timestamp (seconds) of the first count
+    pub since: u64,
+    pub features: BTreeMap<String, u64>,
+    pub errors: BTreeMap<String, u64>,
+    pub network: NetworkStats,
+}
+
+/// Requests sent to Kraken, the connections they opened and their summed 
+/// time, see `kraken::ClientMetrics`. Few connections for many requests 
+/// means the pool is reusing them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct NetworkStats {
+    pub requests: u64,
+    pub connections: u64,
+    pub latency_ms: u64,
+}
+
+impl NetworkStats {
+
+    pub fn add(&mut self, metrics: ClientMetrics) {
+        self.requests += metrics.requests;
+        self.connections += metrics.connections;
+        self.latency_ms += metrics.latency_ms;
+    }
+}
+
+impl UsageStats {
+
+    /// Reads the stats file, starting afresh if it's missing or unreadable
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|d| serde_json::from_str(&d).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
+
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|_| ConfigError::SaveStateFailed)?;
+
+        fs::write(path, json).map_err(|_| ConfigError::SaveStateFailed)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.features.is_empty() && self.errors.is_empty()
+    }
+}
+
+
+/// The name a command is counted under. Says what was used, never what it
+/// was used on.
+pub fn feature_name(cmd: &Command) -> &'static str {
+    match cmd {
+        Command::AddPair { .. } => "database.add_pairs",
+        Command::DropPair { .. } => "database.rm_pairs",
+        Command::DbIntegrityCheck { repair: false, .. } => "database.integrity",
+        Command::DbIntegrityCheck { repair: true, .. } => "database.repair",
+        Command::RollbackBatch { .. } => "database.rollback_batch",
+        Command::UpdatePairs => "database.update",
+        Command::RetryFailed => "database.retry_failed",
+        Command::ApiUsage => "database.api_usage",
+        Command::DbStats => "database.stats",
+        Command::ListPairs => "pairs",
+        Command::ExportPair { .. } => "pair.export",
+        Command::ImportPair { .. } => "pair.import",
+        Command::StartServer { http: true, .. } => "start.http",
+        Command::StartServer { .. } => "start.tui",
+        Command::CandleBuilder { out: Some(_), .. } => "candles.out",
+        Command::CandleBuilder { export: Some(_), .. } => "candles.export",
+        Command::CandleBuilder { .. } => "candles",
+        Command::Chart { .. } => "chart",
+        Command::Backtest { optimize: Some(_), .. } => "backtest.optimize",
+        Command::Backtest { walk_forward: Some(_), .. } => {
+            "backtest.walk_forward"
+        },
+        Command::Backtest { .. } => "backtest",
+        Command::Catalog { .. } => "catalog",
+        Command::Verify { .. } => "verify",
+        Command::ListAliases 
+        | Command::AddAlias { .. } 
+        | Command::RemoveAlias { .. } => "alias",
+        Command::GetConfig { .. } => "config.get",
+        Command::SetConfig { .. } => "config.set",
+        Command::PaperStatus { .. } => "paper.status",
+        Command::PaperOrder { .. } => "paper.order",
+        Command::PaperCancel { .. } => "paper.cancel",
+        Command::PaperModify { .. } => "paper.modify",
+        Command::PaperHistory { .. } => "paper.history",
+        Command::PaperSync { .. } => "paper.sync",
+        Command::PaperReset { .. } => "paper.reset",
+        Command::PaperRebalance { .. } => "paper.rebalance",
+        Command::AuditLog { .. } => "audit",
+        Command::LiveBalance => "live.balance",
+        Command::LiveOpenOrders => "live.orders",
+        Command::LiveOrder { .. } => "live.order",
+        Command::LiveCancel { .. } => "live.cancel",
+        Command::LiveModify { .. } => "live.modify",
+        Command::ExportFills { .. } => "fills.export",
+        Command::RecurringAdd { .. } => "recurring.add",
+        Command::RecurringList => "recurring.list",
+        Command::RecurringRemove { .. } => "recurring.remove",
+        Command::RecurringHistory { .. } => "recurring.history",
+        Command::RecurringRun => "recurring.run",
+        Command::Help => "help",
+    }
+}
+
+/// The kind of error counted, without its message
+pub fn error_category(e: &RunTimeError) -> &'static str {
+    match e {
+        RunTimeError::Init(_) => "init",
+        RunTimeError::DataBase(_) => "database",
+        RunTimeError::Bar(_) => "candles",
+        RunTimeError::Arguments(_) => "arguments",
+        RunTimeError::Backtest(_) => "backtest",
+        RunTimeError::Catalog(_) => "catalog",
+        RunTimeError::Preflight(_) => "preflight",
+        RunTimeError::Paper(_) => "paper",
+        RunTimeError::Live(_) => "live",
+        RunTimeError::Export(_) => "export",
+        RunTimeError::Recurring(_) => "recurring",
+        RunTimeError::Timeout { .. } => "timeout",
+    }
+}
+
+/// Counts a run of `feature`, see `feature_name`, its error if it failed,
+/// and the client metrics since the last run. Does nothing unless telemetry
+/// is on.
+pub fn record(
+    settings: &TelemetrySettings, 
+    paths: &SystemPaths,
+    feature: &str,
+    error: Option<&RunTimeError>
+) {
+
+    // Taken either way, so they don't pile up while telemetry is off
+    let metrics: ClientMetrics = take_client_metrics();
+
+    if !settings.enabled {
+        return
+    };
+
+    let mut stats: UsageStats = UsageStats::load(&paths.telemetry);
+
+    if stats.is_empty() {
+        stats.since = get_current_unix_timestamp();
+    };
+    stats.version = env!("CARGO_PKG_VERSION").to_string();
+
+    *stats.features.entry(feature.to_string()).or_default() += 1;
+    if let Some(e) = error {
+        *stats.errors.entry(error_category(e).to_string()).or_default() += 1;
+    };
+    stats.network.add(metrics);
+
+    // Counts are best effort, a failed write shouldn't fail the command
+    stats.save(&paths.telemetry).ok();
+}
+
+/// Posts the collected counts as JSON to the configured endpoint, and 
+/// clears them once it accepts them
+///
+/// Nothing is sent with telemetry off or without an endpoint. On any 
+/// failure the counts are kept for the next run.
+pub async fn report(
+    settings: &TelemetrySettings, 
+    paths: &SystemPaths, 
+    client: &Client
+) {
+
+    let Some(endpoint) = settings.endpoint.as_ref().filter(|_| settings.enabled)
+    else {
+        return
+    };
+
+    let stats: UsageStats = UsageStats::load(&paths.telemetry);
+    if stats.is_empty() {
+        return
+    };
+
+    let sent: bool = client
+        .post(endpoint)
+        .timeout(REPORT_TIMEOUT)
+        .json(&stats)
+        .send()
+        .await
+        .is_ok_and(|r| r.status().is_success());
+
+    if sent {
+        fs::remove_file(&paths.telemetry).ok();
+    };
+}
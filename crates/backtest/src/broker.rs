@@ -0,0 +1,189 @@
+use chrono::{DateTime, Duration, Utc};
+use num_traits::ToPrimitive;
+
+use bars::Bar;
+use portfolio::OrderSide;
+
+use crate::{BacktestError, CostModel, Liquidity};
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrderKind {
+    Market,
+    Limit(f64),
+}
+
+#[derive(Debug, Clone)]
+pub struct Order {
+    pub side: OrderSide,
+    pub kind: OrderKind,
+    pub volume: f64,
+}
+
+/// An executed order. Fees are charged in the quote currency.
+#[derive(Debug, Clone)]
+pub struct Fill {
+    pub time: DateTime<Utc>,
+    pub side: OrderSide,
+    pub volume: f64,
+    pub price: f64,
+    pub cost: f64,
+    pub fee: f64,
+    pub liquidity: Liquidity,
+}
+
+
+/// A long-only simulated broker for a single pair
+///
+/// Orders execute against the next bar: market orders fill at its open as a
+/// taker (with slippage), limit orders fill at the limit price as a maker if
+/// the bar trades through it, or at the open as a taker if the bar opens
+/// past it. Prices are rounded to the pair's tick size, volumes to its lot
+/// decimals, and fee tiers follow the traded volume of the last 30 days.
+#[derive(Debug, Clone)]
+pub struct SimBroker {
+    cost_model: CostModel,
+    cash: f64,
+    position: f64,
+    fills: Vec<Fill>,
+}
+
+impl SimBroker {
+
+    pub fn new(cost_model: CostModel, starting_cash: f64) -> Self {
+        SimBroker {
+            cost_model,
+            cash: starting_cash,
+            position: 0.0,
+            fills: Vec::new(),
+        }
+    }
+
+    pub fn cash(&self) -> f64 {
+        self.cash
+    }
+
+    pub fn position(&self) -> f64 {
+        self.position
+    }
+
+    pub fn fills(&self) -> &[Fill] {
+        &self.fills
+    }
+
+    /// Cash plus the position marked at `price`
+    pub fn equity(&self, price: f64) -> f64 {
+        self.cash + self.position * price
+    }
+
+    /// Tries to fill `order` within `bar`
+    ///
+    /// Returns `Ok(None)` when a limit order isn't reached. Orders that break
+    /// the pair's minimums, or that the account can't cover, are rejected.
+    pub fn execute(&mut self, order: &Order, bar: &Bar)
+        -> Result<Option<Fill>, BacktestError>
+    {
+        let (open, high, low) = (
+            to_f64(bar.open()),
+            to_f64(bar.high()),
+            to_f64(bar.low())
+        );
+
+        let side: OrderSide = order.side;
+        let model: &CostModel = &self.cost_model;
+        let taker_price: f64 = model.round_price(
+            model.apply_slippage(open, side), 
+            side
+        );
+
+        let (price, liquidity) = match order.kind {
+
+            OrderKind::Market => (taker_price, Liquidity::Taker),
+
+            OrderKind::Limit(limit) => {
+
+                // Limits round in the order's favour so they're never worse
+                // than requested
+                let limit: f64 = match side {
+                    OrderSide::Buy => model.round_price(limit, OrderSide::Sell),
+                    OrderSide::Sell => model.round_price(limit, OrderSide::Buy),
+                };
+
+                let (opens_through, trades_through) = match side {
+                    OrderSide::Buy => (open <= limit, low <= limit),
+                    OrderSide::Sell => (open >= limit, high >= limit),
+                };
+
+                if opens_through {
+                    (taker_price, Liquidity::Taker)
+                }
+                else if trades_through {
+                    (limit, Liquidity::Maker)
+                }
+                else {
+                    return Ok(None)
+                }
+            }
+        };
+
+        let volume: f64 = self.cost_model.round_volume(order.volume);
+        self.cost_model.check_minimums(volume, price)?;
+
+        let cost: f64 = volume * price;
+        let fee: f64 = cost * self.cost_model.fee_rate(
+            liquidity,
+            self.volume_30d(bar.open_date())
+        );
+
+        match side {
+            OrderSide::Buy => {
+                if cost + fee > self.cash {
+                    return Err(BacktestError::InsufficientFunds(format!(
+                        "Need {:.2}, have {:.2}", cost + fee, self.cash
+                    )))
+                };
+                self.cash -= cost + fee;
+                self.position += volume;
+            },
+            OrderSide::Sell => {
+                if volume > self.position {
+                    return Err(BacktestError::InsufficientFunds(format!(
+                        "Can't sell {}, position is {}", volume, self.position
+                    )))
+                };
+                self.cash += cost - fee;
+                self.position -= volume;
+            }
+        };
+
+        let fill = Fill {
+            time: bar.open_date(),
+            side,
+            volume,
+            price,
+            cost,
+            fee,
+            liquidity,
+        };
+
+        self.fills.push(fill.clone());
+
+        Ok(Some(fill))
+    }
+
+    /// Quote volume traded in the 30 days before `now`, for fee tiers
+    fn volume_30d(&self, now: DateTime<Utc>) -> f64 {
+        let since: DateTime<Utc> = now - Duration::days(30);
+        self.fills
+            .iter()
+            .rev()
+            .take_while(|f| f.time > since)
+            .map(|f| f.cost)
+            .sum()
+    }
+}
+
+
+fn to_f64<T: ToPrimitive>(value: &T) -> f64 {
+    value.to_f64().unwrap_or(f64::NAN)
+}
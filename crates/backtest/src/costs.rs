@@ -0,0 +1,151 @@
+use database_ops::kraken::AssetPairInfo;
+use portfolio::OrderSide;
+
+use crate::BacktestError;
+
+
+/// Whether a fill added liquidity to the book (maker) or took it (taker)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Liquidity {
+    Maker,
+    Taker,
+}
+
+
+/// Per-pair trading costs and order constraints
+///
+/// Built from Kraken's `AssetPairInfo`: fee tiers are `[volume, percent]`
+/// pairs keyed on 30 day traded volume, `tick_size` is the price increment,
+/// and `ordermin`/`costmin` are the smallest volume and quote cost an order
+/// may have. Slippage is an extra adverse move, in basis points, applied to
+/// taker fills.
+#[derive(Debug, Clone)]
+pub struct CostModel {
+    taker_tiers: Vec<[f64; 2]>,
+    maker_tiers: Vec<[f64; 2]>,
+    tick_size: f64,
+    lot_decimals: u32,
+    ordermin: f64,
+    costmin: f64,
+    slippage_bps: u32,
+}
+
+impl CostModel {
+
+    pub fn from_pair_info(info: &AssetPairInfo, slippage_bps: u32)
+        -> Result<Self, BacktestError>
+    {
+        let taker_tiers: Vec<[f64; 2]> = info.fees.clone();
+        let maker_tiers: Vec<[f64; 2]> = info.fees_maker
+            .clone()
+            .unwrap_or_else(|| taker_tiers.clone());
+
+        let tick_size: f64 = match parse_field(&info.tick_size, "tick_size")? {
+            t if t > 0.0 => t,
+            _ => 10f64.powi(-(info.pair_decimals as i32))
+        };
+
+        Ok(CostModel {
+            taker_tiers,
+            maker_tiers,
+            tick_size,
+            lot_decimals: info.lot_decimals,
+            ordermin: parse_field(&info.ordermin, "ordermin")?,
+            costmin: parse_field(&info.costmin, "costmin")?,
+            slippage_bps,
+        })
+    }
+
+    /// A model with slippage but no fees, tick grid or minimums, for when
+    /// the pair's info can't be fetched
+    pub fn slippage_only(slippage_bps: u32) -> Self {
+        CostModel {
+            taker_tiers: Vec::new(),
+            maker_tiers: Vec::new(),
+            tick_size: 0.0,
+            lot_decimals: 8,
+            ordermin: 0.0,
+            costmin: 0.0,
+            slippage_bps,
+        }
+    }
+
+    /// Fee as a fraction of cost for the given 30 day traded volume
+    pub fn fee_rate(&self, liquidity: Liquidity, volume_30d: f64) -> f64 {
+
+        let tiers = match liquidity {
+            Liquidity::Maker => &self.maker_tiers,
+            Liquidity::Taker => &self.taker_tiers,
+        };
+
+        tiers
+            .iter()
+            .rfind(|tier| tier[0] <= volume_30d)
+            .or(tiers.first())
+            .map(|tier| tier[1] / 100.0)
+            .unwrap_or(0.0)
+    }
+
+    /// Moves a taker fill price against the order by the slippage amount
+    pub fn apply_slippage(&self, price: f64, side: OrderSide) -> f64 {
+        let slip: f64 = price * self.slippage_bps as f64 / 10_000.0;
+        match side {
+            OrderSide::Buy => price + slip,
+            OrderSide::Sell => price - slip,
+        }
+    }
+
+    /// Rounds a price onto the tick grid, against the order
+    pub fn round_price(&self, price: f64, side: OrderSide) -> f64 {
+        if self.tick_size <= 0.0 {
+            return price
+        };
+        let ticks: f64 = price / self.tick_size;
+        // Guards against 0.30000000000000004 style error pushing a price
+        // that's already on the grid up or down a whole tick
+        let ticks: f64 = match side {
+            OrderSide::Buy => (ticks - 1e-9).ceil(),
+            OrderSide::Sell => (ticks + 1e-9).floor(),
+        };
+        ticks * self.tick_size
+    }
+
+    /// Rounds a volume down to the pair's lot decimals
+    pub fn round_volume(&self, volume: f64) -> f64 {
+        let factor: f64 = 10f64.powi(self.lot_decimals as i32);
+        (volume * factor + 1e-9).floor() / factor
+    }
+
+    /// Rejects orders below the pair's minimum volume or cost
+    pub fn check_minimums(&self, volume: f64, price: f64)
+        -> Result<(), BacktestError>
+    {
+        if volume <= 0.0 || volume < self.ordermin {
+            return Err(BacktestError::OrderRejected(format!(
+                "Volume {} is below the minimum of {}", volume, self.ordermin
+            )))
+        };
+
+        if volume * price < self.costmin {
+            return Err(BacktestError::OrderRejected(format!(
+                "Cost {:.2} is below the minimum of {}",
+                volume * price,
+                self.costmin
+            )))
+        };
+
+        Ok(())
+    }
+}
+
+
+fn parse_field(value: &str, name: &str) -> Result<f64, BacktestError> {
+
+    if value.is_empty() {
+        return Ok(0.0)
+    };
+
+    value.parse::<f64>().map_err(|_| BacktestError::InvalidPairInfo(
+        format!("{}: {}", name, value)
+    ))
+}
@@ -0,0 +1,73 @@
+pub mod broker;
+pub mod costs;
+pub mod optimize;
+pub mod report;
+pub mod runner;
+pub mod script;
+pub mod strategy;
+pub mod timeframes;
+pub mod walk_forward;
+pub use broker::{Fill, Order, OrderKind, SimBroker};
+pub use costs::{CostModel, Liquidity};
+pub use optimize::{
+    Objective, 
+    OptimizationResult, 
+    OptimizeReport, 
+    OptimizeSpec, 
+    ParamGrid, 
+    run_optimization
+};
+pub use report::{BacktestReport, Trade};
+pub use runner::{latest_orders, run_backtest};
+pub use script::{ScriptStrategy, is_script};
+pub use strategy::{
+    BUILT_IN_STRATEGIES,
+    InsideBar,
+    SmaCross, 
+    Strategy, 
+    parse_spec, 
+    strategy_from_params, 
+    strategy_from_spec
+};
+pub use timeframes::Timeframes;
+pub use walk_forward::{
+    WalkForwardConfig, 
+    WalkForwardReport, 
+    WindowResult, 
+    run_walk_forward
+};
+pub use portfolio::OrderSide;
+
+
+#[derive(Debug)]
+pub enum BacktestError {
+    InvalidPairInfo(String),
+    OrderRejected(String),
+    InsufficientFunds(String),
+    InvalidStrategy(String),
+    InvalidWindows(String),
+    NoData,
+}
+
+impl std::fmt::Display for BacktestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BacktestError::InvalidPairInfo(e) => {
+                write!(f, "BacktestError::InvalidPairInfo: {}", e)
+            },
+            BacktestError::OrderRejected(e) => {
+                write!(f, "BacktestError::OrderRejected: {}", e)
+            },
+            BacktestError::InsufficientFunds(e) => {
+                write!(f, "BacktestError::InsufficientFunds: {}", e)
+            },
+            BacktestError::InvalidStrategy(e) => {
+                write!(f, "BacktestError::InvalidStrategy: {}", e)
+            },
+            BacktestError::InvalidWindows(e) => {
+                write!(f, "BacktestError::InvalidWindows: {}", e)
+            },
+            BacktestError::NoData => write!(f, "BacktestError::NoData"),
+        }
+    }
+}
@@ -0,0 +1,423 @@
+use std::{fs, path::Path, str::FromStr};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use bars::{Bar, BarSeries};
+
+use crate::{
+    BacktestError,
+    BacktestReport,
+    CostModel,
+    SimBroker,
+    Timeframes,
+    is_script,
+    runner::{bar_info, simulate},
+    strategy::normalize_name,
+    strategy_from_params
+};
+
+
+/// The report figure parameter searches maximize
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub enum Objective {
+    TotalReturn,
+    #[default]
+    Sharpe,
+    Sortino,
+}
+
+impl Objective {
+
+    /// Score of a report, with NaN (e.g. no returns at all) ranked last
+    pub fn score(&self, report: &BacktestReport) -> f64 {
+        let value: f64 = match self {
+            Objective::TotalReturn => report.total_return,
+            Objective::Sharpe => report.sharpe,
+            Objective::Sortino => report.sortino,
+        };
+        match value.is_nan() {
+            true => f64::NEG_INFINITY,
+            false => value
+        }
+    }
+}
+
+impl FromStr for Objective {
+    type Err = BacktestError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match &s.to_lowercase()[..] {
+            "return" | "total-return" => Ok(Objective::TotalReturn),
+            "sharpe" => Ok(Objective::Sharpe),
+            "sortino" => Ok(Objective::Sortino),
+            _ => Err(BacktestError::InvalidStrategy(
+                format!("Unknown objective: {}", s)
+            ))
+        }
+    }
+}
+
+impl std::fmt::Display for Objective {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Objective::TotalReturn => write!(f, "return"),
+            Objective::Sharpe => write!(f, "sharpe"),
+            Objective::Sortino => write!(f, "sortino"),
+        }
+    }
+}
+
+
+/// Candidate values for each of a strategy's parameters, in the order the
+/// strategy takes them
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParamGrid {
+    pub strategy: String,
+    pub names: Vec<String>,
+    pub axes: Vec<Vec<usize>>,
+}
+
+impl ParamGrid {
+
+    pub fn new(
+        strategy: &str, 
+        names: Vec<String>, 
+        axes: Vec<Vec<usize>>
+    ) -> Self {
+        ParamGrid { strategy: normalize_name(strategy), names, axes }
+    }
+
+    /// A reasonable search space for a built-in strategy
+    pub fn default_for(strategy: &str) -> Result<Self, BacktestError> {
+        match &strategy.to_lowercase()[..] {
+            "sma-cross" => Ok(ParamGrid::new(
+                strategy, 
+                vec!["fast".to_string(), "slow".to_string()],
+                vec![
+                    (5..=20).step_by(5).collect(),
+                    (20..=60).step_by(10).collect(),
+                ]
+            )),
+            "inside-bar" => Ok(ParamGrid::new(
+                strategy, 
+                vec!["expiry".to_string(), "reward".to_string()],
+                vec![
+                    (1..=10).step_by(3).collect(),
+                    (1..=4).collect(),
+                ]
+            )),
+            _ => Err(BacktestError::InvalidStrategy(
+                format!("No default parameter grid for: {}", strategy)
+            ))
+        }
+    }
+
+    /// Every combination of one value per axis
+    pub fn combinations(&self) -> Vec<Vec<usize>> {
+        self.axes.iter().fold(vec![Vec::new()], |acc, axis| {
+            acc.iter()
+                .flat_map(|prefix| axis.iter().map(move |v| {
+                    let mut combo: Vec<usize> = prefix.clone();
+                    combo.push(*v);
+                    combo
+                }))
+                .collect()
+        })
+    }
+}
+
+
+/// A grid search read from a TOML file:
+///
+/// ```toml
+/// strategy = "sma-cross"
+/// objective = "sharpe"     # optional: sharpe, sortino or return
+/// top = 10                 # optional: rows to print
+///
+/// [[params]]
+/// name = "fast"
+/// values = [5, 10, 15]
+///
+/// [[params]]
+/// name = "slow"
+/// range = [20, 60, 10]     # start, end (inclusive), step
+/// ```
+///
+/// `strategy` may also be a `.rhai` script, relative to the TOML file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptimizeSpec {
+    pub grid: ParamGrid,
+    pub objective: Objective,
+    pub top: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct SpecFile {
+    strategy: String,
+    objective: Option<String>,
+    top: Option<usize>,
+    params: Vec<ParamSpec>,
+}
+
+#[derive(Deserialize)]
+struct ParamSpec {
+    name: String,
+    values: Option<Vec<usize>>,
+    range: Option<[usize; 3]>,
+}
+
+impl OptimizeSpec {
+
+    pub fn load(path: &Path) -> Result<Self, BacktestError> {
+        let text: String = fs::read_to_string(path)
+            .map_err(|e| BacktestError::InvalidStrategy(
+                format!("{}: {}", path.display(), e)
+            ))?;
+        let mut spec: OptimizeSpec = text.parse()?;
+
+        if is_script(&spec.grid.strategy) {
+            let script: &Path = Path::new(&spec.grid.strategy);
+            if script.is_relative()
+                && let Some(dir) = path.parent()
+            {
+                spec.grid.strategy = dir.join(script)
+                    .to_string_lossy()
+                    .to_string();
+            };
+        };
+
+        Ok(spec)
+    }
+}
+
+impl FromStr for OptimizeSpec {
+    type Err = BacktestError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+
+        let file: SpecFile = toml::from_str(s)
+            .map_err(|e| BacktestError::InvalidStrategy(e.to_string()))?;
+
+        let mut names: Vec<String> = Vec::with_capacity(file.params.len());
+        let mut axes: Vec<Vec<usize>> = Vec::with_capacity(file.params.len());
+
+        for param in file.params {
+
+            let values: Vec<usize> = match (param.values, param.range) {
+                (Some(values), None) => values,
+                (None, Some([start, end, step])) if step > 0 => {
+                    (start..=end).step_by(step).collect()
+                },
+                _ => Vec::new()
+            };
+
+            if values.is_empty() {
+                return Err(BacktestError::InvalidStrategy(format!(
+                    "Parameter {} needs either values or a range with a \
+                     step above zero", 
+                    param.name
+                )))
+            };
+
+            names.push(param.name);
+            axes.push(values);
+        };
+
+        Ok(OptimizeSpec {
+            grid: ParamGrid::new(&file.strategy, names, axes),
+            objective: match file.objective {
+                Some(o) => o.parse()?,
+                None => Objective::default()
+            },
+            top: file.top,
+        })
+    }
+}
+
+
+/// One backtested parameter combination
+#[derive(Debug, Clone, Serialize)]
+pub struct OptimizationResult {
+    pub params: Vec<usize>,
+    pub score: f64,
+    pub report: BacktestReport,
+}
+
+/// Grid search results, best first
+#[derive(Debug, Clone, Serialize)]
+pub struct OptimizeReport {
+    pub strategy: String,
+    pub param_names: Vec<String>,
+    pub objective: Objective,
+    pub results: Vec<OptimizationResult>,
+}
+
+impl OptimizeReport {
+
+    /// Keeps only the best `n` results
+    pub fn truncate(&mut self, n: usize) {
+        self.results.truncate(n);
+    }
+}
+
+impl std::fmt::Display for OptimizeReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+
+        writeln!(f,
+            "\x1b[1;36mGrid Search:\x1b[0m {} ({}), ranked by {}",
+            self.strategy,
+            self.param_names.join(", "),
+            self.objective
+        )?;
+        writeln!(f,
+            "  \x1b[33m{:>4}  {:<20} {:>9} {:>9} {:>8} {:>8} {:>7}\x1b[0m",
+            "rank", "params", "score", "return", "max_dd", "win", "trades"
+        )?;
+
+        for (i, r) in self.results.iter().enumerate() {
+            let color: &str = match r.report.total_return >= 0.0 {
+                true => "\x1b[32m",
+                false => "\x1b[31m",
+            };
+            writeln!(f,
+                "  {:>4}  {:<20} {:>9.2} {}{:>8.2}%\x1b[0m {:>7.2}% {:>7.1}% \
+                 {:>7}",
+                i + 1,
+                format!("{:?}", r.params),
+                r.score,
+                color,
+                r.report.total_return * 100.0,
+                r.report.max_drawdown * 100.0,
+                r.report.win_rate * 100.0,
+                r.report.trades.len()
+            )?;
+        };
+
+        Ok(())
+    }
+}
+
+
+/// Backtests every combination in `grid` over the whole series, in parallel
+///
+/// Combinations the strategy rejects (e.g. a fast period longer than the
+/// slow one), or that stop it with an error, are left out of the results.
+pub fn run_optimization(
+    series: &BarSeries,
+    timeframes: &Timeframes,
+    grid: &ParamGrid,
+    objective: Objective,
+    cost_model: &CostModel,
+    starting_cash: f64,
+) -> Result<OptimizeReport, BacktestError> {
+
+    if series.is_empty() {
+        return Err(BacktestError::NoData)
+    };
+
+    grid_search(
+        &series.bars,
+        timeframes,
+        bar_info(series),
+        grid,
+        objective,
+        cost_model,
+        starting_cash
+    )
+}
+
+pub(crate) fn grid_search(
+    bars: &[Bar],
+    timeframes: &Timeframes,
+    bar_info: (&str, &str, &str),
+    grid: &ParamGrid,
+    objective: Objective,
+    cost_model: &CostModel,
+    starting_cash: f64,
+) -> Result<OptimizeReport, BacktestError> {
+
+    let mut results: Vec<OptimizationResult> = grid
+        .combinations()
+        .into_par_iter()
+        .filter_map(|params| {
+
+            let mut strategy = strategy_from_params(&grid.strategy, &params)
+                .ok()?;
+
+            let mut broker = SimBroker::new(cost_model.clone(), starting_cash);
+            let mut equity_curve: Vec<(i64, f64)> =
+                Vec::with_capacity(bars.len());
+            simulate(
+                bars,
+                strategy.as_mut(),
+                &mut timeframes.cursor(),
+                &mut broker,
+                &mut equity_curve,
+                Vec::new()
+            );
+
+            if strategy.error().is_some() {
+                return None
+            };
+
+            let report = BacktestReport::new(
+                strategy.name(),
+                bar_info,
+                &equity_curve,
+                broker.fills(),
+                broker.position()
+            );
+
+            Some(OptimizationResult {
+                score: objective.score(&report),
+                params,
+                report,
+            })
+        })
+        .collect();
+
+    if results.is_empty() {
+        return Err(BacktestError::InvalidStrategy(format!(
+            "No valid parameter combinations for {}", grid.strategy
+        )))
+    };
+
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    Ok(OptimizeReport {
+        strategy: grid.strategy.clone(),
+        param_names: grid.names.clone(),
+        objective,
+        results,
+    })
+}
+
+/// The best parameters in `grid` over `bars`, with their score
+pub(crate) fn best_params(
+    bars: &[Bar],
+    timeframes: &Timeframes,
+    bar_info: (&str, &str, &str),
+    grid: &ParamGrid,
+    objective: Objective,
+    cost_model: &CostModel,
+    starting_cash: f64,
+) -> Result<(Vec<usize>, f64), BacktestError> {
+
+    let report = grid_search(
+        bars,
+        timeframes,
+        bar_info,
+        grid,
+        objective,
+        cost_model,
+        starting_cash
+    )?;
+
+    let best = report.results
+        .into_iter()
+        .next()
+        .ok_or(BacktestError::NoData)?;
+
+    Ok((best.params, best.score))
+}
@@ -0,0 +1,381 @@
+use serde::Serialize;
+
+use crate::{Fill, OrderSide};
+
+
+/// A closed round trip, from the first buy out of a flat position to the
+/// sell that brings it back to flat
+#[derive(Debug, Clone, Serialize)]
+pub struct Trade {
+    pub entry_time: i64,
+    pub exit_time: i64,
+    pub volume: f64,
+    pub entry_price: f64,
+    pub exit_price: f64,
+    /// Net of fees, in the quote currency
+    pub pnl: f64,
+    pub return_pct: f64,
+}
+
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BacktestReport {
+    pub strategy: String,
+    pub exchange: String,
+    pub ticker: String,
+    pub period: String,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub starting_equity: f64,
+    pub final_equity: f64,
+    pub total_return: f64,
+    pub max_drawdown: f64,
+    pub sharpe: f64,
+    pub sortino: f64,
+    pub win_rate: f64,
+    pub expectancy: f64,
+    pub total_fees: f64,
+    /// Position still held at the end, marked to the last close
+    pub open_position: f64,
+    pub trades: Vec<Trade>,
+}
+
+impl BacktestReport {
+
+    /// Builds the report from an equity curve (one `(time, equity)` point per
+    /// bar) and the broker's fills
+    pub fn new(
+        strategy: String,
+        bar_info: (&str, &str, &str),
+        equity_curve: &[(i64, f64)],
+        fills: &[Fill],
+        open_position: f64,
+    ) -> Self {
+
+        let (exchange, ticker, period) = bar_info;
+        let starting_equity: f64 = equity_curve.first().map_or(0.0, |p| p.1);
+        let final_equity: f64 = equity_curve.last().map_or(0.0, |p| p.1);
+
+        let trades: Vec<Trade> = round_trips(fills);
+        let wins: usize = trades.iter().filter(|t| t.pnl > 0.0).count();
+
+        let (win_rate, expectancy) = match trades.len() {
+            0 => (0.0, 0.0),
+            n => (
+                wins as f64 / n as f64,
+                trades.iter().map(|t| t.pnl).sum::<f64>() / n as f64
+            )
+        };
+
+        let (sharpe, sortino) = risk_ratios(equity_curve);
+
+        BacktestReport {
+            strategy,
+            exchange: exchange.to_string(),
+            ticker: ticker.to_string(),
+            period: period.to_string(),
+            start_time: equity_curve.first().map_or(0, |p| p.0),
+            end_time: equity_curve.last().map_or(0, |p| p.0),
+            starting_equity,
+            final_equity,
+            total_return: match starting_equity {
+                0.0 => 0.0,
+                s => final_equity / s - 1.0
+            },
+            max_drawdown: max_drawdown(equity_curve),
+            sharpe,
+            sortino,
+            win_rate,
+            expectancy,
+            total_fees: fills.iter().map(|f| f.fee).sum(),
+            open_position,
+            trades,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+impl std::fmt::Display for BacktestReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+
+        fn col(value: f64) -> &'static str {
+            match value >= 0.0 {
+                true => "\x1b[32m",
+                false => "\x1b[31m",
+            }
+        }
+
+        writeln!(f, "\x1b[1;36mBacktest Report:\x1b[0m")?;
+        writeln!(f, "  \x1b[33mstrategy       \x1b[0m: {}", self.strategy)?;
+        writeln!(f, "  \x1b[33mmarket         \x1b[0m: {} {} {}",
+            self.exchange, self.ticker, self.period)?;
+        writeln!(f, "  \x1b[33mstarting_equity\x1b[0m: {:.2}",
+            self.starting_equity)?;
+        writeln!(f, "  \x1b[33mfinal_equity   \x1b[0m: {:.2}",
+            self.final_equity)?;
+        writeln!(f, "  \x1b[33mtotal_return   \x1b[0m: {}{:.2}%\x1b[0m",
+            col(self.total_return), self.total_return * 100.0)?;
+        writeln!(f, "  \x1b[33mmax_drawdown   \x1b[0m: {:.2}%",
+            self.max_drawdown * 100.0)?;
+        writeln!(f, "  \x1b[33msharpe         \x1b[0m: {:.2}", self.sharpe)?;
+        writeln!(f, "  \x1b[33msortino        \x1b[0m: {:.2}", self.sortino)?;
+        writeln!(f, "  \x1b[33mwin_rate       \x1b[0m: {:.1}%",
+            self.win_rate * 100.0)?;
+        writeln!(f, "  \x1b[33mexpectancy     \x1b[0m: {}{:.2}\x1b[0m",
+            col(self.expectancy), self.expectancy)?;
+        writeln!(f, "  \x1b[33mtotal_fees     \x1b[0m: {:.2}",
+            self.total_fees)?;
+        writeln!(f, "  \x1b[33mopen_position  \x1b[0m: {}",
+            self.open_position)?;
+        writeln!(f, "  \x1b[33mtrades         \x1b[0m: {}", self.trades.len())?;
+
+        for trade in &self.trades {
+            writeln!(f,
+                "    {} -> {}  {} @ {:.2} -> {:.2}  {}{:.2} ({:.2}%)\x1b[0m",
+                trade.entry_time,
+                trade.exit_time,
+                trade.volume,
+                trade.entry_price,
+                trade.exit_price,
+                col(trade.pnl),
+                trade.pnl,
+                trade.return_pct * 100.0
+            )?;
+        };
+
+        Ok(())
+    }
+}
+
+
+// --------------------------- HELPER FUNCTIONS ---------------------------- //
+fn round_trips(fills: &[Fill]) -> Vec<Trade> {
+
+    let mut trades: Vec<Trade> = Vec::new();
+
+    let mut position: f64 = 0.0;
+    let mut entry_time: i64 = 0;
+    let mut bought: (f64, f64) = (0.0, 0.0);  // (volume, cost)
+    let mut sold: (f64, f64) = (0.0, 0.0);
+    let mut fees: f64 = 0.0;
+
+    for fill in fills {
+
+        // A sell from flat closes a position opened before these fills
+        if position == 0.0 && fill.side == OrderSide::Sell {
+            continue
+        };
+
+        if position == 0.0 {
+            entry_time = fill.time.timestamp();
+            bought = (0.0, 0.0);
+            sold = (0.0, 0.0);
+            fees = 0.0;
+        };
+
+        fees += fill.fee;
+
+        match fill.side {
+            OrderSide::Buy => {
+                position += fill.volume;
+                bought = (bought.0 + fill.volume, bought.1 + fill.cost);
+            },
+            OrderSide::Sell => {
+                position -= fill.volume;
+                sold = (sold.0 + fill.volume, sold.1 + fill.cost);
+            }
+        };
+
+        // Lot rounding leaves dust rather than an exact zero
+        if position.abs() < 1e-12 && bought.0 > 0.0 {
+            position = 0.0;
+            let pnl: f64 = sold.1 - bought.1 - fees;
+            trades.push(Trade {
+                entry_time,
+                exit_time: fill.time.timestamp(),
+                volume: bought.0,
+                entry_price: bought.1 / bought.0,
+                exit_price: sold.1 / sold.0,
+                pnl,
+                return_pct: pnl / bought.1,
+            });
+        };
+    };
+
+    trades
+}
+
+fn max_drawdown(equity_curve: &[(i64, f64)]) -> f64 {
+
+    let mut peak: f64 = f64::MIN;
+    let mut drawdown: f64 = 0.0;
+
+    for (_, equity) in equity_curve {
+        peak = peak.max(*equity);
+        if peak > 0.0 {
+            drawdown = drawdown.max((peak - equity) / peak);
+        };
+    };
+
+    drawdown
+}
+
+/// Annualized Sharpe and Sortino ratios of per-bar returns
+///
+/// Bars per year comes from the average spacing between bars, which also
+/// works for tick-based series.
+fn risk_ratios(equity_curve: &[(i64, f64)]) -> (f64, f64) {
+
+    const SECONDS_PER_YEAR: f64 = 365.25 * 24.0 * 60.0 * 60.0;
+
+    if equity_curve.len() < 3 {
+        return (0.0, 0.0)
+    };
+
+    let returns: Vec<f64> = equity_curve
+        .windows(2)
+        .filter(|w| w[0].1 > 0.0)
+        .map(|w| w[1].1 / w[0].1 - 1.0)
+        .collect();
+
+    let n: f64 = returns.len() as f64;
+    let mean: f64 = returns.iter().sum::<f64>() / n;
+
+    let std_dev: f64 = (
+        returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0)
+    ).sqrt();
+    let downside_dev: f64 = (
+        returns.iter().map(|r| r.min(0.0).powi(2)).sum::<f64>() / n
+    ).sqrt();
+
+    let span: f64 = (equity_curve[equity_curve.len() - 1].0
+        - equity_curve[0].0) as f64;
+    let bars_per_year: f64 = match span > 0.0 {
+        true => SECONDS_PER_YEAR / (span / (equity_curve.len() - 1) as f64),
+        false => 1.0
+    };
+
+    let ratio = |dev: f64| match dev > 0.0 {
+        true => mean / dev * bars_per_year.sqrt(),
+        false => 0.0
+    };
+
+    (ratio(std_dev), ratio(downside_dev))
+}
+
+
+// -------------------------- UNIT TESTING --------------------------------- //
+#[cfg(test)]
+mod tests {
+
+    use chrono::DateTime;
+
+    use super::*;
+    use crate::Liquidity;
+
+    const DAY: i64 = 86_400;
+
+    fn fill(day: i64, side: OrderSide, volume: f64, price: f64) -> Fill {
+        Fill {
+            time: DateTime::from_timestamp(day * DAY, 0).unwrap(),
+            side,
+            volume,
+            price,
+            cost: volume * price,
+            fee: volume * price / 100.0,
+            liquidity: Liquidity::Taker,
+        }
+    }
+
+    /// Equity at one day intervals
+    fn daily(equity: &[f64]) -> Vec<(i64, f64)> {
+        equity
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (i as i64 * DAY, *e))
+            .collect()
+    }
+
+    #[test]
+    fn round_trip_test() {
+
+        let trades: Vec<Trade> = round_trips(&[
+            // Closes a position from before these fills, so it's skipped
+            fill(0, OrderSide::Sell, 1.0, 90.0),
+            fill(1, OrderSide::Buy, 1.0, 100.0),
+            fill(2, OrderSide::Sell, 0.5, 110.0),
+            fill(3, OrderSide::Sell, 0.5, 120.0),
+            // Still open at the end, so it isn't a trade yet
+            fill(4, OrderSide::Buy, 1.0, 100.0),
+        ]);
+
+        assert_eq!(trades.len(), 1);
+        let trade: &Trade = &trades[0];
+        assert_eq!((trade.entry_time, trade.exit_time), (DAY, 3 * DAY));
+        assert_eq!(trade.volume, 1.0);
+        assert_eq!(trade.entry_price, 100.0);
+        assert_eq!(trade.exit_price, 115.0);
+
+        // 115 back for 100 out, less 1 + 0.55 + 0.6 in fees
+        assert!((trade.pnl - 12.85).abs() < 1e-9);
+        assert!((trade.return_pct - 0.1285).abs() < 1e-9);
+    }
+
+    #[test]
+    fn max_drawdown_test() {
+
+        let curve = daily(&[100.0, 120.0, 90.0, 130.0, 117.0]);
+        assert_eq!(max_drawdown(&curve), 0.25);
+
+        assert_eq!(max_drawdown(&daily(&[100.0, 110.0, 120.0])), 0.0);
+        assert_eq!(max_drawdown(&[]), 0.0);
+    }
+
+    #[test]
+    fn risk_ratio_test() {
+
+        // Returns of +10% then -5%, a day apart
+        let (sharpe, sortino) = risk_ratios(&daily(&[100.0, 110.0, 104.5]));
+
+        let mean: f64 = 0.025;
+        let std_dev: f64 = 0.075 * 2f64.sqrt();
+        let downside_dev: f64 = (0.05f64.powi(2) / 2.0).sqrt();
+        let annual: f64 = 365.25f64.sqrt();
+
+        assert!((sharpe - mean / std_dev * annual).abs() < 1e-9);
+        assert!((sortino - mean / downside_dev * annual).abs() < 1e-9);
+
+        // Too short to measure, or without any variation
+        assert_eq!(risk_ratios(&daily(&[100.0, 110.0])), (0.0, 0.0));
+        assert_eq!(risk_ratios(&daily(&[100.0; 5])), (0.0, 0.0));
+    }
+
+    #[test]
+    fn report_totals_test() {
+
+        let fills = [
+            fill(1, OrderSide::Buy, 1.0, 100.0),
+            fill(2, OrderSide::Sell, 1.0, 120.0),
+            fill(3, OrderSide::Buy, 1.0, 100.0),
+            fill(4, OrderSide::Sell, 1.0, 90.0),
+        ];
+        let report = BacktestReport::new(
+            "test".to_string(),
+            ("kraken", "XBTUSD", "1d"),
+            &daily(&[1_000.0, 1_000.0, 1_017.8, 1_017.8, 1_005.9]),
+            &fills,
+            0.0
+        );
+
+        assert!((report.total_return - 0.0059).abs() < 1e-9);
+        assert_eq!(report.win_rate, 0.5);
+        assert!((report.total_fees - 4.1).abs() < 1e-9);
+
+        // 17.8 won and 11.9 lost, over two trades
+        assert!((report.expectancy - 2.95).abs() < 1e-9);
+        assert_eq!((report.start_time, report.end_time), (0, 4 * DAY));
+    }
+}
@@ -0,0 +1,150 @@
+use num_traits::ToPrimitive;
+
+use bars::{Bar, BarSeries};
+
+use crate::{
+    BacktestError, 
+    BacktestReport, 
+    Order, 
+    SimBroker, 
+    Strategy,
+    Timeframes,
+    timeframes::TimeframeCursor
+};
+
+
+/// Runs `strategy` over every bar of `series`, with the bars of 
+/// `timeframes` fed to it as they close
+///
+/// Orders from one bar are executed against the next, and orders placed
+/// before the strategy has warmed up are discarded. Orders the broker
+/// rejects (below pair minimums, or more than the account can cover) are
+/// dropped, as an exchange would. Any position left at the end is marked to
+/// the final close.
+pub fn run_backtest(
+    series: &BarSeries,
+    timeframes: &Timeframes,
+    strategy: &mut dyn Strategy,
+    mut broker: SimBroker,
+) -> Result<BacktestReport, BacktestError> {
+
+    if series.is_empty() {
+        return Err(BacktestError::NoData)
+    };
+
+    let mut equity_curve: Vec<(i64, f64)> = Vec::with_capacity(series.len());
+
+    simulate(
+        &series.bars,
+        strategy,
+        &mut timeframes.cursor(),
+        &mut broker,
+        &mut equity_curve,
+        Vec::new()
+    );
+
+    if let Some(e) = strategy.error() {
+        return Err(BacktestError::InvalidStrategy(e.to_string()))
+    };
+
+    Ok(BacktestReport::new(
+        strategy.name(),
+        bar_info(series),
+        &equity_curve,
+        broker.fills(),
+        broker.position(),
+    ))
+}
+
+
+/// Runs `strategy` over `bars` and returns the orders it places on the last
+/// one, which a live setup would send before the next bar opens
+///
+/// `bars` should hold closed bars only. Fills are simulated along the way,
+/// so a strategy that only enters when flat stays quiet while the 
+/// simulation holds a position. Empty until the strategy has warmed up.
+pub fn latest_orders(
+    bars: &[Bar],
+    timeframes: &Timeframes,
+    strategy: &mut dyn Strategy,
+    mut broker: SimBroker,
+) -> Result<Vec<Order>, BacktestError> {
+
+    if bars.is_empty() {
+        return Err(BacktestError::NoData)
+    };
+
+    let orders: Vec<Order> = simulate(
+        bars,
+        strategy,
+        &mut timeframes.cursor(),
+        &mut broker,
+        &mut Vec::with_capacity(bars.len()),
+        Vec::new()
+    );
+
+    if let Some(e) = strategy.error() {
+        return Err(BacktestError::InvalidStrategy(e.to_string()))
+    };
+
+    Ok(orders)
+}
+
+
+/// Steps `strategy` through `bars`, appending one `(close time, equity)`
+/// point per bar to `equity_curve`
+///
+/// `pending` are orders placed before the first bar. Returns the orders
+/// placed on the last bar, which are still waiting for the next one.
+pub(crate) fn simulate(
+    bars: &[Bar],
+    strategy: &mut dyn Strategy,
+    timeframes: &mut TimeframeCursor,
+    broker: &mut SimBroker,
+    equity_curve: &mut Vec<(i64, f64)>,
+    mut pending: Vec<Order>,
+) -> Vec<Order> {
+
+    for bar in bars {
+
+        for order in pending.drain(..) {
+            let _ = broker.execute(&order, bar);
+        };
+
+        let close: f64 = bar.close().to_f64().unwrap_or(f64::NAN);
+        equity_curve.push(
+            (bar.close_date().timestamp(), broker.equity(close))
+        );
+
+        timeframes.advance(bar.close_date(), strategy);
+        let orders: Vec<Order> = strategy.on_bar(bar, broker);
+        pending = match strategy.is_warm() {
+            true => orders,
+            false => Vec::new()
+        };
+    };
+
+    pending
+}
+
+/// Feeds `bars` to `strategy` so its indicators are primed, discarding any
+/// orders it places
+pub(crate) fn warm_up(
+    bars: &[Bar],
+    strategy: &mut dyn Strategy,
+    timeframes: &mut TimeframeCursor,
+    broker: &SimBroker
+) {
+    for bar in bars {
+        timeframes.advance(bar.close_date(), strategy);
+        let _ = strategy.on_bar(bar, broker);
+    };
+}
+
+pub(crate) fn bar_info(series: &BarSeries) -> (&str, &str, &str) {
+    (
+        series.info.exchange(),
+        series.info.ticker(),
+        series.info.period()
+    )
+}
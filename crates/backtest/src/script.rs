@@ -0,0 +1,349 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use num_traits::ToPrimitive;
+use rhai::{AST, Array, CallFnOptions, Dynamic, Engine, Map, Scope};
+
+use bars::Bar;
+
+use crate::{BacktestError, Order, OrderKind, OrderSide, SimBroker, Strategy};
+
+
+/// File extension that marks a strategy spec as a script path
+pub const SCRIPT_EXTENSION: &str = "rhai";
+
+/// Upper bound on script operations per call, so a runaway loop fails the
+/// backtest instead of hanging it
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// Leaves headroom for fees and slippage on the next open
+const CASH_BUFFER: f64 = 0.98;
+
+
+/// Whether a strategy name refers to a Rhai script rather than a built-in
+pub fn is_script(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case(SCRIPT_EXTENSION))
+}
+
+
+/// A strategy whose entry and exit rules live in a Rhai script
+///
+/// The script must define `on_bar(bar, account)`, and may define
+/// `init(params)`, which runs once before the first bar, and `warmup()`,
+/// which returns how many bars the script needs to see before its orders are
+/// acted on. All are called with `this` bound to a map that persists between
+/// calls, for the script's own state.
+///
+/// A script can read other periods of the pair by defining `timeframes()`,
+/// returning e.g. `["1h", "1d"]`, and `on_timeframe_bar(period, bar)`, which
+/// gets each of their bars as it closes.
+///
+/// `bar` holds `open`, `high`, `low`, `close`, `volume`, `open_time` and
+/// `close_time`, and `account` holds `cash` and `position`. `on_bar`
+/// returns `()` to do nothing, `"buy"` to go all in, `"sell"` to close the
+/// whole position, or a map such as `#{ side: "buy", volume: 0.5,
+/// limit: 101.5 }` where `volume` and `limit` are optional.
+///
+/// Scripts can call `sma(values, period)`, which averages the last `period`
+/// numbers of an array, or returns `()` when there are too few.
+pub struct ScriptStrategy {
+    path: PathBuf,
+    params: Vec<usize>,
+    engine: Engine,
+    ast: AST,
+    state: Dynamic,
+    timeframes: Vec<String>,
+    /// Whether the script defines `on_timeframe_bar`
+    takes_timeframes: bool,
+    warmup: usize,
+    bars_seen: usize,
+    /// The first runtime error, after which the strategy stops trading
+    error: Option<String>,
+}
+
+impl ScriptStrategy {
+
+    pub fn load(path: &Path, params: &[usize]) -> Result<Self, BacktestError> {
+
+        let invalid = |e: String| BacktestError::InvalidStrategy(
+            format!("{}: {}", path.display(), e)
+        );
+
+        let source: String = fs::read_to_string(path)
+            .map_err(|e| invalid(e.to_string()))?;
+
+        let engine: Engine = script_engine();
+        let ast: AST = engine
+            .compile(&source)
+            .map_err(|e| invalid(e.to_string()))?;
+
+        let defines = |name: &str, arity: usize| ast
+            .iter_functions()
+            .any(|f| f.name == name && f.params.len() == arity);
+
+        let has_init: bool = defines("init", 1);
+        let has_warmup: bool = defines("warmup", 0);
+        let has_timeframes: bool = defines("timeframes", 0);
+        let takes_timeframes: bool = defines("on_timeframe_bar", 2);
+        if !defines("on_bar", 2) {
+            return Err(invalid(
+                "script must define fn on_bar(bar, account)".to_string()
+            ))
+        };
+
+        let mut strategy = ScriptStrategy {
+            path: path.to_path_buf(),
+            params: params.to_vec(),
+            state: Dynamic::from_map(Map::new()),
+            engine,
+            ast,
+            timeframes: Vec::new(),
+            takes_timeframes,
+            warmup: 0,
+            bars_seen: 0,
+            error: None,
+        };
+
+        if has_init {
+            let params: Array = params
+                .iter()
+                .map(|p| Dynamic::from_int(*p as rhai::INT))
+                .collect();
+            let _: Dynamic = strategy.call("init", (params,))
+                .map_err(invalid)?;
+        };
+
+        if has_warmup {
+            let bars: rhai::INT = strategy.call("warmup", ())
+                .and_then(|v| v
+                    .as_int()
+                    .map_err(|t| format!("warmup must return an int, got {}", t))
+                )
+                .map_err(invalid)?;
+            strategy.warmup = bars.max(0) as usize;
+        };
+
+        if has_timeframes {
+            strategy.timeframes = strategy.call("timeframes", ())
+                .and_then(|v| v
+                    .into_array()
+                    .and_then(|periods| periods
+                        .into_iter()
+                        .map(|p| p.into_string())
+                        .collect::<Result<Vec<String>, _>>()
+                    )
+                    .map_err(|t| format!(
+                        "timeframes must return an array of strings, got {}", t
+                    ))
+                )
+                .map_err(invalid)?;
+        };
+
+        Ok(strategy)
+    }
+
+    /// Calls a script function with `this` bound to the persistent state
+    fn call(&mut self, name: &str, args: impl rhai::FuncArgs)
+        -> Result<Dynamic, String>
+    {
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut self.state);
+
+        self.engine
+            .call_fn_with_options::<Dynamic>(
+                options,
+                &mut Scope::new(),
+                &self.ast,
+                name,
+                args
+            )
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl Strategy for ScriptStrategy {
+
+    fn name(&self) -> String {
+        let file = self.path
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        match self.params.is_empty() {
+            true => file,
+            false => format!(
+                "{}({})",
+                file,
+                self.params
+                    .iter()
+                    .map(|p| p.to_string())
+                    .collect::<Vec<String>>()
+                    .join(",")
+            )
+        }
+    }
+
+    fn on_bar(&mut self, bar: &Bar, broker: &SimBroker) -> Vec<Order> {
+
+        if self.error.is_some() {
+            return Vec::new()
+        };
+        self.bars_seen = self.bars_seen.saturating_add(1);
+
+        let mut account: Map = Map::new();
+        account.insert("cash".into(), Dynamic::from_float(broker.cash()));
+        account.insert(
+            "position".into(),
+            Dynamic::from_float(broker.position())
+        );
+
+        let close: f64 = bar.close().to_f64().unwrap_or(f64::NAN);
+
+        let order = self
+            .call("on_bar", (bar_to_map(bar), account))
+            .and_then(|result| to_order(result, close, broker));
+
+        match order {
+            Ok(order) => order.into_iter().collect(),
+            Err(e) => {
+                self.error = Some(format!(
+                    "{}: {}", self.path.display(), e
+                ));
+                Vec::new()
+            }
+        }
+    }
+
+    fn timeframes(&self) -> Vec<String> {
+        self.timeframes.clone()
+    }
+
+    fn on_timeframe_bar(&mut self, period: &str, bar: &Bar) {
+
+        if !self.takes_timeframes || self.error.is_some() {
+            return
+        };
+
+        let args = (period.to_string(), bar_to_map(bar));
+        if let Err(e) = self.call("on_timeframe_bar", args) {
+            self.error = Some(format!("{}: {}", self.path.display(), e));
+        };
+    }
+
+    fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    fn is_warm(&self) -> bool {
+        self.bars_seen >= self.warmup
+    }
+}
+
+
+fn price<T: ToPrimitive>(value: &T) -> Dynamic {
+    Dynamic::from_float(value.to_f64().unwrap_or(f64::NAN))
+}
+
+fn bar_to_map(bar: &Bar) -> Map {
+
+    let mut map: Map = Map::new();
+    map.insert("open".into(), price(bar.open()));
+    map.insert("high".into(), price(bar.high()));
+    map.insert("low".into(), price(bar.low()));
+    map.insert("close".into(), price(bar.close()));
+    map.insert("volume".into(), price(bar.volume()));
+    map.insert(
+        "open_time".into(),
+        Dynamic::from_int(bar.open_date().timestamp())
+    );
+    map.insert(
+        "close_time".into(),
+        Dynamic::from_int(bar.close_date().timestamp())
+    );
+
+    map
+}
+
+fn script_engine() -> Engine {
+
+    let mut engine: Engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+
+    engine.register_fn("sma", |values: Array, period: rhai::INT| -> Dynamic {
+        let period: usize = period.max(0) as usize;
+        if period == 0 || values.len() < period {
+            return Dynamic::UNIT
+        };
+        let sum: f64 = values[values.len() - period..]
+            .iter()
+            .map(|v| v
+                .as_float()
+                .or_else(|_| v.as_int().map(|i| i as f64))
+                .unwrap_or(f64::NAN)
+            )
+            .sum();
+        Dynamic::from_float(sum / period as f64)
+    });
+
+    engine
+}
+
+/// Turns what `on_bar` returned into an order
+fn to_order(result: Dynamic, close: f64, broker: &SimBroker)
+    -> Result<Option<Order>, String>
+{
+    if result.is_unit() {
+        return Ok(None)
+    };
+
+    let (side, volume, limit) = if result.is_string() {
+        (result.into_string()?, None, None)
+    }
+    else if result.is_map() {
+        let map: Map = result.cast::<Map>();
+        let number = |key: &str| -> Result<Option<f64>, String> {
+            match map.get(key) {
+                None => Ok(None),
+                Some(v) if v.is_unit() => Ok(None),
+                Some(v) => v
+                    .as_float()
+                    .or_else(|_| v.as_int().map(|i| i as f64))
+                    .map(Some)
+                    .map_err(|_| format!("{} must be a number", key))
+            }
+        };
+        let side: String = map
+            .get("side")
+            .and_then(|s| s.clone().into_string().ok())
+            .ok_or("order map needs a side of \"buy\" or \"sell\"")?;
+        (side, number("volume")?, number("limit")?)
+    }
+    else {
+        return Err(format!(
+            "on_bar must return (), \"buy\", \"sell\" or an order map, got {}",
+            result.type_name()
+        ))
+    };
+
+    let (side, default_volume) = match &side.to_lowercase()[..] {
+        "buy" => (OrderSide::Buy, broker.cash() * CASH_BUFFER / close),
+        "sell" => (OrderSide::Sell, broker.position()),
+        _ => return Err(format!("Unknown order side: {}", side))
+    };
+
+    let volume: f64 = volume.unwrap_or(default_volume);
+    if volume <= 0.0 || !volume.is_finite() {
+        return Ok(None)
+    };
+
+    Ok(Some(Order {
+        side,
+        kind: match limit {
+            Some(price) => OrderKind::Limit(price),
+            None => OrderKind::Market
+        },
+        volume,
+    }))
+}
@@ -0,0 +1,407 @@
+use std::path::Path;
+
+use num_traits::ToPrimitive;
+
+use bars::Bar;
+use indicators::{Indicator, Sma, Warmup};
+
+use crate::{
+    BacktestError, 
+    Order, 
+    OrderKind, 
+    OrderSide, 
+    ScriptStrategy, 
+    SimBroker, 
+    is_script
+};
+
+
+/// Trading logic driven one closed bar at a time
+///
+/// Orders returned from `on_bar` are executed against the following bar, so
+/// a strategy never trades on the bar it just saw close.
+pub trait Strategy {
+
+    /// Display name including parameters, e.g. `sma-cross(10,30)`
+    fn name(&self) -> String;
+
+    fn on_bar(&mut self, bar: &Bar, broker: &SimBroker) -> Vec<Order>;
+
+    /// Other periods of the same pair the strategy reads, e.g. `["1h"]` to
+    /// trade 5m bars on the hourly trend
+    fn timeframes(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Receives each bar of a period from `timeframes` once it has closed,
+    /// before `on_bar` sees the first traded bar closing at or after it
+    fn on_timeframe_bar(&mut self, _period: &str, _bar: &Bar) {}
+
+    /// An error that stopped the strategy mid-run, e.g. a failing script
+    fn error(&self) -> Option<&str> {
+        None
+    }
+
+    /// Whether the strategy's indicators have seen enough bars to be fully
+    /// formed. Orders placed before then are discarded by the runner.
+    fn is_warm(&self) -> bool {
+        true
+    }
+}
+
+
+/// Goes all in when the fast SMA crosses above the slow one, and exits when
+/// it crosses back below
+pub struct SmaCross {
+    fast_period: usize,
+    slow_period: usize,
+    fast: Sma,
+    slow: Sma,
+    previous: Option<(f64, f64)>,
+}
+
+impl SmaCross {
+
+    pub fn new(fast: usize, slow: usize) -> Result<Self, BacktestError> {
+
+        let invalid = |e: indicators::IndicatorError| {
+            BacktestError::InvalidStrategy(e.to_string())
+        };
+
+        if fast >= slow {
+            return Err(BacktestError::InvalidStrategy(
+                "sma-cross fast period must be shorter than the slow period"
+                    .to_string()
+            ))
+        };
+
+        Ok(SmaCross {
+            fast_period: fast,
+            slow_period: slow,
+            fast: Sma::new(fast).map_err(invalid)?,
+            slow: Sma::new(slow).map_err(invalid)?,
+            previous: None,
+        })
+    }
+}
+
+impl Strategy for SmaCross {
+
+    fn name(&self) -> String {
+        format!("sma-cross({},{})", self.fast_period, self.slow_period)
+    }
+
+    fn on_bar(&mut self, bar: &Bar, broker: &SimBroker) -> Vec<Order> {
+
+        let fast = self.fast.update(bar)[0];
+        let slow = self.slow.update(bar)[0];
+
+        // Values from before a gap in the data aren't crossed against
+        let (Some(fast), Some(slow)) = (fast, slow) else {
+            self.previous = None;
+            return Vec::new()
+        };
+
+        let previous = self.previous.replace((fast, slow));
+        let Some((prev_fast, prev_slow)) = previous else {
+            return Vec::new()
+        };
+
+        let close: f64 = bar.close().to_f64().unwrap_or(f64::NAN);
+
+        let crossed_up: bool = prev_fast <= prev_slow && fast > slow;
+        let crossed_down: bool = prev_fast >= prev_slow && fast < slow;
+
+        if crossed_up && broker.position() == 0.0 {
+            // Leaves headroom for fees and slippage on the next open
+            const CASH_BUFFER: f64 = 0.98;
+            vec![Order {
+                side: OrderSide::Buy,
+                kind: OrderKind::Market,
+                volume: broker.cash() * CASH_BUFFER / close,
+            }]
+        }
+        else if crossed_down && broker.position() > 0.0 {
+            vec![Order {
+                side: OrderSide::Sell,
+                kind: OrderKind::Market,
+                volume: broker.position(),
+            }]
+        }
+        else {
+            Vec::new()
+        }
+    }
+
+    fn is_warm(&self) -> bool {
+        self.fast.is_warm() && self.slow.is_warm()
+    }
+}
+
+
+/// Inside-bar breakout, long only
+///
+/// An inside bar has a lower high and a higher low than the bar before it,
+/// the mother bar. Once one forms, the strategy buys when a close breaks above
+/// the mother bar's high within `expiry` bars, and gives up on the setup if a
+/// close breaks below its low first. The trade is stopped out on a close at
+/// or below the mother bar's low, and takes profit on a close at `reward`
+/// times the entry's risk above the entry.
+///
+/// The mother bar has to be the bar right before, so a missing or filled
+/// bar drops it and any setup, and the strategy is cold until the next
+/// bar with trades.
+pub struct InsideBar {
+    expiry: usize,
+    reward: usize,
+    gaps: Warmup,
+    previous: Option<(f64, f64)>,
+    setup: Option<InsideBarSetup>,
+    /// Stop and target of the open trade
+    exits: Option<(f64, f64)>,
+}
+
+struct InsideBarSetup {
+    mother_high: f64,
+    mother_low: f64,
+    bars_left: usize,
+}
+
+impl InsideBar {
+
+    pub fn new(expiry: usize, reward: usize) -> Result<Self, BacktestError> {
+
+        if expiry == 0 || reward == 0 {
+            return Err(BacktestError::InvalidStrategy(
+                "inside-bar expiry and reward must be at least 1".to_string()
+            ))
+        };
+
+        Ok(InsideBar {
+            expiry,
+            reward,
+            gaps: Warmup::new(1),
+            previous: None,
+            setup: None,
+            exits: None,
+        })
+    }
+}
+
+impl Strategy for InsideBar {
+
+    fn name(&self) -> String {
+        format!("inside-bar({},{})", self.expiry, self.reward)
+    }
+
+    fn on_bar(&mut self, bar: &Bar, broker: &SimBroker) -> Vec<Order> {
+
+        let high: f64 = bar.high().to_f64().unwrap_or(f64::NAN);
+        let low: f64 = bar.low().to_f64().unwrap_or(f64::NAN);
+        let close: f64 = bar.close().to_f64().unwrap_or(f64::NAN);
+
+        if self.gaps.is_gap(bar) {
+            self.previous = None;
+            self.setup = None;
+        };
+
+        let previous = match bar.num_ticks() {
+            0 => self.previous.take(),
+            _ => self.previous.replace((high, low))
+        };
+
+        if broker.position() > 0.0 {
+            // A position without exits wasn't opened by this instance, e.g.
+            // one carried into a walk-forward window, so it's closed
+            let exit: bool = self.exits
+                .is_none_or(|(stop, target)| close <= stop || close >= target);
+
+            if exit {
+                self.exits = None;
+                return vec![Order {
+                    side: OrderSide::Sell,
+                    kind: OrderKind::Market,
+                    volume: broker.position(),
+                }]
+            };
+            return Vec::new()
+        };
+
+        // Flat, so any earlier entry was rejected or has been closed
+        self.exits = None;
+
+        if let Some(setup) = self.setup.take() {
+
+            if close > setup.mother_high {
+                let risk: f64 = close - setup.mother_low;
+                self.exits = Some((
+                    setup.mother_low,
+                    close + risk * self.reward as f64
+                ));
+
+                // Leaves headroom for fees and slippage on the next open
+                const CASH_BUFFER: f64 = 0.98;
+                return vec![Order {
+                    side: OrderSide::Buy,
+                    kind: OrderKind::Market,
+                    volume: broker.cash() * CASH_BUFFER / close,
+                }]
+            };
+
+            if close >= setup.mother_low && setup.bars_left > 1 {
+                self.setup = Some(InsideBarSetup {
+                    bars_left: setup.bars_left - 1,
+                    ..setup
+                });
+            };
+        };
+
+        if let Some((mother_high, mother_low)) = previous
+            && high < mother_high
+            && low > mother_low
+        {
+            self.setup = Some(InsideBarSetup {
+                mother_high,
+                mother_low,
+                bars_left: self.expiry,
+            });
+        };
+
+        Vec::new()
+    }
+
+    fn is_warm(&self) -> bool {
+        self.previous.is_some()
+    }
+}
+
+
+/// Names of the strategies `strategy_from_params` can build
+pub const BUILT_IN_STRATEGIES: [&str; 2] = ["sma-cross", "inside-bar"];
+
+/// Builds a strategy from a spec such as `sma-cross`, `sma-cross(10,30)` or
+/// `strategies/breakout.rhai(20)`
+pub fn strategy_from_spec(spec: &str)
+    -> Result<Box<dyn Strategy + Send>, BacktestError>
+{
+    let (name, params) = parse_spec(spec)?;
+    strategy_from_params(&name, &params)
+}
+
+/// Builds a strategy by name, with missing parameters set to defaults
+///
+/// Names ending in `.rhai` are loaded as script strategies, and get their
+/// parameters as given.
+pub fn strategy_from_params(name: &str, params: &[usize])
+    -> Result<Box<dyn Strategy + Send>, BacktestError>
+{
+    if is_script(name) {
+        return Ok(Box::new(ScriptStrategy::load(Path::new(name), params)?))
+    };
+
+    match &name.to_lowercase()[..] {
+        "sma-cross" => Ok(Box::new(SmaCross::new(
+            params.first().copied().unwrap_or(10),
+            params.get(1).copied().unwrap_or(30)
+        )?)),
+        "inside-bar" => Ok(Box::new(InsideBar::new(
+            params.first().copied().unwrap_or(5),
+            params.get(1).copied().unwrap_or(2)
+        )?)),
+        _ => Err(BacktestError::InvalidStrategy(
+            format!("Unknown strategy: {}", name)
+        ))
+    }
+}
+
+/// Splits `name(a,b,...)` into the name and its parameters. Built-in names
+/// are lowercased, script paths are kept as they are.
+pub fn parse_spec(spec: &str) -> Result<(String, Vec<usize>), BacktestError> {
+
+    let spec: &str = spec.trim();
+    let invalid = || BacktestError::InvalidStrategy(spec.to_string());
+
+    let (name, params) = match spec.split_once('(') {
+        Some((name, rest)) => {
+            let params = rest
+                .strip_suffix(')')
+                .ok_or_else(invalid)?
+                .split(',')
+                .filter(|p| !p.trim().is_empty())
+                .map(|p| p.trim().parse::<usize>().map_err(|_| invalid()))
+                .collect::<Result<Vec<usize>, BacktestError>>()?;
+            (name.trim(), params)
+        },
+        None => (spec, Vec::new())
+    };
+
+    Ok((normalize_name(name), params))
+}
+
+/// Lowercases built-in strategy names, leaving script paths untouched
+pub(crate) fn normalize_name(name: &str) -> String {
+    match is_script(name) {
+        true => name.to_string(),
+        false => name.to_lowercase()
+    }
+}
+
+
+// -------------------------- UNIT TESTING --------------------------------- //
+#[cfg(test)]
+mod tests {
+
+    use bigdecimal::BigDecimal;
+    use bars::{BarInfo, BarOptions, BarSeries, BarType};
+
+    use crate::{CostModel, SimBroker, Strategy};
+    use super::InsideBar;
+
+    /// One minute bars of one tick each, in the minutes given after a
+    /// round hour
+    fn minute_bars(minutes: &[u64]) -> BarSeries {
+
+        let start: u64 = 1_700_002_800 * 1_000_000;
+        let ticks = minutes
+            .iter()
+            .enumerate()
+            .map(|(i, m)| (
+                i as u64,
+                start + (m * 60 + 1) * 1_000_000,
+                BigDecimal::from(100 + *m),
+                BigDecimal::from(1)
+            ))
+            .collect();
+
+        let info = BarInfo::new(
+            "kraken".to_string(),
+            "XBTUSD".to_string(),
+            "1m".to_string(),
+            BarOptions::default()
+        ).unwrap();
+
+        BarSeries::from_ticks(info, BarType::Candle, None, ticks).unwrap()
+    }
+
+    #[test]
+    fn inside_bar_warmup_test() {
+
+        let mut series = minute_bars(&[0, 1, 4]);
+        series.fill_gaps().unwrap();
+
+        let broker = SimBroker::new(CostModel::slippage_only(0), 1000.0);
+        let mut strategy = InsideBar::new(5, 2).unwrap();
+
+        let warm: Vec<bool> = series
+            .bars
+            .iter()
+            .map(|bar| {
+                strategy.on_bar(bar, &broker);
+                strategy.is_warm()
+            })
+            .collect();
+
+        // The filled bars can't be mother bars
+        assert_eq!(warm, vec![true, true, false, false, true]);
+    }
+}
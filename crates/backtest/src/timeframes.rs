@@ -0,0 +1,78 @@
+use chrono::{DateTime, Utc};
+
+use bars::{Bar, BarSeries};
+
+use crate::Strategy;
+
+
+/// Other periods of the traded pair that a strategy reads alongside the bars
+/// it trades, e.g. 1h bars to filter 5m entries on the hourly trend
+///
+/// See `Strategy::timeframes`.
+#[derive(Debug, Clone, Default)]
+pub struct Timeframes {
+    series: Vec<(String, Vec<Bar>)>,
+}
+
+impl Timeframes {
+
+    pub fn new() -> Self {
+        Timeframes { series: Vec::new() }
+    }
+
+    /// Adds the bars of `series` under its period, replacing any bars
+    /// already held for that period
+    pub fn insert(&mut self, series: BarSeries) {
+
+        let period: String = series.info.period().to_string();
+        self.series.retain(|(p, _)| *p != period);
+        self.series.push((period, series.bars));
+    }
+
+    pub fn periods(&self) -> Vec<&str> {
+        self.series.iter().map(|(p, _)| p.as_str()).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.series.is_empty()
+    }
+
+    /// A cursor at the start of every series
+    pub(crate) fn cursor(&self) -> TimeframeCursor<'_> {
+        TimeframeCursor {
+            feeds: self.series
+                .iter()
+                .map(|(period, bars)| (period.as_str(), &bars[..], 0))
+                .collect()
+        }
+    }
+}
+
+
+/// How far into each series a strategy has been fed
+///
+/// One cursor follows one strategy instance, so bars are never repeated when
+/// the same strategy is warmed up and then traded over later bars.
+pub(crate) struct TimeframeCursor<'a> {
+    feeds: Vec<(&'a str, &'a [Bar], usize)>,
+}
+
+impl TimeframeCursor<'_> {
+
+    /// Feeds `strategy` every bar that closed at or before `until` and
+    /// hasn't been fed yet, oldest first within each period
+    pub(crate) fn advance(
+        &mut self,
+        until: DateTime<Utc>,
+        strategy: &mut dyn Strategy
+    ) {
+        for (period, bars, next) in self.feeds.iter_mut() {
+            while let Some(bar) = bars.get(*next)
+                && bar.close_date() <= until
+            {
+                strategy.on_timeframe_bar(period, bar);
+                *next += 1;
+            };
+        };
+    }
+}
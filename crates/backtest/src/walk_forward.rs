@@ -0,0 +1,191 @@
+use serde::Serialize;
+
+use bars::{Bar, BarSeries};
+
+use crate::{
+    BacktestError,
+    BacktestReport,
+    CostModel,
+    Order,
+    SimBroker,
+    Timeframes,
+    optimize::{Objective, ParamGrid, best_params},
+    runner::{bar_info, simulate, warm_up},
+    strategy_from_params
+};
+
+
+/// Window sizes, in bars, for a walk-forward run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WalkForwardConfig {
+    pub in_sample: usize,
+    pub out_of_sample: usize,
+    pub objective: Objective,
+}
+
+
+/// One in-sample optimization and the out-of-sample run that followed it
+#[derive(Debug, Clone, Serialize)]
+pub struct WindowResult {
+    pub in_sample_start: i64,
+    pub in_sample_end: i64,
+    pub params: Vec<usize>,
+    pub in_sample_score: f64,
+    pub out_of_sample: BacktestReport,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WalkForwardReport {
+    pub objective: Objective,
+    pub in_sample_bars: usize,
+    pub out_of_sample_bars: usize,
+    pub windows: Vec<WindowResult>,
+    /// All out-of-sample windows as one continuous run
+    pub combined: BacktestReport,
+}
+
+impl std::fmt::Display for WalkForwardReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+
+        writeln!(f, "\x1b[1;36mWalk-Forward Windows:\x1b[0m")?;
+        writeln!(f,
+            "  in-sample {} bars, out-of-sample {} bars, optimizing {}",
+            self.in_sample_bars,
+            self.out_of_sample_bars,
+            self.objective
+        )?;
+
+        for (i, w) in self.windows.iter().enumerate() {
+            let oos: &BacktestReport = &w.out_of_sample;
+            let color: &str = match oos.total_return >= 0.0 {
+                true => "\x1b[32m",
+                false => "\x1b[31m",
+            };
+            writeln!(f,
+                "  {:>3}  {} -> {}  {:<20} is {:>8.2}  oos {}{:>7.2}%\x1b[0m \
+                 ({} trades)",
+                i + 1,
+                oos.start_time,
+                oos.end_time,
+                format!("{:?}", w.params),
+                w.in_sample_score,
+                color,
+                oos.total_return * 100.0,
+                oos.trades.len()
+            )?;
+        };
+
+        write!(f, "{}", self.combined)
+    }
+}
+
+
+/// Walk-forward analysis of the strategy in `grid`
+///
+/// The series is split into rolling windows of `in_sample` bars followed by
+/// `out_of_sample` bars, stepping forward by `out_of_sample`. In each window
+/// every parameter combination is backtested in-sample, and the best one by
+/// `config.objective` trades the out-of-sample bars, after being warmed up
+/// on the in-sample bars. One broker is carried through all out-of-sample
+/// windows, so positions and pending orders roll into the next window and
+/// `combined` reads as a single run.
+pub fn run_walk_forward(
+    series: &BarSeries,
+    timeframes: &Timeframes,
+    grid: &ParamGrid,
+    config: WalkForwardConfig,
+    cost_model: CostModel,
+    starting_cash: f64,
+) -> Result<WalkForwardReport, BacktestError> {
+
+    let WalkForwardConfig { in_sample, out_of_sample, objective } = config;
+    let bars: &[Bar] = &series.bars;
+    let info = bar_info(series);
+
+    if in_sample == 0 || out_of_sample == 0 {
+        return Err(BacktestError::InvalidWindows(
+            "Window sizes must be at least one bar".to_string()
+        ))
+    };
+    if bars.len() <= in_sample {
+        return Err(BacktestError::InvalidWindows(format!(
+            "Need more than {} bars, series has {}", in_sample, bars.len()
+        )))
+    };
+
+    let mut broker = SimBroker::new(cost_model.clone(), starting_cash);
+    let mut pending: Vec<Order> = Vec::new();
+    let mut combined_curve: Vec<(i64, f64)> = Vec::new();
+    let mut windows: Vec<WindowResult> = Vec::new();
+
+    let mut start: usize = 0;
+    while start + in_sample < bars.len() {
+
+        let is_bars: &[Bar] = &bars[start..start + in_sample];
+        let oos_end: usize = (start + in_sample + out_of_sample)
+            .min(bars.len());
+        let oos_bars: &[Bar] = &bars[start + in_sample..oos_end];
+
+        let (params, in_sample_score) = best_params(
+            is_bars,
+            timeframes,
+            info,
+            grid,
+            objective,
+            &cost_model,
+            starting_cash
+        )?;
+
+        let mut strategy = strategy_from_params(&grid.strategy, &params)?;
+        let mut cursor = timeframes.cursor();
+        warm_up(is_bars, strategy.as_mut(), &mut cursor, &broker);
+
+        let fills_before: usize = broker.fills().len();
+        let mut window_curve: Vec<(i64, f64)> = Vec::new();
+        pending = simulate(
+            oos_bars,
+            strategy.as_mut(),
+            &mut cursor,
+            &mut broker,
+            &mut window_curve,
+            pending
+        );
+
+        if let Some(e) = strategy.error() {
+            return Err(BacktestError::InvalidStrategy(e.to_string()))
+        };
+
+        windows.push(WindowResult {
+            in_sample_start: is_bars[0].open_date().timestamp(),
+            in_sample_end: is_bars[is_bars.len() - 1]
+                .close_date()
+                .timestamp(),
+            params,
+            in_sample_score,
+            out_of_sample: BacktestReport::new(
+                strategy.name(),
+                info,
+                &window_curve,
+                &broker.fills()[fills_before..],
+                broker.position()
+            ),
+        });
+
+        combined_curve.extend(window_curve);
+        start += out_of_sample;
+    };
+
+    Ok(WalkForwardReport {
+        objective,
+        in_sample_bars: in_sample,
+        out_of_sample_bars: out_of_sample,
+        windows,
+        combined: BacktestReport::new(
+            format!("{} (walk-forward)", grid.strategy),
+            info,
+            &combined_curve,
+            broker.fills(),
+            broker.position()
+        ),
+    })
+}
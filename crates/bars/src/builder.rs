@@ -0,0 +1,216 @@
+use chrono::{DateTime, Utc};
+use sqlx::types::BigDecimal;
+
+use timestamp_tools::get_period_portions_from_string;
+
+use crate::{Bar, BarBuildError, BarInfo, BarOptions};
+
+
+/// Incremental bar builder
+///
+/// Where `BarSeries::new` needs every tick up front, a `BarBuilder` takes ticks
+/// one at a time through `push_tick`. Each call returns the bar that the tick
+/// closed, if any, and the bar still being formed is available from
+/// `partial_bar`. Ticks must arrive in time order.
+pub struct BarBuilder {
+    info: BarInfo,
+    period_number: u64,
+    current: Option<Bar>,
+}
+
+impl BarBuilder {
+
+    pub fn new(
+        exchange: String, 
+        ticker: String, 
+        period: String, 
+        options: BarOptions
+    )
+        -> Result<Self, BarBuildError>
+    {
+        let (_, period_number) = get_period_portions_from_string(
+            &period
+        )?;
+
+        if period_number == 0 {
+            return Err(BarBuildError::BuildFailed(
+                "Period length must be greater than zero".to_string()
+            ))
+        };
+
+        let info: BarInfo = BarInfo::new(exchange, ticker, period, options)?;
+
+        Ok(BarBuilder {
+            info,
+            period_number,
+            current: None
+        })
+    }
+
+    /// Adds a tick, returning the bar it completed (if any)
+    ///
+    /// Time-based bars complete when a tick lands past their close date, so
+    /// the completed bar is returned together with the first tick of the next
+    /// one. Tick-based bars complete on their final tick.
+    pub fn push_tick(&mut self, tick: (u64, u64, BigDecimal, BigDecimal))
+        -> Result<Option<Bar>, BarBuildError>
+    {
+        let tick_date: DateTime<Utc> = DateTime::from_timestamp_micros(
+            tick.1 as i64
+        ).ok_or(BarBuildError::DateConversion)?;
+
+        if let Some(bar) = &self.current
+            && tick_date < bar.open_date
+        {
+            return Err(BarBuildError::BuildFailed(format!(
+                "Tick {} is older than the current bar", tick.0
+            )))
+        };
+
+        if !self.info.time_based {
+            return Ok(self.push_count_tick(tick, tick_date))
+        };
+
+        // Without stored history, the first tick given stands in for the
+        // listing time
+        if self.info.options.listing_anchor && self.info.listing.is_none() {
+            self.info.listing = Some(tick.1);
+        };
+
+        let mut completed: Option<Bar> = None;
+
+        if let Some(bar) = &self.current
+            && tick_date >= bar.close_date
+        {
+            completed = self.current.take();
+        };
+
+        match &mut self.current {
+            Some(bar) => bar.push_tick(tick),
+            None => {
+                let (open_date, close_date) = self.info.bounds(tick.1)?;
+                self.current = Some(Bar::new(vec![tick], open_date, close_date));
+            }
+        };
+
+        Ok(completed)
+    }
+
+    fn push_count_tick(
+        &mut self,
+        tick: (u64, u64, BigDecimal, BigDecimal),
+        tick_date: DateTime<Utc>
+    ) -> Option<Bar> {
+
+        match &mut self.current {
+            Some(bar) => {
+                bar.push_tick(tick);
+                bar.close_date = tick_date;
+            },
+            None => {
+                self.current = Some(Bar::new(vec![tick], tick_date, tick_date));
+            }
+        };
+
+        match &self.current {
+            Some(bar) if bar.tick_data.len() as u64 >= self.period_number => {
+                self.current.take()
+            },
+            _ => None
+        }
+    }
+
+    /// The bar currently being formed, updated on every tick
+    pub fn partial_bar(&self) -> Option<&Bar> {
+        self.current.as_ref()
+    }
+
+    /// Consumes the builder, returning the unfinished bar (if any)
+    pub fn finish(self) -> Option<Bar> {
+        self.current
+    }
+
+    pub fn is_time_based(&self) -> bool {
+        self.info.time_based
+    }
+
+}
+
+
+// -------------------------- UNIT TESTING --------------------------------- //
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    /// A round hour, in microseconds
+    const START: u64 = 1_700_002_800 * 1_000_000;
+
+    fn builder(period: &str) -> BarBuilder {
+        BarBuilder::new(
+            "kraken".to_string(),
+            "XBTUSD".to_string(),
+            period.to_string(),
+            BarOptions::default()
+        ).unwrap()
+    }
+
+    /// Tick `id` at `secs` past `START`, with one unit of volume
+    fn tick(id: u64, secs: u64, price: u32) 
+        -> (u64, u64, BigDecimal, BigDecimal) {
+        (id, START + secs * 1_000_000, BigDecimal::from(price), 1.into())
+    }
+
+    #[test]
+    fn time_bar_completes_on_next_period_test() {
+
+        let mut bars: BarBuilder = builder("1m");
+
+        assert!(bars.push_tick(tick(0, 1, 100)).unwrap().is_none());
+        assert!(bars.push_tick(tick(1, 30, 105)).unwrap().is_none());
+        assert_eq!(bars.partial_bar().unwrap().close(), &BigDecimal::from(105));
+
+        let done: Bar = bars.push_tick(tick(2, 61, 99)).unwrap().unwrap();
+        assert_eq!(done.open(), &BigDecimal::from(100));
+        assert_eq!(done.high(), &BigDecimal::from(105));
+        assert_eq!(done.close(), &BigDecimal::from(105));
+        assert_eq!(done.volume(), &BigDecimal::from(2));
+        assert_eq!(done.num_ticks(), 2);
+
+        let partial: Bar = bars.finish().unwrap();
+        assert_eq!(partial.open(), &BigDecimal::from(99));
+        assert_eq!(partial.open_date(), done.close_date());
+    }
+
+    #[test]
+    fn tick_bar_completes_on_its_last_tick_test() {
+
+        let mut bars: BarBuilder = builder("2t");
+        assert!(!bars.is_time_based());
+
+        assert!(bars.push_tick(tick(0, 1, 100)).unwrap().is_none());
+        let done: Bar = bars.push_tick(tick(1, 5, 101)).unwrap().unwrap();
+        assert_eq!(done.num_ticks(), 2);
+        assert!(bars.partial_bar().is_none());
+
+        assert!(bars.push_tick(tick(2, 9, 102)).unwrap().is_none());
+        assert_eq!(bars.finish().unwrap().num_ticks(), 1);
+    }
+
+    #[test]
+    fn rejects_old_ticks_and_empty_periods_test() {
+
+        // Late ticks are fine within the current bar, but not before it
+        let mut bars: BarBuilder = builder("1m");
+        bars.push_tick(tick(0, 90, 100)).unwrap();
+        assert!(bars.push_tick(tick(1, 61, 100)).unwrap().is_none());
+        assert!(bars.push_tick(tick(2, 59, 100)).is_err());
+
+        assert!(BarBuilder::new(
+            "kraken".to_string(),
+            "XBTUSD".to_string(),
+            "0m".to_string(),
+            BarOptions::default()
+        ).is_err());
+    }
+}
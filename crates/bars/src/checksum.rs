@@ -0,0 +1,167 @@
+use std::{
+    fmt::Write as _,
+    fs::{self, File},
+    io::{BufReader, Read},
+    path::{Path, PathBuf}
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::BarBuildError;
+
+
+/// Extension of the checksum file written next to each export
+pub const CHECKSUM_EXTENSION: &str = "sha256";
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChecksumStatus {
+    Valid,
+    Mismatch { expected: String, actual: String },
+    /// The data file has no `.sha256` file next to it
+    MissingChecksum,
+    /// The `.sha256` file names a data file that isn't there
+    MissingFile,
+}
+
+impl std::fmt::Display for ChecksumStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChecksumStatus::Valid => write!(f, "OK"),
+            ChecksumStatus::Mismatch { expected, actual } => write!(
+                f, "MISMATCH (expected {}, got {})", expected, actual
+            ),
+            ChecksumStatus::MissingChecksum => write!(f, "NO CHECKSUM"),
+            ChecksumStatus::MissingFile => write!(f, "MISSING FILE"),
+        }
+    }
+}
+
+
+/// Path of the checksum file for `path`, e.g. `a.csv` -> `a.csv.sha256`
+pub fn checksum_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(CHECKSUM_EXTENSION);
+    PathBuf::from(name)
+}
+
+/// Hex encoded SHA-256 of a file's contents, read as a stream
+pub fn sha256_file(path: &Path) -> Result<String, BarBuildError> {
+
+    fn err<E: std::fmt::Display>(e: E) -> BarBuildError {
+        BarBuildError::Checksum(e.to_string())
+    }
+
+    let mut reader = BufReader::new(File::open(path).map_err(err)?);
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+
+    loop {
+        let n: usize = reader.read(&mut buffer).map_err(err)?;
+        if n == 0 {
+            break
+        };
+        hasher.update(&buffer[..n]);
+    };
+
+    let mut hex = String::with_capacity(64);
+    for byte in hasher.finalize().iter() {
+        let _ = write!(hex, "{:02x}", byte);
+    };
+
+    Ok(hex)
+}
+
+/// Writes `<file>.sha256` next to `path`, in the `sha256sum` format so it
+/// can also be checked with `sha256sum -c`
+pub fn write_checksum(path: &Path) -> Result<PathBuf, BarBuildError> {
+
+    let hash: String = sha256_file(path)?;
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let sum_path: PathBuf = checksum_path(path);
+    fs::write(&sum_path, format!("{}  {}\n", hash, file_name))
+        .map_err(|e| BarBuildError::Checksum(e.to_string()))?;
+
+    Ok(sum_path)
+}
+
+/// Re-checks exported files against their checksum files
+///
+/// `path` may be a data file, its `.sha256` file, or a directory, in which
+/// case every data file in it is checked. Returns each data file with its
+/// status, sorted by path.
+pub fn verify_checksums(path: &Path)
+    -> Result<Vec<(PathBuf, ChecksumStatus)>, BarBuildError>
+{
+    if path.is_dir() {
+
+        let mut files: Vec<PathBuf> = fs::read_dir(path)
+            .map_err(|e| BarBuildError::Checksum(e.to_string()))?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|p| p.is_file())
+            .map(|p| match is_checksum_file(&p) {
+                true => p.with_extension(""),
+                false => p
+            })
+            .collect();
+        files.sort();
+        files.dedup();
+
+        return files
+            .into_iter()
+            .map(|f| verify_file(&f).map(|status| (f, status)))
+            .collect()
+    };
+
+    let data_path: PathBuf = match is_checksum_file(path) {
+        true => path.with_extension(""),
+        false => path.to_path_buf()
+    };
+
+    if !data_path.exists() && !checksum_path(&data_path).exists() {
+        return Err(BarBuildError::Checksum(
+            format!("No such file: {}", path.display())
+        ))
+    };
+
+    let status = verify_file(&data_path)?;
+    Ok(vec![(data_path, status)])
+}
+
+
+// --------------------------- HELPER FUNCTIONS ---------------------------- //
+fn is_checksum_file(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == CHECKSUM_EXTENSION)
+}
+
+fn verify_file(path: &Path) -> Result<ChecksumStatus, BarBuildError> {
+
+    let sum_path: PathBuf = checksum_path(path);
+
+    if !sum_path.exists() {
+        return Ok(ChecksumStatus::MissingChecksum)
+    };
+    if !path.exists() {
+        return Ok(ChecksumStatus::MissingFile)
+    };
+
+    let expected: String = fs::read_to_string(&sum_path)
+        .map_err(|e| BarBuildError::Checksum(e.to_string()))?
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+
+    let actual: String = sha256_file(path)?;
+
+    Ok(match expected == actual {
+        true => ChecksumStatus::Valid,
+        false => ChecksumStatus::Mismatch { expected, actual }
+    })
+}
@@ -0,0 +1,394 @@
+use std::{
+    fmt::Write as _,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc
+};
+
+use chrono::{DateTime, Utc};
+use num_traits::ToPrimitive;
+use parquet::{
+    data_type::{DoubleType, Int64Type},
+    file::{properties::WriterProperties, writer::SerializedFileWriter},
+    schema::parser::parse_message_type
+};
+
+use serde::{Deserialize, Serialize};
+use string_helpers::Locale;
+
+use crate::{BarBuildError, BarSeries, Ohlcv, checksum::write_checksum};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    /// JSON Lines: one object per line, written as a stream
+    Jsonl,
+    Parquet,
+}
+
+impl ExportFormat {
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = BarBuildError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match &s.to_lowercase()[..] {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            "jsonl" | "ndjson" => Ok(ExportFormat::Jsonl),
+            "parquet" => Ok(ExportFormat::Parquet),
+            _ => Err(BarBuildError::Export(
+                format!("Unknown export format: {}", s)
+            ))
+        }
+    }
+}
+
+impl std::fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.extension())
+    }
+}
+
+
+/// When CSV fields get wrapped in double quotes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CsvQuoting {
+    /// Only fields containing the delimiter, a quote or a newline
+    #[default]
+    Minimal,
+    All,
+    Never,
+}
+
+impl FromStr for CsvQuoting {
+    type Err = BarBuildError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match &s.to_lowercase()[..] {
+            "minimal" => Ok(CsvQuoting::Minimal),
+            "all" => Ok(CsvQuoting::All),
+            "never" => Ok(CsvQuoting::Never),
+            _ => Err(BarBuildError::Export(
+                format!("Unknown CSV quoting mode: {}", s)
+            ))
+        }
+    }
+}
+
+impl std::fmt::Display for CsvQuoting {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CsvQuoting::Minimal => write!(f, "minimal"),
+            CsvQuoting::All => write!(f, "all"),
+            CsvQuoting::Never => write!(f, "never"),
+        }
+    }
+}
+
+
+/// Layout options for CSV exports
+///
+/// Anything left unset falls back to the `Locale`: its delimiter, and its
+/// date style for the timestamp column.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CsvDialect {
+    pub delimiter: Option<char>,
+    pub quoting: CsvQuoting,
+    pub header: bool,
+    /// A strftime pattern applied to the bar open time, in UTC
+    pub timestamp_format: Option<String>,
+}
+
+impl Default for CsvDialect {
+    fn default() -> Self {
+        CsvDialect {
+            delimiter: None,
+            quoting: CsvQuoting::Minimal,
+            header: true,
+            timestamp_format: None,
+        }
+    }
+}
+
+impl CsvDialect {
+
+    pub fn delimiter(&self, locale: &Locale) -> char {
+        self.delimiter.unwrap_or_else(|| locale.csv_delimiter())
+    }
+
+    pub(crate) fn format_timestamp(
+        &self, 
+        date: DateTime<Utc>, 
+        locale: &Locale
+    ) -> String {
+
+        if let Some(pattern) = &self.timestamp_format {
+            let mut text: String = String::new();
+            if write!(text, "{}", date.format(pattern)).is_ok() {
+                return text
+            };
+        };
+
+        locale.format_date(date)
+    }
+
+    pub fn quote(&self, field: &str, delimiter: char) -> String {
+
+        let needs_quotes: bool = match self.quoting {
+            CsvQuoting::All => true,
+            CsvQuoting::Never => false,
+            CsvQuoting::Minimal => field.contains(delimiter) 
+                || field.contains('"') 
+                || field.contains('\n')
+        };
+
+        match needs_quotes {
+            true => format!("\"{}\"", field.replace('"', "\"\"")),
+            false => field.to_string()
+        }
+    }
+}
+
+/// Whether `pattern` is a usable strftime pattern for `timestamp_format`
+pub fn timestamp_format_is_valid(pattern: &str) -> bool {
+    !pattern.is_empty() 
+        && chrono::format::StrftimeItems::new(pattern).parse().is_ok()
+}
+
+
+const PARQUET_SCHEMA: &str = "
+    message candles {
+        REQUIRED INT64 open_time;
+        REQUIRED INT64 close_time;
+        REQUIRED DOUBLE open;
+        REQUIRED DOUBLE high;
+        REQUIRED DOUBLE low;
+        REQUIRED DOUBLE close;
+        REQUIRED DOUBLE volume;
+    }
+";
+
+impl BarSeries {
+
+    /// Writes the series into `directory` in the given format
+    ///
+    /// The file is named by `get_file_name`, with the extension swapped for
+    /// the chosen format, and written by `export_to`. Returns the full path 
+    /// of the written file.
+    pub fn export(
+        &self,
+        directory: &Path,
+        format: ExportFormat,
+        locale: &Locale,
+        dialect: &CsvDialect
+    ) -> Result<PathBuf, BarBuildError> {
+
+        let path: PathBuf = directory
+            .join(self.get_file_name())
+            .with_extension(format.extension());
+
+        self.export_to(&path, format, locale, dialect)?;
+
+        Ok(path)
+    }
+
+    /// Writes the series to `path` in the given format
+    ///
+    /// CSV output follows `locale` and `dialect`; JSON and Parquet are 
+    /// always written with plain numbers and Unix timestamps. A `.sha256` 
+    /// checksum file is written alongside.
+    pub fn export_to(
+        &self,
+        path: &Path,
+        format: ExportFormat,
+        locale: &Locale,
+        dialect: &CsvDialect
+    ) -> Result<(), BarBuildError> {
+
+        if self.bars.is_empty() {
+            return Err(BarBuildError::Export(
+                "No bars to export".to_string()
+            ))
+        };
+
+        match format {
+            ExportFormat::Csv | ExportFormat::Json => {
+                std::fs::write(path, self.to_text(format, locale, dialect)?)
+                    .map_err(|e| BarBuildError::Export(e.to_string()))?;
+            },
+            ExportFormat::Jsonl => {
+                write_jsonl(path, self.bars.iter().map(|b| b.to_ohlcv()))?;
+            },
+            ExportFormat::Parquet => {
+                write_parquet(path, &self.to_ohlcv())?;
+            }
+        };
+
+        write_checksum(path)?;
+
+        Ok(())
+    }
+
+    /// The series as text in one of the text formats, to print rather than
+    /// save. Parquet, being binary, is refused.
+    pub fn to_text(
+        &self,
+        format: ExportFormat,
+        locale: &Locale,
+        dialect: &CsvDialect
+    ) -> Result<String, BarBuildError> {
+
+        fn err<E: std::fmt::Display>(e: E) -> BarBuildError {
+            BarBuildError::Export(e.to_string())
+        }
+
+        match format {
+            ExportFormat::Csv => Ok(self.to_csv(locale, dialect)),
+            ExportFormat::Json => {
+                serde_json::to_string(&self.to_ohlcv()).map_err(err)
+            },
+            ExportFormat::Jsonl => self.bars
+                .iter()
+                .map(|b| serde_json::to_string(&b.to_ohlcv()).map_err(err))
+                .collect::<Result<Vec<String>, _>>()
+                .map(|lines| lines.join("\n")),
+            ExportFormat::Parquet => Err(BarBuildError::Export(
+                "Parquet is binary, write it to a file instead".to_string()
+            ))
+        }
+    }
+
+    /// Writes the raw ticks behind the series into `directory` as JSON Lines
+    ///
+    /// Each line is `{"id", "time", "price", "volume"}` with `time` in 
+    /// microseconds. The file is named like the candle export with a 
+    /// `_ticks` suffix, and gets a `.sha256` checksum file like `export`.
+    pub fn export_ticks(&self, directory: &Path)
+        -> Result<PathBuf, BarBuildError>
+    {
+        if self.tick_data.is_empty() {
+            return Err(BarBuildError::Export(
+                "No ticks to export".to_string()
+            ))
+        };
+
+        let stem: String = self.get_file_name().replace(".csv", "");
+        let path: PathBuf = directory.join(format!("{}_ticks.jsonl", stem));
+
+        write_jsonl(
+            &path,
+            self.tick_data.iter().map(|(id, time, price, volume)| TickRecord {
+                id: *id,
+                time: *time,
+                price,
+                volume
+            })
+        )?;
+
+        write_checksum(&path)?;
+
+        Ok(path)
+    }
+}
+
+
+#[derive(Serialize)]
+struct TickRecord<'a> {
+    id: u64,
+    time: u64,
+    price: &'a bigdecimal::BigDecimal,
+    volume: &'a bigdecimal::BigDecimal,
+}
+
+/// Streams rows to `path`, one JSON object per line
+fn write_jsonl<T, I>(path: &Path, rows: I) -> Result<(), BarBuildError>
+where
+    T: Serialize,
+    I: Iterator<Item = T>
+{
+    fn err<E: std::fmt::Display>(e: E) -> BarBuildError {
+        BarBuildError::Export(e.to_string())
+    }
+
+    let mut writer = BufWriter::new(File::create(path).map_err(err)?);
+
+    for row in rows {
+        serde_json::to_writer(&mut writer, &row).map_err(err)?;
+        writer.write_all(b"\n").map_err(err)?;
+    };
+
+    writer.flush().map_err(err)
+}
+
+
+fn write_parquet(path: &Path, rows: &[Ohlcv]) -> Result<(), BarBuildError> {
+
+    fn err<E: std::fmt::Display>(e: E) -> BarBuildError {
+        BarBuildError::Export(e.to_string())
+    }
+
+    let schema = Arc::new(parse_message_type(PARQUET_SCHEMA).map_err(err)?);
+    let props = Arc::new(WriterProperties::builder().build());
+    let file = File::create(path).map_err(err)?;
+
+    let mut writer = SerializedFileWriter::new(file, schema, props)
+        .map_err(err)?;
+    let mut row_group = writer.next_row_group().map_err(err)?;
+
+    let times: [Vec<i64>; 2] = [
+        rows.iter().map(|r| r.open_time).collect(),
+        rows.iter().map(|r| r.close_time).collect(),
+    ];
+
+    let to_f64 = |v: &bigdecimal::BigDecimal| v.to_f64().unwrap_or(f64::NAN);
+    let values: [Vec<f64>; 5] = [
+        rows.iter().map(|r| to_f64(&r.open)).collect(),
+        rows.iter().map(|r| to_f64(&r.high)).collect(),
+        rows.iter().map(|r| to_f64(&r.low)).collect(),
+        rows.iter().map(|r| to_f64(&r.close)).collect(),
+        rows.iter().map(|r| to_f64(&r.volume)).collect(),
+    ];
+
+    let mut index: usize = 0;
+
+    while let Some(mut column) = row_group.next_column().map_err(err)? {
+
+        if index < times.len() {
+            column
+                .typed::<Int64Type>()
+                .write_batch(&times[index], None, None)
+                .map_err(err)?;
+        }
+        else {
+            column
+                .typed::<DoubleType>()
+                .write_batch(&values[index - times.len()], None, None)
+                .map_err(err)?;
+        };
+
+        column.close().map_err(err)?;
+        index += 1;
+    };
+
+    row_group.close().map_err(err)?;
+    writer.close().map_err(err)?;
+
+    Ok(())
+}
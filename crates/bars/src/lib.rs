@@ -2,10 +2,19 @@ use std::fmt;
 use chrono::{DateTime, Utc};
 use sqlx::{PgPool, types::BigDecimal};
 use num_traits::identities::Zero;
+use serde::{Deserialize, Serialize};
 
 use database_ops::*;
+use string_helpers::Locale;
 use timestamp_tools::*;
 
+pub mod builder;
+pub mod checksum;
+pub mod export;
+pub mod resample;
+pub use builder::BarBuilder;
+pub use export::{CsvDialect, CsvQuoting, ExportFormat};
+
 
 #[derive(Debug)]
 pub enum BarBuildError {
@@ -16,6 +25,8 @@ pub enum BarBuildError {
     TickIdCalculation(String),
     Db(DbError),
     IntegrityCorruption,
+    Export(String),
+    Checksum(String),
 }
 
 impl std::fmt::Display for BarBuildError {
@@ -35,7 +46,11 @@ impl std::fmt::Display for BarBuildError {
             BarBuildError::Db(e) => write!(
                 f, "BarBuildError::Db::{}", e),
             BarBuildError::IntegrityCorruption => write!(
-                f, "BarBuildError::IntegrityCorruption")
+                f, "BarBuildError::IntegrityCorruption"),
+            BarBuildError::Export(e) => write!(
+                f, "BarBuildError::Export: {}", e),
+            BarBuildError::Checksum(e) => write!(
+                f, "BarBuildError::Checksum: {}", e)
         }
     }
 }
@@ -52,7 +67,7 @@ pub enum BarType {
 }
 
 // ------------------------------ BAR TYPES -------------------------------- //
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Bar {
     open: BigDecimal, 
     high: BigDecimal,
@@ -81,10 +96,7 @@ impl Bar {
             
             for tick in data {
                 
-                if min.is_zero() { 
-                    min = tick.2.clone(); 
-                } 
-                else if tick.2 < min {
+                if min.is_zero() || tick.2 < min { 
                     min = tick.2.clone(); 
                 };
                 
@@ -113,6 +125,100 @@ impl Bar {
             tick_data 
         }
     }
+
+    /// A bar for a period without trades, flat at the given price
+    fn flat(
+        price: BigDecimal, 
+        open_date: DateTime<Utc>, 
+        close_date: DateTime<Utc>
+    ) -> Self {
+        Bar {
+            open: price.clone(),
+            high: price.clone(),
+            low: price.clone(),
+            close: price,
+            volume: BigDecimal::zero(),
+            open_date,
+            close_date,
+            tick_data: Vec::new()
+        }
+    }
+
+    pub fn open(&self) -> &BigDecimal {
+        &self.open
+    }
+
+    pub fn high(&self) -> &BigDecimal {
+        &self.high
+    }
+
+    pub fn low(&self) -> &BigDecimal {
+        &self.low
+    }
+
+    pub fn close(&self) -> &BigDecimal {
+        &self.close
+    }
+
+    pub fn volume(&self) -> &BigDecimal {
+        &self.volume
+    }
+
+    pub fn open_date(&self) -> DateTime<Utc> {
+        self.open_date
+    }
+
+    pub fn close_date(&self) -> DateTime<Utc> {
+        self.close_date
+    }
+
+    pub fn num_ticks(&self) -> usize {
+        self.tick_data.len()
+    }
+
+    /// Copies the bar into a plain, serializable OHLCV record
+    pub fn to_ohlcv(&self) -> Ohlcv {
+        Ohlcv {
+            open_time: self.open_date.timestamp(),
+            close_time: self.close_date.timestamp(),
+            open: self.open.clone(),
+            high: self.high.clone(),
+            low: self.low.clone(),
+            close: self.close.clone(),
+            volume: self.volume.clone(),
+        }
+    }
+
+    /// Folds one more tick into the bar
+    fn push_tick(&mut self, tick: (u64, u64, BigDecimal, BigDecimal)) {
+        
+        if tick.2 > self.high {
+            self.high = tick.2.clone();
+        };
+
+        if tick.2 < self.low {
+            self.low = tick.2.clone();
+        };
+
+        self.close = tick.2.clone();
+        self.volume += tick.3.clone();
+        self.tick_data.push(tick);
+    }
+}
+
+/// Plain OHLCV record for consumers outside this crate
+///
+/// Times are unix timestamps in seconds. Decimal values serialize as strings
+/// so no precision is lost.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ohlcv {
+    pub open_time: i64,
+    pub close_time: i64,
+    pub open: BigDecimal,
+    pub high: BigDecimal,
+    pub low: BigDecimal,
+    pub close: BigDecimal,
+    pub volume: BigDecimal,
 }
 
 impl fmt::Display for Bar {
@@ -130,40 +236,117 @@ impl fmt::Display for Bar {
 }  
 
 
+/// Ticks read for a build without a time range, the latest ones
+pub const LATEST_TICKS: u64 = 1_000_000;
+
+/// Bytes a build holds per tick: the tick itself, and its copy in a bar
+pub const BYTES_PER_TICK: u64 = 256;
+
+/// Memory a candle build is allowed by default, in megabytes
+pub const DEFAULT_MEMORY_BUDGET_MB: u64 = 4096;
+
+/// Roughly how many bytes building bars from `ticks` ticks takes
+pub fn estimate_build_bytes(ticks: u64) -> u64 {
+    ticks.saturating_mul(BYTES_PER_TICK)
+}
+
+
+/// How bars are aligned to the calendar, and how large a build may get
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BarOptions {
+    pub week_start: WeekStart,
+    /// Open the first bar at the pair's first stored tick (its listing
+    /// time) rather than at a calendar boundary, with the rest following
+    /// back to back
+    pub listing_anchor: bool,
+    /// Builds estimated to need more memory than this are refused, 0 for
+    /// no limit
+    pub memory_budget_mb: u64,
+}
+
+impl Default for BarOptions {
+    fn default() -> Self {
+        BarOptions {
+            week_start: WeekStart::default(),
+            listing_anchor: false,
+            memory_budget_mb: DEFAULT_MEMORY_BUDGET_MB,
+        }
+    }
+}
+
+
 pub struct BarInfo {
     exchange: String,
     ticker: String,
     period: String,
     time_based: bool,
-    seconds_in_period: Option<u64>
+    seconds_in_period: Option<u64>,
+    options: BarOptions,
+    /// First tick time bars are anchored to, in microseconds
+    listing: Option<u64>,
 }
 
 impl BarInfo {
     
-    pub fn new(exchange: String, ticker: String, period: String) 
+    pub fn new(
+        exchange: String, 
+        ticker: String, 
+        period: String, 
+        options: BarOptions
+    ) 
         -> Result<Self, BarBuildError> 
     {
         let (sym, n) = get_period_portions_from_string(&period)
-            .map_err(|e| 
-                BarBuildError::Period(e)
+            .map_err(BarBuildError::Period
             )?;
 
         let time_based = period_is_time_based(sym)
-            .map_err(|e| BarBuildError::Period(e))?;
+            .map_err(BarBuildError::Period)?;
 
-        let seconds_in_period = match calculate_seconds_in_period(n, sym) {
-            Ok(d) => Some(d),
-            Err(_) => None
-        };
+        let seconds_in_period = calculate_seconds_in_period(n, sym).ok();
 
         Ok(BarInfo { 
             exchange, 
             ticker, 
             period, 
             time_based, 
-            seconds_in_period
+            seconds_in_period,
+            options,
+            listing: None
         })
     }
+
+    pub fn exchange(&self) -> &str {
+        &self.exchange
+    }
+
+    pub fn ticker(&self) -> &str {
+        &self.ticker
+    }
+
+    pub fn period(&self) -> &str {
+        &self.period
+    }
+
+    pub fn options(&self) -> BarOptions {
+        self.options
+    }
+
+    /// Open and close dates of the time-based bar that contains a tick,
+    /// from the listing anchor when there is one
+    pub(crate) fn bounds(&self, timestamp: u64) 
+        -> Result<(DateTime<Utc>, DateTime<Utc>), BarBuildError> 
+    {
+        let (symbol, n) = get_period_portions_from_string(&self.period)?;
+
+        let bounds = match self.listing {
+            Some(anchor) => anchored_period_bounds(timestamp, n, symbol, anchor),
+            None => period_bounds(timestamp, n, symbol, self.options.week_start)
+        }?;
+
+        Ok(bounds)
+    }
 }
 
 pub struct BarSeries {
@@ -179,30 +362,169 @@ impl BarSeries {
         ticker: String,
         period: String,
         bar_type: BarType,
+        options: BarOptions,
         db_pool: PgPool 
     ) -> Result<Self, BarBuildError> {
-    
-        let info: BarInfo = BarInfo::new(exchange, ticker, period)?; 
+        Self::build(
+            exchange, 
+            ticker, 
+            period, 
+            bar_type, 
+            options, 
+            None, 
+            db_pool
+        ).await
+    }
+
+    /// Builds bars from the ticks in `range`, from its start up to its end,
+    /// in microseconds, instead of the latest ones. Either end can be left 
+    /// open.
+    pub async fn in_time_range(
+        exchange: String,
+        ticker: String,
+        period: String,
+        bar_type: BarType,
+        options: BarOptions,
+        range: (Option<u64>, Option<u64>),
+        db_pool: PgPool 
+    ) -> Result<Self, BarBuildError> {
+        Self::build(
+            exchange, 
+            ticker, 
+            period, 
+            bar_type, 
+            options, 
+            Some(range), 
+            db_pool
+        ).await
+    }
 
-        let num_ticks: Option<u64> = Some(1_000_000);
+    /// Given a `range`, only its ticks are read, otherwise the latest 
+    /// million
+    async fn build(
+        exchange: String,
+        ticker: String,
+        period: String,
+        bar_type: BarType,
+        options: BarOptions,
+        range: Option<(Option<u64>, Option<u64>)>,
+        db_pool: PgPool 
+    ) -> Result<Self, BarBuildError> {
+    
+        let info: BarInfo = BarInfo::new(
+            exchange, 
+            ticker, 
+            period, 
+            options
+        )?; 
+
+        let listing: Option<u64> = match options.listing_anchor 
+            && info.time_based 
+        {
+            true => Self::fetch_listing(
+                &info.exchange, 
+                &info.ticker, 
+                db_pool.clone()
+            ).await?,
+            false => None
+        };
 
-        type TickRow = Vec<(u64, u64, BigDecimal, BigDecimal)>;
-        let tick_data: TickRow = match fetch_rows(
+        let tick_data = Self::fetch_ticks(
             &info.exchange, 
             &info.ticker, 
-            num_ticks,
-            db_pool 
-        ).await {
-            Ok(d) => d,
-            Err(_) => {
-                return Err(
-                    BarBuildError::TickFetch(format!(
-                        "Failed to fetch rows: asset_{}_{}", 
-                        info.exchange, 
-                        info.ticker 
-                    ))
-                ); 
-            }
+            range, 
+            db_pool
+        ).await?;
+
+        Self::from_ticks(info, bar_type, listing, tick_data)
+    }
+
+    /// Time of the pair's first stored tick, in microseconds, which 
+    /// `listing_anchor` bars open at
+    pub async fn fetch_listing(
+        exchange: &str, 
+        ticker: &str, 
+        db_pool: PgPool
+    ) -> Result<Option<u64>, BarBuildError> {
+
+        let first_row = fetch_first_or_last_row(
+            exchange, 
+            ticker, 
+            db_pool, 
+            false
+        )
+            .await
+            .map_err(|_| BarBuildError::TickFetch(format!(
+                "Failed to fetch first row: asset_{}_{}", 
+                exchange, 
+                ticker 
+            )))?;
+
+        Ok(first_row.first().map(|row| row.1))
+    }
+
+    /// Reads the ticks bars are built from: given a `range`, only its 
+    /// ticks, otherwise the latest `LATEST_TICKS`
+    pub async fn fetch_ticks(
+        exchange: &str, 
+        ticker: &str, 
+        range: Option<(Option<u64>, Option<u64>)>,
+        db_pool: PgPool
+    ) -> Result<Vec<(u64, u64, BigDecimal, BigDecimal)>, BarBuildError> {
+
+        let fetched = match range {
+            Some((from, to)) => fetch_rows_in_time_range(
+                exchange, 
+                ticker, 
+                from,
+                to,
+                db_pool 
+            ).await,
+            None => fetch_rows(
+                exchange, 
+                ticker, 
+                Some(LATEST_TICKS),
+                db_pool 
+            ).await
+        };
+
+        match fetched {
+            Ok(d) if d.is_empty() => Err(BarBuildError::TickFetch(format!(
+                "No ticks in the time range: asset_{}_{}", 
+                exchange, 
+                ticker 
+            ))),
+            Ok(d) => Ok(d),
+            Err(_) => Err(BarBuildError::TickFetch(format!(
+                "Failed to fetch rows: asset_{}_{}", 
+                exchange, 
+                ticker 
+            )))
+        }
+    }
+
+    /// Builds bars from ticks read with `fetch_ticks`, so several periods 
+    /// can be built from a single read
+    ///
+    /// `listing`, from `fetch_listing`, is only used by time based bars 
+    /// with `listing_anchor` set.
+    pub fn from_ticks(
+        mut info: BarInfo,
+        bar_type: BarType,
+        listing: Option<u64>,
+        tick_data: Vec<(u64, u64, BigDecimal, BigDecimal)>
+    ) -> Result<Self, BarBuildError> {
+
+        let options: BarOptions = info.options;
+        info.listing = listing
+            .filter(|_| options.listing_anchor && info.time_based);
+
+        if tick_data.is_empty() {
+            return Err(BarBuildError::TickFetch(format!(
+                "No ticks to build from: asset_{}_{}", 
+                info.exchange, 
+                info.ticker 
+            )))
         };
 
         if info.period.len() < 2 {
@@ -223,8 +545,15 @@ impl BarSeries {
         let (period_char, period_n) = period_keys;
 
         // START PARSING DATA
-        let (tick_indices, open_dates, close_dates) = 
-            get_tick_indices_and_dates(&tick_data, period_n, period_char)?;
+        let (tick_indices, open_dates, close_dates) = match info.listing {
+            Some(_) => anchored_tick_indices_and_dates(&tick_data, &info)?,
+            None => get_tick_indices_and_dates(
+                &tick_data, 
+                period_n, 
+                period_char, 
+                options.week_start
+            )?
+        };
         
         let mut index: usize = 0;
    
@@ -258,13 +587,13 @@ impl BarSeries {
    
         let bars = &self.bars;
 
-        if bars.len() == 0 { 
+        if bars.is_empty() { 
             return false 
         }; 
        
         if self.info.time_based {
         
-            let mut previous_ts: i64 = match bars.into_iter().next() {
+            let mut previous_ts: i64 = match bars.iter().next() {
                 Some(d) => d.close_date.timestamp(),
                 None => return false
             }; 
@@ -277,7 +606,7 @@ impl BarSeries {
             let mut diff: i64;
             let mut this_ts: i64;
             
-            for bar in bars.into_iter().skip(1) {
+            for bar in bars.iter().skip(1) {
                 this_ts = bar.close_date.timestamp(); 
                 diff = this_ts - previous_ts; 
     
@@ -301,11 +630,11 @@ impl BarSeries {
             let expected_length: usize = n as usize;
             let cutoff_target: usize = bars.len() - 1;
     
-            for (i, bar) in bars.into_iter().enumerate() {
-                if i < cutoff_target { 
-                    if bar.tick_data.len() != expected_length {
-                        return false
-                    };
+            for (i, bar) in bars.iter().enumerate() {
+                if i < cutoff_target 
+                    && bar.tick_data.len() != expected_length 
+                {
+                    return false
                 };
             };
     
@@ -319,7 +648,7 @@ impl BarSeries {
     /// Formatted as exchange_ticker_period_startTimestamp-endTimestamp.csv
     pub fn get_file_name(&self) -> String {
 
-        if self.bars.len() == 0 {
+        if self.bars.is_empty() {
             return String::new() 
         };
 
@@ -342,6 +671,88 @@ impl BarSeries {
         self.bars.len()
     }
 
+    pub fn to_ohlcv(&self) -> Vec<Ohlcv> {
+        self.bars.iter().map(Bar::to_ohlcv).collect()
+    }
+
+    /// Inserts flat bars for periods that had no trades
+    ///
+    /// Each filler bar has open, high, low and close set to the previous 
+    /// close and zero volume, giving the series a continuous time axis. Tick
+    /// based series have no empty periods, so they are left as they are.
+    pub fn fill_gaps(&mut self) -> Result<(), BarBuildError> {
+
+        if !self.info.time_based || self.bars.is_empty() {
+            return Ok(())
+        };
+
+        let mut filled: Vec<Bar> = Vec::with_capacity(self.bars.len());
+
+        for bar in std::mem::take(&mut self.bars) {
+
+            if let Some(prev) = filled.last() {
+
+                let price: BigDecimal = prev.close.clone();
+                let mut next_open: DateTime<Utc> = prev.close_date;
+
+                while next_open < bar.open_date {
+                    let (open_date, close_date) = self.info.bounds(
+                        next_open.timestamp_micros() as u64
+                    )?;
+                    filled.push(Bar::flat(price.clone(), open_date, close_date));
+                    next_open = close_date;
+                };
+            };
+
+            filled.push(bar);
+        };
+
+        self.bars = filled;
+
+        Ok(())
+    }
+
+    /// Formats the series as CSV, using the locale's date style and decimal
+    /// separator, laid out according to `dialect`
+    pub fn to_csv(&self, locale: &Locale, dialect: &CsvDialect) -> String {
+
+        let sep: char = dialect.delimiter(locale);
+        let mut rows: Vec<String> = Vec::with_capacity(self.bars.len() + 1);
+
+        if dialect.header {
+            rows.push(
+                ["Timestamp", "Open", "High", "Low", "Close", "Volume"]
+                    .iter()
+                    .map(|h| dialect.quote(h, sep))
+                    .collect::<Vec<String>>()
+                    .join(&sep.to_string())
+            );
+        };
+
+        for bar in &self.bars {
+            rows.push(
+                [
+                    dialect.format_timestamp(bar.open_date, locale),
+                    locale.format_number(&bar.open),
+                    locale.format_number(&bar.high),
+                    locale.format_number(&bar.low),
+                    locale.format_number(&bar.close),
+                    locale.format_number(&bar.volume),
+                ]
+                    .iter()
+                    .map(|v| dialect.quote(v, sep))
+                    .collect::<Vec<String>>()
+                    .join(&sep.to_string())
+            );
+        };
+
+        rows.join("\n")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bars.is_empty()
+    }
+
 }
 
 impl<'a> IntoIterator for &'a BarSeries {
@@ -355,24 +766,46 @@ impl<'a> IntoIterator for &'a BarSeries {
 
 impl fmt::Display for BarSeries {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Timestamp,Open,High,Low,Close,Volume")?;
-        for bar in &self.bars {
-            write!(f, 
-                "\n{},{},{},{},{},{}", 
-                bar.open_date.timestamp(),
-                bar.open,
-                bar.high,
-                bar.low,
-                bar.close,
-                bar.volume
-            )?;
-        };
-        Ok(())
+        let csv = self.to_csv(&Locale::default(), &CsvDialect::default());
+        write!(f, "{}", csv)
     }
 }
 
 
 // --------------------------- HELPER FUNCTIONS ---------------------------- //
+/// Tick slice start indices and bar dates for bars laid out from the 
+/// listing anchor in `info`
+fn anchored_tick_indices_and_dates(
+    tick_data: &[(u64, u64, BigDecimal, BigDecimal)],
+    info: &BarInfo
+) -> Result<TickIndicesAndDates, BarBuildError> {
+
+    let mut indices: Vec<usize> = Vec::new();
+    let mut open_dates: Vec<DateTime<Utc>> = Vec::new();
+    let mut close_dates: Vec<DateTime<Utc>> = Vec::new();
+
+    for (i, tick) in tick_data.iter().enumerate() {
+
+        let tick_date: DateTime<Utc> = DateTime::from_timestamp_micros(
+            tick.1 as i64
+        ).ok_or(BarBuildError::DateConversion)?;
+
+        if close_dates.last().is_some_and(|close| tick_date < *close) {
+            continue
+        };
+
+        let (open_date, close_date) = info.bounds(tick.1)?;
+        indices.push(i);
+        open_dates.push(open_date);
+        close_dates.push(close_date);
+    };
+
+    match indices.is_empty() {
+        true => Err(BarBuildError::Period(TimePeriodError::NotEnoughData)),
+        false => Ok((indices, open_dates, close_dates))
+    }
+}
+
 pub async fn calculate_first_tick_id(
     exchange: &str,
     ticker: &str,
@@ -382,7 +815,7 @@ pub async fn calculate_first_tick_id(
 ) -> Result<u64, BarBuildError> {
 
     let (symbol, n_periods) = get_period_portions_from_string(period)
-        .map_err(|e| BarBuildError::Period(e))?;
+        .map_err(BarBuildError::Period)?;
 
     let last_tick = fetch_first_or_last_row(
         exchange, ticker, db_pool.clone(), true
@@ -397,7 +830,7 @@ pub async fn calculate_first_tick_id(
             "Failed to fetch initial tick value".to_string()
         ))?;
         
-    if period_is_time_based(symbol).map_err(|e| BarBuildError::Period(e))? {
+    if period_is_time_based(symbol).map_err(BarBuildError::Period)? {
         
         let last_tick_timestamp: u64 = last_tick.1 / 1_000_000;
 
@@ -417,7 +850,7 @@ pub async fn calculate_first_tick_id(
             db_pool 
         ).await;
 
-        if tick.len() > 0 {
+        if !tick.is_empty() {
             Ok(tick[0].0)
         }
         else {
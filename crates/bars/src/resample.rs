@@ -0,0 +1,201 @@
+use chrono::{DateTime, Utc};
+
+use timestamp_tools::get_period_portions_from_string;
+
+use crate::{Bar, BarBuildError, BarInfo, BarSeries};
+
+
+impl BarSeries {
+
+    /// Aggregates the series into a higher timeframe without refetching ticks
+    ///
+    /// Time-based series can be resampled into any time-based period whose
+    /// length is a whole multiple of the current one (weeks and months are
+    /// grouped by calendar, or from the listing anchor). Tick-based series
+    /// can only be resampled into tick-based periods that are a whole
+    /// multiple of the current one.
+    pub fn resample(&self, period: &str) -> Result<BarSeries, BarBuildError> {
+
+        let mut info: BarInfo = BarInfo::new(
+            self.info.exchange.clone(),
+            self.info.ticker.clone(),
+            period.to_string(),
+            self.info.options
+        )?;
+
+        info.listing = self.info.listing;
+
+        let (_, n) = get_period_portions_from_string(period)?;
+        let (_, source_n) = get_period_portions_from_string(&self.info.period)?;
+
+        if n == 0 {
+            return Err(BarBuildError::BuildFailed(
+                "Period length must be greater than zero".to_string()
+            ))
+        };
+
+        if info.time_based != self.info.time_based {
+            return Err(BarBuildError::BuildFailed(format!(
+                "Can't resample {} bars into {} bars",
+                self.info.period,
+                period
+            )))
+        };
+
+        let is_multiple: bool = match (
+            self.info.seconds_in_period,
+            info.seconds_in_period
+        ) {
+            (Some(source), Some(target)) => target % source == 0,
+            (Some(source), None) => 86400 % source == 0,
+            (None, None) if !info.time_based => n % source_n == 0,
+            _ => false
+        };
+
+        if !is_multiple {
+            return Err(BarBuildError::BuildFailed(format!(
+                "{} is not a whole multiple of {}",
+                period,
+                self.info.period
+            )))
+        };
+
+        let mut bars: Vec<Bar> = Vec::new();
+
+        if info.time_based {
+
+            let mut bucket_open: Option<DateTime<Utc>> = None;
+
+            for bar in &self.bars {
+
+                let micros = bar.open_date.timestamp_micros() as u64;
+                let (open_date, close_date) = info.bounds(micros)?;
+
+                match bars.last_mut() {
+                    Some(last) if bucket_open == Some(open_date) => {
+                        merge_into(last, bar);
+                    },
+                    _ => {
+                        let mut new_bar = bar.clone();
+                        new_bar.open_date = open_date;
+                        new_bar.close_date = close_date;
+                        bars.push(new_bar);
+                        bucket_open = Some(open_date);
+                    }
+                };
+            };
+        }
+        else {
+
+            let group_size: usize = (n / source_n) as usize;
+
+            for chunk in self.bars.chunks(group_size) {
+                let mut new_bar = chunk[0].clone();
+                for bar in &chunk[1..] {
+                    merge_into(&mut new_bar, bar);
+                    new_bar.close_date = bar.close_date;
+                };
+                bars.push(new_bar);
+            };
+        };
+
+        Ok(BarSeries {
+            tick_data: self.tick_data.clone(),
+            bars,
+            info
+        })
+    }
+}
+
+
+fn merge_into(target: &mut Bar, bar: &Bar) {
+
+    if bar.high > target.high {
+        target.high = bar.high.clone();
+    };
+
+    if bar.low < target.low {
+        target.low = bar.low.clone();
+    };
+
+    target.close = bar.close.clone();
+    target.volume += bar.volume.clone();
+    target.tick_data.extend(bar.tick_data.iter().cloned());
+}
+
+
+// -------------------------- UNIT TESTING --------------------------------- //
+#[cfg(test)]
+mod tests {
+
+    use bigdecimal::BigDecimal;
+
+    use crate::{BarInfo, BarOptions, BarSeries, BarType};
+
+    /// A round hour, in microseconds
+    const START: u64 = 1_700_002_800 * 1_000_000;
+
+    /// `period` bars from one tick a minute, each priced 10 more than its 
+    /// minute's number
+    fn series(period: &str, minutes: u64) -> BarSeries {
+
+        let info = BarInfo::new(
+            "kraken".to_string(),
+            "XBTUSD".to_string(),
+            period.to_string(),
+            BarOptions::default()
+        ).unwrap();
+
+        let ticks = (0..minutes)
+            .map(|m| (
+                m,
+                START + m * 60_000_000 + 1_000_000,
+                BigDecimal::from(10 + m),
+                BigDecimal::from(1)
+            ))
+            .collect();
+
+        BarSeries::from_ticks(info, BarType::Candle, None, ticks).unwrap()
+    }
+
+    #[test]
+    fn time_resample_merges_buckets_test() {
+
+        let bars: BarSeries = series("1m", 4).resample("2m").unwrap();
+        assert_eq!(bars.len(), 2);
+
+        let first = &bars.bars[0];
+        assert_eq!(first.open(), &BigDecimal::from(10));
+        assert_eq!(first.high(), &BigDecimal::from(11));
+        assert_eq!(first.low(), &BigDecimal::from(10));
+        assert_eq!(first.close(), &BigDecimal::from(11));
+        assert_eq!(first.volume(), &BigDecimal::from(2));
+        assert_eq!(first.num_ticks(), 2);
+        assert_eq!(
+            (first.close_date() - first.open_date()).num_minutes(), 
+            2
+        );
+        assert_eq!(bars.bars[1].open_date(), first.close_date());
+    }
+
+    #[test]
+    fn tick_resample_groups_bars_test() {
+
+        // Tick bars are aligned to the tick IDs, so the one with ID 0 is
+        // left out and the rest make three full bars
+        let bars: BarSeries = series("2t", 7).resample("4t").unwrap();
+        assert_eq!(bars.len(), 2);
+        assert_eq!(bars.bars[0].num_ticks(), 4);
+        assert_eq!(bars.bars[0].open(), &BigDecimal::from(11));
+        assert_eq!(bars.bars[1].num_ticks(), 2);
+        assert_eq!(bars.bars[1].close(), &BigDecimal::from(16));
+    }
+
+    #[test]
+    fn resample_needs_a_whole_multiple_test() {
+
+        assert!(series("2m", 6).resample("3m").is_err());
+        assert!(series("1m", 4).resample("4t").is_err());
+        assert!(series("2t", 4).resample("3t").is_err());
+    }
+}
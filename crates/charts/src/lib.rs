@@ -1,4 +1,39 @@
-use bars::{BarSeries};
+use std::ops::Range;
+
+use bars::{Bar, BarSeries};
+use bigdecimal::BigDecimal;
+use num_traits::ToPrimitive;
+
+
+/// A bar's prices and volume as floats, for drawing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CandleValues {
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
+
+impl CandleValues {
+
+    pub fn is_up(&self) -> bool {
+        self.close >= self.open
+    }
+}
+
+impl From<&Bar> for CandleValues {
+    fn from(bar: &Bar) -> Self {
+        let to_f64 = |v: &BigDecimal| v.to_f64().unwrap_or(0.0);
+        CandleValues {
+            open: to_f64(bar.open()),
+            high: to_f64(bar.high()),
+            low: to_f64(bar.low()),
+            close: to_f64(bar.close()),
+            volume: to_f64(bar.volume()),
+        }
+    }
+}
 
 
 pub struct Chart {
@@ -15,6 +50,116 @@ impl Chart {
         self.bars.bars.len()
     }
 
-}
+    /// Up to `num_bars` bars, ending `offset` bars before the last one
+    ///
+    /// An `offset` past the first bar shows the oldest bars.
+    pub fn window(&self, num_bars: usize, offset: usize) -> &[Bar] {
+        &self.bars.bars[self.window_range(num_bars, offset)]
+    }
+
+    /// Indices of the bars `window` returns, for lining up other series
+    /// with them
+    pub fn window_range(&self, num_bars: usize, offset: usize) 
+        -> Range<usize> 
+    {
+        let end: usize = self.bars.len().saturating_sub(offset).max(
+            num_bars.min(self.bars.len())
+        );
+        end.saturating_sub(num_bars)..end
+    }
+
+    /// Renders the last `num_bars` bars as a plain-text candlestick chart
+    ///
+    /// Each bar is one column: `│` for the wick and `█` for the body, green 
+    /// when the bar closed up and red when it closed down. The high and low of
+    /// the visible range are printed on the left axis, and the open dates of 
+    /// the first and last visible bars underneath.
+    pub fn ascii_candles(&self, num_bars: usize, height: usize) -> String {
+
+        let visible: &[Bar] = self.window(num_bars, 0);
+
+        if visible.is_empty() || height < 2 {
+            return String::new()
+        };
 
+        let to_f64 = |v: &BigDecimal| v.to_f64().unwrap_or(0.0);
+
+        let max_price: f64 = visible
+            .iter()
+            .map(|b| to_f64(b.high()))
+            .fold(f64::MIN, f64::max);
+        let min_price: f64 = visible
+            .iter()
+            .map(|b| to_f64(b.low()))
+            .fold(f64::MAX, f64::min);
+        
+        let range: f64 = (max_price - min_price).max(f64::EPSILON);
+        let rows: f64 = (height - 1) as f64;
+
+        // Row 0 is the top of the chart
+        let to_row = |price: f64| -> usize {
+            (((max_price - price) / range) * rows).round() as usize
+        };
+
+        let max_label: String = format!("{:.2}", max_price);
+        let min_label: String = format!("{:.2}", min_price);
+        let label_width: usize = max_label.len().max(min_label.len());
+
+        let mut text = String::new();
+
+        for row in 0..height {
+
+            let label: &str = match row {
+                0 => &max_label,
+                r if r == height - 1 => &min_label,
+                _ => ""
+            };
+            text.push_str(&format!("{:>width$} ┤", label, width = label_width));
+
+            for bar in visible {
+
+                let open = to_f64(bar.open());
+                let close = to_f64(bar.close());
+                let body_top = to_row(open.max(close));
+                let body_btm = to_row(open.min(close));
+
+                let color: &str = match close >= open {
+                    true => "\x1b[32m",
+                    false => "\x1b[31m"
+                };
+
+                if row >= body_top && row <= body_btm {
+                    text.push_str(&format!("{color}█\x1b[0m"));
+                }
+                else if row >= to_row(to_f64(bar.high())) 
+                    && row <= to_row(to_f64(bar.low())) 
+                {
+                    text.push_str(&format!("{color}│\x1b[0m"));
+                }
+                else {
+                    text.push(' ');
+                };
+            };
+
+            text.push('\n');
+        };
+
+        let first_date = visible[0].open_date().format("%Y-%m-%d %H:%M");
+        let last_date = visible[visible.len() - 1]
+            .open_date()
+            .format("%Y-%m-%d %H:%M");
+
+        text.push_str(&format!(
+            "{:>width$}  {} → {} ({} bars)",
+            "",
+            first_date,
+            last_date,
+            visible.len(),
+            width = label_width
+        ));
+
+        text
+    }
+
+}
 
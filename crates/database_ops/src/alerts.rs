@@ -0,0 +1,133 @@
+use std::{fmt, time::Duration};
+
+use serde::Serialize;
+use sqlx::PgPool;
+
+use crate::DbError;
+
+
+/// Side table recording every price alert that fired, one row per threshold
+/// crossed
+///
+/// `tick_time` is the time of the tick that crossed it, in microseconds like
+/// the ticks', and `fired_at` when the alert was sent.
+pub(crate) const CREATE_ALERT_HISTORY_TABLE: &str = r#"
+    CREATE TABLE IF NOT EXISTS _alert_history (
+        id BIGSERIAL PRIMARY KEY,
+        exchange VARCHAR(16) NOT NULL,
+        asset VARCHAR(12) NOT NULL,
+        condition VARCHAR(5) NOT NULL,
+        threshold DOUBLE PRECISION NOT NULL,
+        price DOUBLE PRECISION NOT NULL,
+        tick_time BIGINT NOT NULL,
+        fired_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    );
+"#;
+
+
+/// A price alert that fired
+#[derive(Debug, Clone, Serialize)]
+pub struct FiredAlert {
+    pub exchange: String,
+    pub ticker: String,
+    /// `above` or `below`
+    pub condition: String,
+    pub threshold: f64,
+    pub price: f64,
+    /// `YYYY-MM-DD HH:MM:SS` in UTC, empty until the alert is recorded
+    pub fired_at: String,
+}
+
+impl fmt::Display for FiredAlert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}  {} {} {} {} at {}",
+            self.fired_at,
+            self.exchange,
+            self.ticker,
+            self.condition,
+            self.threshold,
+            self.price
+        )
+    }
+}
+
+
+/// Records `alert`, crossed by the tick at `tick_time` (microseconds)
+pub async fn record_alert(
+    db_pool: PgPool,
+    alert: &FiredAlert,
+    tick_time: u64
+) -> Result<(), DbError> {
+
+    let query: &'static str = r#"
+        INSERT INTO _alert_history (
+            exchange,
+            asset,
+            condition,
+            threshold,
+            price,
+            tick_time
+        ) VALUES ($1, $2, $3, $4, $5, $6);
+    "#;
+
+    sqlx::query(query)
+        .bind(alert.exchange.to_lowercase())
+        .bind(alert.ticker.to_uppercase())
+        .bind(&alert.condition)
+        .bind(alert.threshold)
+        .bind(alert.price)
+        .bind(tick_time.min(i64::MAX as u64) as i64)
+        .execute(&db_pool)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Failed to record the alert: {}", e)
+        ))?;
+
+    Ok(())
+}
+
+/// The alerts fired in the last `window`, newest first
+pub async fn recent_alerts(
+    window: Duration,
+    db_pool: PgPool
+) -> Result<Vec<FiredAlert>, DbError> {
+
+    let query: &'static str = r#"
+        SELECT
+            exchange,
+            asset,
+            condition,
+            threshold,
+            price,
+            to_char(fired_at AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+        FROM _alert_history
+        WHERE fired_at > NOW() - make_interval(secs => $1)
+        ORDER BY id DESC;
+    "#;
+
+    let rows = sqlx::query_as::<
+        _, (String, String, String, f64, f64, String)
+    >(query)
+        .bind(window.as_secs_f64())
+        .fetch_all(&db_pool)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Couldn't fetch the alert history: {}", e)
+        ))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(exchange, ticker, condition, threshold, price, fired_at)| {
+            FiredAlert {
+                exchange,
+                ticker,
+                condition,
+                threshold,
+                price,
+                fired_at,
+            }
+        })
+        .collect())
+}
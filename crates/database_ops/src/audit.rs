@@ -0,0 +1,168 @@
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::PgPool;
+
+use crate::DbError;
+
+
+/// Side table recording every command that changed something, where it came
+/// from and what it was given. `error` is empty for commands that succeeded.
+pub(crate) const CREATE_AUDIT_LOG_TABLE: &str = r#"
+    CREATE TABLE IF NOT EXISTS _audit_log (
+        id BIGSERIAL PRIMARY KEY,
+        time TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        origin VARCHAR(32) NOT NULL,
+        action VARCHAR(32) NOT NULL,
+        params JSONB NOT NULL,
+        error TEXT
+    );
+"#;
+
+/// Rejects changes to `_audit_log` other than new rows
+pub(crate) const CREATE_AUDIT_GUARD_FUNCTION: &str = r#"
+    CREATE OR REPLACE FUNCTION _audit_log_append_only() RETURNS trigger AS $$
+    BEGIN
+        RAISE EXCEPTION '_audit_log is append-only';
+    END;
+    $$ LANGUAGE plpgsql;
+"#;
+
+pub(crate) const DROP_AUDIT_GUARD: &str = r#"
+    DROP TRIGGER IF EXISTS _audit_log_append_only ON _audit_log;
+"#;
+
+pub(crate) const CREATE_AUDIT_GUARD: &str = r#"
+    CREATE TRIGGER _audit_log_append_only
+    BEFORE UPDATE OR DELETE OR TRUNCATE ON _audit_log
+    FOR EACH STATEMENT EXECUTE FUNCTION _audit_log_append_only();
+"#;
+
+
+/// Where a command was run from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuditOrigin {
+    Cli,
+    Tui,
+    /// The API, with the id of the caller's key when keys are configured
+    Http(Option<String>),
+}
+
+impl fmt::Display for AuditOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditOrigin::Cli => write!(f, "cli"),
+            AuditOrigin::Tui => write!(f, "tui"),
+            AuditOrigin::Http(None) => write!(f, "http"),
+            AuditOrigin::Http(Some(key)) => write!(f, "http:{}", key),
+        }
+    }
+}
+
+
+/// Adds a row to the audit log for `action`, e.g. `pair.add`, run with
+/// `params`
+pub async fn record_action(
+    db_pool: PgPool,
+    origin: &AuditOrigin,
+    action: &str,
+    params: &Value,
+    error: Option<&str>
+) -> Result<(), DbError> {
+
+    let query: &'static str = r#"
+        INSERT INTO _audit_log (origin, action, params, error)
+        VALUES ($1, $2, $3::jsonb, $4);
+    "#;
+
+    sqlx::query(query)
+        .bind(origin.to_string())
+        .bind(action)
+        .bind(params.to_string())
+        .bind(error)
+        .execute(&db_pool)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Failed to record the command in the audit log: {}", e)
+        ))?;
+
+    Ok(())
+}
+
+
+/// One row of the audit log
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    /// `YYYY-MM-DD HH:MM:SS`, in UTC
+    pub time: String,
+    pub origin: String,
+    pub action: String,
+    pub params: Value,
+    pub error: Option<String>,
+}
+
+impl fmt::Display for AuditEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>6}  {}  {:<16} {:<18} {}",
+            self.id,
+            self.time,
+            self.origin,
+            self.action,
+            self.params
+        )?;
+        match &self.error {
+            Some(e) => write!(f, "  \x1b[31mfailed: {}\x1b[0m", e),
+            None => Ok(())
+        }
+    }
+}
+
+/// The latest `limit` rows of the audit log, newest first, only those of
+/// `action` when one is given
+pub async fn audit_log(
+    db_pool: PgPool,
+    action: Option<&str>,
+    limit: usize
+) -> Result<Vec<AuditEntry>, DbError> {
+
+    let query: &'static str = r#"
+        SELECT
+            id,
+            to_char(time AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'),
+            origin,
+            action,
+            params::text,
+            error
+        FROM _audit_log
+        WHERE $1::text IS NULL OR action = $1
+        ORDER BY id DESC
+        LIMIT $2;
+    "#;
+
+    let rows = sqlx::query_as::<
+        _, (i64, String, String, String, String, Option<String>)
+    >(query)
+        .bind(action)
+        .bind(limit.min(i64::MAX as usize) as i64)
+        .fetch_all(&db_pool)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Couldn't fetch the audit log: {}", e)
+        ))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(id, time, origin, action, params, error)| AuditEntry {
+            id,
+            time,
+            origin,
+            action,
+            params: serde_json::from_str(&params).unwrap_or(Value::Null),
+            error,
+        })
+        .collect())
+}
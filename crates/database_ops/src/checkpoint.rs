@@ -0,0 +1,151 @@
+use std::{cmp::max, fmt};
+
+use sqlx::{PgConnection, PgPool};
+
+use crate::{DbError, get_table_name, kraken::KrakenCursor};
+
+
+/// An exchange's pagination cursor
+///
+/// Each exchange pages through its trades its own way. Its adapter
+/// implements this so checkpoints are resumed and saved the same way for
+/// all of them, with the cursor kept in `_last_tick_history.cursor_ns`.
+pub trait ExchangeCursor: Copy + Ord + fmt::Display + Send {
+
+    /// The exchange's name, as used in its tick table names
+    const EXCHANGE: &'static str;
+
+    /// The cursor just before a tick stored at `time`, in microseconds
+    fn before_tick(time: u64) -> Self;
+
+    /// Reads the cursor back from `_last_tick_history.cursor_ns`
+    fn from_stored(cursor_ns: u64) -> Self;
+
+    /// The value kept in `_last_tick_history.cursor_ns`
+    fn to_stored(&self) -> u64;
+
+    /// The time the cursor points at, for messages
+    fn to_date_string(&self) -> String;
+}
+
+
+/// Download checkpoint for a single pair
+///
+/// Mirrors the pair's row in `_last_tick_history`. The row is updated in the
+/// same transaction as each batch of ticks, so it always points at the first
+/// tick that has not yet been committed.
+#[derive(Debug, Clone)]
+pub struct DownloadCheckpoint<C: ExchangeCursor> {
+    pub next_tick_id: u64,
+    pub cursor: C,
+}
+
+/// Loads the checkpoint for a pair so a download can pick up where it left off
+///
+/// Tables written before batches were committed atomically may hold ticks
+/// past the recorded checkpoint. In that case the next tick ID and the
+/// cursor are moved forward to the last stored tick, so those pages are not
+/// requested again and their rows are not inserted twice.
+pub async fn resume_from_checkpoint<C: ExchangeCursor>(
+    ticker: &str,
+    db_pool: PgPool
+) -> Result<DownloadCheckpoint<C>, DbError> {
+
+    let checkpoint_query: String = String::from(r#"
+        SELECT next_tick_id, cursor_ns
+        FROM _last_tick_history
+        WHERE asset = $1;
+        "#
+    );
+
+    let (next_tick_id, cursor) = match sqlx::query_as::<_, (i64, i64)>(
+        &checkpoint_query
+    )
+        .bind(ticker)
+        .fetch_optional(&db_pool)
+        .await
+    {
+        Ok(Some((i, c))) => (i as u64, C::from_stored(c as u64)),
+        Ok(None) | Err(_) => return Err(DbError::QueryFailed(
+            "Couldn't fetch last tick time from _last_tick_history".to_string()
+        ))
+    };
+
+    let last_tick_query: String = format!(
+        "SELECT id, time FROM {} ORDER BY id DESC LIMIT 1;",
+        get_table_name(C::EXCHANGE, ticker)
+    );
+
+    let last_stored: Option<(i64, i64)> = sqlx::query_as(&last_tick_query)
+        .fetch_optional(&db_pool)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Couldn't fetch last tick in table: {}", e)
+        ))?;
+
+    Ok(match last_stored {
+        Some((id, time)) => DownloadCheckpoint {
+            next_tick_id: max(next_tick_id, id as u64 + 1),
+            cursor: max(cursor, C::before_tick(time as u64)),
+        },
+        None => DownloadCheckpoint { next_tick_id, cursor }
+    })
+
+}
+
+/// Moves the pair's checkpoint to `checkpoint` on `conn`, which should be
+/// the transaction that inserted the ticks before it
+pub(crate) async fn save_checkpoint<C: ExchangeCursor>(
+    conn: &mut PgConnection,
+    ticker: &str,
+    checkpoint: &DownloadCheckpoint<C>
+) -> Result<(), DbError> {
+
+    let checkpoint_query: &'static str = r#"
+        UPDATE _last_tick_history
+        SET next_tick_id = $1, cursor_ns = $2
+        WHERE asset = $3;
+    "#;
+
+    sqlx::query(checkpoint_query)
+        .bind(checkpoint.next_tick_id as i64)
+        .bind(checkpoint.cursor.to_stored() as i64)
+        .bind(ticker)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Failed to update _last_tick_history: {}", e)
+        ))?;
+
+    Ok(())
+}
+
+/// The time a stored `cursor_ns` points at, read with `exchange`'s cursor,
+/// or the raw value for an exchange without one
+pub fn stored_cursor_date(exchange: &str, cursor_ns: u64) -> String {
+    match exchange.to_lowercase().as_str() {
+        KrakenCursor::EXCHANGE => {
+            KrakenCursor::from_stored(cursor_ns).to_date_string()
+        },
+        _ => cursor_ns.to_string()
+    }
+}
+
+
+// -------------------------- UNIT TESTING --------------------------------- //
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn stored_cursor_date_test() {
+
+        let nanos: u64 = 1_700_000_000_000_000_000;
+        assert_eq!(
+            stored_cursor_date("Kraken", nanos),
+            KrakenCursor::from_stored(nanos).to_date_string()
+        );
+        assert_eq!(stored_cursor_date("binance", nanos), nanos.to_string());
+    }
+}
@@ -1,10 +1,14 @@
+use serde::Serialize;
 use sqlx::{PgPool, postgres::PgPoolOptions};
 use dotenvy;
-use std::env;
+use std::{env, time::Duration};
 use tokio::task::{JoinError};
+use tokio_util::sync::CancellationToken;
 
+use crate::{ExchangeError, lineage::new_job_id};
 
-pub const DATABASE_NAME: &'static str = "dpad_llc_trading_app";
+
+pub const DATABASE_NAME: &str = "dpad_llc_trading_app";
 
 
 // ----------------------- ERROR ENUMS ----------------------------- //
@@ -16,6 +20,7 @@ pub enum RequestError {
     RequestFailed(String),
     ErrorResponse(String),
     NoData,
+    TimedOut(String),
 }
 
 impl std::fmt::Display for RequestError {
@@ -38,6 +43,9 @@ impl std::fmt::Display for RequestError {
             ),
             RequestError::NoData => write!(
                 f, "RequestError::RequestFailed: Request returned no data"
+            ),
+            RequestError::TimedOut(e) => write!(
+                f, "RequestError::TimedOut: {}", e
             )
         }
     }
@@ -67,6 +75,15 @@ pub enum DbError {
     QueryFailed(String),
     TableCreationFailed(String),
     TaskJoin(JoinError),
+    Cancelled,
+    /// The download's pagination cursor stopped advancing
+    Stalled {
+        ticker: String,
+        cursor: String,
+        idle_secs: u64,
+    },
+    /// A pair file couldn't be written, read or imported
+    PairFile(String),
 }
 
 impl From<FetchError> for DbError {
@@ -110,7 +127,21 @@ impl std::fmt::Display for DbError {
             ),
             DbError::TaskJoin(e) => write!(
                 f, "DbError: Async tasks join failed: {} ", e
-            )
+            ),
+            DbError::Cancelled => write!(
+                f, "DbError: Download cancelled, run it again to resume"
+            ),
+            DbError::Stalled { ticker, cursor, idle_secs } => write!(
+                f, 
+                "DbError: Download stalled: {} made no progress past \
+                cursor {} for {}s", 
+                ticker, 
+                cursor, 
+                idle_secs
+            ),
+            DbError::PairFile(e) => write!(
+                f, "DbError: Pair file: {}", e
+            ),
         }
     }
 }
@@ -119,6 +150,8 @@ impl std::fmt::Display for DbError {
 #[derive(Debug)]
 pub enum FetchError {
     Api(RequestError),
+    /// An exchange error, mapped to the common taxonomy by its adapter
+    Exchange(ExchangeError),
     SystemError(String),
 }
 
@@ -128,6 +161,9 @@ impl std::fmt::Display for FetchError {
             FetchError::Api(e) => write!(
                 f, "FetchError::Api: {} ", e
             ),
+            FetchError::Exchange(e) => write!(
+                f, "FetchError::Exchange: {} ", e
+            ),
             FetchError::SystemError(e) => write!(
                 f, "FetchError::SystemError: {} ", e
             )
@@ -141,9 +177,16 @@ impl From<RequestError> for FetchError {
     }
 }
 
+impl From<ExchangeError> for FetchError {
+    fn from(e: ExchangeError) -> Self {
+        FetchError::Exchange(e)
+    }
+}
+
 
 // ----------------------------- STATUS ENUMS ------------------------------ //
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
 pub enum DataDownloadStatus {
     Started {
         exchange: String,
@@ -153,6 +196,8 @@ pub enum DataDownloadStatus {
         exchange: String,
         ticker: String,
         percent: u8,
+        /// Ticks written since the download started
+        ticks: u64,
     },
     Finished {
         exchange: String,
@@ -162,6 +207,26 @@ pub enum DataDownloadStatus {
         exchange: String,
         ticker: String,
     },
+    /// Stopped on request after committing its last batch
+    Cancelled {
+        exchange: String,
+        ticker: String,
+    },
+    /// Aborted by the watchdog, see `DownloadControl`
+    Stalled {
+        exchange: String,
+        ticker: String,
+        cursor: String,
+        idle_secs: u64,
+        retrying: bool,
+    },
+    /// Waiting out a retryable `ExchangeError` before trying again
+    Retrying {
+        exchange: String,
+        ticker: String,
+        reason: String,
+        delay_secs: u64,
+    },
 }
 
 impl DataDownloadStatus {
@@ -170,7 +235,10 @@ impl DataDownloadStatus {
             DataDownloadStatus::Started { exchange, ticker }
             | DataDownloadStatus::Progress { exchange, ticker, .. }
             | DataDownloadStatus::Finished { exchange, ticker }
-            | DataDownloadStatus::Error { exchange, ticker, .. } => {
+            | DataDownloadStatus::Error { exchange, ticker, .. }
+            | DataDownloadStatus::Cancelled { exchange, ticker }
+            | DataDownloadStatus::Stalled { exchange, ticker, .. }
+            | DataDownloadStatus::Retrying { exchange, ticker, .. } => {
                 (exchange.as_str(), ticker.as_str())
             }
         }
@@ -182,6 +250,39 @@ impl DataDownloadStatus {
 
 
 // ----------------------------- STRUCTS ----------------------------------- //
+/// Stop and stall handling shared by every task of a download run
+///
+/// A task whose pagination cursor hasn't advanced for `stall_timeout` (a 
+/// request that never returns, or the exchange handing back the same page)
+/// is aborted with `DbError::Stalled` and restarted from its checkpoint, up 
+/// to `stall_retries` times. Retryable `ExchangeError`s draw from the same
+/// budget, waiting `ExchangeError::retry_delay` before each restart.
+///
+/// Every batch the run writes is recorded under `job_id`, see 
+/// `lineage::IngestBatch`.
+#[derive(Debug, Clone)]
+pub struct DownloadControl {
+    pub cancel: CancellationToken,
+    pub stall_timeout: Duration,
+    pub stall_retries: u32,
+    pub job_id: i64,
+}
+
+impl DownloadControl {
+    pub fn new(
+        cancel: CancellationToken, 
+        stall_timeout: Duration, 
+        stall_retries: u32
+    ) -> Self {
+        DownloadControl { 
+            cancel, 
+            stall_timeout, 
+            stall_retries, 
+            job_id: new_job_id() 
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Db {
     pub pool: PgPool,
@@ -231,6 +332,12 @@ pub struct DbLogin {
     pub port: u16
 }
 
+impl Default for DbLogin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl DbLogin {
     
     pub fn new() -> DbLogin {
@@ -251,7 +358,7 @@ impl DbLogin {
         let mut valid = true;
         let vals: [&str; 3] = [&self.user, &self.host, &self.password];
         for value in vals {
-            if value == "" { valid = false }
+            if value.is_empty() { valid = false }
         };
         valid 
     }
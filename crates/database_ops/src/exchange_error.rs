@@ -0,0 +1,91 @@
+use std::time::Duration;
+
+
+/// Longest wait between retries of a failed request
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(120);
+
+
+/// Exchange-agnostic reasons a download request failed
+///
+/// Each exchange adapter maps its own errors into these, so retry policy and
+/// the messages shown to users don't depend on which exchange was asked. The
+/// strings hold the exchange's own error text.
+#[derive(Debug, Clone)]
+pub enum ExchangeError {
+    RateLimited(String),
+    PairUnknown(String),
+    Maintenance(String),
+    Auth(String),
+    Parse(String),
+    Network(String),
+}
+
+impl ExchangeError {
+
+    /// Whether the same request can succeed if it's sent again later
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ExchangeError::RateLimited(_)
+            | ExchangeError::Maintenance(_)
+            | ExchangeError::Network(_)
+        )
+    }
+
+    /// How long to wait before retry number `attempt`, counting from zero
+    ///
+    /// Doubles with each attempt. Maintenance windows start long, since
+    /// they rarely clear in seconds.
+    pub fn retry_delay(&self, attempt: u32) -> Duration {
+
+        let base: Duration = match self {
+            ExchangeError::RateLimited(_) => Duration::from_secs(5),
+            ExchangeError::Maintenance(_) => Duration::from_secs(30),
+            _ => Duration::from_secs(2)
+        };
+
+        base.saturating_mul(2u32.saturating_pow(attempt))
+            .min(MAX_RETRY_DELAY)
+    }
+
+    /// A short explanation for users, without the exchange's error text
+    pub fn user_message(&self) -> &'static str {
+        match self {
+            ExchangeError::RateLimited(_) => "Rate limited by the exchange",
+            ExchangeError::PairUnknown(_) => {
+                "The exchange doesn't list this pair"
+            },
+            ExchangeError::Maintenance(_) => {
+                "The exchange is down for maintenance"
+            },
+            ExchangeError::Auth(_) => "The exchange rejected the API key",
+            ExchangeError::Parse(_) => "Couldn't read the exchange's response",
+            ExchangeError::Network(_) => "Couldn't reach the exchange",
+        }
+    }
+}
+
+impl std::fmt::Display for ExchangeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ExchangeError::RateLimited(e) => write!(
+                f, "ExchangeError::RateLimited: {}", e
+            ),
+            ExchangeError::PairUnknown(e) => write!(
+                f, "ExchangeError::PairUnknown: {}", e
+            ),
+            ExchangeError::Maintenance(e) => write!(
+                f, "ExchangeError::Maintenance: {}", e
+            ),
+            ExchangeError::Auth(e) => write!(
+                f, "ExchangeError::Auth: {}", e
+            ),
+            ExchangeError::Parse(e) => write!(
+                f, "ExchangeError::Parse: {}", e
+            ),
+            ExchangeError::Network(e) => write!(
+                f, "ExchangeError::Network: {}", e
+            ),
+        }
+    }
+}
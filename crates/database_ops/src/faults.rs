@@ -0,0 +1,162 @@
+use std::{
+    env,
+    fmt,
+    sync::{
+        LazyLock,
+        atomic::{AtomicU64, Ordering}
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use tokio::time::sleep;
+
+use crate::connection::RequestError;
+
+
+/// Longest delay added to a request when `FAULT_LATENCY_MS` isn't set
+const DEFAULT_MAX_LATENCY: Duration = Duration::from_secs(3);
+
+/// How long an injected timeout hangs before failing, as long as a real
+/// connect timeout
+const TIMEOUT_DELAY: Duration = Duration::from_secs(10);
+
+
+/// Faults injected into exchange requests, so the retry, backoff and
+/// cancellation paths can be exercised without waiting for the exchange to
+/// misbehave
+///
+/// Read once from the environment, or the `.env` file, and only in debug
+/// builds. Each chance is between 0 and 1 and defaults to 0:
+///
+/// - `FAULT_LATENCY`: a request is held back by up to `FAULT_LATENCY_MS`
+///   milliseconds (default 3000)
+/// - `FAULT_TIMEOUT`: a request hangs, then times out without being sent
+/// - `FAULT_MALFORMED`: a reply is cut in half, so it fails to parse
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FaultConfig {
+    pub latency: f64,
+    pub max_latency: Duration,
+    pub timeout: f64,
+    pub malformed: f64,
+}
+
+impl FaultConfig {
+
+    pub fn from_env() -> Self {
+
+        let chance = |name: &str| -> f64 {
+            env::var(name)
+                .ok()
+                .and_then(|v| v.trim().parse::<f64>().ok())
+                .filter(|p| p.is_finite())
+                .map(|p| p.clamp(0.0, 1.0))
+                .unwrap_or(0.0)
+        };
+
+        FaultConfig {
+            latency: chance("FAULT_LATENCY"),
+            max_latency: env::var("FAULT_LATENCY_MS")
+                .ok()
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .map(Duration::from_millis)
+                .unwrap_or(DEFAULT_MAX_LATENCY),
+            timeout: chance("FAULT_TIMEOUT"),
+            malformed: chance("FAULT_MALFORMED"),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.latency > 0.0 || self.timeout > 0.0 || self.malformed > 0.0
+    }
+}
+
+impl fmt::Display for FaultConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "latency {:.0}% (up to {}ms), timeouts {:.0}%, malformed \
+            replies {:.0}%",
+            self.latency * 100.0,
+            self.max_latency.as_millis(),
+            self.timeout * 100.0,
+            self.malformed * 100.0
+        )
+    }
+}
+
+static FAULTS: LazyLock<FaultConfig> = LazyLock::new(|| {
+    match cfg!(debug_assertions) {
+        true => FaultConfig::from_env(),
+        false => FaultConfig::default()
+    }
+});
+
+/// The faults being injected, if any
+pub fn active_faults() -> Option<FaultConfig> {
+    match FAULTS.is_active() {
+        true => Some(*FAULTS),
+        false => None
+    }
+}
+
+
+/// Holds a request back, or fails it as a timeout, by chance
+///
+/// Call it after the rate limiter, right before sending.
+pub(crate) async fn before_request() -> Result<(), RequestError> {
+
+    let faults: &FaultConfig = &FAULTS;
+    if !faults.is_active() {
+        return Ok(())
+    };
+
+    if chance(faults.latency) {
+        sleep(faults.max_latency.mul_f64(random())).await;
+    };
+
+    if chance(faults.timeout) {
+        sleep(TIMEOUT_DELAY).await;
+        return Err(RequestError::TimedOut(format!(
+            "Injected timeout after {}s",
+            TIMEOUT_DELAY.as_secs()
+        )))
+    };
+
+    Ok(())
+}
+
+/// Cuts a reply in half by chance, before it's parsed
+pub(crate) fn mangle_reply(raw_text: String) -> String {
+    match chance(FAULTS.malformed) {
+        true => raw_text.chars().take(raw_text.len() / 2).collect(),
+        false => raw_text
+    }
+}
+
+
+fn chance(p: f64) -> bool {
+    p > 0.0 && random() < p
+}
+
+/// A number in [0, 1) from a SplitMix64 sequence seeded by the clock, which
+/// is plenty for deciding faults
+fn random() -> f64 {
+
+    static STATE: LazyLock<AtomicU64> = LazyLock::new(|| {
+        AtomicU64::new(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or_default()
+        )
+    });
+
+    let mut z: u64 = STATE
+        .fetch_add(0x9E37_79B9_7F4A_7C15, Ordering::Relaxed)
+        .wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
@@ -0,0 +1,372 @@
+use std::{
+    fmt,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::Value;
+use sqlx::PgPool;
+
+use crate::DbError;
+
+
+/// Side table recording how each pair's download ended, one row per pair
+/// per job
+///
+/// `job_id` is the run's `DownloadControl::job_id`, shared with the batches
+/// it wrote to `_ingest_batches`. Only the latest row of each pair is read,
+/// see `failed_pairs`.
+pub(crate) const CREATE_JOB_HISTORY_TABLE: &str = r#"
+    CREATE TABLE IF NOT EXISTS _job_history (
+        id BIGSERIAL PRIMARY KEY,
+        job_id BIGINT NOT NULL,
+        exchange VARCHAR(16) NOT NULL,
+        asset VARCHAR(12) NOT NULL,
+        outcome VARCHAR(10) NOT NULL,
+        error TEXT,
+        finished_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    );
+"#;
+
+
+/// Side table keeping the reports built on a schedule, e.g. the daily
+/// summary, with the job ID they were built under
+pub(crate) const CREATE_REPORT_HISTORY_TABLE: &str = r#"
+    CREATE TABLE IF NOT EXISTS _report_history (
+        id BIGSERIAL PRIMARY KEY,
+        job_id BIGINT NOT NULL,
+        kind VARCHAR(16) NOT NULL,
+        report JSONB NOT NULL,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    );
+"#;
+
+
+/// How a pair's download ended
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobOutcome {
+    Finished,
+    Error,
+    Cancelled,
+}
+
+impl JobOutcome {
+
+    pub fn from_result(result: &Result<(), DbError>) -> Self {
+        match result {
+            Ok(()) => JobOutcome::Finished,
+            Err(DbError::Cancelled) => JobOutcome::Cancelled,
+            Err(_) => JobOutcome::Error,
+        }
+    }
+
+    /// Reads back `as_str`, taking anything unknown for an error
+    fn from_name(name: &str) -> Self {
+        match name {
+            "finished" => JobOutcome::Finished,
+            "cancelled" => JobOutcome::Cancelled,
+            _ => JobOutcome::Error
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobOutcome::Finished => "finished",
+            JobOutcome::Error => "error",
+            JobOutcome::Cancelled => "cancelled",
+        }
+    }
+}
+
+
+/// Records how the download of `ticker` in job `job_id` ended, along with
+/// its error if it failed
+pub async fn record_job(
+    db_pool: PgPool,
+    job_id: i64,
+    exchange: &str,
+    ticker: &str,
+    result: &Result<(), DbError>
+) -> Result<(), DbError> {
+
+    let query: &'static str = r#"
+        INSERT INTO _job_history (job_id, exchange, asset, outcome, error)
+        VALUES ($1, $2, $3, $4, $5);
+    "#;
+
+    sqlx::query(query)
+        .bind(job_id)
+        .bind(exchange.to_lowercase())
+        .bind(ticker.to_uppercase())
+        .bind(JobOutcome::from_result(result).as_str())
+        .bind(result.as_ref().err().map(|e| e.to_string()))
+        .execute(&db_pool)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Failed to record job history: {}", e)
+        ))?;
+
+    Ok(())
+}
+
+/// Every pair whose latest download ended in an error, as
+/// `(exchange, ticker)`
+pub async fn failed_pairs(
+    db_pool: PgPool
+) -> Result<Vec<(String, String)>, DbError> {
+
+    let query: &'static str = r#"
+        SELECT exchange, asset FROM (
+            SELECT DISTINCT ON (exchange, asset) exchange, asset, outcome
+            FROM _job_history
+            ORDER BY exchange, asset, id DESC
+        ) AS latest
+        WHERE outcome = 'error'
+        ORDER BY exchange, asset;
+    "#;
+
+    sqlx::query_as::<_, (String, String)>(query)
+        .fetch_all(&db_pool)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Couldn't fetch failed downloads: {}", e)
+        ))
+}
+
+
+/// One pair's part in an update run
+#[derive(Debug, Clone)]
+pub struct PairSummary {
+    pub exchange: String,
+    pub ticker: String,
+    /// Ticks inserted by the run, from its batches in `_ingest_batches`
+    pub ticks: u64,
+    pub outcome: JobOutcome,
+    pub error: Option<String>,
+}
+
+/// What an update run did, built from the job history once it's over
+#[derive(Debug, Clone)]
+pub struct UpdateSummary {
+    pub job_id: i64,
+    /// Since the job started, see `lineage::new_job_id`
+    pub duration: Duration,
+    pub pairs: Vec<PairSummary>,
+}
+
+impl UpdateSummary {
+
+    pub fn count(&self, outcome: JobOutcome) -> usize {
+        self.pairs.iter().filter(|p| p.outcome == outcome).count()
+    }
+
+    pub fn total_ticks(&self) -> u64 {
+        self.pairs.iter().map(|p| p.ticks).sum()
+    }
+
+    /// e.g. "4 updated, 1 failed, 0 cancelled, 120000 ticks in 3m 12s"
+    pub fn headline(&self) -> String {
+        let secs: u64 = self.duration.as_secs();
+        format!(
+            "{} updated, {} failed, {} cancelled, {} ticks in {}m {:02}s",
+            self.count(JobOutcome::Finished),
+            self.count(JobOutcome::Error),
+            self.count(JobOutcome::Cancelled),
+            self.total_ticks(),
+            secs / 60,
+            secs % 60
+        )
+    }
+}
+
+impl fmt::Display for UpdateSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+
+        writeln!(f, "\x1b[1;36mUpdate Summary (job {}):\x1b[0m", self.job_id)?;
+        writeln!(f, "  {}", self.headline())?;
+
+        for pair in &self.pairs {
+            let color: &str = match pair.outcome {
+                JobOutcome::Finished => "\x1b[32m",
+                JobOutcome::Error => "\x1b[31m",
+                JobOutcome::Cancelled => "\x1b[35m",
+            };
+            write!(
+                f, 
+                "  \x1b[33m{} {}\x1b[0m: {}{}\x1b[0m, {} ticks", 
+                pair.exchange, 
+                pair.ticker, 
+                color, 
+                pair.outcome.as_str(), 
+                pair.ticks
+            )?;
+            if let Some(e) = &pair.error {
+                write!(f, " \x1b[31m({})\x1b[0m", e)?;
+            };
+            writeln!(f)?;
+        };
+        Ok(())
+    }
+}
+
+/// Sums up job `job_id` from `_job_history` and `_ingest_batches`, one row
+/// per pair it downloaded
+pub async fn job_summary(
+    job_id: i64,
+    db_pool: PgPool
+) -> Result<UpdateSummary, DbError> {
+
+    let query: &'static str = r#"
+        SELECT 
+            h.exchange, 
+            h.asset, 
+            h.outcome, 
+            h.error,
+            COALESCE(SUM(b.tick_count), 0)::BIGINT
+        FROM _job_history h
+        LEFT JOIN _ingest_batches b
+            ON b.job_id = h.job_id 
+            AND b.exchange = h.exchange 
+            AND UPPER(b.asset) = h.asset
+        WHERE h.job_id = $1
+        GROUP BY h.id, h.exchange, h.asset, h.outcome, h.error
+        ORDER BY h.exchange, h.asset;
+    "#;
+
+    let rows: Vec<SummaryRow> = sqlx::query_as(query)
+        .bind(job_id)
+        .fetch_all(&db_pool)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Couldn't summarise job {}: {}", job_id, e)
+        ))?;
+
+    let started: Duration = Duration::from_micros(job_id.max(0) as u64);
+    let duration: Duration = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.saturating_sub(started))
+        .unwrap_or_default();
+
+    let pairs: Vec<PairSummary> = rows
+        .into_iter()
+        .map(|(exchange, ticker, outcome, error, ticks)| PairSummary {
+            exchange,
+            ticker,
+            ticks: ticks.max(0) as u64,
+            outcome: JobOutcome::from_name(&outcome),
+            error,
+        })
+        .collect();
+
+    Ok(UpdateSummary { job_id, duration, pairs })
+}
+
+
+type SummaryRow = (String, String, String, Option<String>, i64);
+
+
+/// One row of the job history
+#[derive(Debug, Clone)]
+pub struct JobRecord {
+    pub job_id: i64,
+    pub exchange: String,
+    pub ticker: String,
+    pub outcome: JobOutcome,
+    pub error: Option<String>,
+    /// `YYYY-MM-DD HH:MM:SS`, in UTC
+    pub finished_at: String,
+}
+
+/// The latest `limit` rows of the job history, newest first
+pub async fn recent_jobs(
+    limit: usize,
+    db_pool: PgPool
+) -> Result<Vec<JobRecord>, DbError> {
+
+    let query: &'static str = r#"
+        SELECT
+            job_id,
+            exchange,
+            asset,
+            outcome,
+            error,
+            to_char(finished_at AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+        FROM _job_history
+        ORDER BY id DESC
+        LIMIT $1;
+    "#;
+
+    let rows = sqlx::query_as::<
+        _, (i64, String, String, String, Option<String>, String)
+    >(query)
+        .bind(limit.min(i64::MAX as usize) as i64)
+        .fetch_all(&db_pool)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Couldn't fetch the job history: {}", e)
+        ))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(job_id, exchange, ticker, outcome, error, finished_at)| {
+            JobRecord {
+                job_id,
+                exchange,
+                ticker,
+                outcome: JobOutcome::from_name(&outcome),
+                error,
+                finished_at,
+            }
+        })
+        .collect())
+}
+
+
+/// Stores `report`, a report of `kind` built by job `job_id`
+pub async fn record_report(
+    db_pool: PgPool,
+    job_id: i64,
+    kind: &str,
+    report: &Value
+) -> Result<(), DbError> {
+
+    let query: &'static str = r#"
+        INSERT INTO _report_history (job_id, kind, report)
+        VALUES ($1, $2, $3::jsonb);
+    "#;
+
+    sqlx::query(query)
+        .bind(job_id)
+        .bind(kind)
+        .bind(report.to_string())
+        .execute(&db_pool)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Failed to record the {} report: {}", kind, e)
+        ))?;
+
+    Ok(())
+}
+
+/// How long ago the last report of `kind` was stored, `None` before the
+/// first
+pub async fn last_report_age(
+    kind: &str,
+    db_pool: PgPool
+) -> Result<Option<Duration>, DbError> {
+
+    let query: &'static str = r#"
+        SELECT EXTRACT(EPOCH FROM NOW() - MAX(created_at))::DOUBLE PRECISION
+        FROM _report_history
+        WHERE kind = $1;
+    "#;
+
+    let age: Option<f64> = sqlx::query_scalar(query)
+        .bind(kind)
+        .fetch_one(&db_pool)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Couldn't fetch the last {} report: {}", kind, e)
+        ))?;
+
+    Ok(age.map(|secs| Duration::from_secs_f64(secs.max(0.0))))
+}
@@ -1,24 +1,159 @@
 use std::{
     collections::{HashMap, BTreeMap},
-    time::{SystemTime, UNIX_EPOCH},
-    cmp::{min, max}
+    time::{Instant, SystemTime, UNIX_EPOCH},
+    cmp::{min, max},
+    sync::{
+        LazyLock,
+        atomic::{AtomicU64, Ordering}
+    }
 };
 
 use reqwest;
+use tower::util::MapRequestLayer;
 use serde::Deserialize;
-use tokio::{time::{sleep, Duration}, sync::mpsc::UnboundedSender};
+use tokio::{
+    time::{sleep, Duration}, 
+    sync::mpsc::{UnboundedSender, channel}
+};
 use sqlx::{PgPool, pool::{PoolConnection}};
 
-use timestamp_tools::{get_current_unix_timestamp};
+use timestamp_tools::{db_timestamp_to_date_string, get_current_unix_timestamp};
 use connection::{
     DataDownloadStatus, 
     DbError, 
+    DownloadControl,
     FetchError, 
     RequestError, 
     get_table_name
 };
 use super::fetch_tables;
 pub use crate::connection;
+use crate::{
+    ExchangeError, 
+    checkpoint::{
+        DownloadCheckpoint, 
+        ExchangeCursor, 
+        resume_from_checkpoint, 
+        save_checkpoint
+    },
+    faults,
+    lineage::{IngestBatch, new_job_id, record_batch},
+    rate_limit::RateLimiter
+};
+
+
+/// Kraken allows roughly one public request per second
+pub const REQUESTS_PER_SEC: f64 = 1.0;
+
+/// Every tick request, from any download task, has to take a token from 
+/// this bucket first.
+static KRAKEN_RATE_LIMITER: LazyLock<RateLimiter> = LazyLock::new(|| {
+    RateLimiter::new(1, REQUESTS_PER_SEC)
+});
+
+/// Tick requests sent since the count was last saved, see 
+/// `usage::flush_request_counts`
+static KRAKEN_REQUESTS: AtomicU64 = AtomicU64::new(0);
+
+/// Takes the tick requests counted so far, resetting the count
+pub(crate) fn take_request_count() -> u64 {
+    KRAKEN_REQUESTS.swap(0, Ordering::Relaxed)
+}
+
+/// Puts back requests whose count couldn't be saved
+pub(crate) fn restore_request_count(n: u64) {
+    KRAKEN_REQUESTS.fetch_add(n, Ordering::Relaxed);
+}
+
+/// Connections opened to Kraken since the client metrics were last taken.
+/// A request that reuses a pooled connection doesn't open one.
+static KRAKEN_CONNECTIONS: AtomicU64 = AtomicU64::new(0);
+
+/// Requests timed since the client metrics were last taken, and their 
+/// summed time in milliseconds from sending to the reply's headers
+static KRAKEN_TIMED: AtomicU64 = AtomicU64::new(0);
+static KRAKEN_LATENCY_MS: AtomicU64 = AtomicU64::new(0);
+
+/// How well the pooled client does, counted over the requests to Kraken 
+/// since the metrics were last taken
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ClientMetrics {
+    pub requests: u64,
+    /// New connections, so `requests - connections` reused a pooled one
+    pub connections: u64,
+    /// Summed over `requests`
+    pub latency_ms: u64,
+}
+
+/// Takes the client metrics counted so far, resetting them
+pub fn take_client_metrics() -> ClientMetrics {
+    ClientMetrics {
+        requests: KRAKEN_TIMED.swap(0, Ordering::Relaxed),
+        connections: KRAKEN_CONNECTIONS.swap(0, Ordering::Relaxed),
+        latency_ms: KRAKEN_LATENCY_MS.swap(0, Ordering::Relaxed),
+    }
+}
+
+/// Sends a request, adding its time to the client metrics
+async fn timed_send(
+    request: reqwest::RequestBuilder
+) -> Result<reqwest::Response, reqwest::Error> {
+
+    let started: Instant = Instant::now();
+    let response = request.send().await;
+
+    KRAKEN_TIMED.fetch_add(1, Ordering::Relaxed);
+    KRAKEN_LATENCY_MS.fetch_add(
+        started.elapsed().as_millis() as u64, 
+        Ordering::Relaxed
+    );
+
+    response
+}
+
+// The pool settings below are starting points rather than tuned values. 
+// They're checked against `ClientMetrics`, which telemetry reports: with 
+// them right a backfill opens about one connection however many pages it 
+// fetches.
+
+/// Idle connections kept per host. Downloads for every pair share the rate
+/// limiter, so only a few requests are ever in flight at once.
+const POOL_IDLE_PER_HOST: usize = 4;
+
+/// How long an idle connection is kept. Pages are requested about once a
+/// second, well inside this, so it only lapses between backfills.
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30);
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+
+/// Builds the client for every request to Kraken
+///
+/// Connections are pooled and kept alive between pages, and negotiate HTTP/2
+/// when the server offers it, so a long backfill should pay for the TCP and
+/// TLS handshakes once rather than on every page. Every connection it opens
+/// is counted in `ClientMetrics`, to check that it does.
+pub fn kraken_client() -> Result<reqwest::Client, reqwest::Error> {
+    reqwest::Client::builder()
+        .connector_layer(MapRequestLayer::new(|dst| {
+            KRAKEN_CONNECTIONS.fetch_add(1, Ordering::Relaxed);
+            dst
+        }))
+        .pool_max_idle_per_host(POOL_IDLE_PER_HOST)
+        .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+        .tcp_keepalive(KEEPALIVE_INTERVAL)
+        .tcp_nodelay(true)
+        .http2_keep_alive_interval(KEEPALIVE_INTERVAL)
+        .http2_keep_alive_while_idle(true)
+        .http2_adaptive_window(true)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .build()
+}
+
+/// Pages a download may fetch ahead of the ones written to the database
+const PIPELINE_DEPTH: usize = 4;
 
 
 // Tick data structs
@@ -31,42 +166,35 @@ pub struct TickDataResponse {
 impl TickDataResponse {
     
     fn len(&self) -> Option<usize> {
-        if let Some(d) = &self.result {
-            if let Some(v) = d.trades.values().next() {
-                return Some(v.len())
-            }
+        if let Some(d) = &self.result
+            && let Some(v) = d.trades.values().next() 
+        {
+            return Some(v.len())
         };
         None
     }
 
     fn last_tick_id(&self) -> Option<u64> {
-        if let Some(data) = &self.result {
-            if let Some(vector) = data.trades.values().next() {
-                if let Some(v) = vector.last() {
-                    return Some(v.tick_id)
-                }
-            }
-        }
+        if let Some(data) = &self.result
+            && let Some(vector) = data.trades.values().next()
+            && let Some(v) = vector.last() 
+        {
+            return Some(v.tick_id)
+        };
         None
     }
     
-    fn next_fetch_timestamp(&self) -> Option<String> {
-        if let Some(d) = &self.result {
-            Some(d.last.clone())
-        }
-        else {
-            None
-        }
+    fn next_cursor(&self) -> Option<KrakenCursor> {
+        self.result.as_ref().and_then(|d| KrakenCursor::parse(&d.last))
     }
 
     fn timestamp_of_last_tick(&self) -> Option<f64> {
-        if let Some(data) = &self.result {
-            if let Some(vector) = data.trades.values().next() {
-                if let Some(v) = vector.last() {
-                    return Some(v.time.clone())
-                }
-            }
-        }
+        if let Some(data) = &self.result
+            && let Some(vector) = data.trades.values().next()
+            && let Some(v) = vector.last() 
+        {
+            return Some(v.time)
+        };
         None
     }
 
@@ -152,6 +280,32 @@ pub struct AssetPairInfo {
 }
 
 
+/// `CREATE TABLE` for a Kraken pair's ticks, with the `(precision, scale)`
+/// of its price and volume columns
+pub(crate) fn tick_table_query(
+    table_name: &str,
+    price: (u32, u32),
+    volume: (u32, u32)
+) -> String {
+    format!(r#"
+        CREATE TABLE IF NOT EXISTS {} (
+            id BIGINT PRIMARY KEY,
+            price DECIMAL({},{}) NOT NULL, 
+            volume DECIMAL({},{}) NOT NULL, 
+            time BIGINT NOT NULL, 
+            buy_sell CHAR(1) NOT NULL, 
+            market_limit CHAR(1) NOT NULL, 
+            misc VARCHAR(16)
+        );
+        "#,
+        table_name,
+        price.0,
+        price.1,
+        volume.0,
+        volume.1
+    )
+}
+
 pub async fn add_new_db_table(
     ticker: &str,
     start_date_unix_timestamp_offset: u64,
@@ -211,34 +365,22 @@ pub async fn add_new_db_table(
             }
         },
         None => {
-            request_asset_info_from_kraken(&ticker, client)
+            request_asset_info_from_kraken(ticker, client)
                 .await 
                 .map_err(|e|  
                     connection::DbError::Fetch(
-                        FetchError::Api(
-                            RequestError::Http(e)
+                        FetchError::Exchange(
+                            exchange_error(RequestError::Http(e))
                         )
                     )
                 )?
         }
     };
 
-    let create_table: String = format!(r#"
-        CREATE TABLE IF NOT EXISTS {} (
-            id BIGINT PRIMARY KEY,
-            price DECIMAL({},{}) NOT NULL, 
-            volume DECIMAL({},{}) NOT NULL, 
-            time BIGINT NOT NULL, 
-            buy_sell CHAR(1) NOT NULL, 
-            market_limit CHAR(1) NOT NULL, 
-            misc VARCHAR(16)
-        );
-        "#,
-        table_name,
-        max(24, tick_info.pair_decimals * 2),
-        tick_info.pair_decimals,
-        max(24, tick_info.lot_decimals * 2),
-        tick_info.lot_decimals
+    let create_table: String = tick_table_query(
+        &table_name,
+        (max(24, tick_info.pair_decimals * 2), tick_info.pair_decimals),
+        (max(24, tick_info.lot_decimals * 2), tick_info.lot_decimals)
     ); 
 
     let mut conn: PoolConnection<sqlx::Postgres> = db_pool
@@ -246,19 +388,20 @@ pub async fn add_new_db_table(
         .await 
         .map_err(|_| DbError::ConnectionFailed)?;
 
-    if let Err(_) = sqlx::query(&create_table).execute(&mut *conn).await {
+    if sqlx::query(&create_table).execute(&mut *conn).await.is_err() {
         return Err(DbError::TableCreationFailed(
             format!("Failed to create asset_kraken_{} table", ticker) 
         )); 
     };
 
     let initial_time_stamp_query: String = format!(r#"
-        INSERT INTO _last_tick_history (asset, next_tick_id, time) 
+        INSERT INTO _last_tick_history (asset, next_tick_id, cursor_ns) 
         VALUES ('{}', 0, 0);"#, ticker);
 
-    if let Err(_) = sqlx::query(&initial_time_stamp_query)
+    if sqlx::query(&initial_time_stamp_query)
         .execute(&mut *conn)
         .await 
+        .is_err()
     {
         return Err(
             DbError::QueryFailed(
@@ -270,33 +413,53 @@ pub async fn add_new_db_table(
         ); 
     };
 
-    sleep(Duration::from_millis(500)).await;
-   
     let initial_fetch_time = current_ts - start_date_unix_timestamp_offset;  
 
     let initial_data: TickDataResponse = request_tick_data_from_kraken(
         ticker, 
         initial_fetch_time.to_string(),
         client
-    ).await.map_err(|e| DbError::Fetch(FetchError::Api(e)))?;
+    ).await.map_err(|e| DbError::Fetch(FetchError::Exchange(
+        exchange_error(e)
+    )))?;
 
-    write_data_to_db_table(ticker, &initial_data, db_pool.clone(), None)
-        .await?;
+    write_data_to_db_table(
+        ticker, 
+        &initial_data, 
+        db_pool.clone(), 
+        None, 
+        new_job_id()
+    ).await?;
     
     Ok(())
 
 }
 
 
+/// One page of ticks on its way from the fetcher to the writer
+struct FetchedBatch {
+    data: TickDataResponse,
+    /// ID of the first tick in `data`
+    first_tick_id: u64,
+    /// Set on the short page that ends the download
+    last: bool,
+}
+
+/// Downloads every tick since the last one stored for `ticker`
+///
+/// Pages are fetched and written by two tasks joined by a bounded channel,
+/// so the next request is in flight while the last page is inserted. Pages
+/// already fetched when the download is cancelled are still written.
 pub async fn download_new_data_to_db_table(
     ticker: &str,
     db_pool: PgPool,
     initial_unix_timestamp_offset: u64,
     client: &reqwest::Client,
     progress_tx: UnboundedSender<DataDownloadStatus>,
+    control: &DownloadControl,
 ) -> Result<(), DbError> {
 
-    const EXCHANGE: &'static str = "Kraken";
+    const EXCHANGE: &str = "Kraken";
     let ex_name: String = EXCHANGE.to_string();
 
     let current_time: u64 = get_current_unix_timestamp();
@@ -324,41 +487,18 @@ pub async fn download_new_data_to_db_table(
     
     if !existing_tables.contains(&table_name) {
         add_new_db_table(
-            &ticker, 
+            ticker, 
             initial_unix_timestamp_offset, 
-            &client,
+            client,
             db_pool.clone(),
             None
         ).await?;
     };
 
-    // Get the last recorded timestamp from _last_tick_history
-    let ltq = format!(
-        r#"
-        SELECT next_tick_id, time 
-        FROM _last_tick_history
-        WHERE asset = '{}' 
-        "#,
-        ticker
-    ); 
-
-    type Vrow = Vec<(u64, String)>;
-    let valid_row: Vrow = match sqlx::query_as::<_, (i64, String)>(&ltq)
-        .fetch_all(&mut *conn)
-        .await 
-    {
-        Ok(r) => r.into_iter().map(|(i, t)| (i as u64, t)).collect(),
-        Err(_) => return Err(DbError::QueryFailed(
-            "Couldn't fetch last tick time from _last_tick_history".to_string()
-        )) 
-    };
-
-    let (mut next_tick_id, mut next_timestamp) = match valid_row.len() > 0 {
-        true => (valid_row[0].0, valid_row[0].1.clone()),
-        false => return Err(DbError::QueryFailed(
-            "Couldn't fetch last tick time from _last_tick_history".to_string()
-        ))
-    }; 
+    let checkpoint: DownloadCheckpoint<KrakenCursor> = resume_from_checkpoint(
+        ticker, 
+        db_pool.clone()
+    ).await?;
 
     let tq = format!(
         "SELECT time FROM {} ORDER BY id DESC LIMIT 1;", 
@@ -383,17 +523,16 @@ pub async fn download_new_data_to_db_table(
         )),
         _ => last_timestamp_in_db_vec[0] / 1_000_000
     };
+    drop(conn);
 
     let total_expected_seconds = current_time - last_timestamp_in_db;
-    let mut num_seconds_left: u64;
-    let mut percent_complete: u8;
 
     fn get_percent_complete(curr: u64, target: u64) -> u8 {
         100 - ((curr * 100) / target) as u8
     }
 
     fn send_failure_message(
-        progress_tx: UnboundedSender<DataDownloadStatus>,
+        progress_tx: &UnboundedSender<DataDownloadStatus>,
         sym: &str, 
     ) {
         let _ = progress_tx.send(DataDownloadStatus::Error { 
@@ -402,122 +541,371 @@ pub async fn download_new_data_to_db_table(
         });
     }
 
-    loop {
-        
-        let new_data: TickDataResponse = match request_tick_data_from_kraken(
-            ticker, 
-            next_timestamp, 
-            client
-        ).await {
-            Ok(d) => d,
-            Err(e) => {
-                return Err(DbError::Fetch(FetchError::Api(e)))
-            }
+    let cancel = &control.cancel;
+
+    // The fetcher requests pages while the writer inserts the ones before
+    // them, so network and database latency overlap. Once the writer falls
+    // PIPELINE_DEPTH pages behind, the fetcher waits for it.
+    let (batch_tx, mut batch_rx) = channel::<FetchedBatch>(PIPELINE_DEPTH);
+
+    let fetch_progress_tx = progress_tx.clone();
+    let fetcher = async move {
+
+        let mut next_tick_id: u64 = checkpoint.next_tick_id;
+        let mut cursor: KrakenCursor = checkpoint.cursor;
+
+        // Watchdog: the last time the pagination cursor moved forward
+        let mut cursor_moved_at: Instant = Instant::now();
+        let stalled = |cursor: KrakenCursor, since: Instant| {
+            Err(DbError::Stalled { 
+                ticker: ticker.to_string(), 
+                cursor: cursor.to_string(), 
+                idle_secs: since.elapsed().as_secs() 
+            })
         };
 
-        let num_ticks: usize = match new_data.len() {
-            Some(v) => v,
-            None => { 
-                let msg = "Failed to calculate length of trades".to_string();
-                send_failure_message(progress_tx.clone(), ticker);
-                return Err(DbError::Fetch(FetchError::SystemError(msg)))
+        loop {
+
+            // Checked only between pages: a page that has been fetched is 
+            // always handed to the writer, which commits it along with its
+            // cursor before stopping
+            if cancel.is_cancelled() {
+                return Err(DbError::Cancelled)
+            };
+            
+            let request = request_tick_data_from_kraken(
+                ticker, 
+                cursor.to_string(), 
+                client
+            );
+            let deadline = tokio::time::Instant::from_std(
+                cursor_moved_at + control.stall_timeout
+            );
+
+            let new_data: TickDataResponse = tokio::select! {
+                _ = cancel.cancelled() => return Err(DbError::Cancelled),
+                _ = tokio::time::sleep_until(deadline) => {
+                    return stalled(cursor, cursor_moved_at)
+                },
+                response = request => match response {
+                    Ok(d) => d,
+                    Err(e) => {
+                        return Err(DbError::Fetch(FetchError::Exchange(
+                            exchange_error(e)
+                        )))
+                    }
+                }
+            };
+
+            if !new_data.error.is_empty() {
+                return Err(
+                    DbError::Fetch(
+                        FetchError::Exchange(
+                            exchange_error(RequestError::ErrorResponse(
+                                new_data.error[0].clone() 
+                            ))
+                        )
+                    )
+                ) 
+            };
+
+            let num_ticks: usize = match new_data.len() {
+                Some(v) => v,
+                None => { 
+                    let msg = "Failed to calculate length of trades"
+                        .to_string();
+                    send_failure_message(&fetch_progress_tx, ticker);
+                    return Err(DbError::Fetch(FetchError::SystemError(msg)))
+                }
+            };
+
+            let first_tick_id: u64 = next_tick_id;
+            next_tick_id = match &new_data.last_tick_id() {
+                Some(v) => *v + 1,  // Expected first ID of next fetch
+                None => {
+                    let msg = "Failed to fetch last tick ID from \
+                        TickDataResponse".to_string(); 
+                    send_failure_message(&fetch_progress_tx, ticker); 
+                    return Err(DbError::Fetch(FetchError::SystemError(msg)))
+                }
+            };
+
+            let next_cursor: KrakenCursor = match new_data.next_cursor() {
+                Some(v) => v,
+                None => {
+                    let msg = "Failed to fetch next fetch time from \
+                        TickDataResponse".to_string();
+                    send_failure_message(&fetch_progress_tx, ticker);
+                    return Err(DbError::Fetch(FetchError::SystemError(msg)))
+                }
+            };
+            let cursor_moved: bool = next_cursor > cursor;
+            cursor = next_cursor;
+
+            let last: bool = num_ticks < 1000;
+            let batch = FetchedBatch { data: new_data, first_tick_id, last };
+
+            // Fails only once the writer has given up, and its error is the
+            // one reported
+            if batch_tx.send(batch).await.is_err() || last {
+                return Ok(())
+            };
+
+            // A full page that leaves the cursor where it was would be 
+            // fetched again forever. Time spent waiting on the writer 
+            // doesn't count against the cursor.
+            if cursor_moved {
+                cursor_moved_at = Instant::now();
             }
-        };
+            else if cursor_moved_at.elapsed() >= control.stall_timeout {
+                return stalled(cursor, cursor_moved_at)
+            };
+        }
+    };
+
+    let write_progress_tx = progress_tx.clone();
+    let writer = async move {
 
-        if new_data.error.len() == 0 {
+        let mut ticks_written: u64 = 0;
+
+        while let Some(batch) = batch_rx.recv().await {
 
             if let Err(e) = write_data_to_db_table(
                 ticker, 
-                &new_data, 
+                &batch.data, 
                 db_pool.clone(), 
-                Some(next_tick_id)
+                Some(batch.first_tick_id),
+                control.job_id
             ).await {
-                send_failure_message(progress_tx.clone(), ticker);
+                send_failure_message(&write_progress_tx, ticker);
                 return Err(e) 
             };
+            ticks_written += batch.data.len().unwrap_or(0) as u64;
+
+            let last_tick_time: u64 = match &batch.data
+                .timestamp_of_last_tick() 
+            {
+                Some(v) => *v as u64,
+                None => {
+                    let msg = "Failed to fetch last timestamp from \
+                        TickDataResponse".to_string();
+                    return Err(DbError::Fetch(FetchError::SystemError(msg)))
+                }
+            };
+            
+            let num_seconds_left: u64 = current_time 
+                - min(last_tick_time, current_time);
+            let percent_complete: u8 = get_percent_complete(
+                num_seconds_left, total_expected_seconds
+            );
 
-        }
+            let _ = write_progress_tx.send(DataDownloadStatus::Progress { 
+                exchange: ex_name.clone(), 
+                ticker: ticker.to_string(), 
+                percent: percent_complete,
+                ticks: ticks_written
+            });
 
-        else {
+            if batch.last {
 
-            return Err(
-                DbError::Fetch(
-                    FetchError::Api(
-                        RequestError::ErrorResponse(
-                            new_data.error[0].clone() 
-                        )
-                    )
-                )
-            ) 
+                let _ = write_progress_tx.send(DataDownloadStatus::Progress { 
+                    exchange: ex_name.clone(), 
+                    ticker: ticker.to_string(), 
+                    percent: 100,
+                    ticks: ticks_written
+                });
 
+                let _ = write_progress_tx.send(DataDownloadStatus::Finished { 
+                    exchange: ex_name.clone(), 
+                    ticker: ticker.to_string(), 
+                });
+            };
         };
 
-        next_tick_id = match &new_data.last_tick_id() {
-            Some(v) => *v + 1,  // Expected first ID of next fetch
-            None => {
-                let msg = "Failed to fetch last tick ID from TickDataResponse"
-                    .to_string(); 
-                send_failure_message(progress_tx.clone(), ticker); 
-                return Err(DbError::Fetch(FetchError::SystemError(msg)))
-            }
-        };
+        Ok(())
+    };
 
-        next_timestamp = match &new_data.next_fetch_timestamp() {
-            Some(v) => v.to_string(),
-            None => {
-                let msg ="Failed to fetch next fetch time from TickDataResponse"
-                    .to_string();
-                send_failure_message(progress_tx.clone(), ticker);
-                return Err(DbError::Fetch(FetchError::SystemError(msg)))
-            }
-        };
-     
-        let last_tick_time: u64 = match &new_data.timestamp_of_last_tick() {
-            Some(v) => *v as u64,
-            None => {
-                let msg = "Failed to fetch last timestamp from TickDataResponse"
-                    .to_string();
-                return Err(DbError::Fetch(FetchError::SystemError(msg)))
-            }
+    let (fetched, written) = tokio::join!(fetcher, writer);
+
+    // Pages fetched before a stop have all been written by now
+    written?;
+    match fetched {
+        Err(DbError::Cancelled) => {
+            let _ = progress_tx.send(DataDownloadStatus::Cancelled { 
+                exchange: EXCHANGE.to_string(), 
+                ticker: ticker.to_string(), 
+            });
+            Err(DbError::Cancelled)
+        },
+        result => result
+    }
+
+}
+
+
+/// Re-downloads the time windows around missing tick IDs and fills the gaps
+///
+/// Missing IDs are grouped into contiguous gaps. Each gap is re-requested 
+/// starting from the timestamp of the last stored tick before it, and only 
+/// trades whose IDs fall inside the gap are inserted. The download checkpoint
+/// is left untouched. Returns the number of ticks that were inserted.
+pub async fn repair_missing_ticks(
+    ticker: &str,
+    missing_ticks: &[u64],
+    client: &reqwest::Client,
+    db_pool: PgPool
+) -> Result<u64, DbError> {
+
+    let table_name = get_table_name("kraken", ticker);
+
+    let mut gaps: Vec<(u64, u64)> = Vec::new();
+    
+    for &tick_id in missing_ticks {
+        match gaps.last_mut() {
+            Some((_, end)) if tick_id == *end + 1 => *end = tick_id,
+            _ => gaps.push((tick_id, tick_id))
         };
-        
-        num_seconds_left = current_time - min(last_tick_time, current_time);
-        percent_complete = get_percent_complete(
-            num_seconds_left, total_expected_seconds
+    };
+
+    let mut repaired: u64 = 0;
+
+    for (gap_start, gap_end) in gaps {
+
+        let time_query = format!(
+            "SELECT time FROM {} WHERE id < $1 ORDER BY id DESC LIMIT 1;",
+            table_name
         );
 
-        let _ = progress_tx.send(DataDownloadStatus::Progress { 
-            exchange: ex_name.clone(), 
-            ticker: ticker.to_string(), 
-            percent: percent_complete 
-        });
+        let prev_time: i64 = sqlx::query_scalar(&time_query)
+            .bind(gap_start as i64)
+            .fetch_optional(&db_pool)
+            .await
+            .map_err(|e| DbError::QueryFailed(
+                format!("Couldn't fetch tick before gap: {}", e)
+            ))?
+            .unwrap_or(0);
 
-        if num_ticks < 1000 {
+        // Kraken accepts a plain unix timestamp in seconds as the cursor
+        let mut since: String = (prev_time / 1_000_000).to_string();
 
-            let _ = progress_tx.send(DataDownloadStatus::Progress { 
-                exchange: ex_name.clone(), 
-                ticker: ticker.to_string(), 
-                percent: 100 
-            });
+        loop {
 
-            let _ = progress_tx.send(DataDownloadStatus::Finished { 
-                exchange: ex_name.clone(), 
-                ticker: ticker.to_string(), 
-            });
-            
-            break
-        };
-  
-        // Wait 1 sec to prevent rate limits
-        sleep(Duration::from_secs(1)).await;
+            let response: TickDataResponse = request_tick_data_from_kraken(
+                ticker, 
+                since, 
+                client
+            )
+                .await
+                .map_err(|e| DbError::Fetch(FetchError::Exchange(
+                    exchange_error(e)
+                )))?;
+
+            let result = match &response.result {
+                Some(r) => r,
+                None => return Err(DbError::ParseError)
+            };
+
+            let trades: &Vec<Trade> = match result.trades.values().next() {
+                Some(t) => t,
+                None => return Err(DbError::ParseError)
+            };
+
+            let rows: Vec<String> = trades
+                .iter()
+                .filter(|t| t.tick_id >= gap_start && t.tick_id <= gap_end)
+                .map(|t| t.to_db_row())
+                .collect();
+
+            if !rows.is_empty() {
+                
+                let insert_query = format!(
+                    r#"INSERT INTO {} (
+                        id, 
+                        price, 
+                        volume, 
+                        time, 
+                        buy_sell, 
+                        market_limit,
+                        misc
+                    ) VALUES {}
+                    ON CONFLICT (id) DO NOTHING;"#,
+                    table_name,
+                    rows.join(",\n")
+                );
+
+                let result = sqlx::query(&insert_query)
+                    .execute(&db_pool)
+                    .await
+                    .map_err(|e| DbError::QueryFailed(
+                        format!("Failed to insert repaired ticks: {}", e)
+                    ))?;
+
+                repaired += result.rows_affected();
+            };
+
+            let passed_gap = match trades.last() {
+                Some(t) => t.tick_id >= gap_end,
+                None => true
+            };
 
+            if passed_gap || trades.len() < 1000 {
+                break
+            };
+
+            since = result.last.clone();
+
+        };
     };
 
-    Ok(())
+    Ok(repaired)
 
 }
 
 
+/// Kraken's pagination cursor
+///
+/// The `last` value of a trades page, which is the time of its last trade in
+/// nanoseconds since the epoch. Sent back as `since`, it requests the trades
+/// after that one, and it's stored as is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct KrakenCursor(u64);
+
+impl KrakenCursor {
+
+    /// Reads a `last` value, which Kraken sends as a string
+    pub fn parse(last: &str) -> Option<Self> {
+        last.trim().parse::<u64>().ok().map(KrakenCursor)
+    }
+}
+
+impl ExchangeCursor for KrakenCursor {
+
+    const EXCHANGE: &'static str = "kraken";
+
+    fn before_tick(time: u64) -> Self {
+        KrakenCursor((time * 1_000).saturating_sub(1))
+    }
+
+    fn from_stored(cursor_ns: u64) -> Self {
+        KrakenCursor(cursor_ns)
+    }
+
+    fn to_stored(&self) -> u64 {
+        self.0
+    }
+
+    fn to_date_string(&self) -> String {
+        db_timestamp_to_date_string(self.0 / 1_000)
+    }
+}
+
+impl std::fmt::Display for KrakenCursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+
 pub async fn request_tick_data_from_kraken(
     ticker: &str, 
     since_unix_timestamp: String, 
@@ -529,14 +917,18 @@ pub async fn request_tick_data_from_kraken(
         ticker,
         since_unix_timestamp
     );
+
+    KRAKEN_RATE_LIMITER.acquire().await;
+    KRAKEN_REQUESTS.fetch_add(1, Ordering::Relaxed);
+    faults::before_request().await?;
   
-    let response = client.get(&url).send().await?;
+    let response = timed_send(client.get(&url)).await?;
 
     if !response.status().is_success() {
         return Err(RequestError::BadStatus(response.status()));
     }
 
-    let raw_text = response.text().await?;
+    let raw_text = faults::mangle_reply(response.text().await?);
 
     let kraken_resp: TickDataResponse = serde_json::from_str(&raw_text)
         .map_err(|e| {
@@ -544,7 +936,7 @@ pub async fn request_tick_data_from_kraken(
             RequestError::Deserialize(e) 
         })?;
 
-    if kraken_resp.error.len() > 0 {
+    if !kraken_resp.error.is_empty() {
         return Err(RequestError::RequestFailed(
             format!("Request failed: {:?}", kraken_resp.error)
         ))
@@ -555,6 +947,222 @@ pub async fn request_tick_data_from_kraken(
 }
 
 
+/// Maps an error from a Kraken request into the common `ExchangeError`
+/// taxonomy
+pub fn exchange_error(e: RequestError) -> ExchangeError {
+    match e {
+        RequestError::Http(e) => match e.status() {
+            Some(status) => status_error(status, e.to_string()),
+            None if e.is_decode() => ExchangeError::Parse(e.to_string()),
+            None => ExchangeError::Network(e.to_string())
+        },
+        RequestError::BadStatus(status) => {
+            status_error(status, status.to_string())
+        },
+        RequestError::Deserialize(e) => ExchangeError::Parse(e.to_string()),
+        RequestError::RequestFailed(msg)
+        | RequestError::ErrorResponse(msg) => api_error(msg),
+        RequestError::NoData => ExchangeError::Parse(
+            "Request returned no data".to_string()
+        ),
+        RequestError::TimedOut(msg) => ExchangeError::Network(msg),
+    }
+}
+
+fn status_error(status: reqwest::StatusCode, msg: String) -> ExchangeError {
+    match status.as_u16() {
+        429 => ExchangeError::RateLimited(msg),
+        401 | 403 => ExchangeError::Auth(msg),
+        503 => ExchangeError::Maintenance(msg),
+        _ => ExchangeError::Network(msg)
+    }
+}
+
+/// Sorts Kraken's `error` strings, e.g. `EAPI:Rate limit exceeded`, by
+/// their documented codes
+fn api_error(msg: String) -> ExchangeError {
+
+    const RATE_LIMITED: [&str; 2] = [
+        "EAPI:Rate limit exceeded", 
+        "EGeneral:Too many requests"
+    ];
+    const PAIR_UNKNOWN: [&str; 2] = [
+        "EQuery:Unknown asset pair", 
+        "EQuery:Unknown asset"
+    ];
+    const MAINTENANCE: [&str; 3] = [
+        "EService:Unavailable", 
+        "EService:Busy", 
+        "EService:Market in"
+    ];
+    const AUTH: [&str; 4] = [
+        "EAPI:Invalid key", 
+        "EAPI:Invalid signature", 
+        "EAPI:Invalid nonce", 
+        "EGeneral:Permission denied"
+    ];
+    const NETWORK: [&str; 2] = [
+        "EService:Deadline elapsed", 
+        "EGeneral:Internal error"
+    ];
+
+    let has = |codes: &[&str]| codes.iter().any(|c| msg.contains(c));
+
+    if has(&RATE_LIMITED) {
+        ExchangeError::RateLimited(msg)
+    }
+    else if has(&PAIR_UNKNOWN) {
+        ExchangeError::PairUnknown(msg)
+    }
+    else if has(&MAINTENANCE) {
+        ExchangeError::Maintenance(msg)
+    }
+    else if has(&AUTH) {
+        ExchangeError::Auth(msg)
+    }
+    else if has(&NETWORK) {
+        ExchangeError::Network(msg)
+    }
+    else {
+        ExchangeError::Parse(msg)
+    }
+}
+
+
+/// Last trade, best bid and best ask of a pair
+#[derive(Debug, Clone, Copy)]
+pub struct TickerQuote {
+    pub last: f64,
+    pub bid: f64,
+    pub ask: f64,
+}
+
+impl TickerQuote {
+    pub fn spread(&self) -> f64 {
+        self.ask - self.bid
+    }
+}
+
+/// Prices come first in each of Kraken's arrays, followed by volumes
+#[derive(Debug, Deserialize)]
+struct TickerInfo {
+    a: Vec<String>,
+    b: Vec<String>,
+    c: Vec<String>,
+}
+
+/// Sends a request to Kraken's public API, taking a token from the rate
+/// limiter first, and returns the `result` of the reply
+async fn request_public_kraken<T: for<'de> Deserialize<'de>>(
+    url: &str,
+    client: &reqwest::Client
+) -> Result<T, RequestError> {
+
+    #[derive(Deserialize)]
+    struct Reply<T> {
+        error: Vec<String>,
+        result: Option<T>,
+    }
+
+    KRAKEN_RATE_LIMITER.acquire().await;
+    KRAKEN_REQUESTS.fetch_add(1, Ordering::Relaxed);
+    faults::before_request().await?;
+
+    let response = timed_send(client.get(url)).await?;
+
+    if !response.status().is_success() {
+        return Err(RequestError::BadStatus(response.status()));
+    }
+
+    let raw_text = faults::mangle_reply(response.text().await?);
+    let reply: Reply<T> = serde_json::from_str(&raw_text)
+        .map_err(RequestError::Deserialize)?;
+
+    match (reply.error.is_empty(), reply.result) {
+        (true, Some(result)) => Ok(result),
+        (_, _) => Err(RequestError::RequestFailed(
+            format!("Request failed: {:?}", reply.error)
+        ))
+    }
+}
+
+/// Fetches the latest quote for `ticker`
+pub async fn request_ticker_from_kraken(
+    ticker: &str,
+    client: &reqwest::Client
+) -> Result<TickerQuote, RequestError> {
+
+    let url = format!(
+        "https://api.kraken.com/0/public/Ticker?pair={}", 
+        ticker
+    );
+
+    let result: HashMap<String, TickerInfo> = request_public_kraken(
+        &url, 
+        client
+    ).await?;
+
+    let info: TickerInfo = result
+        .into_values()
+        .next()
+        .ok_or(RequestError::RequestFailed(
+            format!("No quote for {}", ticker)
+        ))?;
+
+    let price = |field: &[String]| -> Result<f64, RequestError> {
+        field
+            .first()
+            .and_then(|v| v.parse::<f64>().ok())
+            .ok_or(RequestError::RequestFailed(
+                format!("Malformed quote for {}", ticker)
+            ))
+    };
+
+    Ok(TickerQuote { 
+        last: price(&info.c)?, 
+        bid: price(&info.b)?, 
+        ask: price(&info.a)? 
+    })
+}
+
+/// Fetches the price of `ticker` 24 hours ago, the open of the first hourly
+/// candle in the last day
+pub async fn request_price_24h_ago_from_kraken(
+    ticker: &str,
+    client: &reqwest::Client
+) -> Result<f64, RequestError> {
+
+    const DAY_SECS: u64 = 24 * 60 * 60;
+
+    let url = format!(
+        "https://api.kraken.com/0/public/OHLC?pair={}&interval=60&since={}", 
+        ticker,
+        get_current_unix_timestamp().saturating_sub(DAY_SECS)
+    );
+
+    // Candles come keyed by the pair, next to a "last" cursor
+    let result: HashMap<String, serde_json::Value> = request_public_kraken(
+        &url, 
+        client
+    ).await?;
+
+    result
+        .into_iter()
+        .filter(|(key, _)| key != "last")
+        .find_map(|(_, candles)| {
+            candles
+                .get(0)?
+                .get(1)?
+                .as_str()?
+                .parse::<f64>()
+                .ok()
+        })
+        .ok_or(RequestError::RequestFailed(
+            format!("No candles for {} in the last day", ticker)
+        ))
+}
+
+
 pub async fn request_all_assets_from_kraken(
     client: &reqwest::Client,
 ) -> Result<BTreeMap<String, AssetPairInfo>, reqwest::Error> {
@@ -604,11 +1212,17 @@ pub async fn request_asset_info_from_kraken(
 }
 
 
+/// Inserts a page of ticks and moves the pair's checkpoint past it, in one
+/// transaction
+///
+/// Ticks before `next_tick_id` are skipped. The batch is recorded in 
+/// `_ingest_batches` under `job_id`, along with the checkpoint it replaced.
 pub async fn write_data_to_db_table(
     ticker: &str,
     tick_data: &TickDataResponse, 
     db_pool: PgPool,
-    next_tick_id: Option<u64>
+    next_tick_id: Option<u64>,
+    job_id: i64
 ) -> Result<(), DbError> {
 
     // Insert tick data first
@@ -641,67 +1255,139 @@ pub async fn write_data_to_db_table(
         Err(_) => return Err(DbError::ParseError)
     };
 
-    if tick_data.len() == 0 {
+    if tick_data.is_empty() {
         return Err(DbError::Fetch(FetchError::Api(RequestError::NoData)))
     };
  
-    let max_index = tick_data.len() - 1;
-    for (index, trade) in tick_data.iter().enumerate() {
-       
-        if let Some(next_id) = next_tick_id {
-            if trade.tick_id < next_id {
-                continue 
-            };
-        };
+    let new_trades: Vec<&Trade> = tick_data
+        .iter()
+        .filter(|trade| match next_tick_id {
+            Some(next_id) => trade.tick_id >= next_id,
+            None => true
+        })
+        .collect();
+
+    let rows: Vec<String> = new_trades
+        .iter()
+        .map(|trade| trade.to_db_row())
+        .collect();
+
+    let cursor: KrakenCursor = KrakenCursor::parse(&trade_fetch_response.last)
+        .ok_or(DbError::ParseError)?;
+    let last_tick_id = match tick_data.iter().last() {
+        Some(t) => t.tick_id + 1,
+        None => return Err(DbError::ParseError) 
+    };
 
-        data_insert_query.push_str(&trade.to_db_row());
+    // The tick insert and the checkpoint update are committed together, so
+    // an interrupted download always resumes from the last full batch.
+    let mut tx = db_pool
+        .begin()
+        .await
+        .map_err(|_| DbError::ConnectionFailed)?;
+
+    if let (Some(first), Some(last)) = (new_trades.first(), new_trades.last()) {
+
+        // Locked until commit, so the checkpoint recorded as replaced is the
+        // one this batch moves on from
+        let prev_query: &'static str = r#"
+            SELECT next_tick_id, cursor_ns 
+            FROM _last_tick_history
+            WHERE asset = $1
+            FOR UPDATE;
+        "#;
+
+        let (prev_next_tick_id, prev_cursor_ns): (i64, i64) = sqlx::query_as(
+            prev_query
+        )
+            .bind(ticker)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| DbError::QueryFailed(
+                format!("Couldn't lock checkpoint for {}: {}", ticker, e)
+            ))?;
         
-        if index < max_index {
-            data_insert_query.push_str(",\n");
+        data_insert_query.push_str(&rows.join(",\n"));
+        data_insert_query.push_str("\nON CONFLICT (id) DO NOTHING;");
+
+        let inserted: u64 = match sqlx::query(&data_insert_query)
+            .execute(&mut *tx)
+            .await 
+        {
+            Ok(r) => r.rows_affected(),
+            Err(e) => return Err(DbError::QueryFailed(
+                format!(
+                    "Failed to insert tick data into database: {}: {}", 
+                    e,
+                    &data_insert_query
+                )
+            ))
         };
-    };
-    
-    data_insert_query.push_str(";");
 
-    if let Err(e) = sqlx::query(&data_insert_query)
-        .execute(&db_pool)
-        .await 
-    {
-        return Err(DbError::QueryFailed(
-            format!(
-                "Failed to insert tick data into database: {}: {}", 
-                e,
-                &data_insert_query
-            )
-        )); 
-    };
+        let batch = IngestBatch {
+            job_id,
+            exchange: "kraken".to_string(),
+            asset: ticker.to_string(),
+            first_tick_id: first.tick_id,
+            last_tick_id: last.tick_id,
+            tick_count: inserted,
+            prev_next_tick_id: prev_next_tick_id as u64,
+            prev_cursor_ns: prev_cursor_ns as u64,
+        };
 
-    let last_tick_timestamp = trade_fetch_response.last.clone();
-    let last_tick_id = match tick_data.iter().last() {
-        Some(t) => t.tick_id + 1,
-        None => return Err(DbError::ParseError) 
+        record_batch(&mut tx, &batch).await?;
     };
 
-    let last_tick_query: String = String::from(r#"
-        UPDATE _last_tick_history
-        SET next_tick_id = $1, time = $2
-        WHERE asset = $3;
-        "#
-    );
+    let checkpoint = DownloadCheckpoint { next_tick_id: last_tick_id, cursor };
+    save_checkpoint(&mut tx, ticker, &checkpoint).await?;
 
-    if let Err(_) = sqlx::query(&last_tick_query)
-        .bind(last_tick_id as i64)
-        .bind(last_tick_timestamp)
-        .bind(ticker)
-        .execute(&db_pool) 
-        .await 
-    {
+    if tx.commit().await.is_err() {
         return Err(DbError::QueryFailed(
-            "Failed to fetch update _last_tick_history".to_string()
-        )); 
+            format!("Failed to commit tick batch for {}", ticker)
+        ));
     };
 
     Ok(())
 
 }
 
+
+// -------------------------- UNIT TESTING --------------------------------- //
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn kraken_cursor_parse_test() {
+
+        let cursor: KrakenCursor = KrakenCursor::parse(" 1700000000123456789 ")
+            .unwrap();
+        assert_eq!(cursor.to_stored(), 1_700_000_000_123_456_789);
+        assert_eq!(cursor.to_string(), "1700000000123456789");
+
+        assert!(KrakenCursor::parse("").is_none());
+        assert!(KrakenCursor::parse("1700000000.5").is_none());
+        assert!(KrakenCursor::parse("-1").is_none());
+    }
+
+    #[test]
+    fn kraken_cursor_round_trip_test() {
+
+        let cursor: KrakenCursor = KrakenCursor::from_stored(42);
+        assert_eq!(KrakenCursor::from_stored(cursor.to_stored()), cursor);
+        assert_eq!(KrakenCursor::default().to_stored(), 0);
+    }
+
+    #[test]
+    fn kraken_cursor_before_tick_test() {
+
+        // Ticks are stored in microseconds, the cursor is in nanoseconds
+        let time: u64 = 1_700_000_000_000_000;
+        let cursor: KrakenCursor = KrakenCursor::before_tick(time);
+
+        assert_eq!(cursor.to_stored(), time * 1_000 - 1);
+        assert!(cursor < KrakenCursor::from_stored(time * 1_000));
+        assert_eq!(KrakenCursor::before_tick(0).to_stored(), 0);
+    }
+}
@@ -0,0 +1,538 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::{
+        LazyLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use hmac::{Hmac, Mac};
+use reqwest::header::CONTENT_TYPE;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512};
+
+use timestamp_tools::db_timestamp_to_date_string;
+use crate::{connection::RequestError, rate_limit::RateLimiter};
+
+
+const API_URL: &str = "https://api.kraken.com";
+
+/// Kraken counts private calls per key, allowing 15 at once for a starter
+/// account and taking one off the count about every three seconds
+const PRIVATE_CALL_LIMIT: u32 = 15;
+const PRIVATE_CALLS_PER_SEC: f64 = 0.33;
+
+/// Every private request takes a token from this bucket first. It's kept
+/// apart from the public one, which Kraken limits separately.
+static PRIVATE_RATE_LIMITER: LazyLock<RateLimiter> = LazyLock::new(|| {
+    RateLimiter::new(PRIVATE_CALL_LIMIT, PRIVATE_CALLS_PER_SEC)
+});
+
+/// The last nonce sent. Kraken rejects any nonce no higher than the one
+/// before it on the same key.
+static LAST_NONCE: AtomicU64 = AtomicU64::new(0);
+
+
+/// The key and secret of a Kraken API key
+#[derive(Clone)]
+pub struct KrakenCredentials {
+    key: String,
+    secret: Vec<u8>,
+}
+
+impl KrakenCredentials {
+
+    /// `secret` is the private key as Kraken shows it, base64 encoded
+    pub fn new(key: &str, secret: &str) -> Result<Self, String> {
+
+        let secret: Vec<u8> = STANDARD
+            .decode(secret.trim())
+            .map_err(|e| format!("The API secret isn't valid base64: {}", e))?;
+
+        Ok(KrakenCredentials { key: key.trim().to_string(), secret })
+    }
+
+    /// `API-Sign` for a request to `path`, e.g. `/0/private/Balance`: an
+    /// HMAC-SHA512, keyed with the secret, of the path followed by the
+    /// SHA256 of the nonce and the form body
+    fn sign(&self, path: &str, nonce: u64, body: &str) -> String {
+
+        let mut hasher = Sha256::new();
+        hasher.update(nonce.to_string().as_bytes());
+        hasher.update(body.as_bytes());
+        let digest = hasher.finalize();
+
+        let mut mac = Hmac::<Sha512>::new_from_slice(&self.secret)
+            .expect("HMAC accepts keys of any length");
+        mac.update(path.as_bytes());
+        mac.update(&digest);
+
+        STANDARD.encode(mac.finalize().into_bytes())
+    }
+}
+
+// Keeps the secret out of logs and error messages
+impl fmt::Debug for KrakenCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KrakenCredentials")
+            .field("key", &self.key)
+            .field("secret", &"<redacted>")
+            .finish()
+    }
+}
+
+
+/// Microseconds since the epoch, or one more than the last nonce when the
+/// clock hasn't moved past it
+fn next_nonce() -> u64 {
+
+    let now: u64 = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_micros() as u64)
+        .unwrap_or(0);
+
+    let last: u64 = LAST_NONCE
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| {
+            Some(now.max(last + 1))
+        })
+        .unwrap_or(now);
+
+    now.max(last + 1)
+}
+
+/// Percent encodes `params` as an `application/x-www-form-urlencoded` body
+fn form_body(params: &[(&str, String)]) -> String {
+
+    fn encode(value: &str) -> String {
+        value
+            .bytes()
+            .map(|b| match b {
+                b'A'..=b'Z'
+                | b'a'..=b'z'
+                | b'0'..=b'9'
+                | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+                _ => format!("%{:02X}", b)
+            })
+            .collect()
+    }
+
+    params
+        .iter()
+        .map(|(name, value)| format!("{}={}", encode(name), encode(value)))
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+/// Sends a signed request to the private `endpoint`, e.g. `Balance`, taking
+/// a token from the rate limiter first, and returns the `result` of the
+/// reply
+async fn request_private_kraken<T: for<'de> Deserialize<'de>>(
+    endpoint: &str,
+    params: &[(&str, String)],
+    credentials: &KrakenCredentials,
+    client: &reqwest::Client
+) -> Result<T, RequestError> {
+
+    #[derive(Deserialize)]
+    struct Reply<T> {
+        error: Vec<String>,
+        result: Option<T>,
+    }
+
+    PRIVATE_RATE_LIMITER.acquire().await;
+
+    let path: String = format!("/0/private/{}", endpoint);
+    let nonce: u64 = next_nonce();
+
+    let mut fields: Vec<(&str, String)> = vec![("nonce", nonce.to_string())];
+    fields.extend(params.iter().cloned());
+    let body: String = form_body(&fields);
+
+    let response = client
+        .post(format!("{}{}", API_URL, path))
+        .header("API-Key", &credentials.key)
+        .header("API-Sign", credentials.sign(&path, nonce, &body))
+        .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(body)
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(RequestError::BadStatus(response.status()));
+    }
+
+    let reply: Reply<T> = serde_json::from_str(&response.text().await?)
+        .map_err(RequestError::Deserialize)?;
+
+    match (reply.error.is_empty(), reply.result) {
+        (true, Some(result)) => Ok(result),
+        (_, _) => Err(RequestError::RequestFailed(
+            format!("Request failed: {:?}", reply.error)
+        ))
+    }
+}
+
+/// Kraken sends amounts and prices as strings
+fn parse_amount(field: &str, value: &str) -> Result<f64, RequestError> {
+    value.parse::<f64>().map_err(|_| RequestError::RequestFailed(
+        format!("Couldn't read {} {:?} from Kraken", field, value)
+    ))
+}
+
+
+/// Every asset the account holds, e.g. `XXBT` or `ZUSD`, with its balance.
+/// Assets with nothing left are left out.
+pub async fn request_balances(
+    credentials: &KrakenCredentials,
+    client: &reqwest::Client
+) -> Result<BTreeMap<String, f64>, RequestError> {
+
+    let result: HashMap<String, String> = request_private_kraken(
+        "Balance",
+        &[],
+        credentials,
+        client
+    ).await?;
+
+    let mut balances: BTreeMap<String, f64> = BTreeMap::new();
+
+    for (asset, amount) in result {
+        let amount: f64 = parse_amount(&asset, &amount)?;
+        if amount != 0.0 {
+            balances.insert(asset, amount);
+        };
+    };
+
+    Ok(balances)
+}
+
+
+/// An order open on Kraken
+#[derive(Debug, Clone, Serialize)]
+pub struct KrakenOrder {
+    /// Kraken's transaction ID, e.g. `OQCLML-BW3P3-BUCMWZ`
+    pub txid: String,
+    pub pair: String,
+    /// `buy` or `sell`
+    pub side: String,
+    /// `market`, `limit`, ...
+    pub order_type: String,
+    pub volume: f64,
+    pub volume_filled: f64,
+    /// Limit price, none for market orders
+    pub price: Option<f64>,
+    /// When the order was opened, in microseconds like the stored ticks
+    pub opened: u64,
+    /// Kraken's summary, e.g. `buy 0.01 XBTUSD @ limit 30000.0`
+    pub description: String,
+}
+
+impl fmt::Display for KrakenOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}  {}  {} ({} of {} filled)",
+            self.txid,
+            db_timestamp_to_date_string(self.opened),
+            self.description,
+            self.volume_filled,
+            self.volume
+        )
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenOrdersResult {
+    open: HashMap<String, OpenOrderInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenOrderInfo {
+    opentm: f64,
+    descr: OrderDescription,
+    vol: String,
+    vol_exec: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OrderDescription {
+    pair: String,
+    #[serde(rename = "type")]
+    side: String,
+    ordertype: String,
+    price: String,
+    order: String,
+}
+
+/// The account's open orders, oldest first
+pub async fn request_open_orders(
+    credentials: &KrakenCredentials,
+    client: &reqwest::Client
+) -> Result<Vec<KrakenOrder>, RequestError> {
+
+    let result: OpenOrdersResult = request_private_kraken(
+        "OpenOrders",
+        &[],
+        credentials,
+        client
+    ).await?;
+
+    let mut orders: Vec<KrakenOrder> = Vec::with_capacity(result.open.len());
+
+    for (txid, info) in result.open {
+
+        let price: f64 = parse_amount("price", &info.descr.price)?;
+
+        orders.push(KrakenOrder {
+            txid,
+            pair: info.descr.pair,
+            side: info.descr.side,
+            price: match info.descr.ordertype == "market" {
+                true => None,
+                false => Some(price)
+            },
+            order_type: info.descr.ordertype,
+            volume: parse_amount("volume", &info.vol)?,
+            volume_filled: parse_amount("filled volume", &info.vol_exec)?,
+            opened: (info.opentm * 1_000_000.0) as u64,
+            description: info.descr.order,
+        });
+    };
+
+    orders.sort_by_key(|o| o.opened);
+
+    Ok(orders)
+}
+
+
+/// An order to send to Kraken
+#[derive(Debug, Clone)]
+pub struct KrakenOrderRequest {
+    /// e.g. `XBTUSD`
+    pub pair: String,
+    /// `buy` or `sell`
+    pub side: String,
+    /// Volume in the base currency
+    pub volume: f64,
+    /// Limit price, a market order without one
+    pub limit: Option<f64>,
+    /// Have Kraken check the order without placing it
+    pub validate: bool,
+}
+
+/// What Kraken made of an order
+#[derive(Debug, Clone, Serialize)]
+pub struct AddedOrder {
+    /// Transaction IDs of the orders placed, none when only validated
+    pub txids: Vec<String>,
+    /// Kraken's summary, e.g. `buy 0.01 XBTUSD @ market`
+    pub description: String,
+}
+
+impl fmt::Display for AddedOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.txids.is_empty() {
+            true => write!(f, "{} (validated only)", self.description),
+            false => write!(
+                f,
+                "{} ({})",
+                self.description,
+                self.txids.join(", ")
+            )
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AddOrderResult {
+    descr: AddOrderDescription,
+    #[serde(default)]
+    txid: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AddOrderDescription {
+    order: String,
+}
+
+/// Places `order` on the account, or only checks it when it's
+/// `validate`
+pub async fn add_order(
+    order: &KrakenOrderRequest,
+    credentials: &KrakenCredentials,
+    client: &reqwest::Client
+) -> Result<AddedOrder, RequestError> {
+
+    let mut params: Vec<(&str, String)> = vec![
+        ("ordertype", match order.limit {
+            Some(_) => "limit".to_string(),
+            None => "market".to_string()
+        }),
+        ("type", order.side.clone()),
+        ("volume", order.volume.to_string()),
+        ("pair", order.pair.clone()),
+    ];
+    if let Some(price) = order.limit {
+        params.push(("price", price.to_string()));
+    };
+    if order.validate {
+        params.push(("validate", "true".to_string()));
+    };
+
+    let result: AddOrderResult = request_private_kraken(
+        "AddOrder",
+        &params,
+        credentials,
+        client
+    ).await?;
+
+    Ok(AddedOrder {
+        txids: result.txid,
+        description: result.descr.order
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct CancelOrderResult {
+    count: u32,
+}
+
+/// Cancels the open order `txid`, returning how many orders that closed
+pub async fn cancel_order(
+    txid: &str,
+    credentials: &KrakenCredentials,
+    client: &reqwest::Client
+) -> Result<u32, RequestError> {
+
+    let result: CancelOrderResult = request_private_kraken(
+        "CancelOrder",
+        &[("txid", txid.to_string())],
+        credentials,
+        client
+    ).await?;
+
+    Ok(result.count)
+}
+
+#[derive(Debug, Deserialize)]
+struct EditOrderResult {
+    descr: AddOrderDescription,
+    txid: String,
+}
+
+/// Moves the price of the open order `txid` on `pair`, e.g. `XBTUSD`
+///
+/// Kraken replaces the order with a new one, whose transaction ID is
+/// returned.
+pub async fn edit_order(
+    txid: &str,
+    pair: &str,
+    price: f64,
+    credentials: &KrakenCredentials,
+    client: &reqwest::Client
+) -> Result<AddedOrder, RequestError> {
+
+    let result: EditOrderResult = request_private_kraken(
+        "EditOrder",
+        &[
+            ("txid", txid.to_string()),
+            ("pair", pair.to_string()),
+            ("price", price.to_string()),
+        ],
+        credentials,
+        client
+    ).await?;
+
+    Ok(AddedOrder {
+        txids: vec![result.txid],
+        description: result.descr.order
+    })
+}
+
+
+/// A fill of one of the account's orders
+#[derive(Debug, Clone, Serialize)]
+pub struct KrakenTrade {
+    /// Kraken's trade ID
+    pub txid: String,
+    /// The order it filled
+    pub order_txid: String,
+    /// e.g. `XXBTZUSD`
+    pub pair: String,
+    /// `buy` or `sell`
+    pub side: String,
+    /// Microseconds since the epoch, like the stored ticks
+    pub time: u64,
+    pub price: f64,
+    pub volume: f64,
+    /// In the quote currency
+    pub fee: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct TradesHistoryResult {
+    trades: HashMap<String, TradeInfo>,
+    count: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct TradeInfo {
+    ordertxid: String,
+    pair: String,
+    time: f64,
+    #[serde(rename = "type")]
+    side: String,
+    price: String,
+    fee: String,
+    vol: String,
+}
+
+/// Every fill of the account's orders, oldest first
+///
+/// Kraken sends them 50 at a time, newest first, so the pages are asked
+/// for until all `count` of them are in, each through the rate limiter.
+pub async fn request_trades_history(
+    credentials: &KrakenCredentials,
+    client: &reqwest::Client
+) -> Result<Vec<KrakenTrade>, RequestError> {
+
+    let mut trades: Vec<KrakenTrade> = Vec::new();
+
+    loop {
+
+        let page: TradesHistoryResult = request_private_kraken(
+            "TradesHistory",
+            &[("ofs", trades.len().to_string())],
+            credentials,
+            client
+        ).await?;
+
+        if page.trades.is_empty() {
+            break
+        };
+
+        for (txid, info) in page.trades {
+            trades.push(KrakenTrade {
+                txid,
+                order_txid: info.ordertxid,
+                pair: info.pair,
+                side: info.side,
+                time: (info.time * 1_000_000.0) as u64,
+                price: parse_amount("price", &info.price)?,
+                volume: parse_amount("volume", &info.vol)?,
+                fee: parse_amount("fee", &info.fee)?,
+            });
+        };
+
+        if trades.len() >= page.count {
+            break
+        };
+    };
+
+    trades.sort_by(|a, b| a.time.cmp(&b.time).then(a.txid.cmp(&b.txid)));
+
+    Ok(trades)
+}
@@ -1,22 +1,45 @@
-use std::{cmp::{max, min}, collections::{BTreeMap, HashMap}, fmt};
+use std::{
+    cmp::{max, min}, 
+    collections::{BTreeMap, HashMap}, 
+    fmt, 
+    time::Duration
+};
 
-use reqwest;
+use serde::Serialize;
 use sqlx::{PgPool, pool::{PoolConnection}, types::BigDecimal};
 use tokio::{sync::mpsc::UnboundedSender, task::JoinSet};
+pub use tokio_util::sync::CancellationToken;
 
 use string_helpers::capitlize_first_letter;
-use timestamp_tools::db_timestamp_to_date_string;
+use timestamp_tools::{db_timestamp_to_date_string, get_current_unix_timestamp};
 
+pub mod alerts;
+pub mod audit;
+pub mod checkpoint;
 pub mod connection;
 pub use connection::{
     Db, 
     DbLogin, 
     DbError,
     DataDownloadStatus,
+    DownloadControl,
     FetchError, 
     get_table_name
 };
+pub mod exchange_error;
+pub use exchange_error::ExchangeError;
+pub mod faults;
+pub mod jobs;
 pub mod kraken;
+pub mod kraken_private;
+pub mod lineage;
+pub mod pack;
+pub mod rate_limit;
+pub mod signals;
+pub mod stats;
+pub mod ticker;
+pub use ticker::{Ticker, TickerError};
+pub mod usage;
 use kraken::AssetPairInfo;
 
 
@@ -29,19 +52,15 @@ pub async fn add_new_pair(
     asset_info: Option<&BTreeMap<String, BTreeMap<String, AssetPairInfo>>>
 ) -> Result<(), DbError> {
     
-    match exchange {
-        "kraken" => {
-            kraken::add_new_db_table(
-                ticker, 
-                time_offset, 
-                client, 
-                db_pool.clone(),
-                asset_info
-            ).await?;
-        },
-        _ => {
-
-        }
+    if exchange == "kraken" {
+        kraken::add_new_db_table(
+            ticker, 
+            time_offset, 
+            client, 
+            db_pool.clone(),
+            asset_info
+        ).await?;
+        let _ = usage::flush_request_counts(db_pool).await;
     };
 
     Ok(())
@@ -63,7 +82,7 @@ pub async fn drop_pair(
         .execute(&db_pool)
         .await
         .map_err(|e| DbError::QueryFailed(
-            format!("{}: {}", e, query.to_string())
+            format!("{}: {}", e, query)
         ))?;
 
     if exchange == "kraken" {
@@ -77,15 +96,47 @@ pub async fn drop_pair(
             .execute(&db_pool)
             .await.map_err(|e| 
                 DbError::QueryFailed(
-                    format!("{}: {}", e, drop_query.to_string())
+                    format!("{}: {}", e, drop_query)
                 ))?;
 
+        sqlx::query(
+            "DELETE FROM _ingest_batches WHERE exchange = $1 AND asset = $2;"
+        )
+            .bind(exchange)
+            .bind(ticker.to_uppercase())
+            .execute(&db_pool)
+            .await
+            .map_err(|e| DbError::QueryFailed(
+                format!("Failed to drop ingest batches: {}", e)
+            ))?;
+
+        sqlx::query(
+            "DELETE FROM _job_history WHERE exchange = $1 AND asset = $2;"
+        )
+            .bind(exchange)
+            .bind(ticker.to_uppercase())
+            .execute(&db_pool)
+            .await
+            .map_err(|e| DbError::QueryFailed(
+                format!("Failed to drop job history: {}", e)
+            ))?;
+
     };
 
     Ok(())
 }
 
 /// Downloads missing data to database tables 
+///
+/// Cancelling `control.cancel` stops the download after the batch being 
+/// written, with `DbError::Cancelled`. A download that stalls is retried 
+/// from its checkpoint, see `DownloadControl`, and sends a 
+/// `DataDownloadStatus::Stalled` either way. Retryable exchange errors are
+/// retried the same way after a delay, announced with 
+/// `DataDownloadStatus::Retrying`.
+///
+/// How the download ended is recorded in `_job_history` under 
+/// `control.job_id`, see `jobs::failed_pairs`.
 pub async fn download_new_data_to_db_table(
     exchange: &str, 
     ticker: &str,
@@ -93,19 +144,136 @@ pub async fn download_new_data_to_db_table(
     initial_unix_timestamp_offset: u64,
     client: &reqwest::Client,
     progress_tx: UnboundedSender<DataDownloadStatus>,
+    control: &DownloadControl,
 ) -> Result<(), DbError> {
    
-    if exchange == "kraken" {      
-        kraken::download_new_data_to_db_table(
+    if exchange != "kraken" {
+        return Ok(())
+    };
+
+    let result = download_with_retries(
+        exchange, 
+        ticker, 
+        db_pool.clone(), 
+        initial_unix_timestamp_offset, 
+        client, 
+        progress_tx, 
+        control
+    ).await;
+
+    // Only bookkeeping, so it can't fail the download itself
+    let _ = jobs::record_job(
+        db_pool.clone(), 
+        control.job_id, 
+        exchange, 
+        ticker, 
+        &result
+    ).await;
+    let _ = usage::flush_request_counts(db_pool).await;
+
+    result
+}
+
+/// The retry loop of `download_new_data_to_db_table`
+async fn download_with_retries(
+    exchange: &str, 
+    ticker: &str,
+    db_pool: PgPool,
+    initial_unix_timestamp_offset: u64,
+    client: &reqwest::Client,
+    progress_tx: UnboundedSender<DataDownloadStatus>,
+    control: &DownloadControl,
+) -> Result<(), DbError> {
+
+    let mut attempt: u32 = 0;
+
+    loop {
+
+        let result = kraken::download_new_data_to_db_table(
             ticker, 
-            db_pool, 
+            db_pool.clone(), 
             initial_unix_timestamp_offset,
             client,
-            progress_tx,
-        ).await?; 
+            progress_tx.clone(),
+            control,
+        ).await;
+
+        let retrying: bool = attempt < control.stall_retries;
+        let exchange_name: String = capitlize_first_letter(
+            &exchange.to_string()
+        );
+
+        let delay: Duration = match &result {
+            Err(DbError::Stalled { cursor, idle_secs, .. }) => {
+                let _ = progress_tx.send(DataDownloadStatus::Stalled { 
+                    exchange: exchange_name.clone(), 
+                    ticker: ticker.to_string(), 
+                    cursor: cursor.clone(), 
+                    idle_secs: *idle_secs,
+                    retrying 
+                });
+                Duration::ZERO
+            },
+            Err(DbError::Fetch(FetchError::Exchange(e))) 
+                if e.is_retryable() && retrying => 
+            {
+                let delay: Duration = e.retry_delay(attempt);
+                let _ = progress_tx.send(DataDownloadStatus::Retrying { 
+                    exchange: exchange_name.clone(), 
+                    ticker: ticker.to_string(), 
+                    reason: e.user_message().to_string(), 
+                    delay_secs: delay.as_secs()
+                });
+                delay
+            },
+            _ => return result
+        };
+
+        if !retrying {
+            return result
+        };
+        attempt += 1;
+
+        tokio::select! {
+            _ = control.cancel.cancelled() => {
+                let _ = progress_tx.send(DataDownloadStatus::Cancelled { 
+                    exchange: exchange_name, 
+                    ticker: ticker.to_string() 
+                });
+                return Err(DbError::Cancelled)
+            },
+            _ = tokio::time::sleep(delay) => {}
+        };
+    }
+
+}
+
+/// Fills missing tick IDs reported by `integrity_check`
+///
+/// Returns the number of ticks that were written back into the table.
+pub async fn repair_missing_ticks(
+    exchange: &str,
+    ticker: &str,
+    missing_ticks: &[u64],
+    client: &reqwest::Client,
+    db_pool: PgPool
+) -> Result<u64, DbError> {
+
+    if missing_ticks.is_empty() {
+        return Ok(0)
     };
 
-    Ok(())
+    match exchange {
+        "kraken" => kraken::repair_missing_ticks(
+            ticker, 
+            missing_ticks, 
+            client, 
+            db_pool
+        ).await,
+        _ => Err(DbError::QueryFailed(
+            format!("Repair is not supported for exchange: {}", exchange)
+        ))
+    }
 
 }
 
@@ -152,13 +320,11 @@ pub async fn fetch_tables(
     db_pool: PgPool 
 ) -> Result<Vec<String>, DbError> {
 
-    let table_query: String = format!(
-        r#"
+    let table_query: String = r#"
         SELECT table_name
         FROM information_schema.tables
         WHERE table_schema = 'public'
-        "#
-    );
+        "#.to_string();
 
     let tables: Vec<String> = match sqlx::query_scalar(&table_query)
         .fetch_all(&db_pool)
@@ -176,14 +342,23 @@ pub async fn fetch_tables(
 
 
 /// Fetches all asset pair tables organized by exchange name 
-pub async fn fetch_exchanges_and_pairs_from_db(db_pool: PgPool) 
-    -> HashMap<String, Vec<String>> {
+///
+/// With `with_stats` each pair also carries its table's stats, read with
+/// `fetch_pair_stats`. They are `None` without it, or when they can't be
+/// read.
+pub async fn fetch_exchanges_and_pairs_from_db(
+    db_pool: PgPool,
+    with_stats: bool
+) -> HashMap<String, Vec<(String, Option<PairStats>)>> {
    
-    let tables: Vec<String> = fetch_tables(db_pool)
+    let tables: Vec<String> = fetch_tables(db_pool.clone())
         .await
         .unwrap_or(Vec::new());
 
-    let mut exchanges_and_pairs: HashMap<String, Vec<String>> = HashMap::new();
+    let mut exchanges_and_pairs: HashMap<
+        String, 
+        Vec<(String, Option<PairStats>)>
+    > = HashMap::new();
    
     for table in tables {
         if table.starts_with("asset_") { 
@@ -197,9 +372,16 @@ pub async fn fetch_exchanges_and_pairs_from_db(db_pool: PgPool)
                 &exchange.to_string()
             );
 
+            let stats: Option<PairStats> = match with_stats {
+                true => fetch_pair_stats(exchange, asset, db_pool.clone())
+                    .await
+                    .ok(),
+                false => None
+            };
+
             exchanges_and_pairs.entry(title)
-                .or_insert(Vec::new())
-                .push(asset.to_uppercase());
+                .or_default()
+                .push((asset.to_uppercase(), stats));
 
         };
     };
@@ -252,6 +434,160 @@ pub async fn fetch_first_or_last_row(
 }
 
 
+/// Fetches the row count and the first and last tick times of a table
+///
+/// Times are in the table's microsecond format, and are `None` when the
+/// table is empty.
+pub async fn fetch_table_coverage(
+    exchange: &str,
+    ticker: &str,
+    db_pool: PgPool
+) -> Result<(u64, Option<u64>, Option<u64>), DbError> {
+
+    let table_name = get_table_name(exchange, ticker);
+
+    let query = format!(
+        r#"SELECT COUNT(*), MIN(time), MAX(time) FROM {table_name}"#
+    );
+
+    let (count, first, last) = sqlx::query_as::<
+        _, (i64, Option<i64>, Option<i64>)
+    >(&query)
+        .fetch_one(&db_pool)
+        .await
+        .map_err(|_| DbError::QueryFailed(query))?;
+
+    Ok((
+        count as u64,
+        first.map(|t| t as u64),
+        last.map(|t| t as u64)
+    ))
+
+}
+
+
+/// Size and coverage of one pair's table, for listing pairs
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PairStats {
+    /// An estimate, see `fetch_pair_stats`
+    pub approx_ticks: u64,
+    /// First and last tick times (microseconds), `None` when empty
+    pub first_time: Option<u64>,
+    pub last_time: Option<u64>,
+    /// Table, index and TOAST size on disk, in bytes
+    pub size_bytes: u64,
+}
+
+impl PairStats {
+
+    /// Whether the last tick is older than `max_age_secs`, or there is none
+    pub fn is_stale(&self, max_age_secs: u64) -> bool {
+        match self.last_time {
+            Some(t) => {
+                let now: u64 = get_current_unix_timestamp();
+                now.saturating_sub(t / 1_000_000) > max_age_secs
+            },
+            None => true
+        }
+    }
+}
+
+
+/// Fetches the tick count, first and last tick times and size of a table
+///
+/// The count is the planner's estimate, which is close once the table has
+/// been analyzed and saves scanning millions of rows, so it should be shown
+/// as approximate. Tables that were never analyzed, where Postgres reports
+/// 0 or -1, are counted exactly.
+pub async fn fetch_pair_stats(
+    exchange: &str,
+    ticker: &str,
+    db_pool: PgPool
+) -> Result<PairStats, DbError> {
+
+    let table_name = get_table_name(exchange, ticker);
+
+    let query = format!(
+        r#"SELECT
+            c.reltuples::int8,
+            pg_total_relation_size(c.oid),
+            (SELECT time FROM {table_name} ORDER BY id ASC LIMIT 1),
+            (SELECT time FROM {table_name} ORDER BY id DESC LIMIT 1)
+        FROM pg_class c
+        WHERE c.oid = '{table_name}'::regclass"#
+    );
+
+    let (estimate, size, first, last) = sqlx::query_as::<
+        _, (i64, i64, Option<i64>, Option<i64>)
+    >(&query)
+        .fetch_one(&db_pool)
+        .await
+        .map_err(|_| DbError::QueryFailed(query))?;
+
+    let approx_ticks: u64 = match (estimate, last) {
+        (_, None) => 0,
+        (e, Some(_)) if e > 0 => e as u64,
+        _ => fetch_table_coverage(exchange, ticker, db_pool).await?.0
+    };
+
+    Ok(PairStats {
+        approx_ticks,
+        first_time: first.map(|t| t as u64),
+        last_time: last.map(|t| t as u64),
+        size_bytes: size.max(0) as u64,
+    })
+
+}
+
+
+/// Fetches the closing price of each hour over the last 24 hours of data
+///
+/// The window ends at the most recent tick in the table rather than the 
+/// current time, so pairs that haven't been updated in a while still return
+/// a full day. Hours without trades are left out.
+///
+/// The names come from outside, e.g. the watchlist, so anything that
+/// couldn't be a pair's table is refused before it reaches the query.
+pub async fn fetch_price_snapshot(
+    exchange: &str, 
+    ticker: &str,
+    db_pool: PgPool
+) -> Result<Vec<f64>, DbError> {
+
+    const HOUR_MICROS: i64 = 3_600_000_000;
+
+    if exchange.is_empty() 
+        || !exchange.chars().all(|c| c.is_ascii_alphanumeric())
+        || ticker.parse::<Ticker>().is_err()
+    {
+        return Err(DbError::QueryFailed(format!(
+            "Not a pair: {} {}", exchange, ticker
+        )))
+    };
+
+    let table_name = get_table_name(exchange, ticker);
+
+    let query = format!(
+        r#"SELECT (array_agg(price::float8 ORDER BY id DESC))[1]
+        FROM {table_name}
+        WHERE time > (SELECT MAX(time) FROM {table_name}) - {}
+        GROUP BY time / {}
+        ORDER BY time / {}"#,
+        24 * HOUR_MICROS,
+        HOUR_MICROS,
+        HOUR_MICROS
+    );
+
+    sqlx::query_scalar::<_, f64>(&query)
+        .fetch_all(&db_pool)
+        .await
+        .map_err(|e| DbError::QueryFailed(format!(
+            "Couldn't read the prices of {}: {}", table_name, e
+        )))
+
+}
+
+
 /// # Fetch All Rows of an Asset Table
 ///
 /// If a limit value is provided, then the X most recent ticks are returned.
@@ -273,10 +609,7 @@ pub async fn fetch_rows(
         Err(_) => return Err(DbError::ConnectionFailed)
     };
 
-    let limit: u64 = match limit {
-        Some(i) => i,
-        None => 1_000
-    };
+    let limit: u64 = limit.unwrap_or(1_000);
 
     let last_id_query: &String = &format!(
         r#"SELECT id FROM {table_name} 
@@ -288,7 +621,7 @@ pub async fn fetch_rows(
         .await 
     {
         Ok(d) => {
-            if d.len() > 0 { 
+            if !d.is_empty() { 
                 d[0] as u64
             }
             else {
@@ -340,6 +673,125 @@ pub async fn fetch_rows(
 }
 
 
+/// Fetches every tick from `from` up to, but not including, `to`, both in 
+/// the table's microsecond format. Either end can be left open.
+pub async fn fetch_rows_in_time_range(
+    exchange: &str, 
+    ticker: &str,
+    from: Option<u64>,
+    to: Option<u64>,
+    db_pool: PgPool
+) -> Result<Vec<(u64, u64, BigDecimal, BigDecimal)>, DbError> {
+
+    let table_name = get_table_name(exchange, ticker);
+
+    let query: String = format!(
+        r#"
+        SELECT id, time, price, volume
+        FROM {table_name} WHERE time >= {} AND time < {}
+        ORDER BY id;
+        "#,
+        from.unwrap_or(0).min(i64::MAX as u64),
+        to.unwrap_or(u64::MAX).min(i64::MAX as u64)
+    );
+
+    type Drow = Vec<(u64, u64, BigDecimal, BigDecimal)>;
+
+    let rows: Drow = sqlx::query_as::<_, (i64, i64, BigDecimal, BigDecimal)>(
+        &query
+    )
+        .fetch_all(&db_pool)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Failed to fetch ticks in time range: {}", e)
+        ))?
+        .into_iter()
+        .map(|(i, t, p, vol)| (i as u64, t as u64, p, vol))
+        .collect();
+
+    Ok(rows)
+}
+
+
+/// Counts the ticks `fetch_rows_in_time_range` would return, without 
+/// reading them
+pub async fn count_rows_in_time_range(
+    exchange: &str, 
+    ticker: &str,
+    from: Option<u64>,
+    to: Option<u64>,
+    db_pool: PgPool
+) -> Result<u64, DbError> {
+
+    let table_name = get_table_name(exchange, ticker);
+
+    let query: String = format!(
+        r#"
+        SELECT COUNT(*) FROM {table_name} WHERE time >= {} AND time < {};
+        "#,
+        from.unwrap_or(0).min(i64::MAX as u64),
+        to.unwrap_or(u64::MAX).min(i64::MAX as u64)
+    );
+
+    let count: i64 = sqlx::query_scalar::<_, i64>(&query)
+        .fetch_one(&db_pool)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Failed to count ticks in time range: {}", e)
+        ))?;
+
+    Ok(count.max(0) as u64)
+}
+
+
+/// Fetches up to `limit` ticks in a time range with an id above `after_id`,
+/// in id order, so a long range can be read a page at a time
+///
+/// Times are in the table's microsecond format, and either end of the 
+/// range can be left open like in `fetch_rows_in_time_range`.
+pub async fn fetch_rows_page(
+    exchange: &str, 
+    ticker: &str,
+    after_id: u64,
+    range: (Option<u64>, Option<u64>),
+    limit: u64,
+    db_pool: PgPool
+) -> Result<Vec<(u64, u64, BigDecimal, BigDecimal)>, DbError> {
+
+    let table_name = get_table_name(exchange, ticker);
+
+    let query: String = format!(
+        r#"
+        SELECT id, time, price, volume
+        FROM {table_name} 
+        WHERE id > {} AND time >= {} AND time < {}
+        ORDER BY id
+        LIMIT {};
+        "#,
+        after_id.min(i64::MAX as u64),
+        range.0.unwrap_or(0).min(i64::MAX as u64),
+        range.1.unwrap_or(u64::MAX).min(i64::MAX as u64),
+        limit.min(i64::MAX as u64)
+    );
+
+    type Drow = Vec<(u64, u64, BigDecimal, BigDecimal)>;
+
+    let rows: Drow = sqlx::query_as::<_, (i64, i64, BigDecimal, BigDecimal)>(
+        &query
+    )
+        .fetch_all(&db_pool)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Failed to fetch a page of ticks: {}", e)
+        ))?
+        .into_iter()
+        .map(|(i, t, p, vol)| (i as u64, t as u64, p, vol))
+        .collect();
+
+    Ok(rows)
+}
+
+
 /// # First Time Setup for DB
 ///
 /// Only runs if the database has just been setup
@@ -371,9 +823,10 @@ pub async fn first_time_setup(
                         time VARCHAR(20)
                     ); 
                 "#;
-                if let Err(_) = sqlx::query(&query)
+                if sqlx::query(query)
                     .execute(&mut *conn)
                     .await 
+                    .is_err()
                 {
                     return Err(DbError::QueryFailed(
                             "Failed to create '_last_tick_history'".to_string()
@@ -381,6 +834,40 @@ pub async fn first_time_setup(
                     ); 
                 };
             };
+
+            // Cursors used to be kept as strings in `time`, which is left in
+            // place but no longer written
+            let migrations: [&'static str; 12] = [
+                r#"
+                    ALTER TABLE _last_tick_history 
+                    ADD COLUMN IF NOT EXISTS cursor_ns BIGINT 
+                    NOT NULL DEFAULT 0;
+                "#,
+                r#"
+                    UPDATE _last_tick_history 
+                    SET cursor_ns = time::BIGINT
+                    WHERE cursor_ns = 0 AND time ~ '^[0-9]{1,19}$';
+                "#,
+                lineage::CREATE_BATCH_TABLE,
+                jobs::CREATE_JOB_HISTORY_TABLE,
+                usage::CREATE_API_USAGE_TABLE,
+                audit::CREATE_AUDIT_LOG_TABLE,
+                alerts::CREATE_ALERT_HISTORY_TABLE,
+                signals::CREATE_SIGNAL_HISTORY_TABLE,
+                jobs::CREATE_REPORT_HISTORY_TABLE,
+                audit::CREATE_AUDIT_GUARD_FUNCTION,
+                audit::DROP_AUDIT_GUARD,
+                audit::CREATE_AUDIT_GUARD,
+            ];
+
+            for query in migrations {
+                if let Err(e) = sqlx::query(query).execute(&mut *conn).await {
+                    return Err(DbError::QueryFailed(format!(
+                        "Failed to migrate download bookkeeping tables: {}", 
+                        e
+                    )));
+                };
+            };
         };
     };
 
@@ -405,7 +892,7 @@ pub async fn initialize(active_exchanges: &Vec<String>) -> Result<Db, DbError> {
 
     let db_pool = database.get_pool();
 
-    first_time_setup(&active_exchanges, db_pool.clone()).await?;
+    first_time_setup(active_exchanges, db_pool.clone()).await?;
 
     Ok(database)
 
@@ -414,21 +901,30 @@ pub async fn initialize(active_exchanges: &Vec<String>) -> Result<Db, DbError> {
 
 /// # Update Database Tables 
 ///
-/// Updates all database tables by default. If an exchange is given, then only
-/// the tables of that exchange will be updated. If a ticker is given, then 
-/// only that ticker will be updated, even if it's for multiple exchanges.
-/// If an exchange AND ticker are given, then only that ticker for that 
-/// exchange will be updated.
+/// Updates all database tables by default. `pair` is an optional 
+/// `(exchange, ticker)` filter. If an exchange is given, then only the tables
+/// of that exchange will be updated. If a ticker is given, then only that 
+/// ticker will be updated, even if it's for multiple exchanges. If an 
+/// exchange AND ticker are given, then only that ticker for that exchange 
+/// will be updated.
+///
+/// Cancelling `control.cancel` stops every download after the batch it's 
+/// writing, and stalled downloads are retried as set in `control`. All 
+/// downloads are awaited even after one fails, so none is cut off 
+/// mid-batch. The first real error is returned, otherwise 
+/// `DbError::Cancelled` if the update was cancelled.
 pub async fn update_database_tables(
     active_exchanges: &Vec<String>,
     time_offset: u64,
     client: &reqwest::Client,
     db_pool: PgPool,
     progress_tx: tokio::sync::mpsc::UnboundedSender<DataDownloadStatus>,
-    exchange: Option<&str>,
-    ticker_sym: Option<&str>
+    pair: (Option<&str>, Option<&str>),
+    control: DownloadControl
 ) -> Result<(), DbError> {
 
+    let (exchange, ticker_sym) = pair;
+
     let existing_tables = fetch_tables(db_pool.clone()).await?;
 
     let mut tasks: JoinSet<Result<(), DbError>> = JoinSet::new();
@@ -446,7 +942,7 @@ pub async fn update_database_tables(
 
             for table in &exchange_tables {
 
-                let ticker: String = match table.split('_').last() {
+                let ticker: String = match table.split('_').next_back() {
                     Some(a) => a.to_uppercase(),
                     None => continue 
                 };
@@ -456,34 +952,45 @@ pub async fn update_database_tables(
                 let task_db_pool = db_pool.clone();
                 let task_tx = progress_tx.clone();
                 let task_client = client.clone();
+                let task_control = control.clone();
 
                 tasks.spawn(async move {
-                    kraken::download_new_data_to_db_table(
+                    download_new_data_to_db_table(
+                        "kraken",
                         &ticker, 
                         task_db_pool, 
                         time_offset, 
                         &task_client, 
-                        task_tx 
+                        task_tx,
+                        &task_control
                     ).await 
                 });
             };
         };
     };
 
+    let mut result: Result<(), DbError> = Ok(());
+
     while let Some(res) = tasks.join_next().await {
-        match res {
-            Ok(inner) => inner?,
-            Err(join_err) => {
-                return Err(DbError::TaskJoin(join_err))
-            }
-        } 
+        
+        let outcome: Result<(), DbError> = match res {
+            Ok(inner) => inner,
+            Err(join_err) => Err(DbError::TaskJoin(join_err))
+        };
+
+        if let Err(e) = outcome 
+            && matches!(result, Ok(()) | Err(DbError::Cancelled)) 
+        {
+            result = Err(e);
+        };
     };
 
-    Ok(())
+    result
 
 }
 
 
+#[derive(Debug, Clone, Serialize)]
 pub struct DatabaseIntegrity {
     pub table_name: String,
     pub is_ok: bool,
@@ -506,27 +1013,27 @@ impl fmt::Display for DatabaseIntegrity {
             }
         }
         
-        write!(f, "\x1b[1;36mDatabase Integrity:\x1b[0m\n")?;
-        write!(f, "  \x1b[33mtable_name   \x1b[0m: {}\n", 
+        writeln!(f, "\x1b[1;36mDatabase Integrity:\x1b[0m")?;
+        writeln!(f, "  \x1b[33mtable_name   \x1b[0m: {}", 
             self.table_name)?;
-        write!(f, "  \x1b[33mis_ok        \x1b[0m: {}{}\n", 
+        writeln!(f, "  \x1b[33mis_ok        \x1b[0m: {}{}", 
             col(self.is_ok), self.is_ok)?;
-        write!(f, "  \x1b[33mfirst_tick_id\x1b[0m: {}\n", self.first_tick_id)?;
-        write!(f, "  \x1b[33mlast_tick_id \x1b[0m: {}\n", self.last_tick_id)?;
-        write!(f, "  \x1b[33mfirst_date   \x1b[0m: {}\n", self.first_date)?;
-        write!(f, "  \x1b[33mlast_date    \x1b[0m: {}\n", self.last_date)?;
-        write!(f, "  \x1b[33mtotal_ticks  \x1b[0m: {}\n", self.total_ticks)?;
+        writeln!(f, "  \x1b[33mfirst_tick_id\x1b[0m: {}", self.first_tick_id)?;
+        writeln!(f, "  \x1b[33mlast_tick_id \x1b[0m: {}", self.last_tick_id)?;
+        writeln!(f, "  \x1b[33mfirst_date   \x1b[0m: {}", self.first_date)?;
+        writeln!(f, "  \x1b[33mlast_date    \x1b[0m: {}", self.last_date)?;
+        writeln!(f, "  \x1b[33mtotal_ticks  \x1b[0m: {}", self.total_ticks)?;
         
-        if self.missing_ticks.len() > 0 {
+        if !self.missing_ticks.is_empty() {
             write!(f, "  \x1b[33mmissing_ticks\x1b[0m: [\n\x1b[1;31m")?;
             for missing in &self.missing_ticks {
-                write!(f, "    {}\n", missing)?;
+                writeln!(f, "    {}", missing)?;
             };
-            write!(f, "\x1b[0m  ]\n")?;
+            writeln!(f, "\x1b[0m  ]")?;
         }
         else {
-            write!(f, 
-                "  \x1b[33mmissing_ticks\x1b[0m: \x1b[32mnone\x1b[0m\n")?;
+            writeln!(f, 
+                "  \x1b[33mmissing_ticks\x1b[0m: \x1b[32mnone\x1b[0m")?;
         };
 
         if !self.is_ok {
@@ -634,7 +1141,7 @@ pub async fn integrity_check(
     
     }; 
 
-    if dbi.error.len() > 0 { 
+    if !dbi.error.is_empty() { 
         dbi.is_ok = false 
     };
    
@@ -0,0 +1,390 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use sqlx::{PgConnection, PgPool};
+
+use crate::{DbError, get_table_name};
+
+
+/// Side table recording which download job inserted each range of ticks
+///
+/// One row per committed batch. `prev_next_tick_id` and `prev_cursor_ns` 
+/// hold the pair's checkpoint from before the batch, so the batch can be 
+/// undone without re-reading anything from the exchange.
+pub(crate) const CREATE_BATCH_TABLE: &str = r#"
+    CREATE TABLE IF NOT EXISTS _ingest_batches (
+        id BIGSERIAL PRIMARY KEY,
+        job_id BIGINT NOT NULL,
+        exchange VARCHAR(16) NOT NULL,
+        asset VARCHAR(12) NOT NULL,
+        first_tick_id BIGINT NOT NULL,
+        last_tick_id BIGINT NOT NULL,
+        tick_count BIGINT NOT NULL,
+        prev_next_tick_id BIGINT NOT NULL,
+        prev_cursor_ns BIGINT NOT NULL,
+        inserted_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    );
+"#;
+
+
+/// A fresh ID for a download job
+///
+/// The job's start time in microseconds, so IDs sort by when the job ran. 
+/// Jobs are started by hand or on a timer, never microseconds apart.
+pub fn new_job_id() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_micros() as i64)
+        .unwrap_or(0)
+}
+
+
+/// A range of ticks committed by one download job, with the checkpoint it
+/// replaced
+#[derive(Debug, Clone)]
+pub struct IngestBatch {
+    pub job_id: i64,
+    pub exchange: String,
+    pub asset: String,
+    pub first_tick_id: u64,
+    pub last_tick_id: u64,
+    /// Ticks actually inserted, leaving out any that were already stored
+    pub tick_count: u64,
+    pub prev_next_tick_id: u64,
+    pub prev_cursor_ns: u64,
+}
+
+/// Records `batch` on `conn`, which should be the transaction that inserted
+/// its ticks, and returns the batch ID
+pub(crate) async fn record_batch(
+    conn: &mut PgConnection,
+    batch: &IngestBatch
+) -> Result<i64, DbError> {
+
+    let query: &'static str = r#"
+        INSERT INTO _ingest_batches (
+            job_id,
+            exchange,
+            asset,
+            first_tick_id,
+            last_tick_id,
+            tick_count,
+            prev_next_tick_id,
+            prev_cursor_ns
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING id;
+    "#;
+
+    sqlx::query_scalar(query)
+        .bind(batch.job_id)
+        .bind(&batch.exchange)
+        .bind(&batch.asset)
+        .bind(batch.first_tick_id as i64)
+        .bind(batch.last_tick_id as i64)
+        .bind(batch.tick_count as i64)
+        .bind(batch.prev_next_tick_id as i64)
+        .bind(batch.prev_cursor_ns as i64)
+        .fetch_one(conn)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Failed to record ingest batch: {}", e)
+        ))
+}
+
+/// ID of the last batch recorded, 0 before the first
+pub async fn latest_batch_id(db_pool: PgPool) -> Result<i64, DbError> {
+
+    sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM _ingest_batches;")
+        .fetch_one(&db_pool)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Couldn't fetch the latest ingest batch: {}", e)
+        ))
+}
+
+/// Batches recorded after batch `after_id`, across every pair, oldest first
+/// and with their IDs
+pub async fn batches_after(
+    after_id: i64,
+    db_pool: PgPool
+) -> Result<Vec<(i64, IngestBatch)>, DbError> {
+
+    let query: &'static str = r#"
+        SELECT 
+            id,
+            job_id, 
+            exchange, 
+            asset, 
+            first_tick_id, 
+            last_tick_id, 
+            tick_count, 
+            prev_next_tick_id, 
+            prev_cursor_ns
+        FROM _ingest_batches
+        WHERE id > $1
+        ORDER BY id;
+    "#;
+
+    let rows: Vec<IdBatchRow> = sqlx::query_as(query)
+        .bind(after_id)
+        .fetch_all(&db_pool)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Couldn't fetch ingest batches: {}", e)
+        ))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(id, job_id, exchange, asset, first, last, count, next, ns)| {
+            let row: BatchRow = 
+                (job_id, exchange, asset, first, last, count, next, ns);
+            (id, IngestBatch::from(row))
+        })
+        .collect())
+}
+
+/// Undoes download batch `batch_id`
+///
+/// Deletes the batch's ticks and puts the pair's checkpoint back where it 
+/// was before the batch, so the next update downloads that range again. 
+/// Only a pair's latest batch can be rolled back, since the checkpoints of 
+/// later ones were taken from it. Returns the batch and the number of ticks
+/// deleted.
+pub async fn rollback_batch(
+    batch_id: i64,
+    db_pool: PgPool
+) -> Result<(IngestBatch, u64), DbError> {
+
+    let mut tx = db_pool
+        .begin()
+        .await
+        .map_err(|_| DbError::ConnectionFailed)?;
+
+    let batch_query: &'static str = r#"
+        SELECT 
+            job_id, 
+            exchange, 
+            asset, 
+            first_tick_id, 
+            last_tick_id, 
+            tick_count, 
+            prev_next_tick_id, 
+            prev_cursor_ns
+        FROM _ingest_batches
+        WHERE id = $1
+        FOR UPDATE;
+    "#;
+
+    let batch: IngestBatch = match sqlx::query_as::<_, BatchRow>(batch_query)
+        .bind(batch_id)
+        .fetch_optional(&mut *tx)
+        .await
+    {
+        Ok(Some(row)) => IngestBatch::from(row),
+        Ok(None) => return Err(DbError::QueryFailed(
+            format!("No ingest batch with ID {}", batch_id)
+        )),
+        Err(e) => return Err(DbError::QueryFailed(
+            format!("Couldn't fetch ingest batch {}: {}", batch_id, e)
+        ))
+    };
+
+    let later_query: &'static str = r#"
+        SELECT id FROM _ingest_batches
+        WHERE exchange = $1 AND asset = $2 AND id > $3
+        ORDER BY id;
+    "#;
+
+    let later: Vec<i64> = sqlx::query_scalar(later_query)
+        .bind(&batch.exchange)
+        .bind(&batch.asset)
+        .bind(batch_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Couldn't fetch later ingest batches: {}", e)
+        ))?;
+
+    if !later.is_empty() {
+        return Err(DbError::QueryFailed(format!(
+            "Batch {} isn't the latest for {} {}. Roll back batches {:?} \
+            first",
+            batch_id,
+            batch.exchange,
+            batch.asset,
+            later
+        )))
+    };
+
+    let delete_query: String = format!(
+        "DELETE FROM {} WHERE id BETWEEN $1 AND $2;",
+        get_table_name(&batch.exchange, &batch.asset)
+    );
+
+    let deleted: u64 = sqlx::query(&delete_query)
+        .bind(batch.first_tick_id as i64)
+        .bind(batch.last_tick_id as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Failed to delete ticks of batch {}: {}", batch_id, e)
+        ))?
+        .rows_affected();
+
+    let checkpoint_query: &'static str = r#"
+        UPDATE _last_tick_history
+        SET next_tick_id = $1, cursor_ns = $2
+        WHERE asset = $3;
+    "#;
+
+    sqlx::query(checkpoint_query)
+        .bind(batch.prev_next_tick_id as i64)
+        .bind(batch.prev_cursor_ns as i64)
+        .bind(&batch.asset)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Failed to restore checkpoint: {}", e)
+        ))?;
+
+    sqlx::query("DELETE FROM _ingest_batches WHERE id = $1;")
+        .bind(batch_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Failed to remove batch {}: {}", batch_id, e)
+        ))?;
+
+    if tx.commit().await.is_err() {
+        return Err(DbError::QueryFailed(
+            format!("Failed to commit rollback of batch {}", batch_id)
+        ));
+    };
+
+    Ok((batch, deleted))
+}
+
+
+/// Ticks a pair took in over a window, from its batches
+#[derive(Debug, Clone)]
+pub struct IngestRate {
+    pub exchange: String,
+    pub ticker: String,
+    pub batches: u64,
+    pub ticks: u64,
+    /// The IDs the batches span, from the first batch's first tick to the
+    /// last batch's last
+    pub first_tick_id: u64,
+    pub last_tick_id: u64,
+}
+
+/// Ticks inserted per pair in the last `window`, by exchange and ticker.
+/// Pairs without a batch in the window are left out.
+pub async fn ingest_rates(
+    window: Duration,
+    db_pool: PgPool
+) -> Result<Vec<IngestRate>, DbError> {
+
+    let query: &'static str = r#"
+        SELECT
+            exchange,
+            UPPER(asset),
+            COUNT(*)::BIGINT,
+            COALESCE(SUM(tick_count), 0)::BIGINT,
+            MIN(first_tick_id),
+            MAX(last_tick_id)
+        FROM _ingest_batches
+        WHERE inserted_at > NOW() - make_interval(secs => $1)
+        GROUP BY exchange, UPPER(asset)
+        ORDER BY exchange, UPPER(asset);
+    "#;
+
+    let rows = sqlx::query_as::<
+        _, (String, String, i64, i64, i64, i64)
+    >(query)
+        .bind(window.as_secs_f64())
+        .fetch_all(&db_pool)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Couldn't fetch the ingest rates: {}", e)
+        ))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(exchange, ticker, batches, ticks, first, last)| IngestRate {
+            exchange,
+            ticker,
+            batches: batches.max(0) as u64,
+            ticks: ticks.max(0) as u64,
+            first_tick_id: first.max(0) as u64,
+            last_tick_id: last.max(0) as u64,
+        })
+        .collect())
+}
+
+/// The gaps in the pair's tick IDs from `first_tick_id` to `last_tick_id`,
+/// as `(gaps, missing ticks)`. A gap is a run of missing IDs, like those
+/// `integrity_check` lists one by one.
+pub async fn tick_gaps(
+    exchange: &str,
+    ticker: &str,
+    first_tick_id: u64,
+    last_tick_id: u64,
+    db_pool: PgPool
+) -> Result<(u64, u64), DbError> {
+
+    let query: String = format!(
+        r#"
+            SELECT
+                COUNT(*) FILTER (WHERE step > 1)::BIGINT,
+                COALESCE(SUM(step - 1) FILTER (WHERE step > 1), 0)::BIGINT
+            FROM (
+                SELECT id - LAG(id) OVER (ORDER BY id) AS step
+                FROM {}
+                WHERE id BETWEEN $1 AND $2
+            ) AS steps;
+        "#,
+        get_table_name(exchange, ticker)
+    );
+
+    let (gaps, missing): (i64, i64) = sqlx::query_as(&query)
+        .bind(first_tick_id.min(i64::MAX as u64) as i64)
+        .bind(last_tick_id.min(i64::MAX as u64) as i64)
+        .fetch_one(&db_pool)
+        .await
+        .map_err(|e| DbError::QueryFailed(format!(
+            "Couldn't look for gaps in {} {}: {}", exchange, ticker, e
+        )))?;
+
+    Ok((gaps.max(0) as u64, missing.max(0) as u64))
+}
+
+
+type BatchRow = (i64, String, String, i64, i64, i64, i64, i64);
+/// A `BatchRow` led by the batch ID
+type IdBatchRow = (i64, i64, String, String, i64, i64, i64, i64, i64);
+
+impl From<BatchRow> for IngestBatch {
+    fn from(row: BatchRow) -> Self {
+        let (
+            job_id, 
+            exchange, 
+            asset, 
+            first_tick_id, 
+            last_tick_id, 
+            tick_count, 
+            prev_next_tick_id, 
+            prev_cursor_ns
+        ) = row;
+
+        IngestBatch {
+            job_id,
+            exchange,
+            asset,
+            first_tick_id: first_tick_id as u64,
+            last_tick_id: last_tick_id as u64,
+            tick_count: tick_count as u64,
+            prev_next_tick_id: prev_next_tick_id as u64,
+            prev_cursor_ns: prev_cursor_ns as u64,
+        }
+    }
+}
@@ -0,0 +1,472 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH}
+};
+
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres, QueryBuilder};
+
+use timestamp_tools::db_timestamp_to_date_string;
+
+use crate::{DbError, fetch_tables, get_table_name, kraken::tick_table_query};
+
+
+/// Extension of pair files
+pub const PAIR_FILE_EXTENSION: &str = "dtpack";
+
+/// Written as the header's `format`, so other gzipped JSON isn't taken for
+/// a pair file
+const PAIR_FILE_FORMAT: &str = "dtpack";
+
+/// Bumped whenever the layout of a pair file changes
+const PAIR_FILE_VERSION: u32 = 1;
+
+/// Ticks read from the database per query when exporting
+const EXPORT_PAGE_SIZE: i64 = 50_000;
+
+/// Ticks inserted per statement when importing. Seven parameters each,
+/// which keeps a statement well under Postgres's 65535 parameter limit.
+const IMPORT_CHUNK_SIZE: usize = 5_000;
+
+
+/// First line of a pair file: everything needed to recreate the pair's
+/// table and download position, and to check the ticks that follow
+///
+/// A pair file is gzipped JSON lines. The header is followed by one line per
+/// tick, oldest first, in the form
+/// `[id, "price", "volume", time, "buy_sell", "market_limit", "misc"]`.
+/// Prices and volumes are kept as text so no decimals are lost.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairFileHeader {
+    pub format: String,
+    pub version: u32,
+    pub exchange: String,
+    pub asset: String,
+    /// Unix timestamp in seconds
+    pub exported_at: u64,
+    /// `(precision, scale)` of the price column
+    pub price_decimals: (u32, u32),
+    /// `(precision, scale)` of the volume column
+    pub volume_decimals: (u32, u32),
+    /// The pair's checkpoint in `_last_tick_history`
+    pub next_tick_id: u64,
+    pub cursor_ns: u64,
+    pub tick_count: u64,
+    /// Microsecond timestamps of the first and last tick
+    pub first_tick_time: Option<i64>,
+    pub last_tick_time: Option<i64>,
+}
+
+impl PairFileHeader {
+
+    /// The dates of the first and last tick, e.g. for summaries
+    pub fn date_range(&self) -> String {
+        match (self.first_tick_time, self.last_tick_time) {
+            (Some(first), Some(last)) => format!(
+                "{} to {}",
+                db_timestamp_to_date_string(first as u64),
+                db_timestamp_to_date_string(last as u64)
+            ),
+            _ => "no ticks".to_string()
+        }
+    }
+}
+
+type TickRow = (i64, String, String, i64, String, String, Option<String>);
+
+
+/// Writes a pair's ticks, table schema and download checkpoint to a
+/// compressed pair file at `path`
+///
+/// The ticks are read in one repeatable-read transaction, so a download
+/// running alongside can't leave the file with a checkpoint that doesn't
+/// match its ticks.
+pub async fn export_pair(
+    exchange: &str,
+    ticker: &str,
+    path: &Path,
+    db_pool: PgPool
+) -> Result<PairFileHeader, DbError> {
+
+    if exchange != "kraken" {
+        return Err(DbError::PairFile(
+            format!("Can't export pairs from {}", exchange)
+        ))
+    };
+
+    let asset: String = ticker.to_uppercase();
+    let table_name: String = get_table_name(exchange, &asset);
+
+    let mut tx = db_pool
+        .begin()
+        .await
+        .map_err(|_| DbError::ConnectionFailed)?;
+
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ;")
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Couldn't start export snapshot: {}", e)
+        ))?;
+
+    let schema_query: &'static str = r#"
+        SELECT 
+            column_name::TEXT, 
+            numeric_precision::INT, 
+            numeric_scale::INT
+        FROM information_schema.columns
+        WHERE table_schema = 'public'
+            AND table_name = $1
+            AND column_name IN ('price', 'volume');
+    "#;
+
+    let columns: Vec<(String, Option<i32>, Option<i32>)> = sqlx::query_as(
+        schema_query
+    )
+        .bind(&table_name)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Couldn't read the schema of {}: {}", table_name, e)
+        ))?;
+
+    let decimals = |name: &str| -> Result<(u32, u32), DbError> {
+        columns
+            .iter()
+            .find(|(column, _, _)| column == name)
+            .and_then(|(_, precision, scale)| Some((
+                u32::try_from((*precision)?).ok()?,
+                u32::try_from((*scale)?).ok()?
+            )))
+            .ok_or(DbError::PairFile(
+                format!("{} {} isn't in the database", exchange, asset)
+            ))
+    };
+
+    let price_decimals: (u32, u32) = decimals("price")?;
+    let volume_decimals: (u32, u32) = decimals("volume")?;
+
+    let (next_tick_id, cursor_ns): (i64, i64) = sqlx::query_as(
+        "SELECT next_tick_id, cursor_ns FROM _last_tick_history \
+        WHERE asset = $1;"
+    )
+        .bind(&asset)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Couldn't fetch the checkpoint of {}: {}", asset, e)
+        ))?;
+
+    let stats_query: String = format!(
+        "SELECT COUNT(*), MIN(time), MAX(time) FROM {};",
+        table_name
+    );
+
+    let (tick_count, first_tick_time, last_tick_time): (
+        i64,
+        Option<i64>,
+        Option<i64>
+    ) = sqlx::query_as(&stats_query)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Couldn't count the ticks of {}: {}", table_name, e)
+        ))?;
+
+    let header = PairFileHeader {
+        format: PAIR_FILE_FORMAT.to_string(),
+        version: PAIR_FILE_VERSION,
+        exchange: exchange.to_string(),
+        asset: asset.clone(),
+        exported_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        price_decimals,
+        volume_decimals,
+        next_tick_id: next_tick_id as u64,
+        cursor_ns: cursor_ns as u64,
+        tick_count: tick_count as u64,
+        first_tick_time,
+        last_tick_time,
+    };
+
+    let file: File = File::create(path).map_err(|e| DbError::PairFile(
+        format!("Couldn't create {}: {}", path.display(), e)
+    ))?;
+    let mut writer = GzEncoder::new(
+        BufWriter::new(file), 
+        Compression::default()
+    );
+
+    write_line(&mut writer, &header, path)?;
+
+    let page_query: String = format!(
+        r#"
+        SELECT
+            id,
+            price::TEXT,
+            volume::TEXT,
+            time,
+            buy_sell::TEXT,
+            market_limit::TEXT,
+            misc
+        FROM {}
+        WHERE id > $1
+        ORDER BY id
+        LIMIT $2;
+        "#,
+        table_name
+    );
+
+    let mut last_id: i64 = -1;
+
+    loop {
+        let page: Vec<TickRow> = sqlx::query_as(&page_query)
+            .bind(last_id)
+            .bind(EXPORT_PAGE_SIZE)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| DbError::QueryFailed(
+                format!("Couldn't read the ticks of {}: {}", table_name, e)
+            ))?;
+
+        let Some(last) = page.last() else { break };
+        last_id = last.0;
+
+        for tick in &page {
+            write_line(&mut writer, tick, path)?;
+        };
+    };
+
+    writer
+        .finish()
+        .and_then(|mut w| w.flush())
+        .map_err(|e| DbError::PairFile(
+            format!("Couldn't write {}: {}", path.display(), e)
+        ))?;
+
+    // Read-only, so there's nothing to commit
+    tx.rollback().await.ok();
+
+    Ok(header)
+}
+
+
+/// Recreates a pair from a pair file written by `export_pair`
+///
+/// The table, its ticks and the pair's checkpoint are written in one
+/// transaction, so a file that's cut short or doesn't match its header
+/// leaves nothing behind. Fails if the pair is already in the database;
+/// drop it first to replace it.
+pub async fn import_pair(
+    path: &Path,
+    db_pool: PgPool
+) -> Result<PairFileHeader, DbError> {
+
+    let file: File = File::open(path).map_err(|e| DbError::PairFile(
+        format!("Couldn't open {}: {}", path.display(), e)
+    ))?;
+    let mut lines = BufReader::new(GzDecoder::new(file)).lines();
+
+    let header: PairFileHeader = match lines.next() {
+        Some(Ok(line)) => serde_json::from_str(&line).map_err(|e|
+            DbError::PairFile(format!(
+                "{} isn't a pair file: {}",
+                path.display(),
+                e
+            ))
+        )?,
+        Some(Err(e)) => return Err(DbError::PairFile(
+            format!("Couldn't read {}: {}", path.display(), e)
+        )),
+        None => return Err(DbError::PairFile(
+            format!("{} is empty", path.display())
+        ))
+    };
+
+    check_header(&header)?;
+
+    let table_name: String = get_table_name(&header.exchange, &header.asset);
+
+    if fetch_tables(db_pool.clone()).await?.contains(&table_name) {
+        return Err(DbError::PairFile(format!(
+            "{} {} is already in the database. Remove it with \
+            --rm-pairs first to replace it",
+            header.exchange,
+            header.asset
+        )))
+    };
+
+    let mut tx = db_pool
+        .begin()
+        .await
+        .map_err(|_| DbError::ConnectionFailed)?;
+
+    let create_table: String = tick_table_query(
+        &table_name,
+        header.price_decimals,
+        header.volume_decimals
+    );
+
+    sqlx::query(&create_table)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| DbError::TableCreationFailed(
+            format!("{}: {}", table_name, e)
+        ))?;
+
+    // A checkpoint can outlive its table if a drop was interrupted
+    sqlx::query("DELETE FROM _last_tick_history WHERE asset = $1;")
+        .bind(&header.asset)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Couldn't clear the old checkpoint: {}", e)
+        ))?;
+
+    sqlx::query(
+        "INSERT INTO _last_tick_history (asset, next_tick_id, cursor_ns) \
+        VALUES ($1, $2, $3);"
+    )
+        .bind(&header.asset)
+        .bind(header.next_tick_id as i64)
+        .bind(header.cursor_ns as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Couldn't write the checkpoint: {}", e)
+        ))?;
+
+    let mut chunk: Vec<TickRow> = Vec::with_capacity(IMPORT_CHUNK_SIZE);
+    let mut imported: u64 = 0;
+
+    for (i, line) in lines.enumerate() {
+
+        let line: String = line.map_err(|e| DbError::PairFile(
+            format!("Couldn't read {}: {}", path.display(), e)
+        ))?;
+
+        if line.is_empty() { continue };
+
+        // The header is line 1
+        let tick: TickRow = serde_json::from_str(&line).map_err(|e|
+            DbError::PairFile(format!("Bad tick on line {}: {}", i + 2, e))
+        )?;
+        chunk.push(tick);
+
+        if chunk.len() == IMPORT_CHUNK_SIZE {
+            imported += insert_ticks(&table_name, &chunk, &mut tx).await?;
+            chunk.clear();
+        };
+    };
+
+    if !chunk.is_empty() {
+        imported += insert_ticks(&table_name, &chunk, &mut tx).await?;
+    };
+
+    if imported != header.tick_count {
+        return Err(DbError::PairFile(format!(
+            "{} holds {} ticks but its header says {}",
+            path.display(),
+            imported,
+            header.tick_count
+        )))
+    };
+
+    if tx.commit().await.is_err() {
+        return Err(DbError::QueryFailed(
+            format!("Failed to commit import of {}", header.asset)
+        ));
+    };
+
+    Ok(header)
+}
+
+
+/// Rejects headers this version can't import, and asset names that aren't
+/// safe to put in a table name
+fn check_header(header: &PairFileHeader) -> Result<(), DbError> {
+
+    if header.format != PAIR_FILE_FORMAT {
+        return Err(DbError::PairFile(
+            format!("Unknown file format: {}", header.format)
+        ))
+    };
+
+    if header.version > PAIR_FILE_VERSION {
+        return Err(DbError::PairFile(format!(
+            "File version {} is newer than this version of dtrade \
+            supports ({})",
+            header.version,
+            PAIR_FILE_VERSION
+        )))
+    };
+
+    if header.exchange != "kraken" {
+        return Err(DbError::PairFile(
+            format!("Can't import pairs from {}", header.exchange)
+        ))
+    };
+
+    if header.asset.is_empty()
+        || !header.asset.chars().all(|c| c.is_ascii_alphanumeric())
+    {
+        return Err(DbError::PairFile(
+            format!("Invalid asset name: {}", header.asset)
+        ))
+    };
+
+    Ok(())
+}
+
+fn write_line<T: Serialize>(
+    writer: &mut impl Write,
+    value: &T,
+    path: &Path
+) -> Result<(), DbError> {
+    serde_json::to_writer(&mut *writer, value)
+        .map_err(std::io::Error::from)
+        .and_then(|_| writer.write_all(b"\n"))
+        .map_err(|e| DbError::PairFile(
+            format!("Couldn't write {}: {}", path.display(), e)
+        ))
+}
+
+async fn insert_ticks(
+    table_name: &str,
+    ticks: &[TickRow],
+    tx: &mut sqlx::Transaction<'_, Postgres>
+) -> Result<u64, DbError> {
+
+    let mut query: QueryBuilder<Postgres> = QueryBuilder::new(format!(
+        "INSERT INTO {} \
+        (id, price, volume, time, buy_sell, market_limit, misc) ",
+        table_name
+    ));
+
+    query.push_values(ticks, |mut row, tick| {
+        row.push_bind(tick.0)
+            .push_bind(&tick.1)
+            .push_unseparated("::NUMERIC")
+            .push_bind(&tick.2)
+            .push_unseparated("::NUMERIC")
+            .push_bind(tick.3)
+            .push_bind(&tick.4)
+            .push_bind(&tick.5)
+            .push_bind(&tick.6);
+    });
+    query.push(" ON CONFLICT (id) DO NOTHING;");
+
+    query
+        .build()
+        .execute(&mut **tx)
+        .await
+        .map(|r| r.rows_affected())
+        .map_err(|e| DbError::QueryFailed(
+            format!("Failed to insert ticks into {}: {}", table_name, e)
+        ))
+}
@@ -0,0 +1,121 @@
+use tokio::{
+    sync::Mutex,
+    time::{sleep, Duration, Instant}
+};
+
+
+/// Token bucket rate limiter
+///
+/// One limiter is shared by every request made to an exchange, so concurrent
+/// downloads draw from the same budget instead of each task pacing itself.
+/// Tokens refill continuously at `refill_per_sec` up to `capacity`, and
+/// `acquire` waits until a token is available.
+pub struct RateLimiter {
+    capacity: f64,
+    refill_per_sec: f64,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+
+    pub fn new(capacity: u32, refill_per_sec: f64) -> Self {
+
+        let capacity = capacity.max(1) as f64;
+
+        RateLimiter {
+            capacity,
+            refill_per_sec,
+            bucket: Mutex::new(Bucket {
+                tokens: capacity,
+                last_refill: Instant::now()
+            }),
+        }
+    }
+
+    /// Waits for, then consumes, a single token
+    pub async fn acquire(&self) {
+
+        loop {
+
+            let wait_secs: f64 = {
+
+                let mut bucket = self.bucket.lock().await;
+
+                let now = Instant::now();
+                let elapsed = now
+                    .duration_since(bucket.last_refill)
+                    .as_secs_f64();
+
+                bucket.tokens = (
+                    bucket.tokens + elapsed * self.refill_per_sec
+                ).min(self.capacity);
+                bucket.last_refill = now;
+
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return
+                };
+
+                (1.0 - bucket.tokens) / self.refill_per_sec
+            };
+
+            sleep(Duration::from_secs_f64(wait_secs)).await;
+
+        }
+    }
+}
+
+
+// -------------------------- UNIT TESTING --------------------------------- //
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[tokio::test]
+    async fn burst_then_wait_test() {
+
+        let limiter = RateLimiter::new(2, 10.0);
+        let started = Instant::now();
+
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert!(started.elapsed() < Duration::from_millis(50));
+
+        // The bucket is empty, so the third waits a tenth of a second
+        limiter.acquire().await;
+        assert!(started.elapsed() >= Duration::from_millis(90));
+    }
+
+    #[tokio::test]
+    async fn refill_is_capped_test() {
+
+        let limiter = RateLimiter::new(1, 10.0);
+        limiter.acquire().await;
+
+        // Long enough for three tokens, but the bucket only holds one
+        sleep(Duration::from_millis(300)).await;
+        let started = Instant::now();
+
+        limiter.acquire().await;
+        assert!(started.elapsed() < Duration::from_millis(50));
+
+        limiter.acquire().await;
+        assert!(started.elapsed() >= Duration::from_millis(90));
+    }
+
+    #[tokio::test]
+    async fn zero_capacity_holds_one_test() {
+
+        let limiter = RateLimiter::new(0, 10.0);
+        let started = Instant::now();
+
+        limiter.acquire().await;
+        assert!(started.elapsed() < Duration::from_millis(50));
+    }
+}
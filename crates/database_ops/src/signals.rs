@@ -0,0 +1,140 @@
+use std::time::Duration;
+
+use serde::Serialize;
+use sqlx::PgPool;
+
+use crate::DbError;
+
+
+/// Side table recording every strategy signal published, one row per order
+///
+/// `close_time` is the Unix timestamp (seconds) of the candle the order was
+/// placed on.
+pub(crate) const CREATE_SIGNAL_HISTORY_TABLE: &str = r#"
+    CREATE TABLE IF NOT EXISTS _signal_history (
+        id BIGSERIAL PRIMARY KEY,
+        exchange VARCHAR(16) NOT NULL,
+        asset VARCHAR(12) NOT NULL,
+        period VARCHAR(8) NOT NULL,
+        strategy TEXT NOT NULL,
+        side VARCHAR(4) NOT NULL,
+        kind VARCHAR(6) NOT NULL,
+        limit_price DOUBLE PRECISION,
+        volume DOUBLE PRECISION NOT NULL,
+        close_time BIGINT NOT NULL,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    );
+"#;
+
+
+/// An order a strategy placed on a closed candle
+#[derive(Debug, Clone)]
+pub struct Signal {
+    pub exchange: String,
+    pub ticker: String,
+    pub period: String,
+    /// The strategy's spec, e.g. `sma-cross(20,50)`
+    pub strategy: String,
+    pub side: String,
+    /// `market` or `limit`
+    pub kind: String,
+    pub limit: Option<f64>,
+    pub volume: f64,
+    pub close_time: i64,
+}
+
+/// Records `signal` once it's been published
+pub async fn record_signal(
+    db_pool: PgPool,
+    signal: &Signal
+) -> Result<(), DbError> {
+
+    let query: &'static str = r#"
+        INSERT INTO _signal_history (
+            exchange,
+            asset,
+            period,
+            strategy,
+            side,
+            kind,
+            limit_price,
+            volume,
+            close_time
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9);
+    "#;
+
+    sqlx::query(query)
+        .bind(signal.exchange.to_lowercase())
+        .bind(signal.ticker.to_uppercase())
+        .bind(&signal.period)
+        .bind(&signal.strategy)
+        .bind(&signal.side)
+        .bind(&signal.kind)
+        .bind(signal.limit)
+        .bind(signal.volume)
+        .bind(signal.close_time)
+        .execute(&db_pool)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Failed to record the signal: {}", e)
+        ))?;
+
+    Ok(())
+}
+
+
+/// Signals one strategy placed on one pair and period
+#[derive(Debug, Clone, Serialize)]
+pub struct SignalCount {
+    pub exchange: String,
+    pub ticker: String,
+    pub period: String,
+    pub strategy: String,
+    pub buys: u64,
+    pub sells: u64,
+}
+
+/// Signals recorded in the last `window`, by pair, period and strategy
+pub async fn signal_counts(
+    window: Duration,
+    db_pool: PgPool
+) -> Result<Vec<SignalCount>, DbError> {
+
+    let query: &'static str = r#"
+        SELECT
+            exchange,
+            asset,
+            period,
+            strategy,
+            COUNT(*) FILTER (WHERE side = 'buy')::BIGINT,
+            COUNT(*) FILTER (WHERE side <> 'buy')::BIGINT
+        FROM _signal_history
+        WHERE created_at > NOW() - make_interval(secs => $1)
+        GROUP BY exchange, asset, period, strategy
+        ORDER BY exchange, asset, period, strategy;
+    "#;
+
+    let rows = sqlx::query_as::<
+        _, (String, String, String, String, i64, i64)
+    >(query)
+        .bind(window.as_secs_f64())
+        .fetch_all(&db_pool)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Couldn't count the signals: {}", e)
+        ))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(exchange, ticker, period, strategy, buys, sells)| {
+            SignalCount {
+                exchange,
+                ticker,
+                period,
+                strategy,
+                buys: buys.max(0) as u64,
+                sells: sells.max(0) as u64,
+            }
+        })
+        .collect())
+}
@@ -0,0 +1,173 @@
+use std::fmt;
+
+use serde::Serialize;
+use sqlx::PgPool;
+
+use timestamp_tools::{db_timestamp_to_date_string, get_current_unix_timestamp};
+
+use crate::{
+    PairStats,
+    fetch_exchanges_and_pairs_from_db,
+    get_table_name
+};
+
+
+/// Size and freshness of one asset table
+///
+/// Tick times are in the table's microsecond format, and are `None` when
+/// the table is empty.
+#[derive(Debug, Clone, Serialize)]
+pub struct TableStats {
+    pub exchange: String,
+    pub ticker: String,
+    pub table: String,
+    /// The planner's estimate once the table has been analyzed, otherwise
+    /// counted, so shown as approximate
+    pub rows: u64,
+    /// Table, index and TOAST size on disk
+    pub size_bytes: u64,
+    pub first_tick: Option<u64>,
+    pub last_tick: Option<u64>,
+    /// Seconds between the last tick and when the stats were read
+    pub lag_secs: Option<u64>,
+}
+
+/// Stats of every asset table, sorted by exchange and ticker
+#[derive(Debug, Clone, Serialize)]
+pub struct DatabaseStats {
+    /// Unix timestamp (seconds) the stats were read at
+    pub generated_at: u64,
+    pub tables: Vec<TableStats>,
+}
+
+impl DatabaseStats {
+
+    pub fn total_rows(&self) -> u64 {
+        self.tables.iter().map(|t| t.rows).sum()
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.tables.iter().map(|t| t.size_bytes).sum()
+    }
+}
+
+/// e.g. "512 B", "3.4 MB" or "1.2 GB"
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    let mut size: f64 = bytes as f64;
+    let mut unit: &str = "B";
+    for u in UNITS {
+        if size < 1024.0 { break };
+        size /= 1024.0;
+        unit = u;
+    };
+    match unit {
+        "B" => format!("{bytes} B"),
+        _ => format!("{size:.1} {unit}")
+    }
+}
+
+/// e.g. "45s", "12m", "5h 3m" or "2d 4h"
+fn format_lag(secs: u64) -> String {
+    match secs {
+        s if s < 60 => format!("{s}s"),
+        s if s < 3_600 => format!("{}m", s / 60),
+        s if s < 86_400 => format!("{}h {}m", s / 3_600, s % 3_600 / 60),
+        s => format!("{}d {}h", s / 86_400, s % 86_400 / 3_600)
+    }
+}
+
+fn format_date(time: Option<u64>) -> String {
+    match time {
+        Some(t) => db_timestamp_to_date_string(t),
+        None => "-".to_string()
+    }
+}
+
+impl fmt::Display for DatabaseStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+
+        writeln!(f, "\x1b[1;36mDatabase Stats:\x1b[0m")?;
+
+        if self.tables.is_empty() {
+            return writeln!(f, "  No asset tables")
+        };
+
+        let width: usize = self.tables
+            .iter()
+            .map(|t| t.table.len())
+            .max()
+            .unwrap_or(0)
+            .max("Table".len());
+
+        writeln!(
+            f,
+            "  \x1b[33m{:<width$}  {:>12}  {:>9}  {:<19}  {:<19}  {:>8}\x1b[0m",
+            "Table", "~Rows", "Size", "First tick", "Last tick", "Lag"
+        )?;
+
+        for t in &self.tables {
+            writeln!(
+                f,
+                "  {:<width$}  {:>12}  {:>9}  {:<19}  {:<19}  {:>8}",
+                t.table,
+                t.rows,
+                format_size(t.size_bytes),
+                format_date(t.first_tick),
+                format_date(t.last_tick),
+                t.lag_secs.map_or("-".to_string(), format_lag)
+            )?;
+        };
+
+        writeln!(
+            f,
+            "  {:<width$}  {:>12}  {:>9}",
+            "Total",
+            self.total_rows(),
+            format_size(self.total_bytes())
+        )
+    }
+}
+
+
+/// Reads the stats of every asset table
+///
+/// Tables whose stats can't be read are left out.
+pub async fn database_stats(db_pool: PgPool) -> DatabaseStats {
+
+    let now: u64 = get_current_unix_timestamp();
+
+    let mut tables: Vec<TableStats> = Vec::new();
+
+    for (exchange, tickers) in fetch_exchanges_and_pairs_from_db(
+        db_pool,
+        true
+    ).await {
+        for (ticker, stats) in tickers {
+
+            let Some(PairStats { 
+                approx_ticks, 
+                first_time, 
+                last_time, 
+                size_bytes 
+            }) = stats else {
+                continue
+            };
+
+            tables.push(TableStats {
+                table: get_table_name(&exchange, &ticker),
+                exchange: exchange.to_lowercase(),
+                ticker,
+                rows: approx_ticks,
+                size_bytes,
+                first_tick: first_time,
+                last_tick: last_time,
+                lag_secs: last_time.map(|t| now.saturating_sub(t / 1_000_000)),
+            });
+        };
+    };
+
+    tables.sort_by(|a, b| a.table.cmp(&b.table));
+
+    DatabaseStats { generated_at: now, tables }
+}
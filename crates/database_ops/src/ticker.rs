@@ -0,0 +1,123 @@
+use std::{fmt, ops::Deref, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::connection::get_table_name;
+
+
+/// Longest ticker accepted, well inside Postgres' 63 byte limit on table
+/// names once `asset_<exchange>_` is added
+pub const MAX_TICKER_LEN: usize = 20;
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TickerError {
+    Empty,
+    TooLong(String),
+    InvalidChar(String, char),
+}
+
+impl fmt::Display for TickerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TickerError::Empty => write!(
+                f, "TickerError::Empty: no ticker given"
+            ),
+            TickerError::TooLong(t) => write!(
+                f,
+                "TickerError::TooLong: {} is over {} characters",
+                t,
+                MAX_TICKER_LEN
+            ),
+            TickerError::InvalidChar(t, c) => write!(
+                f,
+                "TickerError::InvalidChar: {:?} in {}, only letters and \
+                digits are allowed",
+                c,
+                t
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TickerError {}
+
+
+// --------------------------------- TICKER -------------------------------- //
+/// An asset pair's ticker, e.g. `XBTUSD`, checked and uppercased once when
+/// it's parsed
+///
+/// Only ASCII letters and digits get through, so a ticker is always safe to
+/// put in a table name. Derefs to `&str`, and (de)serializes as a plain
+/// string, rejecting invalid ones.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Ticker(String);
+
+impl Ticker {
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The table holding the ticker's ticks on `exchange`
+    pub fn table_name(&self, exchange: &str) -> String {
+        get_table_name(exchange, &self.0)
+    }
+}
+
+impl FromStr for Ticker {
+    type Err = TickerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+
+        let s: &str = s.trim();
+
+        if s.is_empty() {
+            return Err(TickerError::Empty)
+        };
+        if s.len() > MAX_TICKER_LEN {
+            return Err(TickerError::TooLong(s.to_string()))
+        };
+        if let Some(c) = s.chars().find(|c| !c.is_ascii_alphanumeric()) {
+            return Err(TickerError::InvalidChar(s.to_string(), c))
+        };
+
+        Ok(Ticker(s.to_ascii_uppercase()))
+    }
+}
+
+impl TryFrom<String> for Ticker {
+    type Error = TickerError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Ticker> for String {
+    fn from(ticker: Ticker) -> Self {
+        ticker.0
+    }
+}
+
+impl Deref for Ticker {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Ticker {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Ticker {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
@@ -0,0 +1,290 @@
+use std::{collections::HashMap, fmt};
+
+use serde::Serialize;
+use sqlx::PgPool;
+
+use crate::{DbError, fetch_exchanges_and_pairs_from_db, kraken};
+
+
+/// Side table counting the requests sent to each exchange, by the hour
+pub(crate) const CREATE_API_USAGE_TABLE: &str = r#"
+    CREATE TABLE IF NOT EXISTS _api_requests (
+        exchange VARCHAR(16) NOT NULL,
+        hour TIMESTAMPTZ NOT NULL,
+        requests BIGINT NOT NULL,
+        PRIMARY KEY (exchange, hour)
+    );
+"#;
+
+/// Days of request counts the report looks back over
+const WINDOW_DAYS: u64 = 30;
+
+/// Share of an exchange's monthly request budget past which the report warns
+const WARN_SHARE: f64 = 0.8;
+
+
+/// Adds the requests counted since the last call to the current hour's row
+///
+/// When the counts can't be saved they're kept for the next call.
+pub async fn flush_request_counts(db_pool: PgPool) -> Result<(), DbError> {
+
+    let requests: u64 = kraken::take_request_count();
+    if requests == 0 {
+        return Ok(())
+    };
+
+    let query: &'static str = r#"
+        INSERT INTO _api_requests (exchange, hour, requests)
+        VALUES ($1, date_trunc('hour', NOW()), $2)
+        ON CONFLICT (exchange, hour)
+        DO UPDATE SET requests = _api_requests.requests + EXCLUDED.requests;
+    "#;
+
+    if let Err(e) = sqlx::query(query)
+        .bind("kraken")
+        .bind(requests as i64)
+        .execute(&db_pool)
+        .await
+    {
+        kraken::restore_request_count(requests);
+        return Err(DbError::QueryFailed(
+            format!("Failed to save request counts: {}", e)
+        ))
+    };
+
+    Ok(())
+}
+
+
+/// One exchange's request counts and where they're heading
+#[derive(Debug, Clone, Serialize)]
+pub struct ExchangeUsage {
+    pub exchange: String,
+    /// Requests per day as `(YYYY-MM-DD, requests)`, oldest first
+    pub daily: Vec<(String, u64)>,
+    /// Days since the first count in the window, at least 1
+    pub days_observed: f64,
+    pub requests: u64,
+    /// Update runs that downloaded from the exchange, from `_job_history`
+    pub updates: u64,
+    /// Pairs those runs downloaded
+    pub pairs_updated: u64,
+    /// Pairs stored now
+    pub pairs: u64,
+    /// Requests a month at the observed cadence, scaled up to every stored
+    /// pair
+    pub monthly_estimate: u64,
+    /// Requests a month the exchange's rate limit allows
+    pub monthly_limit: u64,
+}
+
+impl ExchangeUsage {
+
+    pub fn share(&self) -> f64 {
+        self.monthly_estimate as f64 / self.monthly_limit.max(1) as f64
+    }
+
+    pub fn updates_per_day(&self) -> f64 {
+        self.updates as f64 / self.days_observed
+    }
+
+    /// Whether the estimate leaves some headroom under the limit
+    pub fn within_limits(&self) -> bool {
+        self.share() <= WARN_SHARE
+    }
+}
+
+/// Estimated API usage of every exchange with requests on record
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiUsageReport {
+    pub exchanges: Vec<ExchangeUsage>,
+}
+
+impl fmt::Display for ApiUsageReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+
+        writeln!(f, "\x1b[1;36mAPI Usage (last {} days):\x1b[0m", WINDOW_DAYS)?;
+
+        if self.exchanges.is_empty() {
+            return writeln!(f, "  No requests recorded yet")
+        };
+
+        for usage in &self.exchanges {
+
+            let color: &str = match (usage.within_limits(), usage.share()) {
+                (true, _) => "\x1b[32m",
+                (false, s) if s <= 1.0 => "\x1b[33m",
+                _ => "\x1b[31m",
+            };
+
+            writeln!(f, "  \x1b[1;33m{}\x1b[0m", usage.exchange)?;
+            for (day, requests) in &usage.daily {
+                writeln!(f, "    {}: {}", day, requests)?;
+            };
+            writeln!(
+                f,
+                "    {} requests over {:.1} days, {:.1} updates a day \
+                ({} of {} pairs)",
+                usage.requests,
+                usage.days_observed,
+                usage.updates_per_day(),
+                usage.pairs_updated,
+                usage.pairs
+            )?;
+            writeln!(
+                f,
+                "    Monthly estimate: {}{} of {} ({:.0}%)\x1b[0m",
+                color,
+                usage.monthly_estimate,
+                usage.monthly_limit,
+                usage.share() * 100.0
+            )?;
+
+            if !usage.within_limits() {
+                writeln!(
+                    f,
+                    "    {}Warning: at this cadence, updates will run into \
+                    {}'s rate limit. Update less often or track fewer \
+                    pairs\x1b[0m",
+                    color,
+                    usage.exchange
+                )?;
+            };
+        };
+        Ok(())
+    }
+}
+
+/// Builds the usage report from `_api_requests` and `_job_history`
+///
+/// The estimate takes the average requests per day over the last
+/// `WINDOW_DAYS` days, scales it from the pairs that were updated to every
+/// stored pair, and compares a month of it with what the exchange's rate
+/// limit allows.
+pub async fn api_usage_report(
+    db_pool: PgPool
+) -> Result<ApiUsageReport, DbError> {
+
+    flush_request_counts(db_pool.clone()).await?;
+
+    let daily_query: String = format!(r#"
+        SELECT
+            exchange,
+            to_char(date_trunc('day', hour), 'YYYY-MM-DD') AS day,
+            SUM(requests)::BIGINT
+        FROM _api_requests
+        WHERE hour >= NOW() - INTERVAL '{WINDOW_DAYS} days'
+        GROUP BY exchange, day
+        ORDER BY exchange, day;
+    "#);
+
+    let daily: Vec<(String, String, i64)> = sqlx::query_as(&daily_query)
+        .fetch_all(&db_pool)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Couldn't fetch request counts: {}", e)
+        ))?;
+
+    let observed_query: String = format!(r#"
+        SELECT
+            exchange,
+            EXTRACT(EPOCH FROM NOW() - MIN(hour))::DOUBLE PRECISION
+        FROM _api_requests
+        WHERE hour >= NOW() - INTERVAL '{WINDOW_DAYS} days'
+        GROUP BY exchange;
+    "#);
+
+    let observed: HashMap<String, f64> = sqlx::query_as::<_, (String, f64)>(
+        &observed_query
+    )
+        .fetch_all(&db_pool)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Couldn't fetch request history: {}", e)
+        ))?
+        .into_iter()
+        .collect();
+
+    let jobs_query: String = format!(r#"
+        SELECT exchange, COUNT(DISTINCT job_id), COUNT(DISTINCT asset)
+        FROM _job_history
+        WHERE finished_at >= NOW() - INTERVAL '{WINDOW_DAYS} days'
+        GROUP BY exchange;
+    "#);
+
+    let jobs: HashMap<String, (i64, i64)> = sqlx::query_as::<
+        _, 
+        (String, i64, i64)
+    >(&jobs_query)
+        .fetch_all(&db_pool)
+        .await
+        .map_err(|e| DbError::QueryFailed(
+            format!("Couldn't fetch job history: {}", e)
+        ))?
+        .into_iter()
+        .map(|(exchange, updates, pairs)| (exchange, (updates, pairs)))
+        .collect();
+
+    let stored_pairs: HashMap<String, u64> = 
+        fetch_exchanges_and_pairs_from_db(db_pool, false)
+        .await
+        .into_iter()
+        .map(|(exchange, tickers)| {
+            (exchange.to_lowercase(), tickers.len() as u64)
+        })
+        .collect();
+
+    let mut exchanges: Vec<ExchangeUsage> = Vec::new();
+
+    for (exchange, day, requests) in daily {
+
+        if exchanges.last().is_none_or(|u| u.exchange != exchange) {
+
+            let (updates, pairs_updated) = jobs
+                .get(&exchange)
+                .copied()
+                .unwrap_or((0, 0));
+
+            exchanges.push(ExchangeUsage {
+                days_observed: (observed
+                    .get(&exchange)
+                    .copied()
+                    .unwrap_or(0.0) / 86_400.0)
+                    .max(1.0),
+                requests: 0,
+                daily: Vec::new(),
+                updates: updates as u64,
+                pairs_updated: pairs_updated as u64,
+                pairs: stored_pairs.get(&exchange).copied().unwrap_or(0),
+                monthly_estimate: 0,
+                monthly_limit: monthly_limit(&exchange),
+                exchange,
+            });
+        };
+
+        if let Some(usage) = exchanges.last_mut() {
+            usage.requests += requests.max(0) as u64;
+            usage.daily.push((day, requests.max(0) as u64));
+        };
+    };
+
+    for usage in exchanges.iter_mut() {
+        let per_day: f64 = usage.requests as f64 / usage.days_observed;
+        let scale: f64 = match usage.pairs_updated {
+            0 => 1.0,
+            n => (usage.pairs as f64 / n as f64).max(1.0)
+        };
+        usage.monthly_estimate = (per_day * scale * 30.0).round() as u64;
+    };
+
+    Ok(ApiUsageReport { exchanges })
+}
+
+/// Requests a 30-day month of `exchange`'s rate limit allows
+fn monthly_limit(exchange: &str) -> u64 {
+    let per_sec: f64 = match exchange {
+        "kraken" => kraken::REQUESTS_PER_SEC,
+        _ => 0.0
+    };
+    (per_sec * 86_400.0 * 30.0) as u64
+}
@@ -1 +1,436 @@
+use std::collections::VecDeque;
 
+use num_traits::ToPrimitive;
+
+use bars::{Bar, BarSeries};
+
+pub mod moving_averages;
+pub mod oscillators;
+pub mod volatility;
+pub mod registry;
+pub use moving_averages::{Ema, Sma};
+pub use oscillators::{Macd, Rsi};
+pub use volatility::{Atr, BollingerBands};
+pub use registry::IndicatorRegistry;
+
+
+#[derive(Debug)]
+pub enum IndicatorError {
+    UnknownIndicator(String),
+    InvalidParameters(String),
+}
+
+impl std::fmt::Display for IndicatorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            IndicatorError::UnknownIndicator(e) => write!(
+                f, "IndicatorError::UnknownIndicator: {}", e),
+            IndicatorError::InvalidParameters(e) => write!(
+                f, "IndicatorError::InvalidParameters: {}", e),
+        }
+    }
+}
+
+
+/// One output line of an indicator, aligned index-for-index with the bars
+///
+/// Values are `None` while the indicator is still warming up.
+#[derive(Debug, Clone)]
+pub struct IndicatorLine {
+    pub name: String,
+    pub values: Vec<Option<f64>>,
+}
+
+impl IndicatorLine {
+
+    pub fn new(name: &str, values: Vec<Option<f64>>) -> Self {
+        IndicatorLine { name: name.to_string(), values }
+    }
+
+    /// The most recent value, if the indicator has warmed up
+    pub fn last(&self) -> Option<f64> {
+        self.values.last().copied().flatten()
+    }
+}
+
+
+pub trait Indicator {
+
+    /// Display name including parameters, e.g. `sma(20)`
+    fn name(&self) -> String;
+
+    /// Names of the output lines, in the order `update` returns them
+    fn line_names(&self) -> Vec<&'static str>;
+
+    /// Computes every output line over the full series
+    ///
+    /// This starts from a fresh state and leaves any state built up by
+    /// `update` untouched.
+    fn compute(&self, series: &BarSeries) -> Vec<IndicatorLine>;
+
+    /// Feeds the next completed bar, returning the new value of each line
+    fn update(&mut self, bar: &Bar) -> Vec<Option<f64>>;
+
+    /// The values `update` would return for `bar` without committing it,
+    /// used to refresh indicators on the bar that's still forming
+    fn preview(&self, bar: &Bar) -> Vec<Option<f64>>;
+
+    /// Discards everything fed in through `update`
+    fn reset(&mut self);
+
+    /// Bars `update` needs before every line has a value
+    fn warmup_period(&self) -> usize;
+
+    /// Whether every line had a value on the last `update`. Until then,
+    /// callers shouldn't act on partial output.
+    ///
+    /// A stretch without trades as long as the warmup period, see
+    /// `Warmup`, starts the indicator over, so it's cold again until it
+    /// has seen that many bars after the gap.
+    fn is_warm(&self) -> bool;
+}
+
+
+// ------------------------------- HELPERS --------------------------------- //
+/// Batch computation in terms of `update`, so both paths always agree
+pub(crate) fn compute_by_updates<I>(indicator: &I, series: &BarSeries)
+    -> Vec<IndicatorLine>
+where
+    I: Indicator + Clone
+{
+    let mut fresh: I = indicator.clone();
+    fresh.reset();
+
+    let names = fresh.line_names();
+    let mut values: Vec<Vec<Option<f64>>> = names
+        .iter()
+        .map(|_| Vec::with_capacity(series.len()))
+        .collect();
+
+    for bar in series {
+        for (line, value) in values.iter_mut().zip(fresh.update(bar)) {
+            line.push(value);
+        };
+    };
+
+    names
+        .into_iter()
+        .zip(values)
+        .map(|(name, values)| IndicatorLine::new(name, values))
+        .collect()
+}
+
+pub(crate) fn to_f64<T: ToPrimitive>(value: &T) -> f64 {
+    value.to_f64().unwrap_or(f64::NAN)
+}
+
+pub(crate) fn check_period(name: &str, period: usize)
+    -> Result<(), IndicatorError>
+{
+    if period == 0 {
+        return Err(IndicatorError::InvalidParameters(
+            format!("{} period must be greater than zero", name)
+        ))
+    };
+    Ok(())
+}
+
+
+/// Watches the bars fed to an indicator for stretches without trades, and
+/// remembers whether its last output was complete
+///
+/// Bars with no ticks, the flat ones `BarSeries::fill_gaps` inserts, count
+/// as time without trades just like missing bars. Once such a stretch is
+/// as long as the warmup period, nothing the indicator remembers is worth
+/// keeping, and it starts over.
+#[derive(Debug, Clone)]
+pub struct Warmup {
+    needed: usize,
+    /// Close of the last bar with trades, in microseconds
+    last_traded: Option<i64>,
+    warm: bool,
+}
+
+impl Warmup {
+
+    pub fn new(needed: usize) -> Self {
+        Warmup { needed, last_traded: None, warm: false }
+    }
+
+    /// Whether `bar` ends a stretch without trades of at least `needed`
+    /// bars, or is part of one, going by the bar's own length
+    pub fn is_gap(&mut self, bar: &Bar) -> bool {
+
+        let open: i64 = bar.open_date().timestamp_micros();
+        let close: i64 = bar.close_date().timestamp_micros();
+        let traded: bool = bar.num_ticks() > 0;
+
+        let quiet_since: Option<i64> = self.last_traded.map(|t| match traded {
+            true => open - t,
+            false => close - t
+        });
+
+        if traded {
+            self.last_traded = Some(close);
+        };
+
+        // Bars of a single tick have no length to measure gaps by
+        match (quiet_since, close - open) {
+            (_, length) if length <= 0 => !traded,
+            (Some(quiet), length) => quiet >= length * self.needed as i64,
+            (None, _) => !traded
+        }
+    }
+
+    /// Notes whether every line of an update had a value, and passes the
+    /// values on
+    pub(crate) fn record(&mut self, values: Vec<Option<f64>>)
+        -> Vec<Option<f64>>
+    {
+        self.warm = values.iter().all(Option::is_some);
+        values
+    }
+
+    pub fn needed(&self) -> usize {
+        self.needed
+    }
+
+    pub fn is_warm(&self) -> bool {
+        self.warm
+    }
+
+    pub fn reset(&mut self) {
+        self.last_traded = None;
+        self.warm = false;
+    }
+}
+
+
+/// The last `period` values and their running sum
+#[derive(Debug, Clone)]
+pub(crate) struct RollingWindow {
+    period: usize,
+    values: VecDeque<f64>,
+    sum: f64,
+}
+
+impl RollingWindow {
+
+    pub(crate) fn new(period: usize) -> Self {
+        RollingWindow { period, values: VecDeque::with_capacity(period), sum: 0.0 }
+    }
+
+    /// Adds a value, returning the window mean once it is full
+    pub(crate) fn push(&mut self, value: f64) -> Option<f64> {
+
+        self.values.push_back(value);
+        self.sum += value;
+
+        if self.values.len() > self.period
+            && let Some(oldest) = self.values.pop_front()
+        {
+            self.sum -= oldest;
+        };
+
+        self.mean()
+    }
+
+    pub(crate) fn mean(&self) -> Option<f64> {
+        match self.values.len() == self.period {
+            true => Some(self.sum / self.period as f64),
+            false => None
+        }
+    }
+
+    pub(crate) fn values(&self) -> impl Iterator<Item = &f64> {
+        self.values.iter()
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.values.clear();
+        self.sum = 0.0;
+    }
+}
+
+
+/// Exponential smoothing seeded with the SMA of the first `period` values
+#[derive(Debug, Clone)]
+pub(crate) struct Smoother {
+    period: usize,
+    alpha: f64,
+    seed_sum: f64,
+    seen: usize,
+    current: Option<f64>,
+}
+
+impl Smoother {
+
+    /// Standard EMA smoothing, `2 / (period + 1)`
+    pub(crate) fn ema(period: usize) -> Self {
+        Smoother::new(period, 2.0 / (period as f64 + 1.0))
+    }
+
+    /// Wilder's smoothing, `1 / period`, as used by RSI and ATR
+    pub(crate) fn wilder(period: usize) -> Self {
+        Smoother::new(period, 1.0 / period as f64)
+    }
+
+    fn new(period: usize, alpha: f64) -> Self {
+        Smoother { period, alpha, seed_sum: 0.0, seen: 0, current: None }
+    }
+
+    pub(crate) fn push(&mut self, value: f64) -> Option<f64> {
+
+        self.current = match self.current {
+            Some(current) => Some(current + self.alpha * (value - current)),
+            None => {
+                self.seed_sum += value;
+                self.seen += 1;
+                match self.seen == self.period {
+                    true => Some(self.seed_sum / self.period as f64),
+                    false => None
+                }
+            }
+        };
+
+        self.current
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.seed_sum = 0.0;
+        self.seen = 0;
+        self.current = None;
+    }
+}
+
+
+
+// -------------------------- UNIT TESTING --------------------------------- //
+#[cfg(test)]
+mod tests {
+
+    use bigdecimal::BigDecimal;
+    use bars::{BarInfo, BarOptions, BarSeries, BarType};
+
+    use crate::{Indicator, Rsi, Sma, Warmup};
+
+    const MINUTE: u64 = 60_000_000;
+
+    /// One minute bars, one tick each, in the minutes given after a round
+    /// hour, each closing at its minute's number
+    fn minute_bars(minutes: &[u64]) -> BarSeries {
+
+        let start: u64 = 1_700_002_800 * 1_000_000;
+        let ticks = minutes
+            .iter()
+            .enumerate()
+            .map(|(i, m)| (
+                i as u64,
+                start + m * MINUTE + 1_000_000,
+                BigDecimal::from(*m),
+                BigDecimal::from(1)
+            ))
+            .collect();
+
+        let info = BarInfo::new(
+            "kraken".to_string(),
+            "XBTUSD".to_string(),
+            "1m".to_string(),
+            BarOptions::default()
+        ).unwrap();
+
+        BarSeries::from_ticks(info, BarType::Candle, None, ticks).unwrap()
+    }
+
+    /// The values `indicator` gives each bar, and whether it was warm after
+    fn feed(indicator: &mut impl Indicator, series: &BarSeries)
+        -> Vec<(Option<f64>, bool)>
+    {
+        series
+            .bars
+            .iter()
+            .map(|bar| (indicator.update(bar)[0], indicator.is_warm()))
+            .collect()
+    }
+
+    #[test]
+    fn sma_warmup_test() {
+
+        let mut sma = Sma::new(3).unwrap();
+        let fed = feed(&mut sma, &minute_bars(&[0, 1, 2, 3]));
+
+        assert_eq!(fed, vec![
+            (None, false),
+            (None, false),
+            (Some(1.0), true),
+            (Some(2.0), true),
+        ]);
+    }
+
+    #[test]
+    fn short_gap_keeps_warmup_test() {
+
+        // Minutes 3 and 4 are missing, shorter than the SMA's period
+        let mut sma = Sma::new(3).unwrap();
+        let fed = feed(&mut sma, &minute_bars(&[0, 1, 2, 5]));
+
+        assert_eq!(fed[3], (Some(8.0 / 3.0), true));
+    }
+
+    #[test]
+    fn long_gap_restarts_warmup_test() {
+
+        // Minutes 3 to 5 are missing, as long as the SMA's period
+        let mut sma = Sma::new(3).unwrap();
+        let fed = feed(&mut sma, &minute_bars(&[0, 1, 2, 6, 7, 8]));
+
+        assert_eq!(fed[2], (Some(1.0), true));
+        assert_eq!(fed[3], (None, false));
+        assert_eq!(fed[4], (None, false));
+        assert_eq!(fed[5], (Some(7.0), true));
+    }
+
+    #[test]
+    fn filled_bars_restart_warmup_test() {
+
+        let mut series = minute_bars(&[0, 1, 2, 6, 7, 8]);
+        series.fill_gaps().unwrap();
+        assert_eq!(series.len(), 9);
+
+        let mut sma = Sma::new(3).unwrap();
+        let fed = feed(&mut sma, &series);
+
+        // Two flat bars are averaged in, the third is a whole period
+        // without trades
+        assert_eq!(fed[4], (Some(2.0), true));
+        assert_eq!(fed[5], (None, false));
+        assert_eq!(fed[6], (None, false));
+        assert_eq!(fed[8], (Some(7.0), true));
+    }
+
+    #[test]
+    fn reset_forgets_gaps_test() {
+
+        let series = minute_bars(&[0, 1, 2, 3]);
+        let mut rsi = Rsi::new(2).unwrap();
+
+        feed(&mut rsi, &series);
+        assert!(rsi.is_warm());
+
+        rsi.reset();
+        assert!(!rsi.is_warm());
+        let fresh = feed(&mut Rsi::new(2).unwrap(), &series);
+        assert_eq!(feed(&mut rsi, &series), fresh);
+    }
+
+    #[test]
+    fn back_to_back_bars_test() {
+
+        let mut series = minute_bars(&[0, 1]);
+        series.fill_gaps().unwrap();
+        let mut warmup = Warmup::new(1);
+
+        assert!(!warmup.is_gap(&series.bars[0]));
+        assert!(!warmup.is_gap(&series.bars[1]));
+    }
+}
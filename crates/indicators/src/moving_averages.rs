@@ -1,11 +1,157 @@
+use bars::{Bar, BarSeries};
 
-struct MovingAverage {
-    src: Vec<f64>
+use crate::{
+    Indicator,
+    IndicatorError,
+    IndicatorLine,
+    RollingWindow,
+    Smoother,
+    Warmup,
+    check_period,
+    compute_by_updates,
+    to_f64
+};
+
+
+/// Simple moving average of closing prices
+#[derive(Debug, Clone)]
+pub struct Sma {
+    period: usize,
+    window: RollingWindow,
+    warmup: Warmup,
+}
+
+impl Sma {
+
+    pub fn new(period: usize) -> Result<Self, IndicatorError> {
+        check_period("SMA", period)?;
+        Ok(Sma {
+            period,
+            window: RollingWindow::new(period),
+            warmup: Warmup::new(period),
+        })
+    }
+
+    /// Feeds `bar` to the calculation, see `Indicator::update`
+    fn next(&mut self, bar: &Bar) -> Vec<Option<f64>> {
+        vec![self.window.push(to_f64(bar.close()))]
+    }
+
+    /// Forgets the bars fed so far, keeping the gap tracking
+    fn clear(&mut self) {
+        self.window.reset();
+    }
+}
+
+impl Indicator for Sma {
+
+    fn name(&self) -> String {
+        format!("sma({})", self.period)
+    }
+
+    fn line_names(&self) -> Vec<&'static str> {
+        vec!["sma"]
+    }
+
+    fn compute(&self, series: &BarSeries) -> Vec<IndicatorLine> {
+        compute_by_updates(self, series)
+    }
+
+    fn update(&mut self, bar: &Bar) -> Vec<Option<f64>> {
+        if self.warmup.is_gap(bar) {
+            self.clear();
+        };
+        let values: Vec<Option<f64>> = self.next(bar);
+        self.warmup.record(values)
+    }
+
+    fn preview(&self, bar: &Bar) -> Vec<Option<f64>> {
+        self.clone().update(bar)
+    }
+
+    fn reset(&mut self) {
+        self.clear();
+        self.warmup.reset();
+    }
+
+    fn warmup_period(&self) -> usize {
+        self.warmup.needed()
+    }
+
+    fn is_warm(&self) -> bool {
+        self.warmup.is_warm()
+    }
+}
+
+
+/// Exponential moving average of closing prices, seeded with the SMA of the
+/// first `period` closes
+#[derive(Debug, Clone)]
+pub struct Ema {
+    period: usize,
+    smoother: Smoother,
+    warmup: Warmup,
+}
+
+impl Ema {
+
+    pub fn new(period: usize) -> Result<Self, IndicatorError> {
+        check_period("EMA", period)?;
+        Ok(Ema {
+            period,
+            smoother: Smoother::ema(period),
+            warmup: Warmup::new(period),
+        })
+    }
+
+    /// Feeds `bar` to the calculation, see `Indicator::update`
+    fn next(&mut self, bar: &Bar) -> Vec<Option<f64>> {
+        vec![self.smoother.push(to_f64(bar.close()))]
+    }
+
+    /// Forgets the bars fed so far, keeping the gap tracking
+    fn clear(&mut self) {
+        self.smoother.reset();
+    }
 }
 
-impl MovingAverage {
-    fn new(data_source: Vec<_>) {
-                
+impl Indicator for Ema {
+
+    fn name(&self) -> String {
+        format!("ema({})", self.period)
+    }
+
+    fn line_names(&self) -> Vec<&'static str> {
+        vec!["ema"]
+    }
+
+    fn compute(&self, series: &BarSeries) -> Vec<IndicatorLine> {
+        compute_by_updates(self, series)
+    }
+
+    fn update(&mut self, bar: &Bar) -> Vec<Option<f64>> {
+        if self.warmup.is_gap(bar) {
+            self.clear();
+        };
+        let values: Vec<Option<f64>> = self.next(bar);
+        self.warmup.record(values)
+    }
+
+    fn preview(&self, bar: &Bar) -> Vec<Option<f64>> {
+        self.clone().update(bar)
+    }
+
+    fn reset(&mut self) {
+        self.clear();
+        self.warmup.reset();
+    }
+
+    fn warmup_period(&self) -> usize {
+        self.warmup.needed()
+    }
+
+    fn is_warm(&self) -> bool {
+        self.warmup.is_warm()
     }
 }
 
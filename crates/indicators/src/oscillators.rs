@@ -0,0 +1,216 @@
+use bars::{Bar, BarSeries};
+
+use crate::{
+    Indicator,
+    IndicatorError,
+    IndicatorLine,
+    Smoother,
+    Warmup,
+    check_period,
+    compute_by_updates,
+    to_f64
+};
+
+
+/// Relative strength index using Wilder's smoothing
+#[derive(Debug, Clone)]
+pub struct Rsi {
+    period: usize,
+    prev_close: Option<f64>,
+    gains: Smoother,
+    losses: Smoother,
+    warmup: Warmup,
+}
+
+impl Rsi {
+
+    pub fn new(period: usize) -> Result<Self, IndicatorError> {
+        check_period("RSI", period)?;
+        Ok(Rsi {
+            period,
+            prev_close: None,
+            gains: Smoother::wilder(period),
+            losses: Smoother::wilder(period),
+            // The first close only seeds the change between closes
+            warmup: Warmup::new(period + 1),
+        })
+    }
+
+    /// Feeds `bar` to the calculation, see `Indicator::update`
+    fn next(&mut self, bar: &Bar) -> Vec<Option<f64>> {
+        let close: f64 = to_f64(bar.close());
+        let prev_close = self.prev_close.replace(close);
+
+        let Some(prev) = prev_close else {
+            return vec![None]
+        };
+
+        let change: f64 = close - prev;
+        let gain = self.gains.push(change.max(0.0));
+        let loss = self.losses.push((-change).max(0.0));
+
+        let value = match (gain, loss) {
+            (Some(_), Some(0.0)) => Some(100.0),
+            (Some(gain), Some(loss)) => Some(100.0 - 100.0 / (1.0 + gain / loss)),
+            _ => None
+        };
+
+        vec![value]
+    }
+
+    /// Forgets the bars fed so far, keeping the gap tracking
+    fn clear(&mut self) {
+        self.prev_close = None;
+        self.gains.reset();
+        self.losses.reset();
+    }
+}
+
+impl Indicator for Rsi {
+
+    fn name(&self) -> String {
+        format!("rsi({})", self.period)
+    }
+
+    fn line_names(&self) -> Vec<&'static str> {
+        vec!["rsi"]
+    }
+
+    fn compute(&self, series: &BarSeries) -> Vec<IndicatorLine> {
+        compute_by_updates(self, series)
+    }
+
+    fn update(&mut self, bar: &Bar) -> Vec<Option<f64>> {
+        if self.warmup.is_gap(bar) {
+            self.clear();
+        };
+        let values: Vec<Option<f64>> = self.next(bar);
+        self.warmup.record(values)
+    }
+
+    fn preview(&self, bar: &Bar) -> Vec<Option<f64>> {
+        self.clone().update(bar)
+    }
+
+    fn reset(&mut self) {
+        self.clear();
+        self.warmup.reset();
+    }
+
+    fn warmup_period(&self) -> usize {
+        self.warmup.needed()
+    }
+
+    fn is_warm(&self) -> bool {
+        self.warmup.is_warm()
+    }
+}
+
+
+/// Moving average convergence/divergence
+///
+/// Outputs the MACD line (fast EMA minus slow EMA), its signal line and the
+/// histogram between them.
+#[derive(Debug, Clone)]
+pub struct Macd {
+    fast: usize,
+    slow: usize,
+    signal: usize,
+    fast_ema: Smoother,
+    slow_ema: Smoother,
+    signal_ema: Smoother,
+    warmup: Warmup,
+}
+
+impl Macd {
+
+    pub fn new(fast: usize, slow: usize, signal: usize)
+        -> Result<Self, IndicatorError>
+    {
+        check_period("MACD fast", fast)?;
+        check_period("MACD slow", slow)?;
+        check_period("MACD signal", signal)?;
+
+        if fast >= slow {
+            return Err(IndicatorError::InvalidParameters(
+                "MACD fast period must be shorter than the slow period"
+                    .to_string()
+            ))
+        };
+
+        Ok(Macd {
+            fast,
+            slow,
+            signal,
+            fast_ema: Smoother::ema(fast),
+            slow_ema: Smoother::ema(slow),
+            signal_ema: Smoother::ema(signal),
+            // The signal line starts on the slow EMA's first value
+            warmup: Warmup::new(slow + signal - 1),
+        })
+    }
+
+    /// Feeds `bar` to the calculation, see `Indicator::update`
+    fn next(&mut self, bar: &Bar) -> Vec<Option<f64>> {
+        let close: f64 = to_f64(bar.close());
+        let fast = self.fast_ema.push(close);
+        let slow = self.slow_ema.push(close);
+
+        let Some(macd) = fast.zip(slow).map(|(f, s)| f - s) else {
+            return vec![None, None, None]
+        };
+
+        // The signal EMA only runs over the part of the MACD line that exists
+        let signal = self.signal_ema.push(macd);
+
+        vec![Some(macd), signal, signal.map(|s| macd - s)]
+    }
+
+    /// Forgets the bars fed so far, keeping the gap tracking
+    fn clear(&mut self) {
+        self.fast_ema.reset();
+        self.slow_ema.reset();
+        self.signal_ema.reset();
+    }
+}
+
+impl Indicator for Macd {
+
+    fn name(&self) -> String {
+        format!("macd({},{},{})", self.fast, self.slow, self.signal)
+    }
+
+    fn line_names(&self) -> Vec<&'static str> {
+        vec!["macd", "signal", "histogram"]
+    }
+
+    fn compute(&self, series: &BarSeries) -> Vec<IndicatorLine> {
+        compute_by_updates(self, series)
+    }
+
+    fn update(&mut self, bar: &Bar) -> Vec<Option<f64>> {
+        if self.warmup.is_gap(bar) {
+            self.clear();
+        };
+        let values: Vec<Option<f64>> = self.next(bar);
+        self.warmup.record(values)
+    }
+
+    fn preview(&self, bar: &Bar) -> Vec<Option<f64>> {
+        self.clone().update(bar)
+    }
+
+    fn reset(&mut self) {
+        self.clear();
+        self.warmup.reset();
+    }
+
+    fn warmup_period(&self) -> usize {
+        self.warmup.needed()
+    }
+
+    fn is_warm(&self) -> bool {
+        self.warmup.is_warm()
+    }
+}
+
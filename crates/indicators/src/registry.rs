@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+
+use crate::{
+    Atr,
+    BollingerBands,
+    Ema,
+    Indicator,
+    IndicatorError,
+    Macd,
+    Rsi,
+    Sma
+};
+
+
+/// Builds an indicator from its numeric parameters
+pub type IndicatorConstructor =
+    fn(&[usize]) -> Result<Box<dyn Indicator + Send + Sync>, IndicatorError>;
+
+
+/// Looks up indicators by name so callers (charts, backtests) don't need to
+/// know the concrete types
+///
+/// Missing parameters fall back to the usual defaults, e.g. `macd` alone is
+/// `macd(12,26,9)`.
+pub struct IndicatorRegistry {
+    constructors: HashMap<String, IndicatorConstructor>,
+}
+
+impl IndicatorRegistry {
+
+    /// A registry with all built-in indicators
+    pub fn new() -> Self {
+
+        let mut registry = IndicatorRegistry { constructors: HashMap::new() };
+
+        registry.register("sma", |p| {
+            Ok(Box::new(Sma::new(param(p, 0, 20))?))
+        });
+        registry.register("ema", |p| {
+            Ok(Box::new(Ema::new(param(p, 0, 20))?))
+        });
+        registry.register("rsi", |p| {
+            Ok(Box::new(Rsi::new(param(p, 0, 14))?))
+        });
+        registry.register("macd", |p| {
+            Ok(Box::new(Macd::new(
+                param(p, 0, 12),
+                param(p, 1, 26),
+                param(p, 2, 9)
+            )?))
+        });
+        registry.register("bollinger", |p| {
+            Ok(Box::new(BollingerBands::new(
+                param(p, 0, 20),
+                param(p, 1, 2) as f64
+            )?))
+        });
+        registry.register("atr", |p| {
+            Ok(Box::new(Atr::new(param(p, 0, 14))?))
+        });
+
+        registry
+    }
+
+    /// Adds or replaces an indicator under `name` (case-insensitive)
+    pub fn register(&mut self, name: &str, constructor: IndicatorConstructor) {
+        self.constructors.insert(name.to_lowercase(), constructor);
+    }
+
+    /// Registered indicator names, sorted
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.constructors.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    pub fn create(&self, name: &str, params: &[usize])
+        -> Result<Box<dyn Indicator + Send + Sync>, IndicatorError>
+    {
+        match self.constructors.get(&name.to_lowercase()) {
+            Some(constructor) => constructor(params),
+            None => Err(IndicatorError::UnknownIndicator(name.to_string()))
+        }
+    }
+
+    /// Creates an indicator from a spec such as `rsi`, `sma(50)` or
+    /// `macd(12,26,9)`
+    pub fn from_spec(&self, spec: &str)
+        -> Result<Box<dyn Indicator + Send + Sync>, IndicatorError>
+    {
+        let spec: &str = spec.trim();
+
+        let (name, params) = match spec.split_once('(') {
+            Some((name, rest)) => {
+                let inner: &str = rest.strip_suffix(')').ok_or_else(|| {
+                    IndicatorError::InvalidParameters(spec.to_string())
+                })?;
+                let params = inner
+                    .split(',')
+                    .filter(|p| !p.trim().is_empty())
+                    .map(|p| p.trim().parse::<usize>())
+                    .collect::<Result<Vec<usize>, _>>()
+                    .map_err(|_| {
+                        IndicatorError::InvalidParameters(spec.to_string())
+                    })?;
+                (name.trim(), params)
+            },
+            None => (spec, Vec::new())
+        };
+
+        self.create(name, &params)
+    }
+}
+
+impl Default for IndicatorRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+fn param(params: &[usize], index: usize, default: usize) -> usize {
+    params.get(index).copied().unwrap_or(default)
+}
+
@@ -0,0 +1,191 @@
+use bars::{Bar, BarSeries};
+
+use crate::{
+    Indicator,
+    IndicatorError,
+    IndicatorLine,
+    RollingWindow,
+    Smoother,
+    Warmup,
+    check_period,
+    compute_by_updates,
+    to_f64
+};
+
+
+/// Bollinger bands: an SMA of closes with bands `std_devs` population
+/// standard deviations above and below it
+#[derive(Debug, Clone)]
+pub struct BollingerBands {
+    period: usize,
+    std_devs: f64,
+    window: RollingWindow,
+    warmup: Warmup,
+}
+
+impl BollingerBands {
+
+    pub fn new(period: usize, std_devs: f64) -> Result<Self, IndicatorError> {
+        check_period("Bollinger", period)?;
+
+        if std_devs.is_nan() || std_devs <= 0.0 {
+            return Err(IndicatorError::InvalidParameters(
+                "Bollinger band width must be greater than zero".to_string()
+            ))
+        };
+
+        Ok(BollingerBands {
+            period,
+            std_devs,
+            window: RollingWindow::new(period),
+            warmup: Warmup::new(period),
+        })
+    }
+
+    /// Feeds `bar` to the calculation, see `Indicator::update`
+    fn next(&mut self, bar: &Bar) -> Vec<Option<f64>> {
+        let Some(mean) = self.window.push(to_f64(bar.close())) else {
+            return vec![None, None, None]
+        };
+
+        let variance: f64 = self.window
+            .values()
+            .map(|v| (v - mean).powi(2))
+            .sum::<f64>() / self.period as f64;
+        let width: f64 = variance.sqrt() * self.std_devs;
+
+        vec![Some(mean + width), Some(mean), Some(mean - width)]
+    }
+
+    /// Forgets the bars fed so far, keeping the gap tracking
+    fn clear(&mut self) {
+        self.window.reset();
+    }
+}
+
+impl Indicator for BollingerBands {
+
+    fn name(&self) -> String {
+        format!("bollinger({},{})", self.period, self.std_devs)
+    }
+
+    fn line_names(&self) -> Vec<&'static str> {
+        vec!["upper", "middle", "lower"]
+    }
+
+    fn compute(&self, series: &BarSeries) -> Vec<IndicatorLine> {
+        compute_by_updates(self, series)
+    }
+
+    fn update(&mut self, bar: &Bar) -> Vec<Option<f64>> {
+        if self.warmup.is_gap(bar) {
+            self.clear();
+        };
+        let values: Vec<Option<f64>> = self.next(bar);
+        self.warmup.record(values)
+    }
+
+    fn preview(&self, bar: &Bar) -> Vec<Option<f64>> {
+        self.clone().update(bar)
+    }
+
+    fn reset(&mut self) {
+        self.clear();
+        self.warmup.reset();
+    }
+
+    fn warmup_period(&self) -> usize {
+        self.warmup.needed()
+    }
+
+    fn is_warm(&self) -> bool {
+        self.warmup.is_warm()
+    }
+}
+
+
+/// Average true range using Wilder's smoothing
+#[derive(Debug, Clone)]
+pub struct Atr {
+    period: usize,
+    prev_close: Option<f64>,
+    smoother: Smoother,
+    warmup: Warmup,
+}
+
+impl Atr {
+
+    pub fn new(period: usize) -> Result<Self, IndicatorError> {
+        check_period("ATR", period)?;
+        Ok(Atr {
+            period,
+            prev_close: None,
+            smoother: Smoother::wilder(period),
+            warmup: Warmup::new(period),
+        })
+    }
+
+    /// Feeds `bar` to the calculation, see `Indicator::update`
+    fn next(&mut self, bar: &Bar) -> Vec<Option<f64>> {
+        let high: f64 = to_f64(bar.high());
+        let low: f64 = to_f64(bar.low());
+
+        let true_range: f64 = match self.prev_close {
+            Some(c) => (high - low)
+                .max((high - c).abs())
+                .max((low - c).abs()),
+            None => high - low
+        };
+
+        self.prev_close = Some(to_f64(bar.close()));
+
+        vec![self.smoother.push(true_range)]
+    }
+
+    /// Forgets the bars fed so far, keeping the gap tracking
+    fn clear(&mut self) {
+        self.prev_close = None;
+        self.smoother.reset();
+    }
+}
+
+impl Indicator for Atr {
+
+    fn name(&self) -> String {
+        format!("atr({})", self.period)
+    }
+
+    fn line_names(&self) -> Vec<&'static str> {
+        vec!["atr"]
+    }
+
+    fn compute(&self, series: &BarSeries) -> Vec<IndicatorLine> {
+        compute_by_updates(self, series)
+    }
+
+    fn update(&mut self, bar: &Bar) -> Vec<Option<f64>> {
+        if self.warmup.is_gap(bar) {
+            self.clear();
+        };
+        let values: Vec<Option<f64>> = self.next(bar);
+        self.warmup.record(values)
+    }
+
+    fn preview(&self, bar: &Bar) -> Vec<Option<f64>> {
+        self.clone().update(bar)
+    }
+
+    fn reset(&mut self) {
+        self.clear();
+        self.warmup.reset();
+    }
+
+    fn warmup_period(&self) -> usize {
+        self.warmup.needed()
+    }
+
+    fn is_warm(&self) -> bool {
+        self.warmup.is_warm()
+    }
+}
+
@@ -0,0 +1,107 @@
+use serde::Serialize;
+
+use crate::OrderSide;
+
+
+/// What is held of one pair and what it cost, fees in, by the average
+/// cost method the paper accounts use
+///
+/// Buys add their cost and fee to the position. Sells take the average
+/// cost of the volume sold off it, and realize the difference with what
+/// they made after their fee.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct CostBasis {
+    pub volume: f64,
+    pub cost: f64,
+}
+
+impl CostBasis {
+
+    /// Cost of one unit held, zero without a position
+    pub fn average_cost(&self) -> f64 {
+        match self.volume > 0.0 {
+            true => self.cost / self.volume,
+            false => 0.0
+        }
+    }
+
+    /// Applies a fill, and returns the P&L it realized, zero for a buy
+    ///
+    /// A sell of more than is held, e.g. of funds deposited from elsewhere,
+    /// realizes the part that isn't held against no cost at all.
+    pub fn fill(
+        &mut self,
+        side: OrderSide,
+        volume: f64,
+        price: f64,
+        fee: f64
+    ) -> f64 {
+        match side {
+            OrderSide::Buy => {
+                self.volume += volume;
+                self.cost += volume * price + fee;
+                0.0
+            },
+            OrderSide::Sell => {
+                let sold: f64 = volume.min(self.volume);
+                let cost: f64 = sold * self.average_cost();
+
+                self.cost -= cost;
+                self.volume -= sold;
+                if self.volume <= f64::EPSILON {
+                    *self = CostBasis::default();
+                };
+
+                volume * price - fee - cost
+            }
+        }
+    }
+}
+
+
+// -------------------------- UNIT TESTING --------------------------------- //
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn average_cost_test() {
+
+        let mut basis = CostBasis::default();
+        assert_eq!(basis.average_cost(), 0.0);
+
+        assert_eq!(basis.fill(OrderSide::Buy, 1.0, 100.0, 1.0), 0.0);
+        assert_eq!(basis.fill(OrderSide::Buy, 1.0, 200.0, 1.0), 0.0);
+        assert_eq!(basis.volume, 2.0);
+        assert_eq!(basis.average_cost(), 151.0);
+    }
+
+    #[test]
+    fn realized_pnl_test() {
+
+        let mut basis = CostBasis::default();
+        basis.fill(OrderSide::Buy, 2.0, 100.0, 2.0);
+
+        // Half the position, which cost 101, sold for 150 less a 1.5 fee
+        let pnl: f64 = basis.fill(OrderSide::Sell, 1.0, 150.0, 1.5);
+        assert!((pnl - 47.5).abs() < 1e-9);
+        assert_eq!(basis.volume, 1.0);
+        assert!((basis.cost - 101.0).abs() < 1e-9);
+
+        let pnl: f64 = basis.fill(OrderSide::Sell, 1.0, 90.0, 0.0);
+        assert!((pnl + 11.0).abs() < 1e-9);
+        assert_eq!(basis, CostBasis::default());
+    }
+
+    #[test]
+    fn sell_past_position_test() {
+
+        let mut basis = CostBasis::default();
+        basis.fill(OrderSide::Buy, 1.0, 100.0, 0.0);
+
+        let pnl: f64 = basis.fill(OrderSide::Sell, 3.0, 100.0, 0.0);
+        assert!((pnl - 200.0).abs() < 1e-9);
+        assert_eq!(basis, CostBasis::default());
+    }
+}
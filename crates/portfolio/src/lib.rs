@@ -0,0 +1,10 @@
+pub mod cost_basis;
+pub mod rebalance;
+pub use cost_basis::CostBasis;
+pub use rebalance::{
+    Holding,
+    OrderSide,
+    RebalanceError,
+    RebalanceOrder,
+    compute_rebalance_orders
+};
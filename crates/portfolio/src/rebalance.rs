@@ -0,0 +1,338 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+use database_ops::kraken::AssetPairInfo;
+
+
+#[derive(Debug)]
+pub enum RebalanceError {
+    InvalidWeights(String),
+    MissingPairInfo(String),
+    MissingPrice(String),
+    ParseError(String),
+}
+
+impl std::fmt::Display for RebalanceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RebalanceError::InvalidWeights(e) => {
+                write!(f, "RebalanceError::InvalidWeights: {}", e)
+            },
+            RebalanceError::MissingPairInfo(e) => {
+                write!(f, "RebalanceError::MissingPairInfo: {}", e)
+            },
+            RebalanceError::MissingPrice(e) => {
+                write!(f, "RebalanceError::MissingPrice: {}", e)
+            },
+            RebalanceError::ParseError(e) => {
+                write!(f, "RebalanceError::ParseError: {}", e)
+            },
+        }
+    }
+}
+
+
+/// A current position in one pair
+#[derive(Debug, Clone)]
+pub struct Holding {
+    pub ticker: String,
+    pub quantity: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderSide {
+    Buy,
+    Sell,
+}
+
+impl std::fmt::Display for OrderSide {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrderSide::Buy => write!(f, "buy"),
+            OrderSide::Sell => write!(f, "sell"),
+        }
+    }
+}
+
+/// A single order needed to move a pair towards its target weight
+#[derive(Debug, Clone, Serialize)]
+pub struct RebalanceOrder {
+    pub ticker: String,
+    pub side: OrderSide,
+    pub volume: f64,
+    pub price: f64,
+    pub cost: f64,
+    pub fee: f64,
+}
+
+impl std::fmt::Display for RebalanceOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f,
+            "{} {} {} @ {} (cost: {:.2}, fee: {:.2})",
+            self.side,
+            self.volume,
+            self.ticker,
+            self.price,
+            self.cost,
+            self.fee
+        )
+    }
+}
+
+
+/// Computes the orders needed to rebalance a portfolio
+///
+/// `targets` maps tickers to weights of total equity (holdings plus
+/// `quote_cash`). Weights must be non-negative and sum to at most 1; anything
+/// left over stays in cash. Held pairs missing from `targets` are sold off.
+/// `prices` needs every ticker of `targets` and `holdings`, held or not, so
+/// pairs can be bought into from cash. Pairs whose value is off target by
+/// less than `min_drift` of equity, e.g. 0.02, are left alone.
+///
+/// Volumes are rounded down to the pair's lot decimals, taker fees are taken
+/// from the first tier of `AssetPairInfo.fees`, and orders under `ordermin`
+/// or `costmin` are dropped. Sells come first so their proceeds fund the buys.
+pub fn compute_rebalance_orders(
+    targets: &HashMap<String, f64>,
+    holdings: &[Holding],
+    prices: &HashMap<String, f64>,
+    quote_cash: f64,
+    min_drift: f64,
+    pair_info: &BTreeMap<String, AssetPairInfo>,
+) -> Result<Vec<RebalanceOrder>, RebalanceError> {
+
+    let weight_sum: f64 = targets.values().sum();
+
+    if targets.values().any(|w| *w < 0.0) || weight_sum > 1.0 + f64::EPSILON {
+        return Err(RebalanceError::InvalidWeights(format!(
+            "Weights must be non-negative and sum to at most 1, got {}",
+            weight_sum
+        )))
+    };
+
+    let price_of = |ticker: &str| match prices.get(ticker) {
+        Some(p) if *p > 0.0 => Ok(*p),
+        _ => Err(RebalanceError::MissingPrice(ticker.to_string()))
+    };
+
+    let mut quantities: HashMap<&str, f64> = HashMap::new();
+    let mut equity: f64 = quote_cash;
+
+    for holding in holdings {
+        *quantities.entry(&holding.ticker).or_default() += holding.quantity;
+        equity += holding.quantity * price_of(&holding.ticker)?;
+    };
+
+    let mut tickers: Vec<&str> = targets.keys().map(|t| t.as_str()).collect();
+    for holding in holdings {
+        if !targets.contains_key(&holding.ticker) {
+            tickers.push(&holding.ticker);
+        };
+    };
+    tickers.sort();
+    tickers.dedup();
+
+    let mut orders: Vec<RebalanceOrder> = Vec::new();
+
+    for ticker in tickers {
+
+        let price: f64 = price_of(ticker)?;
+
+        let info: &AssetPairInfo = pair_info
+            .get(ticker)
+            .ok_or_else(|| RebalanceError::MissingPairInfo(
+                ticker.to_string()
+            ))?;
+
+        let fee_rate: f64 = info.fees
+            .first()
+            .map(|tier| tier[1] / 100.0)
+            .unwrap_or(0.0);
+
+        let weight: f64 = targets.get(ticker).copied().unwrap_or(0.0);
+        let held: f64 = quantities.get(ticker).copied().unwrap_or(0.0);
+        let diff_value: f64 = weight * equity - held * price;
+
+        if equity <= 0.0 || diff_value.abs() < min_drift * equity {
+            continue
+        };
+
+        let (side, raw_volume) = if diff_value >= 0.0 {
+            (OrderSide::Buy, diff_value / (price * (1.0 + fee_rate)))
+        }
+        else {
+            (OrderSide::Sell, (-diff_value / price).min(held))
+        };
+
+        let volume: f64 = round_down(raw_volume, info.lot_decimals);
+        let cost: f64 = volume * price;
+
+        let ordermin: f64 = parse_field(ticker, "ordermin", &info.ordermin)?;
+        let costmin: f64 = parse_field(ticker, "costmin", &info.costmin)?;
+
+        if volume <= 0.0 || volume < ordermin || cost < costmin {
+            continue
+        };
+
+        orders.push(RebalanceOrder {
+            ticker: ticker.to_string(),
+            side,
+            volume,
+            price,
+            cost,
+            fee: cost * fee_rate,
+        });
+    };
+
+    orders.sort_by_key(|o| o.side == OrderSide::Buy);
+
+    Ok(orders)
+
+}
+
+
+// --------------------------- HELPER FUNCTIONS ---------------------------- //
+fn round_down(value: f64, decimals: u32) -> f64 {
+    let factor: f64 = 10f64.powi(decimals as i32);
+    (value * factor).floor() / factor
+}
+
+fn parse_field(ticker: &str, name: &str, value: &str)
+    -> Result<f64, RebalanceError>
+{
+    if value.is_empty() {
+        return Ok(0.0)
+    };
+
+    value.parse::<f64>().map_err(|_| RebalanceError::ParseError(
+        format!("{} for {}: {}", name, ticker, value)
+    ))
+}
+
+
+// -------------------------- UNIT TESTING --------------------------------- //
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    /// Kraken's info for a pair with 8 lot decimals and a 0.26% taker fee
+    fn pair_info(ordermin: &str) -> AssetPairInfo {
+        AssetPairInfo {
+            altname: String::new(),
+            wsname: String::new(),
+            aclass_base: "currency".to_string(),
+            base: String::new(),
+            aclass_quote: "currency".to_string(),
+            quote: "ZUSD".to_string(),
+            lot: "unit".to_string(),
+            cost_decimals: 5,
+            pair_decimals: 1,
+            lot_decimals: 8,
+            lot_multiplier: 1,
+            leverage_buy: Vec::new(),
+            leverage_sell: Vec::new(),
+            fees: vec![[0.0, 0.26]],
+            fees_maker: None,
+            fee_volume_currency: "ZUSD".to_string(),
+            margin_call: None,
+            margin_stop: None,
+            ordermin: ordermin.to_string(),
+            costmin: "0.5".to_string(),
+            tick_size: "0.1".to_string(),
+            status: "online".to_string(),
+            long_position_limit: None,
+            short_position_limit: None,
+        }
+    }
+
+    #[test]
+    fn from_cash_test() {
+        let targets = HashMap::from([("BTCUSD".to_string(), 0.5)]);
+        let info = BTreeMap::from([
+            ("BTCUSD".to_string(), pair_info("0.0001"))
+        ]);
+
+        // Nothing held yet, so the price only comes from `prices`
+        let prices = HashMap::from([("BTCUSD".to_string(), 50_000.0)]);
+        let orders = compute_rebalance_orders(
+            &targets, &[], &prices, 10_000.0, 0.0, &info
+        ).unwrap();
+
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].side, OrderSide::Buy);
+        assert_eq!(orders[0].volume, 0.09974067);
+        assert!(orders[0].cost + orders[0].fee <= 5_000.0);
+
+        let missing = compute_rebalance_orders(
+            &targets, &[], &HashMap::new(), 10_000.0, 0.0, &info
+        );
+        assert!(matches!(missing, Err(RebalanceError::MissingPrice(_))));
+    }
+
+    #[test]
+    fn drift_test() {
+        let targets = HashMap::from([("BTCUSD".to_string(), 0.51)]);
+        let holdings = [
+            Holding { ticker: "BTCUSD".to_string(), quantity: 0.1 }
+        ];
+        let prices = HashMap::from([("BTCUSD".to_string(), 50_000.0)]);
+        let info = BTreeMap::from([
+            ("BTCUSD".to_string(), pair_info("0.0001"))
+        ]);
+
+        // 100 off target on 10,000 of equity is a 1% drift
+        let within = compute_rebalance_orders(
+            &targets, &holdings, &prices, 5_000.0, 0.02, &info
+        ).unwrap();
+        assert!(within.is_empty());
+
+        let past = compute_rebalance_orders(
+            &targets, &holdings, &prices, 5_000.0, 0.005, &info
+        ).unwrap();
+        assert_eq!(past.len(), 1);
+        assert_eq!(past[0].side, OrderSide::Buy);
+
+        // Past the drift but under the pair's minimum volume
+        let info = BTreeMap::from([
+            ("BTCUSD".to_string(), pair_info("0.01"))
+        ]);
+        let under_min = compute_rebalance_orders(
+            &targets, &holdings, &prices, 5_000.0, 0.005, &info
+        ).unwrap();
+        assert!(under_min.is_empty());
+    }
+
+    #[test]
+    fn order_test() {
+        // ETHUSD is sold off to fund BTCUSD, which sorts before it by name
+        let targets = HashMap::from([("BTCUSD".to_string(), 0.5)]);
+        let holdings = [
+            Holding { ticker: "ETHUSD".to_string(), quantity: 4.0 }
+        ];
+        let prices = HashMap::from([
+            ("BTCUSD".to_string(), 50_000.0),
+            ("ETHUSD".to_string(), 2_500.0),
+        ]);
+        let info = BTreeMap::from([
+            ("BTCUSD".to_string(), pair_info("0.0001")),
+            ("ETHUSD".to_string(), pair_info("0.01")),
+        ]);
+
+        let orders = compute_rebalance_orders(
+            &targets, &holdings, &prices, 0.0, 0.01, &info
+        ).unwrap();
+
+        let sides: Vec<(&str, OrderSide)> = orders
+            .iter()
+            .map(|o| (o.ticker.as_str(), o.side))
+            .collect();
+        assert_eq!(sides, [
+            ("ETHUSD", OrderSide::Sell),
+            ("BTCUSD", OrderSide::Buy)
+        ]);
+        assert_eq!(orders[0].volume, 4.0);
+    }
+}
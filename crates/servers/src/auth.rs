@@ -0,0 +1,319 @@
+use axum::{
+    Json,
+    extract::{Request, State},
+    http::{StatusCode, header::AUTHORIZATION},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::{Value, json};
+
+use app_core::{
+    app_state::{ApiAccess, ApiKey, ApiRole},
+    arg_parsing::Command,
+    database_ops::audit::AuditOrigin,
+};
+
+use crate::http::ServerState;
+
+
+/// Header clients can send their key in, instead of `Authorization: Bearer`
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Query parameter for clients that can't set headers, e.g. browser
+/// WebSockets
+pub const API_KEY_PARAM: &str = "api_key";
+
+
+/// Who sent a request, added to its extensions by `authorize`
+#[derive(Debug, Clone)]
+pub(crate) struct Caller {
+    /// The role of their key, `None` when the API is open
+    pub role: Option<ApiRole>,
+    /// See `ApiKey::id`
+    pub key_id: Option<String>,
+}
+
+impl Caller {
+
+    /// Where the caller's commands came from, for the audit log
+    pub(crate) fn origin(&self) -> AuditOrigin {
+        AuditOrigin::Http(self.key_id.clone())
+    }
+
+    /// Fails unless the caller's role may run `cmd`, before it reaches the
+    /// engine. Without keys configured, only commands that read are let
+    /// through.
+    pub(crate) fn check(&self, cmd: &Command)
+        -> Result<(), (StatusCode, Json<Value>)>
+    {
+        let access: ApiAccess = required_access(cmd);
+
+        match self.role {
+            None if access != ApiAccess::Read => Err((
+                StatusCode::FORBIDDEN,
+                Json(json!({
+                    "error": "No API keys are configured, so the API is \
+                        read-only. Add a key to http.api_keys to change \
+                        data or trade."
+                }))
+            )),
+            Some(role) if !role.allows(access) => Err((
+                StatusCode::FORBIDDEN,
+                Json(json!({
+                    "error": format!(
+                        "This API key's {} role can't {}",
+                        role,
+                        match access {
+                            ApiAccess::Read => "read data",
+                            ApiAccess::Mutate => "change stored data",
+                            ApiAccess::Trade => "place or cancel orders",
+                        }
+                    )
+                }))
+            )),
+            _ => Ok(())
+        }
+    }
+}
+
+
+/// Rejects requests without a known key, and marks the rest with the 
+/// `Caller` whose role the handlers check
+///
+/// Every request is let through when no keys are configured, and 
+/// `Caller::check` then refuses all but reading.
+pub(crate) async fn authorize(
+    State(state): State<ServerState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+
+    if state.api_keys.is_empty() {
+        request.extensions_mut().insert(Caller { role: None, key_id: None });
+        return next.run(request).await
+    };
+
+    let caller: Option<Caller> = presented_key(&request)
+        .and_then(|key| find_key(&state.api_keys, &key))
+        .map(|k| Caller { role: Some(k.role), key_id: Some(k.id()) });
+
+    match caller {
+        Some(caller) => {
+            request.extensions_mut().insert(caller);
+            next.run(request).await
+        },
+        None => reject(
+            StatusCode::UNAUTHORIZED,
+            "Missing or unknown API key"
+        )
+    }
+}
+
+/// What running `cmd` does, for the roles to be checked against
+///
+/// Every command is listed, so that new ones are given a level.
+pub(crate) fn required_access(cmd: &Command) -> ApiAccess {
+    match cmd {
+        Command::ApiUsage
+        | Command::DbStats
+        | Command::ListPairs
+        | Command::Chart { .. }
+        | Command::Backtest { .. }
+        | Command::Verify { .. }
+        | Command::ListAliases
+        | Command::PaperStatus { .. }
+        | Command::PaperHistory { .. }
+        | Command::AuditLog { .. }
+        | Command::RecurringList
+        | Command::Help => ApiAccess::Read,
+        Command::CandleBuilder { .. }
+        | Command::DbIntegrityCheck { .. } => match cmd.changes_data() {
+            true => ApiAccess::Mutate,
+            false => ApiAccess::Read
+        },
+        Command::Catalog { refresh, .. } => match *refresh {
+            true => ApiAccess::Mutate,
+            false => ApiAccess::Read
+        },
+        // The config holds the API keys, so even reading it is guarded
+        Command::GetConfig { .. }
+        | Command::SetConfig { .. }
+        | Command::AddPair { .. }
+        | Command::DropPair { .. }
+        | Command::RollbackBatch { .. }
+        | Command::UpdatePairs
+        | Command::RetryFailed
+        | Command::ExportPair { .. }
+        | Command::ImportPair { .. }
+        | Command::StartServer { .. }
+        | Command::AddAlias { .. }
+        | Command::RemoveAlias { .. } => ApiAccess::Mutate,
+        Command::PaperOrder { .. }
+        | Command::PaperCancel { .. }
+        | Command::PaperModify { .. }
+        | Command::PaperSync { .. }
+        | Command::PaperReset { .. }
+        | Command::PaperRebalance { .. }
+        // The live account's funds are private to those who can trade them
+        | Command::LiveBalance
+        | Command::LiveOpenOrders
+        | Command::LiveOrder { .. }
+        | Command::LiveCancel { .. }
+        | Command::LiveModify { .. }
+        // Writes a file on the server, of the live account's fills too
+        | Command::ExportFills { .. }
+        | Command::RecurringAdd { .. }
+        | Command::RecurringRemove { .. }
+        | Command::RecurringRun
+        // Reads the live account's fills for a live schedule
+        | Command::RecurringHistory { .. } => ApiAccess::Trade,
+    }
+}
+
+
+fn presented_key(request: &Request) -> Option<String> {
+
+    let headers = request.headers();
+
+    let bearer = headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+
+    let header = headers
+        .get(API_KEY_HEADER)
+        .and_then(|v| v.to_str().ok());
+
+    let query = request
+        .uri()
+        .query()
+        .and_then(|q| q
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(name, _)| *name == API_KEY_PARAM)
+            .map(|(_, value)| value)
+        );
+
+    bearer
+        .or(header)
+        .or(query)
+        .map(|key| key.trim().to_string())
+}
+
+/// Compares against every key without stopping early, so response times
+/// don't hint at how much of a key was right
+pub(crate) fn find_key<'a>(keys: &'a [ApiKey], presented: &str)
+    -> Option<&'a ApiKey>
+{
+    keys.iter().fold(None, |found, key| {
+        match constant_time_eq(key.key.as_bytes(), presented.as_bytes()) {
+            true => found.or(Some(key)),
+            false => found
+        }
+    })
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len()
+        && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn reject(status: StatusCode, message: &str) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+
+// -------------------------- UNIT TESTING --------------------------------- //
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn role_command_table_test() {
+
+        let integrity = |repair: bool| Command::DbIntegrityCheck {
+            exchange: "kraken".to_string(),
+            ticker: "XBTUSD".to_string(),
+            repair
+        };
+        let catalog = |refresh: bool| Command::Catalog {
+            exchange: None,
+            ticker: None,
+            refresh
+        };
+        let account = || "main".to_string();
+
+        // Whether each command is let through with no keys configured, and
+        // for a viewer, operator, trader and admin
+        let table: [(Command, [bool; 5]); 17] = [
+            (Command::ListPairs, [true; 5]),
+            (Command::Help, [true; 5]),
+            (integrity(false), [true; 5]),
+            (catalog(false), [true; 5]),
+            (
+                Command::PaperStatus { account: account() }, 
+                [true; 5]
+            ),
+            (
+                Command::PaperHistory { account: account() }, 
+                [true; 5]
+            ),
+            (
+                Command::AuditLog { action: None, limit: 10 }, 
+                [true; 5]
+            ),
+            (integrity(true), [false, false, true, false, true]),
+            (catalog(true), [false, false, true, false, true]),
+            (Command::UpdatePairs, [false, false, true, false, true]),
+            (
+                Command::RollbackBatch { batch_id: 1 }, 
+                [false, false, true, false, true]
+            ),
+            // Holds the API keys, so reading it is guarded too
+            (
+                Command::GetConfig { key: None }, 
+                [false, false, true, false, true]
+            ),
+            (
+                Command::PaperSync { account: account() }, 
+                [false, false, false, true, true]
+            ),
+            (
+                Command::PaperCancel { account: account(), order_id: 1 }, 
+                [false, false, false, true, true]
+            ),
+            (
+                Command::PaperModify { 
+                    account: account(), 
+                    order_id: 1, 
+                    price: 1.0 
+                }, 
+                [false, false, false, true, true]
+            ),
+            (Command::LiveBalance, [false, false, false, true, true]),
+            (Command::RecurringRun, [false, false, false, true, true]),
+        ];
+
+        let roles: [Option<ApiRole>; 5] = [
+            None,
+            Some(ApiRole::Viewer),
+            Some(ApiRole::Operator),
+            Some(ApiRole::Trader),
+            Some(ApiRole::Admin),
+        ];
+
+        for (cmd, allowed) in &table {
+            for (role, allowed) in roles.iter().zip(allowed) {
+                let caller = Caller { role: *role, key_id: None };
+                assert_eq!(
+                    caller.check(cmd).is_ok(), 
+                    *allowed, 
+                    "{:?} running {:?}", 
+                    role, 
+                    cmd
+                );
+            };
+        };
+    }
+}
@@ -0,0 +1,436 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use arrow_array::{
+    ArrayRef,
+    Float64Array,
+    RecordBatch,
+    TimestampMicrosecondArray,
+    UInt64Array,
+};
+use arrow_flight::{
+    Action,
+    ActionType,
+    Criteria,
+    Empty,
+    FlightData,
+    FlightDescriptor,
+    FlightEndpoint,
+    FlightInfo,
+    HandshakeRequest,
+    HandshakeResponse,
+    PollInfo,
+    PutResult,
+    SchemaResult,
+    Ticket,
+    encode::FlightDataEncoderBuilder,
+    error::FlightError,
+    flight_service_server::{FlightService, FlightServiceServer},
+};
+use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef, TimeUnit};
+use futures::{
+    StreamExt,
+    TryStreamExt,
+    stream::{self, BoxStream},
+};
+use num_traits::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, types::BigDecimal};
+use tonic::{
+    Request,
+    Response,
+    Status,
+    Streaming,
+    metadata::MetadataMap,
+    transport::Server,
+};
+
+use app_core::{
+    app_state::ApiKey,
+    database_ops::{
+        Ticker,
+        TickerError,
+        fetch_exchanges_and_pairs_from_db,
+        fetch_rows_page,
+        fetch_tables,
+    },
+};
+
+use crate::{auth, http::ServerState};
+
+
+/// Ticks per record batch, each read from the database on its own
+const BATCH_TICKS: u64 = 65_536;
+
+
+/// What a ticket, or a descriptor's command, asks for as JSON: a pair's
+/// ticks from `from` on and before `to`, both unix seconds and optional
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TickQuery {
+    pub exchange: String,
+    pub ticker: Ticker,
+    #[serde(default)]
+    pub from: Option<u64>,
+    #[serde(default)]
+    pub to: Option<u64>,
+}
+
+impl TickQuery {
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Status> {
+        serde_json::from_slice(bytes).map_err(|e| Status::invalid_argument(
+            format!(
+                "Expected {{\"exchange\", \"ticker\", \"from\", \"to\"}}: {}",
+                e
+            )
+        ))
+    }
+
+    /// A descriptor path is `[EXCHANGE, TICKER]`, for all of a pair's
+    /// ticks, otherwise its command is read like a ticket
+    fn from_descriptor(descriptor: &FlightDescriptor) -> Result<Self, Status> {
+        match descriptor.path.as_slice() {
+            [] => Self::from_bytes(&descriptor.cmd),
+            [exchange, ticker] => Ok(TickQuery {
+                exchange: exchange.clone(),
+                ticker: ticker
+                    .parse()
+                    .map_err(|e: TickerError| {
+                        Status::invalid_argument(e.to_string())
+                    })?,
+                from: None,
+                to: None,
+            }),
+            _ => Err(Status::invalid_argument(
+                "Descriptor paths are [EXCHANGE, TICKER]"
+            ))
+        }
+    }
+
+    /// The time range in the tick tables' microseconds
+    fn range(&self) -> (Option<u64>, Option<u64>) {
+        (
+            self.from.map(|t| t.saturating_mul(1_000_000)),
+            self.to.map(|t| t.saturating_mul(1_000_000))
+        )
+    }
+
+    fn info(&self) -> Result<FlightInfo, Status> {
+
+        let ticket: Vec<u8> = serde_json::to_vec(self)
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        let info = FlightInfo::new()
+            .try_with_schema(&tick_schema())
+            .map_err(|e| Status::internal(e.to_string()))?
+            .with_descriptor(FlightDescriptor::new_cmd(ticket.clone()))
+            .with_endpoint(FlightEndpoint::new().with_ticket(
+                Ticket::new(ticket)
+            ))
+            .with_ordered(true);
+
+        Ok(info)
+    }
+}
+
+
+/// Columns of every batch: the tick's id, its time in UTC, and its price
+/// and volume as floats like the Parquet export
+pub fn tick_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("id", DataType::UInt64, false),
+        Field::new(
+            "time",
+            DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+            false
+        ),
+        Field::new("price", DataType::Float64, false),
+        Field::new("volume", DataType::Float64, false),
+    ]))
+}
+
+fn to_batch(ticks: &[(u64, u64, BigDecimal, BigDecimal)])
+    -> Result<RecordBatch, ArrowError>
+{
+    let to_f64 = |v: &BigDecimal| v.to_f64().unwrap_or(f64::NAN);
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt64Array::from_iter_values(ticks.iter().map(|t| t.0))),
+        Arc::new(
+            TimestampMicrosecondArray::from_iter_values(
+                ticks.iter().map(|t| t.1 as i64)
+            )
+                .with_timezone("UTC")
+        ),
+        Arc::new(Float64Array::from_iter_values(
+            ticks.iter().map(|t| to_f64(&t.2))
+        )),
+        Arc::new(Float64Array::from_iter_values(
+            ticks.iter().map(|t| to_f64(&t.3))
+        )),
+    ];
+
+    RecordBatch::try_new(tick_schema(), columns)
+}
+
+/// Where a `tick_batches` stream is up to: the id to read after, none once
+/// the last page has been read
+type PageCursor = (Option<u64>, TickQuery, PgPool);
+
+async fn next_batch(cursor: PageCursor)
+    -> Result<Option<(RecordBatch, PageCursor)>, FlightError>
+{
+    let (Some(after_id), query, db_pool) = cursor else {
+        return Ok(None)
+    };
+
+    let ticks = fetch_rows_page(
+        &query.exchange,
+        &query.ticker,
+        after_id,
+        query.range(),
+        BATCH_TICKS,
+        db_pool.clone()
+    )
+        .await
+        .map_err(|e| FlightError::ExternalError(e.to_string().into()))?;
+
+    let Some(last) = ticks.last() else {
+        return Ok(None)
+    };
+
+    let next: Option<u64> = match (ticks.len() as u64) < BATCH_TICKS {
+        true => None,
+        false => Some(last.0)
+    };
+
+    Ok(Some((to_batch(&ticks)?, (next, query, db_pool))))
+}
+
+/// The query's ticks as record batches, read a page at a time so only one
+/// batch is held at once
+fn tick_batches(query: TickQuery, db_pool: PgPool)
+    -> BoxStream<'static, Result<RecordBatch, FlightError>>
+{
+    stream::try_unfold((Some(0), query, db_pool), next_batch).boxed()
+}
+
+
+/// Checks the key sent as `authorization: Bearer KEY` or `x-api-key`
+/// metadata, like `auth::authorize` does for the REST API
+///
+/// Flight only serves reads, which every role covers.
+fn authorize(keys: &[ApiKey], metadata: &MetadataMap) -> Result<(), Status> {
+
+    if keys.is_empty() {
+        return Ok(())
+    };
+
+    let bearer = metadata
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+
+    let header = metadata
+        .get(auth::API_KEY_HEADER)
+        .and_then(|v| v.to_str().ok());
+
+    match bearer.or(header).and_then(|k| auth::find_key(keys, k.trim())) {
+        Some(_) => Ok(()),
+        None => Err(Status::unauthenticated("Missing or unknown API key"))
+    }
+}
+
+
+// ----------------------------- FLIGHT SERVICE ---------------------------- //
+/// Arrow Flight service streaming stored ticks as record batches, for
+/// analytical tools that would rather skip CSV or JSON
+///
+/// `list_flights` lists every stored pair, and `do_get` takes a ticket
+/// holding a `TickQuery`. Nothing can be written through it.
+#[derive(Clone)]
+pub struct FlightServer {
+    db_pool: PgPool,
+    api_keys: Arc<Vec<ApiKey>>,
+}
+
+impl FlightServer {
+
+    pub fn new(state: &ServerState) -> Self {
+        FlightServer {
+            db_pool: state.db_pool.clone(),
+            api_keys: state.api_keys.clone(),
+        }
+    }
+
+    /// Fails unless the query's pair is stored, which also keeps anything
+    /// but a known table name out of the queries
+    async fn check_pair(&self, query: &TickQuery) -> Result<(), Status> {
+
+        let tables: Vec<String> = fetch_tables(self.db_pool.clone())
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        match tables.contains(&query.ticker.table_name(&query.exchange)) {
+            true => Ok(()),
+            false => Err(Status::not_found(format!(
+                "{} {} hasn't been added",
+                query.exchange,
+                query.ticker
+            )))
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl FlightService for FlightServer {
+
+    type HandshakeStream = BoxStream<
+        'static, Result<HandshakeResponse, Status>
+    >;
+    type ListFlightsStream = BoxStream<'static, Result<FlightInfo, Status>>;
+    type DoGetStream = BoxStream<'static, Result<FlightData, Status>>;
+    type DoPutStream = BoxStream<'static, Result<PutResult, Status>>;
+    type DoActionStream = BoxStream<
+        'static, Result<arrow_flight::Result, Status>
+    >;
+    type ListActionsStream = BoxStream<'static, Result<ActionType, Status>>;
+    type DoExchangeStream = BoxStream<'static, Result<FlightData, Status>>;
+
+    async fn handshake(
+        &self,
+        _request: Request<Streaming<HandshakeRequest>>
+    ) -> Result<Response<Self::HandshakeStream>, Status> {
+        Err(Status::unimplemented(
+            "No handshake needed, send the API key with each call"
+        ))
+    }
+
+    async fn list_flights(
+        &self,
+        request: Request<Criteria>
+    ) -> Result<Response<Self::ListFlightsStream>, Status> {
+
+        authorize(&self.api_keys, request.metadata())?;
+
+        let pairs = fetch_exchanges_and_pairs_from_db(
+            self.db_pool.clone(),
+            false
+        ).await;
+
+        let mut infos: Vec<Result<FlightInfo, Status>> = Vec::new();
+        for (exchange, tickers) in pairs {
+            for (ticker, _) in tickers {
+                let Ok(ticker) = ticker.parse::<Ticker>() else {
+                    continue
+                };
+                let query = TickQuery {
+                    exchange: exchange.to_lowercase(),
+                    ticker,
+                    from: None,
+                    to: None,
+                };
+                infos.push(query.info());
+            };
+        };
+
+        Ok(Response::new(stream::iter(infos).boxed()))
+    }
+
+    async fn get_flight_info(
+        &self,
+        request: Request<FlightDescriptor>
+    ) -> Result<Response<FlightInfo>, Status> {
+
+        authorize(&self.api_keys, request.metadata())?;
+
+        let query = TickQuery::from_descriptor(request.get_ref())?;
+        self.check_pair(&query).await?;
+
+        Ok(Response::new(query.info()?))
+    }
+
+    async fn poll_flight_info(
+        &self,
+        _request: Request<FlightDescriptor>
+    ) -> Result<Response<PollInfo>, Status> {
+        Err(Status::unimplemented("Use get_flight_info"))
+    }
+
+    async fn get_schema(
+        &self,
+        request: Request<FlightDescriptor>
+    ) -> Result<Response<SchemaResult>, Status> {
+
+        authorize(&self.api_keys, request.metadata())?;
+
+        let query = TickQuery::from_descriptor(request.get_ref())?;
+        let info: FlightInfo = query.info()?;
+
+        Ok(Response::new(SchemaResult { schema: info.schema }))
+    }
+
+    async fn do_get(
+        &self,
+        request: Request<Ticket>
+    ) -> Result<Response<Self::DoGetStream>, Status> {
+
+        authorize(&self.api_keys, request.metadata())?;
+
+        let query = TickQuery::from_bytes(&request.get_ref().ticket)?;
+        self.check_pair(&query).await?;
+
+        let data = FlightDataEncoderBuilder::new()
+            .with_schema(tick_schema())
+            .build(tick_batches(query, self.db_pool.clone()))
+            .map_err(Status::from)
+            .boxed();
+
+        Ok(Response::new(data))
+    }
+
+    async fn do_put(
+        &self,
+        _request: Request<Streaming<FlightData>>
+    ) -> Result<Response<Self::DoPutStream>, Status> {
+        Err(Status::unimplemented("Ticks can't be written through Flight"))
+    }
+
+    async fn do_action(
+        &self,
+        _request: Request<Action>
+    ) -> Result<Response<Self::DoActionStream>, Status> {
+        Err(Status::unimplemented("No actions are offered"))
+    }
+
+    async fn list_actions(
+        &self,
+        _request: Request<Empty>
+    ) -> Result<Response<Self::ListActionsStream>, Status> {
+        Ok(Response::new(stream::empty().boxed()))
+    }
+
+    async fn do_exchange(
+        &self,
+        _request: Request<Streaming<FlightData>>
+    ) -> Result<Response<Self::DoExchangeStream>, Status> {
+        Err(Status::unimplemented("Ticks can't be written through Flight"))
+    }
+}
+
+
+/// Serves `FlightServer` on `address` until the process exits
+pub async fn serve_flight(state: ServerState, address: &str)
+    -> std::io::Result<()>
+{
+    let address: SocketAddr = address
+        .parse()
+        .map_err(std::io::Error::other)?;
+
+    println!("\x1b[1;32mServing Arrow Flight on grpc://{}\x1b[0m", address);
+
+    Server::builder()
+        .add_service(FlightServiceServer::new(FlightServer::new(&state)))
+        .serve(address)
+        .await
+        .map_err(std::io::Error::other)
+}
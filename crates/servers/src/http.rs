@@ -0,0 +1,557 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension,
+    Json,
+    Router,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    middleware,
+    routing::{delete, get, post},
+};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use sqlx::PgPool;
+use tokio::{
+    net::TcpListener,
+    sync::{Mutex, broadcast},
+};
+
+use app_core::{
+    BarOptions,
+    DataDownloadStatus,
+    DataResponse,
+    PreflightError,
+    Response,
+    RunTimeError,
+    app_state::{ApiKey, LiveTradingSettings, MqttSettings, RedisSettings},
+    arg_parsing::{Command, CsvFlags},
+    audit::{Audited, audited},
+    backtest::OrderSide,
+    catalog::refresh_catalog,
+    database_ops::Ticker,
+    engine::Engine,
+    errors::PaperError,
+    job_registry::JobRegistry,
+    paper::{Contingent, NewOrder, is_account_name},
+    report::DailyReport,
+    telemetry,
+};
+
+use crate::{
+    auth::{self, Caller},
+    flight,
+    mqtt_bridge,
+    redis_bridge,
+    scheduler,
+    sse,
+    status_page,
+    ws,
+};
+
+
+/// The engine shared by every request. Commands run one at a time, so a
+/// long `/update` holds up the requests behind it.
+type SharedEngine = Arc<Mutex<Engine>>;
+
+/// Progress events buffered per `/ws` client before it starts missing them
+const STATUS_BUFFER: usize = 256;
+
+/// Reports buffered per sink, which only fall behind while reconnecting
+const REPORT_BUFFER: usize = 4;
+
+
+#[derive(Clone)]
+pub struct ServerState {
+    pub engine: SharedEngine,
+    /// Download progress from the engine, fanned out to `/ws` and 
+    /// `/update/events` clients
+    pub status: broadcast::Sender<DataDownloadStatus>,
+    /// Lets `/ws` build bars without waiting on the engine
+    pub db_pool: PgPool,
+    pub bar_options: BarOptions,
+    /// Keys accepted by `auth::authorize`, empty for an open API
+    pub api_keys: Arc<Vec<ApiKey>>,
+    /// The engine's jobs, listed by `/status` without waiting on the engine
+    pub jobs: JobRegistry,
+    /// Daily reports from `scheduler`, for the Redis and MQTT sinks to send
+    pub reports: broadcast::Sender<DailyReport>,
+}
+
+impl ServerState {
+
+    pub fn new(mut engine: Engine, api_keys: Vec<ApiKey>) -> Self {
+
+        let (status, _) = broadcast::channel(STATUS_BUFFER);
+        let (reports, _) = broadcast::channel(REPORT_BUFFER);
+        engine.status_feed = Some(status.clone());
+
+        ServerState {
+            db_pool: engine.database.get_pool(),
+            bar_options: engine.state.config.bars,
+            jobs: engine.jobs.clone(),
+            reports,
+            engine: Arc::new(Mutex::new(engine)),
+            status,
+            api_keys: Arc::new(api_keys),
+        }
+    }
+}
+
+type ApiResult = Result<Json<Value>, (StatusCode, Json<Value>)>;
+
+
+/// Serves the REST API on `address` until the process exits, with the Arrow
+/// Flight service alongside it when `http.flight_address` is set, the Redis
+/// bridge when `redis.url` is and the MQTT publisher when `mqtt.host` is.
+/// Recurring buys are placed as they come due, the paper accounts' equity
+/// is recorded for `/status` and the daily report is sent to Redis and
+/// MQTT, see `scheduler`.
+///
+/// Fails before binding when the API keys in the environment are malformed.
+pub async fn serve_http(engine: Engine, address: &str)
+    -> std::io::Result<()>
+{
+    let api_keys: Vec<ApiKey> = engine.state.config.http
+        .all_api_keys()
+        .map_err(std::io::Error::other)?;
+
+    let listener: TcpListener = TcpListener::bind(address).await?;
+
+    println!(
+        "\x1b[1;32mServing the API on http://{}\x1b[0m",
+        listener.local_addr()?
+    );
+    if api_keys.is_empty() {
+        println!(
+            "\x1b[33mNo API keys configured, so the API is read-only and \
+            open to anyone who can reach it\x1b[0m"
+        );
+    };
+
+    let flight_address: Option<String> = engine.state.config.http
+        .flight_address
+        .clone();
+    let redis: RedisSettings = engine.state.config.redis.clone();
+    let mqtt: MqttSettings = engine.state.config.mqtt.clone();
+    let live: LiveTradingSettings = engine.state.config.live.clone();
+    let slippage_bps: u32 = engine.state.config.backtesting.slippage_bps;
+    let state = ServerState::new(engine, api_keys);
+
+    if let Some(flight_address) = flight_address {
+        let flight_state: ServerState = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = flight::serve_flight(
+                flight_state, 
+                &flight_address
+            ).await {
+                eprintln!(
+                    "\x1b[1;31mArrow Flight service failed: {}\x1b[0m", 
+                    e
+                );
+            };
+        });
+    };
+
+    if redis.url.is_some() {
+        let bridge_state: ServerState = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = redis_bridge::run_bridge(
+                bridge_state, 
+                redis, 
+                slippage_bps
+            ).await {
+                eprintln!("\x1b[1;31mRedis bridge failed: {}\x1b[0m", e);
+            };
+        });
+    };
+
+    if mqtt.host.is_some() {
+        let mqtt_state: ServerState = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = mqtt_bridge::run_publisher(mqtt_state, mqtt).await {
+                eprintln!("\x1b[1;31mMQTT publisher failed: {}\x1b[0m", e);
+            };
+        });
+    };
+
+    tokio::spawn(scheduler::run_recurring_buys(
+        state.clone(),
+        live,
+        slippage_bps
+    ));
+    tokio::spawn(scheduler::run_equity_snapshots(state.clone()));
+    tokio::spawn(scheduler::run_daily_reports(state.clone()));
+
+    axum::serve(listener, router(state)).await
+}
+
+pub fn router(state: ServerState) -> Router {
+    Router::new()
+        .route("/pairs", get(pairs))
+        .route("/candles/{exchange}/{ticker}/{period}", get(candles))
+        .route("/integrity", get(integrity_check).post(integrity_repair))
+        .route("/update", post(update))
+        .route("/update/events", get(sse::progress))
+        .route("/ws", get(ws::stream))
+        .route("/status", get(status_page::status))
+        .route("/paper/{account}", get(paper_account))
+        .route("/paper/{account}/orders", get(paper_orders))
+        .route("/paper/{account}/sync", post(paper_sync))
+        .route("/paper/{account}/buy", post(paper_buy))
+        .route("/paper/{account}/sell", post(paper_sell))
+        .route("/paper/{account}/orders/{id}", delete(paper_cancel))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::authorize
+        ))
+        .with_state(state)
+}
+
+
+#[derive(Deserialize)]
+struct CandleParams {
+    #[serde(default)]
+    fill_gaps: bool,
+    #[serde(default)]
+    listing_anchor: bool,
+}
+
+#[derive(Deserialize)]
+struct PairParams {
+    exchange: String,
+    ticker: String,
+}
+
+/// Body of `/paper/{account}/buy` and `/sell`. Without `limit` or `stop`
+/// it's a market order.
+///
+/// `oco_stop` needs a `limit` and pairs it with a stop on the other side,
+/// so whichever fills first cancels the other. `stop_loss` and
+/// `take_profit` come together and make the order the entry of a bracket,
+/// with both exits waiting until it fills.
+#[derive(Deserialize)]
+struct OrderParams {
+    exchange: String,
+    ticker: Ticker,
+    volume: f64,
+    limit: Option<f64>,
+    stop: Option<f64>,
+    /// A stop order next to the limit order, one cancelling the other
+    oco_stop: Option<f64>,
+    /// The exits of a bracket, placed once the order fills
+    stop_loss: Option<f64>,
+    take_profit: Option<f64>,
+    /// Unix seconds to place the order at, replaying the ticks after it
+    at: Option<u64>,
+}
+
+
+async fn pairs(
+    State(state): State<ServerState>,
+    Extension(caller): Extension<Caller>,
+) -> ApiResult {
+    match run(&state.engine, caller, Command::ListPairs).await? {
+        Response::Data(DataResponse::Pairs(pairs)) => Ok(Json(json!(pairs))),
+        _ => Err(unexpected_response())
+    }
+}
+
+async fn candles(
+    State(state): State<ServerState>,
+    Extension(caller): Extension<Caller>,
+    Path((exchange, ticker, period)): Path<(String, Ticker, String)>,
+    Query(params): Query<CandleParams>,
+) -> ApiResult {
+
+    let cmd = Command::CandleBuilder {
+        exchange,
+        ticker,
+        period,
+        integrity_check: false,
+        fill_gaps: params.fill_gaps,
+        listing_anchor: params.listing_anchor,
+        export: None,
+        out: None,
+        export_ticks: false,
+        csv_flags: CsvFlags::default(),
+        from: None,
+        to: None,
+    };
+
+    match run(&state.engine, caller, cmd).await? {
+        Response::Data(DataResponse::Bars(series)) => Ok(Json(json!({
+            "exchange": series.info.exchange(),
+            "ticker": series.info.ticker(),
+            "period": series.info.period(),
+            "bars": series.to_ohlcv(),
+        }))),
+        _ => Err(unexpected_response())
+    }
+}
+
+async fn integrity_check(
+    State(state): State<ServerState>,
+    Extension(caller): Extension<Caller>,
+    Query(params): Query<PairParams>,
+) -> ApiResult {
+    integrity(&state.engine, caller, params, false).await
+}
+
+async fn integrity_repair(
+    State(state): State<ServerState>,
+    Extension(caller): Extension<Caller>,
+    Query(params): Query<PairParams>,
+) -> ApiResult {
+    integrity(&state.engine, caller, params, true).await
+}
+
+async fn integrity(
+    engine: &SharedEngine,
+    caller: Caller,
+    params: PairParams,
+    repair: bool
+) -> ApiResult {
+
+    let cmd = Command::DbIntegrityCheck {
+        exchange: params.exchange,
+        ticker: params.ticker,
+        repair,
+    };
+
+    match run(engine, caller, cmd).await? {
+        Response::Data(DataResponse::Integrity(checks)) => {
+            Ok(Json(json!(checks)))
+        },
+        _ => Err(unexpected_response())
+    }
+}
+
+async fn update(
+    State(state): State<ServerState>,
+    Extension(caller): Extension<Caller>,
+) -> ApiResult {
+    run(&state.engine, caller, Command::UpdatePairs).await?;
+    Ok(Json(json!({ "status": "ok" })))
+}
+
+async fn paper_account(
+    State(state): State<ServerState>,
+    Extension(caller): Extension<Caller>,
+    Path(account): Path<String>,
+) -> ApiResult {
+
+    let cmd = Command::PaperStatus { account: checked_account(account)? };
+
+    match run(&state.engine, caller, cmd).await? {
+        Response::Data(DataResponse::PaperAccount(account)) => {
+            Ok(Json(json!(account)))
+        },
+        _ => Err(unexpected_response())
+    }
+}
+
+async fn paper_orders(
+    State(state): State<ServerState>,
+    Extension(caller): Extension<Caller>,
+    Path(account): Path<String>,
+) -> ApiResult {
+
+    let cmd = Command::PaperHistory { account: checked_account(account)? };
+
+    match run(&state.engine, caller, cmd).await? {
+        Response::Data(DataResponse::PaperOrders(orders)) => {
+            Ok(Json(json!(orders)))
+        },
+        _ => Err(unexpected_response())
+    }
+}
+
+async fn paper_sync(
+    State(state): State<ServerState>,
+    Extension(caller): Extension<Caller>,
+    Path(account): Path<String>,
+) -> ApiResult {
+
+    let cmd = Command::PaperSync { account: checked_account(account)? };
+
+    match run(&state.engine, caller, cmd).await? {
+        Response::Data(DataResponse::PaperOrders(fills)) => {
+            Ok(Json(json!(fills)))
+        },
+        _ => Err(unexpected_response())
+    }
+}
+
+async fn paper_buy(
+    State(state): State<ServerState>,
+    Extension(caller): Extension<Caller>,
+    Path(account): Path<String>,
+    Json(params): Json<OrderParams>,
+) -> ApiResult {
+    paper_order(&state.engine, caller, account, OrderSide::Buy, params).await
+}
+
+async fn paper_sell(
+    State(state): State<ServerState>,
+    Extension(caller): Extension<Caller>,
+    Path(account): Path<String>,
+    Json(params): Json<OrderParams>,
+) -> ApiResult {
+    paper_order(&state.engine, caller, account, OrderSide::Sell, params)
+        .await
+}
+
+async fn paper_order(
+    engine: &SharedEngine,
+    caller: Caller,
+    account: String,
+    side: OrderSide,
+    params: OrderParams
+) -> ApiResult {
+
+    if params.volume <= 0.0 {
+        return Err(bad_request(format!("Invalid volume: {}", params.volume)))
+    };
+    for (name, price) in [
+        ("limit price", params.limit),
+        ("stop price", params.stop),
+        ("OCO stop", params.oco_stop),
+        ("stop loss", params.stop_loss),
+        ("take profit", params.take_profit),
+    ] {
+        if let Some(price) = price
+            && price <= 0.0
+        {
+            return Err(bad_request(format!("Invalid {}: {}", name, price)))
+        };
+    };
+
+    let contingent: Contingent = Contingent::from_prices(
+        params.oco_stop,
+        params.stop_loss,
+        params.take_profit
+    )
+        .map_err(bad_request)?;
+
+    let cmd = Command::PaperOrder {
+        account: checked_account(account)?,
+        order: NewOrder {
+            exchange: params.exchange,
+            ticker: params.ticker,
+            side,
+            volume: params.volume,
+            limit: params.limit,
+            stop: params.stop,
+            at: params.at,
+            contingent,
+        },
+    };
+
+    match run(engine, caller, cmd).await? {
+        Response::Data(DataResponse::PaperOrder(order)) => {
+            Ok(Json(json!(order)))
+        },
+        _ => Err(unexpected_response())
+    }
+}
+
+async fn paper_cancel(
+    State(state): State<ServerState>,
+    Extension(caller): Extension<Caller>,
+    Path((account, order_id)): Path<(String, i64)>,
+) -> ApiResult {
+
+    let cmd = Command::PaperCancel {
+        account: checked_account(account)?,
+        order_id,
+    };
+
+    match run(&state.engine, caller, cmd).await? {
+        Response::Data(DataResponse::PaperOrder(order)) => {
+            Ok(Json(json!(order)))
+        },
+        _ => Err(unexpected_response())
+    }
+}
+
+fn checked_account(account: String)
+    -> Result<String, (StatusCode, Json<Value>)>
+{
+    match is_account_name(&account) {
+        true => Ok(account),
+        false => Err(bad_request(format!(
+            "Invalid account name: {}, use up to 32 letters, digits, - and _",
+            account
+        )))
+    }
+}
+
+
+/// Hands `cmd` to the engine if the caller's role allows it, recording it in
+/// the audit log and regenerating the data catalog afterwards when the 
+/// command changed stored data
+async fn run(engine: &SharedEngine, caller: Caller, cmd: Command)
+    -> Result<Response, (StatusCode, Json<Value>)>
+{
+    caller.check(&cmd)?;
+
+    let mut engine = engine.lock().await;
+    let changes_data: bool = cmd.changes_data();
+    let feature: &str = telemetry::feature_name(&cmd);
+    let audit: Option<Audited> = audited(&cmd);
+
+    let result = engine.handle(cmd).await;
+    engine.record_usage(feature, &result);
+    if let Some(entry) = &audit {
+        engine.record_audit(entry, &caller.origin(), &result).await;
+    };
+    let response = result.map_err(error_response)?;
+
+    if changes_data
+        && let Err(e) = refresh_catalog(engine.database.get_pool()).await
+    {
+        eprintln!(
+            "\x1b[33mCouldn't update the data catalog: {}\x1b[0m",
+            e
+        );
+    };
+
+    Ok(response)
+}
+
+fn error_response(e: RunTimeError) -> (StatusCode, Json<Value>) {
+
+    let status: StatusCode = match &e {
+        RunTimeError::Arguments(_)
+        | RunTimeError::Bar(_)
+        | RunTimeError::Backtest(_) => StatusCode::BAD_REQUEST,
+        RunTimeError::Preflight(
+            PreflightError::PairNotAdded { .. } | PreflightError::NoData { .. }
+        ) => StatusCode::NOT_FOUND,
+        RunTimeError::Preflight(_) => StatusCode::BAD_REQUEST,
+        RunTimeError::Paper(
+            PaperError::NoData { .. } 
+            | PaperError::NoOrder(_) 
+            | PaperError::NoAccount(_)
+        ) => StatusCode::NOT_FOUND,
+        RunTimeError::Paper(
+            PaperError::OrderRejected(_) | PaperError::InsufficientFunds(_)
+        ) => StatusCode::UNPROCESSABLE_ENTITY,
+        RunTimeError::Timeout { .. } => StatusCode::GATEWAY_TIMEOUT,
+        _ => StatusCode::INTERNAL_SERVER_ERROR
+    };
+
+    (status, Json(json!({ "error": e.to_string() })))
+}
+
+fn bad_request(message: String) -> (StatusCode, Json<Value>) {
+    (StatusCode::BAD_REQUEST, Json(json!({ "error": message })))
+}
+
+fn unexpected_response() -> (StatusCode, Json<Value>) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({ "error": "Unexpected response from the engine" }))
+    )
+}
@@ -0,0 +1,11 @@
+pub mod auth;
+pub mod flight;
+pub mod http;
+pub mod mqtt_bridge;
+pub mod redis_bridge;
+pub mod scheduler;
+pub mod sse;
+pub mod status_page;
+pub mod ws;
+pub use http::{ServerState, router, serve_http};
+pub use ws::StreamEvent;
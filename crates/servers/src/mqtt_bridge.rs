@@ -0,0 +1,363 @@
+use std::{collections::HashMap, time::Duration};
+
+use num_traits::ToPrimitive;
+use rumqttc::{
+    AsyncClient,
+    Event,
+    EventLoop,
+    LastWill,
+    MqttOptions,
+    Packet,
+    QoS,
+};
+use serde::Serialize;
+use sqlx::PgPool;
+use tokio::sync::broadcast::error::RecvError;
+
+use app_core::{
+    DataDownloadStatus,
+    app_state::{MqttSettings, PriceAlert},
+    database_ops::{
+        alerts::{FiredAlert, record_alert},
+        fetch_exchanges_and_pairs_from_db,
+        fetch_first_or_last_row,
+    },
+    report::DailyReport,
+};
+
+use crate::http::ServerState;
+
+
+/// Publishes queued while the connection is down, before `publish` waits
+const REQUEST_BUFFER: usize = 64;
+
+/// Wait before reconnecting after the connection drops
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+
+/// Published to `PREFIX/EXCHANGE/TICKER/alert` when an alert triggers
+#[derive(Debug, Serialize)]
+struct AlertEvent<'a> {
+    exchange: &'a str,
+    ticker: &'a str,
+    /// `above` or `below`
+    condition: &'static str,
+    threshold: f64,
+    price: f64,
+    /// Unix timestamp (seconds) of the tick that crossed it
+    time: u64,
+}
+
+/// The last price published for one pair
+struct LatestPrice {
+    tick_id: u64,
+    price: f64,
+}
+
+struct Publisher {
+    client: AsyncClient,
+    settings: MqttSettings,
+    db_pool: PgPool,
+}
+
+impl Publisher {
+
+    fn topic(&self, exchange: &str, ticker: &str, kind: &str) -> String {
+        format!(
+            "{}/{}/{}/{}",
+            self.settings.topic_prefix,
+            exchange,
+            ticker,
+            kind
+        )
+    }
+
+    /// Publishes the pair's latest tick when it's newer than `last`, and any
+    /// alerts its price crossed since then. Returns the new latest price.
+    async fn publish_price(
+        &self,
+        exchange: &str,
+        ticker: &str,
+        last: Option<&LatestPrice>
+    ) -> Result<Option<LatestPrice>, String> {
+
+        let rows = fetch_first_or_last_row(
+            exchange,
+            ticker,
+            self.db_pool.clone(),
+            true
+        )
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let Some((id, time, price, _)) = rows.first() else {
+            return Ok(None)
+        };
+        if last.is_some_and(|l| l.tick_id == *id) {
+            return Ok(None)
+        };
+
+        self.client
+            .publish(
+                self.topic(exchange, ticker, "price"),
+                QoS::AtLeastOnce,
+                true,
+                price.normalized().to_string()
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let latest = LatestPrice {
+            tick_id: *id,
+            price: price.to_f64().unwrap_or(f64::NAN),
+        };
+
+        // The first price seen is only what later ones are compared with
+        if let Some(last) = last {
+            for alert in self.alerts_for(exchange, ticker) {
+                for (condition, threshold) in crossed(alert, last, &latest) {
+                    let payload: String = serde_json::to_string(&AlertEvent {
+                        exchange,
+                        ticker,
+                        condition,
+                        threshold,
+                        price: latest.price,
+                        time: time / 1_000_000,
+                    }).map_err(|e| e.to_string())?;
+                    self.client
+                        .publish(
+                            self.topic(exchange, ticker, "alert"),
+                            QoS::AtLeastOnce,
+                            false,
+                            payload
+                        )
+                        .await
+                        .map_err(|e| e.to_string())?;
+
+                    let fired = FiredAlert {
+                        exchange: exchange.to_string(),
+                        ticker: ticker.to_string(),
+                        condition: condition.to_string(),
+                        threshold,
+                        price: latest.price,
+                        fired_at: String::new(),
+                    };
+                    // Sent already, so a failure only leaves it unlisted
+                    if let Err(e) = record_alert(
+                        self.db_pool.clone(),
+                        &fired,
+                        *time
+                    ).await {
+                        eprintln!("\x1b[1;31mMQTT alert: {}\x1b[0m", e);
+                    };
+                };
+            };
+        };
+
+        Ok(Some(latest))
+    }
+
+    /// Publishes `report` to `PREFIX/report`, retained so a dashboard gets
+    /// the latest as it subscribes
+    async fn publish_report(&self, report: &DailyReport)
+        -> Result<(), String>
+    {
+        let payload: String = serde_json::to_string(report)
+            .map_err(|e| e.to_string())?;
+
+        self.client
+            .publish(
+                format!("{}/report", self.settings.topic_prefix),
+                QoS::AtLeastOnce,
+                true,
+                payload
+            )
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    fn alerts_for<'a>(&'a self, exchange: &'a str, ticker: &'a str)
+        -> impl Iterator<Item = &'a PriceAlert>
+    {
+        self.settings.alerts.iter().filter(move |a| {
+            a.exchange.eq_ignore_ascii_case(exchange)
+                && a.ticker.eq_ignore_ascii_case(ticker)
+        })
+    }
+}
+
+/// The thresholds of `alert` the price moved past from `last` to `latest`
+fn crossed(alert: &PriceAlert, last: &LatestPrice, latest: &LatestPrice)
+    -> Vec<(&'static str, f64)>
+{
+    let mut hits: Vec<(&'static str, f64)> = Vec::new();
+    if let Some(above) = alert.above
+        && last.price <= above
+        && latest.price > above
+    {
+        hits.push(("above", above));
+    };
+    if let Some(below) = alert.below
+        && last.price >= below
+        && latest.price < below
+    {
+        hits.push(("below", below));
+    };
+    hits
+}
+
+/// Polls the connection until the broker accepts it
+async fn wait_for_connack(event_loop: &mut EventLoop) -> Result<(), String> {
+    loop {
+        match event_loop.poll().await {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => return Ok(()),
+            Ok(_) => {},
+            Err(e) => return Err(e.to_string())
+        };
+    };
+}
+
+
+/// Publishes every pair's latest price to MQTT, and the alerts in
+/// `settings` as prices cross them, until the server stops. Alerts are also
+/// kept in `_alert_history`, for the status page, and the daily report is
+/// published retained to `PREFIX/report`.
+///
+/// Prices are published retained when the publisher starts and after each
+/// committed download batch, so a dashboard shows them as soon as it
+/// subscribes. Fails when the broker can't be reached at first, and
+/// reconnects if it's lost later.
+pub async fn run_publisher(state: ServerState, settings: MqttSettings)
+    -> Result<(), String>
+{
+    let Some(host) = settings.host.clone() else {
+        return Ok(())
+    };
+
+    let status_topic: String = format!("{}/status", settings.topic_prefix);
+
+    let mut options = MqttOptions::new(
+        settings.client_id.clone(),
+        host.clone(),
+        settings.port
+    );
+    options.set_keep_alive(Duration::from_secs(30));
+    options.set_last_will(LastWill::new(
+        &status_topic,
+        "offline",
+        QoS::AtLeastOnce,
+        true
+    ));
+    if let Some(username) = &settings.username {
+        options.set_credentials(
+            username,
+            settings.password.clone().unwrap_or_default()
+        );
+    };
+
+    let (client, mut event_loop) = AsyncClient::new(options, REQUEST_BUFFER);
+    wait_for_connack(&mut event_loop).await?;
+
+    // Also reconnects, on the next poll after an error
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = event_loop.poll().await {
+                eprintln!("\x1b[1;31mMQTT connection: {}\x1b[0m", e);
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            };
+        };
+    });
+
+    client
+        .publish(&status_topic, QoS::AtLeastOnce, true, "online")
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut status_rx = state.status.subscribe();
+    let mut report_rx = state.reports.subscribe();
+
+    let publisher = Publisher {
+        client,
+        settings,
+        db_pool: state.db_pool.clone(),
+    };
+
+    let mut prices: HashMap<(String, String), LatestPrice> = HashMap::new();
+    for (exchange, tickers) in fetch_exchanges_and_pairs_from_db(
+        publisher.db_pool.clone(),
+        false
+    ).await {
+        for (ticker, _) in tickers {
+            let exchange: String = exchange.to_lowercase();
+            match publisher.publish_price(&exchange, &ticker, None).await {
+                Ok(Some(latest)) => {
+                    prices.insert((exchange, ticker), latest);
+                },
+                Ok(None) => {},
+                Err(e) => eprintln!(
+                    "\x1b[1;31mMQTT price for {}: {}\x1b[0m",
+                    ticker,
+                    e
+                )
+            };
+        };
+    };
+
+    println!(
+        "\x1b[1;32mPublishing to MQTT at {}:{}\x1b[0m",
+        host,
+        publisher.settings.port
+    );
+
+    loop {
+
+        let event = tokio::select! {
+            report = report_rx.recv() => {
+                if let Ok(report) = report
+                    && let Err(e) = publisher.publish_report(&report).await
+                {
+                    eprintln!("\x1b[1;31mMQTT report: {}\x1b[0m", e);
+                };
+                continue
+            },
+            event = status_rx.recv() => event,
+        };
+
+        let event: DataDownloadStatus = match event {
+            Ok(event) => event,
+            // Only the latest price is published, so the next event will do
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => return Ok(()),
+        };
+
+        if !matches!(
+            event,
+            DataDownloadStatus::Progress { .. }
+            | DataDownloadStatus::Finished { .. }
+            | DataDownloadStatus::Cancelled { .. }
+        ) {
+            continue
+        };
+
+        let (exchange, ticker) = event.exchange_and_ticker();
+        let key: (String, String) = (
+            exchange.to_lowercase(),
+            ticker.to_uppercase()
+        );
+
+        match publisher
+            .publish_price(&key.0, &key.1, prices.get(&key))
+            .await
+        {
+            Ok(Some(latest)) => {
+                prices.insert(key, latest);
+            },
+            Ok(None) => {},
+            Err(e) => eprintln!(
+                "\x1b[1;31mMQTT price for {}: {}\x1b[0m",
+                key.1,
+                e
+            )
+        };
+    };
+}
@@ -0,0 +1,462 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use redis::{AsyncCommands, Client, aio::ConnectionManager};
+use serde::Serialize;
+use sqlx::{PgPool, types::BigDecimal};
+use tokio::sync::broadcast::error::RecvError;
+
+use app_core::{
+    BarOptions,
+    BarSeries,
+    DataDownloadStatus,
+    app_state::RedisSettings,
+    backtest::{
+        CostModel,
+        Order,
+        OrderKind,
+        SimBroker,
+        Timeframes,
+        latest_orders,
+        strategy_from_spec,
+    },
+    bars::{Bar, Ohlcv},
+    build_candles,
+    build_timeframes,
+    database_ops::{
+        fetch_exchanges_and_pairs_from_db,
+        fetch_first_or_last_row,
+        fetch_rows_page,
+        signals::{Signal, record_signal},
+    },
+    report::DailyReport,
+};
+
+use crate::http::ServerState;
+
+
+/// Ticks read from the database and published per round trip to Redis
+const PAGE_TICKS: u64 = 10_000;
+
+/// Starting cash of the simulated account behind published signals
+const SIGNAL_CASH: f64 = 10_000.0;
+
+
+/// Everything published, as JSON tagged by `type`
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum BridgeEvent<'a> {
+    Tick {
+        exchange: &'a str,
+        ticker: &'a str,
+        id: u64,
+        /// Microseconds since the epoch, as stored
+        time: u64,
+        price: &'a BigDecimal,
+        volume: &'a BigDecimal,
+    },
+    Bar {
+        exchange: &'a str,
+        ticker: &'a str,
+        period: &'a str,
+        bar: &'a Ohlcv,
+    },
+    /// An order placed on the candle closing at `close_time`, for the next
+    /// one
+    Signal {
+        exchange: &'a str,
+        ticker: &'a str,
+        period: &'a str,
+        strategy: &'a str,
+        close_time: i64,
+        side: String,
+        kind: &'static str,
+        limit: Option<f64>,
+        volume: f64,
+    },
+    /// The daily summary, see `scheduler::run_daily_reports`
+    Report(&'a DailyReport),
+}
+
+/// Where publishing left off for one pair
+struct PairFeed {
+    /// Id of the last tick published
+    last_tick: u64,
+    /// Close time of the last candle published, per period
+    last_close: HashMap<String, i64>,
+}
+
+struct Bridge {
+    redis: ConnectionManager,
+    settings: RedisSettings,
+    db_pool: PgPool,
+    options: BarOptions,
+    slippage_bps: u32,
+    /// When the bridge started. Candles that closed before it aren't
+    /// published.
+    started: i64,
+}
+
+impl Bridge {
+
+    /// Picks up a pair from its latest tick, so history isn't replayed
+    async fn feed_from_now(&self, exchange: &str, ticker: &str) -> PairFeed {
+
+        let last_tick: u64 = fetch_first_or_last_row(
+            exchange,
+            ticker,
+            self.db_pool.clone(),
+            true
+        )
+            .await
+            .ok()
+            .and_then(|rows| rows.first().map(|row| row.0))
+            .unwrap_or(0);
+
+        PairFeed { last_tick, last_close: HashMap::new() }
+    }
+
+    fn channel(&self, kind: &str, parts: &[&str]) -> String {
+        format!(
+            "{}:{}:{}", 
+            self.settings.channel_prefix, 
+            kind, 
+            parts.join(":")
+        )
+    }
+
+    async fn publish(&mut self, channel: &str, event: &BridgeEvent<'_>)
+        -> Result<(), String>
+    {
+        let payload: String = serde_json::to_string(event)
+            .map_err(|e| e.to_string())?;
+
+        let _: () = self.redis
+            .publish(channel, payload)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+
+    /// Publishes the ticks written since the last call, a page at a time
+    async fn publish_ticks(
+        &mut self,
+        exchange: &str,
+        ticker: &str,
+        feed: &mut PairFeed
+    ) -> Result<(), String> {
+
+        let channel: String = self.channel("ticks", &[exchange, ticker]);
+
+        loop {
+
+            let ticks = fetch_rows_page(
+                exchange,
+                ticker,
+                feed.last_tick,
+                (None, None),
+                PAGE_TICKS,
+                self.db_pool.clone()
+            )
+                .await
+                .map_err(|e| e.to_string())?;
+
+            let Some(last) = ticks.last() else {
+                return Ok(())
+            };
+
+            let mut pipe = redis::pipe();
+            for (id, time, price, volume) in &ticks {
+                let payload: String = serde_json::to_string(
+                    &BridgeEvent::Tick {
+                        exchange,
+                        ticker,
+                        id: *id,
+                        time: *time,
+                        price,
+                        volume,
+                    }
+                ).map_err(|e| e.to_string())?;
+                pipe.publish(&channel, payload).ignore();
+            };
+
+            pipe.query_async::<()>(&mut self.redis)
+                .await
+                .map_err(|e| e.to_string())?;
+
+            feed.last_tick = last.0;
+
+            if (ticks.len() as u64) < PAGE_TICKS {
+                return Ok(())
+            };
+        };
+    }
+
+    /// Rebuilds each configured period and publishes the candles that closed
+    /// since the last call, with the signals placed on the latest of them
+    async fn publish_candles(
+        &mut self,
+        exchange: &str,
+        ticker: &str,
+        feed: &mut PairFeed
+    ) -> Result<(), String> {
+
+        for period in self.settings.candle_periods.clone() {
+
+            let series: BarSeries = build_candles(
+                exchange,
+                ticker,
+                &period,
+                self.options,
+                self.db_pool.clone()
+            ).await.map_err(|e| e.to_string())?;
+
+            let now = Utc::now();
+            let closed_len: usize = series.bars
+                .iter()
+                .take_while(|bar| bar.close_date() <= now)
+                .count();
+            let closed: &[Bar] = &series.bars[..closed_len];
+
+            let last_close: i64 = feed.last_close
+                .get(&period)
+                .copied()
+                .unwrap_or(self.started);
+            let fresh: Vec<Ohlcv> = closed
+                .iter()
+                .map(Bar::to_ohlcv)
+                .filter(|bar| bar.close_time > last_close)
+                .collect();
+
+            let Some(latest) = fresh.last() else {
+                continue
+            };
+
+            let channel: String = self.channel(
+                "candles",
+                &[exchange, ticker, period.as_str()]
+            );
+            for bar in &fresh {
+                self.publish(&channel, &BridgeEvent::Bar {
+                    exchange,
+                    ticker,
+                    period: &period,
+                    bar,
+                }).await?;
+            };
+
+            feed.last_close.insert(period.clone(), latest.close_time);
+
+            self.publish_signals(
+                exchange,
+                ticker,
+                &period,
+                closed,
+                latest.close_time
+            ).await?;
+        };
+
+        Ok(())
+    }
+
+    /// Publishes the orders each configured strategy places on the last of
+    /// `closed`. Earlier candles closed in the same update are past acting
+    /// on.
+    async fn publish_signals(
+        &mut self,
+        exchange: &str,
+        ticker: &str,
+        period: &str,
+        closed: &[Bar],
+        close_time: i64
+    ) -> Result<(), String> {
+
+        let channel: String = self.channel(
+            "signals",
+            &[exchange, ticker, period]
+        );
+
+        for spec in self.settings.strategies.clone() {
+
+            let mut strategy = strategy_from_spec(&spec)
+                .map_err(|e| e.to_string())?;
+
+            let timeframes: Timeframes = build_timeframes(
+                exchange,
+                ticker,
+                &strategy.timeframes(),
+                self.options,
+                self.db_pool.clone()
+            ).await.map_err(|e| e.to_string())?;
+
+            let orders: Vec<Order> = latest_orders(
+                closed,
+                &timeframes,
+                strategy.as_mut(),
+                SimBroker::new(
+                    CostModel::slippage_only(self.slippage_bps),
+                    SIGNAL_CASH
+                )
+            ).map_err(|e| e.to_string())?;
+
+            for order in orders {
+
+                let (kind, limit) = match order.kind {
+                    OrderKind::Market => ("market", None),
+                    OrderKind::Limit(price) => ("limit", Some(price))
+                };
+
+                self.publish(&channel, &BridgeEvent::Signal {
+                    exchange,
+                    ticker,
+                    period,
+                    strategy: &spec,
+                    close_time,
+                    side: order.side.to_string(),
+                    kind,
+                    limit,
+                    volume: order.volume,
+                }).await?;
+
+                let signal = Signal {
+                    exchange: exchange.to_string(),
+                    ticker: ticker.to_string(),
+                    period: period.to_string(),
+                    strategy: spec.clone(),
+                    side: order.side.to_string(),
+                    kind: kind.to_string(),
+                    limit,
+                    volume: order.volume,
+                    close_time,
+                };
+                // Published already, so a failure only leaves it uncounted
+                if let Err(e) = record_signal(
+                    self.db_pool.clone(),
+                    &signal
+                ).await {
+                    eprintln!("\x1b[1;31mRedis signal: {}\x1b[0m", e);
+                };
+            };
+        };
+
+        Ok(())
+    }
+}
+
+
+/// Publishes new ticks, closed candles and strategy signals to Redis as
+/// downloads finish, and the daily report to `PREFIX:report`, until the
+/// server stops. Signals are also kept in `_signal_history`.
+///
+/// Only what arrives after the bridge starts is published. A pair added
+/// while it runs is picked up from its first download. Fails when Redis
+/// can't be reached or a strategy spec is invalid.
+pub async fn run_bridge(
+    state: ServerState,
+    settings: RedisSettings,
+    slippage_bps: u32
+) -> Result<(), String> {
+
+    let Some(url) = settings.url.clone() else {
+        return Ok(())
+    };
+
+    for spec in &settings.strategies {
+        strategy_from_spec(spec).map_err(|e| e.to_string())?;
+    };
+
+    let client: Client = Client::open(url.as_str())
+        .map_err(|e| e.to_string())?;
+    let redis: ConnectionManager = ConnectionManager::new(client)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut status_rx = state.status.subscribe();
+    let mut report_rx = state.reports.subscribe();
+
+    let mut bridge = Bridge {
+        redis,
+        settings,
+        db_pool: state.db_pool.clone(),
+        options: state.bar_options,
+        slippage_bps,
+        started: Utc::now().timestamp(),
+    };
+
+    let mut feeds: HashMap<(String, String), PairFeed> = HashMap::new();
+    for (exchange, tickers) in fetch_exchanges_and_pairs_from_db(
+        bridge.db_pool.clone(),
+        false
+    ).await {
+        for (ticker, _) in tickers {
+            let exchange: String = exchange.to_lowercase();
+            let feed: PairFeed = bridge.feed_from_now(&exchange, &ticker).await;
+            feeds.insert((exchange, ticker), feed);
+        };
+    };
+
+    println!("\x1b[1;32mPublishing to Redis at {}\x1b[0m", url);
+
+    let report_channel: String = format!(
+        "{}:report",
+        bridge.settings.channel_prefix
+    );
+
+    loop {
+
+        let event = tokio::select! {
+            report = report_rx.recv() => {
+                if let Ok(report) = report
+                    && let Err(e) = bridge.publish(
+                        &report_channel,
+                        &BridgeEvent::Report(&report)
+                    ).await
+                {
+                    eprintln!("\x1b[1;31mRedis report: {}\x1b[0m", e);
+                };
+                continue
+            },
+            event = status_rx.recv() => event,
+        };
+
+        let event: DataDownloadStatus = match event {
+            Ok(event) => event,
+            // Ticks are read by id, so the next update catches up on them
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => return Ok(()),
+        };
+
+        let (exchange, ticker) = event.exchange_and_ticker();
+        let key: (String, String) = (
+            exchange.to_lowercase(),
+            ticker.to_uppercase()
+        );
+
+        if !feeds.contains_key(&key) {
+            let feed: PairFeed = bridge.feed_from_now(&key.0, &key.1).await;
+            feeds.insert(key.clone(), feed);
+        };
+
+        if !matches!(
+            event,
+            DataDownloadStatus::Finished { .. }
+            | DataDownloadStatus::Cancelled { .. }
+        ) {
+            continue
+        };
+
+        let (exchange, ticker) = (key.0.as_str(), key.1.as_str());
+        let Some(feed) = feeds.get_mut(&key) else {
+            continue
+        };
+
+        // Whatever failed is tried again on the pair's next update
+        if let Err(e) = bridge.publish_ticks(exchange, ticker, feed).await {
+            eprintln!("\x1b[1;31mRedis ticks for {}: {}\x1b[0m", ticker, e);
+        };
+        if let Err(e) = bridge.publish_candles(exchange, ticker, feed).await {
+            eprintln!("\x1b[1;31mRedis candles for {}: {}\x1b[0m", ticker, e);
+        };
+    };
+}
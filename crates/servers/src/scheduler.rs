@@ -0,0 +1,124 @@
+use std::time::Duration;
+
+use tokio::time::{interval, sleep};
+
+use app_core::{
+    app_state::LiveTradingSettings,
+    database_ops::jobs::last_report_age,
+    paper::{CostModels, record_equity},
+    recurring::run_due,
+    report::{
+        DAILY_REPORT,
+        REPORT_WINDOW,
+        build_daily_report,
+        store_report,
+    },
+};
+
+use crate::http::ServerState;
+
+
+/// Time between two looks for recurring buys that are due
+const RECURRING_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Time between two snapshots of the paper accounts' equity
+const EQUITY_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// Wait before trying again after a daily report fails
+const REPORT_RETRY: Duration = Duration::from_secs(15 * 60);
+
+/// Time the sinks have to connect before a report due at startup is sent
+const SINK_GRACE: Duration = Duration::from_secs(30);
+
+
+/// Places the recurring buys as they come due, see `recurring::run_due`,
+/// for as long as the server runs
+///
+/// Buys are placed without holding the engine, so they don't wait on a
+/// long `/update`.
+pub async fn run_recurring_buys(
+    state: ServerState,
+    live_settings: LiveTradingSettings,
+    slippage_bps: u32
+) {
+    let client = state.engine.lock().await.request_client.clone();
+    let mut cost_models = CostModels::new(slippage_bps, client.clone());
+    let mut ticks = interval(RECURRING_INTERVAL);
+
+    loop {
+        ticks.tick().await;
+
+        match run_due(
+            &live_settings,
+            &mut cost_models,
+            &client,
+            &state.db_pool
+        ).await {
+            Ok(runs) => for run in runs {
+                println!("\x1b[1;36mRecurring buy\x1b[0m {}", run);
+            },
+            Err(e) => eprintln!("\x1b[1;31mRecurring buys: {}\x1b[0m", e)
+        };
+    };
+}
+
+/// Records the equity of every paper account each `EQUITY_INTERVAL`, see
+/// `paper::record_equity`, for the equity curves of the status page
+pub async fn run_equity_snapshots(state: ServerState) {
+
+    let mut ticks = interval(EQUITY_INTERVAL);
+
+    loop {
+        ticks.tick().await;
+
+        if let Err(e) = record_equity(&state.db_pool).await {
+            eprintln!("\x1b[1;31mPaper equity snapshot: {}\x1b[0m", e);
+        };
+    };
+}
+
+/// Builds the daily report each `REPORT_WINDOW`, see
+/// `report::build_daily_report`, keeps it in `_report_history` and hands it
+/// to the Redis and MQTT sinks
+///
+/// The schedule follows the last report stored, so a server that was down
+/// when one was due sends it shortly after it starts.
+pub async fn run_daily_reports(state: ServerState) {
+
+    let mut earliest: Duration = SINK_GRACE;
+
+    loop {
+        let wait: Duration = match last_report_age(
+            DAILY_REPORT,
+            state.db_pool.clone()
+        ).await {
+            Ok(Some(age)) => REPORT_WINDOW.saturating_sub(age),
+            Ok(None) => Duration::ZERO,
+            Err(e) => {
+                eprintln!("\x1b[1;31mDaily report: {}\x1b[0m", e);
+                REPORT_RETRY
+            }
+        };
+        sleep(wait.max(earliest)).await;
+        earliest = Duration::ZERO;
+
+        let report = match build_daily_report(&state.db_pool).await {
+            Ok(report) => report,
+            Err(e) => {
+                eprintln!("\x1b[1;31mDaily report: {}\x1b[0m", e);
+                sleep(REPORT_RETRY).await;
+                continue
+            }
+        };
+
+        if let Err(e) = store_report(&report, &state.db_pool).await {
+            eprintln!("\x1b[1;31mDaily report: {}\x1b[0m", e);
+            sleep(REPORT_RETRY).await;
+            continue
+        };
+
+        print!("{}", report);
+        // No receivers only means no sink is configured
+        let _ = state.reports.send(report);
+    };
+}
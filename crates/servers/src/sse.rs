@@ -0,0 +1,67 @@
+use std::convert::Infallible;
+
+use axum::{
+    extract::{Query, State},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use serde::Deserialize;
+use serde_json::Value;
+use tokio_stream::{Stream, StreamExt, wrappers::BroadcastStream};
+
+use app_core::DataDownloadStatus;
+
+use crate::http::ServerState;
+
+
+/// Optionally narrows the stream to one exchange, or one pair
+#[derive(Deserialize)]
+pub(crate) struct ProgressParams {
+    exchange: Option<String>,
+    ticker: Option<String>,
+}
+
+impl ProgressParams {
+
+    fn matches(&self, status: &DataDownloadStatus) -> bool {
+        let (exchange, ticker) = status.exchange_and_ticker();
+        let wanted = |filter: &Option<String>, value: &str| {
+            filter.as_ref().is_none_or(|f| f.eq_ignore_ascii_case(value))
+        };
+
+        wanted(&self.exchange, exchange) && wanted(&self.ticker, ticker)
+    }
+}
+
+
+/// Relays download progress as Server-Sent Events, so a browser can follow
+/// a `POST /update` without polling
+///
+/// Each event is named after its status, e.g. `progress` or `finished`, and
+/// carries the status as JSON. A client that falls behind skips the events
+/// it missed.
+pub(crate) async fn progress(
+    State(state): State<ServerState>,
+    Query(params): Query<ProgressParams>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+
+    let events = BroadcastStream::new(state.status.subscribe())
+        .filter_map(move |status| match status {
+            Ok(status) if params.matches(&status) => {
+                Some(Ok(event(&status)))
+            },
+            _ => None
+        });
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+fn event(status: &DataDownloadStatus) -> Event {
+
+    let data: Value = serde_json::to_value(status).unwrap_or_default();
+    let name: &str = data
+        .get("status")
+        .and_then(Value::as_str)
+        .unwrap_or("status");
+
+    Event::default().event(name).data(data.to_string())
+}
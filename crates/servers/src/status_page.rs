@@ -0,0 +1,504 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    time::Duration,
+};
+
+use axum::{extract::State, response::Html};
+use chrono::{DateTime, Utc};
+use num_traits::ToPrimitive;
+
+use app_core::{
+    database_ops::{
+        alerts::{FiredAlert, recent_alerts},
+        fetch_exchanges_and_pairs_from_db,
+        fetch_first_or_last_row,
+        jobs::{JobRecord, recent_jobs},
+        lineage::{IngestRate, ingest_rates},
+    },
+    job_registry::Job,
+    paper::{PaperAccount, account_names, equity_curve, load_account},
+};
+
+use crate::http::ServerState;
+
+
+/// Seconds between two reloads of the page
+const REFRESH_SECS: u64 = 30;
+
+/// Rows of the job history shown
+const JOBS_SHOWN: usize = 20;
+
+/// Alerts shown, out of those fired in `DAY`
+const ALERTS_SHOWN: usize = 20;
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How far back the equity curves go
+const CURVE_SPAN: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+const CURVE_WIDTH: f64 = 320.0;
+const CURVE_HEIGHT: f64 = 60.0;
+
+const STYLE: &str = "body{font-family:monospace;margin:2em;background:#111;\
+    color:#ddd}h2{color:#6cf;margin-top:1.5em}table{border-collapse:\
+    collapse}td,th{padding:2px 12px;text-align:left;border-bottom:1px \
+    solid #333}.bad{color:#f66}.good{color:#6f6}.note{color:#888}\
+    svg{background:#1a1a1a}polyline{fill:none;stroke:#6cf;stroke-width:1.5}";
+
+
+/// Serves a status page rendered on the server, for a browser without the
+/// dashboard: the job queue, what each pair took in over the last hour and
+/// day, how old each pair's last tick is, the alerts of the last day and
+/// the equity curve of each paper account
+///
+/// The page reloads itself, keeping its query, so a key given as
+/// `?api_key=` still applies. Each section is rendered on its own, and one
+/// that can't be read says why instead of failing the page.
+pub(crate) async fn status(State(state): State<ServerState>) -> Html<String> {
+
+    let mut page = String::new();
+    let _ = write!(
+        page,
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\">\
+        <meta http-equiv=\"refresh\" content=\"{}\">\
+        <title>Trading app status</title><style>{}</style></head><body>\
+        <h1>Status</h1><p class=\"note\">As of {} UTC</p>",
+        REFRESH_SECS,
+        STYLE,
+        Utc::now().format("%Y-%m-%d %H:%M:%S")
+    );
+
+    job_section(&mut page, &state).await;
+    ingest_section(&mut page, &state).await;
+    freshness_section(&mut page, &state).await;
+    alert_section(&mut page, &state).await;
+    equity_section(&mut page, &state).await;
+
+    page.push_str("</body></html>");
+    Html(page)
+}
+
+
+/// The jobs still queued or running, then the latest downloads to end
+async fn job_section(page: &mut String, state: &ServerState) {
+
+    page.push_str("<h2>Job queue</h2>");
+
+    let active: Vec<Job> = state.jobs
+        .snapshot()
+        .into_iter()
+        .filter(|j| !j.state.is_done())
+        .collect();
+
+    match active.is_empty() {
+        true => page.push_str("<p class=\"note\">No jobs running</p>"),
+        false => {
+            page.push_str(
+                "<table><tr><th>ID</th><th>Kind</th><th>Pair</th>\
+                <th>State</th><th>Progress</th><th>Started</th></tr>"
+            );
+            for job in &active {
+                let _ = write!(
+                    page,
+                    "<tr><td>{}</td><td>{}</td><td>{} {}</td><td>{}</td>\
+                    <td>{}</td><td>{}</td></tr>",
+                    job.id,
+                    job.kind,
+                    escape(&job.exchange),
+                    escape(&job.ticker),
+                    job.state,
+                    job.progress
+                        .map(|p| format!("{}%", p))
+                        .unwrap_or_default(),
+                    date_time(job.started_at)
+                );
+            };
+            page.push_str("</table>");
+        }
+    };
+
+    page.push_str("<h2>Recent downloads</h2>");
+
+    let history: Vec<JobRecord> = match recent_jobs(
+        JOBS_SHOWN,
+        state.db_pool.clone()
+    ).await {
+        Ok(history) => history,
+        Err(e) => return unavailable(page, &e.to_string())
+    };
+    if history.is_empty() {
+        return page.push_str("<p class=\"note\">No downloads yet</p>")
+    };
+
+    page.push_str(
+        "<table><tr><th>Finished</th><th>Job</th><th>Pair</th>\
+        <th>Outcome</th><th>Error</th></tr>"
+    );
+    for job in &history {
+        let _ = write!(
+            page,
+            "<tr><td>{}</td><td>{}</td><td>{} {}</td>\
+            <td class=\"{}\">{}</td><td>{}</td></tr>",
+            job.finished_at,
+            job.job_id,
+            escape(&job.exchange),
+            escape(&job.ticker),
+            match job.error.is_some() {
+                true => "bad",
+                false => "good"
+            },
+            job.outcome.as_str(),
+            escape(job.error.as_deref().unwrap_or(""))
+        );
+    };
+    page.push_str("</table>");
+}
+
+/// Ticks and batches per pair over the last hour and day
+async fn ingest_section(page: &mut String, state: &ServerState) {
+
+    page.push_str("<h2>Ingestion</h2>");
+
+    let (hour, day) = match tokio::try_join!(
+        ingest_rates(HOUR, state.db_pool.clone()),
+        ingest_rates(DAY, state.db_pool.clone())
+    ) {
+        Ok(rates) => rates,
+        Err(e) => return unavailable(page, &e.to_string())
+    };
+    if day.is_empty() {
+        return page.push_str(
+            "<p class=\"note\">Nothing downloaded in the last 24h</p>"
+        )
+    };
+
+    let last_hour: BTreeMap<(&str, &str), &IngestRate> = hour
+        .iter()
+        .map(|r| ((r.exchange.as_str(), r.ticker.as_str()), r))
+        .collect();
+
+    page.push_str(
+        "<table><tr><th>Pair</th><th>Ticks 1h</th><th>Ticks 24h</th>\
+        <th>Ticks/h 24h</th><th>Batches 24h</th></tr>"
+    );
+    for rate in &day {
+        let _ = write!(
+            page,
+            "<tr><td>{} {}</td><td>{}</td><td>{}</td><td>{:.1}</td>\
+            <td>{}</td></tr>",
+            escape(&rate.exchange),
+            escape(&rate.ticker),
+            last_hour
+                .get(&(rate.exchange.as_str(), rate.ticker.as_str()))
+                .map_or(0, |r| r.ticks),
+            rate.ticks,
+            rate.ticks as f64 / (DAY.as_secs() / HOUR.as_secs()) as f64,
+            rate.batches
+        );
+    };
+    page.push_str("</table>");
+}
+
+/// The time of each stored pair's last tick, and how long ago it was
+async fn freshness_section(page: &mut String, state: &ServerState) {
+
+    page.push_str("<h2>Data freshness</h2>");
+
+    let pairs: BTreeMap<String, Vec<String>> =
+        fetch_exchanges_and_pairs_from_db(state.db_pool.clone(), false)
+        .await
+        .into_iter()
+        .map(|(exchange, tickers)| (
+            exchange.to_lowercase(),
+            tickers.into_iter().map(|(ticker, _)| ticker).collect()
+        ))
+        .collect();
+
+    if pairs.values().all(Vec::is_empty) {
+        return page.push_str("<p class=\"note\">No pairs stored</p>")
+    };
+
+    let now: i64 = Utc::now().timestamp();
+
+    page.push_str(
+        "<table><tr><th>Pair</th><th>Last tick</th><th>Price</th>\
+        <th>Age</th></tr>"
+    );
+    for (exchange, tickers) in &pairs {
+        let mut tickers: Vec<&String> = tickers.iter().collect();
+        tickers.sort();
+
+        for ticker in tickers {
+            let last = fetch_first_or_last_row(
+                exchange,
+                ticker,
+                state.db_pool.clone(),
+                true
+            ).await;
+
+            let _ = write!(
+                page,
+                "<tr><td>{} {}</td>",
+                escape(exchange),
+                escape(ticker)
+            );
+            let _ = match last.as_deref() {
+                Ok([(_, time, price, _), ..]) => {
+                    let secs: u64 = time / 1_000_000;
+                    write!(
+                        page,
+                        "<td>{}</td><td>{}</td><td>{}</td></tr>",
+                        date_time(secs),
+                        price.to_f64().unwrap_or(f64::NAN),
+                        age(now.saturating_sub(secs as i64).max(0) as u64)
+                    )
+                },
+                Ok([]) => write!(
+                    page,
+                    "<td colspan=\"3\" class=\"note\">No ticks</td></tr>"
+                ),
+                Err(e) => write!(
+                    page,
+                    "<td colspan=\"3\" class=\"bad\">{}</td></tr>",
+                    escape(&e.to_string())
+                )
+            };
+        };
+    };
+    page.push_str("</table>");
+}
+
+/// The latest alerts fired by the MQTT publisher in the last day
+async fn alert_section(page: &mut String, state: &ServerState) {
+
+    page.push_str("<h2>Recent alerts</h2>");
+
+    let alerts: Vec<FiredAlert> = match recent_alerts(
+        DAY,
+        state.db_pool.clone()
+    ).await {
+        Ok(alerts) => alerts,
+        Err(e) => return unavailable(page, &e.to_string())
+    };
+    if alerts.is_empty() {
+        return page.push_str(
+            "<p class=\"note\">No alerts in the last 24h. Alerts fire \
+            while the MQTT publisher runs, see mqtt.alerts</p>"
+        )
+    };
+
+    page.push_str(
+        "<table><tr><th>Fired</th><th>Pair</th><th>Crossed</th>\
+        <th>Price</th></tr>"
+    );
+    for alert in alerts.iter().take(ALERTS_SHOWN) {
+        let _ = write!(
+            page,
+            "<tr><td>{}</td><td>{} {}</td><td>{} {}</td><td>{}</td></tr>",
+            alert.fired_at,
+            escape(&alert.exchange),
+            escape(&alert.ticker),
+            escape(&alert.condition),
+            alert.threshold,
+            alert.price
+        );
+    };
+    page.push_str("</table>");
+    if alerts.len() > ALERTS_SHOWN {
+        let _ = write!(
+            page,
+            "<p class=\"note\">{} more in the last 24h</p>",
+            alerts.len() - ALERTS_SHOWN
+        );
+    };
+}
+
+/// Each paper account's equity now and over the last week
+async fn equity_section(page: &mut String, state: &ServerState) {
+
+    page.push_str("<h2>Paper accounts</h2>");
+
+    let names: Vec<String> = match account_names(&state.db_pool).await {
+        Ok(names) => names,
+        Err(e) => return unavailable(page, &e.to_string())
+    };
+    if names.is_empty() {
+        return page.push_str("<p class=\"note\">No paper accounts</p>")
+    };
+
+    let since: u64 = (Utc::now().timestamp().max(0) as u64)
+        .saturating_sub(CURVE_SPAN.as_secs());
+
+    page.push_str(
+        "<table><tr><th>Account</th><th>Equity</th><th>Return</th>\
+        <th>Last 7 days</th></tr>"
+    );
+    for name in &names {
+        let account: PaperAccount = match load_account(
+            name,
+            &state.db_pool
+        ).await {
+            Ok(account) => account,
+            Err(e) => {
+                let _ = write!(
+                    page,
+                    "<tr><td>{}</td><td colspan=\"3\" class=\"bad\">{}\
+                    </td></tr>",
+                    escape(name),
+                    escape(&e.to_string())
+                );
+                continue
+            }
+        };
+
+        let curve: String = match equity_curve(
+            name,
+            since,
+            &state.db_pool
+        ).await {
+            Ok(points) if points.len() > 1 => sparkline(&points),
+            Ok(_) => "<span class=\"note\">Not enough snapshots yet\
+                </span>".to_string(),
+            Err(e) => format!(
+                "<span class=\"bad\">{}</span>",
+                escape(&e.to_string())
+            )
+        };
+
+        let _ = write!(
+            page,
+            "<tr><td>{}</td><td>{:.2}</td><td class=\"{}\">{:+.2}%</td>\
+            <td>{}</td></tr>",
+            escape(name),
+            account.equity,
+            match account.return_pct() < 0.0 {
+                true => "bad",
+                false => "good"
+            },
+            account.return_pct(),
+            curve
+        );
+    };
+    page.push_str("</table>");
+}
+
+
+/// Stands in for a section whose data couldn't be read
+fn unavailable(page: &mut String, error: &str) {
+    let _ = write!(
+        page,
+        "<p class=\"bad\">Unavailable: {}</p>",
+        escape(error)
+    );
+}
+
+/// `text` made safe to put in HTML
+fn escape(text: &str) -> String {
+    text.chars().fold(String::with_capacity(text.len()), |mut out, c| {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        };
+        out
+    })
+}
+
+/// Unix seconds as `YYYY-MM-DD HH:MM:SS` in UTC
+fn date_time(secs: u64) -> String {
+    DateTime::from_timestamp(secs.min(i64::MAX as u64) as i64, 0)
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default()
+}
+
+/// A duration in its two largest units, e.g. `3h 12m`
+fn age(secs: u64) -> String {
+    match secs {
+        s if s < 60 => format!("{}s", s),
+        s if s < 3_600 => format!("{}m {}s", s / 60, s % 60),
+        s if s < 86_400 => format!("{}h {}m", s / 3_600, s % 3_600 / 60),
+        s => format!("{}d {}h", s / 86_400, s % 86_400 / 3_600),
+    }
+}
+
+/// An inline SVG line of `(time, equity)` points, at least two, scaled to
+/// fill the chart
+fn sparkline(points: &[(u64, f64)]) -> String {
+
+    let (first, last) = (points[0].0, points[points.len() - 1].0);
+    let (low, high) = points
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), p| {
+            (low.min(p.1), high.max(p.1))
+        });
+
+    let span: f64 = (last.saturating_sub(first)).max(1) as f64;
+    // A flat curve is drawn across the middle
+    let range: f64 = match high > low {
+        true => high - low,
+        false => 1.0
+    };
+    let offset: f64 = match high > low {
+        true => 0.0,
+        false => CURVE_HEIGHT / 2.0
+    };
+
+    let coords: Vec<String> = points
+        .iter()
+        .map(|(time, equity)| format!(
+            "{:.1},{:.1}",
+            (time - first) as f64 / span * CURVE_WIDTH,
+            CURVE_HEIGHT - (equity - low) / range * CURVE_HEIGHT - offset
+        ))
+        .collect();
+
+    format!(
+        "<svg width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\
+        <title>{:.2} to {:.2}</title><polyline points=\"{}\"/></svg>",
+        points[0].1,
+        points[points.len() - 1].1,
+        coords.join(" "),
+        w = CURVE_WIDTH,
+        h = CURVE_HEIGHT
+    )
+}
+
+
+// -------------------------- UNIT TESTING --------------------------------- //
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn escape_test() {
+        assert_eq!(
+            escape("<b class=\"x\">A&B's</b>"),
+            "&lt;b class=&quot;x&quot;&gt;A&amp;B&#39;s&lt;/b&gt;"
+        );
+        assert_eq!(escape("XBTUSD"), "XBTUSD");
+    }
+
+    #[test]
+    fn age_test() {
+        assert_eq!(age(42), "42s");
+        assert_eq!(age(192), "3m 12s");
+        assert_eq!(age(3 * 3_600 + 12 * 60), "3h 12m");
+        assert_eq!(age(2 * 86_400 + 5 * 3_600), "2d 5h");
+    }
+
+    #[test]
+    fn sparkline_test() {
+        let rising: String =
+            sparkline(&[(0, 100.0), (50, 150.0), (100, 200.0)]);
+        assert!(rising.contains("points=\"0.0,60.0 160.0,30.0 320.0,0.0\""));
+
+        let flat: String = sparkline(&[(10, 5.0), (20, 5.0)]);
+        assert!(flat.contains("points=\"0.0,30.0 320.0,30.0\""));
+    }
+}
@@ -0,0 +1,232 @@
+use axum::{
+    Json,
+    extract::{
+        Query,
+        State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use tokio::sync::broadcast::error::RecvError;
+
+use app_core::{
+    BarOptions,
+    BarSeries,
+    DataDownloadStatus,
+    bars::Ohlcv,
+    build_candles,
+    database_ops::Ticker,
+};
+
+use crate::http::ServerState;
+
+
+/// Everything pushed to `/ws` clients, as JSON text frames tagged by `type`
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent {
+    Download(DataDownloadStatus),
+    Bar {
+        exchange: String,
+        ticker: String,
+        period: String,
+        bar: Ohlcv,
+    },
+    Error {
+        message: String,
+    },
+}
+
+
+/// Bars for one pair and period, sent as they close
+#[derive(Deserialize)]
+pub(crate) struct StreamParams {
+    exchange: Option<String>,
+    ticker: Option<Ticker>,
+    period: Option<String>,
+}
+
+struct BarStream {
+    exchange: String,
+    ticker: String,
+    period: String,
+    options: BarOptions,
+    db_pool: PgPool,
+    /// Close time of the last bar sent, or of the last closed bar when the
+    /// client connected
+    last_close: Option<i64>,
+}
+
+impl BarStream {
+
+    /// Rebuilds the series and returns the bars that closed since the last
+    /// call
+    async fn closed_bars(&mut self) -> Result<Vec<Ohlcv>, String> {
+
+        let series: BarSeries = build_candles(
+            &self.exchange,
+            &self.ticker,
+            &self.period,
+            self.options,
+            self.db_pool.clone()
+        ).await.map_err(|e| e.to_string())?;
+
+        let now = Utc::now();
+        let closed: Vec<Ohlcv> = series
+            .bars
+            .iter()
+            .filter(|bar| bar.close_date() <= now)
+            .map(|bar| bar.to_ohlcv())
+            .filter(|bar| self.last_close.is_none_or(|t| bar.close_time > t))
+            .collect();
+
+        if let Some(bar) = closed.last() {
+            self.last_close = Some(bar.close_time);
+        };
+
+        Ok(closed)
+    }
+
+    fn event(&self, bar: Ohlcv) -> StreamEvent {
+        StreamEvent::Bar {
+            exchange: self.exchange.clone(),
+            ticker: self.ticker.clone(),
+            period: self.period.clone(),
+            bar,
+        }
+    }
+}
+
+
+/// Upgrades to a WebSocket that streams download progress, plus closed bars
+/// when `exchange`, `ticker` and `period` are all given
+///
+/// Bars are rebuilt whenever a download for the pair finishes or is
+/// cancelled, and only bars that closed after the client connected are sent.
+pub(crate) async fn stream(
+    ws: WebSocketUpgrade,
+    State(state): State<ServerState>,
+    Query(params): Query<StreamParams>,
+) -> Response {
+
+    let (exchange, ticker, period) = match (
+        params.exchange, params.ticker, params.period
+    ) {
+        (Some(exchange), Some(ticker), Some(period)) => {
+            (exchange, ticker, period)
+        },
+        (None, None, None) => {
+            return ws.on_upgrade(move |socket| relay(socket, state, None))
+        },
+        _ => return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "Bar streams need an exchange, ticker and period"
+            }))
+        ).into_response()
+    };
+
+    let bars = BarStream {
+        exchange,
+        ticker: ticker.into(),
+        period,
+        options: state.bar_options,
+        db_pool: state.db_pool.clone(),
+        last_close: None,
+    };
+
+    ws.on_upgrade(move |socket| relay(socket, state, Some(bars)))
+}
+
+
+async fn relay(
+    mut socket: WebSocket,
+    state: ServerState,
+    mut bars: Option<BarStream>
+) {
+    let mut status_rx = state.status.subscribe();
+
+    // Marks where the stream starts, so history isn't replayed
+    if let Some(stream) = bars.as_mut()
+        && let Err(message) = stream.closed_bars().await
+    {
+        let _ = send(&mut socket, &StreamEvent::Error { message }).await;
+        return
+    };
+
+    loop {
+        tokio::select! {
+
+            event = status_rx.recv() => {
+
+                let event: DataDownloadStatus = match event {
+                    Ok(event) => event,
+                    // A slow client misses progress updates rather than
+                    // holding up the download
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                };
+
+                let refresh: bool = matches!(
+                    event,
+                    DataDownloadStatus::Finished { .. }
+                    | DataDownloadStatus::Cancelled { .. }
+                ) && bars.as_ref().is_some_and(|b| {
+                    event.exchange_and_ticker() 
+                        == (b.exchange.as_str(), b.ticker.as_str())
+                });
+
+                if send(&mut socket, &StreamEvent::Download(event))
+                    .await
+                    .is_err()
+                {
+                    break
+                };
+
+                if refresh && let Some(stream) = bars.as_mut() {
+
+                    let events: Vec<StreamEvent> = match stream
+                        .closed_bars()
+                        .await
+                    {
+                        Ok(closed) => closed
+                            .into_iter()
+                            .map(|bar| stream.event(bar))
+                            .collect(),
+                        Err(message) => vec![StreamEvent::Error { message }]
+                    };
+
+                    for event in events {
+                        if send(&mut socket, &event).await.is_err() {
+                            return
+                        };
+                    };
+                };
+            },
+
+            message = socket.recv() => match message {
+                // Pings are answered by axum, and clients have nothing else
+                // to say
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    };
+}
+
+async fn send(socket: &mut WebSocket, event: &StreamEvent)
+    -> Result<(), axum::Error>
+{
+    let text: String = serde_json::to_string(event)
+        .unwrap_or_else(|e| json!({
+            "type": "error",
+            "message": e.to_string()
+        }).to_string());
+
+    socket.send(Message::Text(text.into())).await
+}
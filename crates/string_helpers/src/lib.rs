@@ -1,3 +1,6 @@
+pub mod locale;
+pub use locale::{DateStyle, Locale};
+
 
 pub fn capitlize_first_letter(input_string: &String) -> String {
     
@@ -31,18 +34,18 @@ pub fn multi_line_to_single_line(
     for c in multi_line_str.chars() {
     
         if c != ' ' && c != '\n' {
-            word_buffer.push_str(&c.to_string());
+            word_buffer.push(c);
             c_count += 1;
         }
         
-        else if c == ' ' && word_buffer.len() > 0 {
+        else if c == ' ' && !word_buffer.is_empty() {
             new_msg.push_str(&format!("{} ", word_buffer));
             word_buffer = String::new();
             c_count += 1;
         };
         
         if c_count > max_line_len {
-            new_msg.push_str("\n");
+            new_msg.push('\n');
             c_count = word_buffer.len() as u16;
         }
         
@@ -51,10 +54,34 @@ pub fn multi_line_to_single_line(
     let next_len: u16 = word_buffer.len() as u16;
     if next_len > 0 {
         if c_count + next_len > width {
-            new_msg.push_str("\n");
+            new_msg.push('\n');
         };
         new_msg.push_str(&word_buffer);
     };
     new_msg 
 }
 
+
+
+/// Renders values as a one-line sparkline using block characters
+pub fn sparkline(values: &[f64]) -> String {
+
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    let min = values.iter().cloned().fold(f64::MAX, f64::min);
+    let max = values.iter().cloned().fold(f64::MIN, f64::max);
+    let range = max - min;
+
+    values
+        .iter()
+        .map(|v| {
+            if range <= 0.0 {
+                BLOCKS[0]
+            }
+            else {
+                let i = (((v - min) / range) * 7.0).round() as usize;
+                BLOCKS[i.min(7)]
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,101 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+
+
+/// How dates are written in displays and exports
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateStyle {
+    /// Seconds since the epoch (UTC)
+    #[default]
+    Unix,
+    /// ISO 8601 in UTC, e.g. `2026-01-01T00:00:00Z`
+    Iso,
+    /// The local time zone, e.g. `2026-01-01 01:00:00`
+    Local,
+}
+
+impl FromStr for DateStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match &s.to_lowercase()[..] {
+            "unix" => Ok(DateStyle::Unix),
+            "iso" => Ok(DateStyle::Iso),
+            "local" => Ok(DateStyle::Local),
+            _ => Err(format!("Unknown date style: {}", s))
+        }
+    }
+}
+
+impl fmt::Display for DateStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DateStyle::Unix => write!(f, "unix"),
+            DateStyle::Iso => write!(f, "iso"),
+            DateStyle::Local => write!(f, "local"),
+        }
+    }
+}
+
+
+/// Date and number formatting preferences
+///
+/// With `decimal_comma` set, numbers are written as `1234,5` and CSV files
+/// switch to `;` as their delimiter, which is what spreadsheet software in
+/// most of Europe expects.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Locale {
+    pub date_style: DateStyle,
+    pub decimal_comma: bool,
+}
+
+impl Locale {
+
+    pub fn format_date(&self, date: DateTime<Utc>) -> String {
+        match self.date_style {
+            DateStyle::Unix => date.timestamp().to_string(),
+            DateStyle::Iso => date.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            DateStyle::Local => date
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string(),
+        }
+    }
+
+    /// Swaps the decimal point for a comma when configured to
+    pub fn format_number<T: fmt::Display>(&self, value: T) -> String {
+        let text: String = value.to_string();
+        match self.decimal_comma {
+            true => text.replace('.', ","),
+            false => text
+        }
+    }
+
+    /// Groups a count's thousands, e.g. `1,234,567`, or `1.234.567` with a
+    /// decimal comma
+    pub fn format_count(&self, count: u64) -> String {
+        let separator: char = match self.decimal_comma {
+            true => '.',
+            false => ','
+        };
+        let digits: String = count.to_string();
+        let mut text: String = String::with_capacity(digits.len() * 4 / 3);
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                text.push(separator);
+            };
+            text.push(c);
+        };
+        text
+    }
+
+    pub fn csv_delimiter(&self) -> char {
+        match self.decimal_comma {
+            true => ';',
+            false => ','
+        }
+    }
+}
@@ -1,5 +1,16 @@
 use num_traits::{PrimInt, Unsigned};
-use chrono::{DateTime, Datelike, TimeZone, Utc, Duration};
+use chrono::{
+    DateTime, 
+    Datelike, 
+    Duration, 
+    Months, 
+    NaiveDate,
+    NaiveDateTime,
+    TimeZone, 
+    Utc, 
+    Weekday
+};
+use serde::{Deserialize, Serialize};
 use sqlx::types::BigDecimal;
 
 
@@ -105,10 +116,7 @@ pub fn period_is_time_based(period_symbol: char)
 
 
 pub fn period_is_valid(period: &str) -> bool {
-    match get_period_portions_from_string(period) {
-        Ok(_) => true,
-        Err(_) => false
-    }
+    get_period_portions_from_string(period).is_ok()
 }
 
 // -------------------------- DATE CONVERSION ------------------------------ //
@@ -159,16 +167,121 @@ pub fn db_timestamp_to_date_string(timestamp: u64) -> String {
 }
 
 
+/// Date and time layouts `parse_date_or_timestamp` accepts, besides RFC 3339
+const DATE_TIME_FORMATS: [&str; 4] = [
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M",
+];
+
+/// Parses a unix timestamp in seconds, or an ISO 8601 date (`2024-03-01`) 
+/// or date and time (`2024-03-01T12:30`), into unix seconds
+///
+/// Dates and times are in UTC unless they end with an offset, as in 
+/// `2024-03-01T12:30:00+02:00`.
+pub fn parse_date_or_timestamp(s: &str) -> Result<u64, TimePeriodError> {
+
+    let s: &str = s.trim();
+
+    if let Ok(seconds) = s.parse::<u64>() {
+        return Ok(seconds)
+    };
+
+    let timestamp: i64 = match DateTime::parse_from_rfc3339(s) {
+        Ok(dt) => dt.timestamp(),
+        Err(_) => DATE_TIME_FORMATS
+            .iter()
+            .find_map(|f| NaiveDateTime::parse_from_str(s, f).ok())
+            .or_else(|| NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+            )
+            .ok_or(TimePeriodError::DateConversion)?
+            .and_utc()
+            .timestamp()
+    };
+
+    u64::try_from(timestamp).map_err(|_| TimePeriodError::DateConversion)
+}
+
+
+// ------------------------------ WEEK START ------------------------------- //
+/// The day weekly bars open on, at 00:00 UTC
+///
+/// Crypto charts commonly start the week on Sunday, while most traditional
+/// markets start it on Monday.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WeekStart {
+    #[default]
+    Sunday,
+    Monday,
+}
+
+impl WeekStart {
+
+    /// Days from the start of the week to `day`
+    fn days_since(&self, day: Weekday) -> i64 {
+        match self {
+            WeekStart::Sunday => day.num_days_from_sunday() as i64,
+            WeekStart::Monday => day.num_days_from_monday() as i64,
+        }
+    }
+}
+
+impl std::str::FromStr for WeekStart {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match &s.to_lowercase()[..] {
+            "sunday" | "sun" => Ok(WeekStart::Sunday),
+            "monday" | "mon" => Ok(WeekStart::Monday),
+            _ => Err(format!("Unknown week start: {}", s))
+        }
+    }
+}
+
+impl std::fmt::Display for WeekStart {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            WeekStart::Sunday => write!(f, "sunday"),
+            WeekStart::Monday => write!(f, "monday"),
+        }
+    }
+}
+
+/// Open date of the week that contains `dt`
+pub fn week_anchor(
+    dt: DateTime<Utc>, 
+    week_start: WeekStart
+) -> Result<DateTime<Utc>, TimePeriodError> {
+    
+    let midnight = dt
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .ok_or(TimePeriodError::DateConversion)?;
+
+    Ok(Utc.from_utc_datetime(
+        &(midnight - Duration::days(week_start.days_since(dt.weekday())))
+    ))
+}
+
+
 // --------------------------- CANDLE PERIOD ------------------------------- //
-pub fn get_tick_indices_and_dates<'a> (
-    tick_data: &'a [(u64, u64, BigDecimal, BigDecimal)],
+/// Tick slice start indices, bar open dates, and bar close dates
+pub type TickIndicesAndDates = (
+    Vec<usize>, 
+    Vec<DateTime<Utc>>, 
+    Vec<DateTime<Utc>>
+);
+
+pub fn get_tick_indices_and_dates(
+    tick_data: &[(u64, u64, BigDecimal, BigDecimal)],
     period_number: u64,
-    period_symbol: char
-) -> Result<
-        (Vec<usize>, Vec<DateTime<Utc>>, Vec<DateTime<Utc>>), 
-        TimePeriodError
-    > 
-{
+    period_symbol: char,
+    week_start: WeekStart
+) -> Result<TickIndicesAndDates, TimePeriodError> {
 
     fn err_msg(msg: &'static str) {
         println!("\x1b[1;31m{}\x1b[0m", msg);
@@ -176,19 +289,15 @@ pub fn get_tick_indices_and_dates<'a> (
 
     fn this_week_or_month(
         ts: u64, 
-        sym: &char
+        sym: &char,
+        week_start: WeekStart
     ) -> Result<DateTime<Utc>, TimePeriodError> {
       
-        fn this_week_start(dt: DateTime<Utc>) -> DateTime<Utc> {
-            let weekday = dt.weekday().num_days_from_sunday() as i64;
-            
-            let next_sunday = dt
-                .date_naive()
-                .and_hms_opt(0, 0, 0)
-                .unwrap()
-                + Duration::days(7 - weekday);
-        
-            Utc.from_utc_datetime(&next_sunday)
+        fn this_week_start(
+            dt: DateTime<Utc>, 
+            week_start: WeekStart
+        ) -> Result<DateTime<Utc>, TimePeriodError> {
+            Ok(week_anchor(dt, week_start)? + Duration::days(7))
         }
         
         fn this_month_start(dt: DateTime<Utc>) -> 
@@ -217,7 +326,7 @@ pub fn get_tick_indices_and_dates<'a> (
         let dt: DateTime<Utc> = micros_u64_to_datetime(ts)?;
         let cut_date: DateTime<Utc> = match is_week {
             true => { 
-                this_week_start(dt)
+                this_week_start(dt, week_start)?
             },
             false => { 
                 this_month_start(dt)?
@@ -259,7 +368,7 @@ pub fn get_tick_indices_and_dates<'a> (
         
         let first_id = tick_data[0].0 / 1_000_000;
         let start_idx: usize = (
-            period_number - (first_id % period_number as u64) - 1
+            period_number - (first_id % period_number) - 1
         ) as usize;
         
         if tick_data.len() < period_number as usize {
@@ -287,12 +396,9 @@ pub fn get_tick_indices_and_dates<'a> (
     }
     else {  // is time based
       
-        let num_seconds: u64 = match calculate_seconds_in_period(
+        let num_seconds: u64 = calculate_seconds_in_period(
             period_number, period_symbol 
-        ) {
-            Ok(s) => s,
-            Err(_) => 0 
-        };
+        ).unwrap_or_default();
         
         let is_week_or_month = ['w', 'M'].contains(&period_symbol);
         let first_ts: u64 = tick_data[0].1 / 1_000_000;
@@ -300,7 +406,11 @@ pub fn get_tick_indices_and_dates<'a> (
         let mut next_open_date = match is_week_or_month {
             
             true => {
-                this_week_or_month(tick_data[0].1, &period_symbol)?
+                this_week_or_month(
+                    tick_data[0].1, 
+                    &period_symbol, 
+                    week_start
+                )?
             },
             
             false => {
@@ -338,6 +448,18 @@ pub fn get_tick_indices_and_dates<'a> (
             let dt = micros_u64_to_datetime(row.1)?;
             
             if dt >= next_open_date { 
+
+                // After a gap the tick opens its own period, not the one 
+                // following the last bar
+                if !is_week_or_month && dt >= next_close_date {
+                    let ts: u64 = row.1 / 1_000_000;
+                    next_open_date = unix_ts_i64_to_datetime(
+                        candle_open_timestamp(ts, num_seconds) as i64
+                    )?;
+                    next_close_date = unix_ts_i64_to_datetime(
+                        candle_close_timestamp(ts, num_seconds) as i64
+                    )?;
+                };
            
                 open_dates.push(next_open_date); 
                 close_dates.push(next_close_date); 
@@ -346,7 +468,7 @@ pub fn get_tick_indices_and_dates<'a> (
                 match is_week_or_month {
                     true => {
                         next_open_date = this_week_or_month(
-                            row.1, &period_symbol
+                            row.1, &period_symbol, week_start
                         )?;
                         next_close_date = next_week_or_month(
                             next_open_date, 
@@ -358,14 +480,14 @@ pub fn get_tick_indices_and_dates<'a> (
                         next_open_date = { 
                             unix_ts_i64_to_datetime(
                                 candle_open_timestamp(
-                                    norm_ts as u64, num_seconds as u64
+                                    norm_ts, num_seconds
                                 ) as i64
                             )?
                         };
                         next_close_date = {   
                             unix_ts_i64_to_datetime(
                                 candle_close_timestamp(
-                                    norm_ts as u64, num_seconds as u64
+                                    norm_ts, num_seconds
                                 ) as i64
                             )?
                         }
@@ -375,13 +497,147 @@ pub fn get_tick_indices_and_dates<'a> (
         };
     }
     
-    match indices.len() > 0 {
+    match !indices.is_empty() {
         true => Ok((indices, open_dates, close_dates)),
         false => Err(TimePeriodError::NotEnoughData)
     }
 }
 
 
+/// Open and close dates of the time-based bar that contains a tick
+///
+/// `timestamp` is in microseconds, as stored in the database. Weekly bars
+/// open at 00:00 UTC on `week_start` and monthly bars on the first of the 
+/// month; all other periods are aligned to multiples of their length in 
+/// seconds.
+pub fn period_bounds(
+    timestamp: u64,
+    period_number: u64,
+    period_symbol: char,
+    week_start: WeekStart
+) -> Result<(DateTime<Utc>, DateTime<Utc>), TimePeriodError> {
+
+    let dt: DateTime<Utc> = micros_u64_to_datetime(timestamp)?;
+
+    match period_symbol {
+        
+        'w' => {
+            let open = week_anchor(dt, week_start)?;
+            Ok((open, open + Duration::days(7 * period_number as i64)))
+        },
+        
+        'M' => {
+            let open = Utc
+                .with_ymd_and_hms(dt.year(), dt.month(), 1, 0, 0, 0)
+                .single()
+                .ok_or(TimePeriodError::DateConversion)?;
+            let months = dt.month0() + period_number as u32;
+            let close = Utc
+                .with_ymd_and_hms(
+                    dt.year() + (months / 12) as i32, 
+                    months % 12 + 1, 
+                    1, 0, 0, 0
+                )
+                .single()
+                .ok_or(TimePeriodError::DateConversion)?;
+            Ok((open, close))
+        },
+
+        't' => Err(TimePeriodError::InvalidPeriod(
+            "Tick based periods have no time bounds"
+        )),
+
+        _ => {
+            let num_seconds: u64 = calculate_seconds_in_period(
+                period_number, period_symbol
+            )?;
+            let ts: u64 = timestamp / 1_000_000;
+            Ok((
+                unix_ts_i64_to_datetime(
+                    candle_open_timestamp(ts, num_seconds) as i64
+                )?,
+                unix_ts_i64_to_datetime(
+                    candle_close_timestamp(ts, num_seconds) as i64
+                )?
+            ))
+        }
+    }
+}
+
+
+/// Open and close dates of the time-based bar that contains a tick, with
+/// bars laid end to end from `anchor` instead of from calendar boundaries
+///
+/// Both timestamps are in microseconds, and the anchor is truncated to the
+/// second. Weeks are 7 days long, and months step by calendar month from
+/// the anchor's day of the month (clamped to shorter months).
+pub fn anchored_period_bounds(
+    timestamp: u64,
+    period_number: u64,
+    period_symbol: char,
+    anchor: u64
+) -> Result<(DateTime<Utc>, DateTime<Utc>), TimePeriodError> {
+
+    let dt: DateTime<Utc> = micros_u64_to_datetime(timestamp)?;
+    let anchor: DateTime<Utc> = unix_ts_i64_to_datetime(
+        (anchor / 1_000_000) as i64
+    )?;
+
+    if period_number == 0 {
+        return Err(TimePeriodError::InvalidPeriod(
+            "Period length must be greater than zero"
+        ))
+    };
+
+    match period_symbol {
+
+        'M' => {
+            let step: i64 = period_number as i64;
+            let months: i64 = (dt.year() as i64 - anchor.year() as i64) * 12
+                + dt.month0() as i64 
+                - anchor.month0() as i64;
+
+            let mut k: i64 = months.div_euclid(step);
+            let mut open = add_months(anchor, k * step)?;
+            if open > dt {
+                k -= 1;
+                open = add_months(anchor, k * step)?;
+            };
+            Ok((open, add_months(anchor, (k + 1) * step)?))
+        },
+
+        't' => Err(TimePeriodError::InvalidPeriod(
+            "Tick based periods have no time bounds"
+        )),
+
+        _ => {
+            let num_seconds: u64 = match period_symbol {
+                'w' => 7 * 86400 * period_number,
+                _ => calculate_seconds_in_period(period_number, period_symbol)?
+            };
+            let num_seconds: i64 = num_seconds as i64;
+            let offset: i64 = (dt.timestamp() - anchor.timestamp())
+                .div_euclid(num_seconds);
+            let open = anchor + Duration::seconds(offset * num_seconds);
+            Ok((open, open + Duration::seconds(num_seconds)))
+        }
+    }
+}
+
+fn add_months(
+    dt: DateTime<Utc>, 
+    months: i64
+) -> Result<DateTime<Utc>, TimePeriodError> {
+
+    let shifted = match months >= 0 {
+        true => dt.checked_add_months(Months::new(months as u32)),
+        false => dt.checked_sub_months(Months::new(months.unsigned_abs() as u32))
+    };
+
+    shifted.ok_or(TimePeriodError::DateConversion)
+}
+
+
 pub fn candle_open_timestamp<T>(timestamp: T, num_seconds: T) -> T 
 where 
     T: PrimInt + Unsigned
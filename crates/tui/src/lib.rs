@@ -1,6 +1,8 @@
 use std::{
-    collections::{BTreeMap, VecDeque}, 
+    collections::{BTreeMap, HashMap, VecDeque}, 
+    iter,
     io::{self}, 
+    path::PathBuf,
     time::Duration,
     sync::Arc,
 };
@@ -13,9 +15,13 @@ use ratatui::{
     crossterm::{
         event::{
             self,
+            DisableMouseCapture,
+            EnableMouseCapture,
             Event,
             KeyCode, 
             KeyEvent, 
+            MouseEvent,
+            MouseEventKind,
         }, 
         execute,
         terminal::{
@@ -36,6 +42,7 @@ use ratatui::{
     }, 
     text::{
         Line, 
+        Span,
         Text
     }, 
     widgets::{
@@ -59,13 +66,14 @@ use tokio::{
 
 use app_core::{
     database_ops::{
-        fetch_exchanges_and_pairs_from_db, kraken::{
+        DataDownloadStatus,
+        kraken::{
             AssetPairInfo, 
             request_all_assets_from_kraken
         } 
     }, 
+    app_state::{JobKind, StartScreen, UiState},
     engine::Engine,
-    errors::{ConfigError},
 };
 
 mod screens;
@@ -82,6 +90,16 @@ use screens::{
         CandleScreen,
         CandleFocus,
     },
+    chart::{
+        ChartScreen,
+        ChartFocus,
+    },
+    integrity::IntegrityScreen,
+    jobs::JobsScreen,
+    keymap::draw_help,
+    orders::OrdersScreen,
+    paper::PaperScreen,
+    watch::WatchScreen,
     strategies::{
         StrategyFocus,
     },
@@ -91,6 +109,7 @@ use screens::{
     Screen,
     move_up,
     move_down,
+    pair_color,
 };
 use string_helpers::multi_line_to_single_line;
 
@@ -99,6 +118,27 @@ use crate::screens::strategies::{
 };
 
 
+/// Lines kept in the output window, oldest dropped first
+const OUTPUT_BUFFER_LINES: usize = 2000;
+
+/// Lines the output window moves per turn of the mouse wheel
+const MOUSE_SCROLL_LINES: u16 = 3;
+
+/// The screen each entry in the operations list opens, by index
+const OPERATION_SCREENS: [StartScreen; 10] = [
+    StartScreen::Database,
+    StartScreen::Candles,
+    StartScreen::Settings,
+    StartScreen::Strategies,
+    StartScreen::Chart,
+    StartScreen::Integrity,
+    StartScreen::Watch,
+    StartScreen::Jobs,
+    StartScreen::Paper,
+    StartScreen::Orders,
+];
+
+
 // ---------------------------- TERMINAL INTERFACE ------------------------- //
 /// # Terminal User Interface (TUI)
 ///
@@ -106,18 +146,30 @@ use crate::screens::strategies::{
 /// delete pairs from it), build and export candle data as CSV files, and
 /// adjust global system settings. Create a TUI instance with the `new` 
 /// method, then start it with `run().await`
-/// ```
+/// ```ignore
 /// let engine = app_core::Engine::new(db_pool: PgPool);
-/// let tui = TerminalUserInterface::new(engine);
+/// let mut tui = TerminalInterface::new(engine).await;
 /// tui.run().await;
 /// ```
 pub struct TerminalInterface {
     operation_state: ListState,
     screen: Screen,
     output_buffer: VecDeque<Line<'static>>,
+    /// Where each downloading pair's status line is in `output_buffer`, so
+    /// its progress rewrites one line instead of adding one per update
+    status_lines: HashMap<(String, String), usize>,
     output_scroll: u16,
+    /// Keeps the newest line in view as lines are added. Scrolling up turns
+    /// it off, and 'G' or scrolling back to the end turns it on again.
+    output_follow: bool,
     output_area: Rect,
     asset_pairs: Arc<BTreeMap<String, BTreeMap<String, AssetPairInfo>>>,
+    last_copyable: Option<String>,
+    last_export: Option<PathBuf>,
+    /// Hides the Operations and Output panes while the chart is open
+    wide_mode: bool,
+    /// Shows the keymap over the active screen until the next key press
+    show_help: bool,
     engine: Engine,
 }
 
@@ -134,12 +186,9 @@ impl TerminalInterface {
         let asset_pairs = Arc::new(BTreeMap::from([
             (
                 "kraken".to_string(), 
-                match request_all_assets_from_kraken(
-                    &engine.request_client
-                ).await {
-                    Ok(d) => d,
-                    Err(_) => BTreeMap::new()
-                } 
+                request_all_assets_from_kraken(&engine.request_client)
+                    .await
+                    .unwrap_or_default()
             )
         ]));
 
@@ -147,15 +196,109 @@ impl TerminalInterface {
             operation_state,
             screen,
             output_buffer,
+            status_lines: HashMap::new(),
             output_scroll: 0,
+            output_follow: true,
             output_area: Rect::new(0, 0, 0, 0),
             asset_pairs,
+            last_copyable: None,
+            last_export: None,
+            wide_mode: false,
+            show_help: false,
             engine,
         }
     }
 
     /// Adds lines of text to the output window
     fn add_line(&mut self, msg: &OutputMsg) {
+
+        self.output_buffer.push_back(Self::styled_line(msg));
+
+        if self.output_buffer.len() > OUTPUT_BUFFER_LINES {
+            self.output_buffer.pop_front();
+
+            // Status lines being rewritten move up with everything else
+            self.status_lines.retain(|_, i| match *i {
+                0 => false,
+                _ => {
+                    *i -= 1;
+                    true
+                }
+            });
+
+            // Keeps the same lines in view while scrolled back
+            if !self.output_follow {
+                self.output_scroll = self.output_scroll.saturating_sub(1);
+            };
+        };
+
+        if self.output_follow {
+            self.output_scroll = self.max_output_scroll();
+        };
+    }
+
+    /// Scroll offset that puts the newest line at the bottom of the window
+    fn max_output_scroll(&self) -> u16 {
+        let visible_height = self.output_area.height.saturating_sub(2);
+        self.output_buffer
+            .len()
+            .saturating_sub(visible_height as usize)
+            .min(u16::MAX as usize) as u16
+    }
+
+    /// Moves the output window `lines` up, or down when negative, following
+    /// new lines again once it's back at the end
+    fn scroll_output(&mut self, lines: i32) {
+        let max: u16 = self.max_output_scroll();
+        self.output_scroll = (self.output_scroll as i32 - lines)
+            .clamp(0, max as i32) as u16;
+        self.output_follow = self.output_scroll == max;
+    }
+
+    /// Adds a download status to the output window
+    ///
+    /// A pair's progress rewrites the line its download started on, which 
+    /// its final status then replaces. Stalls and retries get lines of 
+    /// their own.
+    fn add_status_line(&mut self, status: DataDownloadStatus) {
+
+        let key: (String, String) = {
+            let (exchange, ticker) = status.exchange_and_ticker();
+            (exchange.to_string(), ticker.to_string())
+        };
+
+        let (rewrites, is_final): (bool, bool) = match status {
+            DataDownloadStatus::Started { .. } => (false, false),
+            DataDownloadStatus::Progress { .. } => (true, false),
+            DataDownloadStatus::Stalled { .. }
+            | DataDownloadStatus::Retrying { .. } => {
+                self.add_line(&status.into());
+                return
+            },
+            _ => (true, true)
+        };
+
+        let msg: OutputMsg = status.into();
+
+        match self.status_lines.get(&key) {
+            Some(&i) if rewrites && i < self.output_buffer.len() => {
+                self.output_buffer[i] = Self::styled_line(&msg);
+            },
+            _ => {
+                self.add_line(&msg);
+                self.status_lines.insert(
+                    key.clone(), 
+                    self.output_buffer.len() - 1
+                );
+            }
+        };
+
+        if is_final {
+            self.status_lines.remove(&key);
+        };
+    }
+
+    fn styled_line(msg: &OutputMsg) -> Line<'static> {
         
         let mut style = Style::default().fg(msg.color);
         if msg.bold {
@@ -166,37 +309,289 @@ impl TerminalInterface {
             style = style.bg(col)
         };
 
-        let visible_height = self.output_area.height.saturating_sub(2);
-        self.output_buffer.push_back(Line::styled(msg.text.clone(), style));
-        self.output_scroll = self
-            .output_buffer
-            .len()
-            .saturating_sub(visible_height as usize) 
-            as u16; 
-    
+        // The ticker takes its pair's color, so lines about different pairs
+        // are easy to tell apart
+        match (&msg.exchange, &msg.ticker) {
+            (Some(exchange), Some(ticker)) => match msg.text.split_once(
+                ticker.as_str()
+            ) {
+                Some((before, after)) => Line::from(vec![
+                    Span::styled(before.to_string(), style),
+                    Span::styled(
+                        ticker.clone(), 
+                        style.fg(pair_color(exchange, ticker))
+                    ),
+                    Span::styled(after.to_string(), style),
+                ]),
+                None => Line::styled(msg.text.clone(), style)
+            },
+            _ => Line::styled(msg.text.clone(), style)
+        }
+    }
+
+    /// Copies text to the system clipboard, reporting the result in the 
+    /// output window
+    fn copy_to_clipboard(&mut self, text: String) {
+
+        let result = arboard::Clipboard::new()
+            .and_then(|mut c| c.set_text(text.clone()));
+
+        let msg = match result {
+            Ok(_) => OutputMsg::new(
+                format!("Copied to clipboard: {}", text.lines().next()
+                    .unwrap_or_default()),
+                Color::Green,
+                false,
+                None,
+                None,
+                None
+            ),
+            Err(e) => OutputMsg::new(
+                format!("Clipboard unavailable: {}", e),
+                Color::Red,
+                true,
+                None,
+                None,
+                None
+            )
+        };
+
+        self.add_line(&msg);
+    }
+
+    /// Opens a file with the platform's default application, reporting 
+    /// failures in the output window
+    fn open_file(&mut self, path: PathBuf) {
+
+        let msg = match opener::open(&path) {
+            Ok(_) => OutputMsg::new(
+                format!("Opened {}", path.display()),
+                Color::Green,
+                false,
+                None,
+                None,
+                None
+            ),
+            Err(e) => OutputMsg::new(
+                format!("Failed to open {}: {}", path.display(), e),
+                Color::Red,
+                true,
+                None,
+                None,
+                None
+            )
+        };
+
+        self.add_line(&msg);
+    }
+
+    /// Whether the active screen is taking free text input, or waiting on
+    /// the answer to a popup
+    fn is_typing(&self) -> bool {
+        match &self.screen {
+            Screen::DatabaseManager(s) => {
+                s.confirm.is_some() || s.search.typing
+            },
+            Screen::CandleBuilder(s) => {
+                s.focus == CandleFocus::InputMode || s.search.typing
+            },
+            Screen::ChartViewer(s) => {
+                s.focus == ChartFocus::InputMode || s.search.typing
+            },
+            Screen::StrategyManager(s) => {
+                s.focus == StrategyFocus::InputMode || s.search.typing
+            },
+            Screen::SystemSettings(s) => {
+                matches!(s.config_form.mode, FormMode::Input)
+            },
+            Screen::OrderList(s) => s.price_input.is_some(),
+            _ => false
+        }
+    }
+
+    /// Builds the screen for `kind`. `Menu` and `Last` have no screen of 
+    /// their own, so they get the placeholder.
+    async fn open_screen(
+        &self, 
+        kind: StartScreen, 
+        transmitter: UnboundedSender<AppEvent>
+    ) -> Screen {
+
+        let config = &self.engine.state.config;
+
+        match kind {
+            StartScreen::Database => Screen::DatabaseManager(
+                DatabaseScreen::new(
+                    self.engine.database.get_pool(),
+                    self.engine.pairs.clone(),
+                    transmitter,
+                    Arc::clone(&self.asset_pairs),
+                    config.locale,
+                    self.engine.guest_mode
+                )
+            ),
+            StartScreen::Candles => {
+                let pairs = self.engine.pairs
+                    .load(self.engine.database.get_pool())
+                    .await;
+                Screen::CandleBuilder(
+                    CandleScreen::new(
+                        pairs,
+                        transmitter,
+                        self.engine.database.get_pool(),
+                        config.locale,
+                        config.csv_dialect.clone(),
+                        config.bars
+                    )
+                )
+            },
+            StartScreen::Settings => Screen::SystemSettings(
+                SettingsScreen::new(
+                    config,
+                    transmitter,
+                    self.engine.guest_mode
+                )
+            ),
+            StartScreen::Strategies => {
+                let pairs = self.engine.pairs
+                    .load(self.engine.database.get_pool())
+                    .await;
+                Screen::StrategyManager(
+                    StrategyScreen::new(
+                        transmitter,
+                        pairs,
+                        config.backtesting.clone(),
+                        config.bars,
+                        self.engine.database.get_pool(),
+                        self.engine.request_client.clone()
+                    )
+                )
+            },
+            StartScreen::Chart => {
+                let pairs = self.engine.pairs
+                    .load(self.engine.database.get_pool())
+                    .await;
+                Screen::ChartViewer(
+                    ChartScreen::new(
+                        pairs,
+                        transmitter,
+                        self.engine.database.get_pool(),
+                        config.bars
+                    )
+                )
+            },
+            StartScreen::Integrity => Screen::IntegrityCheck(
+                IntegrityScreen::new(
+                    self.engine.database.get_pool(),
+                    self.engine.request_client.clone(),
+                    transmitter,
+                    config.timeouts.limit(JobKind::IntegrityCheck),
+                    self.engine.guest_mode
+                ).await
+            ),
+            StartScreen::Watch => Screen::LiveWatch(
+                WatchScreen::new(
+                    &config.tui.watchlist,
+                    self.engine.database.get_pool(),
+                    self.engine.request_client.clone(),
+                    transmitter,
+                    config.locale
+                )
+            ),
+            StartScreen::Jobs => Screen::JobQueue(
+                JobsScreen::new(self.engine.jobs.clone(), transmitter)
+            ),
+            StartScreen::Paper => Screen::PaperTrading(
+                PaperScreen::new(
+                    self.engine.database.get_pool(),
+                    self.engine.request_client.clone(),
+                    config.backtesting.slippage_bps,
+                    transmitter,
+                    config.locale,
+                    self.engine.guest_mode
+                )
+            ),
+            StartScreen::Orders => Screen::OrderList(
+                OrdersScreen::new(
+                    self.engine.database.get_pool(),
+                    self.engine.request_client.clone(),
+                    config,
+                    transmitter,
+                    self.engine.guest_mode
+                )
+            ),
+            StartScreen::Menu | StartScreen::Last => Screen::Placeholder
+        }
+    }
+
+    /// Which screen is open, as remembered in the UI-state file
+    fn current_screen(&self) -> StartScreen {
+        match self.screen {
+            Screen::DatabaseManager(_) => StartScreen::Database,
+            Screen::CandleBuilder(_) => StartScreen::Candles,
+            Screen::SystemSettings(_) => StartScreen::Settings,
+            Screen::StrategyManager(_) => StartScreen::Strategies,
+            Screen::ChartViewer(_) => StartScreen::Chart,
+            Screen::IntegrityCheck(_) => StartScreen::Integrity,
+            Screen::LiveWatch(_) => StartScreen::Watch,
+            Screen::JobQueue(_) => StartScreen::Jobs,
+            Screen::PaperTrading(_) => StartScreen::Paper,
+            Screen::OrderList(_) => StartScreen::Orders,
+            Screen::Placeholder => StartScreen::Menu,
+        }
     }
 
     /// Removes all lines from the output window
     fn clear_lines(&mut self) {
         self.output_buffer.clear();
+        self.status_lines.clear();
         self.output_scroll = 0;
+        self.output_follow = true;
+    }
+
+    /// Whether the active screen has work running in the background that 
+    /// can change what it shows without sending an event
+    fn screen_is_busy(&self) -> bool {
+        match &self.screen {
+            Screen::DatabaseManager(s) => s.task_handle.is_some(),
+            Screen::CandleBuilder(s) => s.is_busy(),
+            Screen::ChartViewer(s) => s.is_busy(),
+            Screen::StrategyManager(s) => s.is_busy(),
+            Screen::IntegrityCheck(s) => s.is_busy(),
+            Screen::LiveWatch(s) => s.is_busy(),
+            Screen::JobQueue(s) => s.is_busy(),
+            Screen::PaperTrading(s) => s.is_busy(),
+            Screen::OrderList(s) => s.is_busy(),
+            _ => false
+        }
     }
 
     /// Draws the TUI.
     fn draw(
         &mut self, 
         frame: &mut Frame,
-        operations: &[&'static str; 4],
+        operations: &[&'static str; 10],
         focus: &Focus
     ) {
  
         let size = frame.area();
 
+        let wide: bool = self.wide_mode 
+            && matches!(self.screen, Screen::ChartViewer(_));
+
+        let (operations_width, output_height): (u16, u16) = match wide {
+            true => (0, 0),
+            false => {
+                let params = &self.engine.state.config.chart_parameters;
+                (params.operations_width, params.output_height)
+            }
+        };
+
         let vertical_chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
                 Constraint::Min(5),   
-                Constraint::Length(10),
+                Constraint::Length(output_height),
             ])
             .split(size);
 
@@ -208,10 +603,15 @@ impl TerminalInterface {
                 .collect::<Vec<_>>()
         );
         
+        let output_title: &str = match self.output_follow {
+            true => "Output",
+            false => "Output (scrolled back, G: jump to end)"
+        };
+
         let output = Paragraph::new(text)
             .block(
                 Block::default()
-                .title("Output")
+                .title(output_title)
                 .borders(Borders::ALL))
             .wrap(Wrap { trim: false })
             .scroll((self.output_scroll, 0));
@@ -224,7 +624,7 @@ impl TerminalInterface {
         let main_chunks = Layout::default()
             .direction(Direction::Horizontal)
             .constraints([
-                Constraint::Length(21),
+                Constraint::Length(operations_width),
                 Constraint::Percentage(100),
             ].as_ref())
             .split(vertical_chunks[0]);
@@ -241,7 +641,12 @@ impl TerminalInterface {
                             r#"Press 'Enter' to choose an option, and 'Esc' to 
                             return to the previous window. Up and down arrow
                             keys are used for navigation. Vim style navigation 
-                            works as well ('j' key for down and 'k' for up)."#, 
+                            works as well ('j' key for down and 'k' for up).
+                            Press 'y' to copy the selected pair or the last 
+                            exported file path, and 'Y' to copy the output 
+                            window. Press 'o' to open the last exported file
+                            with its default application, and '?' to list 
+                            every screen's keys."#, 
                             main_area.width
                         ),
                         "Press 'q' to quit"
@@ -256,7 +661,10 @@ impl TerminalInterface {
 
         // ---------------------- Operation Panes ------------------ // 
         let operations_block = Block::default()
-            .title("Operations")
+            .title(match self.engine.guest_mode {
+                true => "Operations (guest)",
+                false => "Operations"
+            })
             .borders(Borders::ALL);
 
         let ops: Vec<ListItem> = operations 
@@ -298,8 +706,36 @@ impl TerminalInterface {
                 screen.draw(frame, main_area)
             }
 
+            Screen::ChartViewer(screen) => {
+                screen.draw(frame, main_area)
+            }
+
+            Screen::IntegrityCheck(screen) => {
+                screen.draw(frame, main_area)
+            }
+
+            Screen::LiveWatch(screen) => {
+                screen.draw(frame, main_area)
+            }
+
+            Screen::JobQueue(screen) => {
+                screen.draw(frame, main_area)
+            }
+
+            Screen::PaperTrading(screen) => {
+                screen.draw(frame, main_area)
+            }
+
+            Screen::OrderList(screen) => {
+                screen.draw(frame, main_area)
+            }
+
             Screen::Placeholder => {}
         }
+
+        if self.show_help {
+            draw_help(frame, size, self.current_screen());
+        };
     }
 
     /// Runs the TUI
@@ -308,24 +744,47 @@ impl TerminalInterface {
 
         enable_raw_mode()?;
         let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen)?;
+        execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
 
         let backend = CrosstermBackend::new(stdout);
         let mut terminal = Terminal::new(backend)?; 
  
         let mut focus = Focus::Operations;
  
-        let operations: [&'static str; 4] = [
+        let operations: [&'static str; 10] = [
             CandleScreen::SCREEN_NAME,
             DatabaseScreen::SCREEN_NAME,
             SettingsScreen::SCREEN_NAME,
             StrategyScreen::SCREEN_NAME,
+            ChartScreen::SCREEN_NAME,
+            IntegrityScreen::SCREEN_NAME,
+            WatchScreen::SCREEN_NAME,
+            JobsScreen::SCREEN_NAME,
+            PaperScreen::SCREEN_NAME,
+            OrdersScreen::SCREEN_NAME,
         ];
 
         let (transmitter, mut receiver) = unbounded_channel::<AppEvent>();
         let listener_tx = transmitter.clone();
         let input_tx = transmitter.clone();
 
+        // `start --screen` wins over the config, and "last" falls back to 
+        // the menu on the first run
+        let start: StartScreen = match self.engine.start_screen
+            .unwrap_or(self.engine.state.config.tui.start_screen) 
+        {
+            StartScreen::Last => UiState::load(&self.engine.state.paths)
+                .last_screen
+                .unwrap_or(StartScreen::Menu),
+            s => s
+        };
+
+        if let Some(i) = OPERATION_SCREENS.iter().position(|s| *s == start) {
+            self.operation_state.select(Some(i));
+            self.screen = self.open_screen(start, transmitter.clone()).await;
+            focus = Focus::Main;
+        };
+
         let tick_listener = tokio::spawn(async move {
             let mut ticker = interval(Duration::from_millis(100)); // 10 FPS
             loop {
@@ -338,15 +797,23 @@ impl TerminalInterface {
             
             loop {
                 
-                if let Ok(_) = event::poll(Duration::from_millis(50)) {
-                    if let Ok(e) = event::read() {
-                        if let Event::Key(key) = e {
+                if event::poll(Duration::from_millis(50)).is_ok() {
+                    match event::read() {
+                        Ok(Event::Key(key)) => {
                             let _ = input_tx.send(AppEvent::Input(key));
-                        }
-                    }
-                    else {
-                        break;
-                    }
+                        },
+                        // Only the wheel is used, so moves and clicks are 
+                        // dropped here rather than waking the loop
+                        Ok(Event::Mouse(m)) if matches!(
+                            m.kind, 
+                            MouseEventKind::ScrollUp 
+                                | MouseEventKind::ScrollDown
+                        ) => {
+                            let _ = input_tx.send(AppEvent::Mouse(m));
+                        },
+                        Ok(_) => {},
+                        Err(_) => break
+                    };
                 }
                 else {
                     break;
@@ -354,9 +821,23 @@ impl TerminalInterface {
             }
         });
  
+        // Anything but a tick changes what's on screen. Keys are drawn at 
+        // once, and everything else waits for the next tick, so a burst of 
+        // progress updates costs one frame.
+        let mut dirty: bool = true;
+        let mut was_busy: bool = false;
+
         loop {
 
-            while let Ok(msg) = receiver.try_recv() {
+            // Sleeps until something happens, then takes whatever else has
+            // queued up behind it
+            let Some(first) = receiver.recv().await else { break };
+            let mut draw_now: bool = false;
+            let mut tick: bool = false;
+
+            for msg in iter::once(first)
+                .chain(iter::from_fn(|| receiver.try_recv().ok())) 
+            {
                 
                 match msg {
                     
@@ -366,104 +847,125 @@ impl TerminalInterface {
                             &operations, 
                             focus, 
                             transmitter.clone()
-                        ).await
+                        ).await;
+                        draw_now = true;
                     },
                     
-                    AppEvent::Tick => {}, // Nothing to do
+                    AppEvent::Mouse(m) => {
+                        self.handle_mouse(m);
+                        draw_now = true;
+                    },
+
+                    AppEvent::Tick => tick = true,
                     
                     AppEvent::Output(msg) => {
-                        self.render_messages(msg);
+                        self.add_line(&msg);
+                        dirty = true;
+                    },
+                    AppEvent::Download(status) => {
+                        if let Some(feed) = self.engine.start_bus_feed().await {
+                            let _ = feed.send(status.clone());
+                        };
+                        self.engine.jobs.apply_status(&status);
+                        match &mut self.screen {
+                            Screen::DatabaseManager(screen) => {
+                                screen.update_download(status);
+                            },
+                            // The gauges left with their screen
+                            _ => self.add_status_line(status)
+                        };
+                        dirty = true;
+                    },
+                    AppEvent::UpdateSummary(summary) => {
+                        for msg in OutputMsg::from_summary(&summary) {
+                            self.add_line(&msg);
+                        };
+                        dirty = true;
+                    },
+                    AppEvent::Clear => {
+                        self.clear_lines();
+                        dirty = true;
                     },
-                    AppEvent::Clear => self.clear_lines()
+                    AppEvent::Exported(path) => {
+                        self.last_copyable = Some(path.display().to_string());
+                        self.last_export = Some(path);
+                    }
                 }
             }
 
-            match &mut self.screen {
+            if let Focus::Quit = focus { break };
 
-                Screen::DatabaseManager(screen) => {
-                    screen.pre_draw().await;
-                },
+            if !draw_now && !tick {
+                continue
+            };
 
+            match &mut self.screen {
+                Screen::DatabaseManager(screen) => screen.pre_draw().await,
+                Screen::ChartViewer(screen) => screen.pre_draw().await,
+                Screen::IntegrityCheck(screen) => screen.pre_draw(),
+                Screen::LiveWatch(screen) => screen.pre_draw(),
+                Screen::JobQueue(screen) => screen.pre_draw(),
+                Screen::PaperTrading(screen) => screen.pre_draw(),
+                Screen::OrderList(screen) => screen.pre_draw(),
                 _ => {}
             };
 
-            terminal.draw(|frame| {
-                self.draw(frame, &operations, &focus);
-            })?;
+            // One more frame once background work stops, to show it stopped
+            let busy: bool = self.screen_is_busy();
 
-            if let Focus::Quit = focus { break };
+            if draw_now || dirty || busy || was_busy {
+                terminal.draw(|frame| {
+                    self.draw(frame, &operations, &focus);
+                })?;
+                dirty = false;
+            };
 
+            was_busy = busy;
         }
 
         // Cleanup
         disable_raw_mode()?;
-        execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+        execute!(
+            terminal.backend_mut(), 
+            LeaveAlternateScreen, 
+            DisableMouseCapture
+        )?;
         terminal.show_cursor()?;
         tick_listener.abort();
         key_reader.abort();
 
+        let ui_state = UiState { last_screen: Some(self.current_screen()) };
+        if let Err(e) = ui_state.save(&self.engine.state.paths) {
+            eprintln!("\x1b[33mCouldn't save the UI state: {}\x1b[0m", e);
+        };
+
         Ok(())
 
     }
 
-    /// Renders messages and stores then adds them to the output window
-    /// with `self.add_line(msg)`
-    fn render_messages(&mut self, msg: OutputMsg) {
-
-        let mut msgs_to_render: Vec<OutputMsg> = Vec::new();
-        let mut clear_lines: bool = false;
+    /// Scrolls the output window with the mouse wheel, when the pointer is
+    /// over it
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
 
-        match &mut self.screen {
-            
-            Screen::DatabaseManager(screen) => {
-            
-                // Handle database update messages here
-                match (&msg.exchange, &msg.ticker) {
-                    (Some(exchange), Some(ticker)) => {
-                       
-                        clear_lines = true;
-                        &screen.db_update_msgs.msgs
-                            .entry(exchange.to_string())
-                            .or_insert_with(|| BTreeMap::new())
-                            .insert(ticker.to_string(), msg);
-
-                        for (ex, pairs) in &screen.db_update_msgs.msgs {
-                            msgs_to_render.push(
-                                OutputMsg::new(
-                                    ex.to_string(),
-                                    Color::Cyan,
-                                    true,
-                                    None,
-                                    None,
-                                    None
-                                )
-                            );
-                            for (_, message) in pairs {
-                                msgs_to_render.push(message.clone());
-                            };
-                        };
-                    },
-                    _ => {
-                        msgs_to_render.push(msg)  
-                    }
+        let area: Rect = self.output_area;
+        let over_output: bool = mouse.column >= area.x
+            && mouse.column < area.x + area.width
+            && mouse.row >= area.y
+            && mouse.row < area.y + area.height;
 
-                }
+        if !over_output {
+            return
+        };
 
+        match mouse.kind {
+            MouseEventKind::ScrollUp => {
+                self.scroll_output(MOUSE_SCROLL_LINES as i32)
             },
-
-            _ => {
-                msgs_to_render.push(msg);
-            }
-        
-        }
-               
-        if clear_lines {
-            self.clear_lines();
-        } 
-        for msg in msgs_to_render {
-            self.add_line(&msg);
+            MouseEventKind::ScrollDown => {
+                self.scroll_output(-(MOUSE_SCROLL_LINES as i32))
+            },
+            _ => {}
         };
-
     }
 
     /// Handles key inputs.
@@ -473,16 +975,89 @@ impl TerminalInterface {
     async fn handle_key(
         &mut self,
         key: KeyEvent, 
-        operations: &[&'static str; 4],
+        operations: &[&'static str; 10],
         focus: Focus,
         transmitter: UnboundedSender<AppEvent>,
     ) -> Focus {
        
         let mut new_focus = focus.clone();
 
-        if let KeyCode::Char('q') = key.code {
+        // Any key closes the help, without doing anything else
+        if self.show_help {
+            self.show_help = false;
+        }
+
+        else if let KeyCode::Char('?') = key.code 
+            && !self.is_typing()
+        {
+            self.show_help = true;
+        }
+
+        else if let KeyCode::Char('q') = key.code 
+            && !self.is_typing()
+        {
             return Focus::Quit;
         }
+
+        // Page keys scroll the output window back through earlier lines,
+        // and 'G' jumps back to the newest
+        else if let KeyCode::PageUp | KeyCode::PageDown = key.code {
+            let page: i32 = self.output_area.height.saturating_sub(2).max(1) 
+                as i32;
+            self.scroll_output(match key.code {
+                KeyCode::PageUp => page,
+                _ => -page
+            });
+        }
+
+        else if let KeyCode::Char('G') = key.code 
+            && !self.is_typing()
+        {
+            self.output_follow = true;
+            self.output_scroll = self.max_output_scroll();
+        }
+
+        // 'y' copies the selected pair or the last exported file path, and
+        // 'Y' copies everything in the output window
+        else if let KeyCode::Char(c @ ('y' | 'Y')) = key.code 
+            && !self.is_typing()
+        {
+            let selected_pair = match &self.screen {
+                Screen::DatabaseManager(s) => s.selected_pair(),
+                _ => None
+            };
+
+            let text: Option<String> = if c == 'Y' {
+                Some(self.output_buffer
+                    .iter()
+                    .map(|l| l.to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+            }
+            else {
+                selected_pair.or_else(|| self.last_copyable.clone())
+            };
+
+            if let Some(t) = text.filter(|t| !t.is_empty()) {
+                self.copy_to_clipboard(t);
+            };
+        }
+
+        // 'w' toggles wide mode, giving the chart the whole terminal
+        else if let KeyCode::Char('w') = key.code 
+            && !self.is_typing()
+            && let Screen::ChartViewer(_) = self.screen
+        {
+            self.wide_mode = !self.wide_mode;
+        }
+
+        // 'o' opens the last exported file with the default application
+        else if let KeyCode::Char('o') = key.code 
+            && !self.is_typing()
+            && let Some(path) = self.last_export.clone()
+        {
+            self.open_file(path);
+        }
         
         else if let Focus::Operations = focus {
            
@@ -506,42 +1081,11 @@ impl TerminalInterface {
                 
                 KeyCode::Enter => {
                     if let Some(i) = self.operation_state.selected() {
-                        self.screen = match i {
-                            0 => Screen::DatabaseManager(
-                                
-                                DatabaseScreen::new(
-                                    self.engine
-                                        .database
-                                        .get_pool(),
-                                    
-                                    transmitter,
-
-                                    Arc::clone(&self.asset_pairs)
-                                )
-                            
-                            ),
-                            1 => {
-                                let pairs = fetch_exchanges_and_pairs_from_db(
-                                    self.engine.database.get_pool()
-                                ).await; 
-                                Screen::CandleBuilder(
-                                    CandleScreen::new(
-                                        pairs,
-                                        transmitter,
-                                        self.engine.database.get_pool()
-                                    )
-                                )
+                        self.screen = match OPERATION_SCREENS.get(i) {
+                            Some(kind) => {
+                                self.open_screen(*kind, transmitter).await
                             },
-                            2 => Screen::SystemSettings(
-                                SettingsScreen::new(
-                                    &self.engine.state.config,
-                                    transmitter 
-                                )
-                            ),
-                            3 => Screen::StrategyManager(
-                                StrategyScreen::new(transmitter)
-                            ), 
-                            _ => Screen::Placeholder 
+                            None => Screen::Placeholder
                         };
                         new_focus = Focus::Main;
                     }
@@ -558,11 +1102,11 @@ impl TerminalInterface {
 
                 Screen::DatabaseManager(screen) => {
 
-                    if let KeyCode::Esc = key.code {
-                        if let DbFocus::Top = screen.focus {
-                            new_focus = Focus::Operations;
-                            breakout = true; 
-                        };
+                    if let KeyCode::Esc = key.code
+                        && let DbFocus::Top = screen.focus 
+                    {
+                        new_focus = Focus::Operations;
+                        breakout = true; 
                     };
                     screen.handle_key(key, &self.engine).await;
 
@@ -570,85 +1114,103 @@ impl TerminalInterface {
 
                 Screen::SystemSettings(screen) => {
                     
-                    if let KeyCode::Esc = key.code {
-                        
-                        if let FormMode::Movement = screen.config_form.mode {
+                    // Settings can't be edited in guest mode, so there's
+                    // nothing to save
+                    if let KeyCode::Esc = key.code
+                        && screen.read_only
+                    {
+                        screen.active = false;
+                        new_focus = Focus::Operations;
+                        breakout = true;
+                    }
+
+                    else if let FormMode::Movement = screen.config_form.mode
+                        && let KeyCode::Esc | KeyCode::Char('s') = key.code
+                    {
+                        screen.save(
+                            &mut self.engine.state.config,
+                            &self.engine.state.paths,
+                            self.engine.database.get_pool()
+                        );
+
+                        if let KeyCode::Esc = key.code {
                             screen.active = false;
                             new_focus = Focus::Operations;
                             breakout = true; 
                         };
-
-                        transmitter.send(AppEvent::Clear);
-                        
-                        match screen.config_form.save_input_values(
-                            &self.engine.state.config,
-                            &self.engine.state.paths
-                        ) {
-                            Ok(c) => {
-                                transmitter.send(AppEvent::Output(
-                                    OutputMsg { 
-                                        text: "Settings saved!".to_string(), 
-                                        color: Color::Green, 
-                                        bold: true, 
-                                        bg_color: None, 
-                                        exchange: None, 
-                                        ticker: None 
-                                    }
-                                ));
-
-                                self.engine.state.config = c;
-                            },
-
-                            Err(e) => {
-                                let mut msg: String = String::new();
-                                let mut col: Color = Color::Red;
-                                match e {
-                                    ConfigError::NoChangesMade => {
-                                        msg = String::from(
-                                            "No changes detected. Not saved."
-                                        );
-                                        col = Color::Yellow;
-                                    },
-                                    _ => {
-                                        msg = format!(
-                                            "Settings save failed: {}", e
-                                        );
-                                    }
-                                };
-                                transmitter.send(AppEvent::Output(
-                                    OutputMsg { 
-                                        text: msg, 
-                                        color: col, 
-                                        bold: true, 
-                                        bg_color: None, 
-                                        exchange: None, 
-                                        ticker: None 
-                                    }
-                                ));
-                            }
-                        };
-                       
                     };
                     
                     screen.handle_key(key).await;
                 },
                 
                 Screen::CandleBuilder(screen) => {
-                    if let KeyCode::Esc = key.code {
-                        if let CandleFocus::Top = screen.focus {
-                            new_focus = Focus::Operations;
-                            breakout = true; 
-                        };
+                    if let KeyCode::Esc = key.code
+                        && let CandleFocus::Top = screen.focus 
+                    {
+                        new_focus = Focus::Operations;
+                        breakout = true; 
                     };
                     screen.handle_key(key).await;
                 },
 
                 Screen::StrategyManager(screen) => {
+                    if let KeyCode::Esc = key.code
+                        && let StrategyFocus::Top = screen.focus 
+                    {
+                        new_focus = Focus::Operations;
+                        breakout = true; 
+                    };
+                    screen.handle_key(key).await;
+                }
+
+                Screen::ChartViewer(screen) => {
+                    if let KeyCode::Esc = key.code
+                        && let ChartFocus::Top = screen.focus 
+                    {
+                        new_focus = Focus::Operations;
+                        breakout = true; 
+                    };
+                    screen.handle_key(key).await;
+                }
+
+                Screen::IntegrityCheck(screen) => {
                     if let KeyCode::Esc = key.code {
-                        if let StrategyFocus::Top = screen.focus {
-                            new_focus = Focus::Operations;
-                            breakout = true; 
-                        };
+                        new_focus = Focus::Operations;
+                        breakout = true; 
+                    };
+                    screen.handle_key(key).await;
+                }
+
+                Screen::LiveWatch(screen) => {
+                    if let KeyCode::Esc = key.code {
+                        new_focus = Focus::Operations;
+                        breakout = true; 
+                    };
+                    screen.handle_key(key).await;
+                }
+
+                Screen::JobQueue(screen) => {
+                    if let KeyCode::Esc = key.code {
+                        new_focus = Focus::Operations;
+                        breakout = true; 
+                    };
+                    screen.handle_key(key, &self.engine).await;
+                }
+
+                Screen::PaperTrading(screen) => {
+                    if let KeyCode::Esc = key.code {
+                        new_focus = Focus::Operations;
+                        breakout = true; 
+                    };
+                    screen.handle_key(key).await;
+                }
+
+                Screen::OrderList(screen) => {
+                    if let KeyCode::Esc = key.code
+                        && screen.price_input.is_none()
+                    {
+                        new_focus = Focus::Operations;
+                        breakout = true; 
                     };
                     screen.handle_key(key).await;
                 }
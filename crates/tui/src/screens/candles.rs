@@ -30,30 +30,33 @@ use ratatui::{
 use tokio::{
     task::JoinHandle,
     sync::mpsc::UnboundedSender,
-    fs::write,
 };
 use sqlx::PgPool;
 
 use crate::{move_up, move_down, AppEvent, OutputMsg};
+use super::search::ListSearch;
 use timestamp_tools::{
     period_is_valid,
     VALID_PERIODS,
 };
-use string_helpers::multi_line_to_single_line;
+use string_helpers::{Locale, multi_line_to_single_line};
 use app_core::{
+    BarSeries,
     build_candles,
     app_state::{SystemPaths},
+    catalog::refresh_catalog,
+    bars::{BarOptions, CsvDialect, ExportFormat},
 };
 
 
 // ---------------------------- INFO STRINGS ------------------------------- //
-const INFO_STRINGS: [&'static str; 4] = [
+const INFO_STRINGS: [&str; 4] = [
     r#"Displays a list of available exchanges. Must choose an exchange before 
     choosing a ticker symbol."#,
 
     r#"Displays a list of available ticker symbols from the given exchange.
     Must choose an exchange before choosing a ticker so that available tickers
-    can be looked up."#,
+    can be looked up. Press '/' to search the list."#,
 
     r#"Press 'Enter' to begin typing a period length, and 'Esc' to cancel. 
     Period lengths must have an integer value followed by a valid period 
@@ -63,17 +66,23 @@ const INFO_STRINGS: [&'static str; 4] = [
     candles."#,
 
     r#"Builds a set of candles if all input values are provided. The candle
-    data will exported as a CSV file."#
+    data will be exported in the format shown next to 'Build'. Press 'f' while 
+    'Build' is highlighted to switch between CSV, JSON, and Parquet. The 
+    latest bars are previewed in the output window."#
 ];
 
 
 // -------------- CANDLE SCREEN ------------- //
-const ERROR_MSGS: [&'static str; 1] = [
+/// Bars shown in the output window after a build
+const PREVIEW_ROWS: usize = 5;
+
+
+const ERROR_MSGS: [&str; 1] = [
     "Needs Exchange, Ticker, and Period to build candle data with."
 ];
 
 #[derive(Clone)]
-enum CandleAction {
+pub enum CandleAction {
     Exchange,
     Ticker,
     Period,
@@ -93,6 +102,7 @@ impl CandleAction {
 
 }
 
+#[derive(PartialEq)]
 pub enum CandleFocus {
     Top,
     Bottom,
@@ -104,6 +114,10 @@ pub struct CandleScreen {
     ticker: String,
     period: String,
     previous_period: String,
+    export_format: ExportFormat,
+    locale: Locale,
+    csv_dialect: CsvDialect,
+    bar_options: BarOptions,
 
     db_pool: PgPool,
 
@@ -112,6 +126,8 @@ pub struct CandleScreen {
     top_state: ListState,
     btm_state: ListState,
     btm_item_data: Vec<String>,
+    /// Filters the bottom list, opened with '/'
+    pub search: ListSearch,
     token_pairs: HashMap<String, Vec<String>>,
     task: Option<JoinHandle<()>>,
     pub transmitter: UnboundedSender<AppEvent>,
@@ -123,6 +139,9 @@ impl CandleScreen {
         token_pairs: HashMap<String, Vec<String>>,
         transmitter: UnboundedSender<AppEvent>,
         db_pool: PgPool,
+        locale: Locale,
+        csv_dialect: CsvDialect,
+        bar_options: BarOptions,
     ) -> Self {
        
         let mut top_state = ListState::default();
@@ -134,6 +153,10 @@ impl CandleScreen {
             ticker: String::new(),
             period: String::new(),
             previous_period: String::new(),  // For error checking
+            export_format: ExportFormat::Csv,
+            locale,
+            csv_dialect,
+            bar_options,
           
             db_pool,
 
@@ -142,6 +165,7 @@ impl CandleScreen {
             top_state,
             btm_state: ListState::default(),
             btm_item_data: Vec::new(),
+            search: ListSearch::default(),
             token_pairs,
             task,
             transmitter,
@@ -188,11 +212,11 @@ impl CandleScreen {
             &mut self.top_state
         );
 
-        self.btm_item_data = match self.step {
+        let items: Vec<String> = match self.step {
             
             CandleAction::Exchange => { 
                 let mut exchanges: Vec<String> = Vec::new();
-                for (ex, _) in &self.token_pairs {
+                for ex in self.token_pairs.keys() {
                     exchanges.push(ex.clone());
                 };
                 exchanges
@@ -225,6 +249,11 @@ impl CandleScreen {
             
             _ => { Vec::new() } 
         };
+        self.btm_item_data = self.search.apply(
+            items, 
+            &self.btm_item_data, 
+            &mut self.btm_state
+        );
 
         let btm_items: Vec<ListItem> = self.btm_item_data.iter()
             .map(|v| ListItem::new(&v[..]))
@@ -233,7 +262,7 @@ impl CandleScreen {
         let btm_list = List::new(btm_items)
             .block(
                 Block::default()
-                    .title(self.step.title())
+                    .title(self.search.title(self.step.title()))
                     .borders(Borders::ALL)
             )
             .highlight_style(
@@ -253,6 +282,11 @@ impl CandleScreen {
         );
     }
 
+    /// Whether a task started from this screen is still running
+    pub fn is_busy(&self) -> bool {
+        self.task.as_ref().is_some_and(|h| !h.is_finished())
+    }
+
     fn get_option_title(&self, action: &CandleAction) -> String {
         
         let mut title = String::new(); 
@@ -261,14 +295,14 @@ impl CandleScreen {
             
             CandleAction::Exchange => {
                 title.push_str("Exchange");
-                if self.exchange.len() > 0 {
+                if !self.exchange.is_empty() {
                     title.push_str(&format!(": {}", self.exchange)) 
                 };
             },
 
             CandleAction::Ticker => {
                 title.push_str("Ticker");
-                if self.ticker.len() > 0 {
+                if !self.ticker.is_empty() {
                     title.push_str(&format!("  : {}", self.ticker)) 
                 };
             },
@@ -277,7 +311,7 @@ impl CandleScreen {
                 title.push_str("Period");
                 
                 if let CandleFocus::InputMode = self.focus {
-                    if self.period.len() > 0 {
+                    if !self.period.is_empty() {
                         title.push_str(&format!("  : {}", self.period)) 
                     }
                     else {
@@ -287,14 +321,14 @@ impl CandleScreen {
                     };
                 }
                 else {
-                    if self.period.len() > 0 {
+                    if !self.period.is_empty() {
                         title.push_str(&format!("  : {}", self.period)) 
                     };
                 };
             },
 
             CandleAction::Build => { 
-                title.push_str("Build") 
+                title.push_str(&format!("Build  : {}", self.export_format)) 
             },
 
             _ => {}
@@ -306,9 +340,9 @@ impl CandleScreen {
 
     pub async fn handle_candle_build(&mut self) {
         
-        let e: bool = self.exchange.len() > 0;
-        let t: bool = self.ticker.len() > 0;
-        let p: bool = self.period.len() > 0;
+        let e: bool = !self.exchange.is_empty();
+        let t: bool = !self.ticker.is_empty();
+        let p: bool = !self.period.is_empty();
         
         if e && t && p {
 
@@ -316,10 +350,14 @@ impl CandleScreen {
             let ticker = self.ticker.clone();
             let period = self.period.clone();
             let pool = self.db_pool.clone(); 
+            let format = self.export_format;
+            let locale = self.locale;
+            let dialect = self.csv_dialect.clone();
+            let bar_options = self.bar_options;
             let tx = self.transmitter.clone();
             
-            self.transmitter.send(AppEvent::Clear);
-            self.transmitter.send(
+            let _ = self.transmitter.send(AppEvent::Clear);
+            let _ = self.transmitter.send(
                 AppEvent::Output(OutputMsg::new(
                     "Building candles.".to_string(),
                     Color::Yellow,
@@ -332,51 +370,83 @@ impl CandleScreen {
 
             self.task = Some(tokio::spawn(async move {
 
-                if let Ok(candles) = build_candles(
-                    &exchange, &ticker, &period, pool 
-                ).await
-                {
-                    let text = candles.to_string();
-                    
-                    if let Ok(paths) = SystemPaths::new() {
+                let send = |text: String, color: Color, bold: bool| {
+                    let _ = tx.send(AppEvent::Output(OutputMsg::new(
+                        text, color, bold, None, None, None
+                    )));
+                };
 
-                        let file_name = paths
-                            .candle_data
-                            .join(candles.get_file_name());
-                        
-                        if let Err(_) = write(&file_name, text).await {
-                            tx.send(AppEvent::Output(OutputMsg::new(
-                                "Failed to export candle data".to_string(),
-                                Color::Red,
-                                true,
-                                None,
-                                None,
-                                None
-                            )));
-                        }
-                        else {
-                            println!();
-                            tx.send(AppEvent::Output(OutputMsg::new(
-                                format!(
-                                    "Saved data to {}", 
-                                    file_name.display()
-                                ),
-                                Color::Green,
-                                true,
-                                None,
-                                None,
-                                None
-                            )));
-                        }; 
-                    }; 
+                let candles: BarSeries = match build_candles(
+                    &exchange, &ticker, &period, bar_options, pool.clone() 
+                ).await {
+                    Ok(c) => c,
+                    Err(e) => {
+                        send(
+                            format!("Failed to build candles: {}", e), 
+                            Color::Red, 
+                            true
+                        );
+                        return
+                    }
                 };
-            
+
+                send(
+                    format!(
+                        "Built {} bars from {} ticks. Latest bars:", 
+                        candles.len(), 
+                        candles.tick_data.len()
+                    ),
+                    Color::Yellow,
+                    false
+                );
+                for row in preview_table(&candles, &locale) {
+                    send(row, Color::White, false);
+                };
+
+                let paths: SystemPaths = match SystemPaths::new() {
+                    Ok(p) => p,
+                    Err(e) => {
+                        send(
+                            format!("Couldn't find the export folder: {}", e),
+                            Color::Red,
+                            true
+                        );
+                        return
+                    }
+                };
+
+                send(format!("Exporting as {}.", format), Color::Yellow, false);
+
+                match candles.export(
+                    &paths.candle_data, 
+                    format, 
+                    &locale, 
+                    &dialect
+                ) {
+                    Ok(file_name) => {
+                        send(
+                            format!(
+                                "Saved data to {} ('o' to open)", 
+                                file_name.display()
+                            ),
+                            Color::Green,
+                            true
+                        );
+                        let _ = tx.send(AppEvent::Exported(file_name));
+                        let _ = refresh_catalog(pool).await;
+                    },
+                    Err(e) => send(
+                        format!("Failed to export candle data: {}", e),
+                        Color::Red,
+                        true
+                    )
+                }; 
             }));
 
         }
         else {
             
-            self.transmitter.send(
+            let _ = self.transmitter.send(
                 AppEvent::Output(OutputMsg { 
                     text: ERROR_MSGS[0]
                         .to_string(), 
@@ -403,19 +473,21 @@ impl CandleScreen {
             };
         };
 
-        if let CandleFocus::InputMode = self.focus {
+        if self.search.typing {
+            self.search.handle_key(&key);
+        }
+
+        else if let CandleFocus::InputMode = self.focus {
             
             if let CandleAction::Period = &self.step {
                 
                 match key.code {
                     KeyCode::Char(c) => {
-                        self.period.push_str(&c.to_string());
+                        self.period.push(c);
                     },
-                    KeyCode::Backspace => {
-                        if self.period.len() > 0 {
-                            let i = self.period.len().saturating_sub(1);
-                            self.period = self.period[..i].to_string(); 
-                        };
+                    KeyCode::Backspace if !self.period.is_empty() => {
+                        let i = self.period.len().saturating_sub(1);
+                        self.period = self.period[..i].to_string(); 
                     },
                     KeyCode::Enter => {
                         if period_is_valid(&self.period) {
@@ -496,6 +568,24 @@ impl CandleScreen {
                     }
                 }
 
+                KeyCode::Char('/') 
+                    if matches!(self.focus, CandleFocus::Bottom) => 
+                {
+                    self.search.open();
+                },
+
+                KeyCode::Char('f') 
+                    if matches!(self.focus, CandleFocus::Top)
+                    && self.top_state.selected() == Some(3) => 
+                {
+                    self.export_format = match self.export_format {
+                        ExportFormat::Csv => ExportFormat::Json,
+                        ExportFormat::Json => ExportFormat::Jsonl,
+                        ExportFormat::Jsonl => ExportFormat::Parquet,
+                        ExportFormat::Parquet => ExportFormat::Csv,
+                    };
+                },
+
                 KeyCode::Enter => {
                 
                     match &self.focus {
@@ -511,7 +601,7 @@ impl CandleScreen {
                                 }, 
                                 
                                 Some(1) => {
-                                    if self.exchange.len() > 0 {
+                                    if !self.exchange.is_empty() {
                                         self.step = CandleAction::Ticker;
                                         self.focus = CandleFocus::Bottom;
                                         self.btm_state.select(Some(0));
@@ -520,7 +610,7 @@ impl CandleScreen {
                                         let msg = String::from(
                                             "Please choose an exchange"
                                         );
-                                        self.transmitter.send(
+                                        let _ = self.transmitter.send(
                                             AppEvent::Output(
                                                 OutputMsg { 
                                                     text: msg, 
@@ -539,7 +629,7 @@ impl CandleScreen {
                                     let msg = "Start typing..."
                                         .to_string();
                                     self.focus = CandleFocus::InputMode;
-                                    self.transmitter.send(
+                                    let _ = self.transmitter.send(
                                         AppEvent::Output(OutputMsg { 
                                             text: msg, 
                                             color: Color::Yellow, 
@@ -555,7 +645,7 @@ impl CandleScreen {
                                 Some(3) => {
                                     self.handle_candle_build().await;
                                 },
-                                _ => { return } 
+                                _ => { } 
                             }
                         },
                         
@@ -590,6 +680,7 @@ impl CandleScreen {
                             self.focus = CandleFocus::Top;  
                             self.step = CandleAction::None;
                             self.btm_state.select(None);
+                            self.search.clear();
                         },
 
                         _ => {}
@@ -604,6 +695,7 @@ impl CandleScreen {
                         CandleFocus::Bottom => {
                             self.step = CandleAction::None;
                             self.btm_state.select(None);
+                            self.search.clear();
                         },
                         CandleFocus::InputMode => { }
                     };
@@ -628,3 +720,44 @@ impl CandleScreen {
 }
 
 
+/// The last `PREVIEW_ROWS` bars of `series` as aligned text rows, under a
+/// header row
+fn preview_table(series: &BarSeries, locale: &Locale) -> Vec<String> {
+
+    let start: usize = series.len().saturating_sub(PREVIEW_ROWS);
+
+    let mut rows: Vec<[String; 6]> = Vec::from([[
+        "Open time", "Open", "High", "Low", "Close", "Volume"
+    ].map(String::from)]);
+
+    for bar in &series.bars[start..] {
+        rows.push([
+            bar.open_date().format("%Y-%m-%d %H:%M").to_string(),
+            locale.format_number(bar.open()),
+            locale.format_number(bar.high()),
+            locale.format_number(bar.low()),
+            locale.format_number(bar.close()),
+            locale.format_number(bar.volume()),
+        ]);
+    };
+
+    let widths: Vec<usize> = (0..6)
+        .map(|col| rows.iter().map(|r| r[col].len()).max().unwrap_or(0))
+        .collect();
+
+    rows
+        .iter()
+        .map(|row| {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .enumerate()
+                .map(|(col, (cell, width))| match col {
+                    0 => format!("{:<width$}", cell),
+                    _ => format!("{:>width$}", cell)
+                })
+                .collect();
+            format!("  {}", cells.join("  "))
+        })
+        .collect()
+}
@@ -0,0 +1,845 @@
+use std::{collections::HashMap, ops::Range};
+
+use ratatui::{
+    widgets::{
+        Block,
+        Borders,
+        List,
+        ListState,
+        ListItem,
+        canvas::{
+            Canvas,
+            Context,
+            Line as CanvasLine,
+        },
+    },
+    crossterm::{
+        event::{
+            KeyEvent,
+            KeyCode
+        }
+    },
+    layout::{
+        Layout,
+        Constraint,
+        Direction,
+        Rect,
+    },
+    style::{
+        Style,
+        Modifier,
+        Color
+    },
+    symbols::Marker,
+    text::{Line, Span},
+    Frame
+};
+use tokio::{
+    task::JoinHandle,
+    sync::mpsc::UnboundedSender,
+};
+use sqlx::PgPool;
+
+use crate::{move_up, move_down, AppEvent, OutputMsg};
+use super::{pair_color, search::ListSearch};
+use timestamp_tools::{
+    period_is_valid,
+    VALID_PERIODS,
+};
+use string_helpers::multi_line_to_single_line;
+use app_core::{
+    BarBuildError,
+    BarSeries,
+    Indicator,
+    build_candles,
+    bars::{Bar, BarOptions},
+    charts::{CandleValues, Chart},
+    indicators::{BollingerBands, Ema, IndicatorLine, Sma},
+};
+
+
+// ---------------------------- INFO STRINGS ------------------------------- //
+const INFO_STRINGS: [&str; 4] = [
+    r#"Displays a list of available exchanges. Must choose an exchange before
+    choosing a ticker symbol."#,
+
+    r#"Displays a list of available ticker symbols from the given exchange.
+    Press '/' to search the list."#,
+
+    r#"Press 'Enter' to begin typing a period length, and 'Esc' to cancel.
+    Period lengths must have an integer value followed by a valid period
+    symbol, e.g. 15m, 4h, 1d or 500t."#,
+
+    r#"Builds candles for the chosen pair and period and draws them. Use the
+    left and right arrow keys ('h' and 'l') to scroll through time, 'H' and
+    'L' to scroll a full screen, and 'End' to jump back to the latest bar.
+    Press 's', 'e' and 'b' to toggle the SMA, EMA and Bollinger band 
+    overlays, 'v' to toggle the volume pane, and 'w' to hide the Operations
+    and Output panes."#
+];
+
+
+// ------------------------------ OVERLAYS --------------------------------- //
+const SMA_PERIOD: usize = 20;
+const EMA_PERIOD: usize = 50;
+const BOLLINGER_PERIOD: usize = 20;
+const BOLLINGER_STD_DEVS: f64 = 2.0;
+
+/// Indicators that can be drawn over the candles, each toggled by its key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Overlay {
+    Sma,
+    Ema,
+    Bollinger,
+}
+
+impl Overlay {
+
+    fn from_key(key: char) -> Option<Self> {
+        match key {
+            's' => Some(Overlay::Sma),
+            'e' => Some(Overlay::Ema),
+            'b' => Some(Overlay::Bollinger),
+            _ => None
+        }
+    }
+
+    fn label(&self) -> String {
+        match self {
+            Overlay::Sma => format!("sma({})", SMA_PERIOD),
+            Overlay::Ema => format!("ema({})", EMA_PERIOD),
+            Overlay::Bollinger => format!(
+                "bollinger({},{})", BOLLINGER_PERIOD, BOLLINGER_STD_DEVS
+            ),
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            Overlay::Sma => Color::Yellow,
+            Overlay::Ema => Color::Cyan,
+            Overlay::Bollinger => Color::Magenta,
+        }
+    }
+
+    /// Every line of the indicator over the whole chart
+    fn compute(&self, series: &BarSeries) -> Vec<IndicatorLine> {
+        let lines = match self {
+            Overlay::Sma => Sma::new(SMA_PERIOD).map(|i| i.compute(series)),
+            Overlay::Ema => Ema::new(EMA_PERIOD).map(|i| i.compute(series)),
+            Overlay::Bollinger => BollingerBands::new(
+                BOLLINGER_PERIOD, 
+                BOLLINGER_STD_DEVS
+            ).map(|i| i.compute(series)),
+        };
+        lines.unwrap_or_default()
+    }
+}
+
+
+// -------------- CHART SCREEN ------------- //
+const ERROR_MSGS: [&str; 1] = [
+    "Needs Exchange, Ticker, and Period to draw a chart."
+];
+
+#[derive(Clone)]
+pub enum ChartAction {
+    Exchange,
+    Ticker,
+    Period,
+    Show,
+    None,
+}
+
+impl ChartAction {
+
+    fn title(&self) -> &'static str {
+        match self {
+            Self::Exchange => "Exchange Selection",
+            Self::Ticker => "Asset Pair Selection",
+            _ => "Info"
+        }
+    }
+
+}
+
+#[derive(PartialEq)]
+pub enum ChartFocus {
+    Top,
+    Bottom,
+    InputMode,
+    /// Scrolling the chart
+    Chart,
+}
+
+pub struct ChartScreen {
+    exchange: String,
+    ticker: String,
+    period: String,
+    previous_period: String,
+    bar_options: BarOptions,
+
+    db_pool: PgPool,
+
+    step: ChartAction,
+    pub focus: ChartFocus,
+    top_state: ListState,
+    btm_state: ListState,
+    btm_item_data: Vec<String>,
+    /// Filters the bottom list, opened with '/'
+    pub search: ListSearch,
+    token_pairs: HashMap<String, Vec<String>>,
+
+    chart: Option<Chart>,
+    /// Bars between the right edge of the chart and the latest bar
+    offset: usize,
+    /// Bars that fit in the chart pane when it was last drawn
+    visible_bars: usize,
+    /// Enabled overlays, with their lines over the whole chart
+    overlays: Vec<(Overlay, Vec<IndicatorLine>)>,
+    show_volume: bool,
+
+    task: Option<JoinHandle<Result<Chart, BarBuildError>>>,
+    pub transmitter: UnboundedSender<AppEvent>,
+}
+
+impl ChartScreen {
+
+    pub fn new(
+        token_pairs: HashMap<String, Vec<String>>,
+        transmitter: UnboundedSender<AppEvent>,
+        db_pool: PgPool,
+        bar_options: BarOptions,
+    ) -> Self {
+
+        let mut top_state = ListState::default();
+        top_state.select(Some(0));
+
+        ChartScreen {
+            exchange: String::new(),
+            ticker: String::new(),
+            period: String::new(),
+            previous_period: String::new(),
+            bar_options,
+
+            db_pool,
+
+            step: ChartAction::None,
+            focus: ChartFocus::Top,
+            top_state,
+            btm_state: ListState::default(),
+            btm_item_data: Vec::new(),
+            search: ListSearch::default(),
+            token_pairs,
+
+            chart: None,
+            offset: 0,
+            visible_bars: 0,
+            overlays: Vec::new(),
+            show_volume: true,
+
+            task: None,
+            transmitter,
+        }
+
+    }
+
+    /// Picks up the chart once its candles have been built
+    pub async fn pre_draw(&mut self) {
+
+        let finished: bool = self.task
+            .as_ref()
+            .is_some_and(|handle| handle.is_finished());
+
+        if !finished {
+            return
+        };
+
+        let Some(handle) = self.task.take() else { return };
+
+        match handle.await {
+            Ok(Ok(chart)) if chart.num_bars_on_chart() > 0 => {
+                for (overlay, lines) in self.overlays.iter_mut() {
+                    *lines = overlay.compute(&chart.bars);
+                };
+                self.chart = Some(chart);
+                self.offset = 0;
+                self.focus = ChartFocus::Chart;
+                let _ = self.transmitter.send(AppEvent::Clear);
+            },
+            Ok(Ok(_)) => self.send_error(
+                "No bars to draw for this pair and period".to_string()
+            ),
+            Ok(Err(e)) => self.send_error(format!(
+                "Failed to build candles: {}", e
+            )),
+            Err(e) => self.send_error(format!(
+                "Chart task failed: {}", e
+            )),
+        };
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect) {
+
+        let nested_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(6),  // 4 options + top and bottom borders
+                Constraint::Percentage(100),
+            ])
+            .split(area);
+
+        let top_items: Vec<ListItem> = Self::SCREEN_OPTIONS
+            .iter()
+            .map(|v| ListItem::new(self.get_option_title(v)))
+            .collect();
+
+        let top_list = List::new(top_items)
+            .block(
+                Block::default()
+                    .title(Self::SCREEN_NAME)
+                    .borders(Borders::ALL)
+            )
+            .highlight_style(
+                match self.focus {
+                    ChartFocus::Top => Style::default()
+                        .add_modifier(Modifier::REVERSED)
+                        .green(),
+                    ChartFocus::InputMode => Style::default()
+                        .add_modifier(Modifier::REVERSED)
+                        .yellow(),
+                    _ => Style::default()
+                }
+            );
+
+        frame.render_stateful_widget(
+            top_list,
+            nested_chunks[0],
+            &mut self.top_state
+        );
+
+        if let (ChartFocus::Chart, Some(chart)) = (&self.focus, &self.chart) {
+
+            // One bar per column inside the borders
+            self.visible_bars = nested_chunks[1].width.saturating_sub(2)
+                as usize;
+
+            draw_candles(
+                frame,
+                nested_chunks[1],
+                chart,
+                &self.overlays,
+                self.show_volume,
+                self.visible_bars,
+                self.offset
+            );
+            return
+        };
+
+        let items: Vec<String> = match self.step {
+
+            ChartAction::Exchange => self.token_pairs
+                .keys()
+                .cloned()
+                .collect(),
+
+            ChartAction::Ticker => self.token_pairs
+                .get(&self.exchange)
+                .cloned()
+                .unwrap_or_default(),
+
+            ChartAction::None => {
+                let width: u16 = nested_chunks[0].width.saturating_sub(2);
+                match self.top_state.selected() {
+                    Some(n) => Vec::from([
+                        multi_line_to_single_line(INFO_STRINGS[n], width)
+                    ]),
+                    None => Vec::new()
+                }
+            }
+
+            _ => Vec::new()
+        };
+        self.btm_item_data = self.search.apply(
+            items, 
+            &self.btm_item_data, 
+            &mut self.btm_state
+        );
+
+        let btm_items: Vec<ListItem> = self.btm_item_data.iter()
+            .map(|v| ListItem::new(&v[..]))
+            .collect();
+
+        let btm_list = List::new(btm_items)
+            .block(
+                Block::default()
+                    .title(self.search.title(self.step.title()))
+                    .borders(Borders::ALL)
+            )
+            .highlight_style(
+                match self.focus {
+                    ChartFocus::Bottom => Style::default()
+                        .add_modifier(Modifier::REVERSED)
+                        .green(),
+                    _ => Style::default()
+                }
+            );
+
+        frame.render_stateful_widget(
+            btm_list,
+            nested_chunks[1],
+            &mut self.btm_state
+        );
+    }
+
+    /// Whether a task started from this screen is still running
+    pub fn is_busy(&self) -> bool {
+        self.task.as_ref().is_some_and(|h| !h.is_finished())
+    }
+
+    fn get_option_title(&self, action: &ChartAction) -> String {
+
+        let value = |label: &str, value: &str| match value.is_empty() {
+            true => label.to_string(),
+            false => format!("{label}: {value}")
+        };
+
+        match action {
+            ChartAction::Exchange => value("Exchange", &self.exchange),
+            ChartAction::Ticker => value("Ticker  ", &self.ticker),
+            ChartAction::Period => match (&self.focus, self.period.is_empty()) {
+                (ChartFocus::InputMode, true) => {
+                    "Period  : Enter period".to_string()
+                },
+                _ => value("Period  ", &self.period)
+            },
+            ChartAction::Show => "Show chart".to_string(),
+            ChartAction::None => String::new()
+        }
+    }
+
+    fn handle_show(&mut self) {
+
+        if self.exchange.is_empty()
+            || self.ticker.is_empty()
+            || self.period.is_empty()
+        {
+            self.send_error(ERROR_MSGS[0].to_string());
+            return
+        };
+
+        let exchange = self.exchange.clone();
+        let ticker = self.ticker.clone();
+        let period = self.period.clone();
+        let pool = self.db_pool.clone();
+        let bar_options = self.bar_options;
+
+        let _ = self.transmitter.send(AppEvent::Clear);
+        let _ = self.transmitter.send(
+            AppEvent::Output(OutputMsg::new(
+                "Building candles.".to_string(),
+                Color::Yellow,
+                false,
+                None,
+                None,
+                None
+            ))
+        );
+
+        self.task = Some(tokio::spawn(async move {
+            let mut series = build_candles(
+                &exchange, &ticker, &period, bar_options, pool
+            ).await?;
+
+            // Only the bars are drawn
+            series.tick_data = Vec::new();
+
+            Ok(Chart::new(series))
+        }));
+    }
+
+    fn send_error(&self, text: String) {
+        let _ = self.transmitter.send(
+            AppEvent::Output(OutputMsg::new(
+                text,
+                Color::Red,
+                true,
+                None,
+                None,
+                None
+            ))
+        );
+    }
+
+    fn toggle_overlay(&mut self, overlay: Overlay) {
+
+        let Some(chart) = &self.chart else { return };
+
+        match self.overlays.iter().position(|(o, _)| *o == overlay) {
+            Some(i) => {
+                self.overlays.remove(i);
+            },
+            None => {
+                let lines = overlay.compute(&chart.bars);
+                self.overlays.push((overlay, lines));
+            }
+        };
+    }
+
+    /// Moves the chart `bars` back in time, or forward when negative
+    fn scroll(&mut self, bars: isize) {
+
+        let Some(chart) = &self.chart else { return };
+
+        let max_offset: usize = chart
+            .num_bars_on_chart()
+            .saturating_sub(self.visible_bars);
+
+        self.offset = self.offset
+            .saturating_add_signed(bars)
+            .min(max_offset);
+    }
+
+    pub async fn handle_key(&mut self, key: KeyEvent) {
+
+        if self.task.is_some() {
+            return
+        };
+
+        if self.search.typing {
+            self.search.handle_key(&key);
+            return
+        };
+
+        match self.focus {
+
+            ChartFocus::Chart => {
+
+                let page: isize = self.visible_bars.max(1) as isize;
+
+                match key.code {
+                    KeyCode::Left | KeyCode::Char('h') => self.scroll(1),
+                    KeyCode::Right | KeyCode::Char('l') => self.scroll(-1),
+                    KeyCode::Char('H') => self.scroll(page),
+                    KeyCode::Char('L') => self.scroll(-page),
+                    KeyCode::End => self.offset = 0,
+                    KeyCode::Char('v') => self.show_volume = !self.show_volume,
+                    KeyCode::Char(c) => {
+                        if let Some(overlay) = Overlay::from_key(c) {
+                            self.toggle_overlay(overlay);
+                        };
+                    },
+                    KeyCode::Esc => self.focus = ChartFocus::Top,
+                    _ => {}
+                };
+            },
+
+            ChartFocus::InputMode => {
+
+                match key.code {
+                    KeyCode::Char(c) => {
+                        self.period.push(c);
+                    },
+                    KeyCode::Backspace => {
+                        self.period.pop();
+                    },
+                    KeyCode::Enter => {
+                        if period_is_valid(&self.period) {
+                            self.previous_period = self.period.clone();
+                            self.focus = ChartFocus::Top;
+                            self.step = ChartAction::None;
+                            let _ = self.transmitter.send(AppEvent::Clear);
+                        }
+                        else {
+                            self.send_error(format!(
+                                "Invalid period length: try integer + {:?}",
+                                VALID_PERIODS
+                            ));
+                        };
+                    },
+                    KeyCode::Esc => {
+                        self.period = self.previous_period.clone();
+                        self.focus = ChartFocus::Top;
+                        self.step = ChartAction::None;
+                    }
+                    _ => {}
+                }
+            },
+
+            ChartFocus::Top => {
+
+                match key.code {
+                    KeyCode::Up | KeyCode::Char('k') => move_up(
+                        &mut self.top_state,
+                        Self::SCREEN_OPTIONS.len(),
+                        1
+                    ),
+                    KeyCode::Down | KeyCode::Char('j') => move_down(
+                        &mut self.top_state,
+                        Self::SCREEN_OPTIONS.len(),
+                        1
+                    ),
+                    KeyCode::Enter => match self.top_state.selected() {
+                        Some(0) => {
+                            self.step = ChartAction::Exchange;
+                            self.focus = ChartFocus::Bottom;
+                            self.btm_state.select(Some(0));
+                        },
+                        Some(1) if !self.exchange.is_empty() => {
+                            self.step = ChartAction::Ticker;
+                            self.focus = ChartFocus::Bottom;
+                            self.btm_state.select(Some(0));
+                        },
+                        Some(1) => self.send_error(
+                            "Please choose an exchange".to_string()
+                        ),
+                        Some(2) => {
+                            self.step = ChartAction::Period;
+                            self.focus = ChartFocus::InputMode;
+                        },
+                        Some(3) => self.handle_show(),
+                        _ => {}
+                    },
+                    KeyCode::Esc => self.top_state.select(None),
+                    _ => {}
+                };
+            },
+
+            ChartFocus::Bottom => {
+
+                match key.code {
+                    KeyCode::Up | KeyCode::Char('k') => move_up(
+                        &mut self.btm_state,
+                        self.btm_item_data.len(),
+                        1
+                    ),
+                    KeyCode::Down | KeyCode::Char('j') => move_down(
+                        &mut self.btm_state,
+                        self.btm_item_data.len(),
+                        1
+                    ),
+                    KeyCode::Char('/') => self.search.open(),
+                    KeyCode::Enter | KeyCode::Esc => {
+
+                        if let KeyCode::Enter = key.code
+                            && let Some(i) = self.btm_state.selected()
+                            && let Some(item) = self.btm_item_data.get(i)
+                        {
+                            match self.step {
+                                ChartAction::Exchange => {
+                                    self.exchange = item.clone();
+                                    self.ticker = String::new();
+                                },
+                                ChartAction::Ticker => {
+                                    self.ticker = item.clone();
+                                },
+                                _ => {}
+                            };
+                        };
+
+                        self.focus = ChartFocus::Top;
+                        self.step = ChartAction::None;
+                        self.btm_state.select(None);
+                        self.search.clear();
+                    },
+                    _ => {}
+                };
+            },
+        }
+    }
+
+    pub const SCREEN_NAME: &'static str = "Chart Viewer";
+
+    pub const SCREEN_OPTIONS: [ChartAction; 4] = [
+        ChartAction::Exchange,
+        ChartAction::Ticker,
+        ChartAction::Period,
+        ChartAction::Show,
+    ];
+}
+
+
+/// Draws `num_bars` candles ending `offset` bars before the latest one, with
+/// the enabled overlays on top and optionally a volume pane underneath
+///
+/// Each bar takes one column: a thin wick from low to high under a body two
+/// dots wide from open to close. The price range of the visible bars and
+/// their dates are shown in the borders.
+fn draw_candles(
+    frame: &mut Frame,
+    area: Rect,
+    chart: &Chart,
+    overlays: &[(Overlay, Vec<IndicatorLine>)],
+    show_volume: bool,
+    num_bars: usize,
+    offset: usize
+) {
+
+    let range: Range<usize> = chart.window_range(num_bars, offset);
+    let bars: &[Bar] = &chart.bars.bars[range.clone()];
+    let values: Vec<CandleValues> = bars
+        .iter()
+        .map(CandleValues::from)
+        .collect();
+
+    // Visible part of every overlay line, with the color it's drawn in
+    let lines: Vec<(Color, &[Option<f64>])> = overlays
+        .iter()
+        .flat_map(|(overlay, lines)| lines
+            .iter()
+            .map(|line| (overlay.color(), &line.values[range.clone()]))
+        )
+        .collect();
+
+    let overlay_values = lines
+        .iter()
+        .flat_map(|(_, values)| values.iter().flatten().copied());
+
+    let high: f64 = values
+        .iter()
+        .map(|v| v.high)
+        .chain(overlay_values.clone())
+        .fold(f64::MIN, f64::max);
+    let low: f64 = values
+        .iter()
+        .map(|v| v.low)
+        .chain(overlay_values)
+        .fold(f64::MAX, f64::min);
+
+    let legend: String = overlays
+        .iter()
+        .map(|(overlay, _)| format!("  {}", overlay.label()))
+        .collect();
+
+    let title: Line = Line::from(vec![
+        Span::styled(
+            format!(
+                "{} {}", 
+                chart.bars.info.exchange(), 
+                chart.bars.info.ticker()
+            ),
+            Style::default().fg(pair_color(
+                chart.bars.info.exchange(), 
+                chart.bars.info.ticker()
+            ))
+        ),
+        Span::raw(format!(
+            " {}  H {:.2}  L {:.2}{}",
+            chart.bars.info.period(),
+            high,
+            low,
+            legend
+        )),
+    ]);
+
+    let dates: String = match (bars.first(), bars.last()) {
+        (Some(first), Some(last)) => format!(
+            "{} → {}{}",
+            first.open_date().format("%Y-%m-%d %H:%M"),
+            last.open_date().format("%Y-%m-%d %H:%M"),
+            match offset {
+                0 => String::new(),
+                n => format!("  ({n} bars back)")
+            }
+        ),
+        _ => String::new()
+    };
+
+    let panes = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(match show_volume {
+            true => [Constraint::Percentage(75), Constraint::Percentage(25)],
+            false => [Constraint::Percentage(100), Constraint::Length(0)]
+        })
+        .split(area);
+
+    let (price_block, volume_block) = match show_volume {
+        true => (
+            Block::default().title(title).borders(Borders::ALL),
+            Block::default().title_bottom(dates).borders(Borders::ALL)
+        ),
+        false => (
+            Block::default()
+                .title(title)
+                .title_bottom(dates)
+                .borders(Borders::ALL),
+            Block::default()
+        )
+    };
+
+    let x_bounds: [f64; 2] = [0.0, num_bars.max(1) as f64];
+
+    let candles = Canvas::default()
+        .block(price_block)
+        .marker(Marker::Braille)
+        .x_bounds(x_bounds)
+        .y_bounds([low, high.max(low + f64::EPSILON)])
+        .paint(|ctx: &mut Context| {
+
+            for (i, bar) in values.iter().enumerate() {
+
+                let x: f64 = i as f64;
+                let color: Color = candle_color(bar);
+                let body_top: f64 = bar.open.max(bar.close);
+                let body_btm: f64 = bar.open.min(bar.close);
+
+                ctx.draw(&CanvasLine::new(
+                    x + 0.5, bar.low, x + 0.5, bar.high, color
+                ));
+                for body_x in [x + 0.2, x + 0.8] {
+                    ctx.draw(&CanvasLine::new(
+                        body_x, body_btm, body_x, body_top, color
+                    ));
+                };
+            };
+
+            // Drawn after the candles so they stay visible over the bodies
+            ctx.layer();
+            for (color, values) in &lines {
+                for (i, pair) in values.windows(2).enumerate() {
+                    if let [Some(from), Some(to)] = pair {
+                        let x: f64 = i as f64 + 0.5;
+                        ctx.draw(&CanvasLine::new(
+                            x, *from, x + 1.0, *to, *color
+                        ));
+                    };
+                };
+            };
+        });
+
+    frame.render_widget(candles, panes[0]);
+
+    if !show_volume {
+        return
+    };
+
+    let max_volume: f64 = values
+        .iter()
+        .map(|v| v.volume)
+        .fold(0.0, f64::max);
+
+    let volume = Canvas::default()
+        .block(volume_block.title(format!("Volume  max {:.2}", max_volume)))
+        .marker(Marker::Braille)
+        .x_bounds(x_bounds)
+        .y_bounds([0.0, max_volume.max(f64::EPSILON)])
+        .paint(|ctx: &mut Context| {
+            for (i, bar) in values.iter().enumerate() {
+                let x: f64 = i as f64;
+                for bar_x in [x + 0.2, x + 0.8] {
+                    ctx.draw(&CanvasLine::new(
+                        bar_x, 0.0, bar_x, bar.volume, candle_color(bar)
+                    ));
+                };
+            };
+        });
+
+    frame.render_widget(volume, panes[1]);
+}
+
+fn candle_color(bar: &CandleValues) -> Color {
+    match bar.is_up() {
+        true => Color::Green,
+        false => Color::Red
+    }
+}
@@ -0,0 +1,67 @@
+use ratatui::{
+    Frame,
+    crossterm::event::{KeyCode, KeyEvent},
+    layout::Rect,
+    style::{Color, Style},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+};
+
+
+/// A yes/no popup guarding a destructive action
+///
+/// Holds the action until it's answered. Screens show it on top of
+/// themselves and send it every key while it's open, so nothing runs until
+/// the user presses 'y'. Anything other than 'y' or 'n' is ignored, and Esc
+/// counts as no.
+pub struct Confirm<A> {
+    pub action: A,
+    title: String,
+    message: String,
+}
+
+impl<A> Confirm<A> {
+
+    pub fn new(title: &str, message: String, action: A) -> Self {
+        Confirm { action, title: title.to_string(), message }
+    }
+
+    /// `Some(true)` for yes, `Some(false)` for no, and `None` for keys that
+    /// don't answer
+    pub fn answer(&self, key: &KeyEvent) -> Option<bool> {
+        match key.code {
+            KeyCode::Char('y' | 'Y') => Some(true),
+            KeyCode::Char('n' | 'N') | KeyCode::Esc => Some(false),
+            _ => None
+        }
+    }
+
+    /// Draws the popup centred in `area`
+    pub fn draw(&self, frame: &mut Frame, area: Rect) {
+
+        const WIDTH: u16 = 60;
+        const HEIGHT: u16 = 7;
+
+        let width: u16 = WIDTH.min(area.width);
+        let height: u16 = HEIGHT.min(area.height);
+        let popup = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height
+        );
+
+        let text: String = format!("{}\n\n[y] Yes   [N] No", self.message);
+
+        let dialog = Paragraph::new(text)
+            .block(
+                Block::default()
+                    .title(self.title.as_str())
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Red))
+            )
+            .wrap(Wrap { trim: true });
+
+        frame.render_widget(Clear, popup);
+        frame.render_widget(dialog, popup);
+    }
+}
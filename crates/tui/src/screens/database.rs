@@ -1,10 +1,13 @@
 // Local imports
 use std::{
+    cmp::min,
     collections::{
         BTreeMap,
+        BTreeSet,
         HashMap
     },
     sync::Arc,
+    time::{Duration, Instant},
 };
 
 // Third party imports
@@ -27,12 +30,18 @@ use ratatui::{
             KeyModifiers
         },
     },
+    text::{
+        Line,
+        Span,
+    },
     widgets::{
         Block,
         Borders,
+        Gauge,
         ListState,
         ListItem,
         List,
+        Paragraph,
     },
     layout::{
         Rect,
@@ -51,68 +60,320 @@ use ratatui::{
 use super::{
     AppEvent,
     OutputMsg,
+    confirm::Confirm,
+    search::ListSearch,
     move_up,
-    move_down
+    move_down,
+    pair_color,
 };
 use app_core::{
+    arg_parsing::Command,
+    audit::record_command,
     database_ops::{
         self,
+        audit::AuditOrigin,
         kraken::{
             AssetPairInfo,
         },
+        PairStats,
+        Ticker,
         fetch_exchanges_and_pairs_from_db,
+        fetch_price_snapshot,
+        jobs::{failed_pairs, job_summary},
+        CancellationToken,
         DataDownloadStatus, 
-        update_database_tables,
     },
+    catalog::refresh_catalog,
     engine::Engine,
+    job_registry::run_backfill_jobs,
+    pair_cache::PairCache,
 };
 use string_helpers::{
+    Locale,
     capitlize_first_letter,
     multi_line_to_single_line,
+    sparkline,
 };
+use timestamp_tools::db_timestamp_to_date_string;
+
 
+const INFO_STRINGS: [&str; 4] = [
+    r#"Downloads new tick data for the given pair to the database. Press 
+    Space to select several pairs, then Enter to add them all. Press '/' to
+    search the list."#,
 
-const INFO_STRINGS: [&'static str; 3] = [
-    r#"Downloads new tick data for the given pair to the database."#,
+    r#"Deletes data from the database. Press Space to select several pairs,
+    then Enter to delete them all."#,
 
-    r#"Deletes data from the database."#,
+    r#"Updates database tables, depending on the asset pair that's chosen.
+    Press Space to select several pairs, then Enter to update them in 
+    turn. Press 'x' to show each pair's download under its exchange. Each
+    pair lists its (estimated) tick count, first and last day and size, with
+    the last day in yellow once it's over a day old."#,
 
-    r#"Updates database tables, depending on the asset pair that's chosen."#
+    r#"Updates only the pairs whose last download ended in an error, as 
+    recorded in the job history. Choose 'All Failed' to retry every one of
+    them, or select some with Space."#
 ];
 
+/// Pairs whose last tick is older than this are shown as stale
+const STALE_AFTER_SECS: u64 = 24 * 3600;
 
-// ------------ DATABASE SCREEN -------------- //
-pub struct DatabaseUpdateMsgs {
-    pub msgs: BTreeMap<String, BTreeMap<String, OutputMsg>>,
+
+// ------------ DOWNLOAD PROGRESS -------------- //
+/// Where a pair's download is at, as counted in its exchange's summary
+#[derive(Clone, Copy, PartialEq)]
+enum DownloadState {
+    /// Downloading, or waiting to retry
+    Running,
+    Done,
+    Failed,
+    Stopped,
 }
 
-impl DatabaseUpdateMsgs {
-    fn new() -> Self {
-        DatabaseUpdateMsgs { msgs: BTreeMap::new() } 
+/// One pair's download as last reported, drawn as a gauge
+pub struct DownloadProgress {
+    ticker: String,
+    percent: u8,
+    ticks: u64,
+    started: Instant,
+    state: DownloadState,
+    /// Set once the download has finished, failed or is waiting, in place
+    /// of its speed and ETA
+    note: Option<(String, Color)>,
+}
+
+impl DownloadProgress {
+
+    fn new(ticker: &str) -> Self {
+        DownloadProgress {
+            ticker: ticker.to_string(),
+            percent: 0,
+            ticks: 0,
+            started: Instant::now(),
+            state: DownloadState::Running,
+            note: None,
+        }
+    }
+
+    fn update(&mut self, status: DataDownloadStatus) {
+        match status {
+            DataDownloadStatus::Started { .. } => {
+                *self = DownloadProgress::new(&self.ticker);
+            },
+            DataDownloadStatus::Progress { percent, ticks, .. } => {
+                self.percent = percent;
+                self.ticks = ticks;
+                self.note = None;
+            },
+            DataDownloadStatus::Finished { .. } => {
+                self.percent = 100;
+                self.state = DownloadState::Done;
+                self.note = Some(("Finished".to_string(), Color::Green));
+            },
+            DataDownloadStatus::Error { .. } => {
+                self.state = DownloadState::Failed;
+                self.note = Some(("ERROR".to_string(), Color::Red));
+            },
+            DataDownloadStatus::Cancelled { .. } => {
+                self.state = DownloadState::Stopped;
+                self.note = Some(("Stopped".to_string(), Color::Magenta));
+            },
+            DataDownloadStatus::Stalled { idle_secs, retrying, .. } => {
+                self.note = Some((
+                    format!(
+                        "STALLED, no progress for {idle_secs}s{}",
+                        match retrying {
+                            true => ", retrying",
+                            false => ""
+                        }
+                    ),
+                    Color::Red
+                ));
+            },
+            DataDownloadStatus::Retrying { reason, delay_secs, .. } => {
+                self.note = Some((
+                    format!("{reason}, retrying in {delay_secs}s"),
+                    Color::Magenta
+                ));
+            }
+        };
+    }
+
+    fn rows_per_sec(&self) -> f64 {
+        self.ticks as f64 / self.started.elapsed().as_secs_f64().max(1.0)
+    }
+
+    /// Time left at the average pace so far, once there's a pace to go by
+    fn eta(&self) -> Option<Duration> {
+        match self.percent {
+            0 | 100.. => None,
+            p => Some(self.started.elapsed().mul_f64(
+                (100 - p) as f64 / p as f64
+            ))
+        }
+    }
+
+    fn label(&self, locale: &Locale) -> String {
+
+        let status: String = match &self.note {
+            Some((note, _)) => note.clone(),
+            None => format!(
+                "{} rows/s  ETA {}",
+                locale.format_number(format!("{:.0}", self.rows_per_sec())),
+                match self.eta() {
+                    Some(eta) => format_eta(eta),
+                    None => "-".to_string()
+                }
+            )
+        };
+
+        format!("{}  {}%  {}", self.ticker, self.percent, status)
+    }
+
+    fn color(&self) -> Color {
+        match &self.note {
+            Some((_, color)) => *color,
+            None => Color::Yellow
+        }
     }
 }
 
+/// One exchange's downloads, counted by state
+struct ExchangeSummary<'a> {
+    exchange: &'a str,
+    downloads: Vec<&'a DownloadProgress>,
+}
+
+impl ExchangeSummary<'_> {
+
+    fn count(&self, state: DownloadState) -> usize {
+        self.downloads.iter().filter(|d| d.state == state).count()
+    }
+
+    fn percent(&self) -> u16 {
+        let total: u32 = self.downloads.iter().map(|d| d.percent as u32).sum();
+        (total / self.downloads.len().max(1) as u32).min(100) as u16
+    }
+
+    /// e.g. "kraken: 3 downloading, 5 done, 1 failed"
+    fn label(&self) -> String {
+
+        let counts: Vec<String> = [
+            (DownloadState::Running, "downloading"),
+            (DownloadState::Done, "done"),
+            (DownloadState::Failed, "failed"),
+            (DownloadState::Stopped, "stopped"),
+        ]
+            .iter()
+            .map(|(state, name)| (self.count(*state), name))
+            .filter(|(n, _)| *n > 0)
+            .map(|(n, name)| format!("{n} {name}"))
+            .collect();
+
+        format!("{}: {}", self.exchange, counts.join(", "))
+    }
+
+    fn color(&self) -> Color {
+        match (
+            self.count(DownloadState::Failed), 
+            self.count(DownloadState::Running)
+        ) {
+            (0, 0) => Color::Green,
+            (0, _) => Color::Yellow,
+            _ => Color::Red
+        }
+    }
+}
+
+/// e.g. "1h 05m", "3m 12s" or "40s"
+fn format_eta(eta: Duration) -> String {
+    let secs: u64 = eta.as_secs();
+    match (secs / 3600, secs % 3600 / 60, secs % 60) {
+        (0, 0, s) => format!("{s}s"),
+        (0, m, s) => format!("{m}m {s:02}s"),
+        (h, m, _) => format!("{h}h {m:02}m"),
+    }
+}
+
+/// The day of a tick time (microseconds), or "-" without one
+fn short_date(time: Option<u64>) -> String {
+    match time {
+        Some(t) => db_timestamp_to_date_string(t)
+            .chars()
+            .take(10)
+            .collect(),
+        None => "-".to_string()
+    }
+}
+
+/// e.g. "512 B", "3.4 MB" or "1.2 GB"
+fn format_size(bytes: u64, locale: &Locale) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    let mut size: f64 = bytes as f64;
+    let mut unit: &str = "B";
+    for u in UNITS {
+        if size < 1024.0 { break };
+        size /= 1024.0;
+        unit = u;
+    };
+    match unit {
+        "B" => format!("{bytes} B"),
+        _ => format!("{} {unit}", locale.format_number(format!("{size:.1}")))
+    }
+}
+
+
+// ------------ DATABASE SCREEN -------------- //
 pub struct DatabaseScreen {
     pub focus: DbFocus,
     pub top_state: ListState,
     pub btm_state: ListState,
     pub btm_item_data: Vec<String>,
+    /// Bottom list rows toggled with Space, run together on Enter
+    pub marked: BTreeSet<String>,
+    /// Filters the bottom list, opened with '/'
+    pub search: ListSearch,
     pub selected_action: Option<DbAction>,
+    /// Copied from `pair_cache` before each frame
     pub token_pairs: HashMap<String, Vec<String>>,
+    pair_cache: PairCache,
+    /// Pairs whose last download failed, refreshed while 'Retry failed' is
+    /// open
+    failed_pairs: Vec<(String, String)>,
     pub asset_pairs: Arc<BTreeMap<String, BTreeMap<String, AssetPairInfo>>>,
     pub db_pool: PgPool,
     pub transmitter: UnboundedSender<AppEvent>,
     pub is_busy: bool,
     pub task_handle: Option<JoinHandle<()>>,
-    pub db_update_msgs: DatabaseUpdateMsgs, 
+    /// Stops the running update cleanly, see `cancel_task`
+    cancel_token: Option<CancellationToken>,
+    /// Open while a destructive action waits for a yes or no
+    pub confirm: Option<Confirm<PendingAction>>,
+    /// Progress of the running or last update, by exchange and ticker
+    pub downloads: BTreeMap<(String, String), DownloadProgress>,
+    /// Shows a gauge per pair under each exchange's summary, toggled with 
+    /// 'x'
+    download_detail: bool,
+    pub sparklines: HashMap<String, String>,
+    /// Tick count, dates and size of each stored pair, keyed like 
+    /// `sparklines`
+    pair_stats: HashMap<String, PairStats>,
+    sparklines_updated: Option<Instant>,
+    locale: Locale,
+    /// Disables deleting pairs, see `Engine::guest_mode`
+    guest_mode: bool,
 }
 
 impl DatabaseScreen {
  
     pub fn new(
         db_pool: PgPool, 
+        pair_cache: PairCache,
         transmitter: UnboundedSender<AppEvent>,
         asset_pairs: Arc<BTreeMap<String, BTreeMap<String, AssetPairInfo>>>, 
+        locale: Locale,
+        guest_mode: bool,
     ) -> Self {
     
         let mut top_state = ListState::default();
@@ -125,36 +386,150 @@ impl DatabaseScreen {
             top_state,
             btm_state: ListState::default(),
             btm_item_data: Vec::new(),
+            marked: BTreeSet::new(),
+            search: ListSearch::default(),
             selected_action: None,
             token_pairs: HashMap::new(),
+            pair_cache,
+            failed_pairs: Vec::new(),
             asset_pairs,
             db_pool,
             transmitter,
             is_busy,
             task_handle,
-            db_update_msgs: DatabaseUpdateMsgs::new(),
+            cancel_token: None,
+            confirm: None,
+            downloads: BTreeMap::new(),
+            download_detail: false,
+            sparklines: HashMap::new(),
+            pair_stats: HashMap::new(),
+            sparklines_updated: None,
+            locale,
+            guest_mode,
         }
 
     }
 
     pub async fn pre_draw(&mut self) {
-        let pool = self.db_pool.clone();
-        self.token_pairs = fetch_exchanges_and_pairs_from_db(pool).await;
+
+        // Reloaded in the background, so frames never wait on the database
+        self.pair_cache.refresh_if_stale(self.db_pool.clone());
+        let pairs: HashMap<String, Vec<String>> = self.pair_cache.pairs();
+        if pairs != self.token_pairs {
+            self.sparklines_updated = None;
+        };
+        self.token_pairs = pairs;
+
+        if let Some(DbAction::RetryFailed) = self.selected_action {
+            self.failed_pairs = failed_pairs(self.db_pool.clone())
+                .await
+                .unwrap_or_default();
+        };
+        self.refresh_sparklines().await;
+    }
+
+    /// Rebuilds the cached 24h sparklines (with the latest hourly close) and
+    /// pair stats, at most once per minute
+    async fn refresh_sparklines(&mut self) {
+
+        const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+        if let Some(t) = self.sparklines_updated 
+            && t.elapsed() < REFRESH_INTERVAL 
+        {
+            return
+        };
+
+        let mut sparklines: HashMap<String, String> = HashMap::new();
+
+        for (exchange, tickers) in &self.token_pairs {
+            for ticker in tickers {
+                if let Ok(prices) = fetch_price_snapshot(
+                    exchange, 
+                    ticker, 
+                    self.db_pool.clone()
+                ).await 
+                    && let Some(last) = prices.last()
+                {
+                    sparklines.insert(
+                        format!("{exchange} - {ticker}"), 
+                        format!(
+                            "{}  {}",
+                            sparkline(&prices),
+                            self.locale.format_number(format!("{:.2}", last))
+                        )
+                    );
+                };
+            };
+        };
+
+        self.sparklines = sparklines;
+        self.pair_stats = fetch_exchanges_and_pairs_from_db(
+            self.db_pool.clone(),
+            true
+        )
+            .await
+            .into_iter()
+            .flat_map(|(exchange, tickers)| tickers
+                .into_iter()
+                .filter_map(move |(ticker, stats)| Some((
+                    format!("{exchange} - {ticker}"), 
+                    stats?
+                )))
+            )
+            .collect();
+        self.sparklines_updated = Some(Instant::now());
+    }
+
+    /// Reloads the pairs, their stats and sparklines on the next frame
+    fn refresh(&mut self) {
+        self.pair_cache.invalidate();
+        self.sparklines_updated = None;
+    }
+
+    /// Records a download status from the running update
+    pub fn update_download(&mut self, status: DataDownloadStatus) {
+
+        let (exchange, ticker) = status.exchange_and_ticker();
+
+        self.downloads
+            .entry((exchange.to_string(), ticker.to_string()))
+            .or_insert_with(|| DownloadProgress::new(ticker))
+            .update(status);
     }
 
     pub fn draw(&mut self, frame: &mut Frame, area: Rect) {
 
+        // A gauge per exchange, one per pair when detailed, plus the borders
+        let progress_height: u16 = match self.downloads.is_empty() {
+            true => 0,
+            false => min(
+                self.download_summaries().len() as u16 + 2 + match 
+                    self.download_detail 
+                {
+                    true => self.downloads.len() as u16,
+                    false => 0
+                },
+                area.height / 2
+            )
+        };
+
         let nested_chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
                 Constraint::Percentage(30),
-                Constraint::Percentage(70),
+                Constraint::Min(3),
+                Constraint::Length(progress_height),
             ])
             .split(area);
 
         let top_items: Vec<ListItem> = Self::SCREEN_OPTIONS
             .iter()
-            .map(|v| ListItem::new(v.name()))
+            .map(|v| match self.guest_mode && v.is_destructive() {
+                true => ListItem::new(format!("{} (guest mode)", v.name()))
+                    .style(Style::default().fg(Color::DarkGray)),
+                false => ListItem::new(v.name())
+            })
             .collect();
 
         let top_list = List::new(top_items)
@@ -177,7 +552,7 @@ impl DatabaseScreen {
             &mut self.top_state
         );
 
-        self.btm_item_data = match self.selected_action {
+        let items: Vec<String> = match self.selected_action {
             Some(DbAction::RemovePairs | DbAction::UpdateData) => {
                 let mut items = Vec::from(["All Tables".to_string()]);
                 for (key, vals) in &self.token_pairs {
@@ -187,6 +562,13 @@ impl DatabaseScreen {
                 };
                 items
             },
+            Some(DbAction::RetryFailed) => {
+                let mut items = Vec::from(["All Failed".to_string()]);
+                for (exchange, ticker) in &self.failed_pairs {
+                    items.push(format!("{exchange} - {ticker}"))
+                };
+                items
+            },
             Some(DbAction::AddPairs) => {
                 let mut items = Vec::new();
                 for (key, pairs) in self.asset_pairs.iter() {
@@ -207,18 +589,84 @@ impl DatabaseScreen {
                 else { Vec::new() }
             },
         };
+        self.btm_item_data = self.search.apply(
+            items, 
+            &self.btm_item_data, 
+            &mut self.btm_state
+        );
+
+        let name_width: usize = self.btm_item_data
+            .iter()
+            .map(|v| v.len())
+            .max()
+            .unwrap_or(0);
 
         let btm_items: Vec<ListItem> = self.btm_item_data.iter()
-            .map(|v| ListItem::new(v.clone()))
+            .map(|v| {
+                let Some((exchange, ticker)) = v.split_once(" - ") else {
+                    return ListItem::new(v.clone())
+                };
+                let mut spans: Vec<Span> = Vec::new();
+                if let Some(stats) = self.pair_stats.get(v) {
+                    spans.push(Span::raw(format!(
+                        "{:<width$}  {:>14} ticks  {} to ", 
+                        v, 
+                        format!(
+                            "~{}", 
+                            self.locale.format_count(stats.approx_ticks)
+                        ), 
+                        short_date(stats.first_time), 
+                        width = name_width
+                    )));
+                    spans.push(Span::styled(
+                        short_date(stats.last_time),
+                        match stats.is_stale(STALE_AFTER_SECS) {
+                            true => Style::default().fg(Color::Yellow),
+                            false => Style::default()
+                        }
+                    ));
+                    spans.push(Span::raw(format!(
+                        "  {:>9}  ", 
+                        format_size(stats.size_bytes, &self.locale)
+                    )));
+                };
+                if let Some(spark) = self.sparklines.get(v) {
+                    if spans.is_empty() {
+                        spans.push(Span::raw(format!(
+                            "{:<width$}  ", v, width = name_width
+                        )));
+                    };
+                    spans.push(Span::styled(
+                        spark.clone(),
+                        Style::default().fg(pair_color(exchange, ticker))
+                    ));
+                };
+                let item = match spans.is_empty() {
+                    true => ListItem::new(v.clone()),
+                    false => ListItem::new(Line::from(spans))
+                };
+                match self.marked.contains(v) {
+                    true => item.style(Style::default().fg(Color::Cyan)),
+                    false => item
+                }
+            })
             .collect();
 
+        let mut btm_title: String = match &self.selected_action {
+            Some(t) => self.search.title(t.name()),
+            None => String::new()
+        };
+        if !self.marked.is_empty() {
+            btm_title.push_str(&format!(" ({} selected)", self.marked.len()));
+        };
+        if self.task_handle.as_ref().is_some_and(|h| !h.is_finished()) {
+            btm_title.push_str(" (running, Ctrl+C to cancel)");
+        };
+
         let btm_list = List::new(btm_items)
             .block(
                 Block::default()
-                    .title(match self.selected_action.clone() {
-                        Some(t) => t.name(),
-                        None => ""
-                    })
+                    .title(btm_title)
                     .borders(Borders::ALL)
             )
             .highlight_style(
@@ -235,29 +683,163 @@ impl DatabaseScreen {
             &mut self.btm_state
         );
 
+        if !self.downloads.is_empty() {
+            self.draw_downloads(frame, nested_chunks[2]);
+        };
+
+        if let Some(confirm) = &self.confirm {
+            confirm.draw(frame, area);
+        };
+
+    }
+
+    /// The downloads grouped by exchange, in exchange order
+    fn download_summaries(&self) -> Vec<ExchangeSummary<'_>> {
+
+        let mut summaries: Vec<ExchangeSummary> = Vec::new();
+
+        for ((exchange, _), download) in self.downloads.iter() {
+            match summaries.last_mut() {
+                Some(s) if s.exchange == exchange => s.downloads.push(download),
+                _ => summaries.push(ExchangeSummary { 
+                    exchange, 
+                    downloads: vec![download] 
+                })
+            };
+        };
+
+        summaries
+    }
+
+    fn draw_downloads(&self, frame: &mut Frame, area: Rect) {
+
+        let title: &str = match self.download_detail {
+            true => "Downloads  (x: summary)",
+            false => "Downloads  (x: detail)"
+        };
+
+        let block = Block::default()
+            .title(title)
+            .borders(Borders::ALL);
+        let inner: Rect = block.inner(area);
+        frame.render_widget(block, area);
+
+        let summaries: Vec<ExchangeSummary> = self.download_summaries();
+        let row_count: usize = summaries.len() + match self.download_detail {
+            true => self.downloads.len(),
+            false => 0
+        };
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![Constraint::Length(1); row_count])
+            .split(inner);
+        let mut rows = rows.iter();
+
+        for summary in summaries.iter() {
+
+            let Some(row) = rows.next() else { return };
+
+            let gauge = Gauge::default()
+                .gauge_style(
+                    Style::default()
+                        .fg(summary.color())
+                        .bg(Color::DarkGray)
+                        .add_modifier(Modifier::BOLD)
+                )
+                .percent(summary.percent())
+                .label(summary.label())
+                .use_unicode(true);
+
+            frame.render_widget(gauge, *row);
+
+            if !self.download_detail { continue };
+
+            for download in summary.downloads.iter() {
+
+                let Some(row) = rows.next() else { return };
+
+                // A strip in the pair's color, matching its lines in the 
+                // output
+                let [marker, bar] = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Length(2), Constraint::Min(0)])
+                    .areas(*row);
+
+                frame.render_widget(
+                    Paragraph::new("▌").style(Style::default().fg(
+                        pair_color(summary.exchange, &download.ticker)
+                    )),
+                    marker
+                );
+
+                let gauge = Gauge::default()
+                    .gauge_style(
+                        Style::default()
+                            .fg(download.color())
+                            .bg(Color::DarkGray)
+                    )
+                    .percent(download.percent.min(100) as u16)
+                    .label(download.label(&self.locale))
+                    .use_unicode(true);
+
+                frame.render_widget(gauge, bar);
+            };
+        };
     }
 
     pub async fn handle_btm_action(&mut self, engine: &Engine) {
  
-        let ACTION = match &self.selected_action {
+        let action = match &self.selected_action {
             Some(a) => a.clone(),
-            None => Self::SCREEN_OPTIONS[3].clone()
+            None => DbAction::None
+        };
+
+        if self.guest_mode && action.is_destructive() {
+            return
         };
 
         if let Some(i) = self.btm_state.selected() {
 
-            // Update option
-            if let DbAction::UpdateData = ACTION { 
+            // Update option, or the same run over the failed pairs
+            if let DbAction::UpdateData | DbAction::RetryFailed = action { 
+
+                // "All Tables" isn't a pair, so it leaves this empty, and
+                // "All Failed" stands for every failed pair
+                let pairs: Vec<(String, String)> = match (
+                    &action, 
+                    self.target_pairs(i)
+                ) {
+                    (DbAction::RetryFailed, p) if p.is_empty() => {
+                        self.failed_pairs.clone()
+                    },
+                    (_, p) => p
+                };
+                self.marked.clear();
+
+                if let DbAction::RetryFailed = action && pairs.is_empty() {
+                    let _ = self.transmitter.send(AppEvent::Output(
+                        OutputMsg::new(
+                            "No failed downloads to retry".to_string(),
+                            Color::Green,
+                            false,
+                            None,
+                            None,
+                            None
+                        )
+                    ));
+                    return
+                };
                
                 let (prog_tx, mut prog_rx) = 
                     unbounded_channel::<DataDownloadStatus>();
 
                 let ui_tx = self.transmitter.clone();
+                self.downloads.clear();
 
                 tokio::spawn(async move {
                     while let Some(stat) = prog_rx.recv().await {
-                        let msg: OutputMsg = stat.into();
-                        let _ = ui_tx.send(AppEvent::Output(msg)); 
+                        let _ = ui_tx.send(AppEvent::Download(stat)); 
                     }
                 });
         
@@ -268,47 +850,112 @@ impl DatabaseScreen {
                 let active_exchanges = engine.state
                     .get_active_exchanges();
 
-                let pair = if self.btm_item_data[i] != "All Tables" {
-                    
-                    let tokens: Vec<&str> = self.btm_item_data[i]
-                        .split(" - ")
-                        .collect();
+                let token = CancellationToken::new();
+                self.cancel_token = Some(token.clone());
 
-                    (
-                        Some(tokens[0].to_lowercase()),
-                        Some(tokens[1].to_uppercase())
-                    )
-                
-                }
-                else {
-                    (None, None)
-                };
+                let control = engine.state
+                    .config
+                    .data_download
+                    .download_control(token.clone());
 
-                let (exchange, ticker) = pair;
+                let tx = self.transmitter.clone();
+                let registry = engine.jobs.clone();
+
+                // Recorded in the audit log like the commands it stands for
+                let cmd: Command = match action {
+                    DbAction::RetryFailed => Command::RetryFailed,
+                    _ => Command::UpdatePairs
+                };
 
                 self.task_handle = Some(tokio::spawn(async move {
-                    update_database_tables(
-                        &active_exchanges,
+
+                    // "All Tables" is every stored pair of an active 
+                    // exchange
+                    let pairs: Vec<(String, String)> = match pairs.is_empty() {
+                        true => fetch_exchanges_and_pairs_from_db(
+                            db_pool.clone(),
+                            false
+                        )
+                            .await
+                            .into_iter()
+                            .filter(|(exchange, _)| active_exchanges
+                                .iter()
+                                .any(|a| a.eq_ignore_ascii_case(exchange))
+                            )
+                            .flat_map(|(exchange, tickers)| {
+                                let exchange = exchange.to_lowercase();
+                                tickers
+                                    .into_iter()
+                                    .map(move |(t, _)| (exchange.clone(), t))
+                            })
+                            .collect(),
+                        false => pairs
+                    };
+
+                    // Each pair is a job of its own on the jobs screen, all
+                    // reporting to the same gauges
+                    let updated = run_backfill_jobs(
+                        registry,
+                        pairs,
                         time_offset, 
-                        &client, 
-                        db_pool, 
+                        client, 
+                        db_pool.clone(), 
                         prog_tx, 
-                        exchange.as_deref(), 
-                        ticker.as_deref()
+                        control.clone()
+                    ).await;
+                    let _ = record_command(
+                        &cmd, 
+                        &AuditOrigin::Tui, 
+                        &updated, 
+                        db_pool.clone()
                     ).await;
+                    let _ = refresh_catalog(db_pool.clone()).await;
+
+                    if let Ok(summary) = job_summary(
+                        control.job_id, 
+                        db_pool.clone()
+                    ).await {
+                        let _ = tx.send(AppEvent::UpdateSummary(summary));
+                    };
+
+                    if let Ok(failed) = failed_pairs(db_pool).await
+                        && !failed.is_empty()
+                        && !token.is_cancelled()
+                    {
+                        let _ = tx.send(AppEvent::Output(OutputMsg::new(
+                            format!(
+                                "{} pair(s) failed to update. Choose 'Retry \
+                                failed updates' to run only those",
+                                failed.len()
+                            ),
+                            Color::Yellow,
+                            true,
+                            None,
+                            None,
+                            None
+                        )));
+                    };
+
+                    if token.is_cancelled() {
+                        let _ = tx.send(AppEvent::Output(OutputMsg::new(
+                            "Update cancelled. Run it again to resume"
+                                .to_string(),
+                            Color::Magenta,
+                            true,
+                            None,
+                            None,
+                            None
+                        )));
+                    };
                 }));
             }
 
-            else if let DbAction::AddPairs = ACTION {
+            else if let DbAction::AddPairs = action {
 
-                if self.btm_item_data.len() > 0 { 
+                let pairs: Vec<(String, String)> = self.target_pairs(i);
+                self.marked.clear();
 
-                    let tokens: Vec<&str> = self.btm_item_data[i]
-                        .split(" - ")
-                        .collect();
-
-                    let exchange: String = tokens[0].to_lowercase();
-                    let ticker: String = tokens[1].to_uppercase();
+                if !pairs.is_empty() { 
 
                     let tx = self.transmitter.clone();
 
@@ -318,76 +965,200 @@ impl DatabaseScreen {
                     let asset_pairs = self.asset_pairs.clone();
 
                     self.task_handle = Some(tokio::spawn(async move {
-                        
-                        tx.send(AppEvent::Output(OutputMsg::new(
-                            format!("Downloading seed data..."),
-                            Color::Yellow,
-                            false,
-                            None,
-                            None,
-                            None
-                        )));
 
-                        database_ops::add_new_pair(
-                            &exchange, 
-                            &ticker, 
-                            time_offset, 
-                            db_pool, 
-                            &client,
-                            Some(&*asset_pairs)
-                        ).await;
+                        for (exchange, ticker) in pairs {
                         
-                        tx.send(AppEvent::Output(OutputMsg::new(
-                            format!("Added {} {}", exchange, ticker),
-                            Color::Green,
-                            true,
-                            None,
-                            None,
-                            None
-                        )));
+                            let _ = tx.send(AppEvent::Output(OutputMsg::new(
+                                format!(
+                                    "Downloading seed data for {}...", 
+                                    ticker
+                                ),
+                                Color::Yellow,
+                                false,
+                                None,
+                                Some(exchange.clone()),
+                                Some(ticker.clone())
+                            )));
+
+                            let added = database_ops::add_new_pair(
+                                &exchange, 
+                                &ticker, 
+                                time_offset, 
+                                db_pool.clone(), 
+                                &client,
+                                Some(&*asset_pairs)
+                            ).await;
+
+                            if let Ok(ticker) = ticker.parse::<Ticker>() {
+                                let _ = record_command(
+                                    &Command::AddPair { 
+                                        exchange: exchange.clone(), 
+                                        ticker 
+                                    },
+                                    &AuditOrigin::Tui,
+                                    &added,
+                                    db_pool.clone()
+                                ).await;
+                            };
+                        
+                            let _ = tx.send(AppEvent::Output(OutputMsg::new(
+                                format!("Added {} {}", exchange, ticker),
+                                Color::Green,
+                                true,
+                                None,
+                                Some(exchange.clone()),
+                                Some(ticker.clone())
+                            )));
+                        };
+                        let _ = refresh_catalog(db_pool).await;
                     }));
                 };
             }
 
-            else if let DbAction::RemovePairs = ACTION {
+            else if let DbAction::RemovePairs = action {
+
+                let pairs: Vec<(String, String)> = self.target_pairs(i);
+
+                let message: String = match &pairs[..] {
+                    [] => return,
+                    [(exchange, ticker)] => format!(
+                        "Delete {} {}? This drops its table and every tick \
+                        stored for it.",
+                        exchange,
+                        ticker
+                    ),
+                    _ => format!(
+                        "Delete {} pairs? This drops their tables and every \
+                        tick stored for them.",
+                        pairs.len()
+                    )
+                };
 
-                if self.btm_item_data.len() > 0 { 
+                self.confirm = Some(Confirm::new(
+                    "Delete pairs",
+                    message,
+                    PendingAction::RemovePairs { pairs },
+                ));
+            };
+        }
+    }
 
-                    let tokens: Vec<&str> = self.btm_item_data[i]
-                        .split(" - ")
-                        .collect();
+    /// The marked pairs as (exchange, ticker), or the highlighted row's pair
+    /// when none are marked
+    fn target_pairs(&self, i: usize) -> Vec<(String, String)> {
 
-                    let exchange: String = tokens[0].to_lowercase();
-                    let ticker: String = tokens[1].to_uppercase();
-                    let tx = self.transmitter.clone();
-                    let db_pool = engine.database.get_pool();
+        let items: Vec<&String> = match self.marked.is_empty() {
+            true => self.btm_item_data.get(i).into_iter().collect(),
+            false => self.marked.iter().collect()
+        };
 
-                    self.task_handle = Some(tokio::spawn(async move {
+        items
+            .into_iter()
+            .filter_map(|item| item.split_once(" - "))
+            .map(|(e, t)| (e.to_lowercase(), t.to_uppercase()))
+            .collect()
+    }
+
+    /// Runs an action the user has confirmed
+    fn run_confirmed(&mut self, action: PendingAction, engine: &Engine) {
+
+        self.marked.clear();
+
+        match action {
+
+            PendingAction::RemovePairs { pairs } => {
+
+                let tx = self.transmitter.clone();
+                let db_pool = engine.database.get_pool();
 
-                        database_ops::drop_pair(
+                self.task_handle = Some(tokio::spawn(async move {
+
+                    for (exchange, ticker) in pairs {
+
+                        let dropped = database_ops::drop_pair(
                             &exchange, 
                             &ticker, 
-                            db_pool, 
+                            db_pool.clone(), 
                         ).await;
-                        
-                        tx.send(AppEvent::Output(OutputMsg::new(
+
+                        if let Ok(ticker) = ticker.parse::<Ticker>() {
+                            let _ = record_command(
+                                &Command::DropPair { 
+                                    exchange: exchange.clone(), 
+                                    ticker 
+                                },
+                                &AuditOrigin::Tui,
+                                &dropped,
+                                db_pool.clone()
+                            ).await;
+                        };
+                    
+                        let _ = tx.send(AppEvent::Output(OutputMsg::new(
                             format!("Deleted {} {}", exchange, ticker),
                             Color::Magenta,
                             true,
                             None,
-                            None,
-                            None
+                            Some(exchange.clone()),
+                            Some(ticker.clone())
                         )));
-                    }));
-                };
-            };
-        }
+                    };
+                    let _ = refresh_catalog(db_pool).await;
+                }));
+            }
+        };
     }
 
     pub async fn handle_key(&mut self, key: KeyEvent, engine: &Engine) {
 
         self.check_and_modify_task_state();
-        if self.is_busy { return };
+        if self.is_busy { 
+            match (key.code, key.modifiers) {
+                (KeyCode::Char('c'), mods) 
+                    if mods.contains(KeyModifiers::CONTROL) => {
+                        self.cancel_task();
+                    },
+                (KeyCode::Char('x'), _) => {
+                    self.download_detail = !self.download_detail;
+                },
+                _ => {}
+            };
+            return 
+        };
+
+        if let Some(confirm) = &self.confirm {
+            match confirm.answer(&key) {
+                Some(true) => {
+                    if let Some(c) = self.confirm.take() {
+                        self.run_confirmed(c.action, engine);
+                    };
+                },
+                Some(false) => {
+                    self.confirm = None;
+                    let _ = self.transmitter.send(AppEvent::Output(
+                        OutputMsg::new(
+                            "Cancelled, nothing was deleted".to_string(),
+                            Color::Yellow,
+                            false,
+                            None,
+                            None,
+                            None
+                        )
+                    ));
+                },
+                None => {}
+            };
+            return
+        };
+
+        if self.search.typing {
+            self.search.handle_key(&key);
+            return
+        };
+
+        if key.code == KeyCode::Char('x') && !self.downloads.is_empty() {
+            self.download_detail = !self.download_detail;
+            return
+        };
 
         let top_len = Self::SCREEN_OPTIONS.len();
         let btm_len = self.btm_item_data.len();
@@ -443,14 +1214,57 @@ impl DatabaseScreen {
                     }
             },
 
+            // --------------------------- SEARCH -------------------------- //
+            (KeyCode::Char('/'), _) => {
+                if let DbFocus::Bottom = self.focus {
+                    self.search.open();
+                };
+            },
+
+            // -------------------------- REFRESH -------------------------- //
+            (KeyCode::Char('r'), _) => self.refresh(),
+
+            // ------------------------- MULTI-SELECT ---------------------- //
+            (KeyCode::Char(' '), _) => {
+                if let DbFocus::Bottom = self.focus
+                    && let Some(item) = self.btm_state
+                        .selected()
+                        .and_then(|i| self.btm_item_data.get(i))
+                        .filter(|item| item.contains(" - "))
+                        .cloned()
+                    && !self.marked.remove(&item)
+                {
+                    self.marked.insert(item);
+                };
+            },
+
             // ------------------------- ENTER & ESC ----------------------- //
             (KeyCode::Enter, _) => match self.focus {
                 
                 DbFocus::Top => {
                     if let Some(i) = self.top_state.selected() {
-                        self.selected_action = Some(
-                            Self::SCREEN_OPTIONS[i].clone()
-                        );
+
+                        let action: DbAction = Self::SCREEN_OPTIONS[i].clone();
+
+                        if self.guest_mode && action.is_destructive() {
+                            let _ = self.transmitter.send(AppEvent::Output(
+                                OutputMsg::new(
+                                    format!(
+                                        "{} is disabled in guest mode", 
+                                        action.name()
+                                    ),
+                                    Color::Yellow,
+                                    true,
+                                    None,
+                                    None,
+                                    None
+                                )
+                            ));
+                            return
+                        };
+
+                        self.selected_action = Some(action);
+                        self.search.clear();
                     };
 
                     self.focus = DbFocus::Bottom;
@@ -467,6 +1281,8 @@ impl DatabaseScreen {
                 DbFocus::Bottom => {
                     self.focus = DbFocus::Top;
                     self.selected_action = None;
+                    self.marked.clear();
+                    self.search.clear();
                 }
                 
                 DbFocus::Top => {
@@ -478,6 +1294,56 @@ impl DatabaseScreen {
         }
     }
 
+    /// The pair highlighted in the bottom list, if one is
+    pub fn selected_pair(&self) -> Option<String> {
+        
+        if let DbFocus::Top = self.focus {
+            return None
+        };
+
+        match self.selected_action {
+            Some(DbAction::None) | None => None,
+            _ => self.btm_state
+                .selected()
+                .and_then(|i| self.btm_item_data.get(i))
+                .filter(|item| item.contains(" - "))
+                .cloned()
+        }
+    }
+
+    /// Stops the running task
+    ///
+    /// Updates are asked to stop, and each download finishes committing the
+    /// batch it's writing first, so the next update resumes where it left 
+    /// off. Other tasks, and updates that are asked a second time, are 
+    /// aborted outright, and any transaction they had open is rolled back.
+    fn cancel_task(&mut self) {
+
+        let text: &str = match self.cancel_token.take() {
+            Some(token) => {
+                token.cancel();
+                "Stopping the update after the current batches..."
+            },
+            None => match self.task_handle.take() {
+                Some(handle) => {
+                    handle.abort();
+                    self.is_busy = false;
+                    "Task cancelled"
+                },
+                None => return
+            }
+        };
+
+        let _ = self.transmitter.send(AppEvent::Output(OutputMsg::new(
+            text.to_string(),
+            Color::Magenta,
+            true,
+            None,
+            None,
+            None
+        )));
+    }
+
     /// Sets the 'is_busy' task state
     pub fn check_and_modify_task_state(&mut self) {
       
@@ -486,6 +1352,8 @@ impl DatabaseScreen {
             if handle.is_finished() { 
                 self.is_busy = false;
                 self.task_handle = None;
+                self.cancel_token = None;
+                self.refresh();
             }
             
             else {
@@ -496,10 +1364,11 @@ impl DatabaseScreen {
 
     pub const SCREEN_NAME: &'static str = "Database Management";
 
-    pub const SCREEN_OPTIONS: [DbAction; 4] = [
+    pub const SCREEN_OPTIONS: [DbAction; 5] = [
         DbAction::AddPairs, 
         DbAction::RemovePairs, 
         DbAction::UpdateData,
+        DbAction::RetryFailed,
         DbAction::None
     ];
 
@@ -510,11 +1379,20 @@ pub enum DbFocus {
     Bottom
 }
 
+/// A destructive action held by a `Confirm` popup until it's answered
+pub enum PendingAction {
+    /// (exchange, ticker) of each pair to drop
+    RemovePairs {
+        pairs: Vec<(String, String)>
+    },
+}
+
 #[derive(Clone)]
-enum DbAction {
+pub enum DbAction {
     AddPairs,
     RemovePairs,
     UpdateData,
+    RetryFailed,
     None
 }
 
@@ -524,9 +1402,15 @@ impl DbAction {
             DbAction::AddPairs => "Add new pairs",
             DbAction::RemovePairs => "Delete pairs",
             DbAction::UpdateData => "Update data",
+            DbAction::RetryFailed => "Retry failed updates",
             _ => ""
         }
     }
+
+    /// Whether the action deletes stored data, which guest mode forbids
+    fn is_destructive(&self) -> bool {
+        matches!(self, DbAction::RemovePairs)
+    }
 }
 
 
@@ -0,0 +1,367 @@
+use std::{
+    collections::BTreeMap,
+    time::Duration,
+};
+
+use ratatui::{
+    Frame,
+    crossterm::event::{KeyCode, KeyEvent},
+    layout::{Constraint, Rect},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Cell, Row, Table, TableState},
+};
+use reqwest::Client;
+use sqlx::PgPool;
+use tokio::{
+    sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel},
+    task::JoinHandle,
+    time::timeout,
+};
+
+use app_core::{
+    arg_parsing::Command,
+    audit::record_command,
+    database_ops::{
+        DatabaseIntegrity,
+        audit::AuditOrigin,
+        fetch_exchanges_and_pairs_from_db,
+        integrity_check,
+        repair_missing_ticks,
+    },
+};
+use super::{AppEvent, OutputMsg, pair_color};
+
+
+/// A finished check, or a repair that's been checked again
+struct Checked {
+    exchange: String,
+    ticker: String,
+    integrity: DatabaseIntegrity,
+}
+
+
+// --------------------------- INTEGRITY SCREEN ---------------------------- //
+/// Checks every pair's table for missing ticks in the background, one row
+/// per pair as the results come in
+///
+/// Enter checks every table again, and 'r' repairs the selected one by
+/// downloading its missing ticks. Repairs write to the database, so guest
+/// mode disables them.
+pub struct IntegrityScreen {
+    db_pool: PgPool,
+    request_client: Client,
+    transmitter: UnboundedSender<AppEvent>,
+    /// `None` until the pair's check comes back
+    results: BTreeMap<(String, String), Option<DatabaseIntegrity>>,
+    table_state: TableState,
+    /// Results from the running task, drained before each frame
+    receiver: Option<UnboundedReceiver<Checked>>,
+    task: Option<JoinHandle<()>>,
+    time_limit: Option<Duration>,
+    guest_mode: bool,
+}
+
+impl IntegrityScreen {
+
+    pub async fn new(
+        db_pool: PgPool,
+        request_client: Client,
+        transmitter: UnboundedSender<AppEvent>,
+        time_limit: Option<Duration>,
+        guest_mode: bool,
+    ) -> Self {
+
+        let mut screen = IntegrityScreen {
+            db_pool,
+            request_client,
+            transmitter,
+            results: BTreeMap::new(),
+            table_state: TableState::default(),
+            receiver: None,
+            task: None,
+            time_limit,
+            guest_mode,
+        };
+
+        screen.check_all().await;
+        screen
+    }
+
+    /// Whether a check or repair is still running
+    pub fn is_busy(&self) -> bool {
+        self.task.as_ref().is_some_and(|h| !h.is_finished())
+    }
+
+    /// Checks every pair's table, replacing the previous results
+    async fn check_all(&mut self) {
+
+        let pairs: Vec<(String, String)> = fetch_exchanges_and_pairs_from_db(
+            self.db_pool.clone(),
+            false
+        )
+            .await
+            .into_iter()
+            .flat_map(|(exchange, tickers)| tickers
+                .into_iter()
+                .map(move |(t, _)| (exchange.clone(), t))
+            )
+            .collect();
+
+        self.results = pairs.iter().cloned().map(|p| (p, None)).collect();
+        self.table_state.select(match self.results.is_empty() {
+            true => None,
+            false => Some(0)
+        });
+
+        let db_pool = self.db_pool.clone();
+
+        self.spawn(|tx| async move {
+            for (exchange, ticker) in pairs {
+                let integrity = integrity_check(
+                    &exchange,
+                    &ticker,
+                    db_pool.clone(),
+                    None
+                ).await;
+                let _ = tx.send(Checked { exchange, ticker, integrity });
+            };
+        });
+    }
+
+    /// Downloads the selected pair's missing ticks, then checks it again
+    fn repair_selected(&mut self) {
+
+        let Some(((exchange, ticker), Some(integrity))) = self.table_state
+            .selected()
+            .and_then(|i| self.results.iter().nth(i))
+            .map(|(k, v)| (k.clone(), v.clone()))
+        else {
+            return
+        };
+
+        if integrity.missing_ticks.is_empty() {
+            self.send_msg(
+                format!("{} {} has no missing ticks", exchange, ticker),
+                Color::Green
+            );
+            return
+        };
+
+        self.results.insert((exchange.clone(), ticker.clone()), None);
+
+        let db_pool = self.db_pool.clone();
+        let client = self.request_client.clone();
+        let msg_tx = self.transmitter.clone();
+
+        self.spawn(|tx| async move {
+
+            let repaired = repair_missing_ticks(
+                &exchange,
+                &ticker,
+                &integrity.missing_ticks,
+                &client,
+                db_pool.clone()
+            ).await;
+
+            let _ = record_command(
+                &Command::DbIntegrityCheck {
+                    exchange: exchange.clone(),
+                    ticker: ticker.clone(),
+                    repair: true
+                },
+                &AuditOrigin::Tui,
+                &repaired,
+                db_pool.clone()
+            ).await;
+
+            let msg = match repaired {
+                Ok(n) => OutputMsg::new(
+                    format!("Repaired {} ticks in {} {}", n, exchange, ticker),
+                    Color::Green,
+                    true,
+                    None,
+                    Some(exchange.clone()),
+                    Some(ticker.clone())
+                ),
+                Err(e) => OutputMsg::new(
+                    format!("Repair failed for {} {}: {}", exchange, ticker, e),
+                    Color::Red,
+                    true,
+                    None,
+                    Some(exchange.clone()),
+                    Some(ticker.clone())
+                )
+            };
+            let _ = msg_tx.send(AppEvent::Output(msg));
+
+            let integrity = integrity_check(
+                &exchange,
+                &ticker,
+                db_pool,
+                None
+            ).await;
+            let _ = tx.send(Checked { exchange, ticker, integrity });
+        });
+    }
+
+    /// Runs `work` in the background under the integrity check time limit,
+    /// sending its results to this screen
+    fn spawn<F, Fut>(&mut self, work: F)
+    where
+        F: FnOnce(UnboundedSender<Checked>) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let (tx, rx) = unbounded_channel::<Checked>();
+        self.receiver = Some(rx);
+
+        let work = work(tx);
+        let limit: Option<Duration> = self.time_limit;
+        let msg_tx = self.transmitter.clone();
+
+        self.task = Some(tokio::spawn(async move {
+            match limit {
+                Some(limit) => {
+                    if timeout(limit, work).await.is_err() {
+                        let _ = msg_tx.send(AppEvent::Output(OutputMsg::new(
+                            format!(
+                                "Integrity check timed out after {} minutes",
+                                limit.as_secs() / 60
+                            ),
+                            Color::Red,
+                            true,
+                            None,
+                            None,
+                            None
+                        )));
+                    };
+                },
+                None => work.await
+            };
+        }));
+    }
+
+    pub fn pre_draw(&mut self) {
+
+        let Some(receiver) = self.receiver.as_mut() else { return };
+
+        while let Ok(c) = receiver.try_recv() {
+            self.results.insert((c.exchange, c.ticker), Some(c.integrity));
+        };
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect) {
+
+        let header = Row::new(["Pair", "Status", "Ticks", "Date range"])
+            .style(Style::default().add_modifier(Modifier::BOLD));
+
+        let rows: Vec<Row> = self.results
+            .iter()
+            .map(|((exchange, ticker), result)| {
+
+                let (status, color): (String, Color) = match result {
+                    None => ("checking...".to_string(), Color::DarkGray),
+                    Some(r) if !r.error.is_empty() => {
+                        (format!("error: {}", r.error), Color::Red)
+                    },
+                    Some(r) if r.is_ok => ("ok".to_string(), Color::Green),
+                    Some(r) => (
+                        format!("{} missing", r.missing_ticks.len()),
+                        Color::Red
+                    ),
+                };
+
+                let (ticks, dates): (String, String) = match result {
+                    Some(r) => (
+                        r.total_ticks.to_string(),
+                        format!("{} → {}", r.first_date, r.last_date)
+                    ),
+                    None => (String::new(), String::new())
+                };
+
+                Row::new([
+                    Cell::from(format!("{} {}", exchange, ticker))
+                        .style(Style::default()
+                            .fg(pair_color(exchange, ticker))),
+                    Cell::from(status).style(Style::default().fg(color)),
+                    Cell::from(ticks),
+                    Cell::from(dates),
+                ])
+            })
+            .collect();
+
+        let mut title: String = Self::SCREEN_NAME.to_string();
+        if self.is_busy() {
+            title.push_str(" (running)");
+        };
+
+        let keys: &str = match self.guest_mode {
+            true => "Enter: check again",
+            false => "Enter: check again  r: repair selected",
+        };
+
+        let table = Table::new(rows, [
+            Constraint::Length(20),
+            Constraint::Length(16),
+            Constraint::Length(14),
+            Constraint::Min(20),
+        ])
+            .header(header)
+            .block(
+                Block::default()
+                    .title(title)
+                    .title_bottom(keys)
+                    .borders(Borders::ALL)
+            )
+            .row_highlight_style(
+                Style::default().add_modifier(Modifier::REVERSED)
+            );
+
+        frame.render_stateful_widget(table, area, &mut self.table_state);
+    }
+
+    pub async fn handle_key(&mut self, key: KeyEvent) {
+
+        match key.code {
+
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.table_state.select_previous();
+            },
+
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.table_state.select_next();
+            },
+
+            KeyCode::Enter if !self.is_busy() => self.check_all().await,
+
+            KeyCode::Char('r') if !self.is_busy() => {
+                match self.guest_mode {
+                    true => self.send_msg(
+                        "Repairs are disabled in guest mode".to_string(),
+                        Color::Yellow
+                    ),
+                    false => self.repair_selected()
+                };
+            },
+
+            KeyCode::Enter | KeyCode::Char('r') => self.send_msg(
+                "Wait for the running check to finish".to_string(),
+                Color::Yellow
+            ),
+
+            _ => {}
+        };
+    }
+
+    fn send_msg(&self, text: String, color: Color) {
+        let _ = self.transmitter.send(AppEvent::Output(OutputMsg::new(
+            text,
+            color,
+            false,
+            None,
+            None,
+            None
+        )));
+    }
+
+    pub const SCREEN_NAME: &'static str = "Integrity Check";
+}
@@ -0,0 +1,275 @@
+use ratatui::{
+    Frame,
+    crossterm::event::{KeyCode, KeyEvent},
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState, Wrap},
+};
+use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
+
+use app_core::{
+    catalog::refresh_catalog,
+    database_ops::{CancellationToken, DataDownloadStatus},
+    engine::Engine,
+    job_registry::{Job, JobRegistry, JobState, run_backfill_jobs},
+};
+use timestamp_tools::db_timestamp_to_date_string;
+use super::{AppEvent, OutputMsg, pair_color};
+
+
+// ------------------------------ JOBS SCREEN ------------------------------ //
+/// Lists every background job in the engine's `JobRegistry`, newest first
+///
+/// 'c' cancels the selected job, 'r' runs a failed or cancelled download
+/// again as a new job, and 'x' clears the jobs that are done.
+pub struct JobsScreen {
+    registry: JobRegistry,
+    transmitter: UnboundedSender<AppEvent>,
+    /// Copied from the registry before each frame
+    jobs: Vec<Job>,
+    table_state: TableState,
+}
+
+impl JobsScreen {
+
+    pub fn new(
+        registry: JobRegistry,
+        transmitter: UnboundedSender<AppEvent>
+    ) -> Self {
+
+        let mut screen = JobsScreen {
+            registry,
+            transmitter,
+            jobs: Vec::new(),
+            table_state: TableState::default(),
+        };
+
+        screen.pre_draw();
+        screen
+    }
+
+    /// Whether any job is still going, so the list keeps moving
+    pub fn is_busy(&self) -> bool {
+        self.jobs.iter().any(|j| !j.state.is_done())
+    }
+
+    pub fn pre_draw(&mut self) {
+
+        let selected_id: Option<u64> = self.selected().map(|j| j.id);
+
+        self.jobs = self.registry.snapshot();
+        self.jobs.reverse();
+
+        // The selection follows its job as newer ones are added on top
+        let index: Option<usize> = match self.jobs.len() {
+            0 => None,
+            len => selected_id
+                .and_then(|id| self.jobs.iter().position(|j| j.id == id))
+                .or(Some(self.table_state.selected().unwrap_or(0).min(len - 1)))
+        };
+        self.table_state.select(index);
+    }
+
+    fn selected(&self) -> Option<&Job> {
+        self.table_state.selected().and_then(|i| self.jobs.get(i))
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect) {
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(5), Constraint::Length(4)])
+            .split(area);
+
+        let header = Row::new(
+            ["ID", "Type", "Pair", "Progress", "State", "Started (UTC)"]
+        )
+            .style(Style::default().add_modifier(Modifier::BOLD));
+
+        let rows: Vec<Row> = self.jobs
+            .iter()
+            .map(|job| {
+
+                let color: Color = match job.state {
+                    JobState::Queued => Color::DarkGray,
+                    JobState::Running => Color::Yellow,
+                    JobState::Finished => Color::Green,
+                    JobState::Failed(_) => Color::Red,
+                    JobState::Cancelled => Color::Magenta,
+                };
+
+                Row::new([
+                    Cell::from(job.id.to_string()),
+                    Cell::from(job.kind.to_string()),
+                    Cell::from(format!("{} {}", job.exchange, job.ticker))
+                        .style(Style::default()
+                            .fg(pair_color(&job.exchange, &job.ticker))),
+                    Cell::from(match job.progress {
+                        Some(p) => format!("{}%", p),
+                        None => "-".to_string()
+                    }),
+                    Cell::from(job.state.to_string())
+                        .style(Style::default().fg(color)),
+                    Cell::from(
+                        db_timestamp_to_date_string(job.started_at * 1_000_000)
+                    ),
+                ])
+            })
+            .collect();
+
+        let running: usize = self.jobs
+            .iter()
+            .filter(|j| !j.state.is_done())
+            .count();
+
+        let title: String = match running {
+            0 => Self::SCREEN_NAME.to_string(),
+            n => format!("{} ({} running)", Self::SCREEN_NAME, n)
+        };
+
+        let table = Table::new(rows, [
+            Constraint::Length(6),
+            Constraint::Length(16),
+            Constraint::Length(18),
+            Constraint::Length(10),
+            Constraint::Length(10),
+            Constraint::Min(19),
+        ])
+            .header(header)
+            .block(
+                Block::default()
+                    .title(title)
+                    .title_bottom("c: cancel  r: retry  x: clear finished")
+                    .borders(Borders::ALL)
+            )
+            .row_highlight_style(
+                Style::default().add_modifier(Modifier::REVERSED)
+            );
+
+        frame.render_stateful_widget(table, chunks[0], &mut self.table_state);
+
+        let detail: String = match self.selected() {
+            None => "No jobs yet. Updates started from the Database \
+                Management screen show up here, one job per pair."
+                .to_string(),
+            Some(Job { state: JobState::Failed(e), .. }) => e.clone(),
+            Some(job) => format!(
+                "{} {} {} {}",
+                job.kind,
+                job.exchange,
+                job.ticker,
+                job.state
+            )
+        };
+
+        let details = Paragraph::new(detail)
+            .block(Block::default().title("Details").borders(Borders::ALL))
+            .wrap(Wrap { trim: true });
+
+        frame.render_widget(details, chunks[1]);
+    }
+
+    pub async fn handle_key(&mut self, key: KeyEvent, engine: &Engine) {
+
+        match key.code {
+
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.table_state.select_previous();
+            },
+
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.table_state.select_next();
+            },
+
+            KeyCode::Char('c') => {
+                let Some(id) = self.selected().map(|j| j.id) else { return };
+                match self.registry.cancel(id) {
+                    true => self.send_msg(
+                        format!("Cancelling job {}", id),
+                        Color::Magenta
+                    ),
+                    false => self.send_msg(
+                        format!("Job {} isn't running", id),
+                        Color::Yellow
+                    )
+                };
+            },
+
+            KeyCode::Char('r') => {
+                match self.selected().cloned() {
+                    Some(job) if job.is_retryable() => self.retry(job, engine),
+                    Some(job) => self.send_msg(
+                        format!(
+                            "Only failed or cancelled downloads can be \
+                            retried, job {} is {}",
+                            job.id,
+                            job.state
+                        ),
+                        Color::Yellow
+                    ),
+                    None => {}
+                };
+            },
+
+            KeyCode::Char('x') => {
+                self.registry.clear_done();
+                self.pre_draw();
+            },
+
+            _ => {}
+        };
+    }
+
+    /// Downloads the job's pair again, as a new job
+    fn retry(&self, job: Job, engine: &Engine) {
+
+        let (prog_tx, mut prog_rx) = unbounded_channel::<DataDownloadStatus>();
+        let ui_tx = self.transmitter.clone();
+
+        tokio::spawn(async move {
+            while let Some(stat) = prog_rx.recv().await {
+                let _ = ui_tx.send(AppEvent::Download(stat));
+            }
+        });
+
+        let registry = self.registry.clone();
+        let time_offset = engine.state.time_offset();
+        let client = engine.request_client.clone();
+        let db_pool = engine.database.get_pool();
+        let control = engine.state
+            .config
+            .data_download
+            .download_control(CancellationToken::new());
+
+        self.send_msg(
+            format!("Retrying {} {}", job.exchange, job.ticker),
+            Color::Yellow
+        );
+
+        tokio::spawn(async move {
+            let _ = run_backfill_jobs(
+                registry,
+                vec![(job.exchange, job.ticker)],
+                time_offset,
+                client,
+                db_pool.clone(),
+                prog_tx,
+                control
+            ).await;
+            let _ = refresh_catalog(db_pool).await;
+        });
+    }
+
+    fn send_msg(&self, text: String, color: Color) {
+        let _ = self.transmitter.send(AppEvent::Output(OutputMsg::new(
+            text,
+            color,
+            false,
+            None,
+            None,
+            None
+        )));
+    }
+
+    pub const SCREEN_NAME: &'static str = "Jobs";
+}
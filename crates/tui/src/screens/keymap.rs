@@ -0,0 +1,179 @@
+use ratatui::{
+    Frame,
+    layout::{Constraint, Rect},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Cell, Clear, Row, Table},
+};
+
+use app_core::app_state::StartScreen;
+
+
+/// A key, or a group of keys doing the same thing, and what it does
+pub struct KeyBinding {
+    pub keys: &'static str,
+    pub action: &'static str,
+}
+
+const fn bind(keys: &'static str, action: &'static str) -> KeyBinding {
+    KeyBinding { keys, action }
+}
+
+
+// -------------------------------- KEYMAP --------------------------------- //
+/// Keys that work on every screen, unless it's taking text input
+pub const GLOBAL_KEYS: &[KeyBinding] = &[
+    bind("?", "show or hide this help"),
+    bind("q", "quit"),
+    bind("y", "copy the selected pair or the last exported path"),
+    bind("Y", "copy the output window"),
+    bind("o", "open the last exported file"),
+    bind("PgUp / PgDn", "scroll the output window back and forth"),
+    bind("Mouse wheel", "scroll the output window under the pointer"),
+    bind("G", "jump to the end of the output window"),
+];
+
+/// Moving around the lists, shared by every screen
+pub const NAVIGATION_KEYS: &[KeyBinding] = &[
+    bind("↑ / k", "move up"),
+    bind("↓ / j", "move down"),
+    bind("Ctrl+u", "page up"),
+    bind("Ctrl+d", "page down"),
+    bind("Enter", "choose, or start typing a value"),
+    bind("Esc", "go back a pane, or stop typing"),
+];
+
+const DATABASE_KEYS: &[KeyBinding] = &[
+    bind("Space", "select or deselect a pair"),
+    bind("/", "search the pair list"),
+    bind("x", "show each pair's download, or just the exchanges"),
+    bind("r", "reload the pair list and stats"),
+    bind("Ctrl+c", "cancel the running update"),
+];
+
+const CANDLE_KEYS: &[KeyBinding] = &[
+    bind("/", "search the pair list"),
+    bind("f", "cycle the export format"),
+];
+
+const SETTINGS_KEYS: &[KeyBinding] = &[
+    bind("s", "save the settings"),
+    bind("u", "undo the last change"),
+    bind("Esc", "save and leave"),
+];
+
+const STRATEGY_KEYS: &[KeyBinding] = &[
+    bind("/", "search the pair list"),
+];
+
+const CHART_KEYS: &[KeyBinding] = &[
+    bind("/", "search the pair list"),
+    bind("← / h, → / l", "scroll one bar"),
+    bind("H / L", "scroll one page"),
+    bind("End", "jump to the latest bar"),
+    bind("v", "show or hide volume"),
+    bind("s / e / b", "toggle the SMA, EMA and Bollinger overlays"),
+    bind("w", "wide mode, hiding the other panes"),
+];
+
+const INTEGRITY_KEYS: &[KeyBinding] = &[
+    bind("Enter", "check every pair again"),
+    bind("r", "repair the selected pair"),
+];
+
+const JOBS_KEYS: &[KeyBinding] = &[
+    bind("c", "cancel the selected job"),
+    bind("r", "run a failed or cancelled download again"),
+    bind("x", "clear the jobs that are done"),
+];
+
+const PAPER_KEYS: &[KeyBinding] = &[
+    bind("r", "fill the orders new ticks reached, and reload"),
+    bind("c", "cancel the selected order"),
+];
+
+const ORDERS_KEYS: &[KeyBinding] = &[
+    bind("r", "fill the paper orders new ticks reached, and reload"),
+    bind("c", "cancel the selected order"),
+    bind("m", "type a new limit or stop price for the selected order"),
+    bind("a", "show only open orders, or all of them"),
+    bind("e", "export every fill, with fees and realized P&L, as CSV"),
+];
+
+const WATCH_KEYS: &[KeyBinding] = &[
+    bind("r", "fetch every quote again now"),
+];
+
+/// The keys only `screen` has, on top of the global and navigation keys
+pub fn screen_keys(screen: StartScreen) -> &'static [KeyBinding] {
+    match screen {
+        StartScreen::Database => DATABASE_KEYS,
+        StartScreen::Candles => CANDLE_KEYS,
+        StartScreen::Settings => SETTINGS_KEYS,
+        StartScreen::Strategies => STRATEGY_KEYS,
+        StartScreen::Chart => CHART_KEYS,
+        StartScreen::Integrity => INTEGRITY_KEYS,
+        StartScreen::Watch => WATCH_KEYS,
+        StartScreen::Jobs => JOBS_KEYS,
+        StartScreen::Paper => PAPER_KEYS,
+        StartScreen::Orders => ORDERS_KEYS,
+        StartScreen::Menu | StartScreen::Last => &[],
+    }
+}
+
+
+// ----------------------------- HELP OVERLAY ------------------------------ //
+/// Draws the cheat sheet for `screen` centred in `area`, on top of whatever
+/// is there
+pub fn draw_help(frame: &mut Frame, area: Rect, screen: StartScreen) {
+
+    const WIDTH: u16 = 72;
+
+    let sections: [(String, &[KeyBinding]); 3] = [
+        ("Global".to_string(), GLOBAL_KEYS),
+        ("Navigation".to_string(), NAVIGATION_KEYS),
+        (format!("This screen ({})", screen), screen_keys(screen)),
+    ];
+
+    let mut rows: Vec<Row> = Vec::new();
+
+    for (title, bindings) in sections.iter() {
+
+        if bindings.is_empty() { continue };
+
+        if !rows.is_empty() {
+            rows.push(Row::new([""; 2]));
+        };
+
+        rows.push(
+            Row::new([title.as_str(), ""]).style(
+                Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)
+            )
+        );
+
+        rows.extend(bindings.iter().map(|b| Row::new([
+            Cell::from(b.keys).style(Style::default().fg(Color::Yellow)),
+            Cell::from(b.action),
+        ])));
+    };
+
+    let width: u16 = WIDTH.min(area.width);
+    let height: u16 = (rows.len() as u16 + 2).min(area.height);
+    let popup = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height
+    );
+
+    let table = Table::new(rows, [Constraint::Length(16), Constraint::Min(0)])
+        .block(
+            Block::default()
+                .title("Keys")
+                .title_bottom("Press any key to close")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan))
+        );
+
+    frame.render_widget(Clear, popup);
+    frame.render_widget(table, popup);
+}
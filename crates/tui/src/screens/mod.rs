@@ -1,19 +1,35 @@
-use std::cmp::min;
+use std::{cmp::min, iter, path::PathBuf};
 
 
 pub mod database;
 pub mod candles;
+pub mod chart;
+pub mod confirm;
+pub mod integrity;
+pub mod jobs;
+pub mod keymap;
+pub mod orders;
+pub mod paper;
+pub mod search;
 pub mod settings;
 pub mod strategies;
+pub mod watch;
 
 use database::DatabaseScreen;
 use settings::SettingsScreen;
 use candles::CandleScreen;
+use chart::ChartScreen;
+use integrity::IntegrityScreen;
+use jobs::JobsScreen;
+use orders::OrdersScreen;
+use paper::PaperScreen;
+use watch::WatchScreen;
 use strategies::StrategyScreen;
 
 use app_core::{
     database_ops::{
         DataDownloadStatus,
+        jobs::{JobOutcome, UpdateSummary},
     }
 };
 
@@ -21,7 +37,7 @@ use app_core::{
 use ratatui::{
     widgets::ListState,
     crossterm::{
-        event::KeyEvent,
+        event::{KeyEvent, MouseEvent},
     },
     style::{
         Color
@@ -56,6 +72,38 @@ pub fn move_down(state: &mut ListState, len: usize, step: usize) {
 
 
 
+/// Colors pairs are told apart by, leaving out the red, green, yellow and
+/// magenta used for statuses
+const PAIR_COLORS: [Color; 8] = [
+    Color::Cyan,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::Blue,
+    Color::LightCyan,
+    Color::Indexed(208), // Orange
+    Color::Indexed(141), // Lavender
+    Color::Indexed(37),  // Teal
+];
+
+/// The color a pair is drawn in wherever several pairs share the screen
+///
+/// Picked by an FNV-1a hash of the pair, so it's the same on every run.
+pub fn pair_color(exchange: &str, ticker: &str) -> Color {
+
+    let hash: u64 = exchange
+        .to_lowercase()
+        .bytes()
+        .chain([b'-'])
+        .chain(ticker.to_uppercase().bytes())
+        .fold(0xcbf29ce484222325, |h, b| {
+            (h ^ b as u64).wrapping_mul(0x100000001b3)
+        });
+
+    PAIR_COLORS[(hash % PAIR_COLORS.len() as u64) as usize]
+}
+
+
+
 #[derive(Clone)]
 pub enum Focus {
     Operations,
@@ -66,8 +114,16 @@ pub enum Focus {
 pub enum AppEvent {
     Input(KeyEvent),
     Output(OutputMsg),
+    /// Mouse wheel turns, which scroll the output window
+    Mouse(MouseEvent),
+    /// Download progress, drawn as gauges on the database screen
+    Download(DataDownloadStatus),
+    /// Sent once an update run is over, drawn as a block in the output
+    UpdateSummary(UpdateSummary),
     Clear,
     Tick,
+    /// A file was exported; 'o' opens it and 'y' copies its path
+    Exported(PathBuf),
 }
 
 // ------------ SCREENS ------------- //
@@ -76,6 +132,12 @@ pub enum Screen {
     CandleBuilder(CandleScreen),
     SystemSettings(SettingsScreen),
     StrategyManager(StrategyScreen),
+    ChartViewer(ChartScreen),
+    IntegrityCheck(IntegrityScreen),
+    LiveWatch(WatchScreen),
+    JobQueue(JobsScreen),
+    PaperTrading(PaperScreen),
+    OrderList(OrdersScreen),
     Placeholder,
 }
 
@@ -105,6 +167,55 @@ impl OutputMsg {
     }
 }
 
+impl OutputMsg {
+
+    /// The lines of an update summary: a headline, then one line per pair
+    pub fn from_summary(summary: &UpdateSummary) -> Vec<OutputMsg> {
+
+        let headline = OutputMsg::new(
+            format!("Update finished: {}", summary.headline()),
+            match summary.count(JobOutcome::Error) {
+                0 => Color::Green,
+                _ => Color::Yellow
+            },
+            true,
+            None,
+            None,
+            None
+        );
+
+        let pairs = summary.pairs.iter().map(|p| {
+
+            let color: Color = match p.outcome {
+                JobOutcome::Finished => Color::Green,
+                JobOutcome::Error => Color::Red,
+                JobOutcome::Cancelled => Color::Magenta,
+            };
+            let error: String = p.error
+                .as_ref()
+                .map(|e| format!(" ({})", e))
+                .unwrap_or_default();
+
+            OutputMsg::new(
+                format!(
+                    "  {}: {}, {} ticks{}", 
+                    p.ticker, 
+                    p.outcome.as_str(), 
+                    p.ticks, 
+                    error
+                ),
+                color,
+                false,
+                None,
+                Some(p.exchange.clone()),
+                Some(p.ticker.clone())
+            )
+        });
+
+        iter::once(headline).chain(pairs).collect()
+    }
+}
+
 impl From<DataDownloadStatus> for OutputMsg {
     
     fn from(status: DataDownloadStatus) -> Self {
@@ -125,6 +236,7 @@ impl From<DataDownloadStatus> for OutputMsg {
                 exchange,
                 ticker,
                 percent,
+                ..
             } => {
                 OutputMsg::new(
                     format!("  {ticker}: {percent}%"),
@@ -157,6 +269,57 @@ impl From<DataDownloadStatus> for OutputMsg {
                     Some(ticker),
                 )
             }
+
+            DataDownloadStatus::Cancelled { exchange, ticker } => {
+                OutputMsg::new(
+                    format!("  {ticker}: Stopped"),
+                    Color::Magenta,
+                    true,
+                    None,
+                    Some(exchange),
+                    Some(ticker),
+                )
+            }
+
+            DataDownloadStatus::Stalled { 
+                exchange, 
+                ticker, 
+                cursor, 
+                idle_secs, 
+                retrying 
+            } => {
+                OutputMsg::new(
+                    format!(
+                        "  {ticker}: STALLED at cursor {cursor}, no progress \
+                        for {idle_secs}s{}",
+                        match retrying {
+                            true => ", retrying",
+                            false => ""
+                        }
+                    ),
+                    Color::Red,
+                    true,
+                    None,
+                    Some(exchange),
+                    Some(ticker),
+                )
+            }
+
+            DataDownloadStatus::Retrying { 
+                exchange, 
+                ticker, 
+                reason, 
+                delay_secs 
+            } => {
+                OutputMsg::new(
+                    format!("  {ticker}: {reason}, retrying in {delay_secs}s"),
+                    Color::Magenta,
+                    true,
+                    None,
+                    Some(exchange),
+                    Some(ticker),
+                )
+            }
         }
     }
 }
@@ -0,0 +1,834 @@
+use std::time::Duration;
+
+use ratatui::{
+    Frame,
+    crossterm::event::{KeyCode, KeyEvent},
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState, Wrap},
+};
+use reqwest::Client;
+use sqlx::PgPool;
+use tokio::{
+    sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel},
+    task::JoinHandle,
+    time::interval,
+};
+
+use app_core::{
+    app_state::{AppConfig, LiveTradingSettings, SystemPaths},
+    arg_parsing::Command,
+    audit::record_command,
+    bars::{CsvDialect, ExportFormat},
+    blotter::{collect_fills, default_file_name, write_fills},
+    database_ops::{audit::AuditOrigin, kraken_private::KrakenOrder},
+    errors::PaperError,
+    live,
+    paper::{
+        CostModels,
+        OrderStatus,
+        PaperOrder,
+        account_names,
+        cancel_order,
+        modify_order,
+        order_history,
+        sync,
+    },
+};
+use string_helpers::Locale;
+use timestamp_tools::get_current_unix_timestamp;
+use super::{AppEvent, OutputMsg, pair_color};
+
+
+/// Time between two loads of the orders
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+
+/// An order on the screen, from a paper account or the live one
+enum Listed {
+    Paper {
+        account: String,
+        order: PaperOrder,
+    },
+    /// Kraken only reports the open ones
+    Live(KrakenOrder),
+}
+
+impl Listed {
+
+    fn source(&self) -> String {
+        match self {
+            Listed::Paper { account, .. } => format!("paper:{}", account),
+            Listed::Live(_) => "live".to_string(),
+        }
+    }
+
+    fn id(&self) -> String {
+        match self {
+            Listed::Paper { order, .. } => order.id.to_string(),
+            Listed::Live(order) => order.txid.clone(),
+        }
+    }
+
+    fn is_open(&self) -> bool {
+        match self {
+            Listed::Paper { order, .. } => matches!(
+                order.status,
+                OrderStatus::Open | OrderStatus::Pending
+            ),
+            Listed::Live(_) => true,
+        }
+    }
+
+    /// When the order was placed, in microseconds
+    fn placed(&self) -> u64 {
+        match self {
+            Listed::Paper { order, .. } => order.placed_time,
+            Listed::Live(order) => order.opened,
+        }
+    }
+
+    /// "open", or how much of it filled so far, for a live order
+    fn status(&self) -> (String, Color) {
+        match self {
+            Listed::Paper { order, .. } => (
+                order.status.to_string(),
+                status_color(order.status)
+            ),
+            Listed::Live(order) => match order.volume_filled > 0.0 {
+                true => (
+                    format!("{} filled", order.volume_filled),
+                    Color::Cyan
+                ),
+                false => ("open".to_string(), Color::Yellow)
+            }
+        }
+    }
+}
+
+
+/// What the polling task sends back to the screen
+enum OrdersUpdate {
+    Loading,
+    Loaded {
+        orders: Vec<Listed>,
+        /// Orders the sync filled, rejected, cancelled or opened
+        fills: Vec<PaperOrder>,
+        /// Why the live orders couldn't be read, while the paper ones were
+        live_error: Option<String>,
+    },
+    Failed(String),
+}
+
+
+fn status_color(status: OrderStatus) -> Color {
+    match status {
+        OrderStatus::Pending => Color::Blue,
+        OrderStatus::Open => Color::Yellow,
+        OrderStatus::Filled => Color::Green,
+        OrderStatus::Cancelled => Color::Magenta,
+        OrderStatus::Rejected => Color::Red,
+    }
+}
+
+/// Time since `placed`, in microseconds, e.g. "3h 20m"
+fn age(placed: u64, now: u64) -> String {
+
+    let secs: u64 = now.saturating_sub(placed) / 1_000_000;
+
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3_600 => format!("{}m", secs / 60),
+        3_600..86_400 => format!("{}h {}m", secs / 3_600, secs % 3_600 / 60),
+        _ => format!("{}d {}h", secs / 86_400, secs % 86_400 / 3_600),
+    }
+}
+
+/// Every paper account's orders, and the live account's open orders once
+/// live trading is on, open ones first, then newest first
+///
+/// With `sync_first` the paper accounts fill their open orders on the
+/// stored ticks first.
+async fn load_orders(
+    sync_first: bool,
+    cost_models: &mut CostModels,
+    live_settings: &LiveTradingSettings,
+    request_client: &Client,
+    db_pool: &PgPool
+) -> Result<OrdersUpdate, PaperError> {
+
+    let mut orders: Vec<Listed> = Vec::new();
+    let mut fills: Vec<PaperOrder> = Vec::new();
+
+    for account in account_names(db_pool).await? {
+        if sync_first {
+            fills.extend(sync(&account, cost_models, db_pool).await?);
+        };
+        orders.extend(order_history(&account, db_pool)
+            .await?
+            .into_iter()
+            .map(|order| Listed::Paper { account: account.clone(), order })
+        );
+    };
+
+    let live_error: Option<String> = match live_settings.enabled {
+        false => None,
+        true => match live::open_orders(live_settings, request_client).await {
+            Ok(open) => {
+                orders.extend(open.into_iter().map(Listed::Live));
+                None
+            },
+            Err(e) => Some(e.to_string())
+        }
+    };
+
+    orders.sort_by_key(|o| (!o.is_open(), std::cmp::Reverse(o.placed())));
+
+    Ok(OrdersUpdate::Loaded { orders, fills, live_error })
+}
+
+
+// ----------------------------- ORDERS SCREEN ----------------------------- //
+/// Every order placed, paper and live, with its status, fill price and age
+///
+/// The orders of every paper account are read from the database, where the
+/// paper engine keeps them as it places, fills and cancels them from any
+/// interface, and the live account's open orders are asked of Kraken once
+/// live trading is on. Both are loaded again every `POLL_INTERVAL`, and 'r'
+/// syncs the paper accounts and loads them at once.
+///
+/// 'c' cancels the selected order while it's open, and 'm' asks for a new
+/// limit or stop price for it. Paper orders sync first, so the ticks
+/// stored before the change fill them at their old price. Both are
+/// recorded in the audit log like their commands. 'a' hides or shows the
+/// orders that are over, and 'e' exports every fill as CSV to the export
+/// folder, like `dtrade fills`. In guest mode orders are only shown.
+pub struct OrdersScreen {
+    db_pool: PgPool,
+    request_client: Client,
+    slippage_bps: u32,
+    live_settings: LiveTradingSettings,
+    transmitter: UnboundedSender<AppEvent>,
+    orders: Vec<Listed>,
+    table_state: TableState,
+    receiver: Option<UnboundedReceiver<OrdersUpdate>>,
+    task: Option<JoinHandle<()>>,
+    /// Whether a load is in flight
+    loading: bool,
+    /// Hides the filled, cancelled and rejected orders
+    open_only: bool,
+    /// The new price being typed for the selected order
+    pub price_input: Option<String>,
+    /// The last live error shown, so each round doesn't repeat it
+    live_error: Option<String>,
+    locale: Locale,
+    csv_dialect: CsvDialect,
+    /// Disables syncing, cancelling, modifying and exporting, see
+    /// `Engine::guest_mode`
+    guest_mode: bool,
+}
+
+impl OrdersScreen {
+
+    /// Takes the slippage, live trading settings, locale and CSV dialect
+    /// from `config`
+    pub fn new(
+        db_pool: PgPool,
+        request_client: Client,
+        config: &AppConfig,
+        transmitter: UnboundedSender<AppEvent>,
+        guest_mode: bool,
+    ) -> Self {
+
+        let mut screen = OrdersScreen {
+            db_pool,
+            request_client,
+            slippage_bps: config.backtesting.slippage_bps,
+            live_settings: config.live.clone(),
+            transmitter,
+            orders: Vec::new(),
+            table_state: TableState::default(),
+            receiver: None,
+            task: None,
+            loading: false,
+            open_only: false,
+            price_input: None,
+            live_error: None,
+            locale: config.locale,
+            csv_dialect: config.csv_dialect.clone(),
+            guest_mode,
+        };
+
+        screen.start_polling(false);
+        screen
+    }
+
+    /// Whether the orders are being loaded
+    pub fn is_busy(&self) -> bool {
+        self.loading
+    }
+
+    /// Starts, or restarts, the task loading the orders, syncing the paper
+    /// accounts on its first round with `sync_first`
+    fn start_polling(&mut self, sync_first: bool) {
+
+        self.stop_polling();
+
+        let (tx, rx) = unbounded_channel::<OrdersUpdate>();
+        self.receiver = Some(rx);
+
+        let db_pool = self.db_pool.clone();
+        let client = self.request_client.clone();
+        let live_settings = self.live_settings.clone();
+        let mut cost_models = CostModels::new(
+            self.slippage_bps,
+            self.request_client.clone()
+        );
+
+        self.task = Some(tokio::spawn(async move {
+
+            let mut poll = interval(POLL_INTERVAL);
+
+            for round in 0u64.. {
+
+                poll.tick().await;
+
+                if tx.send(OrdersUpdate::Loading).is_err() {
+                    break
+                };
+
+                let update = match load_orders(
+                    sync_first && round == 0,
+                    &mut cost_models,
+                    &live_settings,
+                    &client,
+                    &db_pool
+                ).await {
+                    Ok(update) => update,
+                    Err(e) => OrdersUpdate::Failed(e.to_string())
+                };
+                let _ = tx.send(update);
+            };
+        }));
+    }
+
+    fn stop_polling(&mut self) {
+        if let Some(handle) = self.task.take() {
+            handle.abort();
+        };
+        self.receiver = None;
+        self.loading = false;
+    }
+
+    pub fn pre_draw(&mut self) {
+
+        let Some(receiver) = self.receiver.as_mut() else { return };
+
+        let mut updates: Vec<OrdersUpdate> = Vec::new();
+        while let Ok(update) = receiver.try_recv() {
+            updates.push(update);
+        };
+
+        for update in updates {
+            match update {
+                OrdersUpdate::Loading => self.loading = true,
+                OrdersUpdate::Loaded { orders, fills, live_error } => {
+                    self.loading = false;
+
+                    for order in fills {
+                        let color: Color = status_color(order.status);
+                        self.send_msg(order.to_string(), color);
+                    };
+
+                    if live_error.is_some() && live_error != self.live_error {
+                        self.send_msg(
+                            live_error.clone().unwrap_or_default(),
+                            Color::Red
+                        );
+                    };
+                    self.live_error = live_error;
+
+                    self.show(orders);
+                },
+                OrdersUpdate::Failed(e) => {
+                    self.loading = false;
+                    self.send_msg(e, Color::Red);
+                }
+            };
+        };
+    }
+
+    /// Replaces the orders, keeping the same one selected
+    fn show(&mut self, orders: Vec<Listed>) {
+
+        let selected: Option<(String, String)> = self
+            .selected()
+            .map(|o| (o.source(), o.id()));
+
+        self.orders = orders;
+
+        let len: usize = self.visible().len();
+        let index: Option<usize> = match len {
+            0 => None,
+            _ => selected
+                .and_then(|(source, id)| self
+                    .visible()
+                    .iter()
+                    .position(|o| o.source() == source && o.id() == id)
+                )
+                .or(Some(self.table_state.selected().unwrap_or(0).min(len - 1)))
+        };
+        self.table_state.select(index);
+    }
+
+    fn visible(&self) -> Vec<&Listed> {
+        self.orders
+            .iter()
+            .filter(|o| !self.open_only || o.is_open())
+            .collect()
+    }
+
+    fn selected(&self) -> Option<&Listed> {
+        self.table_state
+            .selected()
+            .and_then(|i| self.visible().get(i).copied())
+    }
+
+    fn number(&self, value: f64) -> String {
+        self.locale.format_number(value)
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect) {
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(5), Constraint::Length(4)])
+            .split(area);
+
+        let mut title: String = format!(
+            "{}: {}",
+            Self::SCREEN_NAME,
+            match self.open_only {
+                true => "open",
+                false => "all"
+            }
+        );
+        if self.loading {
+            title.push_str(" (loading)");
+        };
+
+        let header = Row::new([
+            "Source",
+            "ID",
+            "Pair",
+            "Side",
+            "Volume",
+            "Type",
+            "Status",
+            "Fill price",
+            "Age",
+        ])
+            .style(Style::default().add_modifier(Modifier::BOLD));
+
+        let now: u64 = get_current_unix_timestamp() * 1_000_000;
+
+        let rows: Vec<Row> = self.visible()
+            .into_iter()
+            .map(|listed| {
+
+                let (exchange, ticker, side, volume, kind, fill_price) =
+                    match listed {
+                        Listed::Paper { order, .. } => (
+                            order.exchange.clone(),
+                            order.ticker.clone(),
+                            order.side.to_string(),
+                            order.volume,
+                            order.kind(),
+                            order.fill_price,
+                        ),
+                        Listed::Live(order) => (
+                            live::LIVE_EXCHANGE.to_string(),
+                            order.pair.clone(),
+                            order.side.clone(),
+                            order.volume,
+                            match order.price {
+                                Some(p) => {
+                                    format!("{} {}", order.order_type, p)
+                                },
+                                None => order.order_type.clone()
+                            },
+                            None,
+                        ),
+                    };
+
+                let side_color: Color = match side.as_str() {
+                    "sell" => Color::Red,
+                    _ => Color::Green
+                };
+                let (status, status_color) = listed.status();
+
+                Row::new([
+                    Cell::from(listed.source()),
+                    Cell::from(listed.id()),
+                    Cell::from(format!("{} {}", exchange, ticker))
+                        .style(Style::default()
+                            .fg(pair_color(&exchange, &ticker))),
+                    Cell::from(side).style(Style::default().fg(side_color)),
+                    Cell::from(self.number(volume)),
+                    Cell::from(self.locale.format_number(kind)),
+                    Cell::from(status)
+                        .style(Style::default().fg(status_color)),
+                    Cell::from(match fill_price {
+                        Some(price) => self.number(price),
+                        None => "-".to_string()
+                    }),
+                    Cell::from(age(listed.placed(), now)),
+                ])
+            })
+            .collect();
+
+        let keys: &str = match self.guest_mode {
+            true => "r: reload  a: open/all",
+            false => "r: sync  c: cancel  m: modify price  a: open/all  \
+                e: export fills"
+        };
+
+        let table = Table::new(rows, [
+            Constraint::Length(16),
+            Constraint::Length(20),
+            Constraint::Length(18),
+            Constraint::Length(5),
+            Constraint::Length(14),
+            Constraint::Length(18),
+            Constraint::Length(14),
+            Constraint::Length(14),
+            Constraint::Min(8),
+        ])
+            .header(header)
+            .block(
+                Block::default()
+                    .title(title)
+                    .title_bottom(keys)
+                    .borders(Borders::ALL)
+            )
+            .row_highlight_style(
+                Style::default().add_modifier(Modifier::REVERSED)
+            );
+
+        frame.render_stateful_widget(table, chunks[0], &mut self.table_state);
+
+        let (detail_title, detail): (&str, String) = match (
+            &self.price_input,
+            self.selected()
+        ) {
+            (Some(input), _) => (
+                "New price (Enter: change, Esc: keep)",
+                format!("{}_", input)
+            ),
+            (None, None) => (
+                "Details",
+                "No orders yet. Place paper orders with `dtrade paper buy` \
+                and `dtrade paper sell`, and live ones with `dtrade live \
+                buy` and `dtrade live sell` once live trading is on."
+                    .to_string()
+            ),
+            (None, Some(Listed::Paper { account, order })) => (
+                "Details",
+                format!("{}: {}", account, order)
+            ),
+            (None, Some(Listed::Live(order))) => {
+                ("Details", format!("live: {}", order))
+            },
+        };
+
+        let details = Paragraph::new(detail)
+            .block(Block::default().title(detail_title).borders(Borders::ALL))
+            .wrap(Wrap { trim: true });
+
+        frame.render_widget(details, chunks[1]);
+    }
+
+    pub async fn handle_key(&mut self, key: KeyEvent) {
+
+        if self.price_input.is_some() {
+            return self.handle_price_key(key).await
+        };
+
+        match key.code {
+
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.table_state.select_previous();
+            },
+
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.table_state.select_next();
+            },
+
+            KeyCode::Char('a') => {
+                self.open_only = !self.open_only;
+                let orders: Vec<Listed> = std::mem::take(&mut self.orders);
+                self.show(orders);
+            },
+
+            KeyCode::Char('r') => self.start_polling(!self.guest_mode),
+
+            KeyCode::Char('c' | 'm') if self.guest_mode => self.send_msg(
+                "Changing orders is disabled in guest mode".to_string(),
+                Color::Yellow
+            ),
+
+            KeyCode::Char('e') if self.guest_mode => self.send_msg(
+                "Exporting fills is disabled in guest mode".to_string(),
+                Color::Yellow
+            ),
+
+            KeyCode::Char('e') => self.export_fills(),
+
+            KeyCode::Char('c' | 'm') if !self
+                .selected()
+                .is_some_and(Listed::is_open) => self.send_msg(
+                    "Select an open order first".to_string(),
+                    Color::Yellow
+                ),
+
+            KeyCode::Char('c') => self.cancel_selected().await,
+
+            KeyCode::Char('m') => self.price_input = Some(String::new()),
+
+            _ => {}
+        };
+    }
+
+    async fn handle_price_key(&mut self, key: KeyEvent) {
+
+        let Some(input) = self.price_input.as_mut() else { return };
+
+        match key.code {
+            KeyCode::Char(c) if c.is_ascii_digit() || c == '.' => {
+                input.push(c);
+            },
+            KeyCode::Backspace => {
+                input.pop();
+            },
+            KeyCode::Esc => self.price_input = None,
+            KeyCode::Enter => {
+                let typed: String = input.clone();
+                match typed.parse::<f64>() {
+                    Ok(price) if price > 0.0 => {
+                        self.price_input = None;
+                        self.modify_selected(price).await;
+                    },
+                    _ => self.send_msg(
+                        format!("Invalid price: {}", typed),
+                        Color::Yellow
+                    )
+                };
+            },
+            _ => {}
+        };
+    }
+
+    async fn cancel_selected(&mut self) {
+
+        let result: Result<String, String> = match self.selected() {
+            None => return,
+            Some(Listed::Paper { account, order }) => {
+                let cmd = Command::PaperCancel {
+                    account: account.clone(),
+                    order_id: order.id
+                };
+                let cancelled = cancel_order(account, order.id, &self.db_pool)
+                    .await;
+                self.record(&cmd, &cancelled).await;
+                cancelled
+                    .map(|o| o.to_string())
+                    .map_err(|e| e.to_string())
+            },
+            Some(Listed::Live(order)) => {
+                let cmd = Command::LiveCancel { txid: order.txid.clone() };
+                let cancelled = live::cancel_order(
+                    &self.live_settings,
+                    &order.txid,
+                    &self.request_client
+                ).await;
+                self.record(&cmd, &cancelled).await;
+                cancelled
+                    .map(|_| order.to_string())
+                    .map_err(|e| e.to_string())
+            },
+        };
+
+        self.report("Cancelled order", result);
+    }
+
+    async fn modify_selected(&mut self, price: f64) {
+
+        let result: Result<String, String> = match self.selected() {
+            None => return,
+            Some(Listed::Paper { account, order }) => {
+                let cmd = Command::PaperModify {
+                    account: account.clone(),
+                    order_id: order.id,
+                    price
+                };
+                let mut cost_models = CostModels::new(
+                    self.slippage_bps,
+                    self.request_client.clone()
+                );
+                // Fills it at its old price on the ticks stored before now
+                let modified = match sync(
+                    account,
+                    &mut cost_models,
+                    &self.db_pool
+                ).await {
+                    Ok(_) => modify_order(
+                        account,
+                        order.id,
+                        price,
+                        &mut cost_models,
+                        &self.db_pool
+                    ).await,
+                    Err(e) => Err(e)
+                };
+                self.record(&cmd, &modified).await;
+                modified
+                    .map(|o| o.to_string())
+                    .map_err(|e| e.to_string())
+            },
+            Some(Listed::Live(order)) => {
+                let cmd = Command::LiveModify {
+                    txid: order.txid.clone(),
+                    price
+                };
+                let modified = live::modify_order(
+                    &self.live_settings,
+                    &order.txid,
+                    price,
+                    &self.request_client
+                ).await;
+                self.record(&cmd, &modified).await;
+                modified
+                    .map(|o| o.to_string())
+                    .map_err(|e| e.to_string())
+            },
+        };
+
+        self.report("Modified order", result);
+    }
+
+    /// Writes every fill, with its fee and realized P&L, to a CSV file in
+    /// the export folder, in the background
+    fn export_fills(&self) {
+
+        let db_pool = self.db_pool.clone();
+        let client = self.request_client.clone();
+        let live_settings = self.live_settings.clone();
+        let locale = self.locale;
+        let dialect = self.csv_dialect.clone();
+        let tx = self.transmitter.clone();
+
+        self.send_msg("Exporting the fills.".to_string(), Color::Yellow);
+
+        tokio::spawn(async move {
+
+            let send = |text: String, color: Color| {
+                let _ = tx.send(AppEvent::Output(OutputMsg::new(
+                    text,
+                    color,
+                    false,
+                    None,
+                    None,
+                    None
+                )));
+            };
+
+            let path = match SystemPaths::new() {
+                Ok(paths) => paths
+                    .candle_data
+                    .join(default_file_name(ExportFormat::Csv)),
+                Err(e) => return send(
+                    format!("Couldn't find the export folder: {}", e),
+                    Color::Red
+                )
+            };
+
+            let result = match collect_fills(
+                None,
+                None,
+                &live_settings,
+                &client,
+                &db_pool
+            ).await {
+                Ok(fills) => write_fills(
+                    &path,
+                    ExportFormat::Csv,
+                    &fills,
+                    &locale,
+                    &dialect
+                ).map(|_| fills.len()),
+                Err(e) => Err(e)
+            };
+
+            match result {
+                Ok(count) => {
+                    send(
+                        format!(
+                            "Saved {} fills to {} ('o' to open)",
+                            count,
+                            path.display()
+                        ),
+                        Color::Green
+                    );
+                    let _ = tx.send(AppEvent::Exported(path));
+                },
+                Err(e) => send(
+                    format!("Failed to export the fills: {}", e),
+                    Color::Red
+                )
+            };
+        });
+    }
+
+    async fn record<T, E: std::fmt::Display>(
+        &self,
+        cmd: &Command,
+        result: &Result<T, E>
+    ) {
+        let _ = record_command(
+            cmd,
+            &AuditOrigin::Tui,
+            result,
+            self.db_pool.clone()
+        ).await;
+    }
+
+    /// Shows how a change went, and loads the orders again after one
+    fn report(&mut self, done: &str, result: Result<String, String>) {
+        match result {
+            Ok(order) => {
+                self.send_msg(format!("{} {}", done, order), Color::Magenta);
+                self.start_polling(false);
+            },
+            Err(e) => self.send_msg(e, Color::Yellow)
+        };
+    }
+
+    fn send_msg(&self, text: String, color: Color) {
+        let _ = self.transmitter.send(AppEvent::Output(OutputMsg::new(
+            text,
+            color,
+            false,
+            None,
+            None,
+            None
+        )));
+    }
+
+    pub const SCREEN_NAME: &'static str = "Orders";
+}
+
+impl Drop for OrdersScreen {
+    fn drop(&mut self) {
+        self.stop_polling();
+    }
+}
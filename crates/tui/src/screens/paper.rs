@@ -0,0 +1,429 @@
+use ratatui::{
+    Frame,
+    crossterm::event::{KeyCode, KeyEvent},
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState, Wrap},
+};
+use reqwest::Client;
+use sqlx::PgPool;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
+
+use app_core::{
+    arg_parsing::Command,
+    audit::record_command,
+    backtest::OrderSide,
+    database_ops::audit::AuditOrigin,
+    errors::PaperError,
+    paper::{
+        CostModels,
+        DEFAULT_ACCOUNT,
+        OrderStatus,
+        PaperAccount,
+        PaperOrder,
+        cancel_order,
+        load_account,
+        order_history,
+        sync,
+    },
+};
+use string_helpers::Locale;
+use timestamp_tools::db_timestamp_to_date_string;
+use super::{AppEvent, OutputMsg, pair_color};
+
+
+/// What the loading task sends back to the screen
+enum PaperUpdate {
+    Loaded {
+        account: PaperAccount,
+        history: Vec<PaperOrder>,
+        /// Orders the sync filled, rejected, cancelled or opened
+        fills: Vec<PaperOrder>,
+    },
+    Failed(String),
+}
+
+
+fn status_color(status: OrderStatus) -> Color {
+    match status {
+        OrderStatus::Pending => Color::Blue,
+        OrderStatus::Open => Color::Yellow,
+        OrderStatus::Filled => Color::Green,
+        OrderStatus::Cancelled => Color::Magenta,
+        OrderStatus::Rejected => Color::Red,
+    }
+}
+
+
+// ----------------------------- PAPER SCREEN ------------------------------ //
+/// The default paper trading account: its balances, positions and every
+/// order, newest first, with the OCO or bracket it belongs to
+///
+/// Opening the screen fills the open orders the stored ticks have reached,
+/// like `dtrade paper sync`, and 'r' does it again. 'c' cancels the
+/// selected order while it's open. Orders are placed from the command line.
+/// In guest mode the account is only shown as stored: nothing is synced or
+/// cancelled.
+pub struct PaperScreen {
+    db_pool: PgPool,
+    request_client: Client,
+    slippage_bps: u32,
+    transmitter: UnboundedSender<AppEvent>,
+    account: Option<PaperAccount>,
+    orders: Vec<PaperOrder>,
+    table_state: TableState,
+    receiver: Option<UnboundedReceiver<PaperUpdate>>,
+    locale: Locale,
+    /// Disables syncing and cancelling, see `Engine::guest_mode`
+    guest_mode: bool,
+}
+
+impl PaperScreen {
+
+    pub fn new(
+        db_pool: PgPool,
+        request_client: Client,
+        slippage_bps: u32,
+        transmitter: UnboundedSender<AppEvent>,
+        locale: Locale,
+        guest_mode: bool,
+    ) -> Self {
+
+        let mut screen = PaperScreen {
+            db_pool,
+            request_client,
+            slippage_bps,
+            transmitter,
+            account: None,
+            orders: Vec::new(),
+            table_state: TableState::default(),
+            receiver: None,
+            locale,
+            guest_mode,
+        };
+
+        screen.refresh();
+        screen
+    }
+
+    /// Whether the account is being synced and loaded
+    pub fn is_busy(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    /// Syncs the account and loads it again, in the background. Guest mode
+    /// only loads it.
+    fn refresh(&mut self) {
+
+        let (tx, rx) = unbounded_channel::<PaperUpdate>();
+        self.receiver = Some(rx);
+
+        let db_pool = self.db_pool.clone();
+        let guest_mode: bool = self.guest_mode;
+        let mut cost_models = CostModels::new(
+            self.slippage_bps,
+            self.request_client.clone()
+        );
+
+        tokio::spawn(async move {
+
+            let loaded = async {
+                let fills: Vec<PaperOrder> = match guest_mode {
+                    true => Vec::new(),
+                    false => {
+                        sync(DEFAULT_ACCOUNT, &mut cost_models, &db_pool)
+                            .await?
+                    }
+                };
+                let account = load_account(DEFAULT_ACCOUNT, &db_pool).await?;
+                let history = order_history(DEFAULT_ACCOUNT, &db_pool).await?;
+                Ok::<_, PaperError>(PaperUpdate::Loaded {
+                    account,
+                    history,
+                    fills
+                })
+            };
+
+            let _ = tx.send(match loaded.await {
+                Ok(update) => update,
+                Err(e) => PaperUpdate::Failed(e.to_string())
+            });
+        });
+    }
+
+    pub fn pre_draw(&mut self) {
+
+        let Some(receiver) = self.receiver.as_mut() else { return };
+
+        let Ok(update) = receiver.try_recv() else { return };
+        self.receiver = None;
+
+        match update {
+            PaperUpdate::Loaded { account, history, fills } => {
+
+                for order in fills {
+                    let color: Color = status_color(order.status);
+                    self.send_msg(order.to_string(), color);
+                };
+
+                let selected_id: Option<i64> = self.selected().map(|o| o.id);
+
+                self.account = Some(account);
+                self.orders = history;
+
+                let index: Option<usize> = match self.orders.len() {
+                    0 => None,
+                    len => selected_id
+                        .and_then(|id| self.orders
+                            .iter()
+                            .position(|o| o.id == id)
+                        )
+                        .or(Some(
+                            self.table_state.selected().unwrap_or(0)
+                                .min(len - 1)
+                        ))
+                };
+                self.table_state.select(index);
+            },
+            PaperUpdate::Failed(e) => self.send_msg(e, Color::Red)
+        };
+    }
+
+    fn selected(&self) -> Option<&PaperOrder> {
+        self.table_state.selected().and_then(|i| self.orders.get(i))
+    }
+
+    fn number(&self, value: f64) -> String {
+        self.locale.format_number(format!("{:.2}", value))
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect) {
+
+        let positions: usize = self.account
+            .as_ref()
+            .map(|a| a.positions.len())
+            .unwrap_or(0);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(4),
+                Constraint::Length(positions.max(1) as u16 + 3),
+                Constraint::Min(5),
+            ])
+            .split(area);
+
+        let mut title: String = format!(
+            "{}: {}",
+            Self::SCREEN_NAME,
+            DEFAULT_ACCOUNT
+        );
+        if self.is_busy() {
+            title.push_str(match self.guest_mode {
+                true => " (loading)",
+                false => " (syncing)"
+            });
+        };
+
+        let summary: String = match &self.account {
+            None => "Loading...".to_string(),
+            Some(a) => format!(
+                "Cash {}   Equity {} ({}%)   Started with {}\n\
+                {} open orders",
+                self.number(a.cash),
+                self.number(a.equity),
+                self.locale.format_number(format!("{:+.2}", a.return_pct())),
+                self.number(a.starting_cash),
+                a.open_orders.len()
+            )
+        };
+
+        let balances = Paragraph::new(summary)
+            .block(Block::default().title(title).borders(Borders::ALL));
+
+        frame.render_widget(balances, chunks[0]);
+
+        let header = Row::new(["Pair", "Volume", "Last", "Value", "Cost"])
+            .style(Style::default().add_modifier(Modifier::BOLD));
+
+        let held: Vec<Row> = self.account
+            .iter()
+            .flat_map(|a| a.positions.iter())
+            .map(|p| Row::new([
+                Cell::from(format!("{} {}", p.exchange, p.ticker))
+                    .style(Style::default()
+                        .fg(pair_color(&p.exchange, &p.ticker))),
+                Cell::from(self.locale.format_number(p.volume)),
+                Cell::from(match p.last_price {
+                    Some(price) => self.locale.format_number(price),
+                    None => "?".to_string()
+                }),
+                Cell::from(self.number(p.value())),
+                Cell::from(self.number(p.cost)),
+            ]))
+            .collect();
+
+        let positions_table = Table::new(held, [
+            Constraint::Length(18),
+            Constraint::Length(14),
+            Constraint::Length(14),
+            Constraint::Length(14),
+            Constraint::Min(12),
+        ])
+            .header(header)
+            .block(Block::default().title("Positions").borders(Borders::ALL));
+
+        frame.render_widget(positions_table, chunks[1]);
+
+        let header = Row::new([
+            "ID", 
+            "Placed (UTC)", 
+            "Pair", 
+            "Side", 
+            "Volume", 
+            "Type", 
+            "Group", 
+            "State"
+        ])
+            .style(Style::default().add_modifier(Modifier::BOLD));
+
+        let rows: Vec<Row> = self.orders
+            .iter()
+            .map(|order| {
+                Row::new([
+                    Cell::from(order.id.to_string()),
+                    Cell::from(db_timestamp_to_date_string(order.placed_time)),
+                    Cell::from(format!("{} {}", order.exchange, order.ticker))
+                        .style(Style::default()
+                            .fg(pair_color(&order.exchange, &order.ticker))),
+                    Cell::from(order.side.to_string())
+                        .style(Style::default().fg(match order.side {
+                            OrderSide::Buy => Color::Green,
+                            OrderSide::Sell => Color::Red,
+                        })),
+                    Cell::from(self.locale.format_number(order.volume)),
+                    Cell::from(self.locale.format_number(order.kind())),
+                    Cell::from(order.link().unwrap_or("-".to_string())),
+                    Cell::from(order.status.to_string())
+                        .style(Style::default().fg(status_color(order.status))),
+                ])
+            })
+            .collect();
+
+        let orders_area = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(5), Constraint::Length(4)])
+            .split(chunks[2]);
+
+        let table = Table::new(rows, [
+            Constraint::Length(6),
+            Constraint::Length(20),
+            Constraint::Length(18),
+            Constraint::Length(5),
+            Constraint::Length(14),
+            Constraint::Length(18),
+            Constraint::Length(13),
+            Constraint::Min(9),
+        ])
+            .header(header)
+            .block(
+                Block::default()
+                    .title("Orders")
+                    .title_bottom(match self.guest_mode {
+                        true => "r: reload",
+                        false => "r: sync  c: cancel order"
+                    })
+                    .borders(Borders::ALL)
+            )
+            .row_highlight_style(
+                Style::default().add_modifier(Modifier::REVERSED)
+            );
+
+        frame.render_stateful_widget(
+            table,
+            orders_area[0],
+            &mut self.table_state
+        );
+
+        let detail: String = match self.selected() {
+            None => "No orders yet. Place one from the command line, e.g. \
+                `dtrade paper buy kraken BTCUSD 0.01`, and sync with 'r'."
+                .to_string(),
+            Some(order) => order.to_string()
+        };
+
+        let details = Paragraph::new(detail)
+            .block(Block::default().title("Details").borders(Borders::ALL))
+            .wrap(Wrap { trim: true });
+
+        frame.render_widget(details, orders_area[1]);
+    }
+
+    pub async fn handle_key(&mut self, key: KeyEvent) {
+
+        match key.code {
+
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.table_state.select_previous();
+            },
+
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.table_state.select_next();
+            },
+
+            KeyCode::Char('r' | 'c') if self.is_busy() => self.send_msg(
+                "Wait for the account to finish syncing".to_string(),
+                Color::Yellow
+            ),
+
+            KeyCode::Char('r') => self.refresh(),
+
+            KeyCode::Char('c') if self.guest_mode => self.send_msg(
+                "Cancelling orders is disabled in guest mode".to_string(),
+                Color::Yellow
+            ),
+
+            KeyCode::Char('c') => {
+                let Some(id) = self.selected().map(|o| o.id) else { return };
+
+                let cancelled = cancel_order(DEFAULT_ACCOUNT, id, &self.db_pool)
+                    .await;
+                let _ = record_command(
+                    &Command::PaperCancel {
+                        account: DEFAULT_ACCOUNT.to_string(),
+                        order_id: id
+                    },
+                    &AuditOrigin::Tui,
+                    &cancelled,
+                    self.db_pool.clone()
+                ).await;
+
+                match cancelled {
+                    Ok(order) => {
+                        self.send_msg(
+                            format!("Cancelled order {}", order),
+                            Color::Magenta
+                        );
+                        self.refresh();
+                    },
+                    Err(e) => self.send_msg(e.to_string(), Color::Yellow)
+                };
+            },
+
+            _ => {}
+        };
+    }
+
+    fn send_msg(&self, text: String, color: Color) {
+        let _ = self.transmitter.send(AppEvent::Output(OutputMsg::new(
+            text,
+            color,
+            false,
+            None,
+            None,
+            None
+        )));
+    }
+
+    pub const SCREEN_NAME: &'static str = "Paper Trading";
+}
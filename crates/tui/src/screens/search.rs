@@ -0,0 +1,91 @@
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent},
+    widgets::ListState,
+};
+
+
+/// An incremental fuzzy filter over a screen's bottom list
+///
+/// Opened with '/'. While it's `typing`, screens send it every key. Enter
+/// keeps the filter and hands the arrow keys back to the list, and Esc
+/// clears it.
+#[derive(Default)]
+pub struct ListSearch {
+    pub query: String,
+    pub typing: bool,
+}
+
+impl ListSearch {
+
+    pub fn open(&mut self) {
+        self.typing = true;
+    }
+
+    pub fn clear(&mut self) {
+        self.query.clear();
+        self.typing = false;
+    }
+
+    pub fn handle_key(&mut self, key: &KeyEvent) {
+        match key.code {
+            KeyCode::Char(c) => self.query.push(c),
+            KeyCode::Backspace => {
+                self.query.pop();
+            },
+            KeyCode::Enter => self.typing = false,
+            KeyCode::Esc => self.clear(),
+            _ => {}
+        };
+    }
+
+    /// Keeps the rows of `items` matching the query, in their order
+    ///
+    /// `state` indexes into `previous`, the rows as they were last drawn, so
+    /// it's moved to wherever the selected row ended up. When that row was
+    /// filtered out, the first row is selected instead.
+    pub fn apply(
+        &self,
+        items: Vec<String>,
+        previous: &[String],
+        state: &mut ListState
+    ) -> Vec<String> {
+
+        let rows: Vec<String> = items
+            .into_iter()
+            .filter(|item| fuzzy_match(&self.query, item))
+            .collect();
+
+        if let Some(selected) = state.selected()
+            .and_then(|i| previous.get(i))
+        {
+            state.select(Some(
+                rows.iter().position(|r| r == selected).unwrap_or(0)
+            ));
+        };
+
+        rows
+    }
+
+    /// `title` with the query after it, and a cursor while typing
+    pub fn title(&self, title: &str) -> String {
+        match (self.typing, self.query.is_empty()) {
+            (false, true) => title.to_string(),
+            (false, false) => format!("{}  /{}", title, self.query),
+            (true, _) => format!("{}  /{}_", title, self.query),
+        }
+    }
+}
+
+/// Whether every character of `query` appears in `item` in order, ignoring
+/// case and spaces, so "xbtusd" matches "kraken - XBTUSD" and "ethu"
+/// matches "ETHUSDT"
+pub fn fuzzy_match(query: &str, item: &str) -> bool {
+
+    let mut chars = item.chars().flat_map(char::to_lowercase);
+
+    query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .all(|q| chars.any(|c| c == q))
+}
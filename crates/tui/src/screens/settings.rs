@@ -1,20 +1,22 @@
-use std::fmt::{
-    Display,
-    Formatter,
-    self,
-};
+use std::net::SocketAddr;
 
 use app_core::{
     app_state::{
-        AppConfig, AppState, SystemPaths, save_config
+        AppConfig, 
+        SystemPaths, 
+        format_api_keys, 
+        parse_api_keys, 
+        parse_watchlist,
+        save_config
     },
+    audit::record_config_change,
+    config_fields::FieldKind,
+    database_ops::audit::AuditOrigin,
     errors::ConfigError
 };
+use sqlx::PgPool;
 use string_helpers::capitlize_first_letter;
-use timestamp_tools::{
-    VALID_PERIODS, 
-    period_is_valid
-};
+use timestamp_tools::VALID_PERIODS;
 use crate::{AppEvent, OutputMsg};
 
 use ratatui::{
@@ -45,50 +47,88 @@ use ratatui::{
 use tokio::sync::mpsc::UnboundedSender;
 
 
-#[derive(Clone)]
-pub enum FieldKind {
-    Bool,
-    Integer,
-    Float,
-    Text,
-    TimeFrame,
-}
-
-impl Display for FieldKind {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        match self {
-            FieldKind::Bool => write!(f, "Bool"),
-            FieldKind::Integer => write!(f, "Integer"),
-            FieldKind::Float => write!(f, "Float"),
-            FieldKind::Text => write!(f, "Text"),
-            FieldKind::TimeFrame => write!(f, "TimeFrame"),
-        } 
-    }
-}
-
 // ------------------------------ CONFIG KEYS ------------------------------ //
 #[derive(Clone)]
-enum ConfigFieldKey {
+pub enum ConfigFieldKey {
     BackTest(BackTestKeys),
     Downloads(DownloadKeys),
     Exchanges,
     Charts(ChartParams), 
+    Locale(LocaleKeys),
+    Csv(CsvKeys),
+    Timeouts(TimeoutKeys),
+    Bars(BarKeys),
+    Http(HttpKeys),
+    Tui(TuiKeys),
+    Telemetry(TelemetryKeys),
 }
 
 #[derive(Clone)]
-enum BackTestKeys {
+pub enum BackTestKeys {
     InsideBar,
+    SlippageBps,
 }
 
 #[derive(Clone)]
-enum ChartParams {
+pub enum ChartParams {
     NumBarsOnChart,
     LogScale,
+    OperationsWidth,
+    OutputHeight,
+}
+
+#[derive(Clone)]
+pub enum DownloadKeys {
+    CacheSize,
+    StallTimeoutMins,
+    StallRetries,
+}
+
+#[derive(Clone)]
+pub enum TimeoutKeys {
+    IntegrityCheck,
+    Backfill,
+    CandleBuild,
+}
+
+#[derive(Clone)]
+pub enum BarKeys {
+    WeekStart,
+    ListingAnchor,
+    MemoryBudget,
+}
+
+#[derive(Clone)]
+pub enum HttpKeys {
+    Address,
+    ApiKeys,
+    FlightAddress,
+}
+
+#[derive(Clone)]
+pub enum TuiKeys {
+    StartScreen,
+    Watchlist,
 }
 
 #[derive(Clone)]
-enum DownloadKeys {
-    CacheSize
+pub enum TelemetryKeys {
+    Enabled,
+    Endpoint,
+}
+
+#[derive(Clone)]
+pub enum LocaleKeys {
+    DateStyle,
+    DecimalComma,
+}
+
+#[derive(Clone)]
+pub enum CsvKeys {
+    Delimiter,
+    Quoting,
+    Header,
+    TimestampFormat,
 }
 
 // ------------------------------------------------------------------------- //
@@ -102,13 +142,7 @@ pub struct ConfigField {
 
 impl ConfigField {
     fn value_is_acceptable(&self) -> bool {
-        match &self.kind {
-            FieldKind::Bool => true, // Isn't modifiable by user anyway
-            FieldKind::Integer => self.value.parse::<u64>().is_ok(),
-            FieldKind::Float => self.value.parse::<f64>().is_ok(), 
-            FieldKind::Text => true,
-            FieldKind::TimeFrame => period_is_valid(&self.value),
-        } 
+        self.kind.accepts(&self.value)
     }
 }
 
@@ -128,6 +162,10 @@ pub struct ConfigForm {
     pub focused: usize,
     pub rows: Vec<FormRow>,
     pub mode: FormMode,
+    /// The config the form was built from, or last saved. Rows are applied
+    /// on top of it, and it's what changes are detected against. Boxed, as
+    /// it's far larger than the rest of any screen.
+    base: Box<AppConfig>,
 }
 
 impl ConfigForm {
@@ -153,6 +191,14 @@ impl ConfigForm {
                 key: ConfigFieldKey::BackTest(BackTestKeys::InsideBar),
             })
         );
+        rows.push(FormRow::InputRow(
+            ConfigField {
+                label: "Slippage (bps)".to_string(),
+                kind: FieldKind::Integer,
+                value: cfg.backtesting.slippage_bps.to_string(),
+                key: ConfigFieldKey::BackTest(BackTestKeys::SlippageBps),
+            })
+        );
 
         rows.push(FormRow::SectionDivider(
             "Chart Parameters".to_string()
@@ -173,6 +219,22 @@ impl ConfigForm {
                 key: ConfigFieldKey::Charts(ChartParams::LogScale),
             })
         );
+        rows.push(FormRow::InputRow(
+            ConfigField {
+                label: "Operations pane width".to_string(),
+                kind: FieldKind::Integer,
+                value: cfg.chart_parameters.operations_width.to_string(),
+                key: ConfigFieldKey::Charts(ChartParams::OperationsWidth),
+            })
+        );
+        rows.push(FormRow::InputRow(
+            ConfigField {
+                label: "Output pane height".to_string(),
+                kind: FieldKind::Integer,
+                value: cfg.chart_parameters.output_height.to_string(),
+                key: ConfigFieldKey::Charts(ChartParams::OutputHeight),
+            })
+        );
 
         rows.push(FormRow::SectionDivider(
             "Active Exchanges".to_string() 
@@ -201,18 +263,216 @@ impl ConfigForm {
                 key: ConfigFieldKey::Downloads(DownloadKeys::CacheSize)
             })
         );
+        rows.push(FormRow::InputRow(
+            ConfigField {
+                label: "Stalled download timeout (min)".to_string(),
+                kind: FieldKind::Integer,
+                value: cfg.data_download.stall_timeout_mins.to_string(),
+                key: ConfigFieldKey::Downloads(DownloadKeys::StallTimeoutMins)
+            })
+        );
+        rows.push(FormRow::InputRow(
+            ConfigField {
+                label: "Stalled download retries".to_string(),
+                kind: FieldKind::Integer,
+                value: cfg.data_download.stall_retries.to_string(),
+                key: ConfigFieldKey::Downloads(DownloadKeys::StallRetries)
+            })
+        );
+
+        rows.push(FormRow::SectionDivider(
+            "Job Timeouts (minutes, 0 for none)".to_string()
+        ));
+        rows.push(FormRow::InputRow(
+            ConfigField {
+                label: "Integrity check".to_string(),
+                kind: FieldKind::Integer,
+                value: cfg.timeouts.integrity_check_mins.to_string(),
+                key: ConfigFieldKey::Timeouts(TimeoutKeys::IntegrityCheck)
+            })
+        );
+        rows.push(FormRow::InputRow(
+            ConfigField {
+                label: "Backfill".to_string(),
+                kind: FieldKind::Integer,
+                value: cfg.timeouts.backfill_mins.to_string(),
+                key: ConfigFieldKey::Timeouts(TimeoutKeys::Backfill)
+            })
+        );
+        rows.push(FormRow::InputRow(
+            ConfigField {
+                label: "Candle build".to_string(),
+                kind: FieldKind::Integer,
+                value: cfg.timeouts.candle_build_mins.to_string(),
+                key: ConfigFieldKey::Timeouts(TimeoutKeys::CandleBuild)
+            })
+        );
+
+        rows.push(FormRow::SectionDivider(
+            "Bars".to_string()
+        ));
+        rows.push(FormRow::InputRow(
+            ConfigField {
+                label: "Week start".to_string(),
+                kind: FieldKind::WeekStart,
+                value: cfg.bars.week_start.to_string(),
+                key: ConfigFieldKey::Bars(BarKeys::WeekStart)
+            })
+        );
+        rows.push(FormRow::InputRow(
+            ConfigField {
+                label: "Anchor to listing time".to_string(),
+                kind: FieldKind::Bool,
+                value: cfg.bars.listing_anchor.to_string(),
+                key: ConfigFieldKey::Bars(BarKeys::ListingAnchor)
+            })
+        );
+        rows.push(FormRow::InputRow(
+            ConfigField {
+                label: "Build memory budget (MB, 0 for none)".to_string(),
+                kind: FieldKind::Integer,
+                value: cfg.bars.memory_budget_mb.to_string(),
+                key: ConfigFieldKey::Bars(BarKeys::MemoryBudget)
+            })
+        );
+
+        rows.push(FormRow::SectionDivider(
+            "HTTP Server".to_string()
+        ));
+        rows.push(FormRow::InputRow(
+            ConfigField {
+                label: "Address".to_string(),
+                kind: FieldKind::Address,
+                value: cfg.http.address.clone(),
+                key: ConfigFieldKey::Http(HttpKeys::Address)
+            })
+        );
+        rows.push(FormRow::InputRow(
+            ConfigField {
+                label: "API keys".to_string(),
+                kind: FieldKind::ApiKeys,
+                value: format_api_keys(&cfg.http.api_keys),
+                key: ConfigFieldKey::Http(HttpKeys::ApiKeys)
+            })
+        );
+        rows.push(FormRow::InputRow(
+            ConfigField {
+                label: "Arrow Flight address (blank for off)".to_string(),
+                kind: FieldKind::OptionalAddress,
+                value: cfg.http.flight_address.clone().unwrap_or_default(),
+                key: ConfigFieldKey::Http(HttpKeys::FlightAddress)
+            })
+        );
+
+        rows.push(FormRow::SectionDivider(
+            "Terminal Interface".to_string()
+        ));
+        rows.push(FormRow::InputRow(
+            ConfigField {
+                label: "Start screen".to_string(),
+                kind: FieldKind::StartScreen,
+                value: cfg.tui.start_screen.to_string(),
+                key: ConfigFieldKey::Tui(TuiKeys::StartScreen)
+            })
+        );
+        rows.push(FormRow::InputRow(
+            ConfigField {
+                label: "Watchlist".to_string(),
+                kind: FieldKind::Tickers,
+                value: cfg.tui.watchlist.join(", "),
+                key: ConfigFieldKey::Tui(TuiKeys::Watchlist)
+            })
+        );
+
+        rows.push(FormRow::SectionDivider(
+            "Usage Stats (command and error counts only)".to_string()
+        ));
+        rows.push(FormRow::InputRow(
+            ConfigField {
+                label: "Share anonymous usage stats".to_string(),
+                kind: FieldKind::Bool,
+                value: cfg.telemetry.enabled.to_string(),
+                key: ConfigFieldKey::Telemetry(TelemetryKeys::Enabled)
+            })
+        );
+        rows.push(FormRow::InputRow(
+            ConfigField {
+                label: "Report URL".to_string(),
+                kind: FieldKind::Url,
+                value: cfg.telemetry.endpoint.clone().unwrap_or_default(),
+                key: ConfigFieldKey::Telemetry(TelemetryKeys::Endpoint)
+            })
+        );
+
+        rows.push(FormRow::SectionDivider(
+            "Formatting".to_string()
+        ));
+        rows.push(FormRow::InputRow(
+            ConfigField {
+                label: "Date format".to_string(),
+                kind: FieldKind::DateStyle,
+                value: cfg.locale.date_style.to_string(),
+                key: ConfigFieldKey::Locale(LocaleKeys::DateStyle)
+            })
+        );
+        rows.push(FormRow::InputRow(
+            ConfigField {
+                label: "Decimal comma".to_string(),
+                kind: FieldKind::Bool,
+                value: cfg.locale.decimal_comma.to_string(),
+                key: ConfigFieldKey::Locale(LocaleKeys::DecimalComma)
+            })
+        );
+
+        let dialect = &cfg.csv_dialect;
+        rows.push(FormRow::SectionDivider(
+            "CSV Exports".to_string()
+        ));
+        rows.push(FormRow::InputRow(
+            ConfigField {
+                label: "Delimiter".to_string(),
+                kind: FieldKind::Delimiter,
+                value: dialect.delimiter.map(String::from).unwrap_or_default(),
+                key: ConfigFieldKey::Csv(CsvKeys::Delimiter)
+            })
+        );
+        rows.push(FormRow::InputRow(
+            ConfigField {
+                label: "Quoting".to_string(),
+                kind: FieldKind::CsvQuoting,
+                value: dialect.quoting.to_string(),
+                key: ConfigFieldKey::Csv(CsvKeys::Quoting)
+            })
+        );
+        rows.push(FormRow::InputRow(
+            ConfigField {
+                label: "Header row".to_string(),
+                kind: FieldKind::Bool,
+                value: dialect.header.to_string(),
+                key: ConfigFieldKey::Csv(CsvKeys::Header)
+            })
+        );
+        rows.push(FormRow::InputRow(
+            ConfigField {
+                label: "Timestamp format".to_string(),
+                kind: FieldKind::TimestampFormat,
+                value: dialect.timestamp_format.clone().unwrap_or_default(),
+                key: ConfigFieldKey::Csv(CsvKeys::TimestampFormat)
+            })
+        );
 
         ConfigForm {
             focused: 1,
             rows,
             mode,
+            base: Box::new(cfg.clone()),
         }
 
     }
 
     fn to_config(&self) -> AppConfig {
    
-        let mut config = AppConfig::default();
+        let mut config: AppConfig = (*self.base).clone();
 
         for row in &self.rows {
             
@@ -236,6 +496,15 @@ impl ConfigForm {
                                     .parse::<bool>()
                                     .unwrap_or(true);
                                 config.backtesting.inside_bar = parsed;
+                            },
+                            BackTestKeys::SlippageBps => {
+                                let parsed = inp
+                                    .value
+                                    .parse::<u32>()
+                                    .unwrap_or(
+                                        config.backtesting.slippage_bps
+                                    );
+                                config.backtesting.slippage_bps = parsed;
                             }
                         }
                     },
@@ -259,6 +528,20 @@ impl ConfigForm {
                                         config.chart_parameters.num_bars
                                     );
                                 config.chart_parameters.num_bars = parsed;
+                            },
+                            ChartParams::OperationsWidth => {
+                                let params = &mut config.chart_parameters;
+                                params.operations_width = inp
+                                    .value
+                                    .parse::<u16>()
+                                    .unwrap_or(params.operations_width);
+                            },
+                            ChartParams::OutputHeight => {
+                                let params = &mut config.chart_parameters;
+                                params.output_height = inp
+                                    .value
+                                    .parse::<u16>()
+                                    .unwrap_or(params.output_height);
                             }
                         }
                     },
@@ -268,6 +551,164 @@ impl ConfigForm {
                             DownloadKeys::CacheSize => {
                                 let new_time: String = inp.value.clone();
                                 config.data_download.cache_size = new_time;
+                            },
+                            DownloadKeys::StallTimeoutMins => {
+                                let parsed = inp
+                                    .value
+                                    .parse::<u64>()
+                                    .unwrap_or(
+                                        config.data_download.stall_timeout_mins
+                                    );
+                                config.data_download.stall_timeout_mins = 
+                                    parsed;
+                            },
+                            DownloadKeys::StallRetries => {
+                                let parsed = inp
+                                    .value
+                                    .parse::<u32>()
+                                    .unwrap_or(
+                                        config.data_download.stall_retries
+                                    );
+                                config.data_download.stall_retries = parsed;
+                            }
+                        }
+                    },
+
+                    ConfigFieldKey::Timeouts(tk) => {
+                        let timeouts = &mut config.timeouts;
+                        let field: &mut u64 = match tk {
+                            TimeoutKeys::IntegrityCheck => {
+                                &mut timeouts.integrity_check_mins
+                            },
+                            TimeoutKeys::Backfill => {
+                                &mut timeouts.backfill_mins
+                            },
+                            TimeoutKeys::CandleBuild => {
+                                &mut timeouts.candle_build_mins
+                            }
+                        };
+                        *field = inp.value.parse::<u64>().unwrap_or(*field);
+                    },
+
+                    ConfigFieldKey::Bars(bk) => {
+                        match bk {
+                            BarKeys::WeekStart => {
+                                if let Ok(parsed) = inp.value.parse() {
+                                    config.bars.week_start = parsed;
+                                };
+                            },
+                            BarKeys::ListingAnchor => {
+                                config.bars.listing_anchor = inp
+                                    .value
+                                    .parse::<bool>()
+                                    .unwrap_or(false);
+                            },
+                            BarKeys::MemoryBudget => {
+                                let bars = &mut config.bars;
+                                bars.memory_budget_mb = inp
+                                    .value
+                                    .parse::<u64>()
+                                    .unwrap_or(bars.memory_budget_mb);
+                            }
+                        }
+                    },
+
+                    ConfigFieldKey::Http(hk) => {
+                        match hk {
+                            HttpKeys::Address => {
+                                if inp.value.parse::<SocketAddr>().is_ok() {
+                                    config.http.address = inp.value.clone();
+                                };
+                            },
+                            HttpKeys::ApiKeys => {
+                                if let Ok(keys) = parse_api_keys(&inp.value) {
+                                    config.http.api_keys = keys;
+                                };
+                            },
+                            HttpKeys::FlightAddress => {
+                                let value: &str = inp.value.trim();
+                                if value.is_empty() {
+                                    config.http.flight_address = None;
+                                }
+                                else if value.parse::<SocketAddr>().is_ok() {
+                                    config.http.flight_address = 
+                                        Some(value.to_string());
+                                };
+                            }
+                        }
+                    },
+
+                    ConfigFieldKey::Tui(tk) => {
+                        match tk {
+                            TuiKeys::StartScreen => {
+                                if let Ok(parsed) = inp.value.parse() {
+                                    config.tui.start_screen = parsed;
+                                };
+                            },
+                            TuiKeys::Watchlist => {
+                                if let Ok(tickers) = parse_watchlist(
+                                    &inp.value
+                                ) {
+                                    config.tui.watchlist = tickers;
+                                };
+                            }
+                        }
+                    },
+
+                    ConfigFieldKey::Telemetry(tk) => {
+                        match tk {
+                            TelemetryKeys::Enabled => {
+                                config.telemetry.enabled = inp
+                                    .value
+                                    .parse::<bool>()
+                                    .unwrap_or(false);
+                            },
+                            TelemetryKeys::Endpoint => {
+                                config.telemetry.endpoint = Some(
+                                    inp.value.clone()
+                                ).filter(|v| !v.is_empty());
+                            }
+                        }
+                    },
+
+                    ConfigFieldKey::Locale(lc) => {
+                        match lc {
+                            LocaleKeys::DateStyle => {
+                                if let Ok(parsed) = inp.value.parse() {
+                                    config.locale.date_style = parsed;
+                                };
+                            },
+                            LocaleKeys::DecimalComma => {
+                                let parsed = inp
+                                    .value
+                                    .parse::<bool>()
+                                    .unwrap_or(false);
+                                config.locale.decimal_comma = parsed;
+                            }
+                        }
+                    },
+
+                    ConfigFieldKey::Csv(csv) => {
+                        let dialect = &mut config.csv_dialect;
+                        match csv {
+                            CsvKeys::Delimiter => {
+                                dialect.delimiter = inp.value.chars().next();
+                            },
+                            CsvKeys::Quoting => {
+                                if let Ok(parsed) = inp.value.parse() {
+                                    dialect.quoting = parsed;
+                                };
+                            },
+                            CsvKeys::Header => {
+                                dialect.header = inp
+                                    .value
+                                    .parse::<bool>()
+                                    .unwrap_or(true);
+                            },
+                            CsvKeys::TimestampFormat => {
+                                dialect.timestamp_format = Some(
+                                    inp.value.clone()
+                                ).filter(|v| !v.is_empty());
                             }
                         }
                     }
@@ -275,25 +716,25 @@ impl ConfigForm {
             }; 
         };
 
-        // BackTest(BackTestKeys),
-        // Downloads(DownloadKeys),
-        // Exchanges,
-        // Charts(ChartParams), 
-
         config
     
     }
 
+    /// Whether any field differs from the last saved config
+    pub fn is_dirty(&self) -> bool {
+        self.to_config() != *self.base
+    }
+
     pub fn save_input_values(
-        &self,
-        original_config: &AppConfig,
+        &mut self,
         paths: &SystemPaths,
     ) -> Result<AppConfig, ConfigError> {
         
         let config: AppConfig = self.to_config();
         
-        if *original_config != config {
+        if *self.base != config {
             save_config(&config, paths)?;
+            *self.base = config.clone();
             Ok(config)
         }
         else {
@@ -305,31 +746,126 @@ impl ConfigForm {
 
 
 // ------------- SYSTEM SETTINGS -------------- //
+/// Shown when usage stats are turned on, before they're saved
+const TELEMETRY_DISCLOSURE: &str = "Usage stats count how often each \
+    command runs and which kinds of errors it hits, nothing else: no pairs, \
+    files, keys or addresses. They're kept in telemetry.json next to the \
+    config, and only sent to the report URL, if one is set. Save to turn \
+    them on.";
+
 pub struct SettingsScreen {
     pub config_form: ConfigForm,
     pub active: bool,
     pub previous_value: Option<String>,
     pub msg_sender: UnboundedSender<AppEvent>,
+    /// Guest mode: the settings are shown but can't be changed or saved
+    pub read_only: bool,
+    /// Field changes made on this screen, newest last, as the row and the 
+    /// value it replaced. Undone with 'u' before the settings are saved.
+    undo_stack: Vec<(usize, String)>,
 }
 
 impl SettingsScreen {
 
     pub fn new(
         app_config: &AppConfig, 
-        msg_sender: UnboundedSender<AppEvent>
+        msg_sender: UnboundedSender<AppEvent>,
+        read_only: bool
     ) -> Self {
         SettingsScreen {
             config_form: ConfigForm::from_config(app_config),
             active: true,
             previous_value: None,
-            msg_sender
+            msg_sender,
+            read_only,
+            undo_stack: Vec::new(),
         } 
     }
 
+    /// Writes the form to the config file and applies it to `config`, the 
+    /// running engine's, reporting the result in the output window. Each 
+    /// value changed is added to the audit log.
+    pub fn save(
+        &mut self, 
+        config: &mut AppConfig, 
+        paths: &SystemPaths,
+        db_pool: PgPool
+    ) {
+
+        let _ = self.msg_sender.send(AppEvent::Clear);
+
+        let (text, color): (String, Color) = match self.config_form
+            .save_input_values(paths) 
+        {
+            Ok(c) => {
+                let previous: AppConfig = std::mem::replace(config, c);
+                let saved: AppConfig = config.clone();
+                tokio::spawn(async move {
+                    let _ = record_config_change(
+                        &previous, 
+                        &saved, 
+                        &AuditOrigin::Tui, 
+                        db_pool
+                    ).await;
+                });
+                self.undo_stack.clear();
+                ("Settings saved!".to_string(), Color::Green)
+            },
+            Err(ConfigError::NoChangesMade) => (
+                "No changes detected. Not saved.".to_string(),
+                Color::Yellow
+            ),
+            Err(e) => (format!("Settings save failed: {}", e), Color::Red)
+        };
+
+        let _ = self.msg_sender.send(AppEvent::Output(
+            OutputMsg::new(text, color, true, None, None, None)
+        ));
+    }
+
+    /// Puts back the value of the field changed last
+    fn undo(&mut self) {
+
+        let msg: OutputMsg = match self.undo_stack.pop() {
+            Some((i, previous)) => match &mut self.config_form.rows[i] {
+                FormRow::InputRow(r) => {
+                    let text: String = format!(
+                        "Undone: {} {} -> {}", 
+                        r.label, 
+                        r.value, 
+                        previous
+                    );
+                    r.value = previous;
+                    self.config_form.focused = i;
+                    OutputMsg::new(text, Color::Yellow, false, None, None, None)
+                },
+                FormRow::SectionDivider(_) => return
+            },
+            None => OutputMsg::new(
+                "Nothing to undo".to_string(),
+                Color::Yellow,
+                false,
+                None,
+                None,
+                None
+            )
+        };
+
+        let _ = self.msg_sender.send(AppEvent::Output(msg));
+    }
+
     pub fn draw(&mut self, frame: &mut Frame, area: Rect) {
 
         let block = Block::default()
-            .title("System Settings")
+            .title(match (self.read_only, self.config_form.is_dirty()) {
+                (true, _) => "System Settings (read-only)",
+                (false, true) => "System Settings (unsaved changes)",
+                (false, false) => "System Settings"
+            })
+            .title_bottom(match self.read_only {
+                true => "",
+                false => "s: save  u: undo  Esc: save and leave"
+            })
             .borders(Borders::ALL);
 
         frame.render_widget(block.clone(), area);
@@ -465,6 +1001,20 @@ impl SettingsScreen {
                     };
                 },
                 
+                KeyCode::Enter if self.read_only => {
+                    let _ = self.msg_sender.send(AppEvent::Output(
+                        OutputMsg::new(
+                            "Settings can't be changed in guest mode"
+                                .to_string(),
+                            Color::Yellow,
+                            true,
+                            None,
+                            None,
+                            None
+                        )
+                    ));
+                },
+
                 KeyCode::Enter => {
 
                     let i = self.config_form.focused;
@@ -478,6 +1028,16 @@ impl SettingsScreen {
 
                             FieldKind::Bool => { 
                                 
+                                let opting_in: bool = r.value == "false"
+                                    && matches!(
+                                        r.key, 
+                                        ConfigFieldKey::Telemetry(
+                                            TelemetryKeys::Enabled
+                                        )
+                                    );
+
+                                self.undo_stack.push((i, r.value.clone()));
+
                                 if r.value == "true" {
                                     new_row.value = "false".to_string();
                                 }  
@@ -488,6 +1048,19 @@ impl SettingsScreen {
                                 self.config_form.rows[i] = FormRow::InputRow(
                                     new_row
                                 );
+
+                                if opting_in {
+                                    let _ = self.msg_sender.send(
+                                        AppEvent::Output(OutputMsg::new(
+                                            TELEMETRY_DISCLOSURE.to_string(),
+                                            Color::Yellow,
+                                            false,
+                                            None,
+                                            None,
+                                            None
+                                        ))
+                                    );
+                                };
                             },
                             
                             _ => { 
@@ -523,6 +1096,8 @@ impl SettingsScreen {
 
                 },
 
+                KeyCode::Char('u') => self.undo(),
+
                 KeyCode::Esc => {
                     
                     if matches!(self.config_form.mode, FormMode::Input) {
@@ -580,12 +1155,75 @@ impl SettingsScreen {
                                 );
                             };
 
+                            if let FieldKind::DateStyle = r.kind {
+                                msgs.push(
+                                    "Valid formats: unix, iso, local"
+                                        .to_string()
+                                );
+                            };
+
+                            if let FieldKind::CsvQuoting = r.kind {
+                                msgs.push(
+                                    "Valid modes: minimal, all, never"
+                                        .to_string()
+                                );
+                            };
+
+                            if let FieldKind::WeekStart = r.kind {
+                                msgs.push(
+                                    "Valid days: sunday, monday".to_string()
+                                );
+                            };
+
+                            if let FieldKind::StartScreen = r.kind {
+                                msgs.push(
+                                    "Valid screens: last, menu, database, \
+                                    candles, settings, strategies, chart, \
+                                    integrity, watch, jobs, paper, orders"
+                                        .to_string()
+                                );
+                            };
+
+                            if let FieldKind::Tickers = r.kind {
+                                msgs.push(
+                                    "Expected Kraken pairs separated by \
+                                    commas, e.g. XBTUSD, ETHUSD"
+                                        .to_string()
+                                );
+                            };
+
+                            if let FieldKind::Address 
+                                | FieldKind::OptionalAddress = r.kind 
+                            {
+                                msgs.push(
+                                    "Expected ip:port, e.g. 127.0.0.1:8080"
+                                        .to_string()
+                                );
+                            };
+
+                            if let FieldKind::Url = r.kind {
+                                msgs.push(
+                                    "Expected an http:// or https:// URL, \
+                                    or nothing"
+                                        .to_string()
+                                );
+                            };
+
+                            if let FieldKind::ApiKeys = r.kind {
+                                msgs.push(
+                                    "Expected KEY:ROLE pairs separated by \
+                                    commas, with role viewer, operator, \
+                                    trader or admin"
+                                        .to_string()
+                                );
+                            };
+
                             tokio::spawn(async move {
                                 
-                                sender.send(AppEvent::Clear);
+                                let _ = sender.send(AppEvent::Clear);
 
                                 for msg in msgs {
-                                    sender.send(AppEvent::Output(
+                                    let _ = sender.send(AppEvent::Output(
                                         OutputMsg { 
                                             text: msg, 
                                             color: Color::Red, 
@@ -599,9 +1237,13 @@ impl SettingsScreen {
                             }); 
                         }
                         else {
-                            sender.send(AppEvent::Clear);
+                            let _ = sender.send(AppEvent::Clear);
                             self.config_form.mode = FormMode::Movement;
-                            self.previous_value = None;
+                            if let Some(previous) = self.previous_value.take()
+                                && previous != r.value
+                            {
+                                self.undo_stack.push((i, previous));
+                            };
                         };
                     }
                 },
@@ -642,6 +1284,7 @@ impl SettingsScreen {
 
     pub const SCREEN_NAME: &'static str = "System Settings";
 
+    #[allow(dead_code)]
     pub const SCREEN_OPTIONS: [&'static str; 0] = [];
 
 }
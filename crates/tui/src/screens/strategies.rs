@@ -1,6 +1,10 @@
-use std::fmt::{self, Formatter, Display};
+use std::{
+    collections::HashMap,
+    fmt::{self, Formatter, Display},
+};
 
 use tokio::{
+    task::JoinHandle,
     sync::{
         mpsc::{
             UnboundedSender
@@ -23,6 +27,7 @@ use ratatui::{
         ListItem,
     },
     style::{
+        Color,
         Style,
         Modifier,
     },
@@ -33,16 +38,50 @@ use ratatui::{
         },
     },
 };
+use reqwest::Client;
+use sqlx::PgPool;
+
+use crate::{AppEvent, OutputMsg, move_up, move_down};
+use super::search::ListSearch;
+use app_core::{
+    app_state::BackTestSettings,
+    backtest::{
+        BUILT_IN_STRATEGIES,
+        SimBroker,
+        run_backtest,
+        strategy_from_spec
+    },
+    backtest_cost_model,
+    bars::BarOptions,
+    build_candles,
+    build_timeframes,
+};
+use timestamp_tools::{
+    period_is_valid,
+    VALID_PERIODS,
+};
+
 
-use crate::{AppEvent, move_up, move_down};
+// ---------------------------- INFO STRINGS ------------------------------- //
+const STRATEGY_INFO: [&str; 2] = [
+    "sma-cross(10,30): long while the 10 bar SMA is above the 30 bar SMA",
+    "inside-bar(5,2): long on a break of the mother bar's high within 5 bars, \
+     stop at its low, target 2x the risk",
+];
 
+/// Starting cash for backtests run from this screen
+const STARTING_CASH: f64 = 10_000.0;
 
+
+#[derive(PartialEq)]
 pub enum StrategyFocus {
     Top,
     Bottom,
+    InputMode,
 }
 
-enum StrategyAction {
+pub enum StrategyAction {
+    BuiltIn,
     CreateNew,
     ModifyExisting,
     None,
@@ -51,6 +90,7 @@ enum StrategyAction {
 impl StrategyAction {
     fn to_title(&self) -> &'static str {
         match self {
+            StrategyAction::BuiltIn => "Built-in Strategies",
             StrategyAction::CreateNew => "Create New",
             StrategyAction::ModifyExisting => "Modify Existing",
             StrategyAction::None => ""
@@ -64,22 +104,61 @@ impl Display for StrategyAction {
     }
 }
 
+/// Steps for backtesting a built-in strategy: pick the strategy, then the
+/// pair, then type the period
+#[derive(PartialEq)]
+enum BacktestStep {
+    Strategy,
+    Pair,
+    Period,
+    None,
+}
+
+impl BacktestStep {
+    fn title(&self) -> &'static str {
+        match self {
+            BacktestStep::Strategy => "Strategy Selection",
+            BacktestStep::Pair => "Asset Pair Selection",
+            BacktestStep::Period => "Period",
+            BacktestStep::None => "Info"
+        }
+    }
+}
+
 
 pub struct StrategyScreen {
     pub msg_sender: UnboundedSender<AppEvent>,
     top_state: ListState,
     btm_state: ListState,
     btm_item_data: Vec<String>,
+    /// Filters the bottom list, opened with '/'
+    pub search: ListSearch,
     pub focus: StrategyFocus,
-    action: StrategyAction
+    action: StrategyAction,
+
+    step: BacktestStep,
+    strategy: String,
+    pair: (String, String),
+    period: String,
+    token_pairs: HashMap<String, Vec<String>>,
+    settings: BackTestSettings,
+    bar_options: BarOptions,
+    db_pool: PgPool,
+    request_client: Client,
+    task: Option<JoinHandle<()>>,
 }
 
 impl StrategyScreen {
 
     pub fn new(
-        msg_sender: UnboundedSender<AppEvent>
+        msg_sender: UnboundedSender<AppEvent>,
+        token_pairs: HashMap<String, Vec<String>>,
+        settings: BackTestSettings,
+        bar_options: BarOptions,
+        db_pool: PgPool,
+        request_client: Client,
     ) -> Self {
-        
+
         let mut top_state = ListState::default();
         top_state.select(Some(0));
 
@@ -88,9 +167,20 @@ impl StrategyScreen {
             top_state,
             btm_state: ListState::default(),
             btm_item_data: Vec::new(),
+            search: ListSearch::default(),
             focus: StrategyFocus::Top,
             action: StrategyAction::None,
-        } 
+            step: BacktestStep::None,
+            strategy: String::new(),
+            pair: (String::new(), String::new()),
+            period: String::new(),
+            token_pairs,
+            settings,
+            bar_options,
+            db_pool,
+            request_client,
+            task: None,
+        }
     }
 
     pub fn draw(&mut self, frame: &mut Frame, area: Rect) {
@@ -119,28 +209,74 @@ impl StrategyScreen {
                     StrategyFocus::Top => Style::default()
                         .add_modifier(Modifier::REVERSED)
                         .green(),
+                    StrategyFocus::InputMode => Style::default()
+                        .add_modifier(Modifier::REVERSED)
+                        .yellow(),
                     _ => Style::default()
                 }
             );
-        
+
         frame.render_stateful_widget(
             top_list,
             nested_chunks[0],
             &mut self.top_state
         );
 
-        self.btm_item_data = match self.action {
-            
-            StrategyAction::CreateNew => { 
-                Vec::new() 
-            },                 
-            
-            StrategyAction::ModifyExisting => { 
-                Vec::new() 
+        let items: Vec<String> = match self.action {
+
+            StrategyAction::BuiltIn => match self.step {
+
+                BacktestStep::Strategy => BUILT_IN_STRATEGIES
+                    .iter()
+                    .map(|name| match self.strategy_enabled(name) {
+                        true => name.to_string(),
+                        false => format!("{} (disabled in settings)", name)
+                    })
+                    .collect(),
+
+                BacktestStep::Pair => {
+                    let mut pairs: Vec<String> = self.token_pairs
+                        .iter()
+                        .flat_map(|(exchange, tickers)| {
+                            tickers.iter().map(move |t| {
+                                format!("{} {}", exchange, t)
+                            })
+                        })
+                        .collect();
+                    pairs.sort();
+                    pairs
+                },
+
+                BacktestStep::Period => Vec::from([
+                    format!("{} on {} {}",
+                        self.strategy,
+                        self.pair.0,
+                        self.pair.1
+                    ),
+                    format!("Period: {}", self.period),
+                ]),
+
+                BacktestStep::None => STRATEGY_INFO
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect(),
+            },
+
+            StrategyAction::CreateNew => {
+                Vec::new()
+            },
+
+            StrategyAction::ModifyExisting => {
+                Vec::new()
             },
-           
-            _ => { Vec::new() } 
+
+            _ => { Vec::new() }
         };
+        self.btm_item_data = self.search.apply(
+            items, 
+            &self.btm_item_data, 
+            &mut self.btm_state
+        );
 
         let btm_items: Vec<ListItem> = self.btm_item_data.iter()
             .map(|v| ListItem::new(&v[..]))
@@ -149,7 +285,7 @@ impl StrategyScreen {
         let btm_list = List::new(btm_items)
             .block(
                 Block::default()
-                    // .title(self.focus.title())
+                    .title(self.search.title(self.step.title()))
                     .borders(Borders::ALL)
             )
             .highlight_style(
@@ -161,64 +297,333 @@ impl StrategyScreen {
                     Style::default()
                 }
             );
-        
+
         frame.render_stateful_widget(
-            btm_list, 
+            btm_list,
             nested_chunks[1],
             &mut self.btm_state
         );
 
     }
 
+    /// Whether a task started from this screen is still running
+    pub fn is_busy(&self) -> bool {
+        self.task.as_ref().is_some_and(|h| !h.is_finished())
+    }
+
+    fn strategy_enabled(&self, name: &str) -> bool {
+        name != "inside-bar" || self.settings.inside_bar
+    }
+
+    fn send_msg(&self, text: String, color: Color) {
+        let _ = self.msg_sender.send(AppEvent::Output(
+            OutputMsg::new(text, color, true, None, None, None)
+        ));
+    }
+
+    /// Backtests the chosen strategy in the background and prints a summary
+    /// of the report to the output window
+    fn handle_backtest(&mut self) {
+
+        let (exchange, ticker) = self.pair.clone();
+        let period = self.period.clone();
+        let spec = self.strategy.clone();
+        let slippage_bps: u32 = self.settings.slippage_bps;
+        let bar_options: BarOptions = self.bar_options;
+        let pool = self.db_pool.clone();
+        let client = self.request_client.clone();
+        let tx = self.msg_sender.clone();
+
+        let _ = self.msg_sender.send(AppEvent::Clear);
+        self.send_msg(
+            format!("Backtesting {} on {} {} {}", spec, exchange, ticker, period),
+            Color::Yellow
+        );
+
+        self.task = Some(tokio::spawn(async move {
+
+            let send = |text: String, color: Color| {
+                let _ = tx.send(AppEvent::Output(
+                    OutputMsg::new(text, color, false, None, None, None)
+                ));
+            };
+
+            let mut strategy = match strategy_from_spec(&spec) {
+                Ok(strategy) => strategy,
+                Err(e) => return send(e.to_string(), Color::Red)
+            };
+
+            let timeframes = match build_timeframes(
+                &exchange, 
+                &ticker, 
+                &strategy.timeframes(), 
+                bar_options, 
+                pool.clone()
+            )
+                .await
+            {
+                Ok(timeframes) => timeframes,
+                Err(e) => return send(e.to_string(), Color::Red)
+            };
+
+            let bars = match build_candles(
+                &exchange, 
+                &ticker, 
+                &period, 
+                bar_options, 
+                pool
+            )
+                .await
+            {
+                Ok(bars) => bars,
+                Err(e) => return send(e.to_string(), Color::Red)
+            };
+
+            let (cost_model, fetch_error) = match backtest_cost_model(
+                &ticker,
+                slippage_bps,
+                &client
+            ).await {
+                Ok(costs) => costs,
+                Err(e) => return send(e.to_string(), Color::Red)
+            };
+
+            if fetch_error.is_some() {
+                send(
+                    "Couldn't fetch pair info, backtesting without fees or \
+                     order minimums".to_string(),
+                    Color::Yellow
+                );
+            };
+
+            let report = match run_backtest(
+                &bars,
+                &timeframes,
+                strategy.as_mut(),
+                SimBroker::new(cost_model, STARTING_CASH)
+            ) {
+                Ok(report) => report,
+                Err(e) => return send(e.to_string(), Color::Red)
+            };
+
+            let color: Color = match report.total_return >= 0.0 {
+                true => Color::Green,
+                false => Color::Red
+            };
+
+            send(format!("Strategy     : {}", report.strategy), Color::Cyan);
+            send(
+                format!("Return       : {:.2}%", report.total_return * 100.0),
+                color
+            );
+            send(
+                format!("Final equity : {:.2}", report.final_equity),
+                color
+            );
+            send(
+                format!("Max drawdown : {:.2}%", report.max_drawdown * 100.0),
+                Color::White
+            );
+            send(format!("Sharpe       : {:.2}", report.sharpe), Color::White);
+            send(
+                format!("Win rate     : {:.1}%", report.win_rate * 100.0),
+                Color::White
+            );
+            send(
+                format!("Trades       : {}", report.trades.len()),
+                Color::White
+            );
+        }));
+    }
+
     pub async fn handle_key(&mut self, key: KeyEvent) {
 
+        if let Some(handle) = &self.task {
+
+            if handle.is_finished() {
+                self.task = None;
+            }
+
+            else {
+                return
+            };
+        };
+
+        if self.search.typing {
+            self.search.handle_key(&key);
+            return
+        };
+
+        if let StrategyFocus::InputMode = self.focus {
+
+            match key.code {
+                KeyCode::Char(c) => {
+                    self.period.push(c);
+                },
+                KeyCode::Backspace => {
+                    self.period.pop();
+                },
+                KeyCode::Enter => {
+                    if period_is_valid(&self.period) {
+                        self.handle_backtest();
+                        self.focus = StrategyFocus::Top;
+                        self.step = BacktestStep::None;
+                        self.btm_state.select(None);
+                    }
+                    else {
+                        self.send_msg(
+                            format!(
+                                "Invalid period length: try integer + {:?}",
+                                VALID_PERIODS
+                            ),
+                            Color::Red
+                        );
+                    };
+                },
+                KeyCode::Esc => {
+                    self.focus = StrategyFocus::Top;
+                    self.step = BacktestStep::None;
+                    self.btm_state.select(None);
+                },
+                _ => {}
+            };
+
+            return
+        };
+
         match key.code {
-        
+
             KeyCode::Up | KeyCode::Char('k') => {
-                
+
                 match &self.focus {
 
                     StrategyFocus::Top => move_up(
-                        &mut self.top_state, 
+                        &mut self.top_state,
                         Self::SCREEN_OPTIONS.len(),
                         1
                     ),
-                    
+
                     StrategyFocus::Bottom => move_up(
-                        &mut self.btm_state, 
+                        &mut self.btm_state,
                         self.btm_item_data.len(),
                         1
                     ),
 
                     _ => {}
-                
+
                 }
             },
 
             KeyCode::Down | KeyCode::Char('j') => {
-            
+
                 match &self.focus {
 
                     StrategyFocus::Top => move_down(
-                        &mut self.top_state, 
+                        &mut self.top_state,
                         Self::SCREEN_OPTIONS.len(),
                         1
                     ),
-                    
+
                     StrategyFocus::Bottom => move_down(
-                        &mut self.btm_state, 
+                        &mut self.btm_state,
                         self.btm_item_data.len(),
                         1
-                    )
+                    ),
+
+                    _ => {}
                 }
             }
 
+            KeyCode::Char('/') if self.focus == StrategyFocus::Bottom => {
+                self.search.open();
+            },
+
             KeyCode::Enter => {
 
+                match &self.focus {
+
+                    StrategyFocus::Top => {
+                        self.action = match self.top_state.selected() {
+                            Some(0) => {
+                                self.step = BacktestStep::Strategy;
+                                self.focus = StrategyFocus::Bottom;
+                                self.btm_state.select(Some(0));
+                                StrategyAction::BuiltIn
+                            },
+                            Some(1) => StrategyAction::CreateNew,
+                            Some(2) => StrategyAction::ModifyExisting,
+                            _ => StrategyAction::None
+                        };
+                    },
+
+                    StrategyFocus::Bottom => {
+
+                        let Some(selected) = self.btm_state
+                            .selected()
+                            .and_then(|i| self.btm_item_data.get(i))
+                            .cloned()
+                        else {
+                            return
+                        };
+
+                        match self.step {
+
+                            BacktestStep::Strategy => {
+                                let name: &str = selected
+                                    .split_whitespace()
+                                    .next()
+                                    .unwrap_or_default();
+
+                                if self.strategy_enabled(name) {
+                                    self.strategy = name.to_string();
+                                    self.step = BacktestStep::Pair;
+                                    self.btm_state.select(Some(0));
+                                    self.search.clear();
+                                }
+                                else {
+                                    self.send_msg(
+                                        format!(
+                                            "{} is disabled, enable it in \
+                                             System Settings",
+                                            name
+                                        ),
+                                        Color::Yellow
+                                    );
+                                };
+                            },
+
+                            BacktestStep::Pair => {
+                                if let Some((exchange, ticker)) = selected
+                                    .split_once(' ')
+                                {
+                                    self.pair = (
+                                        exchange.to_string(),
+                                        ticker.to_string()
+                                    );
+                                    self.step = BacktestStep::Period;
+                                    self.focus = StrategyFocus::InputMode;
+                                    self.btm_state.select(None);
+                                    self.search.clear();
+                                    self.send_msg(
+                                        "Type a period, e.g. 1h".to_string(),
+                                        Color::Yellow
+                                    );
+                                };
+                            },
+
+                            _ => {}
+                        };
+                    },
+
+                    _ => {}
+                }
             }
 
             KeyCode::Esc => {
-
+                self.focus = StrategyFocus::Top;
+                self.step = BacktestStep::None;
+                self.btm_state.select(None);
+                self.search.clear();
             }
 
             _ => {}
@@ -227,11 +632,10 @@ impl StrategyScreen {
 
     pub const SCREEN_NAME: &'static str = "Strategy Manager";
 
-    pub const SCREEN_OPTIONS: [StrategyAction; 2] = [
+    pub const SCREEN_OPTIONS: [StrategyAction; 3] = [
+        StrategyAction::BuiltIn,
         StrategyAction::CreateNew,
         StrategyAction::ModifyExisting,
     ];
 
 }
-
-
@@ -0,0 +1,422 @@
+use std::time::Duration;
+
+use ratatui::{
+    Frame,
+    crossterm::event::{KeyCode, KeyEvent},
+    layout::{Constraint, Rect},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState, Wrap},
+};
+use reqwest::Client;
+use sqlx::PgPool;
+use tokio::{
+    sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel},
+    task::JoinHandle,
+    time::interval,
+};
+
+use app_core::database_ops::{
+    Ticker,
+    fetch_price_snapshot,
+    kraken::{
+        TickerQuote,
+        request_price_24h_ago_from_kraken,
+        request_ticker_from_kraken,
+    },
+    usage::flush_request_counts,
+};
+use string_helpers::{Locale, sparkline};
+use super::{AppEvent, OutputMsg, pair_color};
+
+
+/// Time between two rounds of quotes
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Rounds between two fetches of the prices 24 hours ago, which move slowly
+/// enough not to spend a request on every round
+const DAY_OPEN_ROUNDS: u64 = 20;
+
+
+/// What the polling task sends back to the screen
+enum WatchUpdate {
+    Refreshing,
+    Quote { ticker: String, quote: TickerQuote },
+    DayOpen { ticker: String, price: f64 },
+    /// Hourly closes of the pair's last day of stored ticks
+    Snapshot { ticker: String, prices: Vec<f64> },
+    Failed { ticker: String, error: String },
+    Refreshed,
+}
+
+/// One pair of the watchlist and its latest figures
+struct WatchRow {
+    ticker: String,
+    quote: Option<TickerQuote>,
+    /// Price 24 hours ago
+    day_open: Option<f64>,
+    /// See `WatchUpdate::Snapshot`, none for pairs that aren't stored
+    snapshot: Vec<f64>,
+    /// Why the last round failed, while the figures before it stay up
+    error: Option<String>,
+}
+
+impl WatchRow {
+
+    /// Change over the last 24 hours, in percent
+    fn change(&self) -> Option<f64> {
+        match (self.quote, self.day_open) {
+            (Some(q), Some(open)) if open > 0.0 => {
+                Some((q.last - open) / open * 100.0)
+            },
+            _ => None
+        }
+    }
+}
+
+
+// ----------------------------- WATCH SCREEN ------------------------------ //
+/// Quotes the Kraken pairs of `tui.watchlist` live, polling the REST API in
+/// the background
+///
+/// Quotes come in on the polling task's channel and are drained on each
+/// tick. 'r' fetches everything again at once. Pairs that are also stored
+/// get a sparkline of their last day of ticks, read from the database as
+/// often as the prices 24 hours ago.
+pub struct WatchScreen {
+    db_pool: PgPool,
+    request_client: Client,
+    transmitter: UnboundedSender<AppEvent>,
+    rows: Vec<WatchRow>,
+    table_state: TableState,
+    receiver: Option<UnboundedReceiver<WatchUpdate>>,
+    task: Option<JoinHandle<()>>,
+    /// Whether a round of quotes is in flight
+    refreshing: bool,
+    locale: Locale,
+}
+
+impl WatchScreen {
+
+    pub fn new(
+        watchlist: &[String],
+        db_pool: PgPool,
+        request_client: Client,
+        transmitter: UnboundedSender<AppEvent>,
+        locale: Locale,
+    ) -> Self {
+
+        let rows: Vec<WatchRow> = watchlist
+            .iter()
+            .filter_map(|t| t.parse::<Ticker>().ok())
+            .map(|ticker| WatchRow {
+                ticker: ticker.into(),
+                quote: None,
+                day_open: None,
+                snapshot: Vec::new(),
+                error: None,
+            })
+            .collect();
+
+        let mut table_state = TableState::default();
+        table_state.select(match rows.is_empty() {
+            true => None,
+            false => Some(0)
+        });
+
+        let mut screen = WatchScreen {
+            db_pool,
+            request_client,
+            transmitter,
+            rows,
+            table_state,
+            receiver: None,
+            task: None,
+            refreshing: false,
+            locale,
+        };
+
+        screen.start_polling();
+        screen
+    }
+
+    /// Whether a round of quotes is coming in
+    pub fn is_busy(&self) -> bool {
+        self.refreshing
+    }
+
+    /// Starts, or restarts, the task polling every pair of the watchlist
+    fn start_polling(&mut self) {
+
+        self.stop_polling();
+
+        if self.rows.is_empty() {
+            return
+        };
+
+        let (tx, rx) = unbounded_channel::<WatchUpdate>();
+        self.receiver = Some(rx);
+
+        let tickers: Vec<String> = self.rows
+            .iter()
+            .map(|r| r.ticker.clone())
+            .collect();
+        let client = self.request_client.clone();
+        let db_pool = self.db_pool.clone();
+
+        self.task = Some(tokio::spawn(async move {
+
+            let mut poll = interval(POLL_INTERVAL);
+
+            for round in 0u64.. {
+
+                poll.tick().await;
+
+                if tx.send(WatchUpdate::Refreshing).is_err() {
+                    break
+                };
+
+                for ticker in tickers.iter().cloned() {
+
+                    let update = match request_ticker_from_kraken(
+                        &ticker,
+                        &client
+                    ).await {
+                        Ok(quote) => WatchUpdate::Quote {
+                            ticker: ticker.clone(),
+                            quote
+                        },
+                        Err(e) => WatchUpdate::Failed {
+                            ticker: ticker.clone(),
+                            error: e.to_string()
+                        }
+                    };
+                    let _ = tx.send(update);
+
+                    if round % DAY_OPEN_ROUNDS != 0 {
+                        continue
+                    };
+
+                    if let Ok(price) = request_price_24h_ago_from_kraken(
+                        &ticker,
+                        &client
+                    ).await {
+                        let _ = tx.send(WatchUpdate::DayOpen {
+                            ticker: ticker.clone(),
+                            price
+                        });
+                    };
+
+                    if let Ok(prices) = fetch_price_snapshot(
+                        "kraken",
+                        &ticker,
+                        db_pool.clone()
+                    ).await {
+                        let _ = tx.send(WatchUpdate::Snapshot {
+                            ticker,
+                            prices
+                        });
+                    };
+                };
+
+                let _ = flush_request_counts(db_pool.clone()).await;
+                let _ = tx.send(WatchUpdate::Refreshed);
+            };
+        }));
+    }
+
+    fn stop_polling(&mut self) {
+        if let Some(handle) = self.task.take() {
+            handle.abort();
+        };
+        self.receiver = None;
+        self.refreshing = false;
+    }
+
+    pub fn pre_draw(&mut self) {
+
+        let Some(receiver) = self.receiver.as_mut() else { return };
+
+        while let Ok(update) = receiver.try_recv() {
+
+            let ticker: &str = match &update {
+                WatchUpdate::Refreshing => {
+                    self.refreshing = true;
+                    continue
+                },
+                WatchUpdate::Refreshed => {
+                    self.refreshing = false;
+                    continue
+                },
+                WatchUpdate::Quote { ticker, .. }
+                | WatchUpdate::DayOpen { ticker, .. }
+                | WatchUpdate::Snapshot { ticker, .. }
+                | WatchUpdate::Failed { ticker, .. } => ticker,
+            };
+
+            let Some(row) = self.rows.iter_mut().find(|r| r.ticker == ticker)
+            else {
+                continue
+            };
+
+            match update {
+                WatchUpdate::Quote { quote, .. } => {
+                    row.quote = Some(quote);
+                    row.error = None;
+                },
+                WatchUpdate::DayOpen { price, .. } => {
+                    row.day_open = Some(price);
+                },
+                WatchUpdate::Snapshot { prices, .. } => row.snapshot = prices,
+                WatchUpdate::Failed { error, .. } => row.error = Some(error),
+                _ => {}
+            };
+        };
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect) {
+
+        let mut title: String = Self::SCREEN_NAME.to_string();
+        if self.refreshing {
+            title.push_str(" (refreshing)");
+        };
+
+        let block = Block::default()
+            .title(title)
+            .title_bottom("r: refresh now")
+            .borders(Borders::ALL);
+
+        if self.rows.is_empty() {
+            let hint = Paragraph::new(
+                "The watchlist is empty. Add Kraken pairs, e.g. XBTUSD, \
+                ETHUSD, to the Watchlist in Settings."
+            )
+                .block(block)
+                .wrap(Wrap { trim: true });
+            frame.render_widget(hint, area);
+            return
+        };
+
+        let header = Row::new([
+            "Pair", 
+            "Last", 
+            "24h change", 
+            "Spread", 
+            "24h stored", 
+            ""
+        ])
+            .style(Style::default().add_modifier(Modifier::BOLD));
+
+        let rows: Vec<Row> = self.rows
+            .iter()
+            .map(|row| {
+
+                let pair = Cell::from(row.ticker.clone()).style(
+                    Style::default().fg(pair_color("kraken", &row.ticker))
+                );
+
+                let Some(quote) = row.quote else {
+                    let (status, color) = match &row.error {
+                        Some(e) => (format!("error: {}", e), Color::Red),
+                        None => ("waiting...".to_string(), Color::DarkGray)
+                    };
+                    return Row::new([
+                        pair,
+                        Cell::from(status).style(Style::default().fg(color))
+                    ])
+                };
+
+                let (change, color): (String, Color) = match row.change() {
+                    Some(c) => (
+                        self.locale.format_number(format!("{:+.2}%", c)),
+                        match c >= 0.0 {
+                            true => Color::Green,
+                            false => Color::Red
+                        }
+                    ),
+                    None => ("-".to_string(), Color::DarkGray)
+                };
+
+                let spread_bps: f64 = match quote.last > 0.0 {
+                    true => quote.spread() / quote.last * 10_000.0,
+                    false => 0.0
+                };
+
+                Row::new([
+                    pair,
+                    Cell::from(self.locale.format_number(quote.last)),
+                    Cell::from(change).style(Style::default().fg(color)),
+                    Cell::from(format!(
+                        "{} ({} bps)",
+                        self.locale.format_number(quote.spread()),
+                        self.locale.format_number(format!("{:.1}", spread_bps))
+                    )),
+                    Cell::from(sparkline(&row.snapshot)).style(
+                        Style::default().fg(color)
+                    ),
+                    Cell::from(match &row.error {
+                        Some(_) => "stale",
+                        None => ""
+                    }).style(Style::default().fg(Color::Red)),
+                ])
+            })
+            .collect();
+
+        let table = Table::new(rows, [
+            Constraint::Length(14),
+            Constraint::Length(16),
+            Constraint::Length(12),
+            Constraint::Length(24),
+            Constraint::Length(26),
+            Constraint::Min(6),
+        ])
+            .header(header)
+            .block(block)
+            .row_highlight_style(
+                Style::default().add_modifier(Modifier::REVERSED)
+            );
+
+        frame.render_stateful_widget(table, area, &mut self.table_state);
+    }
+
+    pub async fn handle_key(&mut self, key: KeyEvent) {
+
+        match key.code {
+
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.table_state.select_previous();
+            },
+
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.table_state.select_next();
+            },
+
+            KeyCode::Char('r') if self.refreshing => self.send_msg(
+                "Wait for the quotes coming in".to_string(),
+                Color::Yellow
+            ),
+
+            KeyCode::Char('r') => self.start_polling(),
+
+            _ => {}
+        };
+    }
+
+    fn send_msg(&self, text: String, color: Color) {
+        let _ = self.transmitter.send(AppEvent::Output(OutputMsg::new(
+            text,
+            color,
+            false,
+            None,
+            None,
+            None
+        )));
+    }
+
+    pub const SCREEN_NAME: &'static str = "Live Watch";
+}
+
+impl Drop for WatchScreen {
+    fn drop(&mut self) {
+        self.stop_polling();
+    }
+}
@@ -1,6 +1,6 @@
 pub use app_core::*;
 pub use app_core::{
-    errors::{error_handler, ConfigError}, 
+    errors::{error_handler, json_error_handler, ConfigError}, 
     engine::{Engine, Server},
     app_state::{SystemPaths},
     RunTimeError,
@@ -9,6 +9,7 @@ pub use app_core::{
     initialize_app_engine,
     build_candles,
 };
+use servers::serve_http;
 use tui::{TerminalInterface};
 
 use std::{
@@ -16,7 +17,7 @@ use std::{
 };
 
 // ------------------------ MAIN PROGRAM FUNCTIONS ------------------------- //
-async fn dev_testing(engine: &Engine) { 
+async fn dev_testing(_engine: &Engine) { 
     println!("\x1b[1;33m------------- DEVELOPMENT MODE -------------\x1b[0m");
 
 }
@@ -29,17 +30,25 @@ pub async fn app_start() -> i32 {
     let mut engine: Engine = match initialize_app_engine().await {
         Ok(s) => s,
         Err(e) => {
-            error_handler(e); 
             exit_code = 2;
+            // The arguments may be what failed to parse
+            match std::env::args().any(|a| a == "--json") {
+                true => json_error_handler(e, exit_code),
+                false => error_handler(e)
+            };
             return exit_code
         }
     };
 
-    if let Err(_) = first_time_setup(&engine.state.paths) {
+    if first_time_setup(&engine.state.paths).is_err() {
         exit_code = 2;
         return exit_code
     };
 
+    if let Some(faults) = database_ops::faults::active_faults() {
+        eprintln!("\x1b[1;33mInjecting request faults: {}\x1b[0m", faults);
+    };
+
     if engine.args.dev_mode {
         dev_testing(&engine).await; 
     }
@@ -53,8 +62,19 @@ pub async fn app_start() -> i32 {
                     RunTimeError::Arguments(_) => 3,
                     RunTimeError::DataBase(_) => 4,
                     RunTimeError::Bar(_) => 5,
+                    RunTimeError::Backtest(_) => 6,
+                    RunTimeError::Catalog(_) => 7,
+                    RunTimeError::Timeout { .. } => 8,
+                    RunTimeError::Preflight(_) => 9,
+                    RunTimeError::Paper(_) => 10,
+                    RunTimeError::Live(_) => 11,
+                    RunTimeError::Export(_) => 12,
+                    RunTimeError::Recurring(_) => 13,
+                };
+                match engine.args.json {
+                    true => json_error_handler(e, exit_code),
+                    false => error_handler(e)
                 };
-                error_handler(e);
                 return exit_code;
             }
         };
@@ -63,18 +83,33 @@ pub async fn app_start() -> i32 {
             match data {
                 DataResponse::Bars(_) => {
                         
-                }
+                },
+                DataResponse::Pairs(_) 
+                | DataResponse::Integrity(_) 
+                | DataResponse::ApiUsage(_)
+                | DataResponse::PaperAccount(_)
+                | DataResponse::PaperOrder(_)
+                | DataResponse::PaperOrders(_)
+                | DataResponse::LiveBalances(_)
+                | DataResponse::LiveOrders(_)
+                | DataResponse::LiveOrder(_) => {}
             }
         };
 
         // Start the server if 'start' was passed as the first argument 
         if let Server::CLI = engine.op_mode {
             let mut tui = TerminalInterface::new(engine).await;
-            tui.run().await;
+            if tui.run().await.is_err() {
+                exit_code = 1;
+            };
         }
 
         else if let Server::HTTP = engine.op_mode {
-            todo!();
+            let address: String = engine.state.config.http.address.clone();
+            if let Err(e) = serve_http(engine, &address).await {
+                eprintln!("\x1b[1;31mHTTP server failed: {}\x1b[0m", e);
+                exit_code = 1;
+            };
         };
     };
 
@@ -87,13 +122,13 @@ fn first_time_setup(paths: &SystemPaths) -> Result<(), ConfigError> {
    
     if !paths.base.exists() {
         
-        if let Err(_) = fs::create_dir_all(&paths.base) {
+        if fs::create_dir_all(&paths.base).is_err() {
             return Err(ConfigError::MissingDirectory(
                 "Failed to create 'dtrade' directory"
             ));
         };
 
-        if let Err(_) = fs::create_dir_all(&paths.candle_data) {
+        if fs::create_dir_all(&paths.candle_data).is_err() {
             return Err(ConfigError::MissingDirectory(
                 "Failed to create 'dtrade/candle_data' directory"
             ));